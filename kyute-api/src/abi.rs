@@ -0,0 +1,138 @@
+//! Versioning, and FFI-safe equivalents of standard types.
+use std::{fmt, marker::PhantomData, slice};
+
+/// Version of the plugin interface.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ApiVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+/// Version of the interface defined by this crate.
+pub const API_VERSION: ApiVersion = ApiVersion::new(1, 0);
+
+impl ApiVersion {
+    pub const fn new(major: u16, minor: u16) -> ApiVersion {
+        ApiVersion { major, minor }
+    }
+
+    /// Returns whether something built against this version can be used by a host that implements the specified
+    /// version.
+    pub fn is_compatible_with(self, host: ApiVersion) -> bool {
+        self.major == host.major && self.minor <= host.minor
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// First field of all vtables: the version of the interface that the vtable implements.
+///
+/// Functions added in a minor version are appended at the end of the vtable. Before calling one of them, check that
+/// the vtable has it with `supports`: the vtable may come from a plugin or host built against an older version.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct VTableHeader {
+    pub version: ApiVersion,
+}
+
+impl VTableHeader {
+    /// Header of the vtables implemented by this crate.
+    pub const CURRENT: VTableHeader = VTableHeader { version: API_VERSION };
+
+    /// Returns whether the vtable has the functions of the specified minor version of the current major version.
+    pub fn supports(&self, minor: u16) -> bool {
+        self.version.major == API_VERSION.major && self.version.minor >= minor
+    }
+}
+
+/// A borrowed UTF-8 string (`&str`).
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Str<'a> {
+    ptr: *const u8,
+    len: usize,
+    _phantom: PhantomData<&'a str>,
+}
+
+// Same as &str.
+unsafe impl<'a> Send for Str<'a> {}
+unsafe impl<'a> Sync for Str<'a> {}
+
+impl<'a> Str<'a> {
+    pub const fn new(s: &'a str) -> Str<'a> {
+        Str {
+            ptr: s.as_ptr(),
+            len: s.len(),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        // SAFETY: created from a `&'a str` in `new`
+        unsafe { std::str::from_utf8_unchecked(slice::from_raw_parts(self.ptr, self.len)) }
+    }
+}
+
+impl<'a> From<&'a str> for Str<'a> {
+    fn from(s: &'a str) -> Self {
+        Str::new(s)
+    }
+}
+
+impl<'a> fmt::Debug for Str<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// A borrowed slice (`&[T]`).
+#[repr(C)]
+pub struct Slice<'a, T> {
+    ptr: *const T,
+    len: usize,
+    _phantom: PhantomData<&'a [T]>,
+}
+
+// Same as &[T].
+unsafe impl<'a, T: Sync> Send for Slice<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Slice<'a, T> {}
+
+impl<'a, T> Clone for Slice<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Slice<'a, T> {}
+
+impl<'a, T> Slice<'a, T> {
+    pub const fn new(s: &'a [T]) -> Slice<'a, T> {
+        Slice {
+            ptr: s.as_ptr(),
+            len: s.len(),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn as_slice(&self) -> &'a [T] {
+        // SAFETY: created from a `&'a [T]` in `new`
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'a, T> From<&'a [T]> for Slice<'a, T> {
+    fn from(s: &'a [T]) -> Self {
+        Slice::new(s)
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Slice<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
@@ -0,0 +1,91 @@
+//! Drawing handle.
+use crate::{Color, Point, Rect, Str, VTableHeader};
+use std::{ffi::c_void, marker::PhantomData};
+
+/// Drawing functions implemented by the host (see `Canvas`).
+#[repr(C)]
+pub struct CanvasVTable {
+    pub header: VTableHeader,
+    pub save: unsafe extern "C" fn(ctx: *mut c_void),
+    pub restore: unsafe extern "C" fn(ctx: *mut c_void),
+    pub translate: unsafe extern "C" fn(ctx: *mut c_void, x: f64, y: f64),
+    pub clip_rect: unsafe extern "C" fn(ctx: *mut c_void, rect: Rect),
+    pub fill_rect: unsafe extern "C" fn(ctx: *mut c_void, rect: Rect, color: Color),
+    pub stroke_rect: unsafe extern "C" fn(ctx: *mut c_void, rect: Rect, width: f64, color: Color),
+    pub fill_rounded_rect: unsafe extern "C" fn(ctx: *mut c_void, rect: Rect, radius: f64, color: Color),
+    pub draw_line: unsafe extern "C" fn(ctx: *mut c_void, from: Point, to: Point, width: f64, color: Color),
+    pub draw_text: unsafe extern "C" fn(ctx: *mut c_void, text: Str, baseline_origin: Point, size: f64, color: Color),
+}
+
+/// Draws the contents of a widget.
+///
+/// Drawing happens in the coordinate system of the widget. The transform and the clip are restored after
+/// `Widget::paint` returns, even if the calls to `save` and `restore` are unbalanced.
+#[repr(C)]
+pub struct Canvas<'a> {
+    ctx: *mut c_void,
+    vtable: &'a CanvasVTable,
+    _phantom: PhantomData<&'a mut ()>,
+}
+
+impl<'a> Canvas<'a> {
+    /// Creates a drawing handle. Used by hosts.
+    ///
+    /// # Safety
+    /// The functions of `vtable` must be safe to call with `ctx` while the handle is alive.
+    pub unsafe fn from_raw(ctx: *mut c_void, vtable: &'a CanvasVTable) -> Canvas<'a> {
+        Canvas {
+            ctx,
+            vtable,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the drawing functions of the host. Use this to check the version of the host before calling
+    /// functions added in later minor versions.
+    pub fn vtable(&self) -> &'a CanvasVTable {
+        self.vtable
+    }
+
+    /// Saves the current transform and clip.
+    pub fn save(&mut self) {
+        unsafe { (self.vtable.save)(self.ctx) }
+    }
+
+    /// Restores the transform and clip saved by the last call to `save`.
+    pub fn restore(&mut self) {
+        unsafe { (self.vtable.restore)(self.ctx) }
+    }
+
+    pub fn translate(&mut self, x: f64, y: f64) {
+        unsafe { (self.vtable.translate)(self.ctx, x, y) }
+    }
+
+    /// Intersects the clip with the specified rectangle.
+    pub fn clip_rect(&mut self, rect: Rect) {
+        unsafe { (self.vtable.clip_rect)(self.ctx, rect) }
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        unsafe { (self.vtable.fill_rect)(self.ctx, rect, color) }
+    }
+
+    /// Draws the outline of a rectangle, centered on its edges.
+    pub fn stroke_rect(&mut self, rect: Rect, width: f64, color: Color) {
+        unsafe { (self.vtable.stroke_rect)(self.ctx, rect, width, color) }
+    }
+
+    pub fn fill_rounded_rect(&mut self, rect: Rect, radius: f64, color: Color) {
+        unsafe { (self.vtable.fill_rounded_rect)(self.ctx, rect, radius, color) }
+    }
+
+    pub fn draw_line(&mut self, from: Point, to: Point, width: f64, color: Color) {
+        unsafe { (self.vtable.draw_line)(self.ctx, from, to, width, color) }
+    }
+
+    /// Draws a single line of text with the default font of the host, starting at the specified point on the
+    /// baseline. `size` is the font size in DIPs.
+    pub fn draw_text(&mut self, text: &str, baseline_origin: Point, size: f64, color: Color) {
+        unsafe { (self.vtable.draw_text)(self.ctx, Str::new(text), baseline_origin, size, color) }
+    }
+}
@@ -0,0 +1,93 @@
+//! Input events sent to plugin widgets.
+use crate::Point;
+
+/// State of the keyboard modifiers.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Modifiers(pub u32);
+
+impl Modifiers {
+    pub const SHIFT: Modifiers = Modifiers(1 << 0);
+    pub const CONTROL: Modifiers = Modifiers(1 << 1);
+    pub const ALT: Modifiers = Modifiers(1 << 2);
+    pub const META: Modifiers = Modifiers(1 << 3);
+
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// A pointer button.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PointerButton(pub u16);
+
+impl PointerButton {
+    pub const LEFT: PointerButton = PointerButton(0);
+    pub const MIDDLE: PointerButton = PointerButton(1);
+    pub const RIGHT: PointerButton = PointerButton(2);
+    pub const X1: PointerButton = PointerButton(3);
+    pub const X2: PointerButton = PointerButton(4);
+    /// No button (e.g. in pointer move events).
+    pub const NONE: PointerButton = PointerButton(u16::MAX);
+}
+
+/// State of the pointer buttons: bit N is set if `PointerButton(N)` is pressed.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PointerButtons(pub u32);
+
+impl PointerButtons {
+    /// Checks if the specified button is pressed.
+    pub fn test(self, button: PointerButton) -> bool {
+        button.0 < 32 && self.0 & (1 << button.0) != 0
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PointerEventKind {
+    PointerDown,
+    PointerUp,
+    PointerMove,
+    /// The pointer entered the widget.
+    PointerEnter,
+    /// The pointer left the widget.
+    PointerExit,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointerEvent {
+    pub kind: PointerEventKind,
+    /// Position relative to the widget.
+    pub position: Point,
+    pub modifiers: Modifiers,
+    pub buttons: PointerButtons,
+    /// The button that triggered the event, or `PointerButton::NONE`.
+    pub button: PointerButton,
+    /// Number of consecutive clicks for `PointerDown` events (2 for a double-click).
+    pub repeat_count: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WheelEvent {
+    /// Position of the pointer relative to the widget.
+    pub position: Point,
+    /// Scroll amount, in DIPs.
+    pub delta_x: f64,
+    pub delta_y: f64,
+    pub modifiers: Modifiers,
+}
+
+/// An input event.
+///
+/// Pointer events are delivered to a widget when the pointer is over it, or when the widget has captured the pointer
+/// (see `Host::capture_pointer`).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    Pointer(PointerEvent),
+    Wheel(WheelEvent),
+}
@@ -0,0 +1,102 @@
+//! Geometry and color types.
+//!
+//! Coordinates and lengths are in device-independent pixels (DIPs). The origin of the coordinate system of a widget
+//! is its top-left corner.
+
+/// Size of a widget.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Size {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Size {
+    pub const ZERO: Size = Size::new(0.0, 0.0);
+
+    pub const fn new(width: f64, height: f64) -> Size {
+        Size { width, height }
+    }
+}
+
+/// A position.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub const ORIGIN: Point = Point::new(0.0, 0.0);
+
+    pub const fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+}
+
+/// A rectangle.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub origin: Point,
+    pub size: Size,
+}
+
+impl Rect {
+    pub const fn new(origin: Point, size: Size) -> Rect {
+        Rect { origin, size }
+    }
+
+    /// Returns a rectangle with the specified size at the origin.
+    pub const fn from_size(size: Size) -> Rect {
+        Rect::new(Point::ORIGIN, size)
+    }
+
+    /// Returns whether the rectangle contains the specified point.
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.origin.x
+            && point.y >= self.origin.y
+            && point.x < self.origin.x + self.size.width
+            && point.y < self.origin.y + self.size.height
+    }
+}
+
+/// Minimum and maximum sizes of a widget. The maximum size can be infinite.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Constraints {
+    pub min: Size,
+    pub max: Size,
+}
+
+impl Constraints {
+    /// Returns the size closest to `size` that satisfies the constraints.
+    pub fn constrain(&self, size: Size) -> Size {
+        Size::new(
+            size.width.max(self.min.width).min(self.max.width),
+            size.height.max(self.min.height).min(self.max.height),
+        )
+    }
+}
+
+/// A color, with non-premultiplied sRGB components between 0 and 1.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Color {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub alpha: f32,
+}
+
+impl Color {
+    pub const fn new(red: f32, green: f32, blue: f32, alpha: f32) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Services provided by the host to plugin widgets.
+use crate::{Slice, Str, VTableHeader};
+use std::{ffi::c_void, marker::PhantomData};
+
+/// Functions implemented by the host (see `Host`).
+#[repr(C)]
+pub struct HostVTable {
+    pub header: VTableHeader,
+    pub scale_factor: unsafe extern "C" fn(ctx: *mut c_void) -> f64,
+    pub request_repaint: unsafe extern "C" fn(ctx: *mut c_void),
+    pub request_relayout: unsafe extern "C" fn(ctx: *mut c_void),
+    pub set_handled: unsafe extern "C" fn(ctx: *mut c_void),
+    pub capture_pointer: unsafe extern "C" fn(ctx: *mut c_void),
+    pub release_pointer: unsafe extern "C" fn(ctx: *mut c_void),
+    /// Calls `read` with `reader` and the value of the state entry, and returns true, if the entry exists.
+    pub load_state: unsafe extern "C" fn(
+        ctx: *mut c_void,
+        key: Str,
+        reader: *mut c_void,
+        read: unsafe extern "C" fn(reader: *mut c_void, value: Slice<u8>),
+    ) -> bool,
+    pub store_state: unsafe extern "C" fn(ctx: *mut c_void, key: Str, value: Slice<u8>),
+}
+
+/// Services of the host, passed to the methods of `Widget`.
+///
+/// # State
+///
+/// Widgets can store state in the host, as byte strings identified by keys (see `load_state` and `store_state`).
+/// The state of a widget is kept by the host as long as the widget stays in the UI, and survives the widget being
+/// recreated (e.g. when its properties change). Storing state repaints and relayouts the widget.
+#[repr(C)]
+pub struct Host<'a> {
+    ctx: *mut c_void,
+    vtable: &'a HostVTable,
+    _phantom: PhantomData<&'a mut ()>,
+}
+
+impl<'a> Host<'a> {
+    /// Creates a host handle. Used by hosts.
+    ///
+    /// # Safety
+    /// The functions of `vtable` must be safe to call with `ctx` while the handle is alive.
+    pub unsafe fn from_raw(ctx: *mut c_void, vtable: &'a HostVTable) -> Host<'a> {
+        Host {
+            ctx,
+            vtable,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the functions of the host. Use this to check the version of the host before calling functions added
+    /// in later minor versions.
+    pub fn vtable(&self) -> &'a HostVTable {
+        self.vtable
+    }
+
+    /// Returns the number of physical pixels per DIP.
+    pub fn scale_factor(&self) -> f64 {
+        unsafe { (self.vtable.scale_factor)(self.ctx) }
+    }
+
+    /// Requests the widget to be repainted.
+    pub fn request_repaint(&self) {
+        unsafe { (self.vtable.request_repaint)(self.ctx) }
+    }
+
+    /// Requests the widget to be laid out again. Only has an effect during `Widget::event`.
+    pub fn request_relayout(&self) {
+        unsafe { (self.vtable.request_relayout)(self.ctx) }
+    }
+
+    /// Marks the current event as handled: it won't be propagated to other widgets. Only has an effect during
+    /// `Widget::event`.
+    pub fn set_handled(&self) {
+        unsafe { (self.vtable.set_handled)(self.ctx) }
+    }
+
+    /// Sends the pointer events to the widget until `release_pointer` is called, even if the pointer leaves the
+    /// widget. Only has an effect during `Widget::event`.
+    pub fn capture_pointer(&self) {
+        unsafe { (self.vtable.capture_pointer)(self.ctx) }
+    }
+
+    /// Releases the pointer captured with `capture_pointer`. Only has an effect during `Widget::event`.
+    pub fn release_pointer(&self) {
+        unsafe { (self.vtable.release_pointer)(self.ctx) }
+    }
+
+    /// Returns the value of the state entry with the specified key.
+    pub fn load_state(&self, key: &str) -> Option<Vec<u8>> {
+        unsafe extern "C" fn read(reader: *mut c_void, value: Slice<u8>) {
+            *(reader as *mut Option<Vec<u8>>) = Some(value.as_slice().to_vec());
+        }
+        let mut value: Option<Vec<u8>> = None;
+        unsafe {
+            (self.vtable.load_state)(
+                self.ctx,
+                Str::new(key),
+                &mut value as *mut Option<Vec<u8>> as *mut c_void,
+                read,
+            );
+        }
+        value
+    }
+
+    /// Sets the value of the state entry with the specified key.
+    pub fn store_state(&self, key: &str, value: &[u8]) {
+        unsafe { (self.vtable.store_state)(self.ctx, Str::new(key), Slice::new(value)) }
+    }
+}
@@ -0,0 +1,283 @@
+//! Stable interface between kyute applications and widget plugins.
+//!
+//! Plugins are dynamic libraries that provide widgets to a host application. They don't depend on kyute: they are
+//! written against this crate, which only contains the subset of the UI model that plugins need:
+//! - the widget interface (`Widget`, and its type-erased form `WidgetObject`)
+//! - a subset of the input events (`Event`)
+//! - a drawing handle (`Canvas`)
+//! - the services of the host, including persistent widget state (`Host`)
+//!
+//! Host applications load plugins with `kyute::plugin::Plugin`, and show their widgets with
+//! `kyute::widget::PluginWidget`.
+//!
+//! # Writing a plugin
+//!
+//! ```ignore
+//! use kyute_api::{export_plugin, Canvas, Color, Constraints, Event, Host, Rect, Size, Widget, WidgetObject};
+//!
+//! struct Swatch(Color);
+//!
+//! impl Widget for Swatch {
+//!     fn layout(&mut self, _host: &Host, constraints: Constraints) -> Size {
+//!         constraints.constrain(Size::new(32.0, 32.0))
+//!     }
+//!
+//!     fn event(&mut self, _host: &Host, _event: &Event) {}
+//!
+//!     fn paint(&mut self, _host: &Host, canvas: &mut Canvas, size: Size) {
+//!         canvas.fill_rect(Rect::from_size(size), self.0);
+//!     }
+//! }
+//!
+//! fn swatch(_props: &str) -> WidgetObject {
+//!     WidgetObject::new(Swatch(Color::new(1.0, 0.5, 0.0, 1.0)))
+//! }
+//!
+//! export_plugin!("swatches", ["swatch" => swatch]);
+//! ```
+//!
+//! # Compatibility
+//!
+//! The interface is versioned with `API_VERSION`, independently of kyute. A plugin built against version `M.m` can be
+//! loaded by hosts that implement version `M.n` with `n >= m` (see `ApiVersion::is_compatible_with`).
+//!
+//! Everything that crosses the boundary between the host and a plugin is `#[repr(C)]`. Calls go through tables of
+//! function pointers (`HostVTable`, `CanvasVTable`, `WidgetVTable`) that start with a `VTableHeader`. Within a major
+//! version:
+//! - existing types, fields and enum variants are never changed or removed;
+//! - new functions are only appended at the end of vtables, and the side that calls them checks the minor version in
+//!   the header of the vtable before doing so (see `VTableHeader::supports`);
+//! - new event types are only sent to plugins whose widgets declare a version that has them.
+//!
+//! Anything else is a breaking change and requires a new major version.
+//!
+//! The tests of this crate check the layout of the types against a baseline: `abi/M.m.txt` describes the layout of
+//! version `M.m`, and must exist for the current `API_VERSION`. When the version is bumped, a baseline is added for
+//! the new version; it must contain all the lines of the baselines of the previous minor versions. Baselines of
+//! published versions are never edited.
+mod abi;
+mod canvas;
+mod event;
+mod geometry;
+mod host;
+mod plugin;
+mod widget;
+
+pub use crate::{
+    abi::{ApiVersion, Slice, Str, VTableHeader, API_VERSION},
+    canvas::{Canvas, CanvasVTable},
+    event::{Event, Modifiers, PointerButton, PointerButtons, PointerEvent, PointerEventKind, WheelEvent},
+    geometry::{Color, Constraints, Point, Rect, Size},
+    host::{Host, HostVTable},
+    plugin::{PluginDescriptor, PluginEntry, WidgetFactory, PLUGIN_ENTRY_SYMBOL},
+    widget::{Widget, WidgetObject, WidgetVTable},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{align_of, offset_of, size_of};
+
+    /// The layout of the types of version 1.0 must never change.
+    #[test]
+    fn layout_v1_0() {
+        assert_eq!(size_of::<ApiVersion>(), 4);
+        assert_eq!(size_of::<Str>(), 2 * size_of::<usize>());
+        assert_eq!(size_of::<Slice<u8>>(), 2 * size_of::<usize>());
+        assert_eq!(size_of::<Size>(), 16);
+        assert_eq!(size_of::<Point>(), 16);
+        assert_eq!(size_of::<Rect>(), 32);
+        assert_eq!(size_of::<Constraints>(), 32);
+        assert_eq!(size_of::<Color>(), 16);
+        assert_eq!(size_of::<PointerButton>(), 2);
+        assert_eq!(size_of::<PointerButtons>(), 4);
+        assert_eq!(size_of::<Modifiers>(), 4);
+
+        assert_eq!(offset_of!(PointerEvent, kind), 0);
+        assert_eq!(offset_of!(PointerEvent, position), 8);
+        assert_eq!(offset_of!(PointerEvent, modifiers), 24);
+        assert_eq!(offset_of!(PointerEvent, buttons), 28);
+        assert_eq!(offset_of!(PointerEvent, button), 32);
+        assert_eq!(offset_of!(PointerEvent, repeat_count), 36);
+        assert_eq!(size_of::<PointerEvent>(), 40);
+        assert_eq!(offset_of!(WheelEvent, position), 0);
+        assert_eq!(offset_of!(WheelEvent, delta_x), 16);
+        assert_eq!(offset_of!(WheelEvent, delta_y), 24);
+        assert_eq!(offset_of!(WheelEvent, modifiers), 32);
+        assert_eq!(size_of::<WheelEvent>(), 40);
+        assert_eq!(size_of::<Event>(), 48);
+        assert_eq!(align_of::<Event>(), 8);
+
+        let ptr = size_of::<usize>();
+        assert_eq!(offset_of!(VTableHeader, version), 0);
+        assert_eq!(size_of::<VTableHeader>(), 4);
+        assert_eq!(offset_of!(WidgetVTable, drop), ptr);
+        assert_eq!(offset_of!(WidgetVTable, layout), 2 * ptr);
+        assert_eq!(offset_of!(WidgetVTable, event), 3 * ptr);
+        assert_eq!(offset_of!(WidgetVTable, paint), 4 * ptr);
+        assert_eq!(offset_of!(HostVTable, scale_factor), ptr);
+        assert_eq!(offset_of!(HostVTable, request_repaint), 2 * ptr);
+        assert_eq!(offset_of!(HostVTable, request_relayout), 3 * ptr);
+        assert_eq!(offset_of!(HostVTable, set_handled), 4 * ptr);
+        assert_eq!(offset_of!(HostVTable, capture_pointer), 5 * ptr);
+        assert_eq!(offset_of!(HostVTable, release_pointer), 6 * ptr);
+        assert_eq!(offset_of!(HostVTable, load_state), 7 * ptr);
+        assert_eq!(offset_of!(HostVTable, store_state), 8 * ptr);
+        assert_eq!(offset_of!(CanvasVTable, save), ptr);
+        assert_eq!(offset_of!(CanvasVTable, restore), 2 * ptr);
+        assert_eq!(offset_of!(CanvasVTable, translate), 3 * ptr);
+        assert_eq!(offset_of!(CanvasVTable, clip_rect), 4 * ptr);
+        assert_eq!(offset_of!(CanvasVTable, fill_rect), 5 * ptr);
+        assert_eq!(offset_of!(CanvasVTable, stroke_rect), 6 * ptr);
+        assert_eq!(offset_of!(CanvasVTable, fill_rounded_rect), 7 * ptr);
+        assert_eq!(offset_of!(CanvasVTable, draw_line), 8 * ptr);
+        assert_eq!(offset_of!(CanvasVTable, draw_text), 9 * ptr);
+        assert_eq!(offset_of!(PluginDescriptor, api_version), 0);
+        assert_eq!(offset_of!(PluginDescriptor, name), ptr);
+        assert_eq!(offset_of!(PluginDescriptor, widgets), 3 * ptr);
+        assert_eq!(offset_of!(WidgetFactory, name), 0);
+        assert_eq!(offset_of!(WidgetFactory, create), 2 * ptr);
+    }
+
+    /// Describes the layout of the types that cross the boundary, in the format of the baselines in `abi/`.
+    fn describe_layout() -> String {
+        use std::fmt::Write;
+
+        macro_rules! describe {
+            ($out:ident, $name:expr, $ty:ty $(, $field:ident)*) => {
+                writeln!($out, "{}: size {}, align {}", $name, size_of::<$ty>(), align_of::<$ty>()).unwrap();
+                $(writeln!($out, "{}.{}: offset {}", $name, stringify!($field), offset_of!($ty, $field)).unwrap();)*
+            };
+        }
+
+        let mut out = String::new();
+        describe!(out, "ApiVersion", ApiVersion, major, minor);
+        describe!(out, "VTableHeader", VTableHeader, version);
+        describe!(out, "Str", Str);
+        describe!(out, "Slice<u8>", Slice<u8>);
+        describe!(out, "Size", Size, width, height);
+        describe!(out, "Point", Point, x, y);
+        describe!(out, "Rect", Rect, origin, size);
+        describe!(out, "Constraints", Constraints, min, max);
+        describe!(out, "Color", Color, red, green, blue, alpha);
+        describe!(out, "Modifiers", Modifiers);
+        describe!(out, "PointerButton", PointerButton);
+        describe!(out, "PointerButtons", PointerButtons);
+        describe!(out, "PointerEventKind", PointerEventKind);
+        for (name, kind) in [
+            ("PointerDown", PointerEventKind::PointerDown),
+            ("PointerUp", PointerEventKind::PointerUp),
+            ("PointerMove", PointerEventKind::PointerMove),
+            ("PointerEnter", PointerEventKind::PointerEnter),
+            ("PointerExit", PointerEventKind::PointerExit),
+        ] {
+            writeln!(out, "PointerEventKind::{} = {}", name, kind as u32).unwrap();
+        }
+        describe!(
+            out,
+            "PointerEvent",
+            PointerEvent,
+            kind,
+            position,
+            modifiers,
+            buttons,
+            button,
+            repeat_count
+        );
+        describe!(out, "WheelEvent", WheelEvent, position, delta_x, delta_y, modifiers);
+        describe!(out, "Event", Event);
+        describe!(out, "Host", Host);
+        describe!(
+            out,
+            "HostVTable",
+            HostVTable,
+            header,
+            scale_factor,
+            request_repaint,
+            request_relayout,
+            set_handled,
+            capture_pointer,
+            release_pointer,
+            load_state,
+            store_state
+        );
+        describe!(out, "Canvas", Canvas);
+        describe!(
+            out,
+            "CanvasVTable",
+            CanvasVTable,
+            header,
+            save,
+            restore,
+            translate,
+            clip_rect,
+            fill_rect,
+            stroke_rect,
+            fill_rounded_rect,
+            draw_line,
+            draw_text
+        );
+        describe!(out, "WidgetObject", WidgetObject);
+        describe!(out, "WidgetVTable", WidgetVTable, header, drop, layout, event, paint);
+        describe!(out, "WidgetFactory", WidgetFactory, name, create);
+        describe!(out, "PluginDescriptor", PluginDescriptor, api_version, name, widgets);
+        out
+    }
+
+    /// The layout matches the baseline of the current version, and keeps everything from the previous minor versions.
+    ///
+    /// Baselines are written for 64-bit targets.
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn layout_matches_baseline() {
+        let baseline = |minor: u16| {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("abi")
+                .join(format!("{}.{}.txt", API_VERSION.major, minor));
+            std::fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))
+        };
+
+        let layout = describe_layout();
+        let current = baseline(API_VERSION.minor).unwrap_or_else(|err| {
+            panic!(
+                "no layout baseline for version {}.{} ({}); current layout:\n{}",
+                API_VERSION.major, API_VERSION.minor, err, layout
+            )
+        });
+        assert_eq!(
+            current, layout,
+            "the layout doesn't match the baseline of version {}.{}",
+            API_VERSION.major, API_VERSION.minor
+        );
+
+        for minor in 0..API_VERSION.minor {
+            let previous = baseline(minor).unwrap();
+            // vtables grow when functions are appended
+            for line in previous.lines().filter(|line| !line.contains("VTable: size")) {
+                assert!(
+                    layout.lines().any(|l| l == line),
+                    "`{}` from the baseline of version {}.{} is not in the current layout",
+                    line,
+                    API_VERSION.major,
+                    minor
+                );
+            }
+        }
+
+        // the version must be bumped along with the baseline
+        assert!(
+            baseline(API_VERSION.minor + 1).is_err(),
+            "there is a baseline for a version newer than API_VERSION"
+        );
+    }
+
+    #[test]
+    fn version_compatibility() {
+        let host = ApiVersion::new(1, 2);
+        assert!(ApiVersion::new(1, 0).is_compatible_with(host));
+        assert!(ApiVersion::new(1, 2).is_compatible_with(host));
+        assert!(!ApiVersion::new(1, 3).is_compatible_with(host));
+        assert!(!ApiVersion::new(0, 1).is_compatible_with(host));
+        assert!(!ApiVersion::new(2, 0).is_compatible_with(host));
+    }
+}
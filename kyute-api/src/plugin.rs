@@ -0,0 +1,70 @@
+//! Plugin entry point.
+use crate::{ApiVersion, Slice, Str, WidgetObject};
+
+/// Name of the function exported by plugins (see `export_plugin!`).
+pub const PLUGIN_ENTRY_SYMBOL: &str = "kyute_plugin_entry";
+
+/// Type of the function exported by plugins.
+pub type PluginEntry = unsafe extern "C" fn() -> &'static PluginDescriptor;
+
+/// Creates a widget of a plugin.
+#[repr(C)]
+pub struct WidgetFactory {
+    /// Name of the widget, unique in the plugin.
+    pub name: Str<'static>,
+    /// Creates a widget. The properties of the widget are passed as a string, in a format chosen by the plugin.
+    pub create: unsafe extern "C" fn(props: Str) -> WidgetObject,
+}
+
+/// Describes the widgets provided by a plugin.
+#[repr(C)]
+pub struct PluginDescriptor {
+    /// Version of the interface that the plugin was built against.
+    pub api_version: ApiVersion,
+    pub name: Str<'static>,
+    pub widgets: Slice<'static, WidgetFactory>,
+}
+
+impl PluginDescriptor {
+    /// Returns the widget factory with the specified name.
+    pub fn widget(&self, name: &str) -> Option<&'static WidgetFactory> {
+        self.widgets
+            .as_slice()
+            .iter()
+            .find(|factory| factory.name.as_str() == name)
+    }
+}
+
+/// Exports the entry point of a plugin, with the name of the plugin and its widgets.
+///
+/// Widgets are given as `"name" => function` pairs, where `function` has the signature
+/// `fn(props: &str) -> WidgetObject`.
+///
+/// ```ignore
+/// kyute_api::export_plugin!("gauges", ["dial" => dial, "meter" => meter]);
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($name:expr, [$($widget:expr => $create:path),* $(,)?]) => {
+        #[no_mangle]
+        pub extern "C" fn kyute_plugin_entry() -> &'static $crate::PluginDescriptor {
+            static WIDGETS: &[$crate::WidgetFactory] = &[$(
+                $crate::WidgetFactory {
+                    name: $crate::Str::new($widget),
+                    create: {
+                        unsafe extern "C" fn create(props: $crate::Str) -> $crate::WidgetObject {
+                            $create(props.as_str())
+                        }
+                        create
+                    },
+                }
+            ),*];
+            static DESCRIPTOR: $crate::PluginDescriptor = $crate::PluginDescriptor {
+                api_version: $crate::API_VERSION,
+                name: $crate::Str::new($name),
+                widgets: $crate::Slice::new(WIDGETS),
+            };
+            &DESCRIPTOR
+        }
+    };
+}
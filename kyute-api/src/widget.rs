@@ -0,0 +1,98 @@
+//! Widget interface.
+use crate::{Canvas, Constraints, Event, Host, Size, VTableHeader};
+use std::{ffi::c_void, marker::PhantomData};
+
+/// A widget provided by a plugin.
+///
+/// The host calls `layout` before the first `event` or `paint`, and again whenever the constraints change or a
+/// relayout is requested.
+pub trait Widget: 'static {
+    /// Returns the size of the widget. The size must satisfy the constraints.
+    fn layout(&mut self, host: &Host, constraints: Constraints) -> Size;
+
+    /// Handles an input event. Positions are relative to the widget.
+    fn event(&mut self, host: &Host, event: &Event);
+
+    /// Paints the widget. `size` is the size returned by the last call to `layout`.
+    fn paint(&mut self, host: &Host, canvas: &mut Canvas, size: Size);
+}
+
+/// Functions of a widget implemented by a plugin (see `WidgetObject`).
+#[repr(C)]
+pub struct WidgetVTable {
+    pub header: VTableHeader,
+    pub drop: unsafe extern "C" fn(data: *mut c_void),
+    pub layout: unsafe extern "C" fn(data: *mut c_void, host: &Host, constraints: Constraints) -> Size,
+    pub event: unsafe extern "C" fn(data: *mut c_void, host: &Host, event: &Event),
+    pub paint: unsafe extern "C" fn(data: *mut c_void, host: &Host, canvas: &mut Canvas, size: Size),
+}
+
+unsafe extern "C" fn drop_widget<W: Widget>(data: *mut c_void) {
+    drop(Box::from_raw(data as *mut W))
+}
+
+unsafe extern "C" fn layout_widget<W: Widget>(data: *mut c_void, host: &Host, constraints: Constraints) -> Size {
+    (*(data as *mut W)).layout(host, constraints)
+}
+
+unsafe extern "C" fn widget_event<W: Widget>(data: *mut c_void, host: &Host, event: &Event) {
+    (*(data as *mut W)).event(host, event)
+}
+
+unsafe extern "C" fn paint_widget<W: Widget>(data: *mut c_void, host: &Host, canvas: &mut Canvas, size: Size) {
+    (*(data as *mut W)).paint(host, canvas, size)
+}
+
+struct VTableOf<W>(PhantomData<W>);
+
+impl<W: Widget> VTableOf<W> {
+    const VTABLE: WidgetVTable = WidgetVTable {
+        header: VTableHeader::CURRENT,
+        drop: drop_widget::<W>,
+        layout: layout_widget::<W>,
+        event: widget_event::<W>,
+        paint: paint_widget::<W>,
+    };
+}
+
+/// An owned, type-erased widget that can be passed between a plugin and the host.
+///
+/// The vtable lives in the plugin that created the widget: the plugin must stay loaded as long as the widget exists.
+/// Panics don't cross the boundary: a widget that panics aborts the process.
+#[repr(C)]
+pub struct WidgetObject {
+    data: *mut c_void,
+    vtable: &'static WidgetVTable,
+}
+
+impl WidgetObject {
+    pub fn new<W: Widget>(widget: W) -> WidgetObject {
+        WidgetObject {
+            data: Box::into_raw(Box::new(widget)) as *mut c_void,
+            vtable: &VTableOf::<W>::VTABLE,
+        }
+    }
+
+    /// Returns the functions of the widget. Use this to check the version that the plugin was built against.
+    pub fn vtable(&self) -> &'static WidgetVTable {
+        self.vtable
+    }
+
+    pub fn layout(&mut self, host: &Host, constraints: Constraints) -> Size {
+        unsafe { (self.vtable.layout)(self.data, host, constraints) }
+    }
+
+    pub fn event(&mut self, host: &Host, event: &Event) {
+        unsafe { (self.vtable.event)(self.data, host, event) }
+    }
+
+    pub fn paint(&mut self, host: &Host, canvas: &mut Canvas, size: Size) {
+        unsafe { (self.vtable.paint)(self.data, host, canvas, size) }
+    }
+}
+
+impl Drop for WidgetObject {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.drop)(self.data) }
+    }
+}
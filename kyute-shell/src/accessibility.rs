@@ -0,0 +1,142 @@
+//! Accessibility: exposes the contents of windows to assistive technologies (screen readers) through the platform
+//! accessibility API (UI Automation on Windows).
+//!
+//! The application describes the accessible elements of a window with an `AccessibleTree`, and updates it with
+//! `Window::set_accessibility_tree` when it changes. Assistive technologies can then inspect the elements (role,
+//! name, value, bounds) and are notified of focus changes. They can't act on the elements yet (e.g. invoke a button).
+use kyute_common::Rect;
+
+/// The role of an element, which determines how assistive technologies present it to the user.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Role {
+    Unknown,
+    Window,
+    /// A group of related elements.
+    Group,
+    /// Non-editable text.
+    Label,
+    Image,
+    Button,
+    CheckBox,
+    /// Single-line text input.
+    TextInput,
+    /// Multi-line text input.
+    MultilineTextInput,
+    Slider,
+    /// A progress bar or an activity indicator.
+    ProgressIndicator,
+    ScrollArea,
+    Menu,
+    MenuItem,
+    /// A strip of tabs. The value is the title of the selected tab.
+    TabList,
+}
+
+impl Role {
+    /// Returns whether elements with this role respond to user input, and are thus focusable by default.
+    pub fn is_interactive(self) -> bool {
+        matches!(
+            self,
+            Role::Button
+                | Role::CheckBox
+                | Role::TextInput
+                | Role::MultilineTextInput
+                | Role::Slider
+                | Role::MenuItem
+                | Role::TabList
+        )
+    }
+}
+
+/// Current value of an element that represents a number in a range (sliders, spin boxes...).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NumericValue {
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Describes an element to assistive technologies.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Semantics {
+    pub role: Role,
+    /// Name of the element (e.g. the label of a button).
+    pub name: Option<String>,
+    /// Current value of the element, as text (e.g. the contents of a text input).
+    pub value: Option<String>,
+    /// Current value of the element, for elements that represent a number in a range.
+    pub numeric_value: Option<NumericValue>,
+    /// Whether the element is checked, for elements that can be toggled.
+    pub checked: Option<bool>,
+    /// Whether the element is disabled.
+    pub disabled: bool,
+}
+
+impl Semantics {
+    /// Creates a new semantic description with the specified role.
+    pub fn new(role: Role) -> Semantics {
+        Semantics {
+            role,
+            name: None,
+            value: None,
+            numeric_value: None,
+            checked: None,
+            disabled: false,
+        }
+    }
+
+    /// Sets the name of the element.
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the current value of the element, as text.
+    #[must_use]
+    pub fn value(mut self, value: impl Into<String>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    /// Sets the current value of an element that represents a number in a range.
+    #[must_use]
+    pub fn numeric_value(mut self, value: f64, min: f64, max: f64) -> Self {
+        self.numeric_value = Some(NumericValue { value, min, max });
+        self
+    }
+
+    /// Sets whether the element is checked.
+    #[must_use]
+    pub fn checked(mut self, checked: bool) -> Self {
+        self.checked = Some(checked);
+        self
+    }
+
+    /// Sets whether the element is disabled.
+    #[must_use]
+    pub fn disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+}
+
+/// An accessible element of a window, and its descendants.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessibleNode {
+    /// Identifies the element across updates of the tree.
+    pub id: u64,
+    pub semantics: Semantics,
+    /// Bounds of the element in the client area of the window, in DIPs.
+    pub bounds: Rect,
+    pub children: Vec<AccessibleNode>,
+}
+
+/// Accessible elements of a window.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AccessibleTree {
+    /// Top-level elements.
+    pub nodes: Vec<AccessibleNode>,
+    /// ID of the element that has the keyboard focus.
+    pub focus: Option<u64>,
+}
@@ -1,5 +1,100 @@
 use crate::backend;
-use kyute_common::{SizeI, Transform};
+use kyute_common::{Color, Offset, RectI, SizeI, Transform};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Number of live compositor layers.
+pub(crate) static LIVE_LAYER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of compositor layers currently alive.
+///
+/// Clones of a `Layer` refer to the same compositor layer and are counted once.
+pub fn live_layer_count() -> usize {
+    LIVE_LAYER_COUNT.load(Ordering::Relaxed)
+}
+
+/// Returns whether compositing layers are supported on this platform.
+///
+/// Layers are implemented with DirectComposition on Windows.
+pub fn layers_supported() -> bool {
+    cfg!(windows)
+}
+
+/// Timing information from the compositor clock.
+#[derive(Copy, Clone, Debug)]
+pub struct FrameTiming {
+    /// Estimated time at which the next frame will be presented.
+    pub next_frame_time: Instant,
+    /// Interval between two frames presented by the compositor.
+    pub frame_interval: Duration,
+}
+
+/// Returns timing information from the compositor clock, which can be used to schedule animation frames.
+pub fn frame_timing() -> FrameTiming {
+    backend::frame_timing()
+}
+
+/// Presentation statistics of the contents of a layer (see `Layer::present_statistics`).
+#[derive(Copy, Clone, Debug)]
+pub struct PresentStatistics {
+    /// Number of frames presented in the layer so far.
+    ///
+    /// This is incremented each time a `Surface` acquired from the layer is dropped.
+    pub present_count: u32,
+    /// Present count of the last frame that was displayed on screen.
+    pub displayed_present_count: u32,
+    /// Time at which this frame was displayed.
+    pub displayed_time: Instant,
+}
+
+/// How the contents of a layer are shown when the size they were painted at doesn't match the size
+/// at which they are displayed.
+///
+/// This happens transiently, e.g. during interactive resizing or after a DPI change, until the contents
+/// are repainted at the new size.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ContentScaling {
+    /// Stretch the contents to fill the target size, ignoring their aspect ratio.
+    ///
+    /// This is the default.
+    Stretch,
+    /// Scale the contents uniformly so that they fit in the target size, centered, and fill the remaining space
+    /// with the specified color.
+    Letterbox(Color),
+    /// Don't scale the contents, keep them anchored in the top-left corner and crop them if the target is smaller.
+    TopLeft,
+}
+
+impl Default for ContentScaling {
+    fn default() -> Self {
+        ContentScaling::Stretch
+    }
+}
+
+impl ContentScaling {
+    /// Returns the transform that maps contents painted at `content_size` to `target_size` (both in pixels).
+    pub fn transform(&self, content_size: SizeI, target_size: SizeI) -> Transform {
+        if content_size.is_empty() {
+            return Transform::identity();
+        }
+        let sx = target_size.width as f64 / content_size.width as f64;
+        let sy = target_size.height as f64 / content_size.height as f64;
+        match *self {
+            ContentScaling::Stretch => Transform::scale(sx, sy),
+            ContentScaling::Letterbox(_) => {
+                let s = sx.min(sy);
+                let offset = Offset::new(
+                    (0.5 * (target_size.width as f64 - s * content_size.width as f64)).round(),
+                    (0.5 * (target_size.height as f64 - s * content_size.height as f64)).round(),
+                );
+                Transform::scale(s, s).then(&offset.to_transform())
+            }
+            ContentScaling::TopLeft => Transform::identity(),
+        }
+    }
+}
 
 /// A compositing layer.
 #[derive(Clone)]
@@ -15,11 +110,53 @@ impl Layer {
         Surface(self.0.acquire_surface())
     }
 
+    /// Acquires a surface to update only the specified rectangles of the contents, in pixels.
+    ///
+    /// The rest of the contents is kept from the previous frame, and the compositor only updates the damaged
+    /// rectangles on screen. The surface may still need to be repainted in other places, or entirely: see
+    /// `Surface::repaint_rects`.
+    pub fn acquire_surface_with_damage(&self, damage: &[RectI]) -> Surface {
+        Surface(self.0.acquire_surface_with_damage(damage))
+    }
+
     /// Sets the transform of this layer.
     pub fn set_transform(&self, transform: &Transform) {
         self.0.set_transform(transform)
     }
 
+    /// Clips the contents of this layer, and of its children, to the specified rectangle, in pixels in the
+    /// coordinate space of the layer (before its transform). `None` removes the clip.
+    pub fn set_clip(&self, clip: Option<RectI>) {
+        self.0.set_clip(clip)
+    }
+
+    /// Sets the opacity of this layer, between 0.0 (transparent) and 1.0 (opaque).
+    pub fn set_opacity(&self, opacity: f32) {
+        self.0.set_opacity(opacity)
+    }
+
+    /// Animates the transform of this layer on the compositor, independently of the application's event loop.
+    ///
+    /// `keyframes` are pairs of a time offset, relative to the next composition commit, and the transform at
+    /// this time, in increasing order of time. The transform is interpolated linearly between keyframes, and stays
+    /// at the last keyframe once the animation is finished. Calling `set_transform` stops the animation.
+    pub fn animate_transform(&self, keyframes: &[(Duration, Transform)]) {
+        self.0.animate_transform(keyframes)
+    }
+
+    /// Animates the opacity of this layer on the compositor, independently of the application's event loop.
+    ///
+    /// See `animate_transform` for the meaning of `keyframes`. Calling `set_opacity` stops the animation.
+    pub fn animate_opacity(&self, keyframes: &[(Duration, f32)]) {
+        self.0.animate_opacity(keyframes)
+    }
+
+    /// Like `animate_opacity`, but the last `repeat` of the animation (ending at the last keyframe) is then repeated
+    /// indefinitely, e.g. to blink. Two keyframes at the same time make an instantaneous change of opacity.
+    pub fn animate_opacity_repeat(&self, keyframes: &[(Duration, f32)], repeat: Duration) {
+        self.0.animate_opacity_repeat(keyframes, repeat)
+    }
+
     /// Adds a child layer.
     pub fn add_child(&self, layer: &Layer) {
         self.0.add_child(&layer.0)
@@ -49,6 +186,46 @@ impl Layer {
     pub fn size(&self) -> SizeI {
         self.0.size()
     }
+
+    /// Releases the buffers holding the contents of this layer.
+    ///
+    /// The layer shows nothing until it is painted again: they are allocated again on the next call to
+    /// `acquire_surface`. Returns whether there were buffers to release.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a surface returned by `acquire_surface` is still alive.
+    pub fn release_contents(&self) -> bool {
+        self.0.release_contents()
+    }
+
+    /// Returns presentation statistics of the contents of this layer.
+    ///
+    /// Returns `None` if they aren't available, e.g. if nothing has been displayed yet.
+    pub fn present_statistics(&self) -> Option<PresentStatistics> {
+        self.0.present_statistics()
+    }
+
+    /// Sets how the contents of this layer are shown when they don't match the size at which they are displayed.
+    pub fn set_content_scaling(&self, content_scaling: ContentScaling) {
+        self.0.set_content_scaling(content_scaling)
+    }
+
+    /// Returns how the contents of this layer are shown when they don't match the size at which they are displayed.
+    pub fn content_scaling(&self) -> ContentScaling {
+        self.0.content_scaling()
+    }
+
+    /// Shows the contents of this layer, as last painted, at the specified pixel size, using the content scaling mode
+    /// of the layer.
+    ///
+    /// This replaces the transform of the layer. Call `set_transform` to restore it once the contents have been
+    /// repainted at the new size. Note that this doesn't paint the letterbox background, which must be provided by
+    /// a layer behind this one.
+    pub fn fit_contents(&self, target_size: SizeI) {
+        let transform = self.content_scaling().transform(self.size(), target_size);
+        self.set_transform(&transform)
+    }
 }
 
 /// Drawing surface returned by `Layer::acquire_surface`.
@@ -62,4 +239,13 @@ impl Surface {
     pub fn size(&self) -> SizeI {
         self.0.size()
     }
+
+    /// Returns the rectangles of the surface that must be repainted, in pixels, or `None` if all of it must be
+    /// repainted.
+    ///
+    /// For surfaces returned by `Layer::acquire_surface_with_damage`, these are the damaged rectangles, plus the
+    /// parts of the surface that are out of date (the surface may show an older frame than the last one).
+    pub fn repaint_rects(&self) -> Option<&[RectI]> {
+        self.0.repaint_rects()
+    }
 }
@@ -1,11 +1,32 @@
 //! Application.
-use crate::backend;
+use crate::{backend, input::ScrollSettings};
 use lazy_static::lazy_static;
 use std::{
     sync::{Arc, Mutex, MutexGuard},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+/// Accessibility preferences set by the user in the OS settings.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AccessibilityPreferences {
+    /// Whether animations and transitions should be disabled or reduced.
+    pub reduced_motion: bool,
+    /// Whether a high-contrast color scheme is enabled.
+    pub high_contrast: bool,
+    /// Scale factor applied to the size of text (1.0 is the default size).
+    pub text_scale_factor: f64,
+}
+
+impl Default for AccessibilityPreferences {
+    fn default() -> Self {
+        AccessibilityPreferences {
+            reduced_motion: false,
+            high_contrast: false,
+            text_scale_factor: 1.0,
+        }
+    }
+}
+
 /// Mutex-protected and ref-counted alias to `graal::Context`.
 pub type GpuContext = Arc<Mutex<graal::Context>>;
 
@@ -61,6 +82,43 @@ impl Application {
         self.backend.double_click_time()
     }
 
+    /// Returns the time during which the text caret stays visible, then hidden, when blinking.
+    ///
+    /// Returns `None` if the user has disabled caret blinking.
+    pub fn caret_blink_time(&self) -> Option<Duration> {
+        self.backend.caret_blink_time()
+    }
+
+    /// Returns the time at which the system produced the input event currently being processed.
+    ///
+    /// Only meaningful while handling an input event (pointer, keyboard or wheel) received from the event loop.
+    /// The resolution is that of the system timer, typically 10 to 16 milliseconds.
+    pub fn input_event_time(&self) -> Instant {
+        self.backend.input_event_time()
+    }
+
+    /// Queries the accessibility preferences of the user.
+    ///
+    /// The preferences are queried each time this method is called: they may change while the application is running.
+    pub fn accessibility_preferences(&self) -> AccessibilityPreferences {
+        self.backend.accessibility_preferences()
+    }
+
+    /// Returns whether the system settings (accessibility preferences, scroll settings, theme) may have changed
+    /// since the last call to this method.
+    ///
+    /// The application should then query them again with `accessibility_preferences` and `scroll_settings`.
+    pub fn take_settings_changed(&self) -> bool {
+        self.backend.take_settings_changed()
+    }
+
+    /// Queries how the mouse wheel should scroll.
+    ///
+    /// Like `accessibility_preferences`, the settings are queried each time this method is called.
+    pub fn scroll_settings(&self) -> ScrollSettings {
+        self.backend.scroll_settings()
+    }
+
     /// Returns the `graal::Device` instance.
     pub fn gpu_device(&self) -> &Arc<graal::Device> {
         &self.gpu_device
@@ -0,0 +1,581 @@
+//! UI Automation provider exposing the accessibility tree of a window.
+//!
+//! All elements of the window are served by `ElementProvider`s, which only hold the ID of their element and a
+//! reference to the tree shared with the window: when the tree is replaced, providers of elements that don't exist
+//! anymore return `UIA_E_ELEMENTNOTAVAILABLE`.
+use crate::accessibility::{AccessibleNode, AccessibleTree, Role, Semantics};
+use kyute_common::{Point, Rect};
+use parking_lot::Mutex;
+use std::{collections::HashMap, ffi::c_void, mem::ManuallyDrop, ptr, sync::Arc};
+use windows::{
+    core::{implement, IUnknown, Interface, HRESULT, PCWSTR},
+    Win32::{
+        Foundation::{BOOL, BSTR, HWND, LPARAM, LRESULT, POINT, S_OK, WPARAM},
+        Graphics::Gdi::{ClientToScreen, ScreenToClient},
+        System::{
+            Com::{SAFEARRAY, VARIANT, VARIANT_0, VARIANT_0_0, VARIANT_0_0_0, VT_BOOL, VT_BSTR, VT_I4},
+            Ole::{SafeArrayCreateVector, SafeArrayPutElement},
+        },
+        UI::Accessibility::{
+            IRangeValueProvider, IRangeValueProvider_Impl, IRawElementProviderFragment,
+            IRawElementProviderFragmentRoot, IRawElementProviderFragmentRoot_Impl, IRawElementProviderFragment_Impl,
+            IRawElementProviderSimple, IRawElementProviderSimple_Impl, IToggleProvider, IToggleProvider_Impl,
+            IValueProvider, IValueProvider_Impl, NavigateDirection, NavigateDirection_FirstChild,
+            NavigateDirection_LastChild, NavigateDirection_NextSibling, NavigateDirection_Parent,
+            NavigateDirection_PreviousSibling, ProviderOptions, ProviderOptions_ServerSideProvider,
+            ProviderOptions_UseComThreading, StructureChangeType_ChildrenInvalidated, ToggleState, ToggleState_Off,
+            ToggleState_On, UIA_AutomationFocusChangedEventId, UIA_AutomationIdPropertyId, UIA_ButtonControlTypeId,
+            UIA_CheckBoxControlTypeId, UIA_ControlTypePropertyId, UIA_CustomControlTypeId, UIA_EditControlTypeId,
+            UIA_FrameworkIdPropertyId, UIA_GroupControlTypeId, UIA_HasKeyboardFocusPropertyId, UIA_ImageControlTypeId,
+            UIA_IsEnabledPropertyId, UIA_IsKeyboardFocusablePropertyId, UIA_MenuControlTypeId,
+            UIA_MenuItemControlTypeId, UIA_NamePropertyId, UIA_ProgressBarControlTypeId, UIA_RangeValuePatternId,
+            UIA_ScrollBarControlTypeId, UIA_SliderControlTypeId, UIA_TabControlTypeId, UIA_TextControlTypeId,
+            UIA_TogglePatternId, UIA_ValuePatternId, UIA_WindowControlTypeId, UiaAppendRuntimeId,
+            UiaClientsAreListening, UiaHostProviderFromHwnd, UiaRaiseAutomationEvent, UiaRaiseStructureChangedEvent,
+            UiaRect, UiaReturnRawElementProvider, UIA_E_ELEMENTNOTAVAILABLE, UIA_E_NOTSUPPORTED,
+        },
+    },
+};
+
+/// An element of the tree, with links to its parent and children.
+struct Element {
+    id: u64,
+    semantics: Semantics,
+    bounds: Rect,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// Accessibility tree of a window, shared between the window and the providers of its elements.
+struct TreeState {
+    hwnd: HWND,
+    scale_factor: f64,
+    /// Whether a tree has been set.
+    initialized: bool,
+    /// Whether an assistive technology has asked for the tree of this window.
+    connected: bool,
+    /// All elements, in depth-first order.
+    elements: Vec<Element>,
+    /// Indices of the top-level elements.
+    roots: Vec<usize>,
+    /// Maps element IDs to indices in `elements`.
+    index: HashMap<u64, usize>,
+    focus: Option<u64>,
+}
+
+impl TreeState {
+    /// Flattens the nodes of an `AccessibleTree`.
+    fn flatten(&mut self, nodes: Vec<AccessibleNode>, parent: Option<usize>) -> Vec<usize> {
+        let mut indices = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            let index = self.elements.len();
+            self.elements.push(Element {
+                id: node.id,
+                semantics: node.semantics,
+                bounds: node.bounds,
+                parent,
+                children: Vec::new(),
+            });
+            self.index.insert(node.id, index);
+            self.elements[index].children = self.flatten(node.children, Some(index));
+            indices.push(index);
+        }
+        indices
+    }
+
+    fn element(&self, id: u64) -> windows::core::Result<&Element> {
+        match self.index.get(&id) {
+            Some(&index) => Ok(&self.elements[index]),
+            None => Err(HRESULT(UIA_E_ELEMENTNOTAVAILABLE as i32).into()),
+        }
+    }
+
+    /// Returns the siblings of an element (including itself).
+    fn siblings(&self, element: &Element) -> &[usize] {
+        match element.parent {
+            Some(parent) => &self.elements[parent].children,
+            None => &self.roots,
+        }
+    }
+
+    /// Converts bounds in DIPs relative to the client area to physical pixels relative to the desktop.
+    fn to_screen(&self, bounds: Rect) -> UiaRect {
+        let mut origin = POINT {
+            x: (bounds.origin.x * self.scale_factor).round() as i32,
+            y: (bounds.origin.y * self.scale_factor).round() as i32,
+        };
+        unsafe {
+            // SAFETY: no particular requirements
+            ClientToScreen(self.hwnd, &mut origin);
+        }
+        UiaRect {
+            left: origin.x as f64,
+            top: origin.y as f64,
+            width: bounds.size.width * self.scale_factor,
+            height: bounds.size.height * self.scale_factor,
+        }
+    }
+
+    /// Returns the innermost element under the specified point, in DIPs relative to the client area.
+    fn hit_test(&self, point: Point) -> Option<u64> {
+        let mut candidates = &self.roots[..];
+        let mut hit = None;
+        // later siblings are painted above the earlier ones
+        while let Some(&index) = candidates
+            .iter()
+            .rev()
+            .find(|&&index| self.elements[index].bounds.contains(point))
+        {
+            hit = Some(self.elements[index].id);
+            candidates = &self.elements[index].children;
+        }
+        hit
+    }
+}
+
+/// Returns whether assistive technologies are listening for UI Automation events.
+pub(crate) fn clients_listening() -> bool {
+    unsafe {
+        // SAFETY: no particular requirements
+        UiaClientsAreListening().as_bool()
+    }
+}
+
+/// Serves the accessibility tree of a window to UI Automation.
+#[derive(Clone)]
+pub(crate) struct AccessibilityProvider {
+    state: Arc<Mutex<TreeState>>,
+}
+
+impl AccessibilityProvider {
+    pub(crate) fn new(hwnd: HWND, scale_factor: f64) -> AccessibilityProvider {
+        AccessibilityProvider {
+            state: Arc::new(Mutex::new(TreeState {
+                hwnd,
+                scale_factor,
+                initialized: false,
+                connected: false,
+                elements: Vec::new(),
+                roots: Vec::new(),
+                index: HashMap::new(),
+                focus: None,
+            })),
+        }
+    }
+
+    /// Returns whether an assistive technology has asked for the tree of the window.
+    pub(crate) fn is_connected(&self) -> bool {
+        self.state.lock().connected
+    }
+
+    pub(crate) fn set_scale_factor(&self, scale_factor: f64) {
+        self.state.lock().scale_factor = scale_factor;
+    }
+
+    /// Replaces the accessibility tree, and notifies assistive technologies of the changes.
+    pub(crate) fn set_tree(&self, tree: AccessibleTree) {
+        let mut state = self.state.lock();
+        let focus_changed = state.focus != tree.focus;
+        state.elements.clear();
+        state.index.clear();
+        state.roots = state.flatten(tree.nodes, None);
+        state.focus = tree.focus;
+        state.initialized = true;
+        drop(state);
+
+        if !clients_listening() {
+            return;
+        }
+        // the tree is only updated after a recomposition or a relayout, which can change any element: let clients
+        // refresh everything below the root
+        unsafe {
+            // SAFETY: no particular requirements (the root has no runtime ID)
+            let root: IRawElementProviderSimple = self.element_provider(None).into();
+            if let Err(err) =
+                UiaRaiseStructureChangedEvent(&root, StructureChangeType_ChildrenInvalidated, ptr::null_mut(), 0)
+            {
+                warn!("failed to raise structure changed event: {}", err);
+            }
+            if focus_changed {
+                if let Some(focus) = tree.focus {
+                    let focused: IRawElementProviderSimple = self.element_provider(Some(focus)).into();
+                    if let Err(err) = UiaRaiseAutomationEvent(&focused, UIA_AutomationFocusChangedEventId) {
+                        warn!("failed to raise focus changed event: {}", err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Answers a `WM_GETOBJECT` message asking for the UI Automation root of the window.
+    ///
+    /// Returns `true` in `needs_tree` if no tree has been set yet.
+    pub(crate) fn handle_get_object(&self, wparam: WPARAM, lparam: LPARAM) -> (LRESULT, bool) {
+        let (hwnd, needs_tree) = {
+            let mut state = self.state.lock();
+            state.connected = true;
+            (state.hwnd, !state.initialized)
+        };
+        let root: IRawElementProviderSimple = self.element_provider(None).into();
+        let result = unsafe {
+            // SAFETY: no particular requirements
+            UiaReturnRawElementProvider(hwnd, wparam, lparam, &root)
+        };
+        (result, needs_tree)
+    }
+
+    fn element_provider(&self, id: Option<u64>) -> ElementProvider {
+        ElementProvider {
+            state: self.state.clone(),
+            id,
+        }
+    }
+}
+
+/// UI Automation control type of a role.
+fn control_type(role: Role) -> i32 {
+    match role {
+        Role::Unknown => UIA_CustomControlTypeId,
+        Role::Window => UIA_WindowControlTypeId,
+        Role::Group => UIA_GroupControlTypeId,
+        Role::Label => UIA_TextControlTypeId,
+        Role::Image => UIA_ImageControlTypeId,
+        Role::Button => UIA_ButtonControlTypeId,
+        Role::CheckBox => UIA_CheckBoxControlTypeId,
+        Role::TextInput | Role::MultilineTextInput => UIA_EditControlTypeId,
+        Role::Slider => UIA_SliderControlTypeId,
+        Role::ProgressIndicator => UIA_ProgressBarControlTypeId,
+        Role::ScrollArea => UIA_ScrollBarControlTypeId,
+        Role::Menu => UIA_MenuControlTypeId,
+        Role::MenuItem => UIA_MenuItemControlTypeId,
+        Role::TabList => UIA_TabControlTypeId,
+    }
+}
+
+fn variant_i32(value: i32) -> VARIANT {
+    VARIANT {
+        Anonymous: VARIANT_0 {
+            Anonymous: ManuallyDrop::new(VARIANT_0_0 {
+                vt: VT_I4.0 as u16,
+                wReserved1: 0,
+                wReserved2: 0,
+                wReserved3: 0,
+                Anonymous: VARIANT_0_0_0 { lVal: value },
+            }),
+        },
+    }
+}
+
+fn variant_bool(value: bool) -> VARIANT {
+    VARIANT {
+        Anonymous: VARIANT_0 {
+            Anonymous: ManuallyDrop::new(VARIANT_0_0 {
+                vt: VT_BOOL.0 as u16,
+                wReserved1: 0,
+                wReserved2: 0,
+                wReserved3: 0,
+                Anonymous: VARIANT_0_0_0 {
+                    boolVal: if value { -1 } else { 0 },
+                },
+            }),
+        },
+    }
+}
+
+fn variant_str(value: &str) -> VARIANT {
+    VARIANT {
+        Anonymous: VARIANT_0 {
+            Anonymous: ManuallyDrop::new(VARIANT_0_0 {
+                vt: VT_BSTR.0 as u16,
+                wReserved1: 0,
+                wReserved2: 0,
+                wReserved3: 0,
+                Anonymous: VARIANT_0_0_0 {
+                    bstrVal: ManuallyDrop::new(BSTR::from(value)),
+                },
+            }),
+        },
+    }
+}
+
+/// Error returned by `Navigate`, `GetFocus`, etc. when there is no element: a null pointer with `S_OK`.
+fn no_element() -> windows::core::Error {
+    S_OK.into()
+}
+
+/// Provider of an element of the tree, or of the root element of the window if `id` is `None`.
+///
+/// Elements can't be acted on: only the read-only parts of the value, toggle and range value patterns are implemented.
+#[implement(
+    IRawElementProviderSimple,
+    IRawElementProviderFragment,
+    IRawElementProviderFragmentRoot,
+    IValueProvider,
+    IToggleProvider,
+    IRangeValueProvider
+)]
+struct ElementProvider {
+    state: Arc<Mutex<TreeState>>,
+    id: Option<u64>,
+}
+
+impl ElementProvider {
+    fn provider(&self, id: Option<u64>) -> ElementProvider {
+        ElementProvider {
+            state: self.state.clone(),
+            id,
+        }
+    }
+
+    fn fragment(&self, id: Option<u64>) -> IRawElementProviderFragment {
+        self.provider(id).into()
+    }
+
+    /// Calls a function with the semantics of the element.
+    fn with_semantics<R>(&self, f: impl FnOnce(&Semantics) -> windows::core::Result<R>) -> windows::core::Result<R> {
+        let state = self.state.lock();
+        match self.id {
+            Some(id) => f(&state.element(id)?.semantics),
+            None => Err(HRESULT(UIA_E_NOTSUPPORTED as i32).into()),
+        }
+    }
+
+    fn numeric_value(&self, f: impl FnOnce(f64, f64, f64) -> f64) -> windows::core::Result<f64> {
+        self.with_semantics(|semantics| match semantics.numeric_value {
+            Some(value) => Ok(f(value.value, value.min, value.max)),
+            None => Err(HRESULT(UIA_E_NOTSUPPORTED as i32).into()),
+        })
+    }
+}
+
+#[allow(non_snake_case)]
+impl IRawElementProviderSimple_Impl for ElementProvider {
+    fn ProviderOptions(&self) -> windows::core::Result<ProviderOptions> {
+        Ok(ProviderOptions(
+            ProviderOptions_ServerSideProvider.0 | ProviderOptions_UseComThreading.0,
+        ))
+    }
+
+    fn GetPatternProvider(&self, patternid: i32) -> windows::core::Result<IUnknown> {
+        let supported = self.with_semantics(|semantics| {
+            Ok(match patternid {
+                UIA_ValuePatternId => semantics.value.is_some(),
+                UIA_TogglePatternId => semantics.checked.is_some(),
+                UIA_RangeValuePatternId => semantics.numeric_value.is_some(),
+                _ => false,
+            })
+        });
+        if !supported.unwrap_or(false) {
+            return Err(no_element());
+        }
+        let provider = self.provider(self.id);
+        match patternid {
+            UIA_ValuePatternId => IValueProvider::from(provider).cast(),
+            UIA_TogglePatternId => IToggleProvider::from(provider).cast(),
+            _ => IRangeValueProvider::from(provider).cast(),
+        }
+    }
+
+    fn GetPropertyValue(&self, propertyid: i32) -> windows::core::Result<VARIANT> {
+        let state = self.state.lock();
+        let id = match self.id {
+            Some(id) => id,
+            // the other properties of the root are provided by the host window
+            None => {
+                return Ok(match propertyid {
+                    UIA_FrameworkIdPropertyId => variant_str("kyute"),
+                    _ => VARIANT::default(),
+                })
+            }
+        };
+        let semantics = &state.element(id)?.semantics;
+        Ok(match propertyid {
+            UIA_ControlTypePropertyId => variant_i32(control_type(semantics.role)),
+            UIA_NamePropertyId => variant_str(semantics.name.as_deref().unwrap_or_default()),
+            UIA_AutomationIdPropertyId => variant_str(&id.to_string()),
+            UIA_FrameworkIdPropertyId => variant_str("kyute"),
+            UIA_IsEnabledPropertyId => variant_bool(!semantics.disabled),
+            UIA_IsKeyboardFocusablePropertyId => variant_bool(semantics.role.is_interactive() && !semantics.disabled),
+            UIA_HasKeyboardFocusPropertyId => variant_bool(state.focus == Some(id)),
+            _ => VARIANT::default(),
+        })
+    }
+
+    fn HostRawElementProvider(&self) -> windows::core::Result<IRawElementProviderSimple> {
+        match self.id {
+            None => unsafe {
+                // SAFETY: no particular requirements
+                UiaHostProviderFromHwnd(self.state.lock().hwnd)
+            },
+            Some(_) => Err(no_element()),
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+impl IRawElementProviderFragment_Impl for ElementProvider {
+    fn Navigate(&self, direction: NavigateDirection) -> windows::core::Result<IRawElementProviderFragment> {
+        let state = self.state.lock();
+        let target = match self.id {
+            None => match direction {
+                NavigateDirection_FirstChild => state.roots.first().map(|&index| Some(state.elements[index].id)),
+                NavigateDirection_LastChild => state.roots.last().map(|&index| Some(state.elements[index].id)),
+                _ => None,
+            },
+            Some(id) => {
+                let element = state.element(id)?;
+                let siblings = state.siblings(element);
+                let position = siblings.iter().position(|&index| state.elements[index].id == id);
+                let sibling = |offset: isize| {
+                    position
+                        .and_then(|position| siblings.get((position as isize + offset) as usize))
+                        .map(|&index| Some(state.elements[index].id))
+                };
+                match direction {
+                    NavigateDirection_Parent => Some(element.parent.map(|index| state.elements[index].id)),
+                    NavigateDirection_NextSibling => sibling(1),
+                    NavigateDirection_PreviousSibling if position != Some(0) => sibling(-1),
+                    NavigateDirection_FirstChild => {
+                        element.children.first().map(|&index| Some(state.elements[index].id))
+                    }
+                    NavigateDirection_LastChild => element.children.last().map(|&index| Some(state.elements[index].id)),
+                    _ => None,
+                }
+            }
+        };
+        drop(state);
+        match target {
+            Some(target) => Ok(self.fragment(target)),
+            None => Err(no_element()),
+        }
+    }
+
+    fn GetRuntimeId(&self) -> windows::core::Result<*mut SAFEARRAY> {
+        let id = match self.id {
+            Some(id) => id,
+            // the runtime ID of the root is provided by the host window
+            None => return Ok(ptr::null_mut()),
+        };
+        self.state.lock().element(id)?;
+        let runtime_id = [UiaAppendRuntimeId as i32, id as u32 as i32, (id >> 32) as u32 as i32];
+        unsafe {
+            // SAFETY: the array has 3 elements of type i32
+            let array = SafeArrayCreateVector(VT_I4.0 as u16, 0, runtime_id.len() as u32);
+            for (i, value) in runtime_id.iter().enumerate() {
+                SafeArrayPutElement(array, &(i as i32), value as *const i32 as *const c_void)?;
+            }
+            Ok(array)
+        }
+    }
+
+    fn BoundingRectangle(&self) -> windows::core::Result<UiaRect> {
+        let state = self.state.lock();
+        match self.id {
+            Some(id) => Ok(state.to_screen(state.element(id)?.bounds)),
+            // the bounds of the root are provided by the host window
+            None => Ok(UiaRect::default()),
+        }
+    }
+
+    fn GetEmbeddedFragmentRoots(&self) -> windows::core::Result<*mut SAFEARRAY> {
+        Ok(ptr::null_mut())
+    }
+
+    fn SetFocus(&self) -> windows::core::Result<()> {
+        Err(HRESULT(UIA_E_NOTSUPPORTED as i32).into())
+    }
+
+    fn FragmentRoot(&self) -> windows::core::Result<IRawElementProviderFragmentRoot> {
+        Ok(self.provider(None).into())
+    }
+}
+
+#[allow(non_snake_case)]
+impl IRawElementProviderFragmentRoot_Impl for ElementProvider {
+    fn ElementProviderFromPoint(&self, x: f64, y: f64) -> windows::core::Result<IRawElementProviderFragment> {
+        let state = self.state.lock();
+        let mut point = POINT {
+            x: x as i32,
+            y: y as i32,
+        };
+        unsafe {
+            // SAFETY: no particular requirements
+            ScreenToClient(state.hwnd, &mut point);
+        }
+        let point = Point::new(point.x as f64 / state.scale_factor, point.y as f64 / state.scale_factor);
+        let hit = state.hit_test(point);
+        drop(state);
+        Ok(self.fragment(hit))
+    }
+
+    fn GetFocus(&self) -> windows::core::Result<IRawElementProviderFragment> {
+        let focus = self.state.lock().focus;
+        match focus {
+            Some(focus) => Ok(self.fragment(Some(focus))),
+            None => Err(no_element()),
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+impl IValueProvider_Impl for ElementProvider {
+    fn SetValue(&self, _val: &PCWSTR) -> windows::core::Result<()> {
+        Err(HRESULT(UIA_E_NOTSUPPORTED as i32).into())
+    }
+
+    fn Value(&self) -> windows::core::Result<BSTR> {
+        self.with_semantics(|semantics| Ok(BSTR::from(semantics.value.as_deref().unwrap_or_default())))
+    }
+
+    fn IsReadOnly(&self) -> windows::core::Result<BOOL> {
+        Ok(true.into())
+    }
+}
+
+#[allow(non_snake_case)]
+impl IToggleProvider_Impl for ElementProvider {
+    fn Toggle(&self) -> windows::core::Result<()> {
+        Err(HRESULT(UIA_E_NOTSUPPORTED as i32).into())
+    }
+
+    fn ToggleState(&self) -> windows::core::Result<ToggleState> {
+        self.with_semantics(|semantics| {
+            Ok(if semantics.checked == Some(true) {
+                ToggleState_On
+            } else {
+                ToggleState_Off
+            })
+        })
+    }
+}
+
+#[allow(non_snake_case)]
+impl IRangeValueProvider_Impl for ElementProvider {
+    fn SetValue(&self, _val: f64) -> windows::core::Result<()> {
+        Err(HRESULT(UIA_E_NOTSUPPORTED as i32).into())
+    }
+
+    fn Value(&self) -> windows::core::Result<f64> {
+        self.numeric_value(|value, _, _| value)
+    }
+
+    fn IsReadOnly(&self) -> windows::core::Result<BOOL> {
+        Ok(true.into())
+    }
+
+    fn Maximum(&self) -> windows::core::Result<f64> {
+        self.numeric_value(|_, _, max| max)
+    }
+
+    fn Minimum(&self) -> windows::core::Result<f64> {
+        self.numeric_value(|_, min, _| min)
+    }
+
+    fn LargeChange(&self) -> windows::core::Result<f64> {
+        self.numeric_value(|_, min, max| (max - min) / 10.0)
+    }
+
+    fn SmallChange(&self) -> windows::core::Result<f64> {
+        self.numeric_value(|_, min, max| (max - min) / 100.0)
+    }
+}
@@ -1,22 +1,24 @@
 //! Composition layers - DirectComposition
-use crate::application::Application;
+use crate::{animation, application::Application, backend::windows::util::qpc_to_instant};
 use graal::{platform::windows::DeviceExtWindows, vk};
-use kyute_common::{counter::Counter, SizeI, Transform};
+use kyute_common::{counter::Counter, RectI, SizeI, Transform};
 use skia_safe::runtime_effect::uniform::Type::Int;
 use std::{
     cell::{Cell, RefCell, RefMut},
     ffi::c_void,
     mem::ManuallyDrop,
     ptr,
-    sync::Arc,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
 };
 use tracing::trace;
 use windows::{
-    core::{Interface, PCWSTR},
+    core::{IUnknown, Interface, PCWSTR},
     Foundation::Numerics::Matrix3x2,
     Win32::{
-        Foundation::{CloseHandle, HANDLE},
+        Foundation::{CloseHandle, HANDLE, RECT},
         Graphics::{
+            Direct2D::Common::D2D_RECT_F,
             Direct3D12::{
                 ID3D12CommandList, ID3D12Fence, ID3D12GraphicsCommandList, ID3D12Resource,
                 D3D12_COMMAND_LIST_TYPE_DIRECT, D3D12_CPU_DESCRIPTOR_HANDLE, D3D12_FENCE_FLAG_SHARED,
@@ -24,15 +26,18 @@ use windows::{
                 D3D12_RESOURCE_BARRIER_TYPE_TRANSITION, D3D12_RESOURCE_STATE_PRESENT,
                 D3D12_RESOURCE_STATE_RENDER_TARGET, D3D12_RESOURCE_TRANSITION_BARRIER,
             },
-            DirectComposition::{IDCompositionVisual2, IDCompositionVisual3},
+            DirectComposition::{
+                IDCompositionAnimation, IDCompositionMatrixTransform, IDCompositionVisual2, IDCompositionVisual3,
+                DCOMPOSITION_FRAME_STATISTICS,
+            },
             Dxgi::{
                 Common::{
                     DXGI_ALPHA_MODE_IGNORE, DXGI_ALPHA_MODE_PREMULTIPLIED, DXGI_FORMAT_R10G10B10A2_UNORM,
                     DXGI_FORMAT_R16G16B16A16_FLOAT, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
                     DXGI_SAMPLE_DESC,
                 },
-                IDXGISwapChain3, DXGI_SCALING_STRETCH, DXGI_SWAP_CHAIN_DESC1,
-                DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT, DXGI_SWAP_EFFECT_FLIP_DISCARD,
+                IDXGISwapChain3, DXGI_FRAME_STATISTICS, DXGI_PRESENT_PARAMETERS, DXGI_SCALING_STRETCH,
+                DXGI_SWAP_CHAIN_DESC1, DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT,
                 DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL, DXGI_USAGE_RENDER_TARGET_OUTPUT, DXGI_USAGE_SHARED,
             },
         },
@@ -46,6 +51,8 @@ use windows::{
 
 //const COMPOSITION_SWAP_CHAIN_COUNTER: Counter = Counter::new();
 
+const SWAP_CHAIN_BUFFER_COUNT: u32 = 2;
+
 struct InteropImage {
     /// Shared handle to DXGI swap chain buffer.
     image_shared_handle: HANDLE,
@@ -54,8 +61,10 @@ struct InteropImage {
     /// Command list containing a single transition barrier for the swap chain buffer.
     ///
     /// Submitted to the D3D12 Queue before Vulkan work, needed to force an implicit synchronization with
-    /// presentation.
+    /// presentation. Discards the contents of the buffer.
     barrier_command_list: ID3D12GraphicsCommandList,
+    /// Same as `barrier_command_list`, but keeps the contents of the buffer, for partial updates.
+    preserve_command_list: ID3D12GraphicsCommandList,
 }
 
 /// A wrapper around a DXGI swap chain, whose buffers are shared with vulkan images.
@@ -88,9 +97,11 @@ impl CompositionSwapChain {
             Stereo: false.into(),
             SampleDesc: DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
             BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
-            BufferCount: 2,
+            BufferCount: SWAP_CHAIN_BUFFER_COUNT,
             Scaling: DXGI_SCALING_STRETCH,
-            SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
+            // the contents of the buffers must be kept for partial updates, and dirty rectangles are
+            // only supported by the sequential flip model
+            SwapEffect: DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL,
             AlphaMode: DXGI_ALPHA_MODE_PREMULTIPLIED,
             Flags: DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT.0 as u32,
         };
@@ -123,7 +134,7 @@ impl CompositionSwapChain {
 
         unsafe {
             // --- wrap swap chain buffers as vulkan images ---
-            for i in 0..2 {
+            for i in 0..SWAP_CHAIN_BUFFER_COUNT {
                 // obtain the ID3D12Resource of each swap chain buffer and create a shared handle for them
                 let swap_chain_buffer: ID3D12Resource = self
                     .swap_chain
@@ -188,10 +199,21 @@ impl CompositionSwapChain {
                 command_list.DiscardResource(&swap_chain_buffer, ptr::null());
                 command_list.Close();
 
+                let preserve_command_list: ID3D12GraphicsCommandList = d3d12_device
+                    .CreateCommandList(
+                        0,
+                        D3D12_COMMAND_LIST_TYPE_DIRECT,
+                        app.backend.d3d12_command_allocator.get_ref().unwrap(),
+                        None,
+                    )
+                    .unwrap();
+                preserve_command_list.Close();
+
                 let interop_image = InteropImage {
                     image_shared_handle: shared_handle,
                     image: imported_image,
                     barrier_command_list: command_list,
+                    preserve_command_list,
                 };
 
                 self.interop_images.push(interop_image);
@@ -230,7 +252,7 @@ impl CompositionSwapChain {
         unsafe {
             self.swap_chain
                 .ResizeBuffers(
-                    2,
+                    SWAP_CHAIN_BUFFER_COUNT,
                     new_size.width as u32,
                     new_size.height as u32,
                     DXGI_FORMAT_R16G16B16A16_FLOAT,
@@ -255,17 +277,22 @@ impl Drop for CompositionSwapChain {
 
 pub struct Surface {
     layer: Arc<LayerImpl>,
-    buffer: graal::ImageInfo,
+    buffer: AcquiredBuffer,
 }
 
 impl Surface {
     pub fn image_info(&self) -> graal::ImageInfo {
-        self.buffer
+        self.buffer.image
     }
 
     pub fn size(&self) -> SizeI {
         self.layer.size.get()
     }
+
+    /// See `crate::animation::Surface::repaint_rects`.
+    pub fn repaint_rects(&self) -> Option<&[RectI]> {
+        self.buffer.repaint.as_deref()
+    }
 }
 
 impl Drop for Surface {
@@ -276,6 +303,17 @@ impl Drop for Surface {
     }
 }
 
+/// A buffer of the swap chain acquired for drawing.
+struct AcquiredBuffer {
+    image: graal::ImageInfo,
+    /// Index of the buffer in the swap chain.
+    index: usize,
+    /// Parts of the contents that change in this frame, in pixels. `None` if they may all change.
+    damage: Option<Vec<RectI>>,
+    /// Parts of the buffer that must be repainted. `None` if the whole buffer must be repainted.
+    repaint: Option<Vec<RectI>>,
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Composition layer
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -306,6 +344,34 @@ pub(crate) struct LayerImpl {
 
     /// Whether there's an instance of `Surface` drawing to a buffer of the swap chain.
     surface_acquired: Cell<bool>,
+
+    /// For each buffer of the swap chain, the parts of the contents that changed since the buffer was last presented,
+    /// in pixels. `None` if the contents of the buffer are unknown.
+    ///
+    /// The buffers keep their contents between frames: a buffer is up-to-date once these parts are repainted.
+    buffer_damage: RefCell<Vec<Option<Vec<RectI>>>>,
+
+    /// How the contents are shown when they don't match the displayed size.
+    content_scaling: Cell<animation::ContentScaling>,
+}
+
+impl Drop for LayerImpl {
+    fn drop(&mut self) {
+        let app = Application::instance();
+        let device = app.gpu_device();
+        unsafe {
+            if self.presentation_fence_value.get() > 1 {
+                // a surface has been acquired: submitted vulkan work may still wait on the semaphore
+                let _gpu_ctx = app.lock_gpu_context();
+                if let Err(err) = device.device.device_wait_idle() {
+                    tracing::error!("vkDeviceWaitIdle failed: {}", err);
+                }
+            }
+            device.device.destroy_semaphore(self.presentation_fence_semaphore, None);
+            CloseHandle(self.presentation_fence_shared_handle);
+        }
+        animation::LIVE_LAYER_COUNT.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl LayerImpl {
@@ -333,6 +399,8 @@ impl LayerImpl {
             )
         };
 
+        animation::LIVE_LAYER_COUNT.fetch_add(1, Ordering::Relaxed);
+
         LayerImpl {
             visual,
             size: Default::default(),
@@ -342,9 +410,16 @@ impl LayerImpl {
             presentation_fence,
             presentation_fence_shared_handle,
             surface_acquired: Default::default(),
+            buffer_damage: RefCell::new(Vec::new()),
+            content_scaling: Default::default(),
         }
     }
 
+    /// Forgets the contents of the buffers of the swap chain, after they have been (re)allocated.
+    fn reset_buffer_damage(&self) {
+        *self.buffer_damage.borrow_mut() = vec![None; SWAP_CHAIN_BUFFER_COUNT as usize];
+    }
+
     fn ensure_swap_chain(&self) -> RefMut<CompositionSwapChain> {
         // FIXME cancerous borrow_mut code
         let mut swap_chain = self.swap_chain.borrow_mut();
@@ -356,12 +431,15 @@ impl LayerImpl {
                     self.visual.SetContent(&sc.swap_chain).expect("SetContent failed");
                 }
                 *swap_chain = Some(sc);
+                self.reset_buffer_damage();
             }
         }
         RefMut::map(swap_chain, |s| s.as_mut().unwrap())
     }
 
-    fn acquire_surface(&self) -> graal::ImageInfo {
+    /// Acquires the next buffer of the swap chain, to update the specified parts of the contents (`None` to update
+    /// all of them).
+    fn acquire_surface(&self, damage: Option<Vec<RectI>>) -> AcquiredBuffer {
         assert!(!self.surface_acquired.get());
 
         let app = Application::instance();
@@ -370,6 +448,17 @@ impl LayerImpl {
         let buf_index = unsafe { swap_chain.swap_chain.GetCurrentBackBufferIndex() };
         let interop_image = &swap_chain.interop_images[buf_index as usize];
 
+        // the buffer must be brought up-to-date with the frames presented since it was last drawn to
+        let repaint = match (&damage, &self.buffer_damage.borrow()[buf_index as usize]) {
+            (Some(damage), Some(missed)) => Some(damage.iter().chain(missed.iter()).copied().collect::<Vec<_>>()),
+            _ => None,
+        };
+        let command_list = if repaint.is_some() {
+            &interop_image.preserve_command_list
+        } else {
+            &interop_image.barrier_command_list
+        };
+
         let fence_value = self.presentation_fence_value.get();
         self.presentation_fence_value.set(fence_value + 1);
 
@@ -379,7 +468,7 @@ impl LayerImpl {
             // dummy rendering
             command_queue
                 .0
-                .ExecuteCommandLists(&[Some(interop_image.barrier_command_list.clone().into())]);
+                .ExecuteCommandLists(&[Some(command_list.clone().into())]);
             command_queue.0.Signal(&self.presentation_fence, fence_value).unwrap();
         }
 
@@ -400,13 +489,18 @@ impl LayerImpl {
         }
 
         self.surface_acquired.set(true);
-        interop_image.image
+        AcquiredBuffer {
+            image: interop_image.image,
+            index: buf_index as usize,
+            damage,
+            repaint,
+        }
     }
 
     /// Presents and releases a surface.
     ///
     /// Called by Surface::drop.
-    unsafe fn present_and_release_surface(&self, _buffer: &graal::ImageInfo) {
+    unsafe fn present_and_release_surface(&self, buffer: &AcquiredBuffer) {
         let _span = trace_span!("present_and_release_surface").entered();
         trace!("surface present");
 
@@ -435,15 +529,127 @@ impl LayerImpl {
             .Wait(&self.presentation_fence, fence_value)
             .unwrap();
 
-        self.ensure_swap_chain()
-            .swap_chain
-            .Present(1, 0)
-            .ok()
-            .expect("Present failed");
+        // the other buffers miss the changes of this frame
+        for (index, missed) in self.buffer_damage.borrow_mut().iter_mut().enumerate() {
+            if index == buffer.index {
+                *missed = Some(Vec::new());
+            } else if let (Some(missed), Some(damage)) = (missed.as_mut(), buffer.damage.as_ref()) {
+                missed.extend_from_slice(damage);
+            } else {
+                *missed = None;
+            }
+        }
+
+        let swap_chain = self.ensure_swap_chain();
+        match (&buffer.damage, &buffer.repaint) {
+            // Dirty rectangles tell the compositor that the rest of the contents is the same as in the previously
+            // presented frame. This is only true if this buffer was up-to-date with it, which is not the case on the
+            // first present.
+            (Some(damage), Some(_)) => {
+                let mut dirty_rects: Vec<RECT> = damage
+                    .iter()
+                    .map(|rect| RECT {
+                        left: rect.min_x(),
+                        top: rect.min_y(),
+                        right: rect.max_x(),
+                        bottom: rect.max_y(),
+                    })
+                    .collect();
+                let params = DXGI_PRESENT_PARAMETERS {
+                    DirtyRectsCount: dirty_rects.len() as u32,
+                    pDirtyRects: dirty_rects.as_mut_ptr(),
+                    pScrollRect: ptr::null_mut(),
+                    pScrollOffset: ptr::null_mut(),
+                };
+                swap_chain
+                    .swap_chain
+                    .Present1(1, 0, &params)
+                    .ok()
+                    .expect("Present1 failed");
+            }
+            _ => {
+                swap_chain.swap_chain.Present(1, 0).ok().expect("Present failed");
+            }
+        }
         self.surface_acquired.set(false);
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Compositor clock & animations
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Frame interval assumed when the compositor doesn't report one.
+const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// See `crate::animation::frame_timing`.
+pub fn frame_timing() -> animation::FrameTiming {
+    let app = Application::instance();
+    let comp_device = app.backend.composition_device.get_ref().unwrap();
+    let now = Instant::now();
+    let mut stats = DCOMPOSITION_FRAME_STATISTICS::default();
+    let stats = unsafe { comp_device.GetFrameStatistics(&mut stats).map(|_| stats) };
+
+    match stats {
+        Ok(stats) if stats.timeFrequency > 0 => {
+            let ticks_to_duration =
+                |ticks: i64| Duration::from_secs_f64(ticks.max(0) as f64 / stats.timeFrequency as f64);
+            let frame_interval = if stats.currentCompositionRate.Numerator > 0 {
+                Duration::from_secs_f64(
+                    stats.currentCompositionRate.Denominator as f64 / stats.currentCompositionRate.Numerator as f64,
+                )
+            } else {
+                DEFAULT_FRAME_INTERVAL
+            };
+            animation::FrameTiming {
+                next_frame_time: now + ticks_to_duration(stats.nextEstimatedFrameTime - stats.currentTime),
+                frame_interval,
+            }
+        }
+        _ => animation::FrameTiming {
+            next_frame_time: now + DEFAULT_FRAME_INTERVAL,
+            frame_interval: DEFAULT_FRAME_INTERVAL,
+        },
+    }
+}
+
+/// Creates a DirectComposition animation function that interpolates linearly between keyframes.
+///
+/// Two keyframes at the same time make an instantaneous change of value. If `repeat` is specified, the last `repeat`
+/// of the animation is repeated indefinitely.
+fn create_animation(
+    keyframes: impl Iterator<Item = (Duration, f32)>,
+    repeat: Option<Duration>,
+) -> IDCompositionAnimation {
+    let app = Application::instance();
+    let comp_device = app.backend.composition_device.get_ref().unwrap();
+    let keyframes: Vec<_> = keyframes.collect();
+    assert!(!keyframes.is_empty(), "empty animation");
+
+    unsafe {
+        let anim = comp_device.CreateAnimation().expect("CreateAnimation failed");
+        for w in keyframes.windows(2) {
+            let (t0, v0) = w[0];
+            let (t1, v1) = w[1];
+            if t1 <= t0 {
+                // instantaneous change: the next segment starts at the new value
+                continue;
+            }
+            let slope = (v1 - v0) / (t1 - t0).as_secs_f32();
+            anim.AddCubic(t0.as_secs_f64(), v0, slope, 0.0, 0.0)
+                .expect("AddCubic failed");
+        }
+        let (t_end, v_end) = *keyframes.last().unwrap();
+        match repeat {
+            Some(repeat) => anim
+                .AddRepeat(t_end.as_secs_f64(), repeat.as_secs_f64())
+                .expect("AddRepeat failed"),
+            None => anim.End(t_end.as_secs_f64(), v_end).expect("End failed"),
+        }
+        anim
+    }
+}
+
 /// A layer in the compositor tree.
 #[derive(Clone)]
 pub struct Layer(pub(crate) Arc<LayerImpl>);
@@ -464,7 +670,16 @@ impl Layer {
     /// The returned `Surface` object must be dropped before `acquire_surface` is called again, otherwise
     /// the function will panic.
     pub fn acquire_surface(&self) -> Surface {
-        let buffer = self.0.acquire_surface();
+        let buffer = self.0.acquire_surface(None);
+        Surface {
+            layer: Arc::clone(&self.0),
+            buffer,
+        }
+    }
+
+    /// See `crate::animation::Layer::acquire_surface_with_damage`.
+    pub fn acquire_surface_with_damage(&self, damage: &[RectI]) -> Surface {
+        let buffer = self.0.acquire_surface(Some(damage.to_vec()));
         Surface {
             layer: Arc::clone(&self.0),
             buffer,
@@ -488,6 +703,84 @@ impl Layer {
         }
     }
 
+    /// See `crate::animation::Layer::set_clip`.
+    pub fn set_clip(&self, clip: Option<RectI>) {
+        let rect = match clip {
+            Some(clip) => D2D_RECT_F {
+                left: clip.min_x() as f32,
+                top: clip.min_y() as f32,
+                right: clip.max_x() as f32,
+                bottom: clip.max_y() as f32,
+            },
+            // an infinite clip rectangle removes the clip
+            None => D2D_RECT_F {
+                left: -f32::MAX,
+                top: -f32::MAX,
+                right: f32::MAX,
+                bottom: f32::MAX,
+            },
+        };
+        unsafe {
+            self.0.visual.SetClip2(&rect).expect("SetClip2 failed");
+        }
+    }
+
+    /// See `crate::animation::Layer::set_opacity`.
+    pub fn set_opacity(&self, opacity: f32) {
+        unsafe {
+            self.0.visual.SetOpacity2(opacity).expect("SetOpacity failed");
+        }
+    }
+
+    /// See `crate::animation::Layer::animate_transform`.
+    pub fn animate_transform(&self, keyframes: &[(Duration, Transform)]) {
+        let app = Application::instance();
+        let comp_device = app.backend.composition_device.get_ref().unwrap();
+        unsafe {
+            let matrix_transform: IDCompositionMatrixTransform = comp_device
+                .CreateMatrixTransform()
+                .expect("CreateMatrixTransform failed");
+            // one animation function for each element of the matrix
+            let elements: [(i32, i32, fn(&Transform) -> f64); 6] = [
+                (0, 0, |t| t.m11),
+                (0, 1, |t| t.m12),
+                (1, 0, |t| t.m21),
+                (1, 1, |t| t.m22),
+                (2, 0, |t| t.m31),
+                (2, 1, |t| t.m32),
+            ];
+            for &(row, column, element) in elements.iter() {
+                let anim = create_animation(
+                    keyframes.iter().map(|(t, transform)| (*t, element(transform) as f32)),
+                    None,
+                );
+                matrix_transform
+                    .SetMatrixElement(row, column, &anim)
+                    .expect("SetMatrixElement failed");
+            }
+            self.0
+                .visual
+                .SetTransform(&matrix_transform)
+                .expect("SetTransform failed");
+        }
+    }
+
+    /// See `crate::animation::Layer::animate_opacity`.
+    pub fn animate_opacity(&self, keyframes: &[(Duration, f32)]) {
+        let anim = create_animation(keyframes.iter().copied(), None);
+        unsafe {
+            self.0.visual.SetOpacity(&anim).expect("SetOpacity failed");
+        }
+    }
+
+    /// See `crate::animation::Layer::animate_opacity_repeat`.
+    pub fn animate_opacity_repeat(&self, keyframes: &[(Duration, f32)], repeat: Duration) {
+        let anim = create_animation(keyframes.iter().copied(), Some(repeat));
+        unsafe {
+            self.0.visual.SetOpacity(&anim).expect("SetOpacity failed");
+        }
+    }
+
     /// See `crate::animation::Layer::add_child`.
     pub fn add_child(&self, layer: &Layer) {
         unsafe {
@@ -520,6 +813,34 @@ impl Layer {
         self.0.size.get()
     }
 
+    /// See `crate::animation::Layer::present_statistics`.
+    pub fn present_statistics(&self) -> Option<animation::PresentStatistics> {
+        let swap_chain = self.0.swap_chain.borrow();
+        let swap_chain = &swap_chain.as_ref()?.swap_chain;
+        unsafe {
+            let mut present_count = 0;
+            swap_chain.GetLastPresentCount(&mut present_count).ok()?;
+            // fails with DXGI_ERROR_FRAME_STATISTICS_DISJOINT until a frame has been displayed
+            let mut stats = DXGI_FRAME_STATISTICS::default();
+            swap_chain.GetFrameStatistics(&mut stats).ok()?;
+            Some(animation::PresentStatistics {
+                present_count,
+                displayed_present_count: stats.PresentCount,
+                displayed_time: qpc_to_instant(stats.SyncQPCTime),
+            })
+        }
+    }
+
+    /// See `crate::animation::Layer::set_content_scaling`.
+    pub fn set_content_scaling(&self, content_scaling: animation::ContentScaling) {
+        self.0.content_scaling.set(content_scaling);
+    }
+
+    /// See `crate::animation::Layer::content_scaling`.
+    pub fn content_scaling(&self) -> animation::ContentScaling {
+        self.0.content_scaling.get()
+    }
+
     /// See `crate::animation::Layer::set_size`.
     pub fn set_size(&self, new_size: SizeI) {
         assert!(!self.0.surface_acquired.get());
@@ -528,6 +849,32 @@ impl Layer {
         let mut swap_chain = self.0.swap_chain.borrow_mut();
         if let Some(swap_chain) = &mut *swap_chain {
             swap_chain.set_size(new_size);
+            self.0.reset_buffer_damage();
+        }
+    }
+
+    /// See `crate::animation::Layer::release_contents`.
+    pub fn release_contents(&self) -> bool {
+        assert!(!self.0.surface_acquired.get());
+        let mut swap_chain = self.0.swap_chain.borrow_mut();
+        if swap_chain.is_none() {
+            return false;
+        }
+        if self.0.presentation_fence_value.get() > 1 {
+            // same as in `LayerImpl::drop`: the buffers may still be in use by submitted work
+            let app = Application::instance();
+            let _gpu_ctx = app.lock_gpu_context();
+            unsafe {
+                if let Err(err) = app.gpu_device().device.device_wait_idle() {
+                    tracing::error!("vkDeviceWaitIdle failed: {}", err);
+                }
+            }
+        }
+        unsafe {
+            self.0.visual.SetContent(None::<&IUnknown>).expect("SetContent failed");
         }
+        // the swap chain is created again on the next call to `acquire_surface`
+        swap_chain.take();
+        true
     }
 }
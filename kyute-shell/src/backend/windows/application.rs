@@ -1,14 +1,20 @@
-use crate::backend::windows::event::Win32Event;
+use crate::{
+    application::AccessibilityPreferences,
+    backend::windows::{event::Win32Event, util::ToWide},
+    input::ScrollSettings,
+};
 use parking_lot::Mutex;
 use std::{
     ffi::{c_void, OsString},
-    ptr,
-    time::Duration,
+    mem, ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
 };
 use threadbound::ThreadBound;
 use windows::{
-    core::Interface,
+    core::{Interface, PCWSTR},
     Win32::{
+        Foundation::{BOOL, ERROR_SUCCESS},
         Graphics::{
             Direct3D::D3D_FEATURE_LEVEL_12_0,
             Direct3D12::{
@@ -23,9 +29,19 @@ use windows::{
         },
         System::{
             Com::{CoCreateInstance, CoInitialize, CLSCTX_INPROC_SERVER},
+            Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD},
+            SystemInformation::GetTickCount,
             Threading::{CreateEventW, WaitForSingleObject},
         },
-        UI::Input::KeyboardAndMouse::GetDoubleClickTime,
+        UI::{
+            Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW},
+            Input::KeyboardAndMouse::GetDoubleClickTime,
+            WindowsAndMessaging::{
+                GetCaretBlinkTime, GetMessageTime, SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION,
+                SPI_GETHIGHCONTRAST, SPI_GETWHEELSCROLLCHARS, SPI_GETWHEELSCROLLLINES,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+            },
+        },
     },
 };
 
@@ -108,6 +124,14 @@ pub(crate) struct Application {
     pub(crate) composition_device: ThreadBound<IDCompositionDesktopDevice>,
 }
 
+/// Set when a window is notified that the system settings or the theme have changed.
+static SETTINGS_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Records that the system settings or the theme have changed (see `Application::take_settings_changed`).
+pub(crate) fn notify_settings_changed() {
+    SETTINGS_CHANGED.store(true, Ordering::Relaxed);
+}
+
 impl Application {
     pub(crate) fn new() -> Application {
         let _d3d12_debug = {
@@ -304,4 +328,111 @@ impl Application {
             Duration::from_millis(ms as u64)
         }
     }
+
+    pub(crate) fn caret_blink_time(&self) -> Option<Duration> {
+        unsafe {
+            // INFINITE if blinking is disabled, 0 on failure
+            match GetCaretBlinkTime() {
+                0 | u32::MAX => None,
+                ms => Some(Duration::from_millis(ms as u64)),
+            }
+        }
+    }
+
+    pub(crate) fn input_event_time(&self) -> Instant {
+        let now = Instant::now();
+        unsafe {
+            // both are in milliseconds since the system was started, and wrap around after ~49 days
+            let elapsed = GetTickCount().wrapping_sub(GetMessageTime() as u32);
+            now.checked_sub(Duration::from_millis(elapsed as u64)).unwrap_or(now)
+        }
+    }
+
+    pub(crate) fn take_settings_changed(&self) -> bool {
+        SETTINGS_CHANGED.swap(false, Ordering::Relaxed)
+    }
+
+    pub(crate) fn accessibility_preferences(&self) -> AccessibilityPreferences {
+        let mut prefs = AccessibilityPreferences::default();
+        unsafe {
+            // "Show animations in Windows"
+            let mut animations_enabled = BOOL(1);
+            if SystemParametersInfoW(
+                SPI_GETCLIENTAREAANIMATION,
+                0,
+                &mut animations_enabled as *mut _ as *mut c_void,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+            .as_bool()
+            {
+                prefs.reduced_motion = !animations_enabled.as_bool();
+            }
+
+            let mut high_contrast = HIGHCONTRASTW {
+                cbSize: mem::size_of::<HIGHCONTRASTW>() as u32,
+                ..Default::default()
+            };
+            if SystemParametersInfoW(
+                SPI_GETHIGHCONTRAST,
+                high_contrast.cbSize,
+                &mut high_contrast as *mut _ as *mut c_void,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+            .as_bool()
+            {
+                prefs.high_contrast = (high_contrast.dwFlags & HCF_HIGHCONTRASTON).0 != 0;
+            }
+
+            // "Make text bigger", stored as a percentage (100 to 225); the value is absent if never changed
+            let subkey = "Software\\Microsoft\\Accessibility".to_wide();
+            let value = "TextScaleFactor".to_wide();
+            let mut text_scale: u32 = 0;
+            let mut size = mem::size_of::<u32>() as u32;
+            if RegGetValueW(
+                HKEY_CURRENT_USER,
+                PCWSTR(subkey.as_ptr()),
+                PCWSTR(value.as_ptr()),
+                RRF_RT_REG_DWORD,
+                ptr::null_mut(),
+                &mut text_scale as *mut _ as *mut c_void,
+                &mut size,
+            ) == ERROR_SUCCESS
+                && text_scale >= 100
+            {
+                prefs.text_scale_factor = text_scale as f64 / 100.0;
+            }
+        }
+        prefs
+    }
+
+    pub(crate) fn scroll_settings(&self) -> ScrollSettings {
+        let mut settings = ScrollSettings::default();
+        unsafe {
+            let mut lines: u32 = 0;
+            if SystemParametersInfoW(
+                SPI_GETWHEELSCROLLLINES,
+                0,
+                &mut lines as *mut _ as *mut c_void,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+            .as_bool()
+            {
+                // WHEEL_PAGESCROLL if set to "one screen at a time"
+                settings.lines_per_notch = if lines == u32::MAX { None } else { Some(lines as f64) };
+            }
+
+            let mut chars: u32 = 0;
+            if SystemParametersInfoW(
+                SPI_GETWHEELSCROLLCHARS,
+                0,
+                &mut chars as *mut _ as *mut c_void,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+            .as_bool()
+            {
+                settings.chars_per_notch = chars as f64;
+            }
+        }
+        settings
+    }
 }
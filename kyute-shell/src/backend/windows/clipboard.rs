@@ -1 +1,544 @@
-struct DropSource {}
+//! Clipboard access and drag and drop sources.
+use crate::{
+    backend::windows::util::{create_premultiplied_bitmap, ToWide},
+    clipboard::{self, DragImage, DropEffect, DropEffects, TypedData, BMP_FILE_HEADER_SIZE},
+    Result,
+};
+use parking_lot::Mutex;
+use std::{convert::TryInto, ptr, slice};
+use windows::{
+    core::{implement, HRESULT, PCWSTR},
+    Win32::{
+        Foundation::{
+            BOOL, DRAGDROP_S_CANCEL, DRAGDROP_S_DROP, DRAGDROP_S_USEDEFAULTCURSORS, DV_E_FORMATETC, E_NOTIMPL, HANDLE,
+            HWND, OLE_E_ADVISENOTSUPPORTED, POINT, SIZE, S_OK,
+        },
+        Graphics::Gdi::DeleteObject,
+        System::{
+            Com::{
+                CoCreateInstance, IAdviseSink, IDataObject, IDataObject_Impl, IEnumFORMATETC, IEnumSTATDATA,
+                CLSCTX_INPROC_SERVER, DVASPECT_CONTENT, FORMATETC, STGMEDIUM, STGMEDIUM_0, TYMED_HGLOBAL,
+            },
+            DataExchange::{
+                CloseClipboard, EmptyClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard,
+                RegisterClipboardFormatW, SetClipboardData,
+            },
+            Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE},
+            Ole::{
+                DoDragDrop, IDropSource, IDropSource_Impl, OleInitialize, ReleaseStgMedium, DROPEFFECT,
+                DROPEFFECT_COPY, DROPEFFECT_LINK, DROPEFFECT_MOVE, DROPEFFECT_NONE,
+            },
+            SystemServices::{MK_LBUTTON, MK_RBUTTON, MODIFIERKEYS_FLAGS},
+        },
+        UI::Shell::{CLSID_DragDropHelper, IDragSourceHelper, SHCreateStdEnumFmtEtc, SHDRAGIMAGE},
+    },
+};
+
+/// Standard clipboard format for UTF-16 text.
+const CF_UNICODETEXT: u32 = 13;
+/// Standard clipboard format for device-independent bitmaps: BMP images without the file header.
+const CF_DIB: u32 = 8;
+/// Standard clipboard format for lists of files (a `DROPFILES` structure followed by the paths).
+const CF_HDROP: u32 = 15;
+/// Size of the `DROPFILES` structure.
+const DROPFILES_SIZE: usize = 20;
+
+/// Keeps the clipboard open, and closes it when dropped.
+struct OpenedClipboard;
+
+impl OpenedClipboard {
+    fn open() -> Result<OpenedClipboard> {
+        unsafe {
+            OpenClipboard(HWND(0)).ok()?;
+        }
+        Ok(OpenedClipboard)
+    }
+}
+
+impl Drop for OpenedClipboard {
+    fn drop(&mut self) {
+        unsafe {
+            CloseClipboard();
+        }
+    }
+}
+
+pub(crate) fn set_text(text: &str) -> Result<()> {
+    let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    let _clipboard = OpenedClipboard::open()?;
+    unsafe {
+        EmptyClipboard().ok()?;
+        let hmem = GlobalAlloc(GMEM_MOVEABLE, wide.len() * 2);
+        if hmem == 0 {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        let dst = GlobalLock(hmem) as *mut u16;
+        if dst.is_null() {
+            GlobalFree(hmem);
+            return Err(windows::core::Error::from_win32().into());
+        }
+        ptr::copy_nonoverlapping(wide.as_ptr(), dst, wide.len());
+        GlobalUnlock(hmem);
+        // on success, the memory is owned by the system
+        if let Err(err) = SetClipboardData(CF_UNICODETEXT, HANDLE(hmem)) {
+            GlobalFree(hmem);
+            return Err(err.into());
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn text() -> Option<String> {
+    unsafe {
+        if !IsClipboardFormatAvailable(CF_UNICODETEXT).as_bool() {
+            return None;
+        }
+        let _clipboard = OpenedClipboard::open().ok()?;
+        let handle = GetClipboardData(CF_UNICODETEXT).ok()?;
+        let src = GlobalLock(handle.0) as *const u16;
+        if src.is_null() {
+            return None;
+        }
+        let mut len = 0;
+        while *src.add(len) != 0 {
+            len += 1;
+        }
+        let text = String::from_utf16_lossy(slice::from_raw_parts(src, len));
+        GlobalUnlock(handle.0);
+        Some(text)
+    }
+}
+
+/// Returns the clipboard format corresponding to a data type.
+fn clipboard_format(type_id: &str) -> u16 {
+    let name = match type_id {
+        clipboard::TEXT => return CF_UNICODETEXT as u16,
+        clipboard::BMP => return CF_DIB as u16,
+        clipboard::FILE_LIST => return CF_HDROP as u16,
+        // CF_HTML
+        clipboard::HTML => "HTML Format",
+        clipboard::PNG => "PNG",
+        _ => type_id,
+    };
+    let name = name.to_wide();
+    unsafe { RegisterClipboardFormatW(PCWSTR(name.as_ptr())) as u16 }
+}
+
+pub(crate) fn has_data(type_id: &str) -> bool {
+    unsafe { IsClipboardFormatAvailable(clipboard_format(type_id) as u32).as_bool() }
+}
+
+pub(crate) fn data(type_id: &str) -> Option<Vec<u8>> {
+    if type_id == "text/plain" {
+        return text().map(String::into_bytes);
+    }
+    let format = clipboard_format(type_id) as u32;
+    unsafe {
+        if !IsClipboardFormatAvailable(format).as_bool() {
+            return None;
+        }
+        let _clipboard = OpenedClipboard::open().ok()?;
+        let handle = GetClipboardData(format).ok()?;
+        from_clipboard_bytes(type_id, global_bytes(handle.0)?)
+    }
+}
+
+pub(crate) fn set_data(items: &[TypedData]) -> Result<()> {
+    let _clipboard = OpenedClipboard::open()?;
+    unsafe {
+        EmptyClipboard().ok()?;
+        for item in items {
+            let hmem = global_alloc_bytes(&clipboard_bytes(item)).ok_or_else(windows::core::Error::from_win32)?;
+            // on success, the memory is owned by the system
+            if let Err(err) = SetClipboardData(clipboard_format(item.type_id) as u32, HANDLE(hmem)) {
+                GlobalFree(hmem);
+                return Err(err.into());
+            }
+        }
+    }
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Format conversions
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Wraps an HTML fragment in the CF_HTML format: a header with the offsets of the document and of the fragment,
+/// then the document.
+fn to_cf_html(fragment: &str) -> Vec<u8> {
+    const HEADER_LEN: usize = 105;
+    const PREFIX: &str = "<html><body>\r\n<!--StartFragment-->";
+    const SUFFIX: &str = "<!--EndFragment-->\r\n</body></html>";
+    let start_fragment = HEADER_LEN + PREFIX.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + SUFFIX.len();
+    let header = format!(
+        "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n",
+        HEADER_LEN, end_html, start_fragment, end_fragment
+    );
+    debug_assert_eq!(header.len(), HEADER_LEN);
+    format!("{}{}{}{}", header, PREFIX, fragment, SUFFIX).into_bytes()
+}
+
+/// Returns the fragment in CF_HTML data (between the `StartFragment` and `EndFragment` markers), or the
+/// whole string if there are no markers.
+fn cf_html_fragment(html: &str) -> &str {
+    const START: &str = "<!--StartFragment-->";
+    const END: &str = "<!--EndFragment-->";
+    match (html.find(START), html.rfind(END)) {
+        (Some(start), Some(end)) if start + START.len() <= end => &html[start + START.len()..end],
+        _ => html,
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// Adds the file header to a device-independent bitmap (CF_DIB) to make a BMP image.
+fn dib_to_bmp(dib: &[u8]) -> Option<Vec<u8>> {
+    const BI_BITFIELDS: u32 = 3;
+    let header_size = read_u32(dib, 0)? as usize;
+    let bit_count = read_u16(dib, 14)?;
+    let compression = read_u32(dib, 16)?;
+    let colors_used = read_u32(dib, 32)? as usize;
+    // the color table (or the color masks) are between the header and the pixels
+    let mut color_table_size = if bit_count <= 8 {
+        4 * if colors_used != 0 { colors_used } else { 1 << bit_count }
+    } else {
+        4 * colors_used
+    };
+    if compression == BI_BITFIELDS && header_size == 40 {
+        color_table_size += 12;
+    }
+    let offset = BMP_FILE_HEADER_SIZE + header_size + color_table_size;
+    let size = BMP_FILE_HEADER_SIZE + dib.len();
+    let mut bmp = Vec::with_capacity(size);
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(size as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&(offset as u32).to_le_bytes());
+    bmp.extend_from_slice(dib);
+    Some(bmp)
+}
+
+/// Reads the paths of a `DROPFILES` structure (CF_HDROP), and returns them separated by null characters.
+fn from_drop_files(data: &[u8]) -> Option<Vec<u8>> {
+    let offset = read_u32(data, 0)? as usize;
+    let wide = read_u32(data, 16)? != 0;
+    let list = data.get(offset..)?;
+    // the paths are null-terminated, and the list ends with an empty path
+    let paths = if wide {
+        let list: Vec<u16> = list.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        String::from_utf16_lossy(&list)
+    } else {
+        String::from_utf8_lossy(list).into_owned()
+    };
+    let paths: Vec<&str> = paths.split('\0').take_while(|path| !path.is_empty()).collect();
+    Some(paths.join("\0").into_bytes())
+}
+
+/// Creates a `DROPFILES` structure (CF_HDROP) from paths separated by null characters.
+fn to_drop_files(paths: &[u8]) -> Vec<u8> {
+    let paths = String::from_utf8_lossy(paths);
+    let mut data = Vec::new();
+    data.extend_from_slice(&(DROPFILES_SIZE as u32).to_le_bytes());
+    // pt, fNC
+    data.extend_from_slice(&[0; 12]);
+    // fWide
+    data.extend_from_slice(&1u32.to_le_bytes());
+    for path in paths.split('\0').filter(|path| !path.is_empty()) {
+        data.extend(path.encode_utf16().chain(std::iter::once(0)).flat_map(u16::to_le_bytes));
+    }
+    data.extend_from_slice(&[0; 2]);
+    data
+}
+
+/// Converts data in a clipboard format to the representation of its type (see `TypedData`).
+fn from_clipboard_bytes(type_id: &str, mut data: Vec<u8>) -> Option<Vec<u8>> {
+    match type_id {
+        clipboard::HTML => {
+            // CF_HTML data is null-terminated, and the size of the block may be larger
+            if let Some(end) = data.iter().position(|&b| b == 0) {
+                data.truncate(end);
+            }
+            let html = String::from_utf8(data).ok()?;
+            Some(cf_html_fragment(&html).as_bytes().to_vec())
+        }
+        clipboard::BMP => dib_to_bmp(&data),
+        clipboard::FILE_LIST => from_drop_files(&data),
+        _ => Some(data),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Drag and drop source
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Copies bytes into a new movable global memory block.
+unsafe fn global_alloc_bytes(data: &[u8]) -> Option<isize> {
+    let hmem = GlobalAlloc(GMEM_MOVEABLE, data.len().max(1));
+    if hmem == 0 {
+        return None;
+    }
+    let dst = GlobalLock(hmem) as *mut u8;
+    if dst.is_null() {
+        GlobalFree(hmem);
+        return None;
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), dst, data.len());
+    GlobalUnlock(hmem);
+    Some(hmem)
+}
+
+/// Returns the contents of a global memory block.
+unsafe fn global_bytes(hmem: isize) -> Option<Vec<u8>> {
+    let src = GlobalLock(hmem) as *const u8;
+    if src.is_null() {
+        return None;
+    }
+    let bytes = slice::from_raw_parts(src, GlobalSize(hmem)).to_vec();
+    GlobalUnlock(hmem);
+    Some(bytes)
+}
+
+/// Converts data to the representation expected in the clipboard format.
+fn clipboard_bytes(data: &TypedData) -> Vec<u8> {
+    match data.type_id {
+        clipboard::TEXT => String::from_utf8_lossy(&data.data)
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .flat_map(u16::to_ne_bytes)
+            .collect(),
+        clipboard::HTML => to_cf_html(&String::from_utf8_lossy(&data.data)),
+        clipboard::BMP => data.data.get(BMP_FILE_HEADER_SIZE..).unwrap_or_default().to_vec(),
+        clipboard::FILE_LIST => to_drop_files(&data.data),
+        _ => data.data.clone(),
+    }
+}
+
+/// Data object holding the payload of a drag operation.
+///
+/// Also stores the data set by the system drag image helper (`SetData`).
+#[implement(IDataObject)]
+struct DataObject {
+    /// Clipboard format and contents.
+    formats: Mutex<Vec<(FORMATETC, Vec<u8>)>>,
+}
+
+impl DataObject {
+    fn new(data: &[TypedData]) -> DataObject {
+        let formats = data
+            .iter()
+            .map(|data| {
+                let format = FORMATETC {
+                    cfFormat: clipboard_format(data.type_id),
+                    ptd: ptr::null_mut(),
+                    dwAspect: DVASPECT_CONTENT.0 as u32,
+                    lindex: -1,
+                    tymed: TYMED_HGLOBAL.0 as u32,
+                };
+                (format, clipboard_bytes(data))
+            })
+            .collect();
+        DataObject {
+            formats: Mutex::new(formats),
+        }
+    }
+
+    fn find(&self, format: &FORMATETC) -> Option<Vec<u8>> {
+        if format.tymed & TYMED_HGLOBAL.0 as u32 == 0 {
+            return None;
+        }
+        self.formats
+            .lock()
+            .iter()
+            .find(|(f, _)| f.cfFormat == format.cfFormat && f.dwAspect == format.dwAspect)
+            .map(|(_, data)| data.clone())
+    }
+}
+
+#[allow(non_snake_case)]
+impl IDataObject_Impl for DataObject {
+    fn GetData(&self, pformatetcin: *const FORMATETC) -> windows::core::Result<STGMEDIUM> {
+        unsafe {
+            let data = self
+                .find(&*pformatetcin)
+                .ok_or_else(|| windows::core::Error::from(DV_E_FORMATETC))?;
+            let hmem = global_alloc_bytes(&data).ok_or_else(windows::core::Error::from_win32)?;
+            Ok(STGMEDIUM {
+                tymed: TYMED_HGLOBAL.0 as u32,
+                Anonymous: STGMEDIUM_0 { hGlobal: hmem },
+                pUnkForRelease: None,
+            })
+        }
+    }
+
+    fn GetDataHere(&self, _pformatetc: *const FORMATETC, _pmedium: *mut STGMEDIUM) -> windows::core::Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn QueryGetData(&self, pformatetc: *const FORMATETC) -> HRESULT {
+        if unsafe { self.find(&*pformatetc) }.is_some() {
+            S_OK
+        } else {
+            DV_E_FORMATETC
+        }
+    }
+
+    fn GetCanonicalFormatEtc(&self, _pformatectin: *const FORMATETC, pformatetcout: *mut FORMATETC) -> HRESULT {
+        unsafe {
+            (*pformatetcout).ptd = ptr::null_mut();
+        }
+        E_NOTIMPL
+    }
+
+    fn SetData(
+        &self,
+        pformatetc: *const FORMATETC,
+        pmedium: *const STGMEDIUM,
+        frelease: BOOL,
+    ) -> windows::core::Result<()> {
+        unsafe {
+            let format = *pformatetc;
+            if (*pmedium).tymed != TYMED_HGLOBAL.0 as u32 {
+                return Err(E_NOTIMPL.into());
+            }
+            let data = global_bytes((*pmedium).Anonymous.hGlobal);
+            if frelease.as_bool() {
+                ReleaseStgMedium(pmedium as *mut STGMEDIUM);
+            }
+            let data = data.ok_or_else(windows::core::Error::from_win32)?;
+            let mut formats = self.formats.lock();
+            formats.retain(|(f, _)| f.cfFormat != format.cfFormat);
+            formats.push((format, data));
+            Ok(())
+        }
+    }
+
+    fn EnumFormatEtc(&self, dwdirection: u32) -> windows::core::Result<IEnumFORMATETC> {
+        // DATADIR_GET
+        if dwdirection != 1 {
+            return Err(E_NOTIMPL.into());
+        }
+        let formats: Vec<FORMATETC> = self.formats.lock().iter().map(|(f, _)| *f).collect();
+        unsafe { SHCreateStdEnumFmtEtc(&formats) }
+    }
+
+    fn DAdvise(
+        &self,
+        _pformatetc: *const FORMATETC,
+        _advf: u32,
+        _padvsink: &Option<IAdviseSink>,
+    ) -> windows::core::Result<u32> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+
+    fn DUnadvise(&self, _dwconnection: u32) -> windows::core::Result<()> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+
+    fn EnumDAdvise(&self) -> windows::core::Result<IEnumSTATDATA> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+}
+
+#[implement(IDropSource)]
+struct DropSource;
+
+#[allow(non_snake_case)]
+impl IDropSource_Impl for DropSource {
+    fn QueryContinueDrag(&self, fescapepressed: BOOL, grfkeystate: MODIFIERKEYS_FLAGS) -> HRESULT {
+        if fescapepressed.as_bool() {
+            DRAGDROP_S_CANCEL
+        } else if grfkeystate & (MK_LBUTTON | MK_RBUTTON) == MODIFIERKEYS_FLAGS(0) {
+            // the button was released
+            DRAGDROP_S_DROP
+        } else {
+            S_OK
+        }
+    }
+
+    fn GiveFeedback(&self, _dweffect: DROPEFFECT) -> HRESULT {
+        DRAGDROP_S_USEDEFAULTCURSORS
+    }
+}
+
+/// Sets the drag image of a data object with the system drag image helper.
+unsafe fn set_drag_image(data_object: &IDataObject, image: &DragImage) -> Result<()> {
+    let helper: IDragSourceHelper = CoCreateInstance(&CLSID_DragDropHelper, None, CLSCTX_INPROC_SERVER)?;
+
+    let bitmap = create_premultiplied_bitmap(image.width, image.height, &image.pixels)?;
+
+    let drag_image = SHDRAGIMAGE {
+        sizeDragImage: SIZE {
+            cx: image.width as i32,
+            cy: image.height as i32,
+        },
+        ptOffset: POINT {
+            x: image.hotspot_x,
+            y: image.hotspot_y,
+        },
+        hbmpDragImage: bitmap,
+        // CLR_NONE
+        crColorKey: 0xFFFFFFFF,
+    };
+    if let Err(err) = helper.InitializeFromBitmap(&drag_image, data_object) {
+        // the helper owns the bitmap only on success
+        DeleteObject(bitmap);
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+pub(crate) fn start_drag(data: &[TypedData], allowed_effects: DropEffects, image: Option<&DragImage>) -> DropEffect {
+    // OLE is usually already initialized on the UI thread (winit does it for drop targets), in which case
+    // this only increments the reference count.
+    if let Err(err) = unsafe { OleInitialize(ptr::null_mut()) } {
+        warn!("OleInitialize failed: {}", err);
+        return DropEffect::None;
+    }
+    let data_object: IDataObject = DataObject::new(data).into();
+    let drop_source: IDropSource = DropSource.into();
+
+    if let Some(image) = image {
+        if let Err(err) = unsafe { set_drag_image(&data_object, image) } {
+            warn!("failed to set drag image: {}", err);
+        }
+    }
+
+    let mut ok_effects = DROPEFFECT_NONE;
+    if allowed_effects.contains(DropEffects::COPY) {
+        ok_effects |= DROPEFFECT_COPY;
+    }
+    if allowed_effects.contains(DropEffects::MOVE) {
+        ok_effects |= DROPEFFECT_MOVE;
+    }
+    if allowed_effects.contains(DropEffects::LINK) {
+        ok_effects |= DROPEFFECT_LINK;
+    }
+
+    let mut effect = DROPEFFECT_NONE;
+    let result = unsafe { DoDragDrop(&data_object, &drop_source, ok_effects, &mut effect) };
+    unsafe {
+        OleUninitialize();
+    }
+
+    if result != DRAGDROP_S_DROP {
+        return DropEffect::None;
+    }
+    // if several effects are set, move takes precedence
+    if effect & DROPEFFECT_MOVE != DROPEFFECT_NONE {
+        DropEffect::Move
+    } else if effect & DROPEFFECT_COPY != DROPEFFECT_NONE {
+        DropEffect::Copy
+    } else if effect & DROPEFFECT_LINK != DROPEFFECT_NONE {
+        DropEffect::Link
+    } else {
+        DropEffect::None
+    }
+}
@@ -0,0 +1,269 @@
+//! Standard dialogs (color chooser, font chooser, file dialogs).
+use crate::{
+    backend::{windows::util::ToWide, Window},
+    dialogs::{DialogResult, FileDialog, FileDialogKind, FontSelection},
+    text::{FontFamily, FontStyle, FontWeight},
+};
+use kyute_common::Color;
+use parking_lot::Mutex;
+use std::{ffi::OsString, mem, os::windows::ffi::OsStringExt, path::PathBuf, ptr};
+use windows::{
+    core::{Interface, HRESULT, PCWSTR, PWSTR},
+    Win32::{
+        Foundation::HWND,
+        Graphics::Gdi::{GetDC, GetDeviceCaps, ReleaseDC, LOGFONTW, LOGPIXELSY},
+        System::Com::{
+            CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize, CLSCTX_INPROC_SERVER,
+            COINIT_APARTMENTTHREADED, COINIT_DISABLE_OLE1DDE,
+        },
+        UI::{
+            Controls::Dialogs::{
+                ChooseColorW, ChooseFontW, CC_FULLOPEN, CC_RGBINIT, CF_INITTOLOGFONTSTRUCT, CF_NOVERTFONTS,
+                CF_SCREENFONTS, CHOOSECOLORW, CHOOSEFONTW,
+            },
+            Shell::{
+                Common::COMDLG_FILTERSPEC, FileOpenDialog, FileSaveDialog, IFileDialog, IFileOpenDialog, IShellItem,
+                SHCreateItemFromParsingName, FOS_ALLOWMULTISELECT, FOS_FORCEFILESYSTEM, FOS_OVERWRITEPROMPT,
+                FOS_PICKFOLDERS, SIGDN_FILESYSPATH,
+            },
+        },
+    },
+};
+
+/// `HRESULT_FROM_WIN32(ERROR_CANCELLED)`, returned by `IFileDialog::Show` when the user cancels the dialog.
+const HRESULT_CANCELLED: HRESULT = HRESULT(0x800704C7u32 as i32);
+
+/// Custom colors of the color dialog, preserved between invocations.
+static CUSTOM_COLORS: Mutex<[u32; 16]> = parking_lot::const_mutex([0x00FFFFFF; 16]);
+
+fn color_to_colorref(color: Color) -> u32 {
+    let (r, g, b, _) = color.to_rgba();
+    let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u32;
+    to_u8(r) | to_u8(g) << 8 | to_u8(b) << 16
+}
+
+fn colorref_to_color(colorref: u32) -> Color {
+    Color::from_rgba_u8(
+        (colorref & 0xFF) as u8,
+        (colorref >> 8 & 0xFF) as u8,
+        (colorref >> 16 & 0xFF) as u8,
+        255,
+    )
+}
+
+/// Returns the vertical DPI of the screen, used to convert between points and LOGFONT heights.
+fn screen_dpi_y() -> i32 {
+    unsafe {
+        let hdc = GetDC(None);
+        let dpi = GetDeviceCaps(hdc, LOGPIXELSY);
+        ReleaseDC(None, hdc);
+        dpi
+    }
+}
+
+pub(crate) fn choose_color(owner: Option<&Window>, initial_color: Option<Color>) -> DialogResult<Color> {
+    let owner = owner.map(Window::hwnd).unwrap_or_default();
+    DialogResult::spawn(move || {
+        let mut custom_colors = *CUSTOM_COLORS.lock();
+        let mut cc = CHOOSECOLORW {
+            lStructSize: mem::size_of::<CHOOSECOLORW>() as u32,
+            hwndOwner: owner,
+            lpCustColors: custom_colors.as_mut_ptr(),
+            Flags: CC_FULLOPEN,
+            ..Default::default()
+        };
+        if let Some(color) = initial_color {
+            cc.rgbResult = color_to_colorref(color);
+            cc.Flags |= CC_RGBINIT;
+        }
+
+        let ok = unsafe {
+            // SAFETY: `cc` is properly initialized and `custom_colors` outlives the call
+            ChooseColorW(&mut cc).as_bool()
+        };
+        *CUSTOM_COLORS.lock() = custom_colors;
+        if ok {
+            Some(colorref_to_color(cc.rgbResult))
+        } else {
+            None
+        }
+    })
+}
+
+pub(crate) fn choose_font(owner: Option<&Window>, initial_font: Option<FontSelection>) -> DialogResult<FontSelection> {
+    let owner = owner.map(Window::hwnd).unwrap_or_default();
+    DialogResult::spawn(move || {
+        let dpi = screen_dpi_y();
+        let mut log_font = LOGFONTW::default();
+        let mut cf = CHOOSEFONTW {
+            lStructSize: mem::size_of::<CHOOSEFONTW>() as u32,
+            hwndOwner: owner,
+            Flags: CF_SCREENFONTS | CF_NOVERTFONTS,
+            ..Default::default()
+        };
+
+        if let Some(font) = initial_font {
+            // negative height: character height, as opposed to cell height
+            log_font.lfHeight = -(font.size * dpi as f64 / 72.0).round() as i32;
+            log_font.lfWeight = font.weight.0 as i32;
+            log_font.lfItalic = (font.style != FontStyle::Normal) as u8;
+            // truncate the name to fit, keeping the null terminator
+            for (dst, src) in log_font.lfFaceName[..31]
+                .iter_mut()
+                .zip(font.family.name().encode_utf16())
+            {
+                *dst = src;
+            }
+            cf.Flags |= CF_INITTOLOGFONTSTRUCT;
+        }
+        cf.lpLogFont = &mut log_font;
+
+        let ok = unsafe {
+            // SAFETY: `cf` is properly initialized and `log_font` outlives the call
+            ChooseFontW(&mut cf).as_bool()
+        };
+        if !ok {
+            return None;
+        }
+
+        let name_len = log_font.lfFaceName.iter().position(|&c| c == 0).unwrap_or(32);
+        let family = String::from_utf16_lossy(&log_font.lfFaceName[..name_len]);
+        Some(FontSelection {
+            family: FontFamily::new(family),
+            // iPointSize is in tenths of a point
+            size: cf.iPointSize as f64 / 10.0,
+            weight: if log_font.lfWeight == 0 {
+                FontWeight::NORMAL
+            } else {
+                FontWeight(log_font.lfWeight as u16)
+            },
+            style: if log_font.lfItalic != 0 {
+                FontStyle::Italic
+            } else {
+                FontStyle::Normal
+            },
+        })
+    })
+}
+
+/// Returns the file system path of a shell item.
+fn shell_item_path(item: &IShellItem) -> windows::core::Result<PathBuf> {
+    unsafe {
+        let name: PWSTR = item.GetDisplayName(SIGDN_FILESYSPATH)?;
+        let len = (0..).take_while(|&i| *name.0.add(i) != 0).count();
+        let path = OsString::from_wide(std::slice::from_raw_parts(name.0, len));
+        CoTaskMemFree(name.0 as *const _);
+        Ok(path.into())
+    }
+}
+
+/// Returns the filter spec of a list of extensions (`*.png;*.jpg`).
+fn filter_spec(extensions: &[String]) -> String {
+    extensions
+        .iter()
+        .map(|ext| {
+            if ext == "*" {
+                "*.*".to_string()
+            } else {
+                format!("*.{}", ext)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Shows a file dialog and returns the selected paths, or `None` if the dialog was cancelled.
+///
+/// Must be called on a thread where COM is initialized.
+unsafe fn run_file_dialog(owner: HWND, options: &FileDialog) -> windows::core::Result<Option<Vec<PathBuf>>> {
+    let dialog: IFileDialog = match options.kind {
+        FileDialogKind::Open | FileDialogKind::PickFolder => {
+            CoCreateInstance::<_, IFileOpenDialog>(&FileOpenDialog, None, CLSCTX_INPROC_SERVER)?.cast()?
+        }
+        FileDialogKind::Save => CoCreateInstance(&FileSaveDialog, None, CLSCTX_INPROC_SERVER)?,
+    };
+
+    let mut flags = dialog.GetOptions()? | FOS_FORCEFILESYSTEM;
+    match options.kind {
+        FileDialogKind::Open if options.multi_select => flags |= FOS_ALLOWMULTISELECT,
+        FileDialogKind::PickFolder if options.multi_select => flags |= FOS_PICKFOLDERS | FOS_ALLOWMULTISELECT,
+        FileDialogKind::PickFolder => flags |= FOS_PICKFOLDERS,
+        FileDialogKind::Save => flags |= FOS_OVERWRITEPROMPT,
+        _ => {}
+    }
+    dialog.SetOptions(flags)?;
+
+    if let Some(ref title) = options.title {
+        let title = title.to_wide();
+        dialog.SetTitle(PCWSTR(title.as_ptr()))?;
+    }
+    if let Some(ref file_name) = options.file_name {
+        let file_name = file_name.to_wide();
+        dialog.SetFileName(PCWSTR(file_name.as_ptr()))?;
+    }
+    if let Some(ref directory) = options.directory {
+        let directory = directory.to_wide();
+        match SHCreateItemFromParsingName::<_, _, IShellItem>(PCWSTR(directory.as_ptr()), None) {
+            Ok(folder) => dialog.SetFolder(&folder)?,
+            Err(err) => warn!("invalid file dialog directory {:?}: {}", options.directory, err),
+        }
+    }
+
+    if options.kind != FileDialogKind::PickFolder && !options.filters.is_empty() {
+        // the strings must outlive the call to SetFileTypes
+        let names: Vec<_> = options.filters.iter().map(|f| f.name.to_wide()).collect();
+        let specs: Vec<_> = options
+            .filters
+            .iter()
+            .map(|f| filter_spec(&f.extensions).to_wide())
+            .collect();
+        let filter_specs: Vec<_> = names
+            .iter()
+            .zip(specs.iter())
+            .map(|(name, spec)| COMDLG_FILTERSPEC {
+                pszName: PCWSTR(name.as_ptr()),
+                pszSpec: PCWSTR(spec.as_ptr()),
+            })
+            .collect();
+        dialog.SetFileTypes(&filter_specs)?;
+        if let Some(ext) = options.filters[0].extensions.first().filter(|ext| *ext != "*") {
+            let ext = ext.to_wide();
+            dialog.SetDefaultExtension(PCWSTR(ext.as_ptr()))?;
+        }
+    }
+
+    match dialog.Show(owner) {
+        Ok(()) => {}
+        Err(err) if err.code() == HRESULT_CANCELLED => return Ok(None),
+        Err(err) => return Err(err),
+    }
+
+    let paths = if options.kind != FileDialogKind::Save && options.multi_select {
+        let results = dialog.cast::<IFileOpenDialog>()?.GetResults()?;
+        let mut paths = Vec::new();
+        for i in 0..results.GetCount()? {
+            paths.push(shell_item_path(&results.GetItemAt(i)?)?);
+        }
+        paths
+    } else {
+        vec![shell_item_path(&dialog.GetResult()?)?]
+    };
+    Ok(Some(paths))
+}
+
+pub(crate) fn show_file_dialog(owner: Option<&Window>, options: FileDialog) -> DialogResult<Vec<PathBuf>> {
+    let owner = owner.map(Window::hwnd).unwrap_or_default();
+    DialogResult::spawn(move || unsafe {
+        // the dialog runs on its own thread, which needs its own COM apartment
+        if let Err(err) = CoInitializeEx(ptr::null(), COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE) {
+            warn!("CoInitializeEx failed: {}", err);
+            return None;
+        }
+        let result = run_file_dialog(owner, &options);
+        CoUninitialize();
+        result.unwrap_or_else(|err| {
+            warn!("file dialog failed: {}", err);
+            None
+        })
+    })
+}
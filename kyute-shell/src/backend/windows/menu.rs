@@ -1,16 +1,50 @@
-use crate::{backend::windows::util::ToWide, Shortcut};
+use crate::{
+    backend::windows::util::{create_premultiplied_bitmap, ToWide},
+    menu::{MenuIcon, MenuItemDraw},
+    Shortcut,
+};
 use std::mem;
 use windows::{
     core::PCWSTR,
-    Win32::UI::WindowsAndMessaging::{
-        AppendMenuW, CreateMenu, CreatePopupMenu, DestroyMenu, HMENU, MF_CHECKED, MF_DISABLED, MF_POPUP, MF_SEPARATOR,
-        MF_STRING,
+    Win32::{
+        Graphics::Gdi::{DeleteObject, HBITMAP},
+        UI::WindowsAndMessaging::{
+            AppendMenuW, CreateMenu, CreatePopupMenu, DestroyMenu, SetMenuItemInfoW, HMENU, MENUITEMINFOW, MF_CHECKED,
+            MF_DISABLED, MF_OWNERDRAW, MF_POPUP, MF_SEPARATOR, MF_STRING, MIIM_BITMAP, MIIM_DATA,
+        },
     },
 };
 
+/// Resources referenced by the items of a menu (icons, custom-drawn items), which must outlive the menu.
+#[derive(Default)]
+pub(crate) struct MenuResources {
+    bitmaps: Vec<HBITMAP>,
+    /// Boxed so that the pointers passed as item data to the menu stay valid.
+    custom_items: Vec<Box<MenuItemDraw>>,
+}
+
+impl MenuResources {
+    fn append(&mut self, mut other: MenuResources) {
+        self.bitmaps.append(&mut other.bitmaps);
+        self.custom_items.append(&mut other.custom_items);
+    }
+}
+
+impl Drop for MenuResources {
+    fn drop(&mut self) {
+        for &bitmap in self.bitmaps.iter() {
+            unsafe {
+                // SAFETY: the bitmap is valid and not selected in a DC
+                DeleteObject(bitmap);
+            }
+        }
+    }
+}
+
 pub struct Menu {
     hmenu: HMENU,
     accels: Vec<(usize, Shortcut)>,
+    resources: MenuResources,
 }
 
 impl Drop for Menu {
@@ -29,7 +63,11 @@ impl Menu {
             // SAFETY: no particular requirements
             CreateMenu().unwrap()
         };
-        Menu { hmenu, accels: vec![] }
+        Menu {
+            hmenu,
+            accels: vec![],
+            resources: MenuResources::default(),
+        }
     }
 
     /// Creates a new menu.
@@ -38,13 +76,19 @@ impl Menu {
             // SAFETY: no particular requirements
             CreatePopupMenu().unwrap()
         };
-        Menu { hmenu, accels: vec![] }
+        Menu {
+            hmenu,
+            accels: vec![],
+            resources: MenuResources::default(),
+        }
     }
 
-    pub(crate) fn into_hmenu(self) -> HMENU {
+    /// Releases ownership of the menu handle. The returned resources must be kept alive as long as the menu.
+    pub(crate) fn into_hmenu(mut self) -> (HMENU, MenuResources) {
         let hmenu = self.hmenu;
+        let resources = mem::take(&mut self.resources);
         mem::forget(self);
-        hmenu
+        (hmenu, resources)
     }
 
     pub fn add_item(&mut self, text: &str, id: usize, shortcut: Option<&Shortcut>, checked: bool, disabled: bool) {
@@ -68,8 +112,57 @@ impl Menu {
         };
     }
 
+    /// Adds an owner-drawn item. It is measured and painted by the window that shows the menu
+    /// (`WM_MEASUREITEM`, `WM_DRAWITEM`).
+    pub fn add_custom_item(&mut self, draw: MenuItemDraw, id: usize, checked: bool, disabled: bool) {
+        let draw = Box::new(draw);
+        unsafe {
+            let mut flags = MF_OWNERDRAW;
+            if checked {
+                flags |= MF_CHECKED;
+            }
+            if disabled {
+                flags |= MF_DISABLED;
+            }
+            // SAFETY: `draw` is kept alive with the menu (in `resources`)
+            AppendMenuW(self.hmenu, flags, id, None);
+            let info = MENUITEMINFOW {
+                cbSize: mem::size_of::<MENUITEMINFOW>() as u32,
+                fMask: MIIM_DATA,
+                dwItemData: &*draw as *const MenuItemDraw as usize,
+                ..Default::default()
+            };
+            SetMenuItemInfoW(self.hmenu, id as u32, false, &info);
+        }
+        self.resources.custom_items.push(draw);
+    }
+
+    /// Sets the bitmap displayed next to the text of the item with the specified ID.
+    pub fn set_item_icon(&mut self, id: usize, icon: &MenuIcon) {
+        unsafe {
+            // SAFETY: the bitmap is deleted with the menu (in `resources`)
+            let bitmap = match create_premultiplied_bitmap(icon.width, icon.height, &icon.pixels) {
+                Ok(bitmap) => bitmap,
+                Err(err) => {
+                    warn!("failed to create menu item bitmap: {}", err);
+                    return;
+                }
+            };
+            let info = MENUITEMINFOW {
+                cbSize: mem::size_of::<MENUITEMINFOW>() as u32,
+                fMask: MIIM_BITMAP,
+                hbmpItem: bitmap,
+                ..Default::default()
+            };
+            SetMenuItemInfoW(self.hmenu, id as u32, false, &info);
+            self.resources.bitmaps.push(bitmap);
+        }
+    }
+
     pub fn add_submenu(&mut self, text: &str, submenu: Menu) {
-        let sub_hmenu = submenu.into_hmenu();
+        // the submenu is destroyed with this menu
+        let (sub_hmenu, resources) = submenu.into_hmenu();
+        self.resources.append(resources);
         unsafe {
             // SAFETY: TODO
             AppendMenuW(
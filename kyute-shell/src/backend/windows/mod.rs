@@ -1,6 +1,8 @@
+pub(crate) mod accessibility;
 pub mod animation;
 mod application;
-mod clipboard;
+pub(crate) mod clipboard;
+pub(crate) mod dialogs;
 pub mod drawing;
 mod error;
 mod event;
@@ -9,8 +11,8 @@ pub mod text;
 mod util;
 mod window;
 
-pub(crate) use animation::{Layer, Surface};
+pub(crate) use animation::{frame_timing, Layer, Surface};
 pub(crate) use application::Application;
 pub(crate) use error::PlatformError;
 pub(crate) use menu::Menu;
-pub(crate) use window::Window;
+pub(crate) use window::{monitor_work_area, Window};
@@ -2,9 +2,10 @@ use crate::{
     application::Application,
     backend::text::{count_until_utf16, count_utf16, dwrite_factory, ToDirectWrite, ToWString},
     text::{
-        Attribute, FontStyle, FontWeight, FormattedText, GlyphMaskData, GlyphMaskFormat, GlyphRunDrawingEffects,
-        HitTestMetrics, HitTestPoint, HitTestTextPosition, LineMetrics, ParagraphStyle, RasterizationOptions, Renderer,
-        TextAffinity, TextAlignment, TextMetrics, TextPosition,
+        line_break::SOFT_HYPHEN, to_utf16_no_wrap, Attribute, FontStyle, FontWeight, FormattedText, GlyphMaskData,
+        GlyphMaskFormat, GlyphRunDrawingEffects, HitTestMetrics, HitTestPoint, HitTestTextPosition, LineMetrics,
+        ParagraphStyle, RasterizationOptions, Renderer, TextAffinity, TextAlignment, TextDecoration, TextLayoutMode,
+        TextMetrics, TextPosition,
     },
     Error,
 };
@@ -15,12 +16,13 @@ use windows::{
     Win32::{
         Foundation::{BOOL, ERROR_INSUFFICIENT_BUFFER, RECT},
         Graphics::DirectWrite::{
-            DWRITE_TEXTURE_ALIASED_1x1, DWRITE_TEXTURE_CLEARTYPE_3x1, IDWriteFontFace, IDWriteGlyphRunAnalysis,
-            IDWriteInlineObject, IDWriteNumberSubstitution, IDWriteNumberSubstitution_Impl, IDWritePixelSnapping_Impl,
-            IDWriteTextLayout, IDWriteTextRenderer, IDWriteTextRenderer_Impl, DWRITE_FONT_STRETCH_NORMAL,
-            DWRITE_GLYPH_RUN, DWRITE_GLYPH_RUN_DESCRIPTION, DWRITE_HIT_TEST_METRICS, DWRITE_LINE_METRICS,
-            DWRITE_MATRIX, DWRITE_MEASURING_MODE, DWRITE_RENDERING_MODE_NATURAL,
-            DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC, DWRITE_STRIKETHROUGH, DWRITE_TEXTURE_TYPE, DWRITE_TEXT_METRICS,
+            DWRITE_TEXTURE_ALIASED_1x1, DWRITE_TEXTURE_CLEARTYPE_3x1, IDWriteFactory2, IDWriteFontFace,
+            IDWriteGlyphRunAnalysis, IDWriteInlineObject, IDWriteNumberSubstitution, IDWriteNumberSubstitution_Impl,
+            IDWritePixelSnapping_Impl, IDWriteTextFormat, IDWriteTextLayout, IDWriteTextRenderer,
+            IDWriteTextRenderer_Impl, DWRITE_FONT_STRETCH_NORMAL, DWRITE_GLYPH_RUN, DWRITE_GLYPH_RUN_DESCRIPTION,
+            DWRITE_GRID_FIT_MODE_DISABLED, DWRITE_HIT_TEST_METRICS, DWRITE_LINE_METRICS, DWRITE_MATRIX,
+            DWRITE_MEASURING_MODE, DWRITE_RENDERING_MODE_NATURAL, DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC,
+            DWRITE_STRIKETHROUGH, DWRITE_TEXTURE_TYPE, DWRITE_TEXT_ANTIALIAS_MODE_CLEARTYPE, DWRITE_TEXT_METRICS,
             DWRITE_TEXT_RANGE, DWRITE_UNDERLINE,
         },
     },
@@ -30,7 +32,19 @@ use windows::{
 #[derive(Clone)]
 pub struct Paragraph {
     layout: IDWriteTextLayout,
+    /// Format of the paragraph, used to create the soft hyphens.
+    format: IDWriteTextFormat,
     text: Arc<str>,
+    /// Hyphens to draw at the end of lines that were broken on a soft hyphen.
+    ///
+    /// DirectWrite breaks lines on soft hyphens but doesn't draw anything there.
+    hyphens: Vec<(Point, IDWriteTextLayout)>,
+    /// Background colors of ranges of text, drawn before the glyphs.
+    ///
+    /// DirectWrite has no background attribute: the boxes of the ranges are computed when drawing.
+    backgrounds: Vec<(Range<usize>, Color)>,
+    /// How the glyphs are rendered.
+    layout_mode: TextLayoutMode,
 }
 
 /// Returns (start, len).
@@ -142,6 +156,22 @@ impl Paragraph {
         }
     }
 
+    /// Changes the layout maximum size.
+    ///
+    /// This only breaks the lines again, the text is not reshaped.
+    pub fn set_max_size(&mut self, size: Size) {
+        unsafe {
+            self.layout.SetMaxWidth(size.width as f32).expect("SetMaxWidth failed");
+            self.layout.SetMaxHeight(size.height as f32).expect("SetMaxHeight failed");
+        }
+        self.hyphens = self.soft_hyphen_breaks();
+    }
+
+    /// Returns the width of the widest unbreakable sequence in the paragraph.
+    pub fn min_width(&self) -> f64 {
+        unsafe { self.layout.DetermineMinWidth().expect("DetermineMinWidth failed") as f64 }
+    }
+
     pub fn hit_test_text_position(&self, text_position: TextPosition) -> HitTestTextPosition {
         // convert the text position to an utf-16 offset (inspired by piet-direct2d).
         let pos_utf16 = count_utf16(&self.text[0..text_position.position]);
@@ -264,6 +294,12 @@ impl Paragraph {
         renderer: &mut dyn Renderer,
         default_drawing_effects: &GlyphRunDrawingEffects,
     ) -> Result<(), Error> {
+        for (range, color) in self.backgrounds.iter() {
+            for hit in self.hit_test_text_range(range.clone(), origin) {
+                renderer.draw_background(hit.bounds, *color);
+            }
+        }
+
         unsafe {
             // DANGER ZONE: erase lifetime on renderer
             // TODO: not sure that this is entirely safe
@@ -273,10 +309,19 @@ impl Paragraph {
             let dwrite_renderer: IDWriteTextRenderer = DWriteRendererProxy {
                 renderer,
                 default_drawing_effects,
+                layout_mode: self.layout_mode,
             }
             .into();
             self.layout
-                .Draw(ptr::null(), &dwrite_renderer, origin.x as f32, origin.y as f32)?
+                .Draw(ptr::null(), &dwrite_renderer, origin.x as f32, origin.y as f32)?;
+            for (pos, hyphen) in self.hyphens.iter() {
+                hyphen.Draw(
+                    ptr::null(),
+                    &dwrite_renderer,
+                    (origin.x + pos.x) as f32,
+                    (origin.y + pos.y) as f32,
+                )?;
+            }
         };
 
         Ok(())
@@ -402,6 +447,7 @@ pub struct GlyphRun<'a> {
     baseline_origin_x: f32,
     baseline_origin_y: f32,
     measuring_mode: DWRITE_MEASURING_MODE,
+    layout_mode: TextLayoutMode,
     glyph_run: &'a DWRITE_GLYPH_RUN,
     glyph_run_description: &'a DWRITE_GLYPH_RUN_DESCRIPTION,
     // TODO: analysis cache?
@@ -411,6 +457,9 @@ pub struct GlyphRun<'a> {
 impl<'a> GlyphRun<'a> {
     /// Creates a `GlyphRunAnalysis` object containing rendering information for the given scale factor and transformation.
     pub fn create_glyph_run_analysis(&self, scale_factor: f64, transform: &Transform) -> GlyphRunAnalysis {
+        if self.layout_mode == TextLayoutMode::Print {
+            return self.create_unhinted_glyph_run_analysis(scale_factor, transform);
+        }
         let transform = transform.to_dwrite();
         //eprintln!("transform={:?}", transform);
         let analysis: IDWriteGlyphRunAnalysis = unsafe {
@@ -430,24 +479,106 @@ impl<'a> GlyphRun<'a> {
         };
         GlyphRunAnalysis { analysis }
     }
+
+    /// Creates a glyph run analysis with grid-fitting disabled, for `TextLayoutMode::Print`.
+    fn create_unhinted_glyph_run_analysis(&self, scale_factor: f64, transform: &Transform) -> GlyphRunAnalysis {
+        // this version has no pixels-per-DIP parameter: the scale factor goes into the transform
+        let transform = transform.then_scale(scale_factor, scale_factor).to_dwrite();
+        let analysis: IDWriteGlyphRunAnalysis = unsafe {
+            let factory: IDWriteFactory2 = dwrite_factory().cast().expect("IDWriteFactory2 not supported");
+            factory
+                .CreateGlyphRunAnalysis2(
+                    self.glyph_run,
+                    &transform,
+                    DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC,
+                    self.measuring_mode,
+                    DWRITE_GRID_FIT_MODE_DISABLED,
+                    DWRITE_TEXT_ANTIALIAS_MODE_CLEARTYPE,
+                    self.baseline_origin_x,
+                    self.baseline_origin_y,
+                )
+                .expect("CreateGlyphRunAnalysis failed")
+        };
+        GlyphRunAnalysis { analysis }
+    }
+}
+
+/// Drawing attributes of a range of text.
+#[derive(Copy, Clone, Debug, Default)]
+struct RangeDrawingEffects {
+    /// Color of the text, overrides the default color.
+    color: Option<Color>,
+    underline: Option<TextDecoration>,
+    strikethrough: Option<TextDecoration>,
 }
 
 /// Drawing attributes passed to IDWriteTextLayout (via SetDrawingEffect).
 // FIXME: `#[implement(IUnknown)]` doesn't work for now, so instead implement a random-ass interface without any methods
 #[implement(IDWriteNumberSubstitution)]
-struct GlyphRunDrawingEffectsWrapper(GlyphRunDrawingEffects);
-impl IDWriteNumberSubstitution_Impl for GlyphRunDrawingEffectsWrapper {}
+struct RangeDrawingEffectsWrapper(RangeDrawingEffects);
+impl IDWriteNumberSubstitution_Impl for RangeDrawingEffectsWrapper {}
+
+/// Returns the drawing attributes of a range of text from the client drawing effect passed to the renderer.
+unsafe fn range_drawing_effects(client_drawing_effect: &Option<IUnknown>) -> Option<RangeDrawingEffects> {
+    client_drawing_effect.as_ref().map(|client_drawing_effect| {
+        // SAFETY: the only drawing effect passed here is an instance of RangeDrawingEffectsWrapper.
+        // TODO erase this disgrace once `implement(IUnknown)` works.
+        let whatever: IDWriteNumberSubstitution = client_drawing_effect.cast().unwrap();
+        let drawing_effects: &RangeDrawingEffectsWrapper = AsImpl::as_impl(&whatever);
+        drawing_effects.0
+    })
+}
 
 /// Custom IDWriteTextRenderer. Delegates to a `Renderer` instance.
 #[implement(IDWriteTextRenderer)]
 struct DWriteRendererProxy {
     default_drawing_effects: *const GlyphRunDrawingEffects,
     renderer: *mut dyn Renderer,
+    layout_mode: TextLayoutMode,
+}
+
+impl DWriteRendererProxy {
+    /// Returns the drawing effects to use for the specified client drawing effect.
+    unsafe fn drawing_effects(&self, client_drawing_effect: &Option<IUnknown>) -> GlyphRunDrawingEffects {
+        // SAFETY: ensured by lifetime of DWriteRendererProxy in Paragraph::draw
+        let default_drawing_effects = &*self.default_drawing_effects;
+        // effects set on text ranges only specify the color: shadow and outline come from the defaults
+        GlyphRunDrawingEffects {
+            color: range_drawing_effects(client_drawing_effect)
+                .and_then(|effects| effects.color)
+                .unwrap_or(default_drawing_effects.color),
+            ..default_drawing_effects.clone()
+        }
+    }
+
+    /// Draws an underline or a strikethrough line.
+    ///
+    /// `offset` is the position of the top of the line relative to the baseline.
+    unsafe fn draw_line(
+        &self,
+        baseline_origin: Point,
+        offset: f32,
+        width: f32,
+        thickness: f32,
+        decoration: TextDecoration,
+        client_drawing_effect: &Option<IUnknown>,
+    ) {
+        let rect = Rect::new(
+            Point::new(baseline_origin.x, baseline_origin.y + offset as f64),
+            Size::new(width as f64, thickness as f64),
+        );
+        let color = decoration
+            .color
+            .unwrap_or_else(|| self.drawing_effects(client_drawing_effect).color);
+        // SAFETY: ensured by lifetime of DWriteRendererProxy in Paragraph::draw
+        (&mut *self.renderer).draw_line(rect, decoration.style, color);
+    }
 }
 
 impl IDWritePixelSnapping_Impl for DWriteRendererProxy {
     fn IsPixelSnappingDisabled(&self, _clientdrawingcontext: *const c_void) -> windows::core::Result<BOOL> {
-        Ok(false.into())
+        // in print mode, glyph runs stay at their fractional positions
+        Ok((self.layout_mode == TextLayoutMode::Print).into())
     }
 
     fn GetCurrentTransform(&self, _clientdrawingcontext: *const c_void) -> windows::core::Result<DWRITE_MATRIX> {
@@ -484,23 +615,16 @@ impl IDWriteTextRenderer_Impl for DWriteRendererProxy {
                 baseline_origin_x: baselineoriginx,
                 baseline_origin_y: baselineoriginy,
                 measuring_mode: measuringmode,
+                layout_mode: self.layout_mode,
                 // SAFETY: only borrowed for the duration of the function; cannot escape through `Renderer::draw_glyph_run` because of lifetime bound.
                 glyph_run: &*glyphrun,
                 glyph_run_description: &*glyphrundescription,
                 analysis: RefCell::new(None),
             });
 
-            if let Some(client_drawing_effect) = clientdrawingeffect {
-                // SAFETY: the only drawing effect passed here is an instance of DWriteRendererProxy.
-                // TODO erase this disgrace once `implement(IUnknown)` works.
-                let whatever: IDWriteNumberSubstitution = client_drawing_effect.cast().unwrap();
-                let drawing_effects: &GlyphRunDrawingEffectsWrapper = AsImpl::as_impl(&whatever);
-                // SAFETY: drawing effect lives as long as the draw call
-                (&mut *self.renderer).draw_glyph_run(&glyph_run, &drawing_effects.0);
-            } else {
-                // SAFETY: drawing effect lives as long as the draw call
-                (&mut *self.renderer).draw_glyph_run(&glyph_run, &*self.default_drawing_effects);
-            };
+            let drawing_effects = self.drawing_effects(clientdrawingeffect);
+            // SAFETY: drawing effect lives as long as the draw call
+            (&mut *self.renderer).draw_glyph_run(&glyph_run, &drawing_effects);
 
             Ok(())
         }
@@ -509,23 +633,55 @@ impl IDWriteTextRenderer_Impl for DWriteRendererProxy {
     fn DrawUnderline(
         &self,
         _clientdrawingcontext: *const c_void,
-        _baselineoriginx: f32,
-        _baselineoriginy: f32,
-        _underline: *const DWRITE_UNDERLINE,
-        _clientdrawingeffect: &Option<::windows::core::IUnknown>,
+        baselineoriginx: f32,
+        baselineoriginy: f32,
+        underline: *const DWRITE_UNDERLINE,
+        clientdrawingeffect: &Option<::windows::core::IUnknown>,
     ) -> windows::core::Result<()> {
-        todo!()
+        unsafe {
+            // SAFETY: only borrowed for the duration of the function
+            let underline = &*underline;
+            let decoration = range_drawing_effects(clientdrawingeffect)
+                .and_then(|effects| effects.underline)
+                .unwrap_or_default();
+            // the offset is relative to the baseline, the thickness extends downwards
+            self.draw_line(
+                Point::new(baselineoriginx as f64, baselineoriginy as f64),
+                underline.offset,
+                underline.width,
+                underline.thickness,
+                decoration,
+                clientdrawingeffect,
+            );
+            Ok(())
+        }
     }
 
     fn DrawStrikethrough(
         &self,
         _clientdrawingcontext: *const c_void,
-        _baselineoriginx: f32,
-        _baselineoriginy: f32,
-        _strikethrough: *const DWRITE_STRIKETHROUGH,
-        _clientdrawingeffect: &Option<::windows::core::IUnknown>,
+        baselineoriginx: f32,
+        baselineoriginy: f32,
+        strikethrough: *const DWRITE_STRIKETHROUGH,
+        clientdrawingeffect: &Option<::windows::core::IUnknown>,
     ) -> windows::core::Result<()> {
-        todo!()
+        unsafe {
+            // SAFETY: only borrowed for the duration of the function
+            let strikethrough = &*strikethrough;
+            let decoration = range_drawing_effects(clientdrawingeffect)
+                .and_then(|effects| effects.strikethrough)
+                .unwrap_or_default();
+            // same as underlines: the offset (negative) is relative to the baseline
+            self.draw_line(
+                Point::new(baselineoriginx as f64, baselineoriginy as f64),
+                strikethrough.offset,
+                strikethrough.width,
+                strikethrough.thickness,
+                decoration,
+                clientdrawingeffect,
+            );
+            Ok(())
+        }
     }
 
     fn DrawInlineObject(
@@ -550,7 +706,9 @@ impl Paragraph {
     ) -> Paragraph {
         unsafe {
             let dwrite_factory = &Application::instance().backend.dwrite_factory.0;
-            let text_wide = formatted_text.plain_text.to_wstring();
+            // replace break opportunities in no-wrap ranges by their non-breaking equivalents
+            let mut text_wide = to_utf16_no_wrap(&formatted_text.plain_text, &formatted_text.no_wrap_ranges());
+            text_wide.push(0);
 
             // FIXME get last-resort defaults from system settings
             const DEFAULT_FONT_FAMILY: &str = "Segoe UI";
@@ -587,6 +745,11 @@ impl Paragraph {
                 .font_size
                 .or(default_paragraph_style.font_size)
                 .unwrap_or(DEFAULT_FONT_SIZE);
+            let paragraph_layout_mode = formatted_text
+                .paragraph_style
+                .layout_mode
+                .or(default_paragraph_style.layout_mode)
+                .unwrap_or_default();
 
             let format = dwrite_factory
                 .CreateTextFormat(
@@ -614,6 +777,7 @@ impl Paragraph {
                 .expect("SetTextAlignment failed");
 
             // apply style ranges
+            let mut backgrounds = vec![];
             for run in formatted_text.runs.runs.iter() {
                 let mut font_family = None;
                 let mut font_weight = None;
@@ -621,6 +785,8 @@ impl Paragraph {
                 //let mut font_stretch = None;
                 let mut font_size = None;
                 let mut color = None;
+                let mut underline = None;
+                let mut strikethrough = None;
 
                 for attr in run.attributes.iter() {
                     match *attr {
@@ -637,6 +803,13 @@ impl Paragraph {
                         Attribute::Color(c) => {
                             color = Some(c);
                         }
+                        // handled when converting the text to UTF-16
+                        Attribute::NoWrap => {}
+                        Attribute::Underline(decoration) => underline = Some(decoration),
+                        Attribute::Strikethrough(decoration) => strikethrough = Some(decoration),
+                        Attribute::Background(color) => backgrounds.push((run.range.clone(), color)),
+                        // hit-tested by the widgets that display the text
+                        Attribute::Link(_) => {}
                     }
                 }
 
@@ -663,18 +836,99 @@ impl Paragraph {
                     layout.SetFontStyle(fs.to_dwrite(), range).expect("SetFontStyle failed");
                 }
 
-                if let Some(color) = color {
-                    let effect: IUnknown = GlyphRunDrawingEffectsWrapper(GlyphRunDrawingEffects { color }).into();
+                if underline.is_some() {
+                    layout.SetUnderline(true, range).expect("SetUnderline failed");
+                }
+
+                if strikethrough.is_some() {
+                    layout.SetStrikethrough(true, range).expect("SetStrikethrough failed");
+                }
+
+                if color.is_some() || underline.is_some() || strikethrough.is_some() {
+                    let effect: IUnknown = RangeDrawingEffectsWrapper(RangeDrawingEffects {
+                        color,
+                        underline,
+                        strikethrough,
+                    })
+                    .into();
                     layout
                         .SetDrawingEffect(&effect, range)
                         .expect("SetDrawingEffect failed");
                 }
             }
 
-            Paragraph {
+            let mut paragraph = Paragraph {
                 layout,
+                format,
                 text: formatted_text.plain_text.clone(),
+                hyphens: vec![],
+                backgrounds,
+                layout_mode: paragraph_layout_mode,
+            };
+            paragraph.hyphens = paragraph.soft_hyphen_breaks();
+            paragraph
+        }
+    }
+
+    /// Creates the hyphens to draw at the end of lines broken on a soft hyphen.
+    fn soft_hyphen_breaks(&self) -> Vec<(Point, IDWriteTextLayout)> {
+        let dwrite_factory = &Application::instance().backend.dwrite_factory.0;
+        let hyphen_wide = "-".to_wstring();
+        let mut hyphens = vec![];
+
+        // position of the end of the current line, in UTF-16 code units
+        let mut line_end = 0;
+        let line_metrics = self.line_metrics();
+        let line_count = line_metrics.len();
+        for (i, line) in line_metrics.into_iter().enumerate() {
+            line_end += line.length as usize;
+            // the last line isn't broken
+            if i + 1 == line_count {
+                break;
+            }
+            let last_char_end = line_end - line.newline_length as usize - line.trailing_whitespace_length as usize;
+            if line.newline_length != 0 || last_char_end == 0 {
+                continue;
+            }
+            let last_char = count_until_utf16(&self.text, last_char_end - 1);
+            if !self.text[last_char..].starts_with(SOFT_HYPHEN) {
+                continue;
+            }
+
+            unsafe {
+                let mut x = 0.0f32;
+                let mut y = 0.0f32;
+                let mut metrics = MaybeUninit::<DWRITE_HIT_TEST_METRICS>::uninit();
+                self.layout
+                    .HitTestTextPosition((last_char_end - 1) as u32, true, &mut x, &mut y, metrics.as_mut_ptr())
+                    .expect("HitTestTextPosition failed");
+
+                // match the font size and drawing effect (color) of the soft hyphen
+                let mut font_size = 0.0f32;
+                self.layout
+                    .GetFontSize((last_char_end - 1) as u32, &mut font_size, ptr::null_mut())
+                    .expect("GetFontSize failed");
+                let mut drawing_effect = None;
+                self.layout
+                    .GetDrawingEffect((last_char_end - 1) as u32, &mut drawing_effect, ptr::null_mut())
+                    .expect("GetDrawingEffect failed");
+
+                let hyphen: IDWriteTextLayout = dwrite_factory
+                    .CreateTextLayout(&hyphen_wide, &self.format, f32::INFINITY, f32::INFINITY)
+                    .expect("CreateTextLayout failed");
+                let range = DWRITE_TEXT_RANGE {
+                    startPosition: 0,
+                    length: 1,
+                };
+                hyphen.SetFontSize(font_size, range).expect("SetFontSize failed");
+                if let Some(effect) = drawing_effect {
+                    hyphen
+                        .SetDrawingEffect(&effect, range)
+                        .expect("SetDrawingEffect failed");
+                }
+                hyphens.push((Point::new(x as f64, y as f64), hyphen));
             }
         }
+        hyphens
     }
 }
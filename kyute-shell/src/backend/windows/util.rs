@@ -1,4 +1,14 @@
-use std::{ffi::OsStr, os::windows::ffi::OsStrExt};
+use std::{
+    ffi::OsStr,
+    mem,
+    os::windows::ffi::OsStrExt,
+    ptr,
+    time::{Duration, Instant},
+};
+use windows::Win32::{
+    Graphics::Gdi::{CreateDIBSection, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HBITMAP},
+    System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency},
+};
 
 // --- this section taken from https://github.com/linebender/druid/blob/f588fa76bc88215ce6b2b500d0eba26149ca8368/druid-shell/src/backend/windows/util.rs#L63
 // see licenses
@@ -19,3 +29,47 @@ where
         self.as_ref().encode_wide().chain(Some(0)).collect()
     }
 }
+
+/// Creates a top-down 32bpp DIB section from premultiplied BGRA pixels.
+pub(crate) unsafe fn create_premultiplied_bitmap(
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> windows::core::Result<HBITMAP> {
+    let mut info: BITMAPINFO = mem::zeroed();
+    info.bmiHeader = BITMAPINFOHEADER {
+        biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+        biWidth: width as i32,
+        biHeight: -(height as i32),
+        biPlanes: 1,
+        biBitCount: 32,
+        biCompression: BI_RGB as u32,
+        ..mem::zeroed()
+    };
+    let mut bits = ptr::null_mut();
+    let bitmap = CreateDIBSection(None, &info, DIB_RGB_COLORS, &mut bits, None, 0)?;
+    let len = width as usize * height as usize * 4;
+    ptr::copy_nonoverlapping(pixels.as_ptr(), bits as *mut u8, len.min(pixels.len()));
+    Ok(bitmap)
+}
+
+/// Converts a `QueryPerformanceCounter` value to an `Instant`.
+pub(crate) fn qpc_to_instant(qpc_time: i64) -> Instant {
+    let now = Instant::now();
+    let (mut counter, mut frequency) = (0, 0);
+    unsafe {
+        if !QueryPerformanceCounter(&mut counter).as_bool() || !QueryPerformanceFrequency(&mut frequency).as_bool() {
+            return now;
+        }
+    }
+    if frequency <= 0 {
+        return now;
+    }
+    let ticks = counter - qpc_time;
+    let elapsed = Duration::from_secs_f64(ticks.unsigned_abs() as f64 / frequency as f64);
+    if ticks >= 0 {
+        now.checked_sub(elapsed).unwrap_or(now)
+    } else {
+        now + elapsed
+    }
+}
@@ -1,21 +1,51 @@
 //! Platform-specific window creation
 use crate::{
+    accessibility::AccessibleTree,
     application::Application,
-    backend::{Layer, Menu, PlatformError},
+    backend::{
+        windows::{
+            accessibility::AccessibilityProvider, application::notify_settings_changed, menu::MenuResources,
+            util::create_premultiplied_bitmap,
+        },
+        Layer, Menu, PlatformError,
+    },
     error::Error,
+    menu::{MenuItemDraw, MenuItemState},
+    window::{CursorImage, CustomFrame, ResizeEdge, WindowPart},
 };
-use kyute_common::{PointI, Size, SizeI};
+use kyute_common::{Point, PointI, RectI, Size, SizeI};
 use raw_window_handle::HasRawWindowHandle;
-use std::{ffi::c_void, mem, ptr};
+use skia_safe as sk;
+use std::{cell::RefCell, ffi::c_void, mem, ptr, time::Duration};
 use windows::Win32::{
-    Foundation::{BOOL, HINSTANCE, HWND, POINT},
+    Foundation::{BOOL, HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
     Graphics::{
         Direct2D::Common::D2D1_COLOR_F,
         DirectComposition::IDCompositionTarget,
         Dwm::{DwmSetWindowAttribute, DWMWA_USE_IMMERSIVE_DARK_MODE, DWMWINDOWATTRIBUTE},
-        Gdi::ClientToScreen,
+        Gdi::{
+            AlphaBlend, ClientToScreen, CreateBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, FillRect,
+            GetMonitorInfoW, GetSysColorBrush, InvalidateRect, MonitorFromPoint, ScreenToClient, SelectObject,
+            AC_SRC_ALPHA, AC_SRC_OVER, BLENDFUNCTION, COLOR_HIGHLIGHT, COLOR_MENU, MONITORINFO,
+            MONITOR_DEFAULTTONEAREST,
+        },
+    },
+    UI::{
+        Accessibility::UiaRootObjectId,
+        Controls::{DRAWITEMSTRUCT, MEASUREITEMSTRUCT, ODS_CHECKED, ODS_DISABLED, ODS_GRAYED, ODS_SELECTED},
+        Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass},
+        WindowsAndMessaging::{
+            CreateIconIndirect, DestroyCursor, DestroyMenu, DrawMenuBar, GetClientRect, GetCursorPos, GetSystemMetrics,
+            GetWindowLongW, GetWindowRect, IsZoomed, KillTimer, LoadCursorW, SetCursor, SetMenu, SetTimer,
+            SetWindowPos, TrackPopupMenu, GWL_STYLE, HCURSOR, HMENU, HTBOTTOM, HTBOTTOMLEFT, HTBOTTOMRIGHT, HTCAPTION,
+            HTCLIENT, HTCLOSE, HTLEFT, HTMAXBUTTON, HTMINBUTTON, HTRIGHT, HTTOP, HTTOPLEFT, HTTOPRIGHT, ICONINFO,
+            IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE, MINMAXINFO, NCCALCSIZE_PARAMS, SM_CXFRAME,
+            SM_CXPADDEDBORDER, SM_CYFRAME, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER,
+            TPM_LEFTALIGN, WM_DPICHANGED, WM_DRAWITEM, WM_ENTERSIZEMOVE, WM_EXITSIZEMOVE, WM_GETMINMAXINFO,
+            WM_GETOBJECT, WM_MEASUREITEM, WM_NCCALCSIZE, WM_NCDESTROY, WM_NCHITTEST, WM_SETCURSOR, WM_SETTINGCHANGE,
+            WM_SYSCOLORCHANGE, WM_THEMECHANGED, WM_TIMER, WS_SIZEBOX,
+        },
     },
-    UI::WindowsAndMessaging::{DestroyMenu, DrawMenuBar, SetMenu, TrackPopupMenu, HMENU, TPM_LEFTALIGN},
 };
 use winit::{
     event_loop::EventLoopWindowTarget,
@@ -23,13 +53,349 @@ use winit::{
     window::{CursorIcon, WindowBuilder, WindowId},
 };
 
+/// ID of the window subclass that handles custom decorations, size limits and accessibility.
+const FRAME_SUBCLASS_ID: usize = 1;
+
+/// ID of the timer set by `Window::request_redraw_after`.
+const REDRAW_TIMER_ID: usize = 1;
+
+/// State of the window subclass that handles custom decorations, size limits and accessibility.
+///
+/// Sizes are in DIPs, converted to physical pixels with the current scale factor of the window.
+struct FrameState {
+    scale_factor: f64,
+    custom_frame: Option<CustomFrame>,
+    min_size: Option<Size>,
+    max_size: Option<Size>,
+    /// Whether the user is moving or resizing the window.
+    in_size_move: bool,
+    /// Cursor shown over the client area instead of the cursor icon (see `Window::set_cursor_image`).
+    cursor: Option<OwnedCursor>,
+    accessibility: AccessibilityProvider,
+}
+
+/// A cursor created from an image, destroyed when dropped.
+struct OwnedCursor(HCURSOR);
+
+impl Drop for OwnedCursor {
+    fn drop(&mut self) {
+        unsafe {
+            DestroyCursor(self.0);
+        }
+    }
+}
+
+/// Creates a cursor from an image.
+unsafe fn create_cursor(image: &CursorImage) -> windows::core::Result<OwnedCursor> {
+    let color = create_premultiplied_bitmap(image.width, image.height, &image.pixels)?;
+    // the mask is unused for cursors with an alpha channel, but it must be specified
+    let mask = CreateBitmap(image.width as i32, image.height as i32, 1, 1, ptr::null());
+    let info = ICONINFO {
+        fIcon: false.into(),
+        xHotspot: image.hotspot_x.clamp(0, image.width as i32) as u32,
+        yHotspot: image.hotspot_y.clamp(0, image.height as i32) as u32,
+        hbmMask: mask,
+        hbmColor: color,
+    };
+    // the cursor has its own copy of the bitmaps
+    let cursor = CreateIconIndirect(&info);
+    DeleteObject(mask);
+    DeleteObject(color);
+    Ok(OwnedCursor(HCURSOR(cursor?.0)))
+}
+
+/// Returns the hit-test code corresponding to a part of the window.
+fn hit_test_code(part: WindowPart) -> u32 {
+    match part {
+        WindowPart::Client => HTCLIENT,
+        WindowPart::Caption => HTCAPTION,
+        WindowPart::MinimizeButton => HTMINBUTTON,
+        WindowPart::MaximizeButton => HTMAXBUTTON,
+        WindowPart::CloseButton => HTCLOSE,
+        WindowPart::Border(ResizeEdge::Left) => HTLEFT,
+        WindowPart::Border(ResizeEdge::Right) => HTRIGHT,
+        WindowPart::Border(ResizeEdge::Top) => HTTOP,
+        WindowPart::Border(ResizeEdge::Bottom) => HTBOTTOM,
+        WindowPart::Border(ResizeEdge::TopLeft) => HTTOPLEFT,
+        WindowPart::Border(ResizeEdge::TopRight) => HTTOPRIGHT,
+        WindowPart::Border(ResizeEdge::BottomLeft) => HTBOTTOMLEFT,
+        WindowPart::Border(ResizeEdge::BottomRight) => HTBOTTOMRIGHT,
+    }
+}
+
+/// Returns the size of the non-client area of a window, in physical pixels.
+unsafe fn non_client_size(hwnd: HWND) -> (i32, i32) {
+    let mut window_rect = RECT::default();
+    let mut client_rect = RECT::default();
+    GetWindowRect(hwnd, &mut window_rect);
+    GetClientRect(hwnd, &mut client_rect);
+    (
+        (window_rect.right - window_rect.left) - (client_rect.right - client_rect.left),
+        (window_rect.bottom - window_rect.top) - (client_rect.bottom - client_rect.top),
+    )
+}
+
+/// Paints an owner-drawn menu item (see `Menu::add_custom_item`).
+unsafe fn draw_menu_item(item: &DRAWITEMSTRUCT, scale_factor: f64) {
+    let draw = &*(item.itemData as *const MenuItemDraw);
+    let rect = item.rcItem;
+    let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+    if width <= 0 || height <= 0 {
+        return;
+    }
+    let item_state = MenuItemState {
+        highlighted: item.itemState.0 & ODS_SELECTED.0 != 0,
+        checked: item.itemState.0 & ODS_CHECKED.0 != 0,
+        disabled: item.itemState.0 & (ODS_DISABLED.0 | ODS_GRAYED.0) != 0,
+    };
+
+    // the background is drawn with GDI, like the other items
+    let background = if item_state.highlighted {
+        COLOR_HIGHLIGHT
+    } else {
+        COLOR_MENU
+    };
+    FillRect(item.hDC, &rect, GetSysColorBrush(background));
+
+    // paint the item on a transparent raster surface, and blend it over the background
+    let info = sk::ImageInfo::new_n32_premul((width, height), None);
+    let mut surface = match sk::Surface::new_raster(&info, None, None) {
+        Some(surface) => surface,
+        None => return,
+    };
+    let canvas = surface.canvas();
+    canvas.clear(sk::Color::TRANSPARENT);
+    canvas.scale((scale_factor as f32, scale_factor as f32));
+    let size = Size::new(width as f64 / scale_factor, height as f64 / scale_factor);
+    (draw.paint)(canvas, size, item_state);
+
+    // N32 is BGRA on windows
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    if !surface.read_pixels(&info, &mut pixels, width as usize * 4, (0, 0)) {
+        return;
+    }
+    let bitmap = match create_premultiplied_bitmap(width as u32, height as u32, &pixels) {
+        Ok(bitmap) => bitmap,
+        Err(err) => {
+            warn!("failed to create menu item bitmap: {}", err);
+            return;
+        }
+    };
+    let mem_dc = CreateCompatibleDC(item.hDC);
+    let old_bitmap = SelectObject(mem_dc, bitmap);
+    AlphaBlend(
+        item.hDC,
+        rect.left,
+        rect.top,
+        width,
+        height,
+        mem_dc,
+        0,
+        0,
+        width,
+        height,
+        BLENDFUNCTION {
+            BlendOp: AC_SRC_OVER as u8,
+            BlendFlags: 0,
+            SourceConstantAlpha: 255,
+            AlphaFormat: AC_SRC_ALPHA as u8,
+        },
+    );
+    SelectObject(mem_dc, old_bitmap);
+    DeleteDC(mem_dc);
+    DeleteObject(bitmap);
+}
+
+/// Window subclass procedure that implements custom decorations and size limits, and serves the accessibility tree.
+///
+/// `data` is a pointer to the `RefCell<FrameState>` of the window.
+unsafe extern "system" fn frame_subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    data: usize,
+) -> LRESULT {
+    let state = &*(data as *const RefCell<FrameState>);
+
+    match msg {
+        WM_NCCALCSIZE if wparam.0 != 0 && state.borrow().custom_frame.is_some() => {
+            // the whole window is client area
+            if IsZoomed(hwnd).as_bool() {
+                // maximized windows extend past the edges of the monitor by the size of the frame: don't put
+                // contents there
+                let params = &mut *(lparam.0 as *mut NCCALCSIZE_PARAMS);
+                let padding = GetSystemMetrics(SM_CXPADDEDBORDER);
+                let frame_x = GetSystemMetrics(SM_CXFRAME) + padding;
+                let frame_y = GetSystemMetrics(SM_CYFRAME) + padding;
+                let rect = &mut params.rgrc[0];
+                rect.left += frame_x;
+                rect.right -= frame_x;
+                rect.top += frame_y;
+                rect.bottom -= frame_y;
+            }
+            return LRESULT(0);
+        }
+        WM_NCHITTEST => {
+            let state = state.borrow();
+            if let Some(ref frame) = state.custom_frame {
+                let mut point = POINT {
+                    x: (lparam.0 & 0xFFFF) as i16 as i32,
+                    y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+                };
+                ScreenToClient(hwnd, &mut point);
+                let mut client_rect = RECT::default();
+                GetClientRect(hwnd, &mut client_rect);
+
+                let scale_factor = state.scale_factor;
+                let position = Point::new(point.x as f64 / scale_factor, point.y as f64 / scale_factor);
+                let size = Size::new(
+                    client_rect.right as f64 / scale_factor,
+                    client_rect.bottom as f64 / scale_factor,
+                );
+                let resizable =
+                    !IsZoomed(hwnd).as_bool() && (GetWindowLongW(hwnd, GWL_STYLE) as u32 & WS_SIZEBOX.0) != 0;
+                return LRESULT(hit_test_code(frame.hit_test(position, size, resizable)) as isize);
+            }
+        }
+        WM_SETCURSOR if (lparam.0 & 0xFFFF) as u32 == HTCLIENT => {
+            if let Some(ref cursor) = state.borrow().cursor {
+                SetCursor(cursor.0);
+                return LRESULT(1);
+            }
+        }
+        WM_SETCURSOR if state.borrow().custom_frame.is_some() => {
+            // the low-order word is the result of the last hit-test
+            let cursor = match (lparam.0 & 0xFFFF) as u32 {
+                HTLEFT | HTRIGHT => Some(IDC_SIZEWE),
+                HTTOP | HTBOTTOM => Some(IDC_SIZENS),
+                HTTOPLEFT | HTBOTTOMRIGHT => Some(IDC_SIZENWSE),
+                HTTOPRIGHT | HTBOTTOMLEFT => Some(IDC_SIZENESW),
+                _ => None,
+            };
+            if let Some(cursor) = cursor {
+                if let Ok(cursor) = LoadCursorW(None, cursor) {
+                    SetCursor(cursor);
+                    return LRESULT(1);
+                }
+            }
+        }
+        WM_GETMINMAXINFO => {
+            // let the default handler fill the structure, then apply our limits
+            let result = DefSubclassProc(hwnd, msg, wparam, lparam);
+            let state = state.borrow();
+            let info = &mut *(lparam.0 as *mut MINMAXINFO);
+            let (nc_width, nc_height) = non_client_size(hwnd);
+            let scale_factor = state.scale_factor;
+            if let Some(min) = state.min_size {
+                info.ptMinTrackSize = POINT {
+                    x: (min.width * scale_factor).ceil() as i32 + nc_width,
+                    y: (min.height * scale_factor).ceil() as i32 + nc_height,
+                };
+            }
+            if let Some(max) = state.max_size {
+                info.ptMaxTrackSize = POINT {
+                    x: (max.width * scale_factor).floor() as i32 + nc_width,
+                    y: (max.height * scale_factor).floor() as i32 + nc_height,
+                };
+            }
+            return result;
+        }
+        // owner-drawn menu items (`wparam` is zero for menus)
+        WM_MEASUREITEM if wparam.0 == 0 => {
+            let item = &mut *(lparam.0 as *mut MEASUREITEMSTRUCT);
+            if item.itemData != 0 {
+                let draw = &*(item.itemData as *const MenuItemDraw);
+                let scale_factor = state.borrow().scale_factor;
+                item.itemWidth = (draw.size.width * scale_factor).ceil() as u32;
+                item.itemHeight = (draw.size.height * scale_factor).ceil() as u32;
+                return LRESULT(1);
+            }
+        }
+        WM_DRAWITEM if wparam.0 == 0 => {
+            let item = &*(lparam.0 as *const DRAWITEMSTRUCT);
+            if item.itemData != 0 {
+                draw_menu_item(item, state.borrow().scale_factor);
+                return LRESULT(1);
+            }
+        }
+        WM_DPICHANGED => {
+            // the low-order word is the new horizontal DPI
+            let mut state = state.borrow_mut();
+            state.scale_factor = (wparam.0 & 0xFFFF) as f64 / 96.0;
+            state.accessibility.set_scale_factor(state.scale_factor);
+        }
+        // UI Automation asks for the root element (the object ID is a 32-bit value)
+        WM_GETOBJECT if lparam.0 as i32 == UiaRootObjectId => {
+            // UI Automation may send other messages to the window before returning
+            let accessibility = state.borrow().accessibility.clone();
+            let (result, needs_tree) = accessibility.handle_get_object(wparam, lparam);
+            if needs_tree {
+                // the tree is built by the application during the next update
+                InvalidateRect(hwnd, ptr::null(), false);
+            }
+            return result;
+        }
+        WM_ENTERSIZEMOVE => {
+            state.borrow_mut().in_size_move = true;
+        }
+        WM_EXITSIZEMOVE => {
+            state.borrow_mut().in_size_move = false;
+            // the application may have deferred work until the end of the resize
+            InvalidateRect(hwnd, ptr::null(), false);
+        }
+        // the accessibility preferences, the scroll settings or the theme may have changed
+        WM_SETTINGCHANGE | WM_THEMECHANGED | WM_SYSCOLORCHANGE => {
+            notify_settings_changed();
+            // wake up the event loop so that the application picks up the new settings
+            InvalidateRect(hwnd, ptr::null(), false);
+        }
+        WM_TIMER if wparam.0 == REDRAW_TIMER_ID => {
+            KillTimer(hwnd, REDRAW_TIMER_ID);
+            InvalidateRect(hwnd, ptr::null(), false);
+            return LRESULT(0);
+        }
+        WM_NCDESTROY => {
+            RemoveWindowSubclass(hwnd, Some(frame_subclass_proc), FRAME_SUBCLASS_ID);
+        }
+        _ => {}
+    }
+
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
+
+/// Returns the work area (the desktop minus the taskbar and docked toolbars) of the monitor containing
+/// the specified point, or of the nearest monitor, in physical pixels.
+pub(crate) fn monitor_work_area(point: PointI) -> Option<RectI> {
+    unsafe {
+        // SAFETY: no particular requirements
+        let monitor = MonitorFromPoint(POINT { x: point.x, y: point.y }, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return None;
+        }
+        let work = info.rcWork;
+        Some(RectI::new(
+            PointI::new(work.left, work.top),
+            SizeI::new(work.right - work.left, work.bottom - work.top),
+        ))
+    }
+}
+
 /// Encapsulates a Win32 window and associated resources for drawing to it.
 pub struct Window {
     window: winit::window::Window,
     hwnd: HWND,
     hinstance: HINSTANCE,
-    menu: Option<HMENU>,
+    /// The menu bar, and the resources used by its items.
+    menu: Option<(HMENU, MenuResources)>,
     composition_target: IDCompositionTarget,
+    /// State of the window subclass. Must be dropped after the window.
+    frame_state: Box<RefCell<FrameState>>,
 }
 
 impl Window {
@@ -40,6 +406,11 @@ impl Window {
         &self.window
     }
 
+    /// Returns the handle of the window.
+    pub(crate) fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+
     /// Returns the underlying winit [`WindowId`].
     /// Equivalent to calling `self.window().id()`.
     ///
@@ -52,22 +423,46 @@ impl Window {
     pub fn set_menu(&mut self, new_menu: Option<Menu>) {
         unsafe {
             // SAFETY: TODO
-            if let Some(current_menu) = self.menu.take() {
+            if let Some((current_menu, _resources)) = self.menu.take() {
                 SetMenu(self.hwnd, None);
                 DestroyMenu(current_menu);
             }
             if let Some(menu) = new_menu {
-                let hmenu = menu.into_hmenu();
+                let (hmenu, resources) = menu.into_hmenu();
                 SetMenu(self.hwnd, hmenu);
-                self.menu = Some(hmenu);
+                self.menu = Some((hmenu, resources));
+            }
+        }
+    }
+
+    /// Converts a point in the client area, in physical pixels, to desktop coordinates.
+    pub fn client_to_screen(&self, point: PointI) -> PointI {
+        let mut point = POINT { x: point.x, y: point.y };
+        unsafe {
+            // SAFETY: hwnd is valid
+            ClientToScreen(self.hwnd, &mut point);
+        }
+        PointI::new(point.x, point.y)
+    }
+
+    /// Returns the current position of the cursor relative to the client area, in physical pixels.
+    pub fn cursor_position(&self) -> Option<PointI> {
+        let mut point = POINT::default();
+        unsafe {
+            // SAFETY: hwnd is valid
+            if !GetCursorPos(&mut point).as_bool() {
+                return None;
             }
+            ScreenToClient(self.hwnd, &mut point);
         }
+        Some(PointI::new(point.x, point.y))
     }
 
     /// Shows a context menu at the specified pixel location.
     pub fn show_context_menu(&self, menu: Menu, at: PointI) {
         unsafe {
-            let hmenu = menu.into_hmenu();
+            // TrackPopupMenu returns once the menu is dismissed, the menu can be destroyed afterwards
+            let (hmenu, _resources) = menu.into_hmenu();
             /*let scale_factor = self.window.scale_factor();
             let x = at.x * scale_factor;
             let y = at.y * scale_factor;*/
@@ -76,6 +471,7 @@ impl Window {
             if TrackPopupMenu(hmenu, TPM_LEFTALIGN, point.x, point.y, 0, self.hwnd, ptr::null()) == false {
                 tracing::warn!("failed to track popup menu");
             }
+            DestroyMenu(hmenu);
         }
     }
 
@@ -137,9 +533,90 @@ impl Window {
 
     /// Sets the current cursor icon.
     pub fn set_cursor_icon(&mut self, cursor_icon: CursorIcon) {
+        // destroy the cursor image once it's not the current cursor anymore
+        let _cursor = self.frame_state.borrow_mut().cursor.take();
         self.window.set_cursor_icon(cursor_icon)
     }
 
+    /// Shows an image as the cursor over the client area.
+    pub fn set_cursor_image(&mut self, image: &CursorImage) {
+        let cursor = match unsafe { create_cursor(image) } {
+            Ok(cursor) => cursor,
+            Err(err) => {
+                warn!("failed to create cursor: {}", err);
+                return;
+            }
+        };
+        unsafe {
+            // SAFETY: the previous cursor is destroyed after it has been replaced
+            SetCursor(cursor.0);
+        }
+        self.frame_state.borrow_mut().cursor = Some(cursor);
+    }
+
+    /// Sets the custom decorations of the window, or restores the system decorations if `None`.
+    pub fn set_custom_frame(&self, frame: Option<CustomFrame>) {
+        let mut state = self.frame_state.borrow_mut();
+        let frame_changed = state.custom_frame.is_some() != frame.is_some();
+        state.scale_factor = self.window.scale_factor();
+        state.custom_frame = frame;
+        drop(state);
+
+        if frame_changed {
+            // recompute the size of the non-client area
+            unsafe {
+                SetWindowPos(
+                    self.hwnd,
+                    HWND(0),
+                    0,
+                    0,
+                    0,
+                    0,
+                    SWP_FRAMECHANGED | SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+                );
+            }
+        }
+    }
+
+    /// Returns whether the user is moving or resizing the window (e.g. by dragging its border).
+    ///
+    /// The window is redrawn once the user releases the border.
+    pub fn is_in_size_move(&self) -> bool {
+        self.frame_state.borrow().in_size_move
+    }
+
+    /// Requests a redraw of the window after the specified delay, replacing the previous delayed request.
+    pub fn request_redraw_after(&self, delay: Duration) {
+        unsafe {
+            // SAFETY: hwnd is valid
+            SetTimer(self.hwnd, REDRAW_TIMER_ID, delay.as_millis().max(1) as u32, None);
+        }
+    }
+
+    /// Replaces the accessibility tree of the window, and notifies assistive technologies of the changes.
+    pub fn set_accessibility_tree(&self, tree: AccessibleTree) {
+        let accessibility = {
+            let mut state = self.frame_state.borrow_mut();
+            state.scale_factor = self.window.scale_factor();
+            state.accessibility.set_scale_factor(state.scale_factor);
+            state.accessibility.clone()
+        };
+        accessibility.set_tree(tree);
+    }
+
+    /// Returns whether an assistive technology has asked for the accessibility tree of the window.
+    pub fn is_accessibility_connected(&self) -> bool {
+        self.frame_state.borrow().accessibility.is_connected()
+    }
+
+    /// Sets the minimum and maximum sizes of the client area, in DIPs.
+    pub fn set_size_limits(&self, min: Option<Size>, max: Option<Size>) {
+        let mut state = self.frame_state.borrow_mut();
+        state.scale_factor = self.window.scale_factor();
+        state.min_size = min;
+        state.max_size = max;
+    }
+
     /// Creates a new window from the options given in the provided [`WindowBuilder`].
     ///
     /// To create the window with an OpenGL context, `with_gl` should be `true`.
@@ -205,6 +682,27 @@ impl Window {
         //}
         //let swap_chain = unsafe { device.create_swapchain(surface, swapchain_size) };
 
+        // subclass the window to handle custom decorations, size limits and accessibility
+        let frame_state = Box::new(RefCell::new(FrameState {
+            scale_factor: window.scale_factor(),
+            custom_frame: None,
+            min_size: None,
+            max_size: None,
+            in_size_move: false,
+            cursor: None,
+            accessibility: AccessibilityProvider::new(hwnd, window.scale_factor()),
+        }));
+        unsafe {
+            // SAFETY: the subclass is removed when the window is destroyed, which happens before `frame_state` is
+            // dropped
+            SetWindowSubclass(
+                hwnd,
+                Some(frame_subclass_proc),
+                FRAME_SUBCLASS_ID,
+                &*frame_state as *const RefCell<FrameState> as usize,
+            );
+        }
+
         let pw = Window {
             window,
             hwnd,
@@ -212,6 +710,7 @@ impl Window {
             // TODO menu initializer
             menu: None,
             composition_target,
+            frame_state,
         };
 
         Ok(pw)
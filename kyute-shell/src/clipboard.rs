@@ -1,7 +1,531 @@
 //! Data exchange API (clipboard & drag/drop)
+use crate::backend;
+use bitflags::bitflags;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use skia_safe as sk;
+use std::{
+    collections::VecDeque,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use thiserror::Error;
 
+/// Plain text, encoded in UTF-8.
+pub const TEXT: &str = "text/plain";
+/// HTML fragment, encoded in UTF-8, without the headers of the clipboard format of the platform.
+pub const HTML: &str = "text/html";
+/// PNG image.
+pub const PNG: &str = "image/png";
+/// BMP image, with its file header.
+pub const BMP: &str = "image/bmp";
+/// List of file paths, encoded in UTF-8 and separated by null characters.
+pub const FILE_LIST: &str = "application/x-file-list";
+
+/// Data exchanged with the clipboard or with drag and drop operations, with its type.
+///
+/// The standard types (`TEXT`, `HTML`, `PNG`, `BMP` and `FILE_LIST`) are converted to and from the native formats
+/// of the platform. Other types are application-defined, and exchanged as-is.
 #[derive(Clone, Debug)]
 pub struct TypedData {
     pub type_id: &'static str,
     pub data: Vec<u8>,
 }
+
+impl TypedData {
+    /// Creates plain text data.
+    pub fn text(text: &str) -> TypedData {
+        TypedData {
+            type_id: TEXT,
+            data: text.as_bytes().to_vec(),
+        }
+    }
+
+    /// Creates data for an HTML fragment.
+    pub fn html(fragment: &str) -> TypedData {
+        TypedData {
+            type_id: HTML,
+            data: fragment.as_bytes().to_vec(),
+        }
+    }
+
+    /// Encodes an image as PNG data. Returns `None` if the image can't be encoded.
+    pub fn png(image: &sk::Image) -> Option<TypedData> {
+        let data = image.encode_to_data(sk::EncodedImageFormat::PNG)?;
+        Some(TypedData {
+            type_id: PNG,
+            data: data.as_bytes().to_vec(),
+        })
+    }
+
+    /// Encodes an image as an uncompressed 32-bit BMP. Returns `None` if the pixels of the image can't be read.
+    ///
+    /// Applications that don't read PNG from the clipboard usually read BMP.
+    pub fn bmp(image: &sk::Image) -> Option<TypedData> {
+        Some(TypedData {
+            type_id: BMP,
+            data: encode_bmp(image)?,
+        })
+    }
+
+    /// Creates a list of files.
+    pub fn files(paths: &[impl AsRef<Path>]) -> TypedData {
+        let paths: Vec<_> = paths.iter().map(|path| path.as_ref().to_string_lossy()).collect();
+        TypedData {
+            type_id: FILE_LIST,
+            data: paths.join("\0").into_bytes(),
+        }
+    }
+
+    /// Returns the text of `text/...` data.
+    pub fn as_text(&self) -> Option<&str> {
+        if self.type_id.starts_with("text/") {
+            std::str::from_utf8(&self.data).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Decodes `image/...` data.
+    pub fn to_image(&self) -> Option<sk::Image> {
+        if self.type_id.starts_with("image/") {
+            // decoded now rather than each time the image is drawn
+            sk::Image::from_encoded(sk::Data::new_copy(&self.data))?.new_raster_image()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the paths of `FILE_LIST` data.
+    pub fn to_files(&self) -> Option<Vec<PathBuf>> {
+        if self.type_id != FILE_LIST {
+            return None;
+        }
+        let paths = std::str::from_utf8(&self.data).ok()?;
+        Some(
+            paths
+                .split('\0')
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+        )
+    }
+}
+
+/// Size of the file header of BMP images.
+pub(crate) const BMP_FILE_HEADER_SIZE: usize = 14;
+
+/// Encodes an image as a bottom-up, 32-bit BMP with a `BITMAPINFOHEADER`.
+fn encode_bmp(image: &sk::Image) -> Option<Vec<u8>> {
+    const INFO_HEADER_SIZE: usize = 40;
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    let info = sk::ImageInfo::new(
+        (image.width(), image.height()),
+        sk::ColorType::BGRA8888,
+        sk::AlphaType::Unpremul,
+        None,
+    );
+    let mut pixels = vec![0u8; width * height * 4];
+    if !image.read_pixels(&info, &mut pixels, width * 4, (0, 0), sk::image::CachingHint::Disallow) {
+        return None;
+    }
+
+    let offset = BMP_FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+    let size = offset + pixels.len();
+    let mut bmp = Vec::with_capacity(size);
+    // BITMAPFILEHEADER
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(size as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&(offset as u32).to_le_bytes());
+    // BITMAPINFOHEADER
+    bmp.extend_from_slice(&(INFO_HEADER_SIZE as u32).to_le_bytes());
+    bmp.extend_from_slice(&(width as i32).to_le_bytes());
+    bmp.extend_from_slice(&(height as i32).to_le_bytes());
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&32u16.to_le_bytes());
+    // BI_RGB, no compression: the rest (image size, resolution, palette) can be zero
+    bmp.extend_from_slice(&[0; 24]);
+    // bottom row first
+    for row in pixels.chunks_exact(width * 4).rev() {
+        bmp.extend_from_slice(row);
+    }
+    Some(bmp)
+}
+
+/// Errors returned when incoming data is rejected by the `DataTransferPolicy`.
+#[derive(Debug, Error)]
+pub enum DataTransferError {
+    #[error("data of type `{type_id}` too large ({size} bytes, the limit is {limit} bytes)")]
+    TooLarge {
+        type_id: &'static str,
+        size: usize,
+        limit: usize,
+    },
+    #[error("image of type `{type_id}` too large ({width}x{height}, the limit is {max_width}x{max_height})")]
+    ImageTooLarge {
+        type_id: &'static str,
+        width: u32,
+        height: u32,
+        max_width: u32,
+        max_height: u32,
+    },
+    #[error("data of type `{type_id}` rejected by sanitizer")]
+    Rejected { type_id: &'static str },
+}
+
+/// Sanitization callback.
+///
+/// Returns the sanitized data, or `None` to reject the data.
+type Sanitizer = Arc<dyn Fn(TypedData) -> Option<TypedData> + Send + Sync>;
+
+/// Size limits and sanitization callbacks applied to the data received from the clipboard or from
+/// drag and drop operations, before it reaches widgets.
+///
+/// The policy is set for the whole application with `set_data_transfer_policy`. By default, no limits are applied.
+///
+/// # Example
+///
+/// ```
+/// use kyute_shell::{set_data_transfer_policy, DataTransferPolicy, TypedData};
+///
+/// set_data_transfer_policy(
+///     DataTransferPolicy::new()
+///         .max_size(16 * 1024 * 1024)
+///         .max_image_dimensions(4096, 4096)
+///         // don't accept HTML at all
+///         .sanitizer("text/html", |_| None),
+/// );
+/// ```
+#[derive(Clone, Default)]
+pub struct DataTransferPolicy {
+    max_size: Option<usize>,
+    max_image_dimensions: Option<(u32, u32)>,
+    /// Sanitizers, with the type (or type prefix) that they apply to.
+    sanitizers: Vec<(String, Sanitizer)>,
+}
+
+impl fmt::Debug for DataTransferPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DataTransferPolicy")
+            .field("max_size", &self.max_size)
+            .field("max_image_dimensions", &self.max_image_dimensions)
+            .field(
+                "sanitizers",
+                &self.sanitizers.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl DataTransferPolicy {
+    /// Creates a policy that accepts all data.
+    pub fn new() -> DataTransferPolicy {
+        DataTransferPolicy::default()
+    }
+
+    /// Sets the maximum size of incoming data, in bytes.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets the maximum dimensions of incoming images (data with a type starting with `image/`), in pixels.
+    ///
+    /// Images that can't be decoded are rejected.
+    pub fn max_image_dimensions(mut self, max_width: u32, max_height: u32) -> Self {
+        self.max_image_dimensions = Some((max_width, max_height));
+        self
+    }
+
+    /// Adds a callback that sanitizes incoming data of the specified type.
+    ///
+    /// `type_id` is either a full type (e.g. `text/html`), or a type prefix ending with `/` (e.g. `image/`).
+    /// The callback returns the sanitized data, or `None` to reject it. Sanitizers run after the size checks,
+    /// in the order they were added.
+    pub fn sanitizer(
+        mut self,
+        type_id: impl Into<String>,
+        sanitizer: impl Fn(TypedData) -> Option<TypedData> + Send + Sync + 'static,
+    ) -> Self {
+        self.sanitizers.push((type_id.into(), Arc::new(sanitizer)));
+        self
+    }
+
+    /// Applies the size limits and sanitizers to incoming data.
+    pub fn apply(&self, data: TypedData) -> Result<TypedData, DataTransferError> {
+        let type_id = data.type_id;
+
+        if let Some(limit) = self.max_size {
+            if data.data.len() > limit {
+                return Err(DataTransferError::TooLarge {
+                    type_id,
+                    size: data.data.len(),
+                    limit,
+                });
+            }
+        }
+
+        if let Some((max_width, max_height)) = self.max_image_dimensions {
+            if type_id.starts_with("image/") {
+                // only decodes the image header
+                let (width, height) = sk::Codec::from_data(sk::Data::new_copy(&data.data))
+                    .map(|codec| {
+                        let size = codec.dimensions();
+                        (size.width.max(0) as u32, size.height.max(0) as u32)
+                    })
+                    .ok_or(DataTransferError::Rejected { type_id })?;
+                if width > max_width || height > max_height {
+                    return Err(DataTransferError::ImageTooLarge {
+                        type_id,
+                        width,
+                        height,
+                        max_width,
+                        max_height,
+                    });
+                }
+            }
+        }
+
+        let mut data = data;
+        for (sanitized_type, sanitizer) in self.sanitizers.iter() {
+            let matches = if sanitized_type.ends_with('/') {
+                type_id.starts_with(sanitized_type.as_str())
+            } else {
+                type_id == sanitized_type
+            };
+            if matches {
+                data = sanitizer(data).ok_or(DataTransferError::Rejected { type_id })?;
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+static DATA_TRANSFER_POLICY: Lazy<RwLock<DataTransferPolicy>> = Lazy::new(Default::default);
+
+/// Sets the policy applied to all data received from the clipboard or from drag and drop operations.
+pub fn set_data_transfer_policy(policy: DataTransferPolicy) {
+    *DATA_TRANSFER_POLICY.write() = policy;
+}
+
+/// Applies the current `DataTransferPolicy` to data received from the clipboard or from a drag and drop operation.
+///
+/// This must be called on all incoming data before it is handed to the application. Returns `None` if the data
+/// was rejected.
+pub fn filter_incoming_data(data: TypedData) -> Option<TypedData> {
+    match DATA_TRANSFER_POLICY.read().apply(data) {
+        Ok(data) => Some(data),
+        Err(err) => {
+            warn!("incoming data rejected: {}", err);
+            None
+        }
+    }
+}
+
+/// Maximum number of entries kept in the clipboard history.
+const HISTORY_LEN: usize = 10;
+
+/// Texts recently placed on the clipboard by the application, most recent first.
+static HISTORY: Lazy<Mutex<VecDeque<String>>> = Lazy::new(Default::default);
+
+/// Places plain text on the system clipboard, replacing its current contents.
+///
+/// The text is also added to the clipboard history (see `history`).
+pub fn set_text(text: &str) -> crate::Result<()> {
+    backend::clipboard::set_text(text)?;
+    let mut history = HISTORY.lock();
+    history.retain(|entry| entry != text);
+    history.push_front(text.to_string());
+    history.truncate(HISTORY_LEN);
+    Ok(())
+}
+
+/// Returns the texts recently copied with `set_text`, most recent first.
+///
+/// This only contains texts copied by this application, not the system clipboard history.
+pub fn history() -> Vec<String> {
+    HISTORY.lock().iter().cloned().collect()
+}
+
+/// Places data on the system clipboard, replacing its current contents.
+///
+/// Each item is a representation of the same contents in a different format (e.g. an HTML fragment and its plain
+/// text, or an image as `PNG` and `BMP`): applications pasting the contents choose the format they prefer.
+/// Plain text is added to the clipboard history (see `history`).
+pub fn set_data(items: &[TypedData]) -> crate::Result<()> {
+    backend::clipboard::set_data(items)?;
+    if let Some(text) = items
+        .iter()
+        .find(|item| item.type_id == TEXT)
+        .and_then(TypedData::as_text)
+    {
+        let mut history = HISTORY.lock();
+        history.retain(|entry| entry != text);
+        history.push_front(text.to_string());
+        history.truncate(HISTORY_LEN);
+    }
+    Ok(())
+}
+
+/// Places an image on the system clipboard, as `PNG` and `BMP`.
+pub fn set_image(image: &sk::Image) -> crate::Result<()> {
+    let items: Vec<_> = vec![TypedData::png(image), TypedData::bmp(image)]
+        .into_iter()
+        .flatten()
+        .collect();
+    set_data(&items)
+}
+
+/// Returns whether the clipboard contains data of the specified type.
+///
+/// See `TypedData` for the supported types.
+pub fn has_data(type_id: &str) -> bool {
+    backend::clipboard::has_data(type_id)
+}
+
+/// Returns the first of the specified types that the clipboard contains, in order of preference.
+///
+/// # Example
+///
+/// ```no_run
+/// use kyute_shell::clipboard;
+///
+/// match clipboard::negotiate(&[clipboard::HTML, clipboard::TEXT]) {
+///     Some(clipboard::HTML) => { /* paste rich text */ }
+///     Some(_) => { /* paste plain text */ }
+///     None => { /* nothing to paste */ }
+/// }
+/// ```
+pub fn negotiate(preferred: &[&'static str]) -> Option<&'static str> {
+    preferred.iter().copied().find(|type_id| has_data(type_id))
+}
+
+/// Returns the data of the first of the specified types that the clipboard contains and that is accepted by the
+/// current `DataTransferPolicy`, in order of preference.
+pub fn best_data(preferred: &[&'static str]) -> Option<TypedData> {
+    preferred
+        .iter()
+        .filter(|type_id| has_data(type_id))
+        .find_map(|type_id| data(type_id))
+}
+
+/// Returns the data of the specified type on the clipboard.
+///
+/// Returns `None` if the clipboard doesn't contain data of this type, or if the data was rejected by the current
+/// `DataTransferPolicy`.
+pub fn data(type_id: &'static str) -> Option<TypedData> {
+    let data = backend::clipboard::data(type_id)?;
+    filter_incoming_data(TypedData { type_id, data })
+}
+
+/// Returns the plain text on the system clipboard.
+///
+/// Returns `None` if the clipboard doesn't contain text, or if the text was rejected by the current
+/// `DataTransferPolicy` (as `text/plain` data).
+pub fn text() -> Option<String> {
+    let text = backend::clipboard::text()?;
+    let data = filter_incoming_data(TypedData {
+        type_id: "text/plain",
+        data: text.into_bytes(),
+    })?;
+    String::from_utf8(data.data).ok()
+}
+
+/// Returns the HTML fragment on the system clipboard.
+pub fn html() -> Option<String> {
+    String::from_utf8(data(HTML)?.data).ok()
+}
+
+/// Returns the image on the system clipboard (e.g. a screenshot), from the `PNG` or `BMP` data.
+pub fn image() -> Option<sk::Image> {
+    best_data(&[PNG, BMP])?.to_image()
+}
+
+/// Returns the files copied to the system clipboard (e.g. from the file explorer).
+pub fn files() -> Option<Vec<PathBuf>> {
+    data(FILE_LIST)?.to_files()
+}
+
+/// Returns the data of files dropped on a window, as a `FILE_LIST`.
+///
+/// Returns `None` if the data was rejected by the current `DataTransferPolicy`.
+pub fn dropped_files(paths: &[impl AsRef<Path>]) -> Option<TypedData> {
+    filter_incoming_data(TypedData::files(paths))
+}
+
+/// Result of a drag and drop operation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DropEffect {
+    /// The drag operation was cancelled, or the data was dropped on a target that didn't accept it.
+    None,
+    /// The data was copied to the drop target.
+    Copy,
+    /// The data was moved to the drop target. The source should remove the original data.
+    Move,
+    /// The drop target created a link to the original data.
+    Link,
+}
+
+bitflags! {
+    /// Set of drop effects allowed by the source of a drag operation.
+    pub struct DropEffects: u32 {
+        const COPY = 1;
+        const MOVE = 2;
+        const LINK = 4;
+    }
+}
+
+impl Default for DropEffects {
+    fn default() -> Self {
+        DropEffects::COPY
+    }
+}
+
+/// Image displayed under the cursor during a drag operation.
+#[derive(Clone, Debug)]
+pub struct DragImage {
+    /// Width of the image in physical pixels.
+    pub width: u32,
+    /// Height of the image in physical pixels.
+    pub height: u32,
+    /// Pixel data, in premultiplied BGRA8 format, top row first, without padding between rows.
+    pub pixels: Vec<u8>,
+    /// Position of the cursor relative to the top-left corner of the image, in physical pixels.
+    pub hotspot_x: i32,
+    pub hotspot_y: i32,
+}
+
+/// Starts an OS drag and drop operation with the specified data.
+///
+/// This function blocks until the data is dropped or the operation is cancelled, and returns the effect
+/// chosen by the drop target. It must be called on the UI thread while a mouse button is pressed.
+pub fn start_drag(data: &[TypedData], allowed_effects: DropEffects, image: Option<&DragImage>) -> DropEffect {
+    backend::clipboard::start_drag(data, allowed_effects, image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_list() {
+        let paths = [PathBuf::from("C:\\Users\\a.txt"), PathBuf::from("/tmp/b c.png")];
+        let data = TypedData::files(&paths);
+        assert_eq!(data.to_files().unwrap(), paths);
+        assert!(TypedData::text("a.txt").to_files().is_none());
+    }
+
+    #[test]
+    fn bmp() {
+        let mut surface = sk::Surface::new_raster_n32_premul((3, 2)).unwrap();
+        surface.canvas().clear(sk::Color::RED);
+        let data = TypedData::bmp(&surface.image_snapshot()).unwrap();
+        assert_eq!(data.data.len(), BMP_FILE_HEADER_SIZE + 40 + 3 * 2 * 4);
+        let image = data.to_image().unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+    }
+}
@@ -0,0 +1,187 @@
+//! Standard system dialogs.
+//!
+//! The dialogs are modal with respect to their parent window, but don't block the caller: they run
+//! on a separate thread and the result is delivered through a future.
+use crate::{
+    backend,
+    text::{FontFamily, FontStyle, FontWeight},
+    window::Window,
+};
+use kyute_common::Color;
+use parking_lot::Mutex;
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+/// A font selected in the font dialog.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontSelection {
+    pub family: FontFamily,
+    /// Font size in points.
+    pub size: f64,
+    pub weight: FontWeight,
+    pub style: FontStyle,
+}
+
+/// A file type filter of a file dialog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileFilter {
+    /// Name of the filter, displayed in the dialog (e.g. "Images").
+    pub name: String,
+    /// File extensions, without the leading dot (e.g. `["png", "jpg"]`). `"*"` matches all files.
+    pub extensions: Vec<String>,
+}
+
+/// Kind of file dialog.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum FileDialogKind {
+    Open,
+    Save,
+    PickFolder,
+}
+
+/// Builder for the system file dialogs: open, save, and folder selection.
+#[derive(Clone, Debug)]
+pub struct FileDialog {
+    pub(crate) kind: FileDialogKind,
+    pub(crate) title: Option<String>,
+    pub(crate) filters: Vec<FileFilter>,
+    pub(crate) directory: Option<PathBuf>,
+    pub(crate) file_name: Option<String>,
+    pub(crate) multi_select: bool,
+}
+
+impl FileDialog {
+    fn new(kind: FileDialogKind) -> FileDialog {
+        FileDialog {
+            kind,
+            title: None,
+            filters: vec![],
+            directory: None,
+            file_name: None,
+            multi_select: false,
+        }
+    }
+
+    /// Creates a dialog that selects existing files to open.
+    pub fn open() -> FileDialog {
+        FileDialog::new(FileDialogKind::Open)
+    }
+
+    /// Creates a dialog that selects the path of a file to save. Asks for confirmation before overwriting a file.
+    pub fn save() -> FileDialog {
+        FileDialog::new(FileDialogKind::Save)
+    }
+
+    /// Creates a dialog that selects an existing folder.
+    pub fn pick_folder() -> FileDialog {
+        FileDialog::new(FileDialogKind::PickFolder)
+    }
+
+    /// Sets the title of the dialog.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Adds a file type filter. The first filter is selected initially.
+    ///
+    /// `extensions` are without the leading dot. In save dialogs, the first extension of the selected filter
+    /// is appended to file names typed without one.
+    pub fn filter(mut self, name: impl Into<String>, extensions: &[&str]) -> Self {
+        self.filters.push(FileFilter {
+            name: name.into(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Sets the folder displayed initially.
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    /// Sets the file name displayed initially.
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Sets whether multiple files or folders can be selected. Ignored by save dialogs.
+    pub fn multi_select(mut self, multi_select: bool) -> Self {
+        self.multi_select = multi_select;
+        self
+    }
+
+    /// Opens the dialog.
+    ///
+    /// The dialog is modal to `parent`, if specified. Resolves to the selected paths (only one unless
+    /// `multi_select` is set), or `None` if the dialog was cancelled.
+    pub fn show(&self, parent: Option<&Window>) -> DialogResult<Vec<PathBuf>> {
+        backend::dialogs::show_file_dialog(parent.map(|w| &w.0), self.clone())
+    }
+}
+
+struct DialogState<T> {
+    result: Option<Option<T>>,
+    waker: Option<Waker>,
+}
+
+/// The future result of a dialog.
+///
+/// Resolves to `None` if the dialog was cancelled.
+pub struct DialogResult<T>(Arc<Mutex<DialogState<T>>>);
+
+impl<T: Send + 'static> DialogResult<T> {
+    /// Runs the specified dialog function on a separate thread.
+    pub(crate) fn spawn(dialog: impl FnOnce() -> Option<T> + Send + 'static) -> DialogResult<T> {
+        let state = Arc::new(Mutex::new(DialogState {
+            result: None,
+            waker: None,
+        }));
+        let thread_state = state.clone();
+        std::thread::spawn(move || {
+            let result = dialog();
+            let mut state = thread_state.lock();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        DialogResult(state)
+    }
+}
+
+impl<T> Future for DialogResult<T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.lock();
+        if let Some(result) = state.result.take() {
+            Poll::Ready(result)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// Opens the system color chooser.
+///
+/// The dialog is modal to `parent`, if specified. Resolves to the selected color, or `None` if the dialog was cancelled.
+/// Alpha is not supported by the system dialog: the selected color is opaque.
+pub fn choose_color(parent: Option<&Window>, initial_color: Option<Color>) -> DialogResult<Color> {
+    backend::dialogs::choose_color(parent.map(|w| &w.0), initial_color)
+}
+
+/// Opens the system font chooser.
+///
+/// The dialog is modal to `parent`, if specified. Resolves to the selected font, or `None` if the dialog was cancelled.
+pub fn choose_font(parent: Option<&Window>, initial_font: Option<&FontSelection>) -> DialogResult<FontSelection> {
+    backend::dialogs::choose_font(parent.map(|w| &w.0), initial_font.cloned())
+}
@@ -0,0 +1,34 @@
+//! Input settings.
+
+/// How the mouse wheel scrolls, as set by the user in the OS settings.
+///
+/// The wheel events delivered to windows are not adjusted by these settings: they report notches (or pixels
+/// for precision touchpads), and it's up to the widgets to convert them to a scroll amount.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScrollSettings {
+    /// Number of lines scrolled by one notch of the wheel, or `None` to scroll by one page.
+    pub lines_per_notch: Option<f64>,
+    /// Number of characters scrolled by one notch of a horizontal wheel (or a tilt of the wheel).
+    pub chars_per_notch: f64,
+    /// Whether the contents follow the movement of the wheel instead of the viewport.
+    ///
+    /// This inverts the direction of the wheel. It's `false` by default on all platforms, since those that
+    /// have a "natural scrolling" option (macOS, precision touchpads on Windows) already invert the deltas
+    /// of the events.
+    pub natural_scrolling: bool,
+    /// Whether the vertical wheel scrolls horizontally while Shift is pressed.
+    ///
+    /// Disabled on macOS, which already delivers horizontal events in this case.
+    pub shift_scrolls_horizontally: bool,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        ScrollSettings {
+            lines_per_notch: Some(3.0),
+            chars_per_notch: 3.0,
+            natural_scrolling: false,
+            shift_scrolls_horizontally: !cfg!(target_os = "macos"),
+        }
+    }
+}
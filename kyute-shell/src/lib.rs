@@ -5,21 +5,27 @@
 #[macro_use]
 extern crate tracing;
 
+pub mod accessibility;
 pub mod animation;
 pub mod application;
 mod backend;
-mod clipboard;
+pub mod clipboard;
+pub mod dialogs;
 pub mod drawing;
 mod error;
+pub mod input;
 mod menu;
 mod shortcut;
 pub mod text;
 pub mod window;
 
-pub use clipboard::TypedData;
+pub use clipboard::{
+    filter_incoming_data, set_data_transfer_policy, DataTransferError, DataTransferPolicy, DragImage, DropEffect,
+    DropEffects, TypedData,
+};
 pub use error::{Error, Result};
 pub use kyute_common::PointI;
-pub use menu::Menu;
+pub use menu::{Menu, MenuIcon, MenuItemDraw, MenuItemState};
 pub use shortcut::{Shortcut, ShortcutKey};
 
 // Re-export winit for WindowBuilder and stuff
@@ -1,5 +1,44 @@
 use crate::{backend, Shortcut};
-use std::mem;
+use kyute_common::Size;
+use skia_safe as sk;
+use std::{fmt, rc::Rc};
+
+/// Bitmap displayed next to the text of a menu item.
+#[derive(Clone, Debug)]
+pub struct MenuIcon {
+    pub width: u32,
+    pub height: u32,
+    /// Premultiplied BGRA pixels, row by row, without padding.
+    pub pixels: Vec<u8>,
+}
+
+/// State of a custom-drawn menu item.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MenuItemState {
+    /// The item is under the pointer or selected with the keyboard.
+    pub highlighted: bool,
+    pub checked: bool,
+    pub disabled: bool,
+}
+
+/// Custom rendering of a menu item.
+#[derive(Clone)]
+pub struct MenuItemDraw {
+    /// Size of the item in DIPs.
+    pub size: Size,
+    /// Paints the item, on top of the background of the menu (or the highlight color if the item is highlighted).
+    ///
+    /// The canvas is scaled so that one unit is one DIP. The second parameter is the size of the item.
+    pub paint: Rc<dyn Fn(&mut sk::Canvas, Size, MenuItemState)>,
+}
+
+impl fmt::Debug for MenuItemDraw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MenuItemDraw")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
 
 pub struct Menu(backend::Menu);
 
@@ -22,6 +61,16 @@ impl Menu {
         self.0.add_item(text, id, shortcut, checked, disabled)
     }
 
+    /// Adds a custom-drawn item (owner-drawn on Windows).
+    pub fn add_custom_item(&mut self, draw: MenuItemDraw, id: usize, checked: bool, disabled: bool) {
+        self.0.add_custom_item(draw, id, checked, disabled)
+    }
+
+    /// Sets the icon displayed next to the text of the item with the specified ID.
+    pub fn set_item_icon(&mut self, id: usize, icon: &MenuIcon) {
+        self.0.set_item_icon(id, icon)
+    }
+
     pub fn add_submenu(&mut self, text: &str, submenu: Menu) {
         self.0.add_submenu(text, submenu.0)
     }
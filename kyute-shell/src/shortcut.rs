@@ -75,6 +75,57 @@ impl ShortcutKey {
         }
     }
 
+    /// Returns the shortcut key corresponding to the given key, if there's one.
+    ///
+    /// Characters are converted to uppercase, and ASCII control characters (as produced by `Ctrl+letter` on
+    /// some platforms) are converted to the corresponding letter.
+    pub fn from_key(key: &keyboard_types::Key) -> Option<ShortcutKey> {
+        let key = match *key {
+            keyboard_types::Key::Character(ref s) => {
+                let mut chars = s.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                let c = match c as u32 {
+                    code @ 0x01..=0x1A => char::from(b'A' + (code - 1) as u8),
+                    _ => c.to_uppercase().next().unwrap_or(c),
+                };
+                ShortcutKey::Character(c)
+            }
+            keyboard_types::Key::Enter => ShortcutKey::Enter,
+            keyboard_types::Key::Tab => ShortcutKey::Tab,
+            keyboard_types::Key::ArrowDown => ShortcutKey::ArrowDown,
+            keyboard_types::Key::ArrowLeft => ShortcutKey::ArrowLeft,
+            keyboard_types::Key::ArrowRight => ShortcutKey::ArrowRight,
+            keyboard_types::Key::ArrowUp => ShortcutKey::ArrowUp,
+            keyboard_types::Key::End => ShortcutKey::End,
+            keyboard_types::Key::Home => ShortcutKey::Home,
+            keyboard_types::Key::PageDown => ShortcutKey::PageDown,
+            keyboard_types::Key::PageUp => ShortcutKey::PageUp,
+            keyboard_types::Key::Backspace => ShortcutKey::Backspace,
+            keyboard_types::Key::Delete => ShortcutKey::Delete,
+            keyboard_types::Key::Insert => ShortcutKey::Insert,
+            keyboard_types::Key::Attn => ShortcutKey::Attn,
+            keyboard_types::Key::Escape => ShortcutKey::Escape,
+            keyboard_types::Key::PrintScreen => ShortcutKey::PrintScreen,
+            keyboard_types::Key::F1 => ShortcutKey::F1,
+            keyboard_types::Key::F2 => ShortcutKey::F2,
+            keyboard_types::Key::F3 => ShortcutKey::F3,
+            keyboard_types::Key::F4 => ShortcutKey::F4,
+            keyboard_types::Key::F5 => ShortcutKey::F5,
+            keyboard_types::Key::F6 => ShortcutKey::F6,
+            keyboard_types::Key::F7 => ShortcutKey::F7,
+            keyboard_types::Key::F8 => ShortcutKey::F8,
+            keyboard_types::Key::F9 => ShortcutKey::F9,
+            keyboard_types::Key::F10 => ShortcutKey::F10,
+            keyboard_types::Key::F11 => ShortcutKey::F11,
+            keyboard_types::Key::F12 => ShortcutKey::F12,
+            _ => return None,
+        };
+        Some(key)
+    }
+
     pub fn to_key(&self) -> keyboard_types::Key {
         match *self {
             ShortcutKey::Character(c) => keyboard_types::Key::Character(c.to_string()),
@@ -185,6 +236,17 @@ impl Shortcut {
     }
 }
 
+impl Shortcut {
+    /// Returns the shortcut corresponding to a keyboard event.
+    ///
+    /// Only the `Ctrl`, `Alt`, `Shift` and `Meta` modifiers are taken into account (the state of lock keys is ignored).
+    pub fn from_key_event(event: &keyboard_types::KeyboardEvent) -> Option<Shortcut> {
+        let modifiers = event.modifiers & (Modifiers::CONTROL | Modifiers::ALT | Modifiers::SHIFT | Modifiers::META);
+        let key = ShortcutKey::from_key(&event.key)?;
+        Some(Shortcut::new(modifiers, key))
+    }
+}
+
 impl fmt::Display for Shortcut {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.modifiers.contains(Modifiers::CONTROL) {
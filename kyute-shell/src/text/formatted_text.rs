@@ -1,4 +1,4 @@
-use crate::text::{resolve_range, Attribute, FontStyle, FontWeight, TextAlignment};
+use crate::text::{resolve_range, Attribute, FontStyle, FontWeight, TextAlignment, TextDecoration, TextLayoutMode};
 use kyute_common::Data;
 use std::{
     cmp::Ordering,
@@ -43,6 +43,30 @@ impl TextRun {
                     found = true;
                     break;
                 }
+                (Attribute::NoWrap, Attribute::NoWrap) => {
+                    found = true;
+                    break;
+                }
+                (Attribute::Underline(u), Attribute::Underline(new_u)) => {
+                    *u = *new_u;
+                    found = true;
+                    break;
+                }
+                (Attribute::Strikethrough(s), Attribute::Strikethrough(new_s)) => {
+                    *s = *new_s;
+                    found = true;
+                    break;
+                }
+                (Attribute::Background(c), Attribute::Background(new_color)) => {
+                    *c = *new_color;
+                    found = true;
+                    break;
+                }
+                (Attribute::Link(target), Attribute::Link(new_target)) => {
+                    *target = new_target.clone();
+                    found = true;
+                    break;
+                }
                 _ => {}
             }
         }
@@ -151,6 +175,7 @@ pub struct ParagraphStyle {
     pub font_weight: Option<FontWeight>,
     pub font_size: Option<f64>,
     pub font_family: Option<String>,
+    pub layout_mode: Option<TextLayoutMode>,
 }
 
 /// Text with formatting information.
@@ -211,6 +236,78 @@ impl FormattedText {
         Arc::make_mut(&mut self.runs).merge_attribute(range, &attribute.into())
     }
 
+    /// Replaces the specified range of characters with the given string.
+    ///
+    /// Attribute runs are adjusted: the inserted text takes the attributes of the text before it
+    /// (or, at the start of the text, of the text after it).
+    pub fn replace_range(&mut self, range: Range<usize>, replace_with: &str) {
+        let mut text = self.plain_text.to_string();
+        text.replace_range(range.clone(), replace_with);
+        self.plain_text = Arc::from(text);
+
+        let shift = |pos: usize| {
+            if pos >= range.end {
+                pos - range.len() + replace_with.len()
+            } else {
+                range.start + replace_with.len()
+            }
+        };
+        let runs = Arc::make_mut(&mut self.runs);
+        for run in runs.runs.iter_mut() {
+            if run.range.start > range.start || (run.range.start == range.start && range.start != 0) {
+                run.range.start = shift(run.range.start);
+            }
+            if run.range.end >= range.start {
+                run.range.end = shift(run.range.end);
+            }
+        }
+        // remove the runs that were entirely replaced
+        runs.runs.retain(|run| !run.range.is_empty());
+    }
+
+    /// Prevents line breaks within the specified range of characters.
+    pub fn set_no_wrap(&mut self, range: impl RangeBounds<usize>) {
+        self.add_attribute(range, Attribute::NoWrap)
+    }
+
+    /// Returns the ranges of text where line breaks are disallowed.
+    pub fn no_wrap_ranges(&self) -> Vec<Range<usize>> {
+        self.runs
+            .runs
+            .iter()
+            .filter(|run| run.attributes.contains(&Attribute::NoWrap))
+            .map(|run| run.range.clone())
+            .collect()
+    }
+
+    /// Makes the range of characters a link to the specified target, and underlines it.
+    pub fn set_link(&mut self, range: impl RangeBounds<usize>, target: impl Into<Arc<str>>) {
+        let range = resolve_range(range, self.plain_text.len());
+        self.add_attribute(range.clone(), Attribute::Link(target.into()));
+        self.add_attribute(range, Attribute::Underline(TextDecoration::default()));
+    }
+
+    /// Returns the ranges of text that are links, and their targets.
+    pub fn links(&self) -> Vec<(Range<usize>, Arc<str>)> {
+        let mut links: Vec<(Range<usize>, Arc<str>)> = vec![];
+        for run in self.runs.runs.iter() {
+            let target = run.attributes.iter().find_map(|attr| match attr {
+                Attribute::Link(target) => Some(target),
+                _ => None,
+            });
+            if let Some(target) = target {
+                // the range of a link can be split in several runs by other attributes
+                match links.last_mut() {
+                    Some((range, last_target)) if range.end == run.range.start && last_target == target => {
+                        range.end = run.range.end;
+                    }
+                    _ => links.push((run.range.clone(), target.clone())),
+                }
+            }
+        }
+        links
+    }
+
     /// Sets the font size.
     pub fn set_font_size(&mut self, font_size: f64) {
         self.paragraph_style.font_size = Some(font_size);
@@ -259,6 +356,10 @@ pub trait FormattedTextExt {
     fn text_alignment(self, alignment: TextAlignment) -> FormattedText;
     /// Returns a new formatted text object with the specified attribute applied on the range of characters.
     fn attribute(self, range: impl RangeBounds<usize>, attribute: impl Into<Attribute>) -> FormattedText;
+    /// Returns a new formatted text object where line breaks are disallowed in the range of characters.
+    fn no_wrap(self, range: impl RangeBounds<usize>) -> FormattedText;
+    /// Returns a new formatted text object where the range of characters is a link to the specified target.
+    fn link(self, range: impl RangeBounds<usize>, target: impl Into<Arc<str>>) -> FormattedText;
 }
 
 impl<T> FormattedTextExt for T
@@ -288,6 +389,14 @@ where
     fn attribute(mut self, range: impl RangeBounds<usize>, attribute: impl Into<Attribute>) -> FormattedText {
         FormattedText::new(self.into()).attribute(range, attribute)
     }
+
+    fn no_wrap(self, range: impl RangeBounds<usize>) -> FormattedText {
+        FormattedText::new(self.into()).no_wrap(range)
+    }
+
+    fn link(self, range: impl RangeBounds<usize>, target: impl Into<Arc<str>>) -> FormattedText {
+        FormattedText::new(self.into()).link(range, target)
+    }
 }
 
 impl FormattedTextExt for FormattedText {
@@ -320,4 +429,14 @@ impl FormattedTextExt for FormattedText {
         self.add_attribute(range, attribute);
         self
     }
+
+    fn no_wrap(mut self, range: impl RangeBounds<usize>) -> FormattedText {
+        self.set_no_wrap(range);
+        self
+    }
+
+    fn link(mut self, range: impl RangeBounds<usize>, target: impl Into<Arc<str>>) -> FormattedText {
+        self.set_link(range, target);
+        self
+    }
 }
@@ -4,7 +4,7 @@ mod paragraph;
 pub use formatted_text::{FormattedText, FormattedTextExt, ParagraphStyle};
 pub use paragraph::{
     GlyphRun, GlyphRunAnalysis, GlyphRunDrawingEffects, HitTestMetrics, HitTestPoint, HitTestTextPosition, LineMetrics,
-    Paragraph, Renderer, TextMetrics,
+    Paragraph, Renderer, TextMetrics, TextOutline, TextShadow,
 };
 
 use kyute_common::{Color, Data, SizeI};
@@ -64,6 +64,54 @@ pub(crate) fn resolve_range(range: impl RangeBounds<usize>, len: usize) -> Range
     start.min(len)..end.min(len)
 }
 
+/// Characters that control line breaking within a paragraph.
+///
+/// They can be inserted in the text of a `FormattedText` to tweak where the paragraph layout is allowed
+/// to break lines. To prevent breaks over a whole range of text, see `Attribute::NoWrap`.
+pub mod line_break {
+    /// A space that never breaks (U+00A0).
+    pub const NO_BREAK_SPACE: char = '\u{00A0}';
+    /// An invisible hyphenation point (U+00AD). If a line breaks there, a hyphen is drawn at the end of the line.
+    pub const SOFT_HYPHEN: char = '\u{00AD}';
+    /// A hyphen that never breaks (U+2011).
+    pub const NON_BREAKING_HYPHEN: char = '\u{2011}';
+    /// An invisible break opportunity (U+200B).
+    pub const ZERO_WIDTH_SPACE: char = '\u{200B}';
+    /// An invisible character that prevents a break between its neighbors (U+2060).
+    pub const WORD_JOINER: char = '\u{2060}';
+}
+
+/// Returns the non-breaking equivalent of a character, if it is a line break opportunity.
+///
+/// Substitutes are always encoded with the same number of UTF-16 code units as the original character.
+fn non_breaking_equivalent(c: char) -> Option<char> {
+    match c {
+        ' ' => Some(line_break::NO_BREAK_SPACE),
+        '-' | '\u{2010}' => Some(line_break::NON_BREAKING_HYPHEN),
+        line_break::SOFT_HYPHEN | line_break::ZERO_WIDTH_SPACE => Some(line_break::WORD_JOINER),
+        _ => None,
+    }
+}
+
+/// Converts text to UTF-16, replacing the break opportunities within the specified ranges by their
+/// non-breaking equivalents.
+///
+/// Text positions in the returned string are the same as in the UTF-16 encoding of the original text.
+/// Ranges are expressed in UTF-8 code units (bytes).
+pub(crate) fn to_utf16_no_wrap(text: &str, no_wrap_ranges: &[Range<usize>]) -> Vec<u16> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut buf = [0u16; 2];
+    for (i, c) in text.char_indices() {
+        let c = if no_wrap_ranges.iter().any(|r| r.contains(&i)) {
+            non_breaking_equivalent(c).unwrap_or(c)
+        } else {
+            c
+        };
+        out.extend_from_slice(c.encode_utf16(&mut buf));
+    }
+    out
+}
+
 /// Describes a font weight.
 ///
 /// It is a value between 1 and 1000, based on the CSS [`font-weight`](https://developer.mozilla.org/en-US/docs/Web/CSS/font-weight) property.
@@ -139,6 +187,64 @@ impl Default for TextAlignment {
     }
 }
 
+/// How text is laid out and rendered.
+///
+/// Paragraphs are always laid out with the ideal (resolution-independent) metrics of the fonts: glyph advances are
+/// fractional and don't depend on the scale factor, so a paragraph created with the same text, style and layout box
+/// breaks lines at the same positions on screen and in print or export output. The modes only differ in how the glyphs
+/// are rendered.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Data)]
+pub enum TextLayoutMode {
+    /// Glyph outlines are hinted (grid-fitted) and glyph runs are snapped to pixels, for legibility on screen.
+    Screen,
+    /// Glyphs are rendered without hinting nor pixel snapping, at their exact fractional positions, so that
+    /// on-screen text matches print output. Text may look blurrier at small sizes.
+    Print,
+}
+
+impl Default for TextLayoutMode {
+    fn default() -> Self {
+        TextLayoutMode::Screen
+    }
+}
+
+/// Style of the lines drawn under or through text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Data)]
+pub enum LineStyle {
+    Solid,
+    Dotted,
+    Dashed,
+    /// A wavy line, e.g. for spell-check errors.
+    Wavy,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle::Solid
+    }
+}
+
+/// A line drawn under or through text (see `Attribute::Underline` and `Attribute::Strikethrough`).
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TextDecoration {
+    pub style: LineStyle,
+    /// Color of the line. If `None`, the line has the color of the text.
+    pub color: Option<Color>,
+}
+
+impl TextDecoration {
+    /// Creates a line with the specified style, with the color of the text.
+    pub fn new(style: LineStyle) -> TextDecoration {
+        TextDecoration { style, color: None }
+    }
+
+    /// Sets the color of the line.
+    pub fn with_color(mut self, color: Color) -> TextDecoration {
+        self.color = Some(color);
+        self
+    }
+}
+
 /// Attributes that can be applied to text.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Attribute {
@@ -152,6 +258,19 @@ pub enum Attribute {
     FontWeight(FontWeight),
     /// Color.
     Color(Color),
+    /// Prevents line breaks within the range of text.
+    NoWrap,
+    /// Underlines the range of text.
+    Underline(TextDecoration),
+    /// Draws a line through the range of text.
+    Strikethrough(TextDecoration),
+    /// Fills the background of the range of text, e.g. to highlight search results.
+    Background(Color),
+    /// Makes the range of text a link to the specified target (e.g. a URL).
+    ///
+    /// This doesn't change the appearance of the text (see `FormattedText::set_link`). Links are activated by clicking
+    /// on them in a `Text` widget.
+    Link(Arc<str>),
 }
 
 impl From<FontFamily> for Attribute {
@@ -235,7 +354,7 @@ pub struct GlyphMaskData {
 mod tests {
     use super::{
         formatted_text::{TextRun, TextRuns},
-        Attribute, FontStyle, FontWeight,
+        Attribute, FontStyle, FontWeight, LineStyle, TextDecoration,
     };
 
     #[test]
@@ -301,4 +420,31 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_merge_decorations() {
+        let mut tr = TextRuns { runs: vec![] };
+        tr.merge_attribute(0..4, &Attribute::Underline(TextDecoration::default()));
+        tr.merge_attribute(0..4, &Attribute::Underline(TextDecoration::new(LineStyle::Wavy)));
+        tr.merge_attribute(0..4, &Attribute::Strikethrough(TextDecoration::default()));
+        assert_eq!(
+            tr.runs,
+            vec![TextRun {
+                range: 0..4,
+                attributes: vec![
+                    Attribute::Underline(TextDecoration::new(LineStyle::Wavy)),
+                    Attribute::Strikethrough(TextDecoration::default())
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_no_wrap_substitution() {
+        let text = "a-b c\u{AD}d e";
+        let out = super::to_utf16_no_wrap(text, &[0..7]);
+        let expected: Vec<u16> = "a\u{2011}b\u{A0}c\u{2060}d e".encode_utf16().collect();
+        assert_eq!(out, expected);
+        assert_eq!(out.len(), text.encode_utf16().count());
+    }
 }
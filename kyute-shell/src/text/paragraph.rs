@@ -1,9 +1,9 @@
 use crate::{
     backend,
-    text::{FormattedText, GlyphMaskData, ParagraphStyle, RasterizationOptions, TextPosition},
+    text::{FormattedText, GlyphMaskData, LineStyle, ParagraphStyle, RasterizationOptions, TextPosition},
     Error,
 };
-use kyute_common::{Color, Data, Point, Rect, RectI, Size, Transform};
+use kyute_common::{Color, Data, Offset, Point, Rect, RectI, Size, Transform};
 use std::ops::Range;
 
 /// Text hit-test metrics.
@@ -80,11 +80,35 @@ impl GlyphRunAnalysis {
     }
 }
 
+/// A shadow drawn behind text.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextShadow {
+    /// Offset of the shadow relative to the text, in DIPs.
+    pub offset: Offset,
+    /// Blur radius in DIPs. Zero for a sharp shadow.
+    pub blur_radius: f64,
+    /// Color of the shadow.
+    pub color: Color,
+}
+
+/// An outline drawn around the glyphs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextOutline {
+    /// Width of the outline in DIPs.
+    pub width: f64,
+    /// Color of the outline.
+    pub color: Color,
+}
+
 /// Drawing parameters passed to `draw_glyph_run`.
 #[derive(Clone, Debug)]
 pub struct GlyphRunDrawingEffects {
     /// The color of the glyph run.
     pub color: Color,
+    /// Shadow drawn behind the glyph run.
+    pub shadow: Option<TextShadow>,
+    /// Outline drawn around the glyphs.
+    pub outline: Option<TextOutline>,
     // TODO application-defined drawing effects
 }
 
@@ -92,6 +116,8 @@ impl Default for GlyphRunDrawingEffects {
     fn default() -> Self {
         GlyphRunDrawingEffects {
             color: Color::new(0.0, 0.0, 0.0, 1.0),
+            shadow: None,
+            outline: None,
         }
     }
 }
@@ -102,6 +128,17 @@ pub trait Renderer {
     // TODO error handling?
     fn draw_glyph_run(&mut self, glyph_run: &GlyphRun, drawing_effects: &GlyphRunDrawingEffects);
 
+    /// Draw an underline or a strikethrough line.
+    ///
+    /// `rect` is the bounds of a solid line in DIPs, before the text transformation: its height is the thickness
+    /// of the line. Wavy lines can extend above and below.
+    fn draw_line(&mut self, rect: Rect, style: LineStyle, color: Color);
+
+    /// Fill the background of a range of text (see `Attribute::Background`).
+    ///
+    /// Called before drawing the glyph runs. `rect` is in DIPs, before the text transformation.
+    fn draw_background(&mut self, rect: Rect, color: Color);
+
     /// Returns the current text transformation.
     fn transform(&self) -> Transform;
 
@@ -134,6 +171,20 @@ impl Paragraph {
         self.0.max_size()
     }
 
+    /// Changes the layout maximum size.
+    ///
+    /// This is much cheaper than creating a new paragraph, since the text is not shaped again: only the line breaks
+    /// are recomputed. Note that clones of a paragraph share the same layout, and are also affected.
+    pub fn set_max_size(&mut self, size: Size) {
+        self.0.set_max_size(size)
+    }
+
+    /// Returns the width of the widest unbreakable sequence of the paragraph (i.e. its width when broken at
+    /// every opportunity).
+    pub fn min_width(&self) -> f64 {
+        self.0.min_width()
+    }
+
     pub fn hit_test_text_position(&self, text_position: TextPosition) -> HitTestTextPosition {
         self.0.hit_test_text_position(text_position)
     }
@@ -152,7 +203,8 @@ impl Paragraph {
 
     /// Draws the paragraph with the specified renderer.
     ///
-    /// This function calls `draw_glyph_run` on the provided renderer for each glyph run in the paragraph.
+    /// This function calls `draw_glyph_run` on the provided renderer for each glyph run in the paragraph, after
+    /// `draw_background` for the ranges of text with a background color.
     pub fn draw(
         &self,
         origin: Point,
@@ -1,15 +1,151 @@
 //! window creation
-use crate::{animation::Layer, application::Application, backend, error::Error, Menu};
-use kyute_common::{PointI, Size, SizeI};
+use crate::{accessibility::AccessibleTree, animation::Layer, application::Application, backend, error::Error, Menu};
+use kyute_common::{Point, PointI, Rect, RectI, Size, SizeI};
 use raw_window_handle::HasRawWindowHandle;
-use std::ptr;
+use std::{ptr, time::Duration};
 use winit::{
+    dpi::{LogicalPosition, PhysicalPosition},
     event_loop::EventLoopWindowTarget,
     window::{CursorIcon, WindowBuilder, WindowId},
 };
 
+/// An edge or a corner of a window, by which it can be resized.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ResizeEdge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ResizeEdge {
+    /// Returns the cursor shown when the pointer is over this edge.
+    pub fn cursor_icon(self) -> CursorIcon {
+        match self {
+            ResizeEdge::Left | ResizeEdge::Right => CursorIcon::EwResize,
+            ResizeEdge::Top | ResizeEdge::Bottom => CursorIcon::NsResize,
+            ResizeEdge::TopLeft | ResizeEdge::BottomRight => CursorIcon::NwseResize,
+            ResizeEdge::TopRight | ResizeEdge::BottomLeft => CursorIcon::NeswResize,
+        }
+    }
+}
+
+/// Part of a window with custom decorations, as reported to the system when it asks what's under the pointer.
+///
+/// This determines how the system reacts to the pointer: for instance, dragging the caption moves the window,
+/// and double-clicking it maximizes the window.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum WindowPart {
+    /// The client area: input is delivered to the application.
+    Client,
+    /// The title bar.
+    Caption,
+    MinimizeButton,
+    /// The maximize button. Reporting it lets the system show the snap layouts flyout on Windows 11.
+    MaximizeButton,
+    CloseButton,
+    /// A resize border or corner.
+    Border(ResizeEdge),
+}
+
+/// Custom decorations of a borderless window.
+///
+/// When a window has custom decorations, its whole area is client area, and the application is responsible for
+/// drawing the title bar and the buttons. The parts of the window that should behave like decorations are
+/// described by a list of regions, and by the thickness of the resize grips along the edges.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CustomFrame {
+    /// Thickness of the resize grips along the edges of the window, in DIPs.
+    ///
+    /// The grips at the corners extend twice as far along the edges, so that they are easier to grab.
+    pub resize_border: f64,
+    /// Regions of the window that act as decorations, in DIPs relative to the client area.
+    ///
+    /// When regions overlap, the last one takes precedence.
+    pub regions: Vec<(Rect, WindowPart)>,
+}
+
+impl CustomFrame {
+    /// Returns the part of the window under `position`, in DIPs relative to the client area of size `size`.
+    ///
+    /// The resize grips take precedence over the regions, but they are ignored if `resizable` is false
+    /// (e.g. when the window is maximized).
+    pub fn hit_test(&self, position: Point, size: Size, resizable: bool) -> WindowPart {
+        if resizable && self.resize_border > 0.0 {
+            let border = self.resize_border;
+            let corner = 2.0 * border;
+            let left = position.x < border;
+            let right = position.x >= size.width - border;
+            let top = position.y < border;
+            let bottom = position.y >= size.height - border;
+            let near_left = position.x < corner;
+            let near_right = position.x >= size.width - corner;
+            let near_top = position.y < corner;
+            let near_bottom = position.y >= size.height - corner;
+
+            let edge = if (top && near_left) || (left && near_top) {
+                Some(ResizeEdge::TopLeft)
+            } else if (top && near_right) || (right && near_top) {
+                Some(ResizeEdge::TopRight)
+            } else if (bottom && near_left) || (left && near_bottom) {
+                Some(ResizeEdge::BottomLeft)
+            } else if (bottom && near_right) || (right && near_bottom) {
+                Some(ResizeEdge::BottomRight)
+            } else if left {
+                Some(ResizeEdge::Left)
+            } else if right {
+                Some(ResizeEdge::Right)
+            } else if top {
+                Some(ResizeEdge::Top)
+            } else if bottom {
+                Some(ResizeEdge::Bottom)
+            } else {
+                None
+            };
+
+            if let Some(edge) = edge {
+                return WindowPart::Border(edge);
+            }
+        }
+
+        self.regions
+            .iter()
+            .rev()
+            .find(|(rect, _)| rect.contains(position))
+            .map(|&(_, part)| part)
+            .unwrap_or(WindowPart::Client)
+    }
+}
+
+/// A static cursor image (see `Window::set_cursor_image`).
+#[derive(Clone, Debug)]
+pub struct CursorImage {
+    /// Width of the image in physical pixels.
+    pub width: u32,
+    /// Height of the image in physical pixels.
+    pub height: u32,
+    /// Pixel data, in premultiplied BGRA8 format, top row first, without padding between rows.
+    pub pixels: Vec<u8>,
+    /// Position of the pointer relative to the top-left corner of the image, in physical pixels.
+    pub hotspot_x: i32,
+    pub hotspot_y: i32,
+}
+
+/// Returns the work area of the monitor containing the specified point (or of the nearest monitor),
+/// in physical pixels relative to the desktop.
+///
+/// The work area is the part of the monitor not covered by the taskbar and docked toolbars. Popups and menus
+/// should stay inside it.
+pub fn monitor_work_area(point: PointI) -> Option<RectI> {
+    backend::monitor_work_area(point)
+}
+
 /// Encapsulates a window and associated resources for drawing to it.
-pub struct Window(backend::Window);
+pub struct Window(pub(crate) backend::Window);
 
 impl Window {
     /*/// Returns the underlying winit [`Window`].
@@ -32,6 +168,19 @@ impl Window {
         self.0.set_menu(new_menu.map(Menu::into_inner))
     }
 
+    /// Converts a point in the client area of the window to desktop coordinates, both in physical pixels.
+    pub fn client_to_screen(&self, point: PointI) -> PointI {
+        self.0.client_to_screen(point)
+    }
+
+    /// Returns the current position of the cursor relative to the client area of the window, in physical pixels.
+    ///
+    /// Unlike the position reported by cursor events, this is also valid while the cursor is captured by another
+    /// window (e.g. during a drag and drop operation).
+    pub fn cursor_position(&self) -> Option<PointI> {
+        self.0.cursor_position()
+    }
+
     /// Shows a context menu at the specified pixel location.
     pub fn show_context_menu(&self, menu: Menu, at: PointI) {
         self.0.show_context_menu(menu.into_inner(), at);
@@ -46,6 +195,63 @@ impl Window {
         self.0.set_root_composition_layer(&layer.0);
     }
 
+    /// Requests a redraw of the window: a `RedrawRequested` event will be emitted for this window.
+    pub fn request_redraw(&self) {
+        self.0.window().request_redraw()
+    }
+
+    /// Shows or hides the window.
+    pub fn set_visible(&self, visible: bool) {
+        self.0.window().set_visible(visible)
+    }
+
+    /// Brings the window to the front and gives it the input focus.
+    pub fn focus(&self) {
+        self.0.window().focus_window()
+    }
+
+    /// Returns the position of the top-left corner of the window, including decorations, in physical pixels
+    /// relative to the desktop.
+    pub fn outer_position(&self) -> Option<PointI> {
+        let position = self.0.window().outer_position().ok()?;
+        Some(PointI::new(position.x, position.y))
+    }
+
+    /// Moves the window so that its top-left corner, including decorations, is at the specified position
+    /// in physical pixels relative to the desktop.
+    pub fn set_outer_position(&self, position: PointI) {
+        self.0
+            .window()
+            .set_outer_position(PhysicalPosition::new(position.x, position.y))
+    }
+
+    /// Returns the size of the window, including decorations, in physical pixels.
+    pub fn outer_size(&self) -> SizeI {
+        let size = self.0.window().outer_size();
+        SizeI::new(size.width as i32, size.height as i32)
+    }
+
+    /// Shows or hides the pointer cursor when it is over the window.
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.0.window().set_cursor_visible(visible)
+    }
+
+    /// Enables or disables input method editors (IME) for the window.
+    ///
+    /// When enabled, the window receives `Ime` events instead of (some) keyboard input.
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.0.window().set_ime_allowed(allowed)
+    }
+
+    /// Sets the position of the IME candidate window, in DIPs relative to the client area.
+    ///
+    /// This should be the position of the text caret.
+    pub fn set_ime_position(&self, position: Point) {
+        self.0
+            .window()
+            .set_ime_position(LogicalPosition::new(position.x, position.y))
+    }
+
     /// Returns the scale factor.
     pub fn scale_factor(&self) -> f64 {
         self.0.scale_factor()
@@ -61,10 +267,58 @@ impl Window {
         self.0.physical_inner_size()
     }
 
+    /// Sets the cursor icon. This replaces the cursor image set with `set_cursor_image`.
     pub fn set_cursor_icon(&mut self, cursor_icon: CursorIcon) {
         self.0.set_cursor_icon(cursor_icon)
     }
 
+    /// Shows an image as the cursor when the pointer is over the client area, until the next call to
+    /// `set_cursor_icon`.
+    pub fn set_cursor_image(&mut self, image: &CursorImage) {
+        self.0.set_cursor_image(image)
+    }
+
+    /// Sets the custom decorations of the window, or restores the system decorations if `None`.
+    ///
+    /// This is intended for windows created without decorations (`WindowBuilder::with_decorations(false)`).
+    /// See `CustomFrame`.
+    pub fn set_custom_frame(&self, frame: Option<CustomFrame>) {
+        self.0.set_custom_frame(frame)
+    }
+
+    /// Sets the minimum and maximum sizes of the _client area_ in DIPs, enforced during interactive resizing.
+    pub fn set_size_limits(&self, min: Option<Size>, max: Option<Size>) {
+        self.0.set_size_limits(min, max)
+    }
+
+    /// Returns whether the user is moving or resizing the window interactively (e.g. by dragging its border).
+    ///
+    /// The window is redrawn once the user releases the border.
+    pub fn is_in_size_move(&self) -> bool {
+        self.0.is_in_size_move()
+    }
+
+    /// Requests a redraw of the window after the specified delay. Replaces the previous delayed redraw request, if
+    /// it hasn't fired yet.
+    pub fn request_redraw_after(&self, delay: Duration) {
+        self.0.request_redraw_after(delay)
+    }
+
+    /// Replaces the accessibility tree of the window, which is exposed to assistive technologies through the
+    /// platform accessibility API. Assistive technologies are notified of the changes.
+    ///
+    /// See `accessibility`.
+    pub fn set_accessibility_tree(&self, tree: AccessibleTree) {
+        self.0.set_accessibility_tree(tree)
+    }
+
+    /// Returns whether an assistive technology has asked for the accessibility tree of the window.
+    ///
+    /// Until then, applications don't need to keep the tree up to date.
+    pub fn is_accessibility_connected(&self) -> bool {
+        self.0.is_accessibility_connected()
+    }
+
     /// Creates a new window from the options given in the provided [`WindowBuilder`].
     ///
     /// To create the window with an OpenGL context, `with_gl` should be `true`.
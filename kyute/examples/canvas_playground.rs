@@ -73,14 +73,8 @@ fn canvas_playground() -> impl Widget + Clone {
     let add_comment_action = Action::new().on_triggered(|| eprintln!("add comment"));
 
     let context_menu = Menu::new(vec![
-        MenuItem::Action {
-            text: "Add Node".to_string(),
-            action: add_node_action,
-        },
-        MenuItem::Action {
-            text: "Add Comment".to_string(),
-            action: add_comment_action,
-        },
+        MenuItem::new("Add Node", add_node_action),
+        MenuItem::new("Add Comment", add_comment_action),
     ]);
 
     let context_menu_area = Container::new(ContextMenu::new(context_menu, drag_controller))
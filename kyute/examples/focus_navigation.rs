@@ -0,0 +1,60 @@
+use kyute::{
+    application, composable,
+    shell::winit::{dpi::LogicalSize, window::WindowBuilder},
+    widget::{Button, FocusScope, Grid, Text, TextField, WidgetExt},
+    UnitExt, Widget, Window,
+};
+
+/// A keypad: the arrow keys move the focus between the keys.
+#[composable]
+fn keypad() -> impl Widget {
+    let mut grid = Grid::with_template("32px 32px 32px / 48px 48px 48px");
+    for i in 1..=9 {
+        kyute::cache::scoped(i, || {
+            grid.insert(Button::new(i.to_string()));
+        });
+    }
+    FocusScope::new(grid).arrow_keys(true)
+}
+
+/// A form that traps `Tab` navigation, like a dialog. The "OK" button comes first in tab order.
+#[composable]
+fn form() -> impl Widget {
+    let mut grid = Grid::with_template("auto auto auto auto / 1fr");
+    grid.insert((
+        TextField::new("Name", "").padding(2.dip()),
+        TextField::new("Address", "").padding(2.dip()),
+        Button::new("Cancel").padding(2.dip()),
+        Button::new("OK").tab_index(1).padding(2.dip()),
+    ));
+    FocusScope::new(grid).trap_focus(true)
+}
+
+#[composable]
+fn ui_root() -> Window {
+    let mut grid = Grid::with_template("auto 1fr / 1fr 1fr");
+    grid.insert((
+        Text::new("Keypad (arrow keys)"),
+        Text::new("Form (Tab stays inside)"),
+        keypad(),
+        form(),
+    ));
+
+    Window::new(
+        WindowBuilder::new()
+            .with_title("Focus navigation")
+            .with_inner_size(LogicalSize::new(480, 200)),
+        grid.padding(8.dip()).frame(100.percent(), 100.percent()),
+        None,
+    )
+}
+
+fn main() {
+    tracing_subscriber::fmt()
+        .compact()
+        .with_target(false)
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    application::run(ui_root);
+}
@@ -23,8 +23,12 @@ mod checkbox;
 mod forms;
 mod grids;
 mod group_box;
+mod progress;
+mod slider;
 mod stepper;
 mod table;
+mod tabs;
+mod tree_view;
 
 /// A 3-element application scaffolding: a sidebar on the left, a toolbar on the top and the rest is the content area.
 #[derive(Widget)]
@@ -84,8 +88,12 @@ enum GalleryWidget {
     GroupBox,
     TextInput,
     TitledPanes,
+    Table,
     TreeView,
     Steppers,
+    Sliders,
+    ProgressBars,
+    Tabs,
     Checkboxes,
 }
 
@@ -117,6 +125,12 @@ fn root_view() -> impl Widget + Clone {
     items.insert(gallery_item("Home", GalleryWidget::Home, &mut selected));
     items.insert(gallery_item("Buttons", GalleryWidget::Buttons, &mut selected));
     items.insert(gallery_item("Steppers", GalleryWidget::Steppers, &mut selected));
+    items.insert(gallery_item("Sliders", GalleryWidget::Sliders, &mut selected));
+    items.insert(gallery_item(
+        "Progress bars",
+        GalleryWidget::ProgressBars,
+        &mut selected,
+    ));
     items.insert(gallery_item(
         "Formatted Text",
         GalleryWidget::FormattedText,
@@ -130,6 +144,8 @@ fn root_view() -> impl Widget + Clone {
     items.insert(gallery_item("Context menu", GalleryWidget::ContextMenu, &mut selected));
     items.insert(gallery_item("Titled panes", GalleryWidget::TitledPanes, &mut selected));
     items.insert(gallery_item("Text input", GalleryWidget::TextInput, &mut selected));
+    items.insert(gallery_item("Table", GalleryWidget::Table, &mut selected));
+    items.insert(gallery_item("Tabs", GalleryWidget::Tabs, &mut selected));
     items.insert(gallery_item("Tree view", GalleryWidget::TreeView, &mut selected));
     items.insert(Checkbox::new(dark_mode).on_toggled(|v| dark_mode = v));
 
@@ -139,6 +155,8 @@ fn root_view() -> impl Widget + Clone {
         GalleryWidget::FormattedText => ("FormattedText", gallery_showcase_unimplemented("Formatted text")),
         GalleryWidget::Buttons => ("Buttons", gallery_showcase_unimplemented("Buttons")),
         GalleryWidget::Steppers => ("Steppers", stepper::showcase()),
+        GalleryWidget::Sliders => ("Sliders", slider::showcase()),
+        GalleryWidget::ProgressBars => ("ProgressBars", progress::showcase()),
         GalleryWidget::Forms => ("Forms", forms::showcase()),
         GalleryWidget::GroupBox => ("GroupBox", group_box::showcase()),
         GalleryWidget::DropDown => ("DropDown", gallery_showcase_unimplemented("Drop-downs")),
@@ -146,7 +164,9 @@ fn root_view() -> impl Widget + Clone {
         GalleryWidget::Grids => ("Grids", gallery_showcase_unimplemented("Grids")),
         GalleryWidget::TextInput => ("TextInput", gallery_showcase_unimplemented("Text input")),
         GalleryWidget::TitledPanes => ("TitledPanes", gallery_showcase_unimplemented("Titled panes")),
-        GalleryWidget::TreeView => ("TreeView", table::showcase()),
+        GalleryWidget::Table => ("Table", table::showcase()),
+        GalleryWidget::Tabs => ("Tabs", tabs::showcase()),
+        GalleryWidget::TreeView => ("TreeView", tree_view::showcase()),
         GalleryWidget::Checkboxes => ("Checkboxes", checkbox::showcase()),
     };
 
@@ -0,0 +1,23 @@
+use kyute::{
+    composable,
+    widget::{grid::TrackBreadth, Grid, ProgressBar, Slider, Spinner, Text, WidgetPod},
+    UnitExt, Widget,
+};
+use std::sync::Arc;
+
+#[composable]
+pub fn showcase() -> Arc<WidgetPod> {
+    #[state]
+    let mut progress = 0.3;
+
+    let mut column = Grid::column(TrackBreadth::Auto);
+    column.set_row_gap(8.dip());
+    column.insert(Text::new(format!("Progress: {:.0}%", progress * 100.0)));
+    column.insert(Slider::new(0.0, 1.0, progress).on_value_changed(|value| progress = value));
+    column.insert(ProgressBar::new(progress));
+    column.insert(Text::new("Indeterminate:"));
+    column.insert(ProgressBar::indeterminate());
+    column.insert(Text::new("Spinner:"));
+    column.insert(Spinner::new().size(24.0));
+    Arc::new(WidgetPod::new(column))
+}
@@ -0,0 +1,41 @@
+use kyute::{
+    composable,
+    widget::{grid::TrackBreadth, Grid, RangeSlider, Slider, Text, WidgetPod},
+    Orientation, UnitExt, Widget,
+};
+use std::sync::Arc;
+
+#[composable]
+pub fn showcase() -> Arc<WidgetPod> {
+    #[state]
+    let mut volume = 50.0;
+    #[state]
+    let mut price_range = (20.0, 80.0);
+    #[state]
+    let mut opacity = 1.0;
+
+    let mut column = Grid::column(TrackBreadth::Auto);
+    column.set_row_gap(8.dip());
+    column.insert(Text::new(format!("Volume: {}", volume)));
+    column.insert(
+        Slider::new(0.0, 100.0, volume)
+            .step(1.0)
+            .ticks(10.0)
+            .on_value_changed(|value| volume = value),
+    );
+    column.insert(Text::new(format!("Price: {} to {}", price_range.0, price_range.1)));
+    column.insert(
+        RangeSlider::new(0.0, 100.0, price_range)
+            .step(5.0)
+            .labeled_ticks(25.0, |value| format!("{}", value))
+            .on_range_changed(|range| price_range = range),
+    );
+    column.insert(Text::new(format!("Opacity: {:.2}", opacity)));
+    column.insert(
+        Slider::new(0.0, 1.0, opacity)
+            .orientation(Orientation::Vertical)
+            .labeled_ticks(0.25, |value| format!("{}%", value * 100.0))
+            .on_value_changed(|value| opacity = value),
+    );
+    Arc::new(WidgetPod::new(column))
+}
@@ -1,11 +1,12 @@
 use crate::main;
 use kyute::{
+    cache,
     text::{FontStyle, FontWeight, FormattedText, FormattedTextExt},
     widget::{
         prelude::*,
         table,
-        table::{Collection, Column, Identifiable},
-        Null, ScrollArea, TableSelection, TableView, TableViewParams, Text,
+        table::{Collection, Column, Identifiable, SortOrder},
+        Null, ScrollArea, TableColumnsState, TableSelection, TableView, TableViewParams, Text,
     },
 };
 use kyute_common::Color;
@@ -72,6 +73,8 @@ struct TagsModel<'a> {
     characters: &'a [(&'a str, u64)],
     tags: &'a [(&'a str, u64)],
     meta: &'a [(&'a str, u64)],
+    /// Column by which the tags of each section are sorted.
+    sort: Option<(usize, SortOrder)>,
 }
 
 impl<'a> Collection<Row<'a>> for TagsModel<'a> {
@@ -105,43 +108,53 @@ impl<'a> Collection<Row<'a>> for TagsModel<'a> {
 
     fn child(&self, parent: &Row, index: usize) -> Row<'a> {
         match parent {
-            Row::Section(section) => match section {
-                Section::Artists => Row::Tag {
-                    ty: TagType::Artist,
-                    post_count: self.artists[index].1,
-                    name: self.artists[index].0,
-                    id: index as u32,
-                },
-                Section::Copyrights => Row::Tag {
-                    ty: TagType::Copyright,
-                    post_count: self.copyrights[index].1,
-                    name: self.copyrights[index].0,
-                    id: index as u32,
-                },
-                Section::Characters => Row::Tag {
-                    ty: TagType::Character,
-                    post_count: self.characters[index].1,
-                    name: self.characters[index].0,
-                    id: index as u32,
-                },
-                Section::Tags => Row::Tag {
-                    ty: TagType::Tag,
-                    post_count: self.tags[index].1,
-                    name: self.tags[index].0,
+            Row::Section(section) => {
+                let (ty, tags) = self.section_tags(*section);
+                let index = self.sorted_index(tags, index);
+                Row::Tag {
+                    ty,
+                    post_count: tags[index].1,
+                    name: tags[index].0,
                     id: index as u32,
-                },
-                Section::Meta => Row::Tag {
-                    ty: TagType::Meta,
-                    post_count: self.meta[index].1,
-                    name: self.meta[index].0,
-                    id: index as u32,
-                },
-            },
+                }
+            }
             _ => panic!("out-of-bounds access"),
         }
     }
 }
 
+impl<'a> TagsModel<'a> {
+    fn section_tags(&self, section: Section) -> (TagType, &'a [(&'a str, u64)]) {
+        match section {
+            Section::Artists => (TagType::Artist, self.artists),
+            Section::Copyrights => (TagType::Copyright, self.copyrights),
+            Section::Characters => (TagType::Character, self.characters),
+            Section::Tags => (TagType::Tag, self.tags),
+            Section::Meta => (TagType::Meta, self.meta),
+        }
+    }
+
+    /// Returns the index of the tag displayed at `index` in the current sort order.
+    fn sorted_index(&self, tags: &[(&str, u64)], index: usize) -> usize {
+        let mut indices: Vec<usize> = (0..tags.len()).collect();
+        if let Some((column, order)) = self.sort {
+            // column 0 is the tag name, column 1 the post count
+            indices.sort_by(|&a, &b| {
+                let ordering = if column == 0 {
+                    tags[a].0.cmp(tags[b].0)
+                } else {
+                    tags[a].1.cmp(&tags[b].1)
+                };
+                match order {
+                    SortOrder::Ascending => ordering,
+                    SortOrder::Descending => ordering.reverse(),
+                }
+            });
+        }
+        indices[index]
+    }
+}
+
 impl Default for RowId {
     fn default() -> Self {
         RowId::Meta(0)
@@ -161,6 +174,7 @@ fn post_count(count: u64) -> impl Widget + 'static {
 #[composable]
 pub fn showcase() -> Arc<WidgetPod> {
     //let mut selection = TableSelection::default();
+    let columns_state = cache::state(TableColumnsState::default);
 
     let model = TagsModel {
         artists: &[("k0nfette", 30)],
@@ -188,6 +202,7 @@ pub fn showcase() -> Arc<WidgetPod> {
             ("teeth", 226000),
         ],
         meta: &[("highres", 2800000)],
+        sort: columns_state.get().sort,
     };
 
     let main_column_delegate = |row: &Row| match *row {
@@ -210,11 +225,13 @@ pub fn showcase() -> Arc<WidgetPod> {
         Row::Tag { post_count: count, .. } => post_count(count).arc_dyn_pod(),
     };
 
-    let main_column = Column::new(Text::new("Tag"), &main_column_delegate);
+    let main_column = Column::new(Text::new("Tag"), &main_column_delegate).resizable(200.0);
     let post_count_column = Column::new(Text::new("Post count"), &post_count_column_delegate);
 
-    let mut table_params = TableViewParams::default();
+    let mut table_params = TableViewParams::default().columns_state(columns_state);
     table_params.show_expand_buttons = false;
+    table_params.sortable_columns = true;
+    table_params.reorderable_columns = true;
     table_params.columns.push(main_column);
     table_params.columns.push(post_count_column);
 
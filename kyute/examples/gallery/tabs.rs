@@ -0,0 +1,47 @@
+use kyute::{
+    composable,
+    widget::{grid::TrackBreadth, Button, Grid, Tab, TabView, Text, WidgetExt, WidgetPod},
+    UnitExt, Widget,
+};
+use std::sync::Arc;
+
+#[composable]
+pub fn showcase() -> Arc<WidgetPod> {
+    #[state]
+    let mut documents: Vec<u32> = (1..=12).collect();
+    #[state]
+    let mut next_document = 13u32;
+    #[state]
+    let mut selected = 0usize;
+
+    let tabs = documents
+        .iter()
+        .map(|&n| {
+            Tab::new(n, format!("Document {}", n), move || {
+                Text::new(format!("Contents of document {}", n)).padding(8.dip())
+            })
+            .closable(true)
+        })
+        .collect();
+    let tab_view = TabView::new(selected, tabs)
+        .on_tab_closed(|index| {
+            documents.remove(index);
+        })
+        .on_tab_moved(|from, to| {
+            let document = documents.remove(from);
+            documents.insert(to, document);
+        })
+        .on_selection_changed(|index| selected = index);
+
+    let new_tab = Button::new("New tab").on_click(|| {
+        documents.push(next_document);
+        next_document += 1;
+        selected = documents.len() - 1;
+    });
+
+    let mut column = Grid::column(TrackBreadth::Auto);
+    column.set_row_gap(8.dip());
+    column.insert(new_tab);
+    column.insert(tab_view.fix_height(200.dip()));
+    Arc::new(WidgetPod::new(column))
+}
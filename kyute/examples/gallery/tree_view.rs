@@ -0,0 +1,58 @@
+use kyute::{
+    composable,
+    widget::{prelude::*, table::Identifiable, ScrollArea, Text, TreeModel, TreeView},
+};
+use std::sync::Arc;
+
+/// A node of the tree, identified by its path from the root.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+struct NodePath(Vec<u32>);
+
+impl Identifiable for NodePath {
+    type Id = NodePath;
+
+    fn id(&self) -> NodePath {
+        self.clone()
+    }
+}
+
+/// A tree where each node has `fan_out` children, down to `depth` levels. Nodes are created when their parent is
+/// expanded.
+struct GeneratedTree {
+    depth: usize,
+    fan_out: u32,
+}
+
+impl TreeModel for GeneratedTree {
+    type Node = NodePath;
+
+    fn roots(&self) -> Vec<NodePath> {
+        (0..self.fan_out).map(|i| NodePath(vec![i])).collect()
+    }
+
+    fn has_children(&self, node: &NodePath) -> bool {
+        node.0.len() < self.depth
+    }
+
+    fn children(&self, node: &NodePath) -> Option<Vec<NodePath>> {
+        Some(
+            (0..self.fan_out)
+                .map(|i| {
+                    let mut path = node.0.clone();
+                    path.push(i);
+                    NodePath(path)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[composable]
+pub fn showcase() -> Arc<WidgetPod> {
+    let model = GeneratedTree { depth: 4, fan_out: 5 };
+    let tree = TreeView::new(&model, |node: &NodePath| {
+        let name: Vec<String> = node.0.iter().map(|i| i.to_string()).collect();
+        Text::new(format!("Item {}", name.join(".")))
+    });
+    ScrollArea::new(tree).arc_pod()
+}
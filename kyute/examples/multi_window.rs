@@ -0,0 +1,60 @@
+use kyute::{
+    application, cache, composable,
+    shell::winit::{dpi::LogicalSize, window::WindowBuilder},
+    widget::{grid::GridLayoutExt, Button, Grid, Text, WidgetExt},
+    Color, UnitExt, Widget, Window, WindowGroup,
+};
+
+#[composable]
+fn document_window(index: u32, open_windows: cache::State<Vec<u32>>) -> Window {
+    let mut grid = Grid::with_template("40px 40px / 1fr");
+    grid.insert((
+        Text::new(format!("Document {}", index)).centered(),
+        Button::new("Open another window")
+            .on_click(|| {
+                let mut windows = open_windows.get();
+                let next = windows.iter().max().map_or(0, |last| last + 1);
+                windows.push(next);
+                open_windows.set(windows);
+            })
+            .centered(),
+    ));
+    let contents = grid
+        .frame(100.percent(), 100.percent())
+        .text_color(Color::from_rgb_u8(200, 200, 200));
+
+    Window::new(
+        WindowBuilder::new()
+            .with_title(format!("Document {}", index))
+            .with_inner_size(LogicalSize::new(300, 120)),
+        contents,
+        None,
+    )
+    .on_close_requested(move || {
+        // removing the window from the group destroys it
+        let mut windows = open_windows.get();
+        windows.retain(|&i| i != index);
+        open_windows.set(windows);
+    })
+}
+
+#[composable]
+fn ui_root() -> WindowGroup {
+    let open_windows = cache::state(|| vec![0u32]);
+    let windows = open_windows
+        .get()
+        .into_iter()
+        .map(|index| cache::keyed(index, || document_window(index, open_windows.clone())))
+        .collect();
+    WindowGroup::new(windows)
+}
+
+fn main() {
+    tracing_subscriber::fmt()
+        .compact()
+        .with_target(false)
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    application::run(ui_root);
+}
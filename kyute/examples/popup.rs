@@ -1,37 +1,78 @@
 use kyute::{
     application, composable,
-    shell::{application::Application, winit::window::WindowBuilder},
-    style::Style,
-    theme,
-    widget::{Button, Container, Flex, Grid, GridLength, Image, Label, Null, Popup, Text, TitledPane},
-    Alignment, AssetId, BoxConstraints, Color, EnvKey, Environment, Orientation, Size, UnitExt, Widget, WidgetExt,
-    WidgetPod, Window,
+    shell::winit::{dpi::LogicalSize, window::WindowBuilder},
+    widget::{Button, Grid, Popup, Side, Text, WidgetExt},
+    Color, Size, UnitExt, Widget, Window,
 };
-use std::sync::Arc;
-use tracing::trace;
 
+/// A button that toggles a popup on the specified side.
 #[composable]
-fn popup_test() -> impl Widget + Clone {
-    let button = Button::new("Click Me".to_string());
-
-    let contents = Image::from_uri("data/bonjour.jpg");
-    let popup = Popup::new(Container::new(contents));
-    if button.clicked() {
-        trace!("button clicked");
-        // launch popup?
-        popup.show();
+fn popup_button(label: &str, side: Side, content: impl Widget + 'static) -> Popup {
+    let button = Button::new(label.to_string());
+    let clicked = button.clicked();
+    let popup = Popup::anchored(button, content, Size::new(180.0, 120.0), side).gap(2.0);
+    if clicked {
+        if popup.is_shown() {
+            popup.hide();
+        } else {
+            popup.show();
+        }
     }
+    popup
+}
 
-    Grid::new().with_item(0, 0, button).with_item(0, 0, popup)
+#[composable]
+fn popup_contents() -> impl Widget {
+    let mut grid = Grid::with_template("40px 40px / 1fr");
+    grid.insert((
+        Text::new("Popup contents").centered(),
+        // nested popup, flipped to the left near the right edge of the screen
+        popup_button("More ▸", Side::Right, Text::new("Nested popup").centered()).centered(),
+    ));
+    grid.frame(100.percent(), 100.percent())
+        .background("rgb(40 40 40)")
+        .text_color(Color::from_rgb_u8(200, 200, 200))
 }
 
 #[composable]
-fn ui_root() -> Arc<WidgetPod> {
-    Arc::new(WidgetPod::new(Window::new(
-        WindowBuilder::new().with_title("Popup"),
-        popup_test(),
+fn ui_root() -> Window {
+    let mut grid = Grid::with_template("1fr 40px / 1fr 1fr 1fr");
+    grid.place(
+        (0, ..),
+        0,
+        Text::new("Move the window near the edges of the screen to see the popups flip")
+            .centered()
+            .arc_dyn_pod(),
+    );
+    grid.place(
+        (1, 0),
+        0,
+        popup_button("Below", Side::Bottom, popup_contents())
+            .centered()
+            .arc_dyn_pod(),
+    );
+    grid.place(
+        (1, 1),
+        0,
+        popup_button("Above", Side::Top, popup_contents())
+            .centered()
+            .arc_dyn_pod(),
+    );
+    grid.place(
+        (1, 2),
+        0,
+        popup_button("Right", Side::Right, popup_contents())
+            .centered()
+            .arc_dyn_pod(),
+    );
+
+    Window::new(
+        WindowBuilder::new()
+            .with_title("Popups")
+            .with_inner_size(LogicalSize::new(480, 160)),
+        grid.frame(100.percent(), 100.percent()),
         None,
-    )))
+    )
 }
 
 fn main() {
@@ -40,7 +81,6 @@ fn main() {
         .with_target(false)
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
-    let _app = Application::new();
+
     application::run(ui_root);
-    Application::shutdown();
 }
@@ -0,0 +1,55 @@
+//! Panel built by a script. Pass the path of a script to load it instead of the demo script; the panel is updated
+//! each time the script file is saved.
+use kyute::{
+    application, composable,
+    scripting::ScriptPanel,
+    shell::winit::{dpi::LogicalSize, window::WindowBuilder},
+    widget::WidgetExt,
+    Color, UnitExt, Widget, Window,
+};
+
+const DEMO_SCRIPT: &str = r#"
+fn init() {
+    this.count = 0;
+    this.enabled = true;
+}
+
+fn ui() {
+    column([
+        text(`Count: ${this.count}`),
+        row([button("+", Fn("add")), button("-", Fn("subtract"))]),
+        checkbox("Enabled", this.enabled, Fn("toggle")),
+    ]).padding(8)
+}
+
+fn add() { if this.enabled { this.count += 1; } }
+fn subtract() { if this.enabled { this.count -= 1; } }
+fn toggle(enabled) { this.enabled = enabled; }
+"#;
+
+#[composable]
+fn main_window() -> impl Widget {
+    let panel = match std::env::args().nth(1) {
+        Some(path) => ScriptPanel::watch(path),
+        None => ScriptPanel::from_source(DEMO_SCRIPT),
+    };
+    Window::new(
+        WindowBuilder::new()
+            .with_title("Scripted panel")
+            .with_inner_size(LogicalSize::new(300, 200)),
+        panel
+            .frame(100.percent(), 100.percent())
+            .text_color(Color::from_rgb_u8(200, 200, 200)),
+        None,
+    )
+}
+
+fn main() {
+    tracing_subscriber::fmt()
+        .compact()
+        .with_target(false)
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    application::run(main_window);
+}
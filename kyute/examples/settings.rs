@@ -0,0 +1,112 @@
+use kyute::{
+    application, composable,
+    shell::winit::{dpi::LogicalSize, window::WindowBuilder},
+    widget::{form::FieldOptions, Setting, SettingsCategory, SettingsStore, SettingsView},
+    Widget, Window,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct AppSettings {
+    user_name: Arc<str>,
+    autosave: bool,
+    autosave_interval: u32,
+    font_size: f64,
+    show_line_numbers: bool,
+    proxy_host: Arc<str>,
+    proxy_port: u16,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            user_name: Arc::from(""),
+            autosave: true,
+            autosave_interval: 5,
+            font_size: 14.0,
+            show_line_numbers: true,
+            proxy_host: Arc::from(""),
+            proxy_port: 8080,
+        }
+    }
+}
+
+#[composable]
+fn settings_view() -> impl Widget {
+    let store = SettingsStore::<AppSettings>::load("settings_example.json").validator(|settings| {
+        if settings.autosave && settings.autosave_interval == 0 {
+            Err("the autosave interval must be at least one minute".to_string())
+        } else {
+            Ok(())
+        }
+    });
+    let draft = store.draft();
+
+    let categories = vec![
+        SettingsCategory::new("General")
+            .section(
+                "User",
+                [Setting::field("User name", draft, |s: &mut AppSettings| {
+                    &mut s.user_name
+                })],
+            )
+            .section(
+                "Saving",
+                [
+                    Setting::field("Autosave", draft, |s: &mut AppSettings| &mut s.autosave),
+                    Setting::field("Autosave interval (minutes)", draft, |s: &mut AppSettings| {
+                        &mut s.autosave_interval
+                    }),
+                ],
+            ),
+        SettingsCategory::new("Editor").section(
+            "Appearance",
+            [
+                Setting::field_with_options(
+                    "Font size",
+                    draft,
+                    &FieldOptions {
+                        min: Some(6.0),
+                        max: Some(72.0),
+                    },
+                    |s: &mut AppSettings| &mut s.font_size,
+                ),
+                Setting::field("Show line numbers", draft, |s: &mut AppSettings| {
+                    &mut s.show_line_numbers
+                }),
+            ],
+        ),
+        SettingsCategory::new("Network").section(
+            "Proxy",
+            [
+                Setting::field("Host", draft, |s: &mut AppSettings| &mut s.proxy_host),
+                Setting::field("Port", draft, |s: &mut AppSettings| &mut s.proxy_port),
+            ],
+        ),
+    ];
+
+    SettingsView::new(&store, categories)
+}
+
+#[composable]
+fn main_window() -> impl Widget {
+    Window::new(
+        WindowBuilder::new()
+            .with_title("Settings")
+            .with_inner_size(LogicalSize::new(640, 480)),
+        settings_view(),
+        None,
+    )
+}
+
+fn main() {
+    tracing_subscriber::fmt()
+        .compact()
+        .with_target(false)
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    application::run(main_window);
+}
@@ -0,0 +1,218 @@
+use crate::CRATE;
+use proc_macro::{Diagnostic, Level};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{spanned::Spanned, Data, Fields, Lit, Meta, NestedMeta};
+
+/// Options parsed from the `#[form(...)]` attributes of a field.
+#[derive(Default)]
+struct FieldAttrs {
+    label: Option<String>,
+    skip: bool,
+    section: bool,
+    min: Option<f64>,
+    max: Option<f64>,
+    validate: Option<syn::Path>,
+}
+
+/// Converts a field name to a label: `line_height` => `Line height`.
+fn label_from_ident(ident: &syn::Ident) -> String {
+    let name = ident.to_string();
+    let name = name.trim_start_matches("r#").replace('_', " ");
+    let mut chars = name.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn lit_to_f64(lit: &Lit) -> Option<f64> {
+    match lit {
+        Lit::Int(i) => i.base10_parse().ok(),
+        Lit::Float(f) => f.base10_parse().ok(),
+        // allows negative values: `min = "-1.0"`
+        Lit::Str(s) => s.value().trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn parse_field_attrs(field: &syn::Field) -> Result<FieldAttrs, Diagnostic> {
+    let mut attrs = FieldAttrs::default();
+
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("form")) {
+        let meta = attr
+            .parse_meta()
+            .map_err(|err| Diagnostic::spanned(attr.span().unwrap(), Level::Error, err.to_string()))?;
+        let list = match meta {
+            Meta::List(list) => list,
+            _ => {
+                return Err(Diagnostic::spanned(
+                    attr.span().unwrap(),
+                    Level::Error,
+                    "expected `#[form(...)]`",
+                ))
+            }
+        };
+
+        for nested in list.nested.iter() {
+            let invalid = || Diagnostic::spanned(nested.span().unwrap(), Level::Error, "invalid form attribute");
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => attrs.skip = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("section") => attrs.section = true,
+                NestedMeta::Meta(Meta::NameValue(nv)) => {
+                    if nv.path.is_ident("label") {
+                        match nv.lit {
+                            Lit::Str(ref s) => attrs.label = Some(s.value()),
+                            _ => return Err(invalid().note("expected a string: `label = \"...\"`")),
+                        }
+                    } else if nv.path.is_ident("min") {
+                        attrs.min = Some(lit_to_f64(&nv.lit).ok_or_else(|| invalid().note("expected a number"))?);
+                    } else if nv.path.is_ident("max") {
+                        attrs.max = Some(lit_to_f64(&nv.lit).ok_or_else(|| invalid().note("expected a number"))?);
+                    } else if nv.path.is_ident("validate") {
+                        match nv.lit {
+                            Lit::Str(ref s) => {
+                                attrs.validate = Some(s.parse().map_err(|_| {
+                                    invalid().note("expected a path to a function: `validate = \"path::to::fn\"`")
+                                })?)
+                            }
+                            _ => {
+                                return Err(
+                                    invalid().note("expected a path to a function: `validate = \"path::to::fn\"`")
+                                )
+                            }
+                        }
+                    } else {
+                        return Err(invalid());
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+    }
+
+    Ok(attrs)
+}
+
+fn option_tokens(value: Option<f64>) -> TokenStream {
+    match value {
+        Some(v) => quote! { ::std::option::Option::Some(#v) },
+        None => quote! { ::std::option::Option::None },
+    }
+}
+
+pub(crate) fn derive_form_model_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    let fields = match input.data {
+        Data::Struct(syn::DataStruct {
+            fields: Fields::Named(ref named),
+            ..
+        }) => &named.named,
+        _ => {
+            Diagnostic::spanned(
+                input.span().unwrap(),
+                Level::Error,
+                "`FormModel` can only be derived on structs with named fields",
+            )
+            .emit();
+            return quote! {}.into();
+        }
+    };
+
+    let mut field_rows = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let attrs = match parse_field_attrs(field) {
+            Ok(attrs) => attrs,
+            Err(diag) => {
+                diag.emit();
+                return quote! {}.into();
+            }
+        };
+        if attrs.skip {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let label = attrs.label.unwrap_or_else(|| label_from_ident(ident));
+        let validate = match attrs.validate {
+            Some(ref path) => quote! { #path(&edited) },
+            None => quote! { true },
+        };
+
+        let row = if attrs.section {
+            quote! {
+                let (section_rows, edited) = <#ty as #CRATE::widget::form::FormModel>::form_rows(&value.#ident);
+                rows.push(#CRATE::widget::form::Section::new(#CRATE::widget::Text::new(#label), section_rows).into());
+            }
+        } else {
+            let min = option_tokens(attrs.min);
+            let max = option_tokens(attrs.max);
+            quote! {
+                let (row, edited) = <#ty as #CRATE::widget::form::FormField>::form_row(
+                    #label,
+                    &value.#ident,
+                    &#CRATE::widget::form::FieldOptions { min: #min, max: #max },
+                );
+                rows.push(row);
+            }
+        };
+
+        // each field is composed in its own scope, since all the calls share the same source location
+        field_rows.push(quote! {
+            #CRATE::cache::scoped(#index, || {
+                #row
+                if let ::std::option::Option::Some(edited) = edited {
+                    if #validate {
+                        new_value.get_or_insert_with(|| ::std::clone::Clone::clone(value)).#ident = edited;
+                    }
+                }
+            });
+        });
+    }
+
+    let ty = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #CRATE::widget::form::FormModel for #ty #type_generics #where_clause {
+            fn form_rows(value: &Self) -> (::std::vec::Vec<#CRATE::widget::form::Row>, ::std::option::Option<Self>) {
+                let mut rows = ::std::vec::Vec::new();
+                let mut new_value: ::std::option::Option<Self> = ::std::option::Option::None;
+                #(#field_rows)*
+                (rows, new_value)
+            }
+        }
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        assert_eq!(label_from_ident(&syn::parse_quote!(line_height)), "Line height");
+        assert_eq!(label_from_ident(&syn::parse_quote!(r#type)), "Type");
+    }
+
+    #[test]
+    fn field_attrs() {
+        let field: syn::FieldsNamed = syn::parse_quote!({
+            #[form(label = "Opacity", min = 0, max = "1.5", validate = "validators::opacity")]
+            #[form(section)]
+            opacity: f64
+        });
+        let attrs = parse_field_attrs(&field.named[0]).ok().unwrap();
+        assert_eq!(attrs.label.as_deref(), Some("Opacity"));
+        assert_eq!((attrs.min, attrs.max), (Some(0.0), Some(1.5)));
+        assert!(attrs.section && !attrs.skip);
+        let validate = attrs.validate.unwrap();
+        assert_eq!(quote!(#validate).to_string(), quote!(validators::opacity).to_string());
+
+        let negative: syn::Lit = syn::parse_quote!("-1.0");
+        assert_eq!(lit_to_f64(&negative), Some(-1.0));
+    }
+}
@@ -5,9 +5,11 @@ use proc_macro2::Span;
 use quote::{ToTokens, TokenStreamExt};
 
 mod composable;
+mod form_model;
 mod widget_wrapper;
 
 use composable::generate_composable;
+use form_model::derive_form_model_impl;
 use widget_wrapper::derive_widget_wrapper_impl;
 
 //--------------------------------------------------------------------------------------------------
@@ -30,3 +32,8 @@ pub fn composable(attr: proc_macro::TokenStream, item: proc_macro::TokenStream)
 pub fn widget_wrapper_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     derive_widget_wrapper_impl(input)
 }
+
+#[proc_macro_derive(FormModel, attributes(form))]
+pub fn form_model_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive_form_model_impl(input)
+}
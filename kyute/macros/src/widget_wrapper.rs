@@ -113,6 +113,22 @@ pub(crate) fn derive_widget_wrapper_impl(input: proc_macro::TokenStream) -> proc
             fn debug_node(&self) -> #CRATE::DebugNode {
                 self.#access.debug_node()
             }
+
+            fn semantics(&self) -> Option<#CRATE::accessibility::Semantics> {
+                self.#access.semantics()
+            }
+
+            fn is_focusable(&self) -> bool {
+                self.#access.is_focusable()
+            }
+
+            fn is_in_focus_chain(&self) -> bool {
+                self.#access.is_in_focus_chain()
+            }
+
+            fn z_order(&self) -> i32 {
+                self.#access.z_order()
+            }
         }
     }
     .into()
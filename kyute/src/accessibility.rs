@@ -0,0 +1,114 @@
+//! Accessibility: semantic description of the widget tree for assistive technologies (screen readers).
+//!
+//! Widgets describe what they are (a button, a checkbox, a slider...) by returning `Semantics` from
+//! `Widget::semantics`, or by being wrapped in `Accessible` (see `WidgetExt::accessible`).
+//! The window collects the widgets with semantics into an `AccessibilityTree`, which is exposed to assistive
+//! technologies through the platform accessibility API (see `kyute_shell::accessibility`), and passed to the
+//! handler set with `Window::on_accessibility_update`.
+pub use kyute_shell::accessibility::{NumericValue, Role, Semantics};
+
+use crate::{Rect, WidgetId};
+use kyute_shell::accessibility::{AccessibleNode, AccessibleTree};
+
+/// A node in the accessibility tree, corresponding to a widget with semantics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessibilityNode {
+    /// ID of the widget.
+    pub id: WidgetId,
+    pub semantics: Semantics,
+    /// Bounds of the widget in window coordinates (DIPs).
+    pub bounds: Rect,
+    /// Nodes of the descendant widgets with semantics.
+    ///
+    /// Widgets without semantics are not part of the tree: their children are attached to the nearest ancestor
+    /// with semantics.
+    pub children: Vec<AccessibilityNode>,
+}
+
+/// Accessibility tree of the contents of a window.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AccessibilityTree {
+    /// Top-level nodes.
+    pub nodes: Vec<AccessibilityNode>,
+    /// The widget that has the keyboard focus.
+    pub focus: Option<WidgetId>,
+}
+
+impl AccessibilityTree {
+    /// Finds the node of the specified widget.
+    pub fn find(&self, id: WidgetId) -> Option<&AccessibilityNode> {
+        fn find_rec(nodes: &[AccessibilityNode], id: WidgetId) -> Option<&AccessibilityNode> {
+            for node in nodes {
+                if node.id == id {
+                    return Some(node);
+                }
+                if let Some(node) = find_rec(&node.children, id) {
+                    return Some(node);
+                }
+            }
+            None
+        }
+        find_rec(&self.nodes, id)
+    }
+
+    /// Returns the node that has the keyboard focus, if it's part of the tree.
+    pub fn focused_node(&self) -> Option<&AccessibilityNode> {
+        self.focus.and_then(|id| self.find(id))
+    }
+
+    /// Converts the tree to the representation of the platform accessibility layer.
+    pub(crate) fn to_platform(&self) -> AccessibleTree {
+        fn convert(nodes: &[AccessibilityNode]) -> Vec<AccessibleNode> {
+            nodes
+                .iter()
+                .map(|node| AccessibleNode {
+                    id: node.id.to_u64(),
+                    semantics: node.semantics.clone(),
+                    bounds: node.bounds,
+                    children: convert(&node.children),
+                })
+                .collect()
+        }
+        AccessibleTree {
+            nodes: convert(&self.nodes),
+            focus: self.focus.map(WidgetId::to_u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::{noop_waker, Cache},
+        Environment, Point, Size,
+    };
+
+    #[test]
+    fn platform_tree() {
+        let id = Cache::new(noop_waker()).recompose(&Environment::new(), WidgetId::here);
+        let bounds = Rect::new(Point::new(10.0, 10.0), Size::new(50.0, 20.0));
+        let tree = AccessibilityTree {
+            nodes: vec![AccessibilityNode {
+                id,
+                semantics: Semantics::new(Role::Group),
+                bounds,
+                children: vec![AccessibilityNode {
+                    id,
+                    semantics: Semantics::new(Role::Button).name("OK"),
+                    bounds,
+                    children: vec![],
+                }],
+            }],
+            focus: Some(id),
+        };
+
+        let platform = tree.to_platform();
+        assert_eq!(platform.focus, Some(id.to_u64()));
+        assert_eq!(platform.nodes.len(), 1);
+        assert_eq!(platform.nodes[0].bounds, bounds);
+        let button = &platform.nodes[0].children[0];
+        assert_eq!(button.id, id.to_u64());
+        assert_eq!(button.semantics.name.as_deref(), Some("OK"));
+    }
+}
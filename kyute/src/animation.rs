@@ -0,0 +1,484 @@
+//! Animations and transitions.
+//!
+//! Values are animated during composition: `Animated` (or the `animate` shorthand) returns the current value of
+//! an animation, and schedules a recomposition at the next frame of the compositor until the animation is finished.
+//! For animations of the opacity or the transform of a widget, which can run on the compositor without
+//! recomposing or repainting anything, see `AnimatedLayer`.
+//!
+//! All animations follow the `REDUCED_MOTION` preference (see `theme::animation_duration`).
+//!
+//! [`AnimatedLayer`]: crate::widget::AnimatedLayer
+use crate::{
+    cache,
+    color::{self, InterpolatedColor},
+    composable, theme, Color, Offset, Point, Size, Transform,
+};
+use kyute_shell::animation::frame_timing;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Easing curves, which map the linear progress of an animation to the progress of the animated value.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    /// Equivalent to CSS `ease-in`.
+    EaseIn,
+    /// Equivalent to CSS `ease-out`.
+    EaseOut,
+    /// Equivalent to CSS `ease-in-out`.
+    EaseInOut,
+    /// A cubic bezier curve from (0,0) to (1,1) with the specified control points `(x1, y1, x2, y2)`,
+    /// like CSS `cubic-bezier()`.
+    CubicBezier(f64, f64, f64, f64),
+}
+
+impl Default for Easing {
+    fn default() -> Self {
+        Easing::EaseInOut
+    }
+}
+
+impl Easing {
+    /// Applies the easing curve to the linear progress `t`, between 0.0 and 1.0.
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+            Easing::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+            Easing::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+/// Evaluates the y coordinate of a cubic bezier easing curve at the specified x coordinate.
+fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+    // coordinates of the curve at parameter s, with P0 = (0,0) and P3 = (1,1)
+    let bezier = |p1: f64, p2: f64, s: f64| {
+        let r = 1.0 - s;
+        3.0 * r * r * s * p1 + 3.0 * r * s * s * p2 + s * s * s
+    };
+    let bezier_derivative = |p1: f64, p2: f64, s: f64| {
+        let r = 1.0 - s;
+        3.0 * r * r * p1 + 6.0 * r * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+    };
+
+    // find the parameter for x: a few Newton iterations, with bisection as a fallback
+    let mut s = x;
+    for _ in 0..8 {
+        let err = bezier(x1, x2, s) - x;
+        if err.abs() < 1e-6 {
+            return bezier(y1, y2, s);
+        }
+        let d = bezier_derivative(x1, x2, s);
+        if d.abs() < 1e-6 {
+            break;
+        }
+        s -= err / d;
+    }
+
+    let (mut lo, mut hi) = (0.0, 1.0);
+    s = x;
+    for _ in 0..32 {
+        let v = bezier(x1, x2, s);
+        if (v - x).abs() < 1e-6 {
+            break;
+        }
+        if v < x {
+            lo = s;
+        } else {
+            hi = s;
+        }
+        s = 0.5 * (lo + hi);
+    }
+    bezier(y1, y2, s)
+}
+
+/// Values that can be interpolated.
+pub trait Interpolate: Clone {
+    /// Returns the value between `self` (`t == 0.0`) and `other` (`t == 1.0`).
+    ///
+    /// `t` may be outside of `0.0..=1.0` for easing curves that overshoot.
+    fn interpolate(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t as f32
+    }
+}
+
+impl Interpolate for Point {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Offset {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Size {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        let (r0, g0, b0, a0) = self.to_rgba();
+        let (r1, g1, b1, a1) = other.to_rgba();
+        Color::new(
+            r0.interpolate(&r1, t),
+            g0.interpolate(&g1, t),
+            b0.interpolate(&b1, t),
+            a0.interpolate(&a1, t),
+        )
+    }
+}
+
+impl Interpolate for InterpolatedColor {
+    /// Interpolates the colors in the color space of the target color.
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        InterpolatedColor {
+            color: color::mix(self.color, other.color, t as f32, other.interpolation),
+            interpolation: other.interpolation,
+        }
+    }
+}
+
+impl Interpolate for Transform {
+    /// Interpolates the elements of the matrices.
+    ///
+    /// This is fine for translations and scales, but rotations are not interpolated along an arc.
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Transform::new(
+            self.m11.interpolate(&other.m11, t),
+            self.m12.interpolate(&other.m12, t),
+            self.m21.interpolate(&other.m21, t),
+            self.m22.interpolate(&other.m22, t),
+            self.m31.interpolate(&other.m31, t),
+            self.m32.interpolate(&other.m32, t),
+        )
+    }
+}
+
+/// Describes the timing of an animation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transition {
+    pub duration: Duration,
+    /// Delay before the animation starts.
+    pub delay: Duration,
+    pub easing: Easing,
+}
+
+impl Transition {
+    /// Creates a transition with the specified duration, the default easing curve and no delay.
+    pub fn new(duration: Duration) -> Transition {
+        Transition {
+            duration,
+            delay: Duration::ZERO,
+            easing: Easing::default(),
+        }
+    }
+
+    /// Sets the easing curve.
+    #[must_use]
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Sets the delay before the animation starts.
+    #[must_use]
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Returns this transition adjusted for the motion preferences in the current environment.
+    pub(crate) fn for_environment(self) -> Transition {
+        let env = cache::environment();
+        Transition {
+            duration: theme::animation_duration(&env, self.duration),
+            delay: theme::animation_duration(&env, self.delay),
+            easing: self.easing,
+        }
+    }
+
+    /// Returns the linear progress (between 0.0 and 1.0) at the specified time since the start of the transition.
+    pub fn linear_progress(&self, elapsed: Duration) -> f64 {
+        let elapsed = elapsed.saturating_sub(self.delay);
+        if elapsed >= self.duration {
+            1.0
+        } else {
+            elapsed.as_secs_f64() / self.duration.as_secs_f64()
+        }
+    }
+
+    /// Returns the total time until the end of the transition, including the delay.
+    pub fn total_duration(&self) -> Duration {
+        self.delay + self.duration
+    }
+}
+
+/// A sequence of values at given points of the progress of an animation.
+#[derive(Clone, Debug)]
+pub struct Keyframes<T> {
+    /// Progress at which the keyframe is reached, value, and easing from the previous keyframe.
+    frames: Vec<(f64, T, Easing)>,
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    /// Creates keyframes starting with the specified value.
+    pub fn new(start: T) -> Keyframes<T> {
+        Keyframes {
+            frames: vec![(0.0, start, Easing::Linear)],
+        }
+    }
+
+    /// Adds a keyframe at `offset`, between 0.0 (start of the animation) and 1.0 (end of the animation).
+    ///
+    /// The value is interpolated from the previous keyframe with the specified easing curve.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is outside of `0.0..=1.0` or before the previous keyframe.
+    #[must_use]
+    pub fn then(mut self, offset: f64, value: T, easing: Easing) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&offset) && offset >= self.frames.last().unwrap().0,
+            "keyframes must be in increasing order, between 0.0 and 1.0"
+        );
+        self.frames.push((offset, value, easing));
+        self
+    }
+
+    /// Returns the value at the specified (linear) progress of the animation.
+    pub fn sample(&self, progress: f64) -> T {
+        let next = self.frames.iter().position(|(offset, _, _)| *offset > progress);
+        match next {
+            None => self.frames.last().unwrap().1.clone(),
+            Some(0) => self.frames[0].1.clone(),
+            Some(i) => {
+                let (start, ref from, _) = self.frames[i - 1];
+                let (end, ref to, easing) = self.frames[i];
+                from.interpolate(to, easing.apply((progress - start) / (end - start)))
+            }
+        }
+    }
+}
+
+/// Schedules a recomposition of the calling composable function at the next frame of the compositor.
+///
+/// Calling this several times before the frame is presented schedules only one recomposition.
+#[composable]
+pub fn request_animation_frame() {
+    let tick = cache::state(|| 0u64);
+    let pending = cache::once(|| Arc::new(AtomicBool::new(false)));
+    if !pending.swap(true, Ordering::AcqRel) {
+        let delay = frame_timing().next_frame_time.saturating_duration_since(Instant::now());
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            pending.store(false, Ordering::Release);
+            tick.set(tick.get().wrapping_add(1));
+        });
+    }
+}
+
+/// Interval between the samples of an animation sent to the compositor.
+const COMPOSITOR_KEYFRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// State of a transition between two values, stored in the cache.
+#[derive(Clone)]
+pub(crate) struct TransitionState<T> {
+    keyframes: Keyframes<T>,
+    /// The value that the transition ends at.
+    target: T,
+    start: Instant,
+    /// Incremented each time the transition is restarted.
+    pub(crate) generation: u64,
+}
+
+impl<T: Interpolate + PartialEq + 'static> TransitionState<T> {
+    /// Returns the transition to `target`, which is restarted from its current value each time `target` changes.
+    ///
+    /// The initial value is not animated.
+    #[composable]
+    pub(crate) fn update(target: T, transition: &Transition) -> TransitionState<T> {
+        let now = Instant::now();
+        let state = cache::state(|| TransitionState {
+            keyframes: Keyframes::new(target.clone()),
+            target: target.clone(),
+            start: now,
+            generation: 0,
+        });
+
+        let mut current = state.get();
+        if current.target != target {
+            // start from where the previous transition currently is, so that interrupted transitions don't jump
+            let value = current.value_at(now, transition);
+            current = TransitionState {
+                keyframes: Keyframes::new(value).then(1.0, target.clone(), transition.easing),
+                target,
+                start: now,
+                generation: current.generation + 1,
+            };
+            state.set_without_invalidation(current.clone());
+        }
+        current
+    }
+
+    /// Returns the value of the transition at the specified time.
+    pub(crate) fn value_at(&self, time: Instant, transition: &Transition) -> T {
+        self.keyframes
+            .sample(transition.linear_progress(time.saturating_duration_since(self.start)))
+    }
+
+    /// Returns whether the transition is finished at the specified time.
+    pub(crate) fn is_finished(&self, time: Instant, transition: &Transition) -> bool {
+        time.saturating_duration_since(self.start) >= transition.total_duration()
+    }
+
+    /// Samples the remainder of the transition, starting at `now`, as keyframes for the compositor
+    /// (see `Layer::animate_transform`).
+    pub(crate) fn compositor_keyframes(&self, now: Instant, transition: &Transition) -> Vec<(Duration, T)> {
+        if self.is_finished(now, transition) {
+            return vec![(Duration::ZERO, self.target.clone())];
+        }
+        let elapsed = now.saturating_duration_since(self.start);
+        let remaining = transition.total_duration() - elapsed;
+        let n = ((remaining.as_secs_f64() / COMPOSITOR_KEYFRAME_INTERVAL.as_secs_f64()).ceil() as u32).max(1);
+        (0..=n)
+            .map(|i| {
+                let dt = remaining * i / n;
+                (dt, self.keyframes.sample(transition.linear_progress(elapsed + dt)))
+            })
+            .collect()
+    }
+}
+
+/// The current value of an animation.
+///
+/// The function that creates the animation is recomposed at each frame until the animation is finished.
+#[derive(Clone, Debug)]
+pub struct Animated<T> {
+    value: T,
+    running: bool,
+}
+
+impl<T: Interpolate + PartialEq + 'static> Animated<T> {
+    /// Transitions to `target` each time it changes, starting from the current value of the animation.
+    ///
+    /// The initial value is not animated.
+    #[composable]
+    pub fn new(target: T, transition: Transition) -> Animated<T> {
+        let transition = transition.for_environment();
+        let state = TransitionState::update(target, &transition);
+        let now = Instant::now();
+        let running = !state.is_finished(now, &transition);
+        if running {
+            request_animation_frame();
+        }
+        Animated {
+            value: state.value_at(now, &transition),
+            running,
+        }
+    }
+
+    /// Plays an animation through keyframes, once, starting from the first composition.
+    ///
+    /// The easing curve of the transition is ignored: each keyframe has its own.
+    #[composable]
+    pub fn keyframes(keyframes: Keyframes<T>, transition: Transition) -> Animated<T> {
+        let transition = transition.for_environment();
+        let start = cache::once(Instant::now);
+        let elapsed = Instant::now().duration_since(start);
+        let running = elapsed < transition.total_duration();
+        if running {
+            request_animation_frame();
+        }
+        Animated {
+            value: keyframes.sample(transition.linear_progress(elapsed)),
+            running,
+        }
+    }
+
+    /// Plays an animation through keyframes repeatedly, starting from the first composition.
+    #[composable]
+    pub fn repeat(keyframes: Keyframes<T>, transition: Transition) -> Animated<T> {
+        let transition = transition.for_environment();
+        let start = cache::once(Instant::now);
+        let period = transition.total_duration();
+        if period.is_zero() {
+            return Animated {
+                value: keyframes.sample(1.0),
+                running: false,
+            };
+        }
+        let elapsed = Instant::now().duration_since(start).as_secs_f64() % period.as_secs_f64();
+        request_animation_frame();
+        Animated {
+            value: keyframes.sample(transition.linear_progress(Duration::from_secs_f64(elapsed))),
+            running: true,
+        }
+    }
+
+    /// Returns the current value.
+    pub fn value(&self) -> T {
+        self.value.clone()
+    }
+
+    /// Returns whether the animation is still running.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+}
+
+/// Returns the current value of a transition to `target`. Shorthand for `Animated::new(target, transition).value()`.
+#[composable]
+pub fn animate<T: Interpolate + PartialEq + 'static>(target: T, transition: Transition) -> T {
+    Animated::new(target, transition).value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easing_curves() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert!(easing.apply(0.0).abs() < 1e-5);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5);
+        }
+        assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-5);
+        assert!(Easing::EaseIn.apply(0.25) < 0.25);
+        assert!(Easing::EaseOut.apply(0.25) > 0.25);
+    }
+
+    #[test]
+    fn keyframes_sample() {
+        let keyframes = Keyframes::new(0.0)
+            .then(0.5, 10.0, Easing::Linear)
+            .then(1.0, 0.0, Easing::Linear);
+        assert_eq!(keyframes.sample(0.0), 0.0);
+        assert_eq!(keyframes.sample(0.25), 5.0);
+        assert_eq!(keyframes.sample(0.5), 10.0);
+        assert_eq!(keyframes.sample(0.75), 5.0);
+        assert_eq!(keyframes.sample(1.0), 0.0);
+    }
+}
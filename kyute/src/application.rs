@@ -7,41 +7,122 @@ use crate::{
     cache,
     cache::Cache,
     core::{dump_widget_tree, WidgetId},
+    diagnostics,
     drawing::{ImageCache, IMAGE_CACHE},
-    theme,
+    frame_debugger, gc, jank, theme,
     util::fs_watch::{FileSystemWatcher, FILE_SYSTEM_WATCHER},
+    window::WindowState,
     AssetLoader, Environment, Event, InternalEvent, Widget,
 };
 use kyute_shell::{
+    application::Application,
     winit,
     winit::{
+        event::WindowEvent,
         event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget},
         window::WindowId,
     },
 };
+use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use std::{
+    cell::RefCell,
     collections::{hash_map::Entry, HashMap},
     fmt, mem,
-    sync::Arc,
+    sync::{Arc, Weak},
     task::{Wake, Waker},
+    time::{Duration, Instant},
 };
 
+/// Time allotted to a frame. Idle-priority work (see `cache::idle`) runs in the time left once the frame has been
+/// presented.
+const FRAME_BUDGET: Duration = Duration::from_millis(16);
+
 pub enum ExtEvent {
     /// Triggers a recomposition
     Recompose,
+    /// Runs a closure on the UI thread, then triggers a recomposition.
+    Call(Box<dyn FnOnce() + Send>),
 }
 
 impl fmt::Debug for ExtEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("ExtEvent").finish()
+        match self {
+            ExtEvent::Recompose => f.write_str("ExtEvent::Recompose"),
+            ExtEvent::Call(_) => f.write_str("ExtEvent::Call"),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// AppProxy
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Handle to the application event loop that can be sent to other threads.
+///
+/// Use it to run code on the UI thread from async tasks or worker threads (`post`), or to get a `Waker` that triggers
+/// a recomposition when woken.
+///
+/// Messages sent after the event loop has exited are silently dropped.
+#[derive(Clone)]
+pub struct AppProxy(Arc<Mutex<EventLoopProxy<ExtEvent>>>);
+
+impl AppProxy {
+    fn new(event_loop: &EventLoop<ExtEvent>) -> AppProxy {
+        AppProxy(Arc::new(Mutex::new(event_loop.create_proxy())))
+    }
+
+    fn send(&self, event: ExtEvent) {
+        if self.0.lock().send_event(event).is_err() {
+            trace!("AppProxy: event loop closed");
+        }
+    }
+
+    /// Runs the given closure on the UI thread, then recomposes the UI.
+    ///
+    /// The closure runs outside of recomposition: it can update `State` variables, but shouldn't call composable functions.
+    pub fn post(&self, f: impl FnOnce() + Send + 'static) {
+        self.send(ExtEvent::Call(Box::new(f)))
+    }
+
+    /// Requests a recomposition of the UI.
+    pub fn request_recompose(&self) {
+        self.send(ExtEvent::Recompose)
+    }
+
+    /// Returns a waker that requests a recomposition when woken.
+    ///
+    /// This is the waker used to poll the futures passed to `cache::run_async`.
+    pub fn waker(&self) -> Waker {
+        Waker::from(Arc::new(EventLoopWaker(self.clone())))
+    }
+}
+
+impl fmt::Debug for AppProxy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AppProxy").finish_non_exhaustive()
     }
 }
 
+static APP_PROXY: OnceCell<AppProxy> = OnceCell::new();
+
+/// Returns a proxy to the running application, or `None` if the application hasn't been started yet.
+pub fn proxy() -> Option<AppProxy> {
+    APP_PROXY.get().cloned()
+}
+
+/// A native window registered to the event loop.
+pub(crate) struct WindowEntry {
+    /// The `Window` widget that receives the events of the window.
+    widget_id: WidgetId,
+    /// State of the window widget. Once it's gone, the window has been removed from the UI tree.
+    state: Weak<RefCell<WindowState>>,
+}
+
 /// Global application context. Contains stuff passed to all widget contexts (Event,Layout,Paint...)
 pub struct AppCtx {
     /// Open windows, mapped to their corresponding widget.
-    pub(crate) windows: HashMap<WindowId, WidgetId>,
+    pub(crate) windows: HashMap<WindowId, WindowEntry>,
     pub(crate) pending_events: Vec<Event<'static>>,
     cache: Cache,
 }
@@ -59,18 +140,46 @@ impl AppCtx {
     /// Registers a widget as a native window widget.
     ///
     /// The event loop will call `window_event` whenever an event targeting the window is received.
-    pub(crate) fn register_window_widget(&mut self, window_id: WindowId, widget_id: WidgetId) {
+    /// The window is unregistered once its state is dropped (see `remove_closed_windows`).
+    pub(crate) fn register_window_widget(
+        &mut self,
+        window_id: WindowId,
+        widget_id: WidgetId,
+        state: Weak<RefCell<WindowState>>,
+    ) {
         match self.windows.entry(window_id) {
             Entry::Occupied(_) => {
                 warn!("window id {:?} already registered", window_id);
             }
             Entry::Vacant(entry) => {
                 trace!("registered window id {:?} to widget {:?}", window_id, widget_id);
-                entry.insert(widget_id);
+                entry.insert(WindowEntry { widget_id, state });
             }
         }
     }
 
+    /// Returns the widget that receives the events of the specified window, if the window is still open.
+    fn window_widget(&self, window_id: WindowId) -> Option<WidgetId> {
+        self.windows
+            .get(&window_id)
+            .filter(|entry| entry.state.strong_count() > 0)
+            .map(|entry| entry.widget_id)
+    }
+
+    /// Unregisters the windows that have been removed from the UI tree.
+    ///
+    /// Another `Window` widget created at the same place in the tree has the same widget ID: without this,
+    /// it would receive the last events of the native window that was destroyed.
+    fn remove_closed_windows(&mut self) {
+        self.windows.retain(|window_id, entry| {
+            let open = entry.state.strong_count() > 0;
+            if !open {
+                trace!("unregistered window id {:?} (widget {:?})", window_id, entry.widget_id);
+            }
+            open
+        });
+    }
+
     /// Posts a widget event.
     pub fn post_event(&mut self, event: Event<'static>) {
         //tracing::trace!("post_event {:?}", &event);
@@ -117,9 +226,10 @@ fn update_ui<W: Widget + 'static>(
     // evaluate the root widget
     let root_widget = {
         let _span = trace_span!("UI recomposition").entered();
+        // the root is recomposed when the root environment changes (see `root_environment`)
         app_ctx
             .cache
-            .recompose(root_env, || cache::memoize((), || Arc::new(ui())))
+            .recompose(root_env, || cache::memoize(root_env.clone(), || Arc::new(ui())))
         // ensures that all widgets have received the `Initialize` event.
     };
 
@@ -131,48 +241,67 @@ fn update_ui<W: Widget + 'static>(
         crate::core::send_root_event(app_ctx, event_loop, &root_widget, &mut Event::Initialize, root_env);
     }
 
+    // notify the subtrees that have been recomposed independently of the root
+    for call_id in app_ctx.cache.take_recomposed_scopes() {
+        let mut event = Event::Internal(InternalEvent::RouteEvent {
+            target: WidgetId::from_call_id(call_id),
+            event: Box::new(Event::Internal(InternalEvent::SubtreeRecomposed)),
+        });
+        crate::core::send_root_event(app_ctx, event_loop, &root_widget, &mut event, root_env);
+    }
+
     //dump_widget_tree(&root_widget);
     root_widget
 }
 
-struct EventLoopWaker(Mutex<EventLoopProxy<ExtEvent>>);
-
-impl EventLoopWaker {
-    fn new(event_loop: &EventLoop<ExtEvent>) -> EventLoopWaker {
-        EventLoopWaker(Mutex::new(event_loop.create_proxy()))
-    }
-}
+struct EventLoopWaker(AppProxy);
 
 impl Wake for EventLoopWaker {
     fn wake(self: Arc<Self>) {
-        self.0
-            .lock()
-            .send_event(ExtEvent::Recompose)
-            .expect("failed to wake event loop");
+        self.0.request_recompose()
     }
 
     fn wake_by_ref(self: &Arc<Self>) {
-        self.0
-            .lock()
-            .send_event(ExtEvent::Recompose)
-            .expect("failed to wake event loop");
+        self.0.request_recompose()
     }
 }
 
 pub fn run<W: Widget + 'static>(ui: fn() -> W) {
-    run_inner(ui, Environment::new())
+    run_inner(ui, Environment::new(), None)
 }
 
 pub fn run_with_env<W: Widget + 'static>(ui: fn() -> W, env_overrides: Environment) {
-    run_inner(ui, env_overrides)
+    run_inner(ui, env_overrides, None)
 }
 
-fn run_inner<W: Widget + 'static>(ui: fn() -> W, env_overrides: Environment) {
-    let event_loop = EventLoop::<ExtEvent>::with_user_event();
-    let event_loop_waker = Waker::from(Arc::new(EventLoopWaker::new(&event_loop)));
-    let mut app_ctx = AppCtx::new(event_loop_waker);
+/// Runs the application with an existing tokio runtime.
+///
+/// By default, `run` creates its own runtime. Use this instead if the application already has a runtime
+/// (for instance with `#[tokio::main]`) so that tasks spawned from the UI and from the rest of the application
+/// share the same executor. The winit event loop still runs on the calling thread, which must be the main thread.
+///
+/// The runtime must be multi-threaded: the event loop blocks the calling thread, so on a current-thread runtime
+/// (e.g. `#[tokio::main(flavor = "current_thread")]`), spawned tasks would never run.
+///
+/// Tasks running on the runtime can communicate with the UI with an `AppProxy` (see `proxy`).
+///
+/// # Panics
+///
+/// Panics if `runtime` is a current-thread runtime, or if the application is already running.
+pub fn run_with_runtime<W: Widget + 'static>(
+    ui: fn() -> W,
+    env_overrides: Environment,
+    runtime: tokio::runtime::Handle,
+) {
+    assert!(
+        runtime.runtime_flavor() != tokio::runtime::RuntimeFlavor::CurrentThread,
+        "`run_with_runtime` requires a multi-threaded runtime"
+    );
+    run_inner(ui, env_overrides, Some(runtime))
+}
 
-    // setup env
+/// Returns the environment containing the asset loader, the image cache, and the default style.
+pub(crate) fn base_environment() -> Environment {
     let mut env = Environment::new();
 
     // TODO: move those to lazy_statics
@@ -183,26 +312,59 @@ fn run_inner<W: Widget + 'static>(ui: fn() -> W, env_overrides: Environment) {
     let fs_watcher = FileSystemWatcher::new();
     env.set(&FILE_SYSTEM_WATCHER, fs_watcher);
     theme::setup_default_style(&mut env);
+    env
+}
+
+/// Returns the root environment: the base environment with the current accessibility preferences of the user, and
+/// the overrides of the application.
+fn root_environment(base_env: &Environment, env_overrides: &Environment) -> Environment {
+    let mut env = base_env.clone();
+    theme::apply_accessibility_preferences(&mut env, &Application::instance().accessibility_preferences());
+    env.merged(env_overrides.clone())
+}
+
+fn run_inner<W: Widget + 'static>(ui: fn() -> W, env_overrides: Environment, runtime: Option<tokio::runtime::Handle>) {
+    let event_loop = EventLoop::<ExtEvent>::with_user_event();
+    let app_proxy = AppProxy::new(&event_loop);
+    if APP_PROXY.set(app_proxy.clone()).is_err() {
+        panic!("the application is already running");
+    }
+    let mut app_ctx = AppCtx::new(app_proxy.waker());
 
-    env = env.merged(env_overrides);
+    // setup env
+    let base_env = base_environment();
+    let mut env = root_environment(&base_env, &env_overrides);
 
     // setup and enter the tokio runtime
-    let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
-    let _rt_guard = rt.enter();
+    let (_rt, rt_handle) = match runtime {
+        Some(handle) => (None, handle),
+        None => {
+            let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
+            let handle = rt.handle().clone();
+            (Some(rt), handle)
+        }
+    };
+    let _rt_guard = rt_handle.enter();
 
     // initial evaluation of the root widget in the main UI cache.
     let mut root_widget = update_ui(&mut app_ctx, &event_loop, &env, ui);
 
     // run event loop
+    let mut frame_start = Instant::now();
     event_loop.run(move |event, elwt, control_flow| {
         *control_flow = ControlFlow::Wait;
         match event {
+            // --- START OF FRAME ------------------------------------------------------------------
+            winit::event::Event::NewEvents(_) => {
+                frame_start = Instant::now();
+                jank::begin_frame();
+            }
             // --- WINDOW EVENT PROCESSING ---------------------------------------------------------
             winit::event::Event::WindowEvent {
                 window_id,
                 event: winit_event,
             } => {
-                if let Some(&target) = app_ctx.windows.get(&window_id) {
+                if let Some(target) = app_ctx.window_widget(window_id) {
                     if let Some(event) = winit_event.to_static() {
                         app_ctx.send_event(
                             &root_widget,
@@ -211,17 +373,58 @@ fn run_inner<W: Widget + 'static>(ui: fn() -> W, env_overrides: Environment) {
                             &env,
                         );
                     }
-                } else {
-                    warn!("unregistered window id: {:?}", window_id);
+                } else if !matches!(winit_event, WindowEvent::Destroyed) {
+                    // the last events of a window can arrive after it's been removed from the tree
+                    trace!("event for closed or unregistered window id: {:?}", window_id);
                 }
             }
             // --- RECOMPOSITION -------------------------------------------------------------------
             // happens after window event processing
             winit::event::Event::MainEventsCleared => {
+                // The user may have changed the accessibility preferences or the theme in the OS settings: query
+                // them again, and update the root environment.
+                let settings_changed = Application::instance().take_settings_changed();
+                if settings_changed {
+                    trace!("system settings changed, updating the root environment");
+                    env = root_environment(&base_env, &env_overrides);
+                }
+
                 // Re-evaluate the root widget.
                 // If no state variable in the cache has changed (because of an event), then it will simply
                 // return the same root widget.
                 root_widget = update_ui(&mut app_ctx, elwt, &env, ui);
+                if settings_changed {
+                    // the widgets that weren't recomposed must discard the values resolved from the old environment
+                    app_ctx.send_event(
+                        &root_widget,
+                        elwt,
+                        Event::Internal(InternalEvent::EnvironmentChanged),
+                        &env,
+                    );
+                }
+                // the previous widget tree is dropped at this point, along with the windows that are not in the new one
+                let had_windows = !app_ctx.windows.is_empty();
+                app_ctx.remove_closed_windows();
+                if had_windows && app_ctx.windows.is_empty() {
+                    trace!("last window closed, exiting");
+                    *control_flow = ControlFlow::Exit;
+                }
+                if frame_debugger::capture_requested() {
+                    // repaint all windows now, while recording paint commands
+                    let _span = trace_span!("Frame capture").entered();
+                    frame_debugger::begin_capture();
+                    let targets: Vec<_> = app_ctx.windows.values().map(|entry| entry.widget_id).collect();
+                    for target in targets {
+                        app_ctx.send_event(
+                            &root_widget,
+                            elwt,
+                            Event::Internal(InternalEvent::RouteRedrawRequest(target)),
+                            &env,
+                        );
+                    }
+                    frame_debugger::end_capture();
+                }
+                diagnostics::check_growth();
             }
             // --- EXT EVENTS ----------------------------------------------------------------------
             winit::event::Event::UserEvent(ext_event) => match ext_event {
@@ -229,17 +432,42 @@ fn run_inner<W: Widget + 'static>(ui: fn() -> W, env_overrides: Environment) {
                     // will recomp in maineventscleared
                     //root_widget = eval_root_widget(&mut app_ctx, elwt, &env, ui);
                 }
+                ExtEvent::Call(f) => {
+                    // recomposition happens in maineventscleared
+                    f();
+                }
             },
+            // --- END OF FRAME --------------------------------------------------------------------
+            // happens after repaint
+            winit::event::Event::RedrawEventsCleared => {
+                jank::end_frame(frame_start);
+                // idle-priority work runs in the time left in the frame; if there's more, continue in the next one
+                if app_ctx.cache.run_idle_tasks(frame_start + FRAME_BUDGET) {
+                    *control_flow = ControlFlow::Poll;
+                }
+                // then release the resources that aren't used anymore, if there's still time
+                if gc::end_frame(frame_start + FRAME_BUDGET) {
+                    app_ctx.send_event(
+                        &root_widget,
+                        elwt,
+                        Event::Internal(InternalEvent::MarkUsedResources),
+                        &env,
+                    );
+                    gc::collect(&env);
+                }
+            }
             // --- REPAINT -------------------------------------------------------------------------
             // happens after recomposition
             winit::event::Event::RedrawRequested(window_id) => {
-                if let Some(&target) = app_ctx.windows.get(&window_id) {
+                if let Some(target) = app_ctx.window_widget(window_id) {
                     app_ctx.send_event(
                         &root_widget,
                         elwt,
                         Event::Internal(InternalEvent::RouteRedrawRequest(target)),
                         &env,
-                    )
+                    );
+                    // schedules the updates that were waiting for this frame (see `cache::incremental`)
+                    app_ctx.cache.frame_presented();
                 } else {
                     warn!("unregistered window id: {:?}", window_id);
                 }
@@ -1,13 +1,13 @@
 //! GUI positional cache.
 use crate::{
-    call_id::{CallId, CallIdStack, CallNode},
-    composable, Data, Environment,
+    call_id::{CallId, CallIdStack, CallIdStackSnapshot, CallNode},
+    composable, Data, EnvKey, EnvState, EnvValue, Environment,
 };
 use parking_lot::Mutex;
 use std::{
     any::Any,
     cell::{Cell, RefCell},
-    collections::hash_map::DefaultHasher,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
     convert::TryInto,
     fmt,
     fmt::Write,
@@ -15,11 +15,14 @@ use std::{
     hash::{Hash, Hasher},
     mem,
     panic::Location,
+    ptr,
+    rc::Rc,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Weak,
     },
-    task::{Poll, Waker},
+    task::{Poll, RawWaker, RawWakerVTable, Waker},
+    time::Instant,
 };
 
 slotmap::new_key_type! {
@@ -138,6 +141,20 @@ impl<T: 'static> StateCell<T> {
         #[cfg(debug_assertions)] cause: (&'static Location<'static>, &str),
     ) -> T {
         self.update_dependents();
+        #[cfg(debug_assertions)]
+        let ret = self.replace_untracked(new_value, invalidate, cause);
+        #[cfg(not(debug_assertions))]
+        let ret = self.replace_untracked(new_value, invalidate);
+        ret
+    }
+
+    /// Like `replace`, but the calling scope doesn't become a dependent of the state variable.
+    fn replace_untracked(
+        &self,
+        new_value: T,
+        invalidate: bool,
+        #[cfg(debug_assertions)] cause: (&'static Location<'static>, &str),
+    ) -> T {
         let mut value = self.value.lock();
         let ret = mem::replace(&mut *value, new_value);
         if invalidate {
@@ -233,6 +250,19 @@ impl<T: 'static> State<T> {
         self.0.replace(new_value, true);
     }
 
+    /// Sets the value of the state variable, without making the calling scope depend on it.
+    ///
+    /// Use this to invalidate another part of the UI during recomposition: with `set`, the calling scope would be
+    /// recomposed along with the dependents of the variable.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub(crate) fn set_untracked(&self, new_value: T) {
+        #[cfg(debug_assertions)]
+        self.0
+            .replace_untracked(new_value, true, (Location::caller(), "state variable updated"));
+        #[cfg(not(debug_assertions))]
+        self.0.replace_untracked(new_value, true);
+    }
+
     pub fn set_without_invalidation(&self, new_value: T) {
         // TODO idea: log the call sites that invalidated the cache, for debugging
         // e.g. `state entry @ (call site) invalidated because of (state entries), because of manual invalidation @ (call site) OR invalidated externally `
@@ -277,6 +307,8 @@ enum Slot {
     StartGroup {
         call_id: CallId,
         len: u32,
+        /// Whether this is a keyed group (see `keyed`).
+        keyed: bool,
     },
     /// Marks the end of a scope.
     EndGroup,
@@ -285,11 +317,47 @@ enum Slot {
     },
 }
 
+/// A scope that can be recomposed independently of the enclosing scopes (see `restartable`).
+struct RestartableScope {
+    /// Position in the call tree of the scope.
+    call_ids: CallIdStackSnapshot,
+    /// Environment at the call site.
+    env: Environment,
+    /// Dependency node of the result of the scope. Dirty if the scope must be recomposed.
+    dep_node: Arc<DepNode>,
+    /// Runs the scope again.
+    body: Rc<dyn Fn()>,
+}
+
+/// A state update deferred until a frame has been presented (see `incremental`).
+struct DeferredUpdate {
+    /// Dropped when the update is cancelled: the call site was removed from the cache, or the update was superseded.
+    alive: Weak<()>,
+    run: Box<dyn FnOnce()>,
+}
+
+/// A computation scheduled in the idle lane (see `idle`).
+struct IdleTask {
+    /// Dropped when the task is cancelled: the call site was removed from the cache, or its arguments changed.
+    alive: Weak<()>,
+    run: Box<dyn FnOnce()>,
+}
+
 /// Composition cache. Contains the recorded call tree and state entries.
 struct CacheInner {
     waker: Waker,
     /// The call tree, represented as an array of slots.
     slots: Vec<Slot>,
+    /// Keyed groups removed from the slot table during the current revision, in case they reappear elsewhere.
+    detached: HashMap<CallId, Vec<Slot>>,
+    /// Restartable scopes, by call ID of their group.
+    restartable: HashMap<CallId, Rc<RestartableScope>>,
+    /// State updates deferred until the current frame has been presented (see `incremental`).
+    deferred_updates: Vec<DeferredUpdate>,
+    /// Deferred updates whose frame has been presented, applied at the start of the next recomposition.
+    due_updates: Vec<DeferredUpdate>,
+    /// Computations that run only when the event loop has time left in a frame (see `idle`).
+    idle_tasks: VecDeque<IdleTask>,
     /// The number of times `Cache::run` has been called.
     revision: usize,
 }
@@ -299,12 +367,50 @@ impl CacheInner {
         CacheInner {
             waker,
             slots: vec![],
+            detached: HashMap::new(),
+            restartable: HashMap::new(),
+            deferred_updates: vec![],
+            due_updates: vec![],
+            idle_tasks: VecDeque::new(),
             revision: 0,
         }
     }
 
     //fn create_state_proxy(&self, key: Key<T>) ->
 
+    /// Returns the restartable scope that must be recomposed and that comes first in the slot table,
+    /// along with its call ID and the position of the group containing its contents.
+    fn next_dirty_restartable_scope(&self) -> Option<(CallId, usize, Rc<RestartableScope>)> {
+        if !self.restartable.values().any(|scope| scope.dep_node.is_dirty()) {
+            return None;
+        }
+        // scopes are recomposed in order, so that a scope is recomposed before the scopes nested in it
+        self.slots.iter().enumerate().find_map(|(i, slot)| match *slot {
+            Slot::StartGroup { call_id, .. } => {
+                let scope = self.restartable.get(&call_id)?;
+                // skip the group start, the argument and the result slots
+                scope.dep_node.is_dirty().then(|| (call_id, i + 3, scope.clone()))
+            }
+            _ => None,
+        })
+    }
+
+    /// Removes the restartable scopes that don't appear in the slot table anymore.
+    fn remove_dead_restartable_scopes(&mut self) {
+        if self.restartable.is_empty() {
+            return;
+        }
+        let live_groups: HashSet<CallId> = self
+            .slots
+            .iter()
+            .filter_map(|slot| match *slot {
+                Slot::StartGroup { call_id, .. } => Some(call_id),
+                _ => None,
+            })
+            .collect();
+        self.restartable.retain(|call_id, _| live_groups.contains(call_id));
+    }
+
     fn dump(&self, current_position: usize) {
         for (i, s) in self.slots.iter().enumerate() {
             if i == current_position {
@@ -313,13 +419,14 @@ impl CacheInner {
                 eprint!("  ");
             }
             match s {
-                Slot::StartGroup { call_id, len } => {
+                Slot::StartGroup { call_id, len, keyed } => {
                     eprintln!(
-                        "{:3} StartGroup call_id={:?} len={} (end={})",
+                        "{:3} StartGroup call_id={:?} len={} (end={}){}",
                         i,
                         call_id,
                         *len,
                         i + *len as usize - 1,
+                        if *keyed { " keyed" } else { "" }
                     )
                 }
                 Slot::EndGroup => {
@@ -367,6 +474,8 @@ struct CacheWriter {
     group_stack: Vec<usize>,
     /// Stack of dependent state variables.
     state_stack: Vec<Arc<StateCell<dyn Any>>>,
+    /// IDs of the keyed groups entered by this writer, to detect duplicate keys (see `keyed`).
+    keyed_ids: HashSet<CallId>,
 }
 
 impl CacheWriter {
@@ -377,6 +486,7 @@ impl CacheWriter {
             id_stack: CallIdStack::new(),
             group_stack: vec![],
             state_stack: vec![],
+            keyed_ids: HashSet::new(),
         };
 
         writer.enter_scope(root_location, 0);
@@ -411,11 +521,58 @@ impl CacheWriter {
         self.exit_scope();
         assert!(self.group_stack.is_empty(), "unbalanced groups");
         assert!(self.id_stack.is_empty(), "unbalanced scopes");
+        // keyed groups that haven't reappeared during this revision are dead
+        if !self.cache.detached.is_empty() {
+            trace!("removing {} unused keyed group(s)", self.cache.detached.len());
+            self.cache.detached.clear();
+        }
         // may not be true anymore (we can have multiple roots on a single cache)
         //assert_eq!(self.pos, self.cache.slots.len());
         (self.cache, should_rerun)
     }
 
+    /// Creates a writer that recomposes the contents of a restartable scope.
+    ///
+    /// `pos` is the position of the group containing the contents of the scope.
+    fn new_partial(cache: CacheInner, pos: usize, call_ids: &CallIdStackSnapshot) -> CacheWriter {
+        CacheWriter {
+            cache,
+            pos,
+            id_stack: CallIdStack::from_snapshot(call_ids),
+            group_stack: vec![],
+            state_stack: vec![],
+            keyed_ids: HashSet::new(),
+        }
+    }
+
+    /// Finishes the recomposition of a restartable scope.
+    ///
+    /// `start` is the position of the group containing the contents of the scope, and `prev_len` the length
+    /// of the group before recomposition.
+    fn finish_partial(mut self, start: usize, prev_len: u32) -> CacheInner {
+        assert!(self.group_stack.is_empty(), "unbalanced groups");
+        assert!(self.state_stack.is_empty(), "unbalanced state scopes");
+        let len = match self.cache.slots[start] {
+            Slot::StartGroup { len, .. } => len,
+            _ => panic!("expected group start"),
+        };
+        // the enclosing groups haven't been visited: update their lengths here
+        if len != prev_len {
+            for i in 0..start {
+                if let Slot::StartGroup {
+                    len: ref mut group_len, ..
+                } = self.cache.slots[i]
+                {
+                    if i + *group_len as usize > start {
+                        *group_len = *group_len + len - prev_len;
+                    }
+                }
+            }
+        }
+        self.cache.detached.clear();
+        self.cache
+    }
+
     fn enter_scope(&mut self, location: &'static Location<'static>, index: usize) {
         self.id_stack.enter(location, index);
     }
@@ -511,6 +668,7 @@ impl CacheWriter {
                 Slot::StartGroup {
                     call_id,
                     len: 2, // 2 = initial length of group (start+end slots)
+                    keyed: false,
                 },
             );
             self.cache.slots.insert(self.pos + 1, Slot::EndGroup);
@@ -521,6 +679,68 @@ impl CacheWriter {
         self.pos += 1;
     }
 
+    /// Starts a keyed group.
+    ///
+    /// If the group isn't found in the current group, it is moved from the slots that haven't been visited yet
+    /// in this revision, or restored from the keyed groups removed earlier in this revision.
+    fn start_keyed_group(&mut self) {
+        let call_id = self.id_stack.current();
+        if !self.sync() {
+            if let Some(slots) = self.cache.detached.remove(&call_id) {
+                trace!("restoring keyed group {:?}", call_id);
+                self.cache.slots.splice(self.pos..self.pos, slots);
+            } else if !self.relocate_keyed_group(call_id) {
+                self.cache.slots.insert(
+                    self.pos,
+                    Slot::StartGroup {
+                        call_id,
+                        len: 2,
+                        keyed: true,
+                    },
+                );
+                self.cache.slots.insert(self.pos + 1, Slot::EndGroup);
+            }
+        }
+
+        self.group_stack.push(self.pos);
+        self.pos += 1;
+    }
+
+    /// Looks for a keyed group in the slots after the current position (not visited yet in this revision),
+    /// and moves it to the current position.
+    fn relocate_keyed_group(&mut self, call_id: CallId) -> bool {
+        let slots = &mut self.cache.slots;
+        let found = (self.pos..slots.len()).find_map(|i| match slots[i] {
+            Slot::StartGroup {
+                call_id: this_call_id,
+                len,
+                keyed: true,
+            } if this_call_id == call_id => Some((i, len)),
+            _ => None,
+        });
+        let (start, len) = match found {
+            Some(found) => found,
+            None => return false,
+        };
+
+        trace!("relocating keyed group {:?}", call_id);
+        // update the length of the enclosing groups that haven't been entered yet
+        // (the lengths of the groups on the group stack are recalculated in `end_group`)
+        for i in self.pos..start {
+            if let Slot::StartGroup {
+                len: ref mut group_len, ..
+            } = slots[i]
+            {
+                if i + *group_len as usize > start {
+                    *group_len -= len;
+                }
+            }
+        }
+        let group: Vec<Slot> = slots.drain(start..start + len as usize).collect();
+        slots.splice(self.pos..self.pos, group);
+        true
+    }
+
     fn dump(&self) {
         eprintln!("position : {}", self.pos);
         eprintln!("stack    : {:?}", self.group_stack);
@@ -550,14 +770,34 @@ impl CacheWriter {
         let group_end_pos = self.group_end_position();
 
         // remove the extra slots, and associated entries
-        for slot in self.cache.slots.drain(self.pos..group_end_pos) {
-            if let Slot::Value { var } = slot {
-                trace!(
-                    "removing cache entry dep_node={:?} call_id={:?}, call_node={:#?}",
-                    var.dep_node,
-                    var.call_id,
-                    var.call_node
-                );
+        // keyed groups are set aside until the end of the revision, in case they reappear elsewhere
+        let mut dead_slots = self
+            .cache
+            .slots
+            .drain(self.pos..group_end_pos)
+            .collect::<Vec<_>>()
+            .into_iter();
+        while let Some(slot) = dead_slots.next() {
+            match slot {
+                Slot::StartGroup {
+                    call_id,
+                    len,
+                    keyed: true,
+                } => {
+                    let mut group = Vec::with_capacity(len as usize);
+                    group.push(slot);
+                    group.extend(dead_slots.by_ref().take(len as usize - 1));
+                    self.cache.detached.insert(call_id, group);
+                }
+                Slot::Value { var } => {
+                    trace!(
+                        "removing cache entry dep_node={:?} call_id={:?}, call_node={:#?}",
+                        var.dep_node,
+                        var.call_id,
+                        var.call_node
+                    );
+                }
+                _ => {}
             }
         }
 
@@ -731,14 +971,27 @@ thread_local! {
     static CACHE_CONTEXT: RefCell<Option<CacheContext>> = RefCell::new(None);
 }
 
+/// Returns a waker that does nothing, for caches that are recomposed explicitly (offscreen rendering, tests).
+pub(crate) fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+}
+
 pub struct Cache {
     inner: Option<CacheInner>,
+    /// Restartable scopes recomposed independently of the root since the last call to `take_recomposed_scopes`.
+    recomposed_scopes: Vec<CallId>,
 }
 
 impl Cache {
     pub fn new(waker: Waker) -> Cache {
         Cache {
             inner: Some(CacheInner::new(waker)),
+            recomposed_scopes: vec![],
         }
     }
 
@@ -750,6 +1003,14 @@ impl Cache {
             let mut result;
             let mut inner = self.inner.take().unwrap();
 
+            // apply the updates that were waiting for a frame to be presented: they invalidate their call sites,
+            // which are recomposed below
+            for update in mem::take(&mut inner.due_updates) {
+                if update.alive.strong_count() > 0 {
+                    (update.run)();
+                }
+            }
+
             loop {
                 inner.revision += 1;
 
@@ -778,11 +1039,81 @@ impl Cache {
                 break;
             }
 
+            // recompose the restartable scopes that have been invalidated without invalidating the root
+            while let Some((call_id, start, scope)) = inner.next_dirty_restartable_scope() {
+                trace!("recomposing restartable scope {:?}", call_id);
+                inner.revision += 1;
+                let prev_len = match inner.slots[start] {
+                    Slot::StartGroup { len, .. } => len,
+                    _ => panic!("expected group start"),
+                };
+
+                {
+                    let mut cx = cx_cell.borrow_mut();
+                    cx.replace(CacheContext {
+                        writer: CacheWriter::new_partial(inner, start, &scope.call_ids),
+                        env: scope.env.clone(),
+                    });
+                }
+
+                (scope.body)();
+
+                let mut cx = cx_cell.borrow_mut();
+                inner = cx.take().unwrap().writer.finish_partial(start, prev_len);
+                if !self.recomposed_scopes.contains(&call_id) {
+                    self.recomposed_scopes.push(call_id);
+                }
+            }
+
+            inner.remove_dead_restartable_scopes();
             self.inner = Some(inner);
             result
         })
     }
 
+    /// Returns the call IDs of the restartable scopes that have been recomposed independently of the root
+    /// since the last call to this function.
+    ///
+    /// Widgets showing the result of those scopes should be notified (see `Subtree`).
+    pub(crate) fn take_recomposed_scopes(&mut self) -> Vec<CallId> {
+        mem::take(&mut self.recomposed_scopes)
+    }
+
+    /// Signals that the frame showing the result of the last recompositions has been presented.
+    ///
+    /// This is called by the event loop after repainting a window. The state updates that were deferred until then
+    /// (see `incremental`) are applied at the start of the next recomposition, which is scheduled with the waker
+    /// of the cache. Updates whose call site has been removed from the cache are dropped.
+    pub(crate) fn frame_presented(&mut self) {
+        let inner = self.inner.as_mut().unwrap();
+        let updates = mem::take(&mut inner.deferred_updates);
+        inner
+            .due_updates
+            .extend(updates.into_iter().filter(|update| update.alive.strong_count() > 0));
+        if !inner.due_updates.is_empty() {
+            inner.waker.wake_by_ref();
+        }
+    }
+
+    /// Runs the tasks of the idle lane (see `idle`) until `deadline`. Returns whether tasks remain.
+    ///
+    /// This is called by the event loop once the frame has been presented. Tasks whose call site has been removed
+    /// from the cache are dropped without running.
+    pub(crate) fn run_idle_tasks(&mut self, deadline: Instant) -> bool {
+        while Instant::now() < deadline {
+            let task = match self.inner.as_mut().unwrap().idle_tasks.pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+            if task.alive.strong_count() > 0 {
+                (task.run)();
+            }
+        }
+        let idle_tasks = &mut self.inner.as_mut().unwrap().idle_tasks;
+        idle_tasks.retain(|task| task.alive.strong_count() > 0);
+        !idle_tasks.is_empty()
+    }
+
     pub fn dump(&self) {
         self.inner.as_ref().unwrap().dump(0)
     }
@@ -811,6 +1142,16 @@ fn parent_state() -> Option<Arc<StateCell>> {
     })
 }
 
+/// Returns the source location of the innermost composable function call, or `None` if not called
+/// during recomposition.
+pub(crate) fn current_call_location() -> Option<&'static Location<'static>> {
+    CACHE_CONTEXT.with(|cx_cell| {
+        let cx = cx_cell.try_borrow().ok()?;
+        let node = cx.as_ref()?.writer.id_stack.current_call_node()?;
+        Some(node.location)
+    })
+}
+
 /// Returns the current call identifier.
 pub fn current_call_id() -> CallId {
     with_cache_cx(|cx| cx.writer.id_stack.current())
@@ -868,6 +1209,29 @@ pub fn with_environment<R>(env: Environment, f: impl FnOnce() -> R) -> R {
     r
 }
 
+/// Provides a writable environment value to the composables called in `f`.
+///
+/// On the first call, the value is initialized with `init`. Afterwards, the value can be changed with the
+/// returned `EnvState`, or from within the scope by retrieving the slot from the environment with `EnvKey::state`
+/// (e.g. in an event handler). Changing the value invalidates this scope.
+#[track_caller]
+pub fn with_env_state<T: EnvValue, R>(
+    key: &EnvKey<T>,
+    init: impl FnOnce() -> T,
+    f: impl FnOnce(EnvState<T>) -> R,
+) -> R {
+    // the slot and the scope depending on it live in a group identified by the environment key
+    scoped(key.name(), || {
+        group(|| {
+            let state = EnvState::new(state(init));
+            let mut env = Environment::new();
+            // reads the state within the group: the enclosing scope now depends on it
+            env.set_state(key, state.clone());
+            with_environment(env, || f(state))
+        })
+    })
+}
+
 #[track_caller]
 pub fn changed<T: Data>(value: T) -> bool {
     let location = Location::caller();
@@ -965,6 +1329,128 @@ pub fn group<R>(f: impl FnOnce() -> R) -> R {
     r
 }
 
+/// Runs a function in a scope identified by a key instead of its position in the call tree.
+///
+/// The state created within `f` follows the key: if the scope moves to another position during a recomposition,
+/// including under another parent scope (e.g. an item moved from one list to another), the state entries (scroll
+/// offsets, text contents, expansion state, widget IDs...) are moved to the new position instead of being recreated.
+/// The state is released if the key doesn't reappear by the end of the recomposition.
+///
+/// Keys are scoped to the location of the call to `keyed`, but not to the parent scope: for the state of an item to
+/// follow it from one list to another, both lists must create their items at the same call site (e.g. in a shared
+/// function). Keys must be unique among the scopes created at a call site: if the same key appears twice during a
+/// recomposition, the second scope gets its own state, which doesn't follow it when it moves.
+#[track_caller]
+pub fn keyed<R>(key: impl Hash, f: impl FnOnce() -> R) -> R {
+    let location = Location::caller();
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    let key = hasher.finish();
+    let unique = with_cache_cx(|cx| {
+        if cx.writer.keyed_ids.insert(CallIdStack::keyed_call_id(location, key)) {
+            cx.writer.id_stack.enter_keyed(location, key);
+            cx.writer.start_keyed_group();
+            true
+        } else {
+            warn!("duplicate key in keyed scope at {}", location);
+            cx.writer.enter_scope(location, key as usize);
+            cx.writer.start_group();
+            false
+        }
+    });
+    let r = f();
+    with_cache_cx(|cx| {
+        cx.writer.end_group();
+        if unique {
+            cx.writer.id_stack.exit_keyed();
+        } else {
+            cx.writer.exit_scope();
+        }
+    });
+    r
+}
+
+/// Creates `count` items in chunks, spreading their creation over several frames.
+///
+/// The first `chunk_size` items are created immediately. Then, in the recomposition that follows the presentation
+/// of a frame, `chunk_size` more items are created, and so on until all items are. Returns the items created so far, in order: the view showing
+/// them fills progressively instead of blocking the UI until all items are created. Use this to build large views
+/// (e.g. documents with thousands of rows).
+///
+/// Each item is created in a scope identified by its index. Once created, items stay created: if `count` grows,
+/// only the new items are created incrementally. If the call site is removed from the cache before all items are
+/// created, the creation of the remaining items is cancelled.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+#[track_caller]
+pub fn incremental<T>(count: usize, chunk_size: usize, mut f: impl FnMut(usize) -> T) -> Vec<T> {
+    assert!(chunk_size > 0, "chunk size must be greater than zero");
+    group(move || {
+        let created_count = state(|| 0);
+        // the pending update of `created_count`, cancelled when this entry is dropped
+        let pending: State<Option<(usize, Arc<()>)>> = state(|| None);
+        let n = created_count.get().max(chunk_size).min(count);
+        let items = (0..n).map(|i| scoped(i, || f(i))).collect();
+        if n < count {
+            // create the next chunk after the next frame, unless it's already scheduled
+            let next = n + chunk_size;
+            if !matches!(pending.get(), Some((scheduled, _)) if scheduled == next) {
+                let token = Arc::new(());
+                let alive = Arc::downgrade(&token);
+                pending.set_without_invalidation(Some((next, token)));
+                with_cache_cx(|cx| {
+                    cx.writer.cache.deferred_updates.push(DeferredUpdate {
+                        alive,
+                        run: Box::new(move || created_count.set(next)),
+                    })
+                });
+            }
+        } else {
+            pending.set_without_invalidation(None);
+        }
+        items
+    })
+}
+
+/// Computes a value in the idle lane, i.e. only when the event loop has time left in a frame.
+///
+/// Returns `None` until the value for the current `args` has been computed. `f` runs on the UI thread, outside of
+/// recomposition, after a frame has been presented and only if the frame budget isn't exhausted; the result then
+/// triggers a recomposition of the call site. Use this for expensive content that can appear later without
+/// blocking the UI (e.g. thumbnails, syntax highlighting of lines that are not visible yet).
+///
+/// The computation is cancelled if the call site is removed from the cache (e.g. the widget showing it is not
+/// created anymore) or if `args` change before it has run. `f` shouldn't call composable functions.
+#[track_caller]
+pub fn idle<Args: Data, T: Clone + 'static>(args: Args, f: impl FnOnce(&Args) -> T + 'static) -> Option<T> {
+    group(move || {
+        let result: State<Option<T>> = state(|| None);
+        // the task is cancelled when this entry is dropped
+        let pending: State<Option<Arc<()>>> = state(|| None);
+
+        if changed(args.clone()) {
+            result.set_without_invalidation(None);
+            pending.set_without_invalidation(None);
+        }
+
+        let value = result.get();
+        if value.is_none() && pending.get().is_none() {
+            let token = Arc::new(());
+            let alive = Arc::downgrade(&token);
+            pending.set_without_invalidation(Some(token));
+            with_cache_cx(|cx| {
+                cx.writer.cache.idle_tasks.push_back(IdleTask {
+                    alive,
+                    run: Box::new(move || result.set(Some(f(&args)))),
+                })
+            });
+        }
+        value
+    })
+}
+
 pub fn skip_to_end_of_group() {
     with_cache_cx(|cx| {
         cx.writer.skip_until_end_of_group();
@@ -1009,6 +1495,110 @@ pub fn memoize<Args: Data, T: Clone + 'static>(args: Args, f: impl FnOnce() -> T
     })
 }
 
+/// Handle to the result of a restartable scope (see `restartable`).
+pub struct Restartable<T> {
+    call_id: CallId,
+    result: State<Option<T>>,
+}
+
+impl<T> Clone for Restartable<T> {
+    fn clone(&self) -> Self {
+        Restartable {
+            call_id: self.call_id,
+            result: self.result.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Restartable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Restartable").field(&self.call_id).finish()
+    }
+}
+
+impl<T: Clone + 'static> Restartable<T> {
+    /// Returns the latest result of the scope.
+    ///
+    /// Unlike `State::get`, this doesn't make the current scope dependent on the result:
+    /// it is not recomposed when the restartable scope is.
+    pub fn get(&self) -> T {
+        self.result
+            .0
+            .value
+            .lock()
+            .clone()
+            .expect("restartable: no value calculated")
+    }
+
+    /// Returns the call ID of the scope.
+    pub(crate) fn call_id(&self) -> CallId {
+        self.call_id
+    }
+}
+
+/// Runs a function in a scope that can be recomposed independently of the enclosing scopes.
+///
+/// When a state variable read in `f` changes, only `f` is called again, during the next recomposition:
+/// the enclosing scopes aren't invalidated, and their cached results are kept. The new result
+/// must be retrieved with `Restartable::get` outside of composition (usually in a widget, see `Subtree`).
+///
+/// Like `memoize`, `f` is also called again when `args` change.
+#[track_caller]
+pub fn restartable<Args: Data, T: Clone + 'static>(args: Args, f: impl Fn(&Args) -> T + 'static) -> Restartable<T> {
+    let location = Location::caller();
+    group(move || {
+        let (call_id, result_key, args_changed, dirty) = with_cache_cx(|cx| {
+            let call_id = cx.writer.id_stack.current();
+            let args_changed = cx.writer.compare_and_update(args.clone());
+            let CacheEntryInsertResult {
+                key: result_key, dirty, ..
+            } = cx.writer.get_or_insert_entry(|| None);
+            (call_id, result_key, args_changed, dirty)
+        });
+
+        let body: Rc<dyn Fn()> = {
+            let result_key = result_key.clone();
+            Rc::new(move || {
+                with_cache_cx(|cx| {
+                    cx.writer.enter_scope(location, 1);
+                    cx.writer.start_group();
+                    cx.writer.start_state(&result_key);
+                });
+                let result = f(&args);
+                with_cache_cx(|cx| {
+                    cx.writer.end_state();
+                    cx.writer.end_group();
+                    cx.writer.exit_scope();
+                });
+                // don't go through `State::set_without_invalidation`, which would make the parent scope
+                // dependent on the result
+                *result_key.0.value.lock() = Some(result);
+            })
+        };
+
+        if args_changed || dirty || result_key.0.value.lock().is_none() {
+            body();
+        } else {
+            skip_to_end_of_group();
+        }
+
+        with_cache_cx(|cx| {
+            let scope = RestartableScope {
+                call_ids: cx.writer.id_stack.snapshot(),
+                env: cx.env.clone(),
+                dep_node: result_key.0.dep_node.clone(),
+                body,
+            };
+            cx.writer.cache.restartable.insert(call_id, Rc::new(scope));
+        });
+
+        Restartable {
+            call_id,
+            result: result_key,
+        }
+    })
+}
+
 /// Runs the function only once at the call site and caches the result (like memoize without parameters).
 /// TODO better docs
 #[track_caller]
@@ -1186,3 +1776,451 @@ mod tests {
         }
     }
 }*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_registered(cache: &Cache, call_id: CallId) -> bool {
+        cache.inner.as_ref().unwrap().restartable.contains_key(&call_id)
+    }
+
+    #[test]
+    fn env_state_slots() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let first: EnvKey<f64> = EnvKey::new(crate::Atom::from("test-env-state-first"));
+        let second: EnvKey<f64> = EnvKey::new(crate::Atom::from("test-env-state-second"));
+        let runs = Rc::new(Cell::new(0));
+
+        let ui = || {
+            memoize((), || {
+                // same call site for both keys
+                [&first, &second]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &key)| {
+                        with_env_state(
+                            key,
+                            || i as f64,
+                            |slot| {
+                                runs.set(runs.get() + 1);
+                                (slot, key.get(&environment()).unwrap())
+                            },
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let slots = cache.recompose(&env, ui);
+        assert_eq!(slots[0].1, 0.0);
+        assert_eq!(slots[1].1, 1.0);
+        assert_eq!(runs.get(), 2);
+
+        // the slot set from outside (e.g. from an event handler) invalidates the scope providing it
+        slots[1].0.set(5.0);
+        let slots = cache.recompose(&env, ui);
+        assert_eq!(slots[0].1, 0.0);
+        assert_eq!(slots[1].1, 5.0);
+        assert_eq!(runs.get(), 4);
+    }
+
+    #[test]
+    fn restartable_nested_partial_recomposition() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let root_runs = Rc::new(Cell::new(0));
+        let outer_runs = Rc::new(Cell::new(0));
+        let inner_runs = Rc::new(Cell::new(0));
+
+        let ui = || {
+            memoize((), || {
+                root_runs.set(root_runs.get() + 1);
+                let outer_runs = outer_runs.clone();
+                let inner_runs = inner_runs.clone();
+                group(|| {
+                    scoped(0, || {
+                        restartable((), move |_| {
+                            outer_runs.set(outer_runs.get() + 1);
+                            let outer_value = state(|| 1);
+                            let inner_runs = inner_runs.clone();
+                            let inner = group(|| {
+                                restartable((), move |_| {
+                                    inner_runs.set(inner_runs.get() + 1);
+                                    let inner_value = state(|| 10);
+                                    (inner_value.clone(), inner_value.get())
+                                })
+                            });
+                            (outer_value.clone(), outer_value.get(), inner)
+                        })
+                    })
+                })
+            })
+        };
+
+        let outer = cache.recompose(&env, ui);
+        let (outer_state, _, inner) = outer.get();
+        let (inner_state, _) = inner.get();
+        assert_eq!(outer.get().1, 1);
+        assert_eq!(inner.get().1, 10);
+        assert_eq!((root_runs.get(), outer_runs.get(), inner_runs.get()), (1, 1, 1));
+        assert!(cache.take_recomposed_scopes().is_empty());
+
+        // only the inner scope is recomposed
+        inner_state.set(11);
+        cache.recompose(&env, ui);
+        assert_eq!(inner.get().1, 11);
+        assert_eq!((root_runs.get(), outer_runs.get(), inner_runs.get()), (1, 1, 2));
+        assert_eq!(cache.take_recomposed_scopes(), vec![inner.call_id()]);
+
+        // the outer scope is recomposed, the inner scope is skipped and keeps its state
+        outer_state.set(2);
+        cache.recompose(&env, ui);
+        assert_eq!(outer.get().1, 2);
+        assert_eq!(inner.get().1, 11);
+        assert_eq!((root_runs.get(), outer_runs.get(), inner_runs.get()), (1, 2, 2));
+        assert_eq!(cache.take_recomposed_scopes(), vec![outer.call_id()]);
+
+        // both are dirty: the inner scope is recomposed once, as part of the outer scope
+        outer_state.set(3);
+        inner_state.set(12);
+        cache.recompose(&env, ui);
+        assert_eq!(outer.get().1, 3);
+        assert_eq!(inner.get().1, 12);
+        assert_eq!((root_runs.get(), outer_runs.get(), inner_runs.get()), (1, 3, 3));
+        assert_eq!(cache.take_recomposed_scopes(), vec![outer.call_id()]);
+    }
+
+    #[test]
+    fn restartable_removed() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let runs = Rc::new(Cell::new(0));
+
+        let ui = || {
+            let show = state(|| true);
+            let runs = runs.clone();
+            let scope = show.get().then(|| {
+                restartable((), move |_| {
+                    runs.set(runs.get() + 1);
+                    let value = state(|| 0);
+                    (value.clone(), value.get())
+                })
+            });
+            (show, scope)
+        };
+
+        let (show, scope) = cache.recompose(&env, ui);
+        let scope = scope.unwrap();
+        let (value, _) = scope.get();
+        assert_eq!(runs.get(), 1);
+        assert!(is_registered(&cache, scope.call_id()));
+
+        show.set(false);
+        let (_, removed) = cache.recompose(&env, ui);
+        assert!(removed.is_none());
+        assert!(!is_registered(&cache, scope.call_id()));
+
+        // updating the state of the removed scope doesn't recompose it
+        value.set(5);
+        cache.recompose(&env, ui);
+        assert_eq!(runs.get(), 1);
+        assert!(cache.take_recomposed_scopes().is_empty());
+
+        // when it reappears, the scope starts with fresh state
+        show.set(true);
+        let (_, scope) = cache.recompose(&env, ui);
+        let scope = scope.unwrap();
+        assert_eq!(runs.get(), 2);
+        assert_eq!(scope.get().1, 0);
+        assert!(is_registered(&cache, scope.call_id()));
+    }
+
+    #[test]
+    fn restartable_state_created_after_partial_restart() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let root_revision = Cell::new(0);
+        let runs = Rc::new(Cell::new(0));
+
+        let ui = || {
+            memoize(root_revision.get(), || {
+                let runs = runs.clone();
+                let scope = scoped(0, || {
+                    restartable((), move |_| {
+                        runs.set(runs.get() + 1);
+                        let extended = state(|| false);
+                        let extra = extended.get().then(|| state(|| 42));
+                        let extra_value = extra.as_ref().map(State::get);
+                        (extended, extra, extra_value)
+                    })
+                });
+                // state after the scope, to check that the enclosing groups are still consistent
+                let trailing = state(|| 0);
+                (scope, trailing)
+            })
+        };
+
+        let (scope, trailing) = cache.recompose(&env, ui);
+        let (extended, extra, _) = scope.get();
+        assert!(extra.is_none());
+        trailing.set_without_invalidation(7);
+
+        // the new state variable is inserted during the partial recomposition
+        extended.set(true);
+        cache.recompose(&env, ui);
+        let (_, extra, extra_value) = scope.get();
+        let extra = extra.unwrap();
+        assert_eq!(extra_value, Some(42));
+        assert_eq!(runs.get(), 2);
+
+        // and it persists across the following partial recompositions
+        extra.set(43);
+        cache.recompose(&env, ui);
+        assert_eq!(scope.get().2, Some(43));
+        assert_eq!(runs.get(), 3);
+
+        // recompose the root: the scope is skipped and the state after it is preserved
+        root_revision.set(1);
+        let (scope_after, trailing_after) = cache.recompose(&env, ui);
+        assert_eq!(scope_after.call_id(), scope.call_id());
+        assert_eq!(scope_after.get().2, Some(43));
+        assert_eq!(trailing_after.get(), 7);
+        assert_eq!(runs.get(), 3);
+
+        // remove the state variable in a partial recomposition, then recompose the root again
+        extended.set(false);
+        cache.recompose(&env, ui);
+        assert_eq!(scope.get().2, None);
+        root_revision.set(2);
+        let (_, trailing_after) = cache.recompose(&env, ui);
+        assert_eq!(trailing_after.get(), 7);
+        assert_eq!(runs.get(), 4);
+    }
+
+    #[test]
+    fn keyed_state_follows_items() {
+        fn item_list(items: &[u32]) -> Vec<State<u32>> {
+            items.iter().map(|&item| keyed(item, || state(|| item * 10))).collect()
+        }
+
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let lists = RefCell::new((Vec::<u32>::new(), Vec::<u32>::new()));
+        let ui = || {
+            let (a, b) = lists.borrow().clone();
+            let a = group(|| item_list(&a));
+            let b = group(|| item_list(&b));
+            (a, b)
+        };
+        let mut compose = |a: &[u32], b: &[u32]| {
+            *lists.borrow_mut() = (a.to_vec(), b.to_vec());
+            cache.recompose(&env, ui)
+        };
+        let values = |(a, b): (Vec<State<u32>>, Vec<State<u32>>)| {
+            let get = |states: Vec<State<u32>>| states.iter().map(State::get).collect::<Vec<_>>();
+            (get(a), get(b))
+        };
+
+        {
+            let (a, b) = compose(&[1, 2, 3], &[4]);
+            for state in a.iter().chain(&b) {
+                state.set_without_invalidation(state.get() + 1);
+            }
+        }
+
+        // reordered within a list
+        assert_eq!(values(compose(&[3, 1, 2], &[4])), (vec![31, 11, 21], vec![41]));
+        // moved to a list composed later
+        assert_eq!(values(compose(&[3, 1], &[2, 4])), (vec![31, 11], vec![21, 41]));
+        // moved to a list composed earlier
+        assert_eq!(values(compose(&[4, 3, 1], &[2])), (vec![41, 31, 11], vec![21]));
+
+        // removed: the state is released, and recreated if the item comes back
+        let released = Arc::downgrade(&compose(&[4, 3, 1], &[2]).0[2].0);
+        assert_eq!(values(compose(&[4, 3], &[2])), (vec![41, 31], vec![21]));
+        assert!(released.upgrade().is_none());
+        assert_eq!(values(compose(&[4, 3, 1], &[2])), (vec![41, 31, 10], vec![21]));
+    }
+
+    #[test]
+    fn keyed_duplicate_keys() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let ui = || {
+            [1, 1]
+                .iter()
+                .map(|&item| keyed(item, || state(|| item)))
+                .collect::<Vec<_>>()
+        };
+
+        let states = cache.recompose(&env, ui);
+        states[1].set_without_invalidation(2);
+        // the second scope has its own state
+        let states = cache.recompose(&env, ui);
+        assert_eq!(states[0].get(), 1);
+        assert_eq!(states[1].get(), 2);
+    }
+
+    fn live_deferred_updates(cache: &Cache) -> usize {
+        let inner = cache.inner.as_ref().unwrap();
+        inner
+            .deferred_updates
+            .iter()
+            .chain(inner.due_updates.iter())
+            .filter(|update| update.alive.strong_count() > 0)
+            .count()
+    }
+
+    #[test]
+    fn incremental_chunks() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let count = Cell::new(5);
+        let ui = || incremental(count.get(), 2, |i| i);
+
+        assert_eq!(cache.recompose(&env, ui), [0, 1]);
+        // no new chunk until a frame has been presented, and the next chunk is only scheduled once
+        assert_eq!(cache.recompose(&env, ui), [0, 1]);
+        assert_eq!(live_deferred_updates(&cache), 1);
+
+        cache.frame_presented();
+        assert_eq!(cache.recompose(&env, ui), [0, 1, 2, 3]);
+        cache.frame_presented();
+        assert_eq!(cache.recompose(&env, ui), [0, 1, 2, 3, 4]);
+        assert_eq!(live_deferred_updates(&cache), 0);
+
+        // only the new items are created incrementally
+        count.set(10);
+        assert_eq!(cache.recompose(&env, ui), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(live_deferred_updates(&cache), 1);
+
+        // shrinking the count cancels the pending chunk
+        count.set(3);
+        assert_eq!(cache.recompose(&env, ui), [0, 1, 2]);
+        assert_eq!(live_deferred_updates(&cache), 0);
+        cache.frame_presented();
+        assert_eq!(cache.recompose(&env, ui), [0, 1, 2]);
+    }
+
+    #[test]
+    fn incremental_evicted() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let show = Cell::new(true);
+        let created = Rc::new(Cell::new(0));
+
+        let ui = || {
+            show.get().then(|| {
+                incremental(10, 2, |i| {
+                    created.set(created.get() + 1);
+                    i
+                })
+            })
+        };
+
+        assert_eq!(cache.recompose(&env, ui), Some(vec![0, 1]));
+        assert_eq!(live_deferred_updates(&cache), 1);
+
+        // the call site is removed before the frame is presented: the next chunk is cancelled
+        show.set(false);
+        assert_eq!(cache.recompose(&env, ui), None);
+        assert_eq!(live_deferred_updates(&cache), 0);
+        cache.frame_presented();
+        assert!(cache.inner.as_ref().unwrap().due_updates.is_empty());
+        assert_eq!(cache.recompose(&env, ui), None);
+        assert_eq!(created.get(), 2);
+
+        // when it reappears, the items are created from the start
+        show.set(true);
+        assert_eq!(cache.recompose(&env, ui), Some(vec![0, 1]));
+    }
+
+    /// Deadline far enough to run all the idle tasks.
+    fn no_deadline() -> Instant {
+        Instant::now() + std::time::Duration::from_secs(60)
+    }
+
+    #[test]
+    fn idle_cancelled_when_args_change() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let arg = Cell::new(1);
+        let runs = Rc::new(RefCell::new(vec![]));
+
+        let ui = || {
+            let runs = runs.clone();
+            idle(arg.get(), move |&arg| {
+                runs.borrow_mut().push(arg);
+                arg * 10
+            })
+        };
+
+        assert_eq!(cache.recompose(&env, ui), None);
+        arg.set(2);
+        assert_eq!(cache.recompose(&env, ui), None);
+        // only the task for the current arguments runs
+        assert!(!cache.run_idle_tasks(no_deadline()));
+        assert_eq!(*runs.borrow(), [2]);
+        assert_eq!(cache.recompose(&env, ui), Some(20));
+    }
+
+    #[test]
+    fn idle_cancelled_when_evicted() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let show = Cell::new(true);
+        let runs = Rc::new(Cell::new(0));
+
+        let ui = || {
+            let runs = runs.clone();
+            show.get().then(|| idle((), move |_| runs.set(runs.get() + 1)))
+        };
+
+        assert_eq!(cache.recompose(&env, ui), Some(None));
+        show.set(false);
+        cache.recompose(&env, ui);
+        assert!(!cache.run_idle_tasks(no_deadline()));
+        assert_eq!(runs.get(), 0);
+        assert!(cache.inner.as_ref().unwrap().idle_tasks.is_empty());
+    }
+
+    #[test]
+    fn idle_budget_exhausted() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let runs = Rc::new(RefCell::new(vec![]));
+
+        let ui = || {
+            (0..2)
+                .map(|i| {
+                    let runs = runs.clone();
+                    scoped(i, || {
+                        idle(i, move |&i| {
+                            runs.borrow_mut().push(i);
+                            // the first task takes the whole budget
+                            if i == 0 {
+                                std::thread::sleep(std::time::Duration::from_millis(20));
+                            }
+                            i
+                        })
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(cache.recompose(&env, ui), [None, None]);
+        // no time left: nothing runs, and the tasks remain
+        assert!(cache.run_idle_tasks(Instant::now()));
+        assert!(runs.borrow().is_empty());
+
+        // the remaining task runs in the next frame
+        assert!(cache.run_idle_tasks(Instant::now() + std::time::Duration::from_millis(10)));
+        assert_eq!(*runs.borrow(), [0]);
+        assert_eq!(cache.recompose(&env, ui), [Some(0), None]);
+        assert!(!cache.run_idle_tasks(no_deadline()));
+        assert_eq!(*runs.borrow(), [0, 1]);
+        assert_eq!(cache.recompose(&env, ui), [Some(0), Some(1)]);
+    }
+}
@@ -77,6 +77,14 @@ impl fmt::Debug for CallNode {
     }
 }
 
+/// Saved position in a call tree, from which call IDs can be generated again (see `CallIdStack::from_snapshot`).
+#[derive(Clone, Debug)]
+pub(crate) struct CallIdStackSnapshot {
+    /// The two innermost IDs of the stack (child IDs only depend on those).
+    ids: Vec<NonZeroU64>,
+    current_node: Option<Arc<CallNode>>,
+}
+
 pub(crate) struct CallIdStack {
     id_stack: Vec<NonZeroU64>,
     nodes: HashMap<CallId, Arc<CallNode>>,
@@ -93,6 +101,27 @@ impl CallIdStack {
         }
     }
 
+    /// Creates a stack positioned at a previously saved position in the call tree.
+    ///
+    /// IDs generated from the returned stack are the same as the ones that would have been generated
+    /// from the stack at the time of the snapshot. Scopes entered before the snapshot can't be exited.
+    pub fn from_snapshot(snapshot: &CallIdStackSnapshot) -> CallIdStack {
+        CallIdStack {
+            id_stack: snapshot.ids.clone(),
+            nodes: Default::default(),
+            current_node: snapshot.current_node.clone(),
+        }
+    }
+
+    /// Saves the current position in the call tree.
+    pub fn snapshot(&self) -> CallIdStackSnapshot {
+        let start = self.id_stack.len().saturating_sub(2);
+        CallIdStackSnapshot {
+            ids: self.id_stack[start..].to_vec(),
+            current_node: self.current_node.clone(),
+        }
+    }
+
     fn chain_hash<H: Hash>(&self, s: &H) -> u64 {
         let stacklen = self.id_stack.len();
         let key1 = if stacklen >= 2 {
@@ -128,6 +157,41 @@ impl CallIdStack {
         id
     }
 
+    /// Returns the ID of the scopes entered with `enter_keyed` at the specified location with the specified key.
+    pub fn keyed_call_id(location: &'static Location<'static>, key: u64) -> CallId {
+        let mut hasher = DefaultHasher::new();
+        "keyed".hash(&mut hasher);
+        location.hash(&mut hasher);
+        key.hash(&mut hasher);
+        CallId(NonZeroU64::new(hasher.finish()).expect("invalid CallId hash"))
+    }
+
+    /// Enters a scope identified by a key instead of its position in the call graph.
+    ///
+    /// The ID depends on the call location and on the key, but not on the parent scopes: the scope, and the scopes
+    /// inside it, get the same IDs wherever the scope is in the call graph. Must be exited with `exit_keyed`.
+    pub fn enter_keyed(&mut self, location: &'static Location<'static>, key: u64) -> CallId {
+        let id = Self::keyed_call_id(location, key);
+        // pushed twice because child IDs are derived from the two innermost IDs
+        self.id_stack.push(id.0);
+        self.id_stack.push(id.0);
+        let node = Arc::new(CallNode {
+            id,
+            parent: self.current_node.clone(),
+            location,
+            index: key as usize,
+        });
+        self.nodes.insert(id, node.clone());
+        self.current_node = Some(node);
+        id
+    }
+
+    /// Exits a scope previously entered with `enter_keyed`.
+    pub fn exit_keyed(&mut self) {
+        self.id_stack.pop();
+        self.exit();
+    }
+
     /// Exits a scope previously entered with `enter`.
     pub fn exit(&mut self) {
         self.id_stack.pop();
@@ -156,3 +220,34 @@ impl CallIdStack {
         self.id_stack.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyed_ids() {
+        let here = Location::caller();
+        let elsewhere = Location::caller();
+        let mut stack = CallIdStack::new();
+
+        stack.enter(here, 0);
+        let a = stack.enter_keyed(here, 42);
+        let child_a = stack.enter(here, 0);
+        stack.exit();
+        stack.exit_keyed();
+        // same key, other location
+        assert_ne!(stack.enter_keyed(elsewhere, 42), a);
+        stack.exit_keyed();
+        stack.exit();
+
+        // same key and location, other parent: the scope and its children get the same IDs
+        stack.enter(here, 1);
+        assert_eq!(stack.enter_keyed(here, 42), a);
+        assert_eq!(stack.enter(here, 0), child_a);
+        stack.exit();
+        stack.exit_keyed();
+        stack.exit();
+        assert!(stack.is_empty());
+    }
+}
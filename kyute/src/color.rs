@@ -0,0 +1,460 @@
+//! Color spaces and color interpolation.
+//!
+//! Colors (`Color`) are stored as sRGB components with alpha. This module converts them to and from the other color
+//! spaces of CSS Color 4, and interpolates between colors in a selectable space. It is used by `color-mix()` in
+//! styles, by gradients (`LinearGradient::interpolation`) and by color animations (`InterpolatedColor`).
+//!
+//! Conversions don't clip components, so that colors can go through an intermediate space without losing
+//! precision; colors outside of the sRGB gamut are clipped when converted back to a `Color`.
+use crate::Color;
+use palette::{convert::FromColorUnclamped, Hsl, Hwb, Srgb};
+
+/// Color spaces of CSS Color 4.
+///
+/// The components of a color in each space (see `ColorSpace::to_components`) are in the units of the
+/// corresponding CSS functions, with percentages resolved: e.g. lightness between 0.0 and 100.0 for `Lab`,
+/// but between 0.0 and 1.0 for `Oklab`. Hues are in degrees.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorSpace {
+    /// sRGB: red, green and blue, between 0.0 and 1.0.
+    Srgb,
+    /// sRGB without the transfer function.
+    SrgbLinear,
+    /// Hue, saturation and lightness (between 0.0 and 1.0).
+    Hsl,
+    /// Hue, whiteness and blackness (between 0.0 and 1.0).
+    Hwb,
+    /// CIE Lab, D50 white point.
+    Lab,
+    /// Polar form of `Lab`: lightness, chroma and hue.
+    Lch,
+    Oklab,
+    /// Polar form of `Oklab`: lightness, chroma and hue.
+    Oklch,
+    /// CIE XYZ, D50 white point.
+    XyzD50,
+    /// CIE XYZ, D65 white point.
+    XyzD65,
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        ColorSpace::Srgb
+    }
+}
+
+/// Method used to interpolate the hues of colors in a polar color space.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HueInterpolation {
+    /// Takes the shorter arc between the two hues.
+    Shorter,
+    /// Takes the longer arc between the two hues.
+    Longer,
+    /// Hues increase from the first color to the second.
+    Increasing,
+    /// Hues decrease from the first color to the second.
+    Decreasing,
+}
+
+impl Default for HueInterpolation {
+    fn default() -> Self {
+        HueInterpolation::Shorter
+    }
+}
+
+/// How colors are interpolated: the equivalent of the CSS `<color-interpolation-method>`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, serde::Deserialize)]
+pub struct ColorInterpolation {
+    /// Color space in which the colors are interpolated.
+    pub space: ColorSpace,
+    /// Interpolation of hues, if `space` is a polar color space.
+    #[serde(default)]
+    pub hue: HueInterpolation,
+}
+
+impl From<ColorSpace> for ColorInterpolation {
+    fn from(space: ColorSpace) -> Self {
+        ColorInterpolation {
+            space,
+            hue: HueInterpolation::Shorter,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// conversions
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+type Matrix = [[f64; 3]; 3];
+
+fn transform(m: &Matrix, v: [f32; 3]) -> [f32; 3] {
+    let [x, y, z] = v.map(f64::from);
+    m.map(|row| (row[0] * x + row[1] * y + row[2] * z) as f32)
+}
+
+// Matrices from the sample code of CSS Color 4, section 18.
+const LINEAR_SRGB_TO_XYZ_D65: Matrix = [
+    [0.41239080, 0.35758434, 0.18048079],
+    [0.21263901, 0.71516868, 0.07219232],
+    [0.01933082, 0.11919478, 0.95053215],
+];
+const XYZ_D65_TO_LINEAR_SRGB: Matrix = [
+    [3.24096994, -1.53738318, -0.49861076],
+    [-0.96924364, 1.87596750, 0.04155506],
+    [0.05563008, -0.20397696, 1.05697151],
+];
+/// Bradford chromatic adaptation.
+const D65_TO_D50: Matrix = [
+    [1.04792979, 0.02294680, -0.05019223],
+    [0.02962782, 0.99043448, -0.01707383],
+    [-0.00924306, 0.01505514, 0.75187429],
+];
+const D50_TO_D65: Matrix = [
+    [0.95547345, -0.02309854, 0.06325931],
+    [-0.02836971, 1.00999546, 0.02104140],
+    [0.01231400, -0.02050770, 1.33036594],
+];
+const D50_WHITE: [f32; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+// Matrices from "A perceptual color space for image processing" (Björn Ottosson).
+const LINEAR_SRGB_TO_LMS: Matrix = [
+    [0.41222147, 0.53633254, 0.05144599],
+    [0.21190350, 0.68069955, 0.10739696],
+    [0.08830246, 0.28171884, 0.62997870],
+];
+const LMS_TO_OKLAB: Matrix = [
+    [0.21045426, 0.79361779, -0.00407205],
+    [1.97799850, -2.42859221, 0.45059371],
+    [0.02590404, 0.78277177, -0.80867577],
+];
+const OKLAB_TO_LMS: Matrix = [
+    [1.0, 0.39633778, 0.21580376],
+    [1.0, -0.10556135, -0.06385417],
+    [1.0, -0.08948418, -1.29148555],
+];
+const LMS_TO_LINEAR_SRGB: Matrix = [
+    [4.07674166, -3.30771159, 0.23096993],
+    [-1.26843800, 2.60975740, -0.34131940],
+    [-0.00419609, -0.70341861, 1.70761470],
+];
+
+/// sRGB transfer function, extended to negative values.
+fn srgb_to_linear(c: f32) -> f32 {
+    let abs = c.abs();
+    if abs <= 0.04045 {
+        c / 12.92
+    } else {
+        c.signum() * ((abs + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let abs = c.abs();
+    if abs <= 0.0031308 {
+        c * 12.92
+    } else {
+        c.signum() * (1.055 * abs.powf(1.0 / 2.4) - 0.055)
+    }
+}
+
+const LAB_EPSILON: f32 = 216.0 / 24389.0;
+const LAB_KAPPA: f32 = 24389.0 / 27.0;
+
+fn xyz_d50_to_lab(xyz: [f32; 3]) -> [f32; 3] {
+    let f = |i: usize| {
+        let v = xyz[i] / D50_WHITE[i];
+        if v > LAB_EPSILON {
+            v.cbrt()
+        } else {
+            (LAB_KAPPA * v + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(0), f(1), f(2));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn lab_to_xyz_d50([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let fx = a / 500.0 + fy;
+    let fz = fy - b / 200.0;
+    let f_inv = |f: f32| {
+        let f3 = f * f * f;
+        if f3 > LAB_EPSILON {
+            f3
+        } else {
+            (116.0 * f - 16.0) / LAB_KAPPA
+        }
+    };
+    let y = if l > LAB_KAPPA * LAB_EPSILON {
+        fy * fy * fy
+    } else {
+        l / LAB_KAPPA
+    };
+    [f_inv(fx) * D50_WHITE[0], y * D50_WHITE[1], f_inv(fz) * D50_WHITE[2]]
+}
+
+fn linear_srgb_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    let [l, m, s] = transform(&LINEAR_SRGB_TO_LMS, rgb);
+    transform(&LMS_TO_OKLAB, [l.cbrt(), m.cbrt(), s.cbrt()])
+}
+
+fn oklab_to_linear_srgb(lab: [f32; 3]) -> [f32; 3] {
+    let [l, m, s] = transform(&OKLAB_TO_LMS, lab);
+    transform(&LMS_TO_LINEAR_SRGB, [l * l * l, m * m * m, s * s * s])
+}
+
+/// Converts rectangular coordinates (`Lab`, `Oklab`) to polar coordinates (`Lch`, `Oklch`).
+fn to_polar([l, a, b]: [f32; 3]) -> [f32; 3] {
+    [l, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0)]
+}
+
+fn from_polar([l, c, h]: [f32; 3]) -> [f32; 3] {
+    let (sin, cos) = h.to_radians().sin_cos();
+    [l, c * cos, c * sin]
+}
+
+impl ColorSpace {
+    /// Returns the color space with the specified CSS name (as in the `color()` and `color-mix()` functions).
+    pub fn from_name(name: &str) -> Option<ColorSpace> {
+        match name {
+            "srgb" => Some(ColorSpace::Srgb),
+            "srgb-linear" => Some(ColorSpace::SrgbLinear),
+            "hsl" => Some(ColorSpace::Hsl),
+            "hwb" => Some(ColorSpace::Hwb),
+            "lab" => Some(ColorSpace::Lab),
+            "lch" => Some(ColorSpace::Lch),
+            "oklab" => Some(ColorSpace::Oklab),
+            "oklch" => Some(ColorSpace::Oklch),
+            "xyz-d50" => Some(ColorSpace::XyzD50),
+            "xyz" | "xyz-d65" => Some(ColorSpace::XyzD65),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the color space has a hue component.
+    pub fn is_polar(self) -> bool {
+        self.hue_index().is_some()
+    }
+
+    /// Index of the hue among the components.
+    fn hue_index(self) -> Option<usize> {
+        match self {
+            ColorSpace::Hsl | ColorSpace::Hwb => Some(0),
+            ColorSpace::Lch | ColorSpace::Oklch => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the hue of a color is meaningless, as for grays.
+    fn is_hue_powerless(self, components: [f32; 3]) -> bool {
+        match self {
+            ColorSpace::Hsl => components[1].abs() < 1e-4,
+            ColorSpace::Hwb => components[1] + components[2] >= 1.0 - 1e-4,
+            ColorSpace::Lch => components[1] < 0.02,
+            ColorSpace::Oklch => components[1] < 2e-4,
+            _ => false,
+        }
+    }
+
+    /// Returns the components of a color in this color space.
+    pub fn to_components(self, color: Color) -> [f32; 3] {
+        let (r, g, b, _) = color.to_rgba();
+        self.convert_from_srgb([r, g, b])
+    }
+
+    /// Creates a color from components in this color space.
+    pub fn color_from_components(self, components: [f32; 3], alpha: f32) -> Color {
+        let [r, g, b] = self.to_srgb(components);
+        Color::new(
+            r.clamp(0.0, 1.0),
+            g.clamp(0.0, 1.0),
+            b.clamp(0.0, 1.0),
+            alpha.clamp(0.0, 1.0),
+        )
+    }
+
+    /// Converts sRGB components to this color space, without clipping.
+    fn convert_from_srgb(self, rgb: [f32; 3]) -> [f32; 3] {
+        let linear = || rgb.map(srgb_to_linear);
+        match self {
+            ColorSpace::Srgb => rgb,
+            ColorSpace::SrgbLinear => linear(),
+            ColorSpace::Hsl => {
+                let hsl: Hsl = Hsl::from_color_unclamped(Srgb::new(rgb[0], rgb[1], rgb[2]));
+                [hsl.hue.to_positive_degrees(), hsl.saturation, hsl.lightness]
+            }
+            ColorSpace::Hwb => {
+                let hwb: Hwb = Hwb::from_color_unclamped(Srgb::new(rgb[0], rgb[1], rgb[2]));
+                [hwb.hue.to_positive_degrees(), hwb.whiteness, hwb.blackness]
+            }
+            ColorSpace::XyzD65 => transform(&LINEAR_SRGB_TO_XYZ_D65, linear()),
+            ColorSpace::XyzD50 => transform(&D65_TO_D50, transform(&LINEAR_SRGB_TO_XYZ_D65, linear())),
+            ColorSpace::Lab => xyz_d50_to_lab(ColorSpace::XyzD50.convert_from_srgb(rgb)),
+            ColorSpace::Lch => to_polar(ColorSpace::Lab.convert_from_srgb(rgb)),
+            ColorSpace::Oklab => linear_srgb_to_oklab(linear()),
+            ColorSpace::Oklch => to_polar(linear_srgb_to_oklab(linear())),
+        }
+    }
+
+    /// Converts components in this color space to sRGB, without clipping.
+    fn to_srgb(self, c: [f32; 3]) -> [f32; 3] {
+        let encode = |linear: [f32; 3]| linear.map(linear_to_srgb);
+        match self {
+            ColorSpace::Srgb => c,
+            ColorSpace::SrgbLinear => encode(c),
+            ColorSpace::Hsl => {
+                let rgb: Srgb = Srgb::from_color_unclamped(Hsl::new(c[0], c[1], c[2]));
+                [rgb.red, rgb.green, rgb.blue]
+            }
+            ColorSpace::Hwb => {
+                let rgb: Srgb = Srgb::from_color_unclamped(Hwb::new(c[0], c[1], c[2]));
+                [rgb.red, rgb.green, rgb.blue]
+            }
+            ColorSpace::XyzD65 => encode(transform(&XYZ_D65_TO_LINEAR_SRGB, c)),
+            ColorSpace::XyzD50 => encode(transform(&XYZ_D65_TO_LINEAR_SRGB, transform(&D50_TO_D65, c))),
+            ColorSpace::Lab => ColorSpace::XyzD50.to_srgb(lab_to_xyz_d50(c)),
+            ColorSpace::Lch => ColorSpace::Lab.to_srgb(from_polar(c)),
+            ColorSpace::Oklab => encode(oklab_to_linear_srgb(c)),
+            ColorSpace::Oklch => encode(oklab_to_linear_srgb(from_polar(c))),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// interpolation
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Interpolates between two hues, in degrees.
+fn interpolate_hue(h0: f32, h1: f32, t: f32, method: HueInterpolation) -> f32 {
+    let (h0, mut h1) = (h0.rem_euclid(360.0), h1.rem_euclid(360.0));
+    let delta = h1 - h0;
+    match method {
+        HueInterpolation::Shorter if delta > 180.0 => h1 -= 360.0,
+        HueInterpolation::Shorter if delta < -180.0 => h1 += 360.0,
+        HueInterpolation::Longer if 0.0 < delta && delta < 180.0 => h1 -= 360.0,
+        HueInterpolation::Longer if -180.0 < delta && delta <= 0.0 => h1 += 360.0,
+        HueInterpolation::Increasing if delta < 0.0 => h1 += 360.0,
+        HueInterpolation::Decreasing if delta > 0.0 => h1 -= 360.0,
+        _ => {}
+    }
+    (h0 + (h1 - h0) * t).rem_euclid(360.0)
+}
+
+/// Returns the color between `a` (`t == 0.0`) and `b` (`t == 1.0`), interpolated in the specified color space.
+///
+/// Components are interpolated with premultiplied alpha, so that fully transparent colors don't tint the
+/// result. The hue of grays is ignored, as if it was the hue of the other color.
+pub fn mix(a: Color, b: Color, t: f32, interpolation: impl Into<ColorInterpolation>) -> Color {
+    let ColorInterpolation { space, hue } = interpolation.into();
+    let mut c0 = space.to_components(a);
+    let mut c1 = space.to_components(b);
+    let (alpha0, alpha1) = (a.alpha(), b.alpha());
+    let alpha = alpha0 + (alpha1 - alpha0) * t;
+
+    let hue_index = space.hue_index();
+    if let Some(h) = hue_index {
+        match (space.is_hue_powerless(c0), space.is_hue_powerless(c1)) {
+            (true, false) => c0[h] = c1[h],
+            (false, true) => c1[h] = c0[h],
+            _ => {}
+        }
+    }
+
+    let c = std::array::from_fn(|i| {
+        if Some(i) == hue_index {
+            interpolate_hue(c0[i], c1[i], t, hue)
+        } else {
+            let premultiplied = c0[i] * alpha0 + (c1[i] * alpha1 - c0[i] * alpha0) * t;
+            if alpha > 0.0 {
+                premultiplied / alpha
+            } else {
+                0.0
+            }
+        }
+    });
+    space.color_from_components(c, alpha)
+}
+
+/// A color that is animated in a specific color space.
+///
+/// Colors animated directly (`Animated<Color>`) are interpolated in sRGB. Wrap them in this type to interpolate
+/// in another space, e.g. `Oklch` to keep a constant lightness across hues.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InterpolatedColor {
+    pub color: Color,
+    pub interpolation: ColorInterpolation,
+}
+
+impl InterpolatedColor {
+    pub fn new(color: Color, interpolation: impl Into<ColorInterpolation>) -> InterpolatedColor {
+        InterpolatedColor {
+            color,
+            interpolation: interpolation.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 3], b: [f32; 3], tolerance: f32) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() < tolerance, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        // reference values from CSS Color 4
+        assert_close(ColorSpace::Lab.to_components(red), [54.29, 80.80, 69.89], 0.05);
+        assert_close(ColorSpace::Oklab.to_components(red), [0.628, 0.2249, 0.1258], 0.001);
+        assert_close(ColorSpace::Oklch.to_components(red), [0.628, 0.2577, 29.23], 0.01);
+
+        let color = Color::new(0.2, 0.6, 0.8, 1.0);
+        for space in [
+            ColorSpace::SrgbLinear,
+            ColorSpace::Hsl,
+            ColorSpace::Hwb,
+            ColorSpace::Lab,
+            ColorSpace::Lch,
+            ColorSpace::Oklab,
+            ColorSpace::Oklch,
+            ColorSpace::XyzD50,
+            ColorSpace::XyzD65,
+        ] {
+            let round_trip = space.color_from_components(space.to_components(color), 1.0);
+            let (r, g, b, _) = round_trip.to_rgba();
+            assert_close([r, g, b], [0.2, 0.6, 0.8], 1e-3);
+        }
+    }
+
+    #[test]
+    fn hue_interpolation() {
+        assert!((interpolate_hue(350.0, 10.0, 0.5, HueInterpolation::Shorter) - 0.0).abs() < 1e-3);
+        assert!((interpolate_hue(350.0, 10.0, 0.5, HueInterpolation::Longer) - 180.0).abs() < 1e-3);
+        assert!((interpolate_hue(10.0, 350.0, 0.5, HueInterpolation::Increasing) - 180.0).abs() < 1e-3);
+        assert!((interpolate_hue(10.0, 350.0, 0.5, HueInterpolation::Decreasing) - 0.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn mix_gray() {
+        // the hue of white doesn't shift the hue of blue
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let blue = Color::new(0.0, 0.0, 1.0, 1.0);
+        let mixed = mix(white, blue, 0.5, ColorSpace::Oklch);
+        let [_, _, hue] = ColorSpace::Oklch.to_components(mixed);
+        let [_, _, blue_hue] = ColorSpace::Oklch.to_components(blue);
+        assert!((hue - blue_hue).abs() < 2.0);
+
+        // transparent colors don't tint the result
+        let transparent = Color::new(1.0, 0.0, 0.0, 0.0);
+        let mixed = mix(transparent, blue, 0.5, ColorSpace::Srgb);
+        let (r, g, b, a) = mixed.to_rgba();
+        assert_close([r, g, b], [0.0, 0.0, 1.0], 1e-4);
+        assert!((a - 0.5).abs() < 1e-4);
+    }
+}
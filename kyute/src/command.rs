@@ -0,0 +1,366 @@
+//! Named commands and their keyboard shortcuts.
+//!
+//! Commands are actions identified by name (e.g. `edit.copy`) that can be bound to key sequences: single shortcuts
+//! like `Ctrl+C`, or chords like `Ctrl+K Ctrl+C` (several shortcuts pressed one after the other).
+//! The bindings of all commands are stored in the `CommandMap` of the application.
+//!
+//! When the keys pressed in a window complete the key sequence of a command, an `Event::Command` is sent to the
+//! focused widget. The widgets on the path to the focused widget can handle it on the way back
+//! (see `widget::CommandScope`). If no widget handles it, the global handler of the command is triggered
+//! (see `Command`).
+use crate::{composable, widget::Action, Signal};
+use kyute_shell::Shortcut;
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, fmt};
+use thiserror::Error;
+
+/// Identifies a command.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CommandId(Cow<'static, str>);
+
+impl CommandId {
+    pub const fn new(name: &'static str) -> CommandId {
+        CommandId(Cow::Borrowed(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for CommandId {
+    fn from(name: String) -> Self {
+        CommandId(Cow::Owned(name))
+    }
+}
+
+impl fmt::Display for CommandId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A sequence of shortcuts pressed one after the other.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct KeySequence(Vec<Shortcut>);
+
+impl KeySequence {
+    /// Parses a space-separated list of shortcuts (e.g. `"Ctrl+K Ctrl+C"`).
+    ///
+    /// Panics if one of the shortcuts is invalid (see `Shortcut::from_str`).
+    pub fn parse(s: &str) -> KeySequence {
+        KeySequence(s.split_whitespace().map(Shortcut::from_str).collect())
+    }
+
+    pub fn shortcuts(&self) -> &[Shortcut] {
+        &self.0
+    }
+
+    /// Returns whether the key sequences conflict, i.e. if one is a prefix of the other.
+    ///
+    /// A chord can't be entered if its first key is already bound to another command.
+    pub fn conflicts_with(&self, other: &KeySequence) -> bool {
+        let n = self.0.len().min(other.0.len());
+        self.0[..n] == other.0[..n]
+    }
+}
+
+impl From<Shortcut> for KeySequence {
+    fn from(shortcut: Shortcut) -> Self {
+        KeySequence(vec![shortcut])
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, shortcut) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", shortcut)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors returned when binding key sequences.
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("unknown command `{0}`")]
+    UnknownCommand(CommandId),
+    #[error("key sequence `{sequence}` of command `{command}` conflicts with `{other_sequence}` of command `{other}`")]
+    Conflict {
+        command: CommandId,
+        sequence: KeySequence,
+        other: CommandId,
+        other_sequence: KeySequence,
+    },
+}
+
+/// Result of matching keys against the key sequences of a `CommandMap`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeyMatch {
+    /// The keys don't match any key sequence.
+    None,
+    /// The keys are the beginning of a chord: more keys are needed.
+    Prefix,
+    /// The keys are the key sequence of a command.
+    Command(CommandId),
+}
+
+struct CommandEntry {
+    label: String,
+    sequences: Vec<KeySequence>,
+    /// Triggered when the command isn't handled by a widget.
+    handler: Option<Signal<()>>,
+}
+
+/// Commands of the application and their key bindings.
+#[derive(Default)]
+pub struct CommandMap {
+    commands: HashMap<CommandId, CommandEntry>,
+}
+
+impl CommandMap {
+    /// Creates a command map with the builtin commands, bound to the shortcuts of the platform
+    /// (e.g. `history::UNDO`).
+    pub fn with_builtin_commands() -> CommandMap {
+        let mut map = CommandMap::default();
+        crate::history::register_commands(&mut map);
+        map
+    }
+
+    /// Registers a command, or updates its label if it's already registered.
+    pub fn register(&mut self, id: CommandId, label: impl Into<String>) {
+        let label = label.into();
+        self.commands
+            .entry(id)
+            .and_modify(|entry| entry.label = label.clone())
+            .or_insert_with(|| CommandEntry {
+                label,
+                sequences: vec![],
+                handler: None,
+            });
+    }
+
+    /// Binds a key sequence to a command.
+    ///
+    /// Fails if the key sequence conflicts with the key sequence of another command (see `KeySequence::conflicts_with`).
+    /// Binding a sequence already bound to the command does nothing.
+    pub fn bind(&mut self, id: &CommandId, sequence: KeySequence) -> Result<(), CommandError> {
+        if !self.commands.contains_key(id) {
+            return Err(CommandError::UnknownCommand(id.clone()));
+        }
+        if let Some((other, other_sequence)) = self.find_conflicts(&sequence).into_iter().find(|(other, s)| {
+            // rebinding the same sequence is fine
+            !(other == id && *s == sequence)
+        }) {
+            return Err(CommandError::Conflict {
+                command: id.clone(),
+                sequence,
+                other,
+                other_sequence,
+            });
+        }
+        let entry = self.commands.get_mut(id).unwrap();
+        if !entry.sequences.contains(&sequence) {
+            entry.sequences.push(sequence);
+        }
+        Ok(())
+    }
+
+    /// Removes a key binding of a command.
+    pub fn unbind(&mut self, id: &CommandId, sequence: &KeySequence) {
+        if let Some(entry) = self.commands.get_mut(id) {
+            entry.sequences.retain(|s| s != sequence);
+        }
+    }
+
+    /// Returns the commands with a key sequence that conflicts with the specified one, along with their conflicting sequence.
+    pub fn find_conflicts(&self, sequence: &KeySequence) -> Vec<(CommandId, KeySequence)> {
+        let mut conflicts = vec![];
+        for (id, entry) in self.commands.iter() {
+            for s in entry.sequences.iter() {
+                if s.conflicts_with(sequence) {
+                    conflicts.push((id.clone(), s.clone()));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Returns the label of a command.
+    pub fn label(&self, id: &CommandId) -> Option<&str> {
+        self.commands.get(id).map(|entry| entry.label.as_str())
+    }
+
+    /// Returns the key sequences bound to a command.
+    pub fn key_sequences(&self, id: &CommandId) -> &[KeySequence] {
+        self.commands.get(id).map_or(&[], |entry| &entry.sequences)
+    }
+
+    /// Returns the IDs of all registered commands.
+    pub fn commands(&self) -> impl Iterator<Item = &CommandId> {
+        self.commands.keys()
+    }
+
+    /// Matches keys pressed one after the other against the key sequences of the commands.
+    pub fn lookup(&self, keys: &[Shortcut]) -> KeyMatch {
+        let mut result = KeyMatch::None;
+        for (id, entry) in self.commands.iter() {
+            for s in entry.sequences.iter() {
+                if s.0 == keys {
+                    return KeyMatch::Command(id.clone());
+                }
+                if s.0.len() > keys.len() && s.0.starts_with(keys) {
+                    result = KeyMatch::Prefix;
+                }
+            }
+        }
+        result
+    }
+
+    /// Sets the signal triggered when a command isn't handled by a widget.
+    pub(crate) fn set_handler(&mut self, id: &CommandId, handler: Signal<()>) {
+        if let Some(entry) = self.commands.get_mut(id) {
+            entry.handler = Some(handler);
+        }
+    }
+
+    /// Triggers the global handler of a command. Returns false if the command has no handler.
+    pub(crate) fn trigger(&self, id: &CommandId) -> bool {
+        match self.commands.get(id).and_then(|entry| entry.handler.as_ref()) {
+            Some(handler) => {
+                handler.signal(());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+thread_local! {
+    static COMMAND_MAP: RefCell<CommandMap> = RefCell::new(CommandMap::with_builtin_commands());
+}
+
+/// Runs a closure with the command map of the application.
+pub fn with_command_map<R>(f: impl FnOnce(&mut CommandMap) -> R) -> R {
+    COMMAND_MAP.with(|map| f(&mut map.borrow_mut()))
+}
+
+/// A command registered in the `CommandMap`, with a global handler.
+///
+/// The global handler is triggered when the key sequence of the command is entered and no widget on the path
+/// to the focused widget handled the command.
+#[derive(Clone, Debug)]
+pub struct Command {
+    id: CommandId,
+    triggered: Signal<()>,
+}
+
+impl Command {
+    /// Registers a command with the specified label.
+    #[composable]
+    pub fn new(id: CommandId, label: &str) -> Command {
+        let triggered = Signal::new();
+        with_command_map(|map| {
+            map.register(id.clone(), label);
+            map.set_handler(&id, triggered.clone());
+        });
+        Command { id, triggered }
+    }
+
+    /// Binds a key sequence to the command.
+    ///
+    /// Conflicts with the bindings of other commands are logged and the sequence is ignored.
+    #[must_use]
+    pub fn shortcut(self, sequence: &str) -> Self {
+        if let Err(err) = with_command_map(|map| map.bind(&self.id, KeySequence::parse(sequence))) {
+            warn!("{}", err);
+        }
+        self
+    }
+
+    pub fn id(&self) -> &CommandId {
+        &self.id
+    }
+
+    /// Returns whether the global handler of the command was triggered.
+    pub fn triggered(&self) -> bool {
+        self.triggered.signalled()
+    }
+
+    #[must_use]
+    pub fn on_triggered(self, f: impl FnOnce()) -> Self {
+        if self.triggered.signalled() {
+            f()
+        }
+        self
+    }
+}
+
+impl Action {
+    /// Creates an action that triggers the global handler of a command.
+    ///
+    /// The shortcut of the action is the first single-shortcut key sequence of the command: chords can't be
+    /// displayed in native menus.
+    #[composable]
+    pub fn from_command(command: &Command) -> Action {
+        let shortcut = with_command_map(|map| {
+            map.key_sequences(command.id())
+                .iter()
+                .find(|s| s.shortcuts().len() == 1)
+                .map(|s| s.shortcuts()[0])
+        });
+        let mut action = match shortcut {
+            Some(shortcut) => Action::with_shortcut(shortcut),
+            None => Action::new(),
+        };
+        action.triggered = command.triggered.clone();
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COPY: CommandId = CommandId::new("test.copy");
+    const COMMENT: CommandId = CommandId::new("test.comment");
+
+    #[test]
+    fn chords() {
+        let mut map = CommandMap::default();
+        map.register(COPY, "Copy");
+        map.register(COMMENT, "Comment");
+        map.bind(&COPY, KeySequence::parse("Ctrl+C")).unwrap();
+        map.bind(&COMMENT, KeySequence::parse("Ctrl+K Ctrl+C")).unwrap();
+
+        let ctrl_k = Shortcut::from_str("Ctrl+K");
+        let ctrl_c = Shortcut::from_str("Ctrl+C");
+        assert_eq!(map.lookup(&[ctrl_c]), KeyMatch::Command(COPY));
+        assert_eq!(map.lookup(&[ctrl_k]), KeyMatch::Prefix);
+        assert_eq!(map.lookup(&[ctrl_k, ctrl_c]), KeyMatch::Command(COMMENT));
+        assert_eq!(map.lookup(&[ctrl_k, ctrl_k]), KeyMatch::None);
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut map = CommandMap::default();
+        map.register(COPY, "Copy");
+        map.register(COMMENT, "Comment");
+        map.bind(&COPY, KeySequence::parse("Ctrl+K")).unwrap();
+        // rebinding is fine
+        map.bind(&COPY, KeySequence::parse("Ctrl+K")).unwrap();
+        assert!(matches!(
+            map.bind(&COMMENT, KeySequence::parse("Ctrl+K Ctrl+C")),
+            Err(CommandError::Conflict { .. })
+        ));
+        assert!(matches!(
+            map.bind(&CommandId::new("test.unknown"), KeySequence::parse("Ctrl+U")),
+            Err(CommandError::UnknownCommand(_))
+        ));
+        map.unbind(&COPY, &KeySequence::parse("Ctrl+K"));
+        map.bind(&COMMENT, KeySequence::parse("Ctrl+K Ctrl+C")).unwrap();
+        assert_eq!(map.key_sequences(&COMMENT).len(), 1);
+    }
+}
@@ -0,0 +1,214 @@
+//! Conversions between the layout types of kyute and those of `kyute2`.
+//!
+//! Both cores describe layout with constraints going down and a geometry coming up, but the types differ:
+//!
+//! | kyute                         | kyute2                           |
+//! |-------------------------------|----------------------------------|
+//! | `LayoutParams` (min, max, scale factor, widget state) | `BoxConstraints` (min, max), the scale factor is in `LayoutCtx` |
+//! | `BoxConstraints`              | `BoxConstraints`                 |
+//! | `Geometry` (measurements, padding, alignment) | `Geometry` (size, baseline, bounds) |
+//! | `Measurements` (size, clip bounds, baseline) | `Geometry`               |
+//! | `Size`, `Rect` (euclid)       | `Size`, `Rect` (kurbo)           |
+//!
+//! In kyute, the padding and alignment of a box are applied by its parent when placing it. kyute2 has no such
+//! properties: a kyute geometry is converted to the geometry of its padding box, and the parent hosting the widget
+//! must offset the content by the top-left padding (see `content_offset`). The alignment is dropped.
+//!
+//! This only covers the data exchanged during layout. Hosting a widget of one core in the other also requires
+//! translating events, focus, and the composition of the contents (kyute's positional cache against kyute2's
+//! widget tree); this isn't provided here.
+use crate::{BoxConstraints, Geometry, LayoutParams, Measurements, Offset, Point, Rect, Size};
+
+/// Converts a kyute2 size.
+pub fn size_from_kyute2(size: kyute2::Size) -> Size {
+    Size::new(size.width, size.height)
+}
+
+/// Converts a size to a kyute2 size.
+pub fn size_to_kyute2(size: Size) -> kyute2::Size {
+    kyute2::Size::new(size.width, size.height)
+}
+
+/// Converts a kyute2 rectangle.
+pub fn rect_from_kyute2(rect: kyute2::Rect) -> Rect {
+    Rect::new(Point::new(rect.x0, rect.y0), Size::new(rect.width(), rect.height()))
+}
+
+/// Converts a rectangle to a kyute2 rectangle.
+pub fn rect_to_kyute2(rect: Rect) -> kyute2::Rect {
+    kyute2::Rect::new(rect.min_x(), rect.min_y(), rect.max_x(), rect.max_y())
+}
+
+/// Creates the layout parameters corresponding to kyute2 constraints.
+///
+/// kyute2 passes the scale factor in the layout context instead of the constraints.
+pub fn layout_params_from_kyute2(constraints: &kyute2::BoxConstraints, scale_factor: f64) -> LayoutParams {
+    LayoutParams {
+        scale_factor,
+        min: size_from_kyute2(constraints.min),
+        max: size_from_kyute2(constraints.max),
+        ..Default::default()
+    }
+}
+
+/// Returns the position of the content box of a kyute widget relative to the origin of the kyute2 geometry
+/// it was converted to.
+pub fn content_offset(geometry: &Geometry) -> Offset {
+    Offset::new(geometry.padding_left, geometry.padding_top)
+}
+
+impl From<kyute2::BoxConstraints> for BoxConstraints {
+    fn from(constraints: kyute2::BoxConstraints) -> Self {
+        BoxConstraints {
+            min: size_from_kyute2(constraints.min),
+            max: size_from_kyute2(constraints.max),
+        }
+    }
+}
+
+impl From<BoxConstraints> for kyute2::BoxConstraints {
+    fn from(constraints: BoxConstraints) -> Self {
+        kyute2::BoxConstraints {
+            min: size_to_kyute2(constraints.min),
+            max: size_to_kyute2(constraints.max),
+        }
+    }
+}
+
+/// Drops the scale factor and the widget state.
+impl From<LayoutParams> for kyute2::BoxConstraints {
+    fn from(params: LayoutParams) -> Self {
+        kyute2::BoxConstraints {
+            min: size_to_kyute2(params.min),
+            max: size_to_kyute2(params.max),
+        }
+    }
+}
+
+/// The bounding rectangle of the descendants is dropped: kyute widgets don't report overflowing content.
+impl From<kyute2::Geometry> for Measurements {
+    fn from(geometry: kyute2::Geometry) -> Self {
+        Measurements {
+            size: size_from_kyute2(geometry.size),
+            clip_bounds: None,
+            baseline: geometry.baseline,
+        }
+    }
+}
+
+impl From<Measurements> for kyute2::Geometry {
+    fn from(measurements: Measurements) -> Self {
+        let mut geometry = kyute2::Geometry::new(size_to_kyute2(measurements.size));
+        geometry.baseline = measurements.baseline;
+        if let Some(clip_bounds) = measurements.clip_bounds {
+            geometry.bounding_rect = rect_to_kyute2(clip_bounds);
+            geometry.paint_bounding_rect = geometry.bounding_rect;
+        }
+        geometry
+    }
+}
+
+impl From<kyute2::Geometry> for Geometry {
+    fn from(geometry: kyute2::Geometry) -> Self {
+        Geometry {
+            measurements: geometry.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Returns the geometry of the padding box: see the module documentation.
+impl From<Geometry> for kyute2::Geometry {
+    fn from(geometry: Geometry) -> Self {
+        let offset = content_offset(&geometry);
+        let mut converted = kyute2::Geometry::from(Measurements {
+            size: geometry.padding_box_size(),
+            baseline: geometry.padding_box_baseline(),
+            clip_bounds: None,
+        });
+        if let Some(clip_bounds) = geometry.measurements.clip_bounds {
+            converted.bounding_rect = rect_to_kyute2(clip_bounds.translate(offset));
+            converted.paint_bounding_rect = converted.bounding_rect;
+        }
+        converted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constraints_round_trip() {
+        let constraints = BoxConstraints {
+            min: Size::new(10.0, 20.0),
+            max: Size::new(100.0, f64::INFINITY),
+        };
+        let converted = kyute2::BoxConstraints::from(constraints);
+        assert_eq!(converted.min, kyute2::Size::new(10.0, 20.0));
+        assert_eq!(converted.max, kyute2::Size::new(100.0, f64::INFINITY));
+        let back = BoxConstraints::from(converted);
+        assert_eq!(back.min, constraints.min);
+        assert_eq!(back.max, constraints.max);
+
+        // the scale factor is passed separately in kyute2
+        let params = layout_params_from_kyute2(&converted, 2.0);
+        assert_eq!(params.scale_factor, 2.0);
+        assert_eq!((params.min, params.max), (constraints.min, constraints.max));
+        let converted_again = kyute2::BoxConstraints::from(params);
+        assert_eq!(
+            (converted_again.min, converted_again.max),
+            (converted.min, converted.max)
+        );
+    }
+
+    #[test]
+    fn rect_round_trip() {
+        let rect = Rect::new(Point::new(-5.0, 10.0), Size::new(30.0, 40.0));
+        let converted = rect_to_kyute2(rect);
+        assert_eq!(converted, kyute2::Rect::new(-5.0, 10.0, 25.0, 50.0));
+        assert_eq!(rect_from_kyute2(converted), rect);
+        assert_eq!(size_from_kyute2(size_to_kyute2(rect.size)), rect.size);
+    }
+
+    #[test]
+    fn measurements_round_trip() {
+        let measurements = Measurements {
+            size: Size::new(50.0, 20.0),
+            clip_bounds: Some(Rect::new(Point::new(-2.0, -2.0), Size::new(54.0, 24.0))),
+            baseline: Some(14.0),
+        };
+        let converted = kyute2::Geometry::from(measurements);
+        assert_eq!(converted.size, kyute2::Size::new(50.0, 20.0));
+        assert_eq!(converted.baseline, Some(14.0));
+        assert_eq!(converted.bounding_rect, kyute2::Rect::new(-2.0, -2.0, 52.0, 22.0));
+        let back = Measurements::from(converted);
+        assert_eq!(back.size, measurements.size);
+        assert_eq!(back.baseline, measurements.baseline);
+        // kyute widgets don't report overflowing content
+        assert_eq!(back.clip_bounds, None);
+    }
+
+    #[test]
+    fn padded_geometry() {
+        let mut geometry = Geometry::new(Size::new(100.0, 20.0));
+        geometry.measurements.baseline = Some(15.0);
+        geometry.padding_left = 4.0;
+        geometry.padding_top = 2.0;
+        geometry.padding_right = 4.0;
+        geometry.padding_bottom = 2.0;
+        assert_eq!(content_offset(&geometry), Offset::new(4.0, 2.0));
+
+        let converted = kyute2::Geometry::from(geometry);
+        assert_eq!(converted.size, kyute2::Size::new(108.0, 24.0));
+        assert_eq!(converted.baseline, Some(17.0));
+        assert_eq!(converted.bounding_rect, kyute2::Rect::new(0.0, 0.0, 108.0, 24.0));
+
+        // round trip: the padding is now part of the size
+        let back = Geometry::from(converted);
+        assert_eq!(back.measurements.size, Size::new(108.0, 24.0));
+        assert_eq!(back.measurements.baseline, Some(17.0));
+        assert_eq!(back.padding_left, 0.0);
+        assert_eq!(kyute2::Geometry::from(back), converted);
+    }
+}
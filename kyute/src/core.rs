@@ -1,31 +1,50 @@
 use crate::{
+    accessibility::{AccessibilityNode, Semantics},
     application::{AppCtx, ExtEvent},
     bloom::Bloom,
     cache,
     call_id::CallId,
     composable,
     drawing::PaintCtx,
+    event::PointerId,
+    frame_debugger,
     graal::vk::Handle,
+    region::Region,
     shell::{
         graal,
         winit::{event_loop::EventLoopWindowTarget, window::WindowId},
     },
     widget::WidgetExt,
-    EnvKey, Environment, Event, Geometry, InternalEvent, LayoutParams, Point, PointI, PointerEvent, PointerEventKind,
-    Rect, Transform,
+    EnvKey, Environment, Event, Geometry, InternalEvent, IntrinsicWidths, LayoutParams, Offset, Point, PointI,
+    PointerEvent, PointerEventKind, Rect, RectI, Size, SizeI, Transform,
+};
+use kyute::window::{DragRequest, WindowState};
+use kyute_shell::{
+    animation::Layer, application::Application, window::CursorImage, winit, DragImage, DropEffect, DropEffects,
+    TypedData,
 };
-use kyute::window::WindowState;
-use kyute_shell::{animation::Layer, application::Application, winit};
 use skia_safe as sk;
 use std::{
     cell::{Ref, RefCell},
+    collections::HashMap,
     fmt,
     hash::Hash,
-    sync::Arc,
+    mem,
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
 };
 use tracing::{trace, warn};
 
 pub const SHOW_DEBUG_OVERLAY: EnvKey<bool> = builtin_env_key!("kyute.show-debug-overlay");
+/// Shows a heatmap of the areas of windows that are painted over several times in a frame.
+///
+/// While enabled, all layers are repainted entirely on each update. See `PaintCtx::record_overdraw`.
+pub const SHOW_OVERDRAW: EnvKey<bool> = builtin_env_key!("kyute.show-overdraw");
+/// Shows an overlay with the end-to-end latency of input events in the top-right corner of windows: the time between
+/// the moment the OS produced an input event, and the display of the first frame that shows its effects.
+///
+/// The statistics are updated when the window processes events. See `diagnostics::LatencyProbe`.
+pub const SHOW_PERFORMANCE_HUD: EnvKey<bool> = builtin_env_key!("kyute.show-performance-hud");
 //pub const SELECTED: EnvKey<bool> = EnvKey::new("kyute.core.selected");
 //pub const DISABLED: EnvKey<bool> = EnvKey::new("kyute.core.disabled");
 
@@ -60,6 +79,9 @@ pub struct LayoutCtx {
     pub scale_factor: f64,
     pub speculative: bool,
     pub paint_damage: Option<PaintDamage>,
+    /// Names of the widgets being laid out, from the root. Used to report layout errors.
+    #[cfg(debug_assertions)]
+    pub(crate) layout_path: Vec<String>,
 }
 
 impl LayoutCtx {
@@ -69,6 +91,32 @@ impl LayoutCtx {
             scale_factor,
             speculative: false,
             paint_damage: None,
+            #[cfg(debug_assertions)]
+            layout_path: vec![],
+        }
+    }
+
+    /// Creates a `LayoutCtx` to relayout a descendant widget in place, keeping the path to the widget being laid
+    /// out for diagnostics.
+    pub(crate) fn relayout_ctx(&self, scale_factor: f64) -> LayoutCtx {
+        LayoutCtx {
+            #[cfg(debug_assertions)]
+            layout_path: self.layout_path.clone(),
+            ..LayoutCtx::new(scale_factor)
+        }
+    }
+
+    /// Returns the path to the widget being laid out, for diagnostics.
+    ///
+    /// Only available in debug builds; returns an empty string otherwise.
+    pub fn widget_path(&self) -> String {
+        #[cfg(debug_assertions)]
+        {
+            self.layout_path.join(" > ")
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            String::new()
         }
     }
 
@@ -107,10 +155,13 @@ impl Default for EventResult {
 #[derive(Clone, Debug, Default)]
 pub struct FocusState {
     pub(crate) focus: Option<WidgetId>,
-    pub(crate) pointer_grab: Option<WidgetId>,
+    /// Widgets capturing pointers. Each pointer can be captured by a different widget.
+    pub(crate) pointer_grabs: HashMap<PointerId, WidgetId>,
     pub(crate) hot: Option<WidgetId>,
     /// Target of popup menu events
     pub(crate) popup_target: Option<WidgetId>,
+    /// In-window menu bar, receives menu activation keys (Alt, Alt+mnemonic) before the focused widget.
+    pub(crate) menu_bar: Option<WidgetId>,
 }
 
 /*impl FocusState {
@@ -211,10 +262,13 @@ fn do_event<W: Widget + ?Sized>(
         id: widget_id,
         handled: false,
         relayout: false,
+        child_relayout: false,
         hot: parent_ctx.hot,
         hit_test_pass: true, // hit-test passes by default, widgets that do a hit-test set this to false
         paint_damage: PaintDamage::None,
+        damaged: Region::new(),
         focus_change: None,
+        scroll_into_view: None,
     };
 
     // finally, transform the event to widget-local coordinates and pass it to the widget
@@ -224,8 +278,11 @@ fn do_event<W: Widget + ?Sized>(
 
     let handled = target_ctx.handled;
     let relayout = target_ctx.relayout;
+    let child_relayout = target_ctx.child_relayout;
     let paint_damage = target_ctx.paint_damage;
+    let damaged = mem::take(&mut target_ctx.damaged);
     let focus_change = target_ctx.focus_change;
+    let scroll_into_view = target_ctx.scroll_into_view;
     let hit_test_pass = target_ctx.hit_test_pass;
     let mut hot = target_ctx.hot;
 
@@ -267,13 +324,18 @@ fn do_event<W: Widget + ?Sized>(
 
     // merge the results of event delivery to the parent EventCtx
     parent_ctx.relayout |= relayout;
+    parent_ctx.child_relayout |= child_relayout;
     parent_ctx.handled |= handled;
     parent_ctx.paint_damage.merge_up(paint_damage);
+    parent_ctx.damaged.add_region(&damaged);
     parent_ctx.hot = hot;
     //parent_ctx.hit_test_pass = hit_test_pass;
     if let Some(focus_change) = focus_change {
         parent_ctx.focus_change = Some(focus_change);
     }
+    if scroll_into_view.is_some() {
+        parent_ctx.scroll_into_view = scroll_into_view;
+    }
 }
 
 /// Damage done to the contents of a layer that possibly justifies a repaint.
@@ -285,6 +347,8 @@ pub enum PaintDamage {
     None,
     /// This layer is undamaged, but one or more of its sublayers are.
     SubLayers,
+    /// Parts of this layer are damaged and need to be repainted (see `EventCtx::request_repaint_rect`).
+    Partial,
     /// This layer is damaged and needs to be repainted.
     Repaint,
 }
@@ -297,11 +361,9 @@ impl Default for PaintDamage {
 
 impl PaintDamage {
     pub fn merge_up(&mut self, down: PaintDamage) {
-        match (*self, down) {
-            (PaintDamage::None, _) | (PaintDamage::SubLayers, PaintDamage::Repaint) => {
-                *self = down;
-            }
-            _ => {}
+        // variants are ordered by increasing damage
+        if down > *self {
+            *self = down;
         }
     }
 }
@@ -340,11 +402,18 @@ pub struct EventCtx<'a> {
     // event result propagated upwards
     pub(crate) handled: bool,
     pub(crate) relayout: bool,
+    /// A descendant `WidgetPod` needs a relayout (see `WidgetPod::layout`). Unlike `relayout`, this doesn't
+    /// invalidate the layout of the widgets between it and the descendant.
+    pub(crate) child_relayout: bool,
     pub(crate) hit_test_pass: bool,
     // first widget that passed the hit-test
     pub(crate) hot: Option<WidgetId>,
     pub(crate) paint_damage: PaintDamage,
+    /// Parts of the window damaged by `request_repaint_rect`, in window coordinates.
+    pub(crate) damaged: Region,
     pub(crate) focus_change: Option<FocusChange>,
+    /// Rectangle (in window coordinates) that should be made visible by the enclosing scrollable containers.
+    pub(crate) scroll_into_view: Option<Rect>,
 }
 
 /// Sends an event to the specified root widget.
@@ -363,15 +432,18 @@ pub(crate) fn send_root_event(
         id: widget.widget_id(),
         handled: false,
         relayout: false,
+        child_relayout: false,
         hit_test_pass: true,
         hot: None,
         paint_damage: PaintDamage::None,
+        damaged: Region::new(),
         focus_change: None,
+        scroll_into_view: None,
     };
     widget.route_event(&mut ctx, event, env);
     EventResult {
         handled: ctx.handled,
-        relayout: ctx.relayout,
+        relayout: ctx.relayout || ctx.child_relayout,
         paint_damage: ctx.paint_damage,
         focus_change: ctx.focus_change,
     }
@@ -392,15 +464,18 @@ pub(crate) fn send_event_with_parent_window<W: Widget + ?Sized>(
         id: ctx.id,
         handled: false,
         relayout: false,
+        child_relayout: false,
         hit_test_pass: true,
         hot: None,
         paint_damage: PaintDamage::None,
+        damaged: Region::new(),
         focus_change: None,
+        scroll_into_view: None,
     };
     widget.route_event(&mut child_ctx, event, env);
     EventResult {
         handled: child_ctx.handled,
-        relayout: child_ctx.relayout,
+        relayout: child_ctx.relayout || child_ctx.child_relayout,
         paint_damage: child_ctx.paint_damage,
         focus_change: child_ctx.focus_change,
     }
@@ -416,10 +491,13 @@ pub(crate) fn send_utility_event<W: Widget + ?Sized>(widget: &W, event: &mut Eve
         id: widget.widget_id(),
         handled: false,
         relayout: false,
+        child_relayout: false,
         hit_test_pass: true,
         hot: None,
         paint_damage: PaintDamage::None,
+        damaged: Region::new(),
         focus_change: None,
+        scroll_into_view: None,
     };
     widget.route_event(&mut ctx, event, env);
 }
@@ -444,23 +522,80 @@ impl<'a> EventCtx<'a> {
         &self.window_transform
     }
 
+    /// Returns the size of the parent window, or `None` if the widget is not in a window.
+    pub fn window_size(&self) -> Option<Size> {
+        self.window_state
+            .as_ref()
+            .map(|window_state| window_state.layout_size())
+    }
+
+    /// Converts a rectangle in local coordinates to physical pixels relative to the desktop.
+    ///
+    /// Returns `None` if the widget is not in a window, or if the window hasn't been created yet.
+    pub(crate) fn local_to_screen(&self, rect: &Rect) -> Option<RectI> {
+        let window = self.window_state.as_ref()?.window.as_ref()?;
+        let scale_factor = window.scale_factor();
+        let window_rect = self.window_transform.outer_transformed_rect(rect);
+        let origin = window.client_to_screen(PointI::new(
+            (window_rect.origin.x * scale_factor).round() as i32,
+            (window_rect.origin.y * scale_factor).round() as i32,
+        ));
+        Some(RectI::new(
+            origin,
+            SizeI::new(
+                (window_rect.size.width * scale_factor).round() as i32,
+                (window_rect.size.height * scale_factor).round() as i32,
+            ),
+        ))
+    }
+
+    /// Requests the enclosing scrollable containers (e.g. `ScrollArea`) to scroll so that the specified
+    /// rectangle (in local coordinates) becomes visible.
+    pub fn scroll_into_view(&mut self, rect: Rect) {
+        self.scroll_into_view = Some(self.window_transform.outer_transformed_rect(&rect));
+    }
+
+    /// Takes the pending request made with `scroll_into_view` by a descendant widget, and returns the rectangle
+    /// to make visible, in local coordinates.
+    ///
+    /// Scrollable containers should call this after routing an event to their contents.
+    pub(crate) fn take_scroll_into_view_request(&mut self) -> Option<Rect> {
+        let rect = self.scroll_into_view.take()?;
+        let inverse = self.window_transform.inverse()?;
+        Some(inverse.outer_transformed_rect(&rect))
+    }
+
     /// Requests a repaint of the widgets.
     pub fn request_repaint(&mut self) {
         self.paint_damage = PaintDamage::Repaint;
     }
 
-    /*pub fn request_layer_repaint(&mut self) {
-        if self.paint_damage.is_none() {
-            self.paint_damage = Some(PaintDamage::SubLayers);
-        }
-    }*/
+    /// Requests a repaint of the specified rectangle (in local coordinates).
+    ///
+    /// Only this part of the layer that contains the widget is rasterized again, and presented to the compositor:
+    /// prefer this to `request_repaint` for small updates (a caret, a selection, a hover highlight...). The widget
+    /// is still painted as a whole, clipped to the damaged parts.
+    pub fn request_repaint_rect(&mut self, rect: Rect) {
+        self.damaged
+            .add_rect(self.window_transform.outer_transformed_rect(&rect));
+        self.paint_damage.merge_up(PaintDamage::Partial);
+    }
 
-    pub fn register_window(&mut self, window_id: WindowId) {
+    /// Requests an update of the compositor layers owned by the widgets, without repainting the layer that
+    /// contains them.
+    ///
+    /// The widgets update their layers when they receive `InternalEvent::UpdateLayers`.
+    pub fn request_layer_update(&mut self) {
+        self.paint_damage.merge_up(PaintDamage::SubLayers);
+    }
+
+    /// Registers the native window created by the current widget, so that its events are routed to it.
+    pub(crate) fn register_window(&mut self, window_id: WindowId, window_state: Weak<RefCell<WindowState>>) {
         if let Some(id) = self.id {
             self.app_ctx
                 .as_deref_mut()
                 .expect("invalid EventCtx call")
-                .register_window_widget(window_id, id);
+                .register_window_widget(window_id, id, window_state);
         } else {
             warn!("register_window: the widget registering the window must have an ID")
         }
@@ -490,6 +625,48 @@ impl<'a> EventCtx<'a> {
         }
     }
 
+    /// Shows a static image as the cursor, until the next call to `set_cursor_icon`.
+    pub fn set_cursor_image(&mut self, image: &CursorImage) {
+        if let Some(window_state) = self.window_state.as_mut() {
+            if let Some(window) = window_state.window.as_mut() {
+                window.set_cursor_image(image)
+            }
+        }
+    }
+
+    /// Enables or disables input method editors (IME) for the window.
+    ///
+    /// Text input widgets should enable IME when they gain focus, and disable it when they lose it.
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        if let Some(window_state) = self.window_state.as_mut() {
+            if let Some(window) = window_state.window.as_ref() {
+                window.set_ime_allowed(allowed)
+            }
+        }
+    }
+
+    /// Sets the position of the IME candidate window, so that it appears next to the text being composed.
+    ///
+    /// `rect` is the area of the caret, in local coordinates. The candidate window is placed below it.
+    pub fn set_ime_cursor_area(&mut self, rect: Rect) {
+        let rect = self.window_transform.outer_transformed_rect(&rect);
+        if let Some(window_state) = self.window_state.as_mut() {
+            if let Some(window) = window_state.window.as_ref() {
+                window.set_ime_position(Point::new(rect.min_x(), rect.max_y()))
+            }
+        }
+    }
+
+    /// Replaces the pointer cursor with a compositing layer that follows the pointer, or restores the regular
+    /// cursor if `layer` is `None`.
+    ///
+    /// `hotspot` is the position of the pointer within the layer, in DIPs.
+    pub fn set_cursor_layer(&mut self, layer: Option<&Layer>, hotspot: Offset) {
+        if let Some(window_state) = self.window_state.as_mut() {
+            window_state.set_cursor_layer(layer.map(|layer| (layer.clone(), hotspot)));
+        }
+    }
+
     #[track_caller]
     fn window_state(&self) -> &WindowState {
         // TODO better panic message
@@ -505,35 +682,94 @@ impl<'a> EventCtx<'a> {
             .expect("this method can only be called when the current widget is contained in a parent window")
     }
 
-    /// Requests that the current node grabs all pointer events in the parent window.
+    /// Returns the time at which the OS produced the input event being delivered.
+    ///
+    /// Returns `None` if the event is not an input event received by a window. Unlike pointer events, keyboard events
+    /// don't carry their timestamp: use this method instead.
+    pub fn input_timestamp(&self) -> Option<Instant> {
+        self.window_state
+            .as_ref()
+            .and_then(|window_state| window_state.input_timestamp)
+    }
+
+    /// Returns the average end-to-end latency of the input events of the window: the time between the production of
+    /// an input event by the OS and the display of its effects.
+    ///
+    /// Returns `None` if no input has been measured yet, or outside of a window.
+    pub fn input_latency(&self) -> Option<Duration> {
+        let window_state = self.window_state.as_ref()?;
+        Some(window_state.latency.stats()?.average)
+    }
+
+    /// Returns the pointer that produced the event being delivered, if it's a pointer event.
+    pub fn pointer_id(&self) -> Option<PointerId> {
+        self.window_state
+            .as_ref()
+            .and_then(|window_state| window_state.current_pointer)
+    }
+
+    /// Requests that the current node grabs all events of the pointer that produced the current event.
+    ///
+    /// The grab is released automatically when the pointer is released.
     pub fn capture_pointer(&mut self) {
+        if let Some(pointer_id) = self.pointer_id() {
+            self.capture_pointer_id(pointer_id);
+        } else {
+            warn!("capture_pointer: the current event was not produced by a pointer")
+        }
+    }
+
+    /// Requests that the current node grabs all events of the specified pointer in the parent window.
+    ///
+    /// Other pointers (e.g. other contacts on a touch screen) are not affected, and can be captured by other widgets.
+    pub fn capture_pointer_id(&mut self, pointer_id: PointerId) {
         if let Some(id) = self.id {
             // TODO this should be a request
-            self.window_state_mut().focus_state.pointer_grab = Some(id);
+            self.window_state_mut().focus_state.pointer_grabs.insert(pointer_id, id);
         } else {
             warn!("capture_pointer: the widget capturing the pointer must have an ID")
         }
     }
 
-    /// Returns whether the current node is capturing the pointer.
+    /// Returns whether the current node is capturing the pointer that produced the current event, or any pointer if
+    /// the current event wasn't produced by a pointer.
     #[must_use]
     pub fn is_capturing_pointer(&self) -> bool {
         if let Some(id) = self.id {
-            self.window_state().focus_state.pointer_grab == Some(id)
+            match self.pointer_id() {
+                Some(pointer_id) => self.pointer_capture(pointer_id) == Some(id),
+                None => self
+                    .window_state()
+                    .focus_state
+                    .pointer_grabs
+                    .values()
+                    .any(|grab| *grab == id),
+            }
         } else {
             false
         }
     }
 
-    /// Returns the current pointer-grabbing widgets ID.
+    /// Returns the ID of the widget capturing the pointer that produced the current event.
     pub fn pointer_capturing_widget(&self) -> Option<WidgetId> {
-        self.window_state().focus_state.pointer_grab
+        self.pointer_capture(self.pointer_id()?)
+    }
+
+    /// Returns the ID of the widget capturing the specified pointer.
+    pub fn pointer_capture(&self, pointer_id: PointerId) -> Option<WidgetId> {
+        self.window_state().focus_state.pointer_grabs.get(&pointer_id).copied()
     }
 
-    /// Releases the pointer grab, if the current node is holding it.
+    /// Releases the grab of the pointer that produced the current event, if the current node is holding it.
+    ///
+    /// If the current event wasn't produced by a pointer, releases all the pointers captured by the current node.
     pub fn release_pointer(&mut self) {
         if let Some(id) = self.id {
-            if self.window_state().focus_state.pointer_grab == Some(id) {
+            let pointer_id = self.pointer_id();
+            let grabs = &mut self.window_state_mut().focus_state.pointer_grabs;
+            let count = grabs.len();
+            grabs.retain(|pointer, grab| *grab != id || pointer_id.map_or(false, |pointer_id| *pointer != pointer_id));
+            if grabs.len() < count {
                 trace!("releasing pointer grab");
             } else {
                 warn!("pointer capture release requested but the current widget isn't capturing the pointer");
@@ -543,6 +779,25 @@ impl<'a> EventCtx<'a> {
         }
     }
 
+    /// Starts an OS drag and drop operation with the specified data.
+    ///
+    /// The operation starts once the current event has been processed, and `on_finished` is called with the
+    /// effect chosen by the drop target when it ends. This should be called while the left mouse button is pressed.
+    pub fn start_drag(
+        &mut self,
+        data: Vec<TypedData>,
+        allowed_effects: DropEffects,
+        image: Option<DragImage>,
+        on_finished: impl FnOnce(DropEffect) + 'static,
+    ) {
+        self.window_state_mut().pending_drag = Some(DragRequest {
+            data,
+            allowed_effects,
+            image,
+            on_finished: Box::new(on_finished),
+        });
+    }
+
     /// Acquires the focus.
     pub fn request_focus(&mut self) {
         if let Some(id) = self.id {
@@ -552,6 +807,13 @@ impl<'a> EventCtx<'a> {
         }
     }
 
+    /// Cancels a pending request to move the focus to the specified widget.
+    pub(crate) fn cancel_focus_request(&mut self, id: WidgetId) {
+        if matches!(self.focus_change, Some(FocusChange::MoveTo(target)) if target == id) {
+            self.focus_change = None;
+        }
+    }
+
     /// Moves the focus to the next element in the focus chain.
     pub fn focus_next(&mut self) {
         self.focus_change = Some(FocusChange::MoveNext);
@@ -562,6 +824,11 @@ impl<'a> EventCtx<'a> {
         self.focus_change = Some(FocusChange::MovePrev);
     }
 
+    /// Moves the focus to the specified widget.
+    pub(crate) fn move_focus_to(&mut self, id: WidgetId) {
+        self.focus_change = Some(FocusChange::MoveTo(id));
+    }
+
     /// Returns whether the current node has the focus.
     #[must_use]
     pub fn has_focus(&self) -> bool {
@@ -655,6 +922,17 @@ impl<'a> EventCtx<'a> {
                     do_event(self, widget, id, event, transform, env)
                 }
             }
+            Event::Internal(InternalEvent::RouteWheelEvent {
+                target,
+                event: ref mut wheel_event,
+            }) => {
+                // same as routed pointer events
+                if id == Some(target) {
+                    do_event(self, widget, id, &mut Event::Wheel(*wheel_event), transform, env)
+                } else {
+                    do_event(self, widget, id, event, transform, env)
+                }
+            }
             // TODO remove? not sure that's still used
             Event::Internal(InternalEvent::RouteRedrawRequest(target)) => {
                 if id == Some(target) {
@@ -684,6 +962,48 @@ impl<'a> EventCtx<'a> {
                 });
             }
 
+            ////////////////////////////////////////////////////////////////////////////////////////
+            // Accessibility
+            Event::Internal(InternalEvent::BuildAccessibilityTree {
+                ref mut nodes,
+                bounds: parent_bounds,
+            }) => {
+                let bounds = cached_layout
+                    .map(|layout| {
+                        transform
+                            .then(&self.window_transform)
+                            .outer_transformed_rect(&layout.measurements.local_bounds())
+                    })
+                    .unwrap_or(parent_bounds);
+
+                match (id, widget.semantics()) {
+                    (Some(id), Some(semantics)) => {
+                        let mut children = Vec::new();
+                        {
+                            let mut child_event = Event::Internal(InternalEvent::BuildAccessibilityTree {
+                                nodes: &mut children,
+                                bounds,
+                            });
+                            do_event(self, widget, Some(id), &mut child_event, transform, env);
+                        }
+                        nodes.push(AccessibilityNode {
+                            id,
+                            semantics,
+                            bounds,
+                            children,
+                        });
+                    }
+                    _ => {
+                        // not part of the tree, but the descendants may be
+                        let mut child_event = Event::Internal(InternalEvent::BuildAccessibilityTree {
+                            nodes: &mut **nodes,
+                            bounds,
+                        });
+                        do_event(self, widget, id, &mut child_event, transform, env);
+                    }
+                }
+            }
+
             ////////////////////////////////////////////////////////////////////////////////////////
             // Other internal events
             Event::Internal(InternalEvent::UpdateChildFilter { ref mut filter }) => {
@@ -693,6 +1013,31 @@ impl<'a> EventCtx<'a> {
                 // propagate
                 do_event(self, widget, id, event, transform, env)
             }
+            Event::BuildFocusChain { ref mut chain } => {
+                let start = chain.len();
+                let mut entered = false;
+                if let Some(id) = id {
+                    entered = chain.enter_widget(id, widget.is_focusable());
+                    // wrappers share the ID of the widget they wrap: don't add it twice
+                    if widget.is_in_focus_chain() && chain.last_id() != Some(id) {
+                        chain.push(id, widget.tab_order());
+                    }
+                }
+                do_event(self, widget, id, event, transform, env);
+
+                if let Event::BuildFocusChain { chain } = &mut *event {
+                    if entered {
+                        chain.exit_widget();
+                    }
+                    // the bounds of the widget are known by its `WidgetPod`, which has the same ID
+                    if let (Some(id), Some(layout)) = (id, cached_layout) {
+                        let bounds = transform
+                            .then(&self.window_transform)
+                            .outer_transformed_rect(&layout.measurements.local_bounds());
+                        chain.set_bounds(start, id, bounds);
+                    }
+                }
+            }
             Event::Initialize => {
                 // directly pass to widget
                 do_event(self, widget, id, event, transform, env)
@@ -742,10 +1087,35 @@ impl DebugNode {
 
 pub struct LayerPaintCtx<'a> {
     pub skia_gpu_context: &'a mut sk::gpu::DirectContext,
+    /// Parts of the layer to repaint, in DIPs. `None` to repaint all of it.
+    pub(crate) damage: Option<Region>,
+}
+
+/// Converts a region in DIPs to the rectangles of pixels that it covers, inside a surface of the specified size.
+pub(crate) fn region_to_pixels(region: &Region, scale_factor: f64, surface_size: SizeI) -> Vec<RectI> {
+    let surface_bounds = RectI::new(PointI::origin(), surface_size);
+    region
+        .rects()
+        .iter()
+        .filter_map(|rect| {
+            let rect = Rect::new(
+                (rect.origin.to_vector() * scale_factor).to_point(),
+                rect.size * scale_factor,
+            )
+            .round_out();
+            RectI::new(
+                PointI::new(rect.origin.x as i32, rect.origin.y as i32),
+                SizeI::new(rect.size.width as i32, rect.size.height as i32),
+            )
+            .intersection(&surface_bounds)
+        })
+        .collect()
 }
 
 impl<'a> LayerPaintCtx<'a> {
     /// Creates a painting context on the layer and paints the content it using the specified closure.
+    ///
+    /// If only parts of the layer are damaged, the painting is clipped to them.
     pub fn paint_layer(&mut self, layer: &Layer, scale_factor: f64, f: impl FnOnce(&mut PaintCtx)) {
         // `layer.size()` is zero initially, and can stay that way if we did not call set_size.
         // In this case, there's nothing to paint, and return early.
@@ -753,7 +1123,10 @@ impl<'a> LayerPaintCtx<'a> {
             return;
         }
 
-        let layer_surface = layer.acquire_surface();
+        let layer_surface = match self.damage.take() {
+            Some(damage) => layer.acquire_surface_with_damage(&region_to_pixels(&damage, scale_factor, layer.size())),
+            None => layer.acquire_surface(),
+        };
         let surface_image_info = layer_surface.image_info();
         let surface_size = layer_surface.size();
 
@@ -789,13 +1162,31 @@ impl<'a> LayerPaintCtx<'a> {
             Some(&sk::SurfaceProps::new(Default::default(), sk::PixelGeometry::RGBH)),
         )
         .unwrap();
+
+        // the rest of the surface keeps the contents of the previous frames
+        if let Some(rects) = layer_surface.repaint_rects() {
+            let mut clip = sk::Region::new();
+            for rect in rects {
+                clip.op_rect(
+                    sk::IRect::new(rect.min_x(), rect.min_y(), rect.max_x(), rect.max_y()),
+                    sk::region::RegionOp::Union,
+                );
+            }
+            surface.canvas().clip_region(&clip, None);
+        }
         surface.canvas().clear(sk::Color4f::new(0.0, 0.0, 0.0, 0.0));
 
         // invoke the provided closure
+        frame_debugger::begin_layer(
+            SizeI::new(surface_size.width as i32, surface_size.height as i32),
+            scale_factor,
+        );
         {
             let mut paint_ctx = PaintCtx::new(&mut surface, layer, scale_factor, self.skia_gpu_context);
             f(&mut paint_ctx);
+            paint_ctx.draw_overdraw_heatmap();
         }
+        frame_debugger::end_layer(&mut surface);
 
         // flush the GPU frame
         let _span = trace_span!("Flush skia surface").entered();
@@ -835,6 +1226,14 @@ pub trait Widget {
     /// Measures this widgets and layouts the children of this widgets.
     fn layout(&self, ctx: &mut LayoutCtx, params: &LayoutParams, env: &Environment) -> Geometry;
 
+    /// Returns the intrinsic widths of the widget, if they can be computed without a full layout.
+    ///
+    /// Containers that size their children to their content (like auto-sized grid tracks) call this before falling
+    /// back to `speculative_layout`. The default implementation returns `None`.
+    fn intrinsic_widths(&self, _ctx: &mut LayoutCtx, _env: &Environment) -> Option<IntrinsicWidths> {
+        None
+    }
+
     /// Routes an event from a parent widgets to this widgets.
     ///
     /// This method should be called by parent widgets to propagate events to their children, instead of directly
@@ -869,6 +1268,48 @@ pub trait Widget {
     fn debug_node(&self) -> DebugNode {
         DebugNode { content: None }
     }
+
+    /// Describes this widget to assistive technologies.
+    ///
+    /// Widgets that return `None` (the default) are not part of the accessibility tree, but their children may be.
+    /// Only widgets with an ID can be part of the tree.
+    fn semantics(&self) -> Option<Semantics> {
+        None
+    }
+
+    /// Returns whether this widget can receive the keyboard focus.
+    ///
+    /// By default, widgets with interactive semantics (buttons, text inputs, sliders...) are focusable,
+    /// unless they are disabled. Override with `WidgetExt::focusable`.
+    fn is_focusable(&self) -> bool {
+        self.semantics()
+            .map(|semantics| semantics.role.is_interactive() && !semantics.disabled)
+            .unwrap_or(false)
+    }
+
+    /// Returns whether this widget is part of the focus chain, i.e. whether it can be focused with the keyboard (tab stops).
+    ///
+    /// By default, all focusable widgets are in the focus chain.
+    /// Widgets that are not in the chain can still be focused by clicking on them.
+    fn is_in_focus_chain(&self) -> bool {
+        self.is_focusable()
+    }
+
+    /// Returns the position of this widget in the `Tab` navigation order.
+    ///
+    /// Widgets with a positive tab index are visited first, in increasing order, then the widgets with a tab index
+    /// of zero (the default) in tree order. Override with `WidgetExt::tab_index`.
+    fn tab_order(&self) -> i32 {
+        0
+    }
+
+    /// Returns the stacking order of this widget among its siblings.
+    ///
+    /// Containers paint the children with a higher z-order above the others, and deliver events to them first.
+    /// The default is 0; wrappers should forward it to the wrapped widget. Set with `WidgetExt::z_index`.
+    fn z_order(&self) -> i32 {
+        0
+    }
 }
 
 /// Arc'd widgets.
@@ -881,6 +1322,10 @@ impl<T: Widget + ?Sized> Widget for Arc<T> {
         Widget::layout(&**self, ctx, params, env)
     }
 
+    fn intrinsic_widths(&self, ctx: &mut LayoutCtx, env: &Environment) -> Option<IntrinsicWidths> {
+        Widget::intrinsic_widths(&**self, ctx, env)
+    }
+
     fn route_event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
         Widget::route_event(&**self, ctx, event, env)
     }
@@ -904,6 +1349,26 @@ impl<T: Widget + ?Sized> Widget for Arc<T> {
     fn debug_node(&self) -> DebugNode {
         Widget::debug_node(&**self)
     }
+
+    fn semantics(&self) -> Option<Semantics> {
+        Widget::semantics(&**self)
+    }
+
+    fn is_focusable(&self) -> bool {
+        Widget::is_focusable(&**self)
+    }
+
+    fn is_in_focus_chain(&self) -> bool {
+        Widget::is_in_focus_chain(&**self)
+    }
+
+    fn tab_order(&self) -> i32 {
+        Widget::tab_order(&**self)
+    }
+
+    fn z_order(&self) -> i32 {
+        Widget::z_order(&**self)
+    }
 }
 
 pub struct WidgetIdDebug(Option<WidgetId>);
@@ -928,6 +1393,11 @@ impl WidgetId {
         WidgetId(call_id)
     }
 
+    /// Returns the ID as an integer, e.g. to identify the widget in the platform accessibility tree.
+    pub(crate) fn to_u64(self) -> u64 {
+        self.0.to_u64()
+    }
+
     #[composable]
     pub fn here() -> WidgetId {
         WidgetId(cache::current_call_id())
@@ -0,0 +1,309 @@
+//! Runtime diagnostics.
+//!
+//! Keeps track of the number of live `WidgetPod`s and compositor layers. Widget pods retain state
+//! (cached layouts, child filters) and possibly compositor resources: when a widget doesn't have a stable
+//! identity across recompositions (e.g. a composable called in a loop without a key), new pods are created on every
+//! recomposition and the old ones may be kept alive by the cache. The counters help catch this kind of leak.
+//!
+//! In debug builds, live widget pods are also attributed to the source location where they were created.
+//!
+//! This module also reports the results of the collection of unused resources (see `gc_stats`), and measures the
+//! end-to-end latency of input events (see `LatencyProbe`).
+use crate::gc::Collection;
+use kyute_shell::animation::PresentStatistics;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+#[cfg(debug_assertions)]
+use crate::cache;
+#[cfg(debug_assertions)]
+use std::{collections::HashMap, panic::Location};
+
+/// Number of latency samples kept to compute the statistics returned by `LatencyProbe::stats`.
+const LATENCY_SAMPLE_COUNT: usize = 120;
+/// Number of consecutive recompositions with a growing number of live objects after which a warning is emitted.
+const GROWTH_WARNING_THRESHOLD: usize = 10;
+/// Number of call sites listed in growth warnings.
+#[cfg(debug_assertions)]
+const GROWTH_WARNING_CALL_SITES: usize = 5;
+
+static LIVE_WIDGET_PODS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(debug_assertions)]
+static WIDGET_POD_CALL_SITES: Lazy<Mutex<HashMap<&'static Location<'static>, usize>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Number of live objects.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LiveCounts {
+    /// Number of live `WidgetPod`s.
+    pub widget_pods: usize,
+    /// Number of live compositor layers.
+    pub layers: usize,
+}
+
+/// Returns the number of live widget pods and compositor layers.
+pub fn live_counts() -> LiveCounts {
+    LiveCounts {
+        widget_pods: LIVE_WIDGET_PODS.load(Ordering::Relaxed),
+        layers: kyute_shell::animation::live_layer_count(),
+    }
+}
+
+/// Returns the number of live widget pods for each call site that created them, sorted by decreasing count.
+///
+/// The call site is the location of the innermost composable function call that created the widget pod,
+/// or the caller of `WidgetPod::new` when created outside of recomposition.
+#[cfg(debug_assertions)]
+pub fn widget_pod_call_sites() -> Vec<(&'static Location<'static>, usize)> {
+    let mut sites: Vec<_> = WIDGET_POD_CALL_SITES
+        .lock()
+        .iter()
+        .map(|(location, count)| (*location, *count))
+        .collect();
+    sites.sort_by(|a, b| b.1.cmp(&a.1));
+    sites
+}
+
+/// Counts a live widget pod. Owned by the `WidgetPod`.
+pub(crate) struct WidgetPodToken {
+    #[cfg(debug_assertions)]
+    location: &'static Location<'static>,
+}
+
+impl WidgetPodToken {
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub(crate) fn new() -> WidgetPodToken {
+        LIVE_WIDGET_PODS.fetch_add(1, Ordering::Relaxed);
+        WidgetPodToken {
+            #[cfg(debug_assertions)]
+            location: {
+                let location = cache::current_call_location().unwrap_or_else(Location::caller);
+                *WIDGET_POD_CALL_SITES.lock().entry(location).or_insert(0) += 1;
+                location
+            },
+        }
+    }
+}
+
+impl Drop for WidgetPodToken {
+    fn drop(&mut self) {
+        LIVE_WIDGET_PODS.fetch_sub(1, Ordering::Relaxed);
+        #[cfg(debug_assertions)]
+        {
+            let mut sites = WIDGET_POD_CALL_SITES.lock();
+            if let Some(count) = sites.get_mut(self.location) {
+                *count -= 1;
+                if *count == 0 {
+                    sites.remove(self.location);
+                }
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Growth detection
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+struct GrowthTracker {
+    last: LiveCounts,
+    /// Number of consecutive recompositions during which the counts grew.
+    streak: usize,
+}
+
+static GROWTH_TRACKER: Lazy<Mutex<GrowthTracker>> = Lazy::new(|| {
+    Mutex::new(GrowthTracker {
+        last: LiveCounts::default(),
+        streak: 0,
+    })
+});
+
+/// Compares the live counts with those of the previous recomposition, and warns if they have been
+/// growing monotonically for a while.
+///
+/// Should be called once the previous widget tree has been replaced by the result of a recomposition.
+pub(crate) fn check_growth() {
+    let counts = live_counts();
+    let mut tracker = GROWTH_TRACKER.lock();
+    let last = tracker.last;
+    tracker.last = counts;
+
+    if counts.widget_pods < last.widget_pods || counts.layers < last.layers {
+        tracker.streak = 0;
+        return;
+    }
+    if counts == last {
+        // nothing was recomposed, or the tree is stable
+        return;
+    }
+
+    tracker.streak += 1;
+    if tracker.streak % GROWTH_WARNING_THRESHOLD == 0 {
+        warn!(
+            "live object counts have grown over the last {} recompositions ({} widget pods, {} layers): widget identities may not be stable across recompositions",
+            tracker.streak, counts.widget_pods, counts.layers
+        );
+        #[cfg(debug_assertions)]
+        for (location, count) in widget_pod_call_sites().into_iter().take(GROWTH_WARNING_CALL_SITES) {
+            warn!("    {} live widget pods created at {}", count, location);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Resource collection
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Statistics of the collection of unused compositor and GPU resources (see `gc`).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct GcStats {
+    /// Number of collection passes so far.
+    pub passes: usize,
+    /// Total number of compositor layers whose contents were released.
+    pub layers_released: usize,
+    /// Total number of surfaces released.
+    pub surfaces_released: usize,
+    /// Total number of image textures released.
+    pub textures_released: usize,
+    /// Size in bytes of the GPU resources cached by skia after the last pass.
+    pub gpu_resource_bytes: usize,
+    /// Number of GPU resources cached by skia after the last pass.
+    pub gpu_resource_count: usize,
+}
+
+static GC_STATS: Lazy<Mutex<GcStats>> = Lazy::new(|| Mutex::new(GcStats::default()));
+
+/// Returns the statistics of the resource collection passes.
+pub fn gc_stats() -> GcStats {
+    *GC_STATS.lock()
+}
+
+/// Accumulates the results of a collection pass.
+pub(crate) fn record_collection(collection: &Collection) {
+    let mut stats = GC_STATS.lock();
+    stats.passes += 1;
+    stats.layers_released += collection.layers_released;
+    stats.surfaces_released += collection.surfaces_released;
+    stats.textures_released += collection.textures_released;
+    stats.gpu_resource_bytes = collection.gpu_resource_bytes;
+    stats.gpu_resource_count = collection.gpu_resource_count;
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Input latency
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Statistics over the last latency samples of a `LatencyProbe`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LatencyStats {
+    /// Latency of the last measured input event.
+    pub last: Duration,
+    pub average: Duration,
+    pub max: Duration,
+    /// Number of samples.
+    pub count: usize,
+}
+
+/// Measures the time between input events and the display of the first frame that shows their effects.
+///
+/// The timestamps of input events are provided by the OS (see `PointerEvent::timestamp` and
+/// `EventCtx::input_timestamp`), and the display times come from the presentation statistics of a compositor layer.
+/// An input is measured once the layer has been repainted in response to it (`frame_presented`), and the frame has
+/// been displayed (`update`).
+#[derive(Clone, Debug, Default)]
+pub struct LatencyProbe {
+    /// Inputs waiting for the display of the frame painted in response to them, and the present count of this frame.
+    in_flight: VecDeque<(Instant, u32)>,
+    /// Latest samples, oldest first.
+    samples: VecDeque<Duration>,
+}
+
+impl LatencyProbe {
+    pub fn new() -> LatencyProbe {
+        LatencyProbe::default()
+    }
+
+    /// Signals that a frame was presented in the layer in response to an input event produced at `input_time`.
+    ///
+    /// `present_count` is the present count of the layer once the frame was presented (see
+    /// `PresentStatistics::present_count`).
+    pub fn frame_presented(&mut self, input_time: Instant, present_count: u32) {
+        self.in_flight.push_back((input_time, present_count));
+    }
+
+    /// Measures the latency of the inputs whose frames have been displayed, given the current presentation
+    /// statistics of the layer.
+    ///
+    /// Returns whether new samples were recorded.
+    pub fn update(&mut self, stats: &PresentStatistics) -> bool {
+        let mut updated = false;
+        while let Some(&(input_time, present_count)) = self.in_flight.front() {
+            if stats.displayed_present_count < present_count {
+                break;
+            }
+            self.in_flight.pop_front();
+            // the frame may have been displayed before the input if the layer wasn't actually repainted
+            // (e.g. only child layers were), in which case there's nothing to measure
+            if let Some(latency) = stats.displayed_time.checked_duration_since(input_time) {
+                if self.samples.len() == LATENCY_SAMPLE_COUNT {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back(latency);
+                updated = true;
+            }
+        }
+        updated
+    }
+
+    /// Returns statistics over the last samples, or `None` if no input has been measured yet.
+    pub fn stats(&self) -> Option<LatencyStats> {
+        let last = *self.samples.back()?;
+        let count = self.samples.len();
+        Some(LatencyStats {
+            last,
+            average: self.samples.iter().sum::<Duration>() / count as u32,
+            max: self.samples.iter().copied().max().unwrap_or_default(),
+            count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_probe() {
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+        let mut probe = LatencyProbe::new();
+        probe.frame_presented(t0, 1);
+        probe.frame_presented(t0 + ms(10), 2);
+
+        // first frame displayed
+        let mut stats = PresentStatistics {
+            present_count: 2,
+            displayed_present_count: 1,
+            displayed_time: t0 + ms(20),
+        };
+        assert!(probe.update(&stats));
+        assert_eq!(probe.stats().unwrap().last, ms(20));
+
+        // a frame displayed before the input is not a sample
+        probe.frame_presented(t0 + ms(40), 2);
+        stats.displayed_present_count = 2;
+        stats.displayed_time = t0 + ms(30);
+        assert!(probe.update(&stats));
+        let latency = probe.stats().unwrap();
+        assert_eq!(latency.count, 2);
+        assert_eq!(latency.last, ms(20));
+        assert_eq!(latency.max, ms(20));
+        assert!(probe.in_flight.is_empty());
+    }
+}
@@ -1,13 +1,15 @@
 //! Wrapper around skia images.
-use crate::{asset::AssetLoadError, drawing::ToSkia, Asset, AssetLoader, Data, EnvKey, SizeI};
+use crate::{asset::AssetLoadError, drawing::ToSkia, gc, Asset, AssetLoader, Data, EnvKey, SizeI};
 use std::{
     collections::HashMap,
     ffi::c_void,
+    future::Future,
     io,
     io::Read,
     mem,
     sync::{Arc, Mutex},
 };
+use tokio::sync::OnceCell;
 
 /// An image. Paper-thin wrapper around skia images.
 #[derive(Clone, Debug)]
@@ -34,6 +36,12 @@ impl Image {
     }
 }
 
+impl From<skia_safe::Image> for Image {
+    fn from(image: skia_safe::Image) -> Self {
+        Image(image)
+    }
+}
+
 impl ToSkia for Image {
     type Target = skia_safe::Image;
 
@@ -56,32 +64,55 @@ impl Asset for Image {
             // There used to be a public `DecodeToRaster` API that could take a void* but it was removed because it was "unused"
             let sk_data = skia_safe::Data::new_bytes(bytes);
             let sk_image = skia_safe::Image::from_encoded(sk_data)
-                .unwrap()
-                .new_raster_image() // must call to force decoding and release
-                .unwrap();
+                .and_then(|image| image.new_raster_image()) // must call to force decoding and release
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unsupported or invalid image data"))?;
             Ok(Image(sk_image))
         }
     }
 }
 
 /// Image cache entry.
-#[derive(Clone)]
+#[derive(Default)]
 struct Entry {
+    /// The decoded image, set once loading has finished (`None` if loading failed).
+    decoded: OnceCell<Option<Image>>,
+    /// GPU texture uploaded from the decoded image.
+    texture: Mutex<Option<Texture>>,
+}
+
+/// GPU texture of an image cache entry.
+struct Texture {
+    /// ID of the GPU context that owns the texture.
+    context_id: usize,
     image: Image,
+    /// Last frame in which the texture was requested (see `gc::current_frame`).
+    last_used: u64,
 }
 
 /// Image cache innards.
 struct Inner {
-    entries: HashMap<String, Entry>,
+    entries: HashMap<String, Arc<Entry>>,
 }
 
 /// Loads and caches images by URI.
+///
+/// Images can be loaded asynchronously with `load_async`, in which case they are decoded on a worker thread.
+/// Decoded images are also uploaded to GPU textures on demand (see `texture`).
 #[derive(Clone)]
 pub struct ImageCache {
     asset_loader: AssetLoader,
     inner: Arc<Mutex<Inner>>,
 }
 
+/// Returns an ID that identifies a GPU context, for the lifetime of the context.
+fn gpu_context_id(context: &skia_safe::gpu::DirectContext) -> usize {
+    // same as `Image::same`: no way to access the native pointer
+    unsafe {
+        let ptr: *const c_void = mem::transmute_copy(context);
+        ptr as usize
+    }
+}
+
 impl ImageCache {
     pub fn new(asset_loader: AssetLoader) -> ImageCache {
         ImageCache {
@@ -92,17 +123,100 @@ impl ImageCache {
         }
     }
 
-    pub fn load(&self, uri: &str) -> Result<Image, AssetLoadError<io::Error>> {
+    fn entry(&self, uri: &str) -> Arc<Entry> {
         let mut inner = self.inner.lock().unwrap();
+        inner.entries.entry(uri.to_owned()).or_default().clone()
+    }
 
-        if let Some(entry) = inner.entries.get(uri) {
-            return Ok(entry.image.clone());
+    /// Loads an image synchronously, or returns the cached image.
+    pub fn load(&self, uri: &str) -> Result<Image, AssetLoadError<io::Error>> {
+        let entry = self.entry(uri);
+        if let Some(Some(image)) = entry.decoded.get() {
+            return Ok(image.clone());
         }
 
         let image = self.asset_loader.load::<Image>(uri)?;
-        inner.entries.insert(uri.to_owned(), Entry { image: image.clone() });
+        // may fail if an asynchronous load finished in the meantime, in which case it has the same image
+        let _ = entry.decoded.set(Some(image.clone()));
         Ok(image)
     }
+
+    /// Loads an image asynchronously, or returns the cached image.
+    ///
+    /// The image is read and decoded on a worker thread. Concurrent loads of the same URI share the same task.
+    /// Resolves to `None` if the image couldn't be loaded; failures are cached as well until the image
+    /// is invalidated.
+    pub fn load_async(&self, uri: &str) -> impl Future<Output = Option<Image>> + Send + 'static {
+        let entry = self.entry(uri);
+        let load = self.asset_loader.load_async::<Image>(uri);
+        let uri = uri.to_owned();
+        async move {
+            entry
+                .decoded
+                .get_or_init(|| async move {
+                    match load.await {
+                        Ok(image) => {
+                            trace!("image `{}` successfully loaded", uri);
+                            Some(image)
+                        }
+                        Err(err) => {
+                            warn!("failed to load image `{}`: {}", uri, err);
+                            None
+                        }
+                    }
+                })
+                .await
+                .clone()
+        }
+    }
+
+    /// Returns a GPU texture for the image at the specified URI, or `None` if the image hasn't been loaded yet
+    /// or couldn't be uploaded.
+    ///
+    /// The texture is created on first use, and reused as long as it's requested with the same GPU context.
+    pub fn texture(&self, uri: &str, context: &mut skia_safe::gpu::DirectContext) -> Option<Image> {
+        let entry = self.inner.lock().unwrap().entries.get(uri).cloned()?;
+        let image = entry.decoded.get()?.as_ref()?;
+        let context_id = gpu_context_id(context);
+        let mut texture = entry.texture.lock().unwrap();
+        match *texture {
+            Some(ref mut texture) if texture.context_id == context_id => {
+                texture.last_used = gc::current_frame();
+                Some(texture.image.clone())
+            }
+            _ => {
+                let uploaded = Image(image.0.new_texture_image(context, skia_safe::gpu::Mipmapped::No)?);
+                *texture = Some(Texture {
+                    context_id,
+                    image: uploaded.clone(),
+                    last_used: gc::current_frame(),
+                });
+                Some(uploaded)
+            }
+        }
+    }
+
+    /// Releases the GPU textures that haven't been requested since the specified frame. The decoded images are kept,
+    /// and uploaded again when requested.
+    ///
+    /// Returns the number of textures released.
+    pub(crate) fn release_unused_textures(&self, unused_since: u64) -> usize {
+        let inner = self.inner.lock().unwrap();
+        let mut released = 0;
+        for entry in inner.entries.values() {
+            let mut texture = entry.texture.lock().unwrap();
+            if matches!(*texture, Some(ref texture) if texture.last_used < unused_since) {
+                *texture = None;
+                released += 1;
+            }
+        }
+        released
+    }
+
+    /// Removes an image from the cache, so that it's loaded again the next time it's requested.
+    pub fn invalidate(&self, uri: &str) {
+        self.inner.lock().unwrap().entries.remove(uri);
+    }
 }
 
 impl_env_value!(ImageCache);
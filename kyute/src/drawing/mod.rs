@@ -7,6 +7,7 @@ use std::fmt;
 mod border;
 mod box_shadow;
 mod image;
+pub(crate) mod overdraw;
 mod paint;
 mod path;
 mod svg_path;
@@ -305,6 +306,9 @@ impl Shape {
     pub fn fill(&self, ctx: &mut PaintCtx, paint: &Paint) {
         match self {
             Shape::RoundedRect(rrect) => {
+                if !paint.is_transparent() {
+                    ctx.record_overdraw(rrect.rect);
+                }
                 let mut paint = paint.to_sk_paint(rrect.rect);
                 paint.set_style(sk::PaintStyle::Fill);
                 ctx.surface.canvas().draw_rrect(rrect.to_skia(), &paint);
@@ -345,6 +349,8 @@ pub struct PaintCtx<'a> {
     pub bounds: Rect,
     pub clip_bounds: Rect,
     pub(crate) debug: bool,
+    /// Overdraw counts of the layer, if the overdraw visualization is enabled (see `SHOW_OVERDRAW`).
+    overdraw: Option<overdraw::OverdrawMap>,
 }
 
 impl<'a> fmt::Debug for PaintCtx<'a> {
@@ -364,6 +370,11 @@ impl<'a> PaintCtx<'a> {
         let width = parent_layer.size().width as f64 / scale_factor;
         let height = parent_layer.size().height as f64 / scale_factor;
         let bounds = Rect::new(Point::origin(), Size::new(width, height));
+        let overdraw = if overdraw::is_enabled() {
+            Some(overdraw::OverdrawMap::new(surface.width(), surface.height()))
+        } else {
+            None
+        };
         PaintCtx {
             parent_layer,
             layer_transform: Transform::identity(),
@@ -374,6 +385,7 @@ impl<'a> PaintCtx<'a> {
             bounds,
             clip_bounds: bounds,
             debug: false,
+            overdraw,
         }
     }
 
@@ -392,6 +404,34 @@ impl<'a> PaintCtx<'a> {
         self.debug = enabled;
     }
 
+    /// Records that an area, in local coordinates, has been painted over.
+    ///
+    /// Used by the overdraw visualization (see `SHOW_OVERDRAW`). The drawing helpers in `PaintCtxExt` call this
+    /// automatically; widgets that fill large areas directly on the canvas can call it to appear in the heatmap.
+    pub fn record_overdraw(&mut self, rect: Rect) {
+        if let Some(ref mut map) = self.overdraw {
+            let canvas = self.surface.canvas();
+            let (mut device_rect, _) = canvas.local_to_device_as_3x3().map_rect(rect.to_skia());
+            // nothing is painted outside the clip
+            let clip = match canvas.device_clip_bounds() {
+                Some(clip) => sk::Rect::from(clip),
+                None => return,
+            };
+            if device_rect.intersect(clip) {
+                map.record(device_rect);
+            }
+        }
+    }
+
+    /// Draws the overdraw heatmap over the contents of the layer, if the overdraw visualization is enabled.
+    ///
+    /// Called once the layer has been painted.
+    pub(crate) fn draw_overdraw_heatmap(&mut self) {
+        if let Some(ref map) = self.overdraw {
+            map.draw_heatmap(self.surface.canvas());
+        }
+    }
+
     /*/// Overrides the current visual state flags and calls the provided closure.
     ///
     /// # Examples
@@ -0,0 +1,133 @@
+//! Overdraw visualization.
+//!
+//! When enabled (see `SHOW_OVERDRAW`), the areas written by the drawing helpers of `PaintCtx` are counted during
+//! the painting of each layer, and a heatmap is drawn over the layer once it's painted.
+//! Areas painted once are left as is; then, from twice to five times or more: blue, green, pink, red.
+use crate::{drawing::ToSkia, Color};
+use skia_safe as sk;
+use std::cell::Cell;
+
+/// Size of the cells of the overdraw map, in physical pixels.
+const CELL_SIZE: i32 = 4;
+
+/// Heatmap colors, indexed by the number of times a cell was written minus 2.
+const HEATMAP_COLORS: [Color; 4] = [
+    Color::new(0.0, 0.0, 1.0, 0.35),
+    Color::new(0.0, 1.0, 0.0, 0.35),
+    Color::new(1.0, 0.4, 0.7, 0.4),
+    Color::new(1.0, 0.0, 0.0, 0.45),
+];
+
+#[derive(Copy, Clone, Default)]
+struct Mode {
+    enabled: bool,
+    repaint_all: bool,
+}
+
+thread_local! {
+    /// Overdraw visualization mode of the window being repainted.
+    static MODE: Cell<Mode> = Cell::new(Mode::default());
+}
+
+/// Called before repainting the layers of a window.
+///
+/// `repaint_all` forces all layers to be repainted, even if their contents are still valid.
+pub(crate) fn begin_window_repaint(enabled: bool, repaint_all: bool) {
+    MODE.with(|mode| mode.set(Mode { enabled, repaint_all }));
+}
+
+/// Called after repainting the layers of a window.
+pub(crate) fn end_window_repaint() {
+    MODE.with(|mode| mode.set(Mode::default()));
+}
+
+/// Returns whether the overdraw of the layers being painted is counted.
+pub(crate) fn is_enabled() -> bool {
+    MODE.with(|mode| mode.get().enabled)
+}
+
+/// Returns whether all layers must be repainted.
+///
+/// While the overdraw visualization is enabled, every layer is repainted entirely, so that the heatmap reflects
+/// the whole contents of the layer.
+pub(crate) fn repaint_all() -> bool {
+    MODE.with(|mode| mode.get().repaint_all)
+}
+
+/// Number of times each area of a layer was written.
+pub(crate) struct OverdrawMap {
+    columns: i32,
+    rows: i32,
+    counts: Vec<u8>,
+}
+
+impl OverdrawMap {
+    /// Creates an overdraw map for a layer of the specified size in pixels.
+    pub(crate) fn new(width: i32, height: i32) -> OverdrawMap {
+        let columns = (width + CELL_SIZE - 1) / CELL_SIZE;
+        let rows = (height + CELL_SIZE - 1) / CELL_SIZE;
+        OverdrawMap {
+            columns,
+            rows,
+            counts: vec![0; (columns * rows).max(0) as usize],
+        }
+    }
+
+    /// Records a write to the specified area, in layer pixels.
+    ///
+    /// Only the cells whose center is inside the area are counted, so that antialiased edges of adjacent
+    /// shapes aren't seen as overdraw.
+    pub(crate) fn record(&mut self, rect: sk::Rect) {
+        let cell = CELL_SIZE as f32;
+        let x0 = ((rect.left / cell - 0.5).ceil() as i32).max(0);
+        let y0 = ((rect.top / cell - 0.5).ceil() as i32).max(0);
+        let x1 = ((rect.right / cell - 0.5).ceil() as i32).min(self.columns);
+        let y1 = ((rect.bottom / cell - 0.5).ceil() as i32).min(self.rows);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let count = &mut self.counts[(y * self.columns + x) as usize];
+                *count = count.saturating_add(1);
+            }
+        }
+    }
+
+    /// Draws the heatmap on a canvas, in layer pixels.
+    pub(crate) fn draw_heatmap(&self, canvas: &mut sk::Canvas) {
+        let paints: Vec<sk::Paint> = HEATMAP_COLORS
+            .iter()
+            .map(|color| sk::Paint::new(color.to_skia(), None))
+            .collect();
+        let level = |count: u8| {
+            (count as usize)
+                .checked_sub(2)
+                .map(|level| level.min(HEATMAP_COLORS.len() - 1))
+        };
+
+        canvas.save();
+        canvas.reset_matrix();
+        for y in 0..self.rows {
+            // draw runs of cells with the same level
+            let row = &self.counts[(y * self.columns) as usize..((y + 1) * self.columns) as usize];
+            let mut x = 0;
+            while x < row.len() {
+                let run_level = level(row[x]);
+                let start = x;
+                while x < row.len() && level(row[x]) == run_level {
+                    x += 1;
+                }
+                if let Some(run_level) = run_level {
+                    canvas.draw_rect(
+                        sk::Rect::from_xywh(
+                            (start as i32 * CELL_SIZE) as f32,
+                            (y * CELL_SIZE) as f32,
+                            ((x - start) as i32 * CELL_SIZE) as f32,
+                            CELL_SIZE as f32,
+                        ),
+                        &paints[run_level],
+                    );
+                }
+            }
+        }
+        canvas.restore();
+    }
+}
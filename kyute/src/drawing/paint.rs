@@ -1,6 +1,7 @@
 //! Description of paints.
 use crate::{
     cache,
+    color::{self, ColorInterpolation, ColorSpace},
     drawing::{Image, ToSkia, IMAGE_CACHE},
     style, Angle, Color, Data, Offset, Rect,
 };
@@ -138,6 +139,7 @@ impl Paint {
 
                 let mut resolved_gradient = linear_gradient.clone();
                 resolved_gradient.resolve_stop_positions();
+                resolved_gradient.resample_stops();
 
                 let positions: Vec<_> = resolved_gradient
                     .stops
@@ -260,6 +262,9 @@ pub struct LinearGradient {
     pub angle: Angle,
     /// List of color stops.
     pub stops: Vec<ColorStop>,
+    /// How colors are interpolated between stops.
+    #[serde(default)]
+    pub interpolation: ColorInterpolation,
 }
 
 impl Data for LinearGradient {
@@ -274,6 +279,7 @@ impl LinearGradient {
         LinearGradient {
             angle: Default::default(),
             stops: vec![],
+            interpolation: Default::default(),
         }
     }
 
@@ -283,6 +289,12 @@ impl LinearGradient {
         self
     }
 
+    /// Sets how colors are interpolated between stops (by default, in sRGB).
+    pub fn interpolation(mut self, interpolation: impl Into<ColorInterpolation>) -> Self {
+        self.interpolation = interpolation.into();
+        self
+    }
+
     /// Appends a color stop to this gradient.
     pub fn stop(mut self, color: Color, position: impl Into<Option<f64>>) -> Self {
         self.stops.push(ColorStop {
@@ -347,6 +359,35 @@ impl LinearGradient {
             }
         }
     }
+
+    /// Adds intermediate stops between each pair of stops, so that the gradient, which is drawn in sRGB,
+    /// follows the interpolation space of the gradient.
+    ///
+    /// Stop positions must be resolved.
+    pub(crate) fn resample_stops(&mut self) {
+        /// Number of intermediate stops between two stops.
+        const SAMPLES: usize = 8;
+
+        let interpolation = self.interpolation;
+        if interpolation.space == ColorSpace::Srgb || self.stops.len() < 2 {
+            return;
+        }
+
+        let mut stops = Vec::with_capacity((self.stops.len() - 1) * SAMPLES + 1);
+        for pair in self.stops.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let (pa, pb) = (a.position.unwrap(), b.position.unwrap());
+            for i in 0..SAMPLES {
+                let t = i as f64 / SAMPLES as f64;
+                stops.push(ColorStop {
+                    position: Some(pa + (pb - pa) * t),
+                    color: color::mix(a.color, b.color, t as f32, interpolation),
+                });
+            }
+        }
+        stops.push(self.stops.last().unwrap().clone());
+        self.stops = stops;
+    }
 }
 
 impl Default for LinearGradient {
@@ -1,4 +1,4 @@
-use crate::{cache, Atom, Color, Data, Length, SideOffsets};
+use crate::{cache, text::TextLayoutMode, Atom, Color, Data, Length, SideOffsets, State};
 use once_cell::sync::Lazy;
 use std::{
     any::Any,
@@ -78,6 +78,8 @@ impl_env_value!(Color);
 impl_env_value!(String);
 impl_env_value!(SideOffsets);
 impl_env_value!(Length);
+impl_env_value!(TextLayoutMode);
+impl_env_value!(kyute_shell::input::ScrollSettings);
 
 impl<T: Any + Send + Sync> EnvValue for Arc<T> {
     fn as_any(&self) -> &dyn Any {
@@ -203,6 +205,32 @@ impl Environment {
         self.0.get(&name)
     }
 
+    /// Returns whether both environments bind the same keys to the same values.
+    ///
+    /// Values are compared by identity, since they can't be compared in general. Unlike `same`, this returns true
+    /// for environments built separately from the same values, e.g. by merging the same environments twice.
+    pub(crate) fn same_values(&self, other: &Environment) -> bool {
+        fn flatten<'a>(env: &'a EnvImpl, values: &mut HashMap<&'a Atom, &'a Arc<dyn Any + Send + Sync>>) {
+            if let Some(ref parent) = env.parent {
+                flatten(parent, values);
+            }
+            values.extend(env.values.iter());
+        }
+
+        if self.same(other) {
+            return true;
+        }
+        let mut a = HashMap::new();
+        let mut b = HashMap::new();
+        flatten(&self.0, &mut a);
+        flatten(&other.0, &mut b);
+        a.len() == b.len()
+            && a.iter().all(|(key, value)| match b.get(key) {
+                Some(other) => Arc::as_ptr(value) as *const () == Arc::as_ptr(other) as *const (),
+                None => false,
+            })
+    }
+
     pub fn merged(&self, mut with: Environment) -> Environment {
         let inner = Arc::make_mut(&mut with.0);
         if let Some(parent) = inner.parent.take() {
@@ -217,6 +245,79 @@ impl Environment {
 
 //--------------------------------------------------------------------------------------------------
 
+/// A writable environment value.
+///
+/// Created with [`cache::with_env_state`], which provides the value to the composables called in its scope.
+/// Widgets in that scope can retrieve the slot from the environment (see [`EnvKey::state`]) and set the value
+/// in response to an event: this invalidates the scope of `with_env_state`, which is then recomposed
+/// with the new value. Other parts of the UI are not affected.
+pub struct EnvState<T> {
+    state: State<T>,
+}
+
+impl<T> Clone for EnvState<T> {
+    fn clone(&self) -> Self {
+        EnvState {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for EnvState<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("EnvState").field(&self.state).finish()
+    }
+}
+
+impl<T: EnvValue> EnvState<T> {
+    pub(crate) fn new(state: State<T>) -> EnvState<T> {
+        EnvState { state }
+    }
+
+    /// Returns the current value.
+    pub fn get(&self) -> T {
+        self.state.get()
+    }
+
+    /// Sets the value, and invalidates the scope that provides it.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn set(&self, value: T) {
+        self.state.set(value)
+    }
+}
+
+impl<T: EnvValue> EnvValue for EnvState<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Returns the name under which the writable slot of an environment key is stored.
+fn env_state_atom(key: &Atom) -> Atom {
+    Atom::from(format!("{}#state", key))
+}
+
+impl<T: EnvValue> EnvKey<T> {
+    /// Returns the writable slot of this key in the given environment, if the value was provided by
+    /// [`cache::with_env_state`].
+    pub fn state(&self, env: &Environment) -> Option<EnvState<T>> {
+        env.0.get(&env_state_atom(&self.key))
+    }
+}
+
+impl Environment {
+    /// Adds or overrides a given key and the associated writable slot.
+    pub(crate) fn set_state<T>(&mut self, key: &EnvKey<T>, state: EnvState<T>)
+    where
+        T: EnvValue,
+    {
+        self.set_inner(key.key.clone(), state.get());
+        self.set_inner(env_state_atom(&key.key), state);
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+
 /// Either a value or a reference to a value in an environment.
 #[derive(Clone, serde::Deserialize)]
 #[serde(untagged)]
@@ -301,3 +402,28 @@ where
         EnvRef::Inline(T::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_values() {
+        let first: EnvKey<f64> = EnvKey::new(Atom::from("test-env-same-values-first"));
+        let second: EnvKey<f64> = EnvKey::new(Atom::from("test-env-same-values-second"));
+        let base = Environment::new().add(first, 1.0);
+        let overrides = Environment::new().add(second.clone(), 2.0);
+        let merged = base.merged(overrides.clone());
+        let merged_again = base.merged(overrides.clone());
+        assert!(!merged.same(&merged_again));
+        assert!(merged.same_values(&merged_again));
+
+        // overriding a value with an equal one creates a new value
+        let overridden = merged.clone().add(second, 2.0);
+        assert!(!merged.same_values(&overridden));
+        // and so does adding a key
+        let extended = base.merged(Environment::new());
+        assert!(!extended.same_values(&merged));
+        assert!(extended.same_values(&base));
+    }
+}
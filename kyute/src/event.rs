@@ -1,16 +1,20 @@
 //! [`Events`](Event) sent to widgets, and related types.
-use crate::{bloom::Bloom, Point, WidgetId};
+use crate::{
+    accessibility::AccessibilityNode, bloom::Bloom, command::CommandId, focus::FocusChain, LayoutCtx, Offset, Point,
+    Rect, Size, WidgetId,
+};
 use std::{
     collections::{HashMap, HashSet},
     fmt,
     fmt::Formatter,
+    time::Instant,
 };
 use winit::event::DeviceId;
 // FIXME: reexport/import from kyute-shell?
-use crate::core::DebugWidgetTreeNode;
+use crate::{core::DebugWidgetTreeNode, widget::ScrollLinkedElement};
 pub use keyboard_types::{CompositionEvent, Key, KeyboardEvent, Modifiers};
 use kyute_common::Transform;
-use kyute_shell::winit;
+use kyute_shell::{input::ScrollSettings, window::WindowPart, winit, TypedData};
 
 /// Represents the type of pointer.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -18,9 +22,38 @@ pub enum PointerType {
     Mouse,
     Pen,
     Stylus,
+    Touch,
     Other,
 }
 
+/// Identifies a pointer.
+///
+/// Each contact on a touch screen is a distinct pointer, so that several of them can be tracked (and captured by
+/// different widgets) at the same time.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PointerId {
+    /// The pointer of a mouse, or of a device that emulates one (touchpad, pen).
+    Mouse(DeviceId),
+    /// A contact on a touch screen, with the ID of the touch, which is unique while the contact lasts.
+    Touch(DeviceId, u64),
+}
+
+impl PointerId {
+    /// Returns the device that produces the pointer.
+    pub fn device_id(&self) -> DeviceId {
+        match *self {
+            PointerId::Mouse(device_id) | PointerId::Touch(device_id, _) => device_id,
+        }
+    }
+
+    pub fn pointer_type(&self) -> PointerType {
+        match *self {
+            PointerId::Mouse(_) => PointerType::Mouse,
+            PointerId::Touch(_, _) => PointerType::Touch,
+        }
+    }
+}
+
 /// Represents a pointer button.
 // TODO why u no bitflags?
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -126,12 +159,17 @@ pub struct PointerEvent {
     /// The state of the mouse buttons when this event was emitted.
     pub buttons: PointerButtons,
     /// Identifies the pointer.
-    pub pointer_id: winit::event::DeviceId,
+    pub pointer_id: PointerId,
     /// The button that triggered this event, if there is one.
     pub button: Option<PointerButton>,
     /// The repeat count for double, triple (and more) for button press events (`Event::PointerDown`).
     /// Otherwise, the value is unspecified.
     pub repeat_count: u32,
+    /// Time at which the OS produced the input event.
+    ///
+    /// For synthetic events (e.g. `PointerOver`), this is the time of the input event that caused them, or the time at
+    /// which they were created.
+    pub timestamp: Instant,
     //pub contact_width: f64,
     //pub contact_height: f64,
     //pub pressure: f32,
@@ -187,6 +225,50 @@ pub struct WheelEvent {
     pub delta_mode: WheelDeltaMode,
 }
 
+impl WheelEvent {
+    /// Returns the distance by which this event scrolls the contents of a view, in DIPs.
+    ///
+    /// Positive values scroll towards the end of the contents (right and down). Line deltas are counted in wheel
+    /// notches, converted with `settings`: `line_size` is the width of a character and the height of a line,
+    /// and `page_size` the size of the visible area of the view.
+    pub fn scroll_delta(&self, settings: &ScrollSettings, line_size: Size, page_size: Size) -> Offset {
+        let (mut dx, mut dy) = (self.delta_x, self.delta_y);
+        if settings.shift_scrolls_horizontally && self.pointer.modifiers.contains(Modifiers::SHIFT) && dx == 0.0 {
+            dx = dy;
+            dy = 0.0;
+        }
+        let (dx, dy) = match self.delta_mode {
+            WheelDeltaMode::Pixel => (dx, dy),
+            WheelDeltaMode::Line => (
+                dx * settings.chars_per_notch * line_size.width,
+                match settings.lines_per_notch {
+                    Some(lines) => dy * lines * line_size.height,
+                    None => dy * page_size.height,
+                },
+            ),
+            WheelDeltaMode::Page => (dx * page_size.width, dy * page_size.height),
+        };
+        // positive deltas move the wheel (and the viewport) towards the start of the contents
+        if settings.natural_scrolling {
+            Offset::new(dx, dy)
+        } else {
+            Offset::new(-dx, -dy)
+        }
+    }
+}
+
+/// Data dropped on the window, e.g. files dragged from the file explorer.
+///
+/// The data has already been checked against the `DataTransferPolicy` of the application (see
+/// `shell::set_data_transfer_policy`): data that was rejected isn't delivered.
+#[derive(Clone, Debug)]
+pub struct DropEvent {
+    /// Position of the drop, in local coordinates.
+    pub position: Point,
+    /// The dropped data.
+    pub data: TypedData,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct InputEvent {
     pub character: char,
@@ -238,6 +320,66 @@ pub enum InternalEvent<'a> {
     DumpTree {
         nodes: &'a mut Vec<DebugWidgetTreeNode>,
     },
+    /// Collects the input zones with a hit-test priority, with their bounds in window coordinates and their
+    /// priority, in traversal order.
+    ///
+    /// See `InputZone`.
+    CollectInputZones {
+        zones: &'a mut Vec<(Rect, i32, WidgetId)>,
+    },
+    /// Looks for the in-window menu bar, which receives the keyboard events used for menu activation.
+    ///
+    /// See `MenuBar`.
+    FindMenuBar {
+        menu_bar: &'a mut Option<WidgetId>,
+    },
+    /// Sent to a `Subtree` widget (in a `RouteEvent`) when its contents have been recomposed independently
+    /// of the rest of the UI.
+    SubtreeRecomposed,
+    /// Relays out the `WidgetPod`s that need a relayout, with the constraints of their last layout, and sets
+    /// `geometry_changed` if the geometry of one of them has changed.
+    ///
+    /// Sent by a `WidgetPod` to its contents when its own layout is still valid but some of its descendants need a
+    /// relayout (see `WidgetPod::layout`). Like other internal events, containers must forward it to all their
+    /// children.
+    RelayoutDirty {
+        /// Layout context of the widget pod that sent the event, used to report the path of the descendants in
+        /// layout diagnostics.
+        parent_ctx: &'a LayoutCtx,
+        geometry_changed: &'a mut bool,
+    },
+    /// Sent by `ThemeProvider` to its contents when the environment that it passes to them has changed without a
+    /// recomposition.
+    ///
+    /// Widgets that cache values resolved from the environment (e.g. in a `LayoutCache`) should discard them.
+    /// `WidgetPod`s invalidate their cached layout and repaint.
+    EnvironmentChanged,
+    /// Sent to the root widget during a resource collection pass: `WidgetPod`s mark their compositor layer or
+    /// surface as used.
+    ///
+    /// See `gc`.
+    MarkUsedResources,
+    /// Collects the nodes of the accessibility tree.
+    ///
+    /// `bounds` is the bounds of the nearest ancestor with a known layout, in window coordinates.
+    /// See `accessibility::AccessibilityTree`.
+    BuildAccessibilityTree {
+        nodes: &'a mut Vec<AccessibilityNode>,
+        bounds: Rect,
+    },
+    /// Collects the regions of the window that act as decorations, in window coordinates.
+    ///
+    /// See `FrameRegion`.
+    CollectFrameRegions {
+        regions: &'a mut Vec<(Rect, WindowPart)>,
+    },
+    /// Collects the widgets whose position depends on the scroll offset of the enclosing scroll area, with their
+    /// bounds in the coordinates of its contents. Sent by `ScrollArea` to its contents after a layout.
+    ///
+    /// See `ScrollLinked`.
+    CollectScrollLinked {
+        elements: &'a mut Vec<ScrollLinkedElement>,
+    },
 }
 
 /// Events.
@@ -248,16 +390,24 @@ pub enum Event<'a> {
     FocusGained,
     FocusLost,
     MenuCommand(usize),
+    /// The key sequence of a command was entered (see `command::CommandMap`).
+    ///
+    /// Sent to the focused widget; the widgets on the path can handle it with `widget::CommandScope`.
+    Command(CommandId),
     Pointer(PointerEvent),
     Wheel(WheelEvent),
     /// A keyboard event.
     Keyboard(KeyboardEvent),
     /// A composition event.
     Composition(CompositionEvent),
+    /// Data was dropped on the window.
+    ///
+    /// Like pointer events, it is delivered to the widgets under the drop position. See `widget::DropTarget`.
+    Drop(DropEvent),
     WindowEvent(winit::event::WindowEvent<'static>),
     WindowRedrawRequest,
     BuildFocusChain {
-        chain: &'a mut Vec<WidgetId>,
+        chain: &'a mut FocusChain,
     },
     Internal(InternalEvent<'a>),
 }
@@ -292,6 +442,24 @@ impl<'a> Event<'a> {
                 event_copy.position = transform.inverse().unwrap().transform_point(event_copy.position);
                 f(&mut Event::Pointer(event_copy))
             }
+            Event::Wheel(ref wheel_event) => {
+                let mut event_copy = *wheel_event;
+                event_copy.pointer.position = transform
+                    .inverse()
+                    .unwrap()
+                    .transform_point(event_copy.pointer.position);
+                f(&mut Event::Wheel(event_copy))
+            }
+            Event::Drop(ref mut drop_event) => {
+                // the data isn't copied: convert the position in place, and restore it afterwards
+                let parent_position = drop_event.position;
+                drop_event.position = transform.inverse().unwrap().transform_point(parent_position);
+                let result = f(self);
+                if let Event::Drop(ref mut drop_event) = *self {
+                    drop_event.position = parent_position;
+                }
+                result
+            }
             _ => f(self),
         }
     }
@@ -323,6 +491,9 @@ impl<'a> Event<'a> {
 pub struct PointerState {
     pub(crate) buttons: PointerButtons,
     pub(crate) position: Point,
+    /// Offset added to the positions reported by the OS while the pointer is captured, to keep them continuous
+    /// after a scale factor change (see `WindowState::rescale_pointers`).
+    pub(crate) grab_offset: Offset,
 }
 
 impl Default for PointerState {
@@ -330,6 +501,7 @@ impl Default for PointerState {
         PointerState {
             buttons: PointerButtons(0),
             position: Point::origin(),
+            grab_offset: Offset::zero(),
         }
     }
 }
@@ -340,27 +512,105 @@ pub struct InputState {
     /// Current state of keyboard modifiers.
     pub modifiers: Modifiers,
     /// Current state of pointers.
-    pub pointers: HashMap<DeviceId, PointerState>,
+    pub pointers: HashMap<PointerId, PointerState>,
+    /// Whether an IME composition session is in progress.
+    pub composing: bool,
 }
 
 impl InputState {
     pub fn synthetic_pointer_event(
         &self,
-        device_id: DeviceId,
+        pointer_id: PointerId,
         target: Option<WidgetId>,
         kind: PointerEventKind,
         button: Option<PointerButton>,
     ) -> Option<PointerEvent> {
-        self.pointers.get(&device_id).map(|state| PointerEvent {
+        self.pointers.get(&pointer_id).map(|state| PointerEvent {
             kind,
             target,
             position: state.position,
             window_position: state.position,
             modifiers: self.modifiers,
             buttons: state.buttons,
-            pointer_id: device_id,
+            pointer_id,
             button,
             repeat_count: 0,
+            timestamp: Instant::now(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wheel_event(delta_x: f64, delta_y: f64, delta_mode: WheelDeltaMode, modifiers: Modifiers) -> WheelEvent {
+        WheelEvent {
+            pointer: PointerEvent {
+                kind: PointerEventKind::PointerMove,
+                target: None,
+                position: Point::origin(),
+                window_position: Point::origin(),
+                modifiers,
+                buttons: PointerButtons(0),
+                pointer_id: PointerId::Mouse(unsafe { DeviceId::dummy() }),
+                button: None,
+                repeat_count: 0,
+                timestamp: Instant::now(),
+            },
+            delta_x,
+            delta_y,
+            delta_z: 0.0,
+            delta_mode,
+        }
+    }
+
+    #[test]
+    fn wheel_scroll_delta() {
+        let line = Size::new(8.0, 20.0);
+        let page = Size::new(400.0, 300.0);
+        let mut settings = ScrollSettings {
+            lines_per_notch: Some(3.0),
+            chars_per_notch: 2.0,
+            natural_scrolling: false,
+            shift_scrolls_horizontally: true,
+        };
+
+        // one notch up
+        let up = wheel_event(0.0, 1.0, WheelDeltaMode::Line, Modifiers::empty());
+        assert_eq!(up.scroll_delta(&settings, line, page), Offset::new(0.0, -60.0));
+        // tilt
+        let tilt = wheel_event(-1.0, 0.0, WheelDeltaMode::Line, Modifiers::empty());
+        assert_eq!(tilt.scroll_delta(&settings, line, page), Offset::new(16.0, 0.0));
+        // Shift+wheel
+        let shift = wheel_event(0.0, -1.0, WheelDeltaMode::Line, Modifiers::SHIFT);
+        assert_eq!(shift.scroll_delta(&settings, line, page), Offset::new(16.0, 0.0));
+        // pixel deltas aren't scaled
+        let pixels = wheel_event(0.0, -45.0, WheelDeltaMode::Pixel, Modifiers::empty());
+        assert_eq!(pixels.scroll_delta(&settings, line, page), Offset::new(0.0, 45.0));
+
+        settings.lines_per_notch = None;
+        settings.natural_scrolling = true;
+        assert_eq!(up.scroll_delta(&settings, line, page), Offset::new(0.0, 300.0));
+        settings.shift_scrolls_horizontally = false;
+        assert_eq!(shift.scroll_delta(&settings, line, page), Offset::new(0.0, -300.0));
+    }
+
+    #[test]
+    fn drop_local_coordinates() {
+        let mut event = Event::Drop(DropEvent {
+            position: Point::new(30.0, 40.0),
+            data: TypedData::text("a"),
+        });
+        let transform = Transform::translation(10.0, 20.0);
+        event.with_local_coordinates(&transform, |event| match event {
+            Event::Drop(drop_event) => assert_eq!(drop_event.position, Point::new(20.0, 20.0)),
+            _ => panic!("unexpected event"),
+        });
+        // the position is restored for the next widgets
+        match event {
+            Event::Drop(drop_event) => assert_eq!(drop_event.position, Point::new(30.0, 40.0)),
+            _ => panic!("unexpected event"),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Keyboard focus navigation.
+//!
+//! The focus chain lists the widgets that can be focused with the keyboard, in tree order. It is built after each
+//! recomposition by sending `Event::BuildFocusChain` to the contents of the window.
+//!
+//! The order in which `Tab` visits the widgets can be changed with tab indices (see `Widget::tab_order`), and
+//! navigation can be confined to a part of the UI with `widget::FocusScope`, which also supports moving the focus
+//! with the arrow keys.
+//!
+//! Building the chain also records which widgets are in the window, so that the focus can be moved elsewhere
+//! when the focused widget is removed by a recomposition (see `FocusChain::focus_replacement`).
+use crate::{Rect, WidgetId};
+use std::collections::HashMap;
+
+/// An entry of the focus chain.
+#[derive(Copy, Clone, Debug)]
+pub struct FocusChainEntry {
+    /// The focusable widget.
+    pub id: WidgetId,
+    /// Tab index of the widget (see `Widget::tab_order`).
+    pub tab_index: i32,
+    /// Innermost `FocusScope` that traps the focus containing the widget.
+    pub scope: Option<WidgetId>,
+    /// Bounds of the widget in window coordinates, if it has been laid out.
+    pub bounds: Option<Rect>,
+}
+
+/// Widgets that can receive the focus with the keyboard, in tree order.
+#[derive(Clone, Debug, Default)]
+pub struct FocusChain {
+    entries: Vec<FocusChainEntry>,
+    /// Focus-trapping scopes being visited while building the chain.
+    scopes: Vec<WidgetId>,
+    /// All widgets with an ID visited while building the chain, and whether they are focusable.
+    widgets: HashMap<WidgetId, bool>,
+    /// Widgets being visited while building the chain, from the root.
+    path: Vec<WidgetId>,
+    /// Widget whose ancestors are recorded while building the chain (see `track`).
+    tracked: Option<WidgetId>,
+    /// Ancestors of the tracked widget, from the root, if it was visited.
+    tracked_ancestors: Option<Vec<WidgetId>>,
+}
+
+/// Direction of arrow-key focus navigation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FocusDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl FocusChain {
+    pub fn new() -> FocusChain {
+        FocusChain::default()
+    }
+
+    /// Returns the entries of the chain, in tree order.
+    pub fn entries(&self) -> &[FocusChainEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.scopes.clear();
+        self.widgets.clear();
+        self.path.clear();
+        self.tracked = None;
+        self.tracked_ancestors = None;
+    }
+
+    /// Records the ancestors of the specified widget (usually the focused widget) when the chain is built.
+    ///
+    /// They are used to find where the focus should go if the widget is removed later.
+    pub(crate) fn track(&mut self, widget: Option<WidgetId>) {
+        self.tracked = widget;
+        self.tracked_ancestors = None;
+    }
+
+    /// Returns whether the widget was visited when the chain was built, i.e. whether it is in the window.
+    pub fn contains_widget(&self, id: WidgetId) -> bool {
+        self.widgets.contains_key(&id)
+    }
+
+    /// Returns whether the widget was visited when the chain was built, and is focusable.
+    pub fn is_focusable(&self, id: WidgetId) -> bool {
+        self.widgets.get(&id).copied().unwrap_or(false)
+    }
+
+    /// Called when visiting a widget while building the chain, before its descendants.
+    ///
+    /// Must be followed by a call to `exit_widget` if it returns true.
+    pub(crate) fn enter_widget(&mut self, id: WidgetId, focusable: bool) -> bool {
+        *self.widgets.entry(id).or_default() |= focusable;
+        if self.tracked == Some(id) && self.tracked_ancestors.is_none() {
+            self.tracked_ancestors = Some(self.path.clone());
+        }
+        // wrappers share the ID of the widget they wrap
+        if self.path.last() == Some(&id) {
+            false
+        } else {
+            self.path.push(id);
+            true
+        }
+    }
+
+    pub(crate) fn exit_widget(&mut self) {
+        self.path.pop();
+    }
+
+    /// Returns the widget that should receive the focus in place of `lost`, the focused widget, which is not in the
+    /// window anymore.
+    ///
+    /// `previous` is the chain built before `lost` was removed, while it was tracked (see `track`).
+    /// The focus goes to the innermost ancestor of `lost` that is still in the window and focusable. Otherwise, it goes
+    /// to the widget that followed `lost` in the previous chain, or else the one that preceded it, preferably in the
+    /// same focus-trapping scope. Returns `None` if no widget can receive the focus.
+    pub fn focus_replacement(&self, lost: WidgetId, previous: &FocusChain) -> Option<WidgetId> {
+        if previous.tracked == Some(lost) {
+            if let Some(ref ancestors) = previous.tracked_ancestors {
+                if let Some(&ancestor) = ancestors.iter().rev().find(|&&id| self.is_focusable(id)) {
+                    return Some(ancestor);
+                }
+            }
+        }
+
+        let position = previous.entries.iter().position(|entry| entry.id == lost)?;
+        let scope = previous.entries[position].scope;
+        let following = previous.entries[position + 1..].iter();
+        let preceding = previous.entries[..position].iter().rev();
+        let candidates = || following.clone().chain(preceding.clone());
+        let in_chain = |entry: &&FocusChainEntry| self.entries.iter().any(|e| e.id == entry.id);
+        candidates()
+            .filter(|entry| entry.scope == scope)
+            .find(in_chain)
+            .or_else(|| candidates().find(in_chain))
+            .map(|entry| entry.id)
+    }
+
+    /// Returns the entry at the specified position.
+    pub fn get(&self, index: usize) -> Option<&FocusChainEntry> {
+        self.entries.get(index)
+    }
+
+    /// Returns the ID of the last widget added to the chain.
+    pub fn last_id(&self) -> Option<WidgetId> {
+        self.entries.last().map(|entry| entry.id)
+    }
+
+    /// Adds a widget at the end of the chain, in the current scope.
+    pub(crate) fn push(&mut self, id: WidgetId, tab_index: i32) {
+        self.entries.push(FocusChainEntry {
+            id,
+            tab_index,
+            scope: self.scopes.last().copied(),
+            bounds: None,
+        });
+    }
+
+    /// Removes the entry at the specified position.
+    pub(crate) fn remove(&mut self, index: usize) {
+        self.entries.remove(index);
+    }
+
+    /// Sets the bounds of the entry at the specified position, if it's the entry of the specified widget and its
+    /// bounds are not known yet.
+    pub(crate) fn set_bounds(&mut self, index: usize, id: WidgetId, bounds: Rect) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            if entry.id == id && entry.bounds.is_none() {
+                entry.bounds = Some(bounds);
+            }
+        }
+    }
+
+    /// Widgets added until the matching call to `exit_scope` can't be left with `Tab`.
+    pub(crate) fn enter_scope(&mut self, scope: WidgetId) {
+        self.scopes.push(scope);
+    }
+
+    pub(crate) fn exit_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Returns the widgets of a scope in tab order.
+    ///
+    /// Widgets with a positive tab index come first, in increasing order, then the widgets with a tab index of zero
+    /// in tree order.
+    fn tab_stops(&self, scope: Option<WidgetId>) -> Vec<WidgetId> {
+        let mut members: Vec<_> = self.entries.iter().filter(|entry| entry.scope == scope).collect();
+        // stable sort: ties stay in tree order
+        members.sort_by_key(|entry| if entry.tab_index > 0 { entry.tab_index } else { i32::MAX });
+        members.into_iter().map(|entry| entry.id).collect()
+    }
+
+    /// Returns the widget that receives the focus when pressing `Tab` (or `Shift+Tab` if `backwards` is true)
+    /// while `from` has the focus.
+    ///
+    /// Navigation wraps around, and stays within the focus-trapping scope of `from`. Returns `None` if `from` is not
+    /// in the chain.
+    pub fn next(&self, from: WidgetId, backwards: bool) -> Option<WidgetId> {
+        let scope = self.entries.iter().find(|entry| entry.id == from)?.scope;
+        let order = self.tab_stops(scope);
+        let pos = order.iter().position(|id| *id == from)?;
+        let len = order.len();
+        let next = if backwards {
+            (pos + len - 1) % len
+        } else {
+            (pos + 1) % len
+        };
+        Some(order[next])
+    }
+
+    /// Returns the closest widget in the specified direction from `from`, using the bounds of the widgets.
+    ///
+    /// Widgets are scored by their distance along the direction, with a penalty for the distance across it,
+    /// so that widgets in the same row (or column) are preferred.
+    pub fn nearest_in_direction(&self, from: WidgetId, direction: FocusDirection) -> Option<WidgetId> {
+        let origin = self.entries.iter().find(|entry| entry.id == from)?.bounds?;
+        let mut best: Option<(f64, WidgetId)> = None;
+        for entry in self.entries.iter() {
+            let bounds = match entry.bounds {
+                Some(bounds) if entry.id != from => bounds,
+                _ => continue,
+            };
+            // distance along the direction, between the facing edges, and across the direction, between the centers
+            let (along, across) = match direction {
+                FocusDirection::Left => (origin.min_x() - bounds.max_x(), bounds.center().y - origin.center().y),
+                FocusDirection::Right => (bounds.min_x() - origin.max_x(), bounds.center().y - origin.center().y),
+                FocusDirection::Up => (origin.min_y() - bounds.max_y(), bounds.center().x - origin.center().x),
+                FocusDirection::Down => (bounds.min_y() - origin.max_y(), bounds.center().x - origin.center().x),
+            };
+            // allow some overlap, but the widget must be past the center of the origin
+            let past_center = match direction {
+                FocusDirection::Left => bounds.center().x < origin.center().x,
+                FocusDirection::Right => bounds.center().x > origin.center().x,
+                FocusDirection::Up => bounds.center().y < origin.center().y,
+                FocusDirection::Down => bounds.center().y > origin.center().y,
+            };
+            if !past_center {
+                continue;
+            }
+            let score = along.max(0.0) + 2.0 * across.abs();
+            if best.map_or(true, |(best_score, _)| score < best_score) {
+                best = Some((score, entry.id));
+            }
+        }
+        best.map(|(_, id)| id)
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{Length, UnitExt};
-use kyute_shell::text::{FontStyle, FontWeight};
+use kyute_shell::{
+    dialogs::FontSelection,
+    text::{FontStyle, FontWeight},
+};
 
 #[derive(Clone, Debug)]
 pub struct Font {
@@ -41,4 +44,15 @@ impl Font {
     }
 }
 
+impl From<FontSelection> for Font {
+    fn from(font: FontSelection) -> Self {
+        Font {
+            family: font.family.name().to_string(),
+            weight: font.weight,
+            size: font.size.pt(),
+            style: font.style,
+        }
+    }
+}
+
 impl_env_value!(Font);
@@ -0,0 +1,286 @@
+//! Frame debugger: captures the paint commands of a frame.
+//!
+//! A capture records, for each layer painted during the frame, the list of paint commands in the order they were
+//! issued. A paint command is the drawing done by a widget until the next widget starts painting, and is attributed
+//! to that widget. Each command keeps a snapshot of the area of the layer covered by the widget once it has been
+//! painted, so that the frame can be replayed step by step (see `widget::FrameDebugger`).
+//!
+//! Request a capture with `capture_next_frame`: all windows are then repainted entirely, and the capture is passed to
+//! the callback once they are done.
+use crate::{application, widget::prelude::*, PointI, RectI, SizeI};
+use serde_json::json;
+use skia_safe as sk;
+use std::{cell::RefCell, fmt, fs, io, path::Path};
+
+/// A paint command: the drawing done by a widget before the next widget starts painting.
+#[derive(Clone)]
+pub struct PaintCommand {
+    /// ID of the widget that issued the command.
+    pub widget_id: Option<WidgetId>,
+    /// Debug name of the widget that issued the command.
+    pub widget_name: String,
+    /// Nesting depth of the widget in the layer.
+    pub depth: usize,
+    /// Bounds of the widget, in layer pixels.
+    pub bounds: RectI,
+    /// Clip rectangle in effect when the widget started painting, in layer pixels.
+    pub clip: Option<RectI>,
+    /// Contents of the layer inside `bounds` after the command, and their position in the layer.
+    pub(crate) snapshot: Option<(PointI, sk::Image)>,
+}
+
+impl fmt::Debug for PaintCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PaintCommand")
+            .field("widget_id", &self.widget_id)
+            .field("widget_name", &self.widget_name)
+            .field("depth", &self.depth)
+            .field("bounds", &self.bounds)
+            .field("clip", &self.clip)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Paint commands of a layer.
+#[derive(Clone, Debug)]
+pub struct LayerCapture {
+    /// Size of the layer in pixels.
+    pub size: SizeI,
+    /// Scale factor of the layer (pixels per DIP).
+    pub scale_factor: f64,
+    /// Paint commands, in the order they were issued.
+    pub commands: Vec<PaintCommand>,
+}
+
+/// Paint commands of all the layers painted during a frame.
+#[derive(Clone, Debug, Default)]
+pub struct FrameCapture {
+    /// The layers, in the order they were painted.
+    pub layers: Vec<LayerCapture>,
+}
+
+fn rect_to_json(rect: &RectI) -> serde_json::Value {
+    json!({
+        "x": rect.origin.x,
+        "y": rect.origin.y,
+        "width": rect.size.width,
+        "height": rect.size.height,
+    })
+}
+
+impl FrameCapture {
+    /// Returns the display list of the frame as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        let layers: Vec<_> = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let commands: Vec<_> = layer
+                    .commands
+                    .iter()
+                    .map(|command| {
+                        json!({
+                            "widget_id": command.widget_id.map(|id| format!("{:?}", id)),
+                            "widget": command.widget_name,
+                            "depth": command.depth,
+                            "bounds": rect_to_json(&command.bounds),
+                            "clip": command.clip.as_ref().map(rect_to_json),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "width": layer.size.width,
+                    "height": layer.size.height,
+                    "scale_factor": layer.scale_factor,
+                    "commands": commands,
+                })
+            })
+            .collect();
+        json!({ "layers": layers })
+    }
+
+    /// Writes the display list of the frame to a JSON file.
+    pub fn save_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json())?;
+        fs::write(path, json)
+    }
+}
+
+/// Layer being captured.
+struct LayerState {
+    layer: LayerCapture,
+    depth: usize,
+}
+
+#[derive(Default)]
+struct CaptureState {
+    /// Callback of the pending capture request.
+    request: Option<Box<dyn FnOnce(FrameCapture)>>,
+    /// The capture in progress.
+    capture: Option<FrameCapture>,
+    /// Layers being painted (child layers can be painted while painting their parent).
+    layer_stack: Vec<LayerState>,
+    /// Widget owning the next layer to be painted.
+    layer_owner: Option<(Option<WidgetId>, String)>,
+}
+
+thread_local! {
+    static CAPTURE_STATE: RefCell<CaptureState> = RefCell::new(CaptureState::default());
+}
+
+/// Captures the paint commands of the next frame. `f` is called with the capture once all windows have been repainted.
+///
+/// Replaces any pending request.
+pub fn capture_next_frame(f: impl FnOnce(FrameCapture) + 'static) {
+    CAPTURE_STATE.with(|state| state.borrow_mut().request = Some(Box::new(f)));
+    // make sure the event loop wakes up to process the request
+    if let Some(proxy) = application::proxy() {
+        proxy.request_recompose();
+    }
+}
+
+/// Returns whether a capture has been requested.
+pub(crate) fn capture_requested() -> bool {
+    CAPTURE_STATE.with(|state| state.borrow().request.is_some())
+}
+
+/// Starts capturing paint commands.
+pub(crate) fn begin_capture() {
+    CAPTURE_STATE.with(|state| state.borrow_mut().capture = Some(FrameCapture::default()));
+}
+
+/// Stops capturing and calls the callback of the request with the capture.
+pub(crate) fn end_capture() {
+    let (request, capture) = CAPTURE_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.layer_stack.clear();
+        (state.request.take(), state.capture.take())
+    });
+    if let (Some(request), Some(capture)) = (request, capture) {
+        trace!("frame captured: {} layer(s)", capture.layers.len());
+        request(capture);
+    }
+}
+
+/// Returns whether paint commands are being captured.
+///
+/// While capturing, layers must be repainted even if their contents are still valid.
+pub(crate) fn is_capturing() -> bool {
+    CAPTURE_STATE.with(|state| state.borrow().capture.is_some())
+}
+
+/// Sets the widget whose contents are painted on the next layer.
+pub(crate) fn set_layer_owner(widget_id: Option<WidgetId>, widget_name: &str) {
+    CAPTURE_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.capture.is_some() {
+            state.layer_owner = Some((widget_id, widget_name.to_string()));
+        }
+    })
+}
+
+/// Called before painting a layer.
+pub(crate) fn begin_layer(size: SizeI, scale_factor: f64) {
+    CAPTURE_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.capture.is_none() {
+            return;
+        }
+        let mut layer_state = LayerState {
+            layer: LayerCapture {
+                size,
+                scale_factor,
+                commands: vec![],
+            },
+            depth: 0,
+        };
+        // the widget owning the layer paints first
+        if let Some((widget_id, widget_name)) = state.layer_owner.take() {
+            layer_state.layer.commands.push(PaintCommand {
+                widget_id,
+                widget_name,
+                depth: 0,
+                bounds: RectI::new(Default::default(), size),
+                clip: None,
+                snapshot: None,
+            });
+            layer_state.depth = 1;
+        }
+        state.layer_stack.push(layer_state);
+    })
+}
+
+/// Takes the snapshot of the last command of the current layer.
+fn snapshot_last_command(layer_state: &mut LayerState, surface: &mut sk::Surface) {
+    if let Some(command) = layer_state.layer.commands.last_mut() {
+        let surface_bounds = sk::IRect::from_wh(surface.width(), surface.height());
+        let bounds = sk::IRect::from_xywh(
+            command.bounds.origin.x,
+            command.bounds.origin.y,
+            command.bounds.size.width,
+            command.bounds.size.height,
+        );
+        if let Some(bounds) = sk::IRect::intersect(&bounds, &surface_bounds) {
+            command.snapshot = surface
+                .image_snapshot_with_bounds(bounds)
+                .map(|image| (PointI::new(bounds.left, bounds.top), image));
+        }
+    }
+}
+
+/// Called after painting a layer.
+pub(crate) fn end_layer(surface: &mut sk::Surface) {
+    CAPTURE_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(mut layer_state) = state.layer_stack.pop() {
+            snapshot_last_command(&mut layer_state, surface);
+            if let Some(ref mut capture) = state.capture {
+                capture.layers.push(layer_state.layer);
+            }
+        }
+    })
+}
+
+/// Called when a widget starts painting, with the transform and clip of the widget applied to the canvas.
+pub(crate) fn begin_paint(ctx: &mut PaintCtx, widget_id: Option<WidgetId>, widget_name: &str, bounds: Rect) {
+    CAPTURE_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let layer_state = match state.layer_stack.last_mut() {
+            Some(layer_state) => layer_state,
+            None => return,
+        };
+        // the previous command ends here
+        snapshot_last_command(layer_state, ctx.surface);
+
+        let canvas = ctx.surface.canvas();
+        let (device_bounds, _) = canvas.local_to_device_as_3x3().map_rect(sk::Rect::new(
+            bounds.min_x() as f32,
+            bounds.min_y() as f32,
+            bounds.max_x() as f32,
+            bounds.max_y() as f32,
+        ));
+        let device_bounds = device_bounds.round_out();
+        let clip = canvas.device_clip_bounds();
+        layer_state.layer.commands.push(PaintCommand {
+            widget_id,
+            widget_name: widget_name.to_string(),
+            depth: layer_state.depth,
+            bounds: RectI::new(
+                (device_bounds.left, device_bounds.top).into(),
+                (device_bounds.width(), device_bounds.height()).into(),
+            ),
+            clip: clip.map(|clip| RectI::new((clip.left, clip.top).into(), (clip.width(), clip.height()).into())),
+            snapshot: None,
+        });
+        layer_state.depth += 1;
+    })
+}
+
+/// Called when a widget has finished painting.
+pub(crate) fn end_paint() {
+    CAPTURE_STATE.with(|state| {
+        if let Some(layer_state) = state.borrow_mut().layer_stack.last_mut() {
+            layer_state.depth = layer_state.depth.saturating_sub(1);
+        }
+    })
+}
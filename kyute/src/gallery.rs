@@ -0,0 +1,199 @@
+//! Contact sheets of widgets in all their states, to preview themes.
+//!
+//! `Gallery` renders widgets (by default, the builtin widgets) in each `WidgetState` under a theme, and assembles the
+//! renderings in a single image with one row per widget and one column per state. Theme authors can check the effect
+//! of a change at a glance; applications can compare the sheets in CI with `SnapshotTester::check_image` to catch
+//! unwanted changes of their theme.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use kyute::{gallery::Gallery, theme::Theme};
+//!
+//! Gallery::builtin(Theme::Light).write_png("gallery-light.png").unwrap();
+//! ```
+use crate::{
+    application::base_environment,
+    drawing::ToSkia,
+    snapshot::{write_png, OffscreenRenderer, SnapshotError},
+    style::WidgetState,
+    theme,
+    widget::{Button, CheckboxField, DisplayFormatter, DropDown, Label, Stepper, TextEdit},
+    Environment, Size, Widget,
+};
+use skia_safe as sk;
+use std::{path::Path, sync::Arc};
+
+/// Columns of the contact sheet.
+///
+/// `ACTIVE` is shown with `HOVER` since a widget can't be pressed without the pointer being over it.
+const STATES: [(&str, WidgetState); 5] = [
+    ("Default", WidgetState::DEFAULT),
+    ("Hover", WidgetState::HOVER),
+    ("Focus", WidgetState::FOCUS),
+    (
+        "Active",
+        WidgetState::from_bits_truncate(WidgetState::ACTIVE.bits() | WidgetState::HOVER.bits()),
+    ),
+    ("Disabled", WidgetState::DISABLED),
+];
+
+/// Width of the column of widget names, in DIPs.
+const NAME_COLUMN_WIDTH: f64 = 120.0;
+/// Height of the row of state names, in DIPs.
+const HEADER_HEIGHT: f64 = 24.0;
+/// Space around each rendering, in DIPs.
+const CELL_PADDING: f64 = 8.0;
+
+struct GalleryItem {
+    name: String,
+    size: Size,
+    ui: Box<dyn Fn() -> Arc<dyn Widget>>,
+}
+
+/// Renders widgets in all their states to a contact sheet.
+///
+/// The states are forced on the widgets with `theme::FORCED_WIDGET_STATE`: they affect the widgets that compute
+/// their style from their state (e.g. with a `StyledBox`).
+pub struct Gallery {
+    env: Environment,
+    scale_factor: f64,
+    items: Vec<GalleryItem>,
+}
+
+impl Gallery {
+    /// Creates an empty gallery, whose widgets are rendered in the specified theme environment (e.g.
+    /// `theme::light_theme()`, or the environment of a custom theme).
+    pub fn new(theme: Environment) -> Gallery {
+        Gallery {
+            env: base_environment().merged(theme),
+            scale_factor: 1.0,
+            items: vec![],
+        }
+    }
+
+    /// Creates a gallery of the builtin widgets, in a builtin theme.
+    pub fn builtin(theme: theme::Theme) -> Gallery {
+        let env = match theme {
+            theme::Theme::Dark => theme::dark_theme(),
+            theme::Theme::Light => theme::light_theme(),
+        };
+        Gallery::new(env).builtin_widgets()
+    }
+
+    /// Sets the scale factor of the contact sheet.
+    pub fn scale_factor(mut self, scale_factor: f64) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
+    /// Adds a widget to the gallery, rendered in a box of the specified size (in DIPs).
+    ///
+    /// `ui` is composed once for each state.
+    pub fn widget<W: Widget + 'static>(
+        mut self,
+        name: impl Into<String>,
+        size: Size,
+        ui: impl Fn() -> W + 'static,
+    ) -> Self {
+        self.items.push(GalleryItem {
+            name: name.into(),
+            size,
+            ui: Box::new(move || Arc::new(ui())),
+        });
+        self
+    }
+
+    /// Adds the builtin widgets to the gallery.
+    pub fn builtin_widgets(self) -> Self {
+        self.widget("Label", Size::new(120.0, 24.0), || Label::new("Label"))
+            .widget("Button", Size::new(120.0, 40.0), || Button::new("Button"))
+            .widget("Checkbox", Size::new(120.0, 24.0), || {
+                CheckboxField::new("Checkbox", true)
+            })
+            .widget("Text edit", Size::new(160.0, 32.0), || TextEdit::new("Text"))
+            .widget("Drop-down", Size::new(160.0, 32.0), || {
+                DropDown::with_selected_index(0, vec!["First".to_string(), "Second".to_string()], DisplayFormatter)
+            })
+            .widget("Stepper", Size::new(60.0, 40.0), || Stepper::new(1.0, 0.0, 10.0, 1.0))
+    }
+
+    /// Returns the names of the widgets in the gallery, in the order of the rows of the contact sheet.
+    pub fn widget_names(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().map(|item| item.name.as_str())
+    }
+
+    /// Renders the contact sheet, on the window background color of the theme.
+    pub fn render(&self) -> sk::Image {
+        let mut renderer = OffscreenRenderer::new();
+        let scale_factor = self.scale_factor;
+
+        let column_width = self.items.iter().map(|item| item.size.width).fold(0.0, f64::max) + 2.0 * CELL_PADDING;
+        let row_heights: Vec<f64> = self
+            .items
+            .iter()
+            .map(|item| item.size.height + 2.0 * CELL_PADDING)
+            .collect();
+        let sheet_size = Size::new(
+            NAME_COLUMN_WIDTH + column_width * STATES.len() as f64,
+            HEADER_HEIGHT + row_heights.iter().sum::<f64>(),
+        );
+
+        let mut sheet = sk::Surface::new_raster_n32_premul((
+            ((sheet_size.width * scale_factor).ceil() as i32).max(1),
+            ((sheet_size.height * scale_factor).ceil() as i32).max(1),
+        ))
+        .expect("failed to create skia surface");
+        let background = self
+            .env
+            .get(&theme::WINDOW_BACKGROUND_COLOR)
+            .unwrap_or_else(|| theme::tokens(&self.env).colors.window_background);
+        sheet.canvas().clear(background.to_skia());
+
+        let mut draw = |image: &sk::Image, x: f64, y: f64| {
+            sheet.canvas().draw_image(
+                image,
+                ((x * scale_factor).round() as f32, (y * scale_factor).round() as f32),
+                None,
+            );
+        };
+
+        // state names
+        for (i, (state_name, _)) in STATES.iter().enumerate() {
+            let image = renderer.render(&self.env, Size::new(column_width, HEADER_HEIGHT), scale_factor, || {
+                Label::new(*state_name)
+            });
+            draw(&image, NAME_COLUMN_WIDTH + column_width * i as f64, 0.0);
+        }
+
+        let mut y = HEADER_HEIGHT;
+        for (item, row_height) in self.items.iter().zip(row_heights) {
+            let image = renderer.render(
+                &self.env,
+                Size::new(NAME_COLUMN_WIDTH, row_height),
+                scale_factor,
+                || Label::new(item.name.clone()),
+            );
+            draw(&image, 0.0, y);
+
+            for (i, (_, state)) in STATES.iter().enumerate() {
+                let mut env = self.env.clone();
+                env.set(&theme::FORCED_WIDGET_STATE, *state);
+                let image = renderer.render(&env, item.size, scale_factor, || (item.ui)());
+                draw(
+                    &image,
+                    NAME_COLUMN_WIDTH + column_width * i as f64 + CELL_PADDING,
+                    y + CELL_PADDING,
+                );
+            }
+            y += row_height;
+        }
+
+        sheet.image_snapshot()
+    }
+
+    /// Renders the contact sheet and writes it to a PNG file.
+    pub fn write_png(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        write_png(&self.render(), path.as_ref())
+    }
+}
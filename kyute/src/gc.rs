@@ -0,0 +1,213 @@
+//! Idle-time release of compositor and GPU resources.
+//!
+//! The compositor layers and surfaces of widget pods are kept in the cache (see `WidgetPod::with_native_layer`) along
+//! with their contents (swap chains, skia surfaces). When a widget is removed from the UI but its cache entry survives
+//! (e.g. the contents of a closed window or tab, or an item evicted from a virtualized list), these resources linger.
+//! So do the GPU textures of images that aren't drawn anymore, and the resources cached by skia (glyph atlases,
+//! scratch textures).
+//!
+//! When there's time left at the end of a frame, a collection pass marks the resources of the widget pods reachable
+//! from the root widget, and releases those that haven't been reachable for a number of frames (see
+//! `set_unused_frames`). A released layer or surface is allocated again the next time its widget pod is painted.
+//!
+//! Resources age with frames: in an application that doesn't receive events, nothing is released.
+//! The results of the collections are reported by `diagnostics::gc_stats`.
+use crate::{diagnostics, drawing::IMAGE_CACHE, window, Environment};
+use kyute_shell::animation::Layer;
+use std::{
+    cell::{Cell, RefCell},
+    ops::Deref,
+    rc::{Rc, Weak},
+    time::{Duration, Instant},
+};
+
+/// Default number of frames after which resources that aren't used are released.
+const DEFAULT_UNUSED_FRAMES: u64 = 300;
+/// Number of frames between two collection passes.
+const COLLECTION_INTERVAL: u64 = 60;
+/// Resources cached by skia are released if they haven't been used for this long.
+const SKIA_RESOURCE_MAX_AGE: Duration = Duration::from_secs(5);
+
+thread_local! {
+    static FRAME: Cell<u64> = Cell::new(0);
+    static LAST_COLLECTION: Cell<u64> = Cell::new(0);
+    static UNUSED_FRAMES: Cell<u64> = Cell::new(DEFAULT_UNUSED_FRAMES);
+    static TRACKED: RefCell<Vec<Weak<dyn TrackedResource>>> = RefCell::new(Vec::new());
+}
+
+/// Sets the number of frames after which layers, surfaces and image textures that aren't used are released.
+pub fn set_unused_frames(frames: u64) {
+    UNUSED_FRAMES.with(|f| f.set(frames.max(1)))
+}
+
+/// Returns the number of frames after which resources that aren't used are released.
+///
+/// See `set_unused_frames`.
+pub fn unused_frames() -> u64 {
+    UNUSED_FRAMES.with(|f| f.get())
+}
+
+/// Returns the number of the current frame.
+pub(crate) fn current_frame() -> u64 {
+    FRAME.with(|f| f.get())
+}
+
+/// Returns the frame before which a resource must have been last used to be released.
+fn release_threshold() -> u64 {
+    current_frame().saturating_sub(unused_frames())
+}
+
+/// A resource that can be released by the collector, and allocated again on demand.
+pub(crate) trait Releasable {
+    /// Releases the resource. Returns whether there was something to release.
+    fn release(&self) -> bool;
+}
+
+impl Releasable for Layer {
+    fn release(&self) -> bool {
+        self.release_contents()
+    }
+}
+
+trait TrackedResource {
+    fn last_used(&self) -> u64;
+    fn release(&self) -> bool;
+    fn is_layer(&self) -> bool;
+}
+
+/// A resource tracked by the collector.
+///
+/// The owner marks the resource as used during collections (see `InternalEvent::MarkUsedResources`), and checks
+/// whether it has been released before using it (`take_released`).
+pub(crate) struct Tracked<T> {
+    resource: T,
+    last_used: Cell<u64>,
+    released: Cell<bool>,
+    is_layer: bool,
+}
+
+impl<T: Releasable + 'static> Tracked<T> {
+    fn new(resource: T, is_layer: bool) -> Rc<Tracked<T>> {
+        let tracked = Rc::new(Tracked {
+            resource,
+            last_used: Cell::new(current_frame()),
+            released: Cell::new(false),
+            is_layer,
+        });
+        let weak = Rc::downgrade(&tracked) as Weak<dyn TrackedResource>;
+        TRACKED.with(|t| t.borrow_mut().push(weak));
+        tracked
+    }
+
+    /// Tracks a surface.
+    pub(crate) fn surface(surface: T) -> Rc<Tracked<T>> {
+        Tracked::new(surface, false)
+    }
+
+    /// Marks the resource as used in the current frame.
+    pub(crate) fn mark_used(&self) {
+        self.last_used.set(current_frame());
+    }
+
+    /// Returns whether the resource has been released since the last call, in which case it must be
+    /// reinitialized (e.g. repainted).
+    pub(crate) fn take_released(&self) -> bool {
+        self.released.replace(false)
+    }
+}
+
+impl Tracked<Layer> {
+    /// Creates a compositor layer tracked by the collector.
+    pub(crate) fn layer() -> Rc<Tracked<Layer>> {
+        Tracked::new(Layer::new(), true)
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<T: Releasable> TrackedResource for Tracked<T> {
+    fn last_used(&self) -> u64 {
+        self.last_used.get()
+    }
+
+    fn release(&self) -> bool {
+        let released = self.resource.release();
+        if released {
+            self.released.set(true);
+        }
+        released
+    }
+
+    fn is_layer(&self) -> bool {
+        self.is_layer
+    }
+}
+
+/// Results of a collection pass.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Collection {
+    pub(crate) layers_released: usize,
+    pub(crate) surfaces_released: usize,
+    pub(crate) textures_released: usize,
+    pub(crate) gpu_resource_bytes: usize,
+    pub(crate) gpu_resource_count: usize,
+}
+
+/// Ends the current frame, and returns whether a collection pass is due and can run before `deadline`.
+///
+/// If so, the caller should send `InternalEvent::MarkUsedResources` to the root widget, then call `collect`.
+pub(crate) fn end_frame(deadline: Instant) -> bool {
+    let frame = FRAME.with(|f| {
+        let frame = f.get() + 1;
+        f.set(frame);
+        frame
+    });
+    let last_collection = LAST_COLLECTION.with(|c| c.get());
+    if frame - last_collection < COLLECTION_INTERVAL || Instant::now() >= deadline {
+        return false;
+    }
+    LAST_COLLECTION.with(|c| c.set(frame));
+    true
+}
+
+/// Releases the resources that haven't been marked as used in the last frames.
+pub(crate) fn collect(env: &Environment) {
+    let _span = trace_span!("Collect resources").entered();
+
+    let threshold = release_threshold();
+    let mut collection = Collection::default();
+    TRACKED.with(|tracked| {
+        tracked.borrow_mut().retain(|resource| {
+            if let Some(resource) = resource.upgrade() {
+                if resource.last_used() < threshold && resource.release() {
+                    if resource.is_layer() {
+                        collection.layers_released += 1;
+                    } else {
+                        collection.surfaces_released += 1;
+                    }
+                }
+                true
+            } else {
+                // dropped along with its cache entry
+                false
+            }
+        })
+    });
+
+    if let Some(image_cache) = env.get(&IMAGE_CACHE) {
+        collection.textures_released = image_cache.release_unused_textures(threshold);
+    }
+
+    let (bytes, count) = window::purge_unused_gpu_resources(SKIA_RESOURCE_MAX_AGE);
+    collection.gpu_resource_bytes = bytes;
+    collection.gpu_resource_count = count;
+
+    trace!("resource collection: {:?}", collection);
+    diagnostics::record_collection(&collection);
+}
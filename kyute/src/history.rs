@@ -0,0 +1,348 @@
+//! Undo and redo.
+//!
+//! Edits are recorded as `UndoCommand`s on an `UndoStack`, after they have been applied. The stack reverts them
+//! one after the other, and re-applies the reverted ones until a new edit is recorded. Consecutive edits of the same
+//! kind (e.g. the characters typed in a text editor) can be merged into a single command (see `UndoCommand::merge`).
+//!
+//! Text editors keep their own stack: they handle the `UNDO` and `REDO` commands when focused, as long as they have
+//! something to undo or redo. Otherwise, the commands go to the enclosing `CommandScope`s, and then to their global
+//! handler (see `command::Command`), where applications can undo and redo on the stack of their documents:
+//!
+//! ```no_run
+//! use kyute::{cache, command::Command, composable, history::{self, UndoStack}};
+//! use parking_lot::Mutex;
+//! use std::sync::Arc;
+//!
+//! #[composable]
+//! fn document_history() -> Arc<Mutex<UndoStack>> {
+//!     let stack = cache::once(|| Arc::new(Mutex::new(UndoStack::new())));
+//!     if Command::new(history::UNDO, "Undo").triggered() {
+//!         stack.lock().undo();
+//!     }
+//!     if Command::new(history::REDO, "Redo").triggered() {
+//!         stack.lock().redo();
+//!     }
+//!     stack
+//! }
+//! ```
+use crate::{
+    command::{CommandId, CommandMap},
+    event::Modifiers,
+};
+use kyute_shell::{Shortcut, ShortcutKey};
+use std::{any::Any, collections::VecDeque};
+
+/// Reverts the last edit (`Ctrl+Z`, or `Cmd+Z` on macOS).
+pub const UNDO: CommandId = CommandId::new("edit.undo");
+/// Re-applies the last reverted edit (`Ctrl+Shift+Z` or `Ctrl+Y`, or `Cmd+Shift+Z` on macOS).
+pub const REDO: CommandId = CommandId::new("edit.redo");
+
+/// Default maximum number of commands kept on an `UndoStack`.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// Registers the `UNDO` and `REDO` commands, bound to the shortcuts of the platform.
+pub(crate) fn register_commands(map: &mut CommandMap) {
+    let primary = if cfg!(target_os = "macos") {
+        Modifiers::META
+    } else {
+        Modifiers::CONTROL
+    };
+    map.register(UNDO, "Undo");
+    map.register(REDO, "Redo");
+    map.bind(&UNDO, Shortcut::new(primary, ShortcutKey::Character('Z')).into())
+        .unwrap();
+    map.bind(
+        &REDO,
+        Shortcut::new(primary | Modifiers::SHIFT, ShortcutKey::Character('Z')).into(),
+    )
+    .unwrap();
+    if !cfg!(target_os = "macos") {
+        map.bind(
+            &REDO,
+            Shortcut::new(Modifiers::CONTROL, ShortcutKey::Character('Y')).into(),
+        )
+        .unwrap();
+    }
+}
+
+/// A reversible edit.
+///
+/// Commands are recorded on an `UndoStack` once the edit has been applied. `Ctx` is passed to `undo` and `redo` by
+/// the owner of the stack, to give access to the edited data (see `UndoStack::undo_with`).
+pub trait UndoCommand<Ctx = ()>: 'static {
+    /// Returns the name of the edit, displayed in menus (e.g. "Undo Typing").
+    fn label(&self) -> String {
+        String::new()
+    }
+
+    /// Reverts the edit.
+    fn undo(&mut self, ctx: &mut Ctx);
+
+    /// Applies the edit again, after it was reverted.
+    fn redo(&mut self, ctx: &mut Ctx);
+
+    /// Merges a command recorded just after this one into this one, so that both are undone at once.
+    ///
+    /// Returns `false` if the commands can't be merged: `next` is then recorded separately.
+    fn merge(&mut self, _next: &Self) -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+}
+
+/// Gives access to the concrete type of commands on the stack, to merge them.
+trait AnyUndoCommand<Ctx>: UndoCommand<Ctx> {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<Ctx, C: UndoCommand<Ctx>> AnyUndoCommand<Ctx> for C {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// An `UndoCommand` made of closures, for applications that don't need a dedicated type.
+pub struct FnCommand<Undo, Redo> {
+    label: String,
+    undo: Undo,
+    redo: Redo,
+}
+
+impl<Undo, Redo> FnCommand<Undo, Redo>
+where
+    Undo: FnMut() + 'static,
+    Redo: FnMut() + 'static,
+{
+    pub fn new(label: impl Into<String>, undo: Undo, redo: Redo) -> FnCommand<Undo, Redo> {
+        FnCommand {
+            label: label.into(),
+            undo,
+            redo,
+        }
+    }
+}
+
+impl<Undo, Redo> UndoCommand for FnCommand<Undo, Redo>
+where
+    Undo: FnMut() + 'static,
+    Redo: FnMut() + 'static,
+{
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn undo(&mut self, _ctx: &mut ()) {
+        (self.undo)()
+    }
+
+    fn redo(&mut self, _ctx: &mut ()) {
+        (self.redo)()
+    }
+}
+
+/// History of the edits of a document or of an editor.
+pub struct UndoStack<Ctx = ()> {
+    /// Commands that can be undone, oldest first.
+    done: VecDeque<Box<dyn AnyUndoCommand<Ctx>>>,
+    /// Commands that can be redone, most recently undone last.
+    undone: Vec<Box<dyn AnyUndoCommand<Ctx>>>,
+    limit: usize,
+}
+
+impl<Ctx: 'static> Default for UndoStack<Ctx> {
+    fn default() -> Self {
+        UndoStack::new()
+    }
+}
+
+impl<Ctx: 'static> UndoStack<Ctx> {
+    /// Creates an empty stack, keeping at most `DEFAULT_LIMIT` commands.
+    pub fn new() -> UndoStack<Ctx> {
+        UndoStack {
+            done: VecDeque::new(),
+            undone: vec![],
+            limit: DEFAULT_LIMIT,
+        }
+    }
+
+    /// Sets the maximum number of commands that can be undone. The oldest commands are forgotten first.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit.max(1);
+        while self.done.len() > self.limit {
+            self.done.pop_front();
+        }
+    }
+
+    /// Records an edit that has just been applied.
+    ///
+    /// The edit is merged with the last recorded edit if they have the same type and `UndoCommand::merge` accepts it.
+    /// The edits that were undone can't be redone anymore.
+    pub fn push<C: UndoCommand<Ctx>>(&mut self, command: C) {
+        self.undone.clear();
+        if let Some(last) = self
+            .done
+            .back_mut()
+            .and_then(|last| last.as_any_mut().downcast_mut::<C>())
+        {
+            if last.merge(&command) {
+                return;
+            }
+        }
+        self.done.push_back(Box::new(command));
+        if self.done.len() > self.limit {
+            self.done.pop_front();
+        }
+    }
+
+    /// Reverts the last edit. Returns `false` if there was nothing to undo.
+    pub fn undo_with(&mut self, ctx: &mut Ctx) -> bool {
+        match self.done.pop_back() {
+            Some(mut command) => {
+                command.undo(ctx);
+                self.undone.push(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the last reverted edit again. Returns `false` if there was nothing to redo.
+    pub fn redo_with(&mut self, ctx: &mut Ctx) -> bool {
+        match self.undone.pop() {
+            Some(mut command) => {
+                command.redo(ctx);
+                self.done.push_back(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Returns the label of the edit that `undo` would revert.
+    pub fn undo_label(&self) -> Option<String> {
+        self.done.back().map(|command| command.label())
+    }
+
+    /// Returns the label of the edit that `redo` would apply again.
+    pub fn redo_label(&self) -> Option<String> {
+        self.undone.last().map(|command| command.label())
+    }
+
+    /// Returns the last recorded edit if it is a `C`.
+    pub fn last<C: UndoCommand<Ctx>>(&self) -> Option<&C> {
+        self.done.back().and_then(|last| last.as_any().downcast_ref::<C>())
+    }
+
+    /// Forgets all edits, e.g. when the document is replaced.
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+    }
+}
+
+impl UndoStack {
+    /// Reverts the last edit. Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.undo_with(&mut ())
+    }
+
+    /// Applies the last reverted edit again. Returns `false` if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.redo_with(&mut ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds `amount` to the counter passed as context. Consecutive additions are merged.
+    struct Add(i32);
+
+    impl UndoCommand<i32> for Add {
+        fn undo(&mut self, value: &mut i32) {
+            *value -= self.0;
+        }
+
+        fn redo(&mut self, value: &mut i32) {
+            *value += self.0;
+        }
+
+        fn merge(&mut self, next: &Self) -> bool {
+            self.0 += next.0;
+            true
+        }
+    }
+
+    struct Reset(i32);
+
+    impl UndoCommand<i32> for Reset {
+        fn undo(&mut self, value: &mut i32) {
+            *value = self.0;
+        }
+
+        fn redo(&mut self, value: &mut i32) {
+            *value = 0;
+        }
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut value = 0;
+        let mut stack = UndoStack::<i32>::new();
+        value += 1;
+        stack.push(Add(1));
+        value += 2;
+        stack.push(Add(2));
+        stack.push(Reset(value));
+        value = 0;
+        value += 4;
+        stack.push(Add(4));
+
+        assert!(stack.undo_with(&mut value));
+        assert_eq!(value, 0);
+        assert!(stack.undo_with(&mut value));
+        assert_eq!(value, 3);
+        // merged with the first addition
+        assert!(stack.undo_with(&mut value));
+        assert_eq!(value, 0);
+        assert!(!stack.undo_with(&mut value));
+
+        assert!(stack.redo_with(&mut value));
+        assert_eq!(value, 3);
+        // a new edit drops the undone ones
+        value += 1;
+        stack.push(Add(1));
+        assert!(!stack.can_redo());
+        assert!(stack.last::<Add>().is_some());
+    }
+
+    #[test]
+    fn limit() {
+        let mut value = 0;
+        let mut stack = UndoStack::<i32>::new();
+        stack.set_limit(2);
+        for i in 1..=3 {
+            stack.push(Reset(value));
+            value = i;
+        }
+        while stack.undo_with(&mut value) {}
+        // the first reset was forgotten
+        assert_eq!(value, 1);
+    }
+}
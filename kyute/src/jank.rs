@@ -0,0 +1,325 @@
+//! Jank detection: reports the frames that exceed a time budget, along with the widgets that took the longest to lay
+//! out and paint.
+//!
+//! Detection is enabled by setting a budget (see `set_frame_budget`). Widget pods then time their layout and paint,
+//! in the same scopes as the "WidgetPod layout" and "Repaint layer" tracing spans. The time spent in each widget,
+//! excluding the time spent in child widget pods, is accumulated over the frame. When a frame exceeds the budget, a
+//! `JankReport` is logged, added to the recent reports (see `reports`), and passed to the listeners registered with
+//! `on_report`. `widget::JankMonitor` shows the reports in the application, and exports them as JSON.
+//!
+//! The duration of a frame goes from the moment the event loop wakes up to the end of the repaint; idle tasks and
+//! resource collections that run afterwards are not included.
+use crate::WidgetId;
+use serde_json::json;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    fmt, fs, io,
+    path::Path,
+    rc::{Rc, Weak},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Number of widgets listed in a report.
+const SLOWEST_WIDGET_COUNT: usize = 10;
+/// Number of reports kept (see `reports`).
+const MAX_REPORTS: usize = 32;
+
+/// Phase of the frame in which a widget was timed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Phase {
+    Layout,
+    Paint,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::Layout => f.write_str("layout"),
+            Phase::Paint => f.write_str("paint"),
+        }
+    }
+}
+
+/// Time spent in a widget during a phase of a frame.
+#[derive(Clone, Debug)]
+pub struct WidgetTiming {
+    pub widget_id: Option<WidgetId>,
+    /// Debug name of the widget.
+    pub widget_name: String,
+    pub phase: Phase,
+    /// Time spent in the widget, excluding child widget pods.
+    pub self_time: Duration,
+    /// Time spent in the widget, including child widget pods.
+    pub total_time: Duration,
+    /// Number of times the widget was laid out or painted during the frame (layouts include speculative ones).
+    pub count: usize,
+}
+
+/// A frame that exceeded the budget.
+#[derive(Clone, Debug)]
+pub struct JankReport {
+    /// When the frame ended.
+    pub timestamp: SystemTime,
+    /// Duration of the frame.
+    pub duration: Duration,
+    /// Budget in effect during the frame.
+    pub budget: Duration,
+    /// Time spent laying out widgets.
+    pub layout_time: Duration,
+    /// Time spent painting widgets.
+    pub paint_time: Duration,
+    /// The widgets with the longest self time, slowest first.
+    pub slowest: Vec<WidgetTiming>,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl JankReport {
+    /// Time spent outside of layout and paint: event handling, recomposition, presentation.
+    pub fn other_time(&self) -> Duration {
+        self.duration.saturating_sub(self.layout_time + self.paint_time)
+    }
+
+    /// Returns a one-line summary of the report.
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.1} ms frame (budget {:.1} ms): layout {:.1} ms, paint {:.1} ms, other {:.1} ms",
+            millis(self.duration),
+            millis(self.budget),
+            millis(self.layout_time),
+            millis(self.paint_time),
+            millis(self.other_time()),
+        )
+    }
+
+    /// Returns the report as JSON. Durations are in milliseconds.
+    pub fn to_json(&self) -> serde_json::Value {
+        let slowest: Vec<_> = self
+            .slowest
+            .iter()
+            .map(|timing| {
+                json!({
+                    "widget_id": timing.widget_id.map(|id| format!("{:?}", id)),
+                    "widget": timing.widget_name,
+                    "phase": timing.phase.to_string(),
+                    "self_ms": millis(timing.self_time),
+                    "total_ms": millis(timing.total_time),
+                    "count": timing.count,
+                })
+            })
+            .collect();
+        json!({
+            "timestamp": self.timestamp.duration_since(UNIX_EPOCH).map_or(0.0, |t| t.as_secs_f64()),
+            "duration_ms": millis(self.duration),
+            "budget_ms": millis(self.budget),
+            "layout_ms": millis(self.layout_time),
+            "paint_ms": millis(self.paint_time),
+            "other_ms": millis(self.other_time()),
+            "slowest": slowest,
+        })
+    }
+}
+
+/// Writes the specified reports to a JSON file.
+pub fn save_reports_json(reports: &[Arc<JankReport>], path: impl AsRef<Path>) -> io::Result<()> {
+    let reports: Vec<_> = reports.iter().map(|report| report.to_json()).collect();
+    let json = serde_json::to_string_pretty(&json!({ "reports": reports }))?;
+    fs::write(path, json)
+}
+
+/// A widget being timed.
+struct OpenScope {
+    start: Instant,
+    /// Time spent in child scopes.
+    children: Duration,
+}
+
+#[derive(Default)]
+struct FrameProfile {
+    stack: Vec<OpenScope>,
+    timings: HashMap<(Option<WidgetId>, Phase), Vec<WidgetTiming>>,
+}
+
+thread_local! {
+    static FRAME_BUDGET: Cell<Option<Duration>> = Cell::new(None);
+    static PROFILE: RefCell<FrameProfile> = RefCell::new(FrameProfile::default());
+    static REPORTS: RefCell<VecDeque<Arc<JankReport>>> = RefCell::new(VecDeque::new());
+    static LISTENERS: RefCell<Vec<Weak<dyn Fn(&Arc<JankReport>)>>> = RefCell::new(Vec::new());
+}
+
+/// Sets the time budget of a frame. `None` disables jank detection (the default).
+pub fn set_frame_budget(budget: Option<Duration>) {
+    FRAME_BUDGET.with(|b| b.set(budget))
+}
+
+/// Returns the time budget of a frame, or `None` if jank detection is disabled.
+pub fn frame_budget() -> Option<Duration> {
+    FRAME_BUDGET.with(|b| b.get())
+}
+
+/// Returns the most recent reports, oldest first.
+pub fn reports() -> Vec<Arc<JankReport>> {
+    REPORTS.with(|r| r.borrow().iter().cloned().collect())
+}
+
+/// Discards the recent reports.
+pub fn clear_reports() {
+    REPORTS.with(|r| r.borrow_mut().clear())
+}
+
+/// Keeps a listener registered with `on_report` alive. The listener is unregistered once all clones are dropped.
+#[derive(Clone)]
+pub struct ReportListener(Rc<dyn Fn(&Arc<JankReport>)>);
+
+/// Registers a function called with each new report.
+#[must_use]
+pub fn on_report(f: impl Fn(&Arc<JankReport>) + 'static) -> ReportListener {
+    let listener: Rc<dyn Fn(&Arc<JankReport>)> = Rc::new(f);
+    LISTENERS.with(|l| l.borrow_mut().push(Rc::downgrade(&listener)));
+    ReportListener(listener)
+}
+
+/// Times the layout or paint of a widget until dropped. See `time_widget`.
+pub(crate) struct WidgetScope<'a> {
+    widget: Option<(Option<WidgetId>, &'a str, Phase)>,
+}
+
+/// Starts timing the layout or paint of a widget, if jank detection is enabled.
+pub(crate) fn time_widget(widget_id: Option<WidgetId>, widget_name: &str, phase: Phase) -> WidgetScope {
+    if frame_budget().is_none() {
+        return WidgetScope { widget: None };
+    }
+    PROFILE.with(|p| {
+        p.borrow_mut().stack.push(OpenScope {
+            start: Instant::now(),
+            children: Duration::ZERO,
+        })
+    });
+    WidgetScope {
+        widget: Some((widget_id, widget_name, phase)),
+    }
+}
+
+impl<'a> Drop for WidgetScope<'a> {
+    fn drop(&mut self) {
+        let (widget_id, widget_name, phase) = match self.widget {
+            Some(widget) => widget,
+            None => return,
+        };
+        PROFILE.with(|p| {
+            let mut profile = p.borrow_mut();
+            let scope = match profile.stack.pop() {
+                Some(scope) => scope,
+                // the profile was reset by `begin_frame` in the middle of the scope
+                None => return,
+            };
+            let total_time = scope.start.elapsed();
+            let self_time = total_time.saturating_sub(scope.children);
+            if let Some(parent) = profile.stack.last_mut() {
+                parent.children += total_time;
+            }
+            // widgets without IDs are told apart by their names
+            let timings = profile.timings.entry((widget_id, phase)).or_default();
+            let timing = match timings.iter().position(|timing| timing.widget_name == widget_name) {
+                Some(i) => &mut timings[i],
+                None => {
+                    timings.push(WidgetTiming {
+                        widget_id,
+                        widget_name: widget_name.to_string(),
+                        phase,
+                        self_time: Duration::ZERO,
+                        total_time: Duration::ZERO,
+                        count: 0,
+                    });
+                    timings.last_mut().unwrap()
+                }
+            };
+            timing.self_time += self_time;
+            timing.total_time += total_time;
+            timing.count += 1;
+        })
+    }
+}
+
+/// Called at the start of a frame.
+pub(crate) fn begin_frame() {
+    PROFILE.with(|p| {
+        let mut profile = p.borrow_mut();
+        profile.stack.clear();
+        profile.timings.clear();
+    })
+}
+
+/// Called at the end of a frame that started at `frame_start`. Reports the frame if it exceeded the budget.
+pub(crate) fn end_frame(frame_start: Instant) {
+    let budget = match frame_budget() {
+        Some(budget) => budget,
+        None => return,
+    };
+    let duration = frame_start.elapsed();
+    if duration <= budget {
+        return;
+    }
+
+    let mut timings: Vec<_> = PROFILE.with(|p| {
+        p.borrow_mut()
+            .timings
+            .drain()
+            .flat_map(|(_, timings)| timings)
+            .collect()
+    });
+    let phase_time = |phase| {
+        timings
+            .iter()
+            .filter(|timing| timing.phase == phase)
+            .map(|timing| timing.self_time)
+            .sum::<Duration>()
+    };
+    let layout_time = phase_time(Phase::Layout);
+    let paint_time = phase_time(Phase::Paint);
+    timings.sort_by(|a, b| b.self_time.cmp(&a.self_time));
+    timings.truncate(SLOWEST_WIDGET_COUNT);
+
+    let report = Arc::new(JankReport {
+        timestamp: SystemTime::now(),
+        duration,
+        budget,
+        layout_time,
+        paint_time,
+        slowest: timings,
+    });
+
+    warn!("jank: {}", report.summary());
+    for timing in report.slowest.iter().take(3) {
+        warn!(
+            "    {} {} {:?}: {:.1} ms ({} times)",
+            timing.phase,
+            timing.widget_name,
+            WidgetId::dbg_option(timing.widget_id),
+            millis(timing.self_time),
+            timing.count
+        );
+    }
+
+    REPORTS.with(|r| {
+        let mut reports = r.borrow_mut();
+        if reports.len() == MAX_REPORTS {
+            reports.pop_front();
+        }
+        reports.push_back(report.clone());
+    });
+
+    // listeners may register other listeners
+    let listeners: Vec<_> = LISTENERS.with(|l| {
+        let mut listeners = l.borrow_mut();
+        listeners.retain(|listener| listener.strong_count() > 0);
+        listeners.iter().filter_map(Weak::upgrade).collect()
+    });
+    for listener in listeners {
+        listener(&report);
+    }
+}
@@ -15,7 +15,12 @@ use std::{
 #[derive(Copy, Clone)]
 pub struct LayoutParams {
     pub widget_state: WidgetState,
-    /// Scale factor.
+    /// Scale factor of the monitor showing the window (physical pixels per DIP).
+    ///
+    /// It changes when the window moves to a monitor with a different resolution. Since it's part of the layout
+    /// parameters, this invalidates the cached layouts (`WidgetPod`, `LayoutCache`). Widgets can use it to snap
+    /// positions to physical pixels (see `RoundToPixel`) or to pick assets of the appropriate resolution
+    /// (see `Image::from_uri_variants`).
     pub scale_factor: f64,
     /// Minimum allowed size.
     pub min: Size,
@@ -85,6 +90,50 @@ impl LayoutParams {
         }
     }
 
+    /// Checks that the constraints are well-formed.
+    ///
+    /// Constraints are well-formed if they don't contain NaNs, the minimum size is finite, `min <= max`
+    /// and the scale factor is positive. Returns a description of the problem otherwise.
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [self.min.width, self.min.height, self.max.width, self.max.height];
+        if values.iter().any(|v| v.is_nan()) || self.scale_factor.is_nan() {
+            return Err("NaN in layout constraints".to_string());
+        }
+        if !self.min.width.is_finite() || !self.min.height.is_finite() {
+            return Err("infinite minimum size".to_string());
+        }
+        if self.min.width > self.max.width || self.min.height > self.max.height {
+            return Err("minimum size greater than maximum size".to_string());
+        }
+        if self.scale_factor <= 0.0 || !self.scale_factor.is_finite() {
+            return Err(format!("invalid scale factor {}", self.scale_factor));
+        }
+        Ok(())
+    }
+
+    /// Checks that the measurements returned by a widget are valid and satisfy the constraints.
+    ///
+    /// The size must be finite and between `min` and `max` (with some tolerance for rounding errors),
+    /// and the baseline, if any, must not be NaN.
+    pub fn validate_measurements(&self, measurements: &Measurements) -> Result<(), String> {
+        const TOLERANCE: f64 = 1e-3;
+        let size = measurements.size;
+        if !size.width.is_finite() || !size.height.is_finite() {
+            return Err(format!("non-finite size {:?}", size));
+        }
+        if size.width < self.min.width - TOLERANCE
+            || size.height < self.min.height - TOLERANCE
+            || size.width > self.max.width + TOLERANCE
+            || size.height > self.max.height + TOLERANCE
+        {
+            return Err(format!("size {:?} doesn't satisfy the constraints", size));
+        }
+        if measurements.baseline.map_or(false, f64::is_nan) {
+            return Err("NaN baseline".to_string());
+        }
+        Ok(())
+    }
+
     pub fn finite_max_width(&self) -> Option<f64> {
         if self.max.width.is_finite() {
             Some(self.max.width)
@@ -506,6 +555,15 @@ impl Default for Geometry {
     }
 }
 
+/// Intrinsic widths of a widget, returned by `Widget::intrinsic_widths`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct IntrinsicWidths {
+    /// Smallest width that the widget can take without overflowing (e.g. the widest word of a paragraph).
+    pub min: f64,
+    /// Width that the widget takes when unconstrained (e.g. the width of a paragraph laid out without line breaks).
+    pub max: f64,
+}
+
 /// Measurements of a box, returned by `Widget::layout`.
 #[derive(Copy, Clone, Debug)]
 pub struct Measurements {
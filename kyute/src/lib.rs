@@ -12,19 +12,38 @@ mod env;
 #[macro_use]
 mod atoms;
 
+pub mod accessibility;
+pub mod animation;
 pub mod application;
 pub mod asset;
 mod bloom;
 pub mod cache;
 mod call_id;
+pub mod color;
+pub mod command;
+#[cfg(feature = "kyute2")]
+pub mod compat;
 mod core;
 mod css;
+pub mod diagnostics;
 mod drawing;
 pub mod event;
+pub mod focus;
 mod font;
+pub mod frame_debugger;
+pub mod gallery;
+pub mod gc;
+pub mod history;
+pub mod jank;
 mod layout;
 mod live_literal;
+pub mod metrics;
+pub mod plugin;
+pub mod prediction;
 pub mod region;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod snapshot;
 mod state;
 pub mod style;
 pub mod theme;
@@ -36,21 +55,26 @@ pub use crate::{
     asset::{Asset, AssetId, AssetLoader, AssetUri},
     atoms::Atom,
     bloom::Bloom,
-    cache::{changed, environment, memoize, once, run_async, state, with_environment, Signal, State},
+    cache::{
+        changed, environment, idle, incremental, keyed, memoize, once, restartable, run_async, state, with_env_state,
+        with_environment, Restartable, Signal, State,
+    },
     core::{
         DebugNode, EventCtx, LayerPaintCtx, LayoutCache, LayoutCtx, Widget, WidgetFilter, WidgetId, SHOW_DEBUG_OVERLAY,
+        SHOW_OVERDRAW, SHOW_PERFORMANCE_HUD,
     },
     drawing::PaintCtx,
-    env::{EnvKey, EnvRef, EnvValue, Environment},
+    env::{EnvKey, EnvRef, EnvState, EnvValue, Environment},
     event::{Event, InputEvent, InternalEvent, PointerEvent, PointerEventKind},
     font::Font,
-    layout::{Alignment, BoxConstraints, Geometry, LayoutParams, Measurements},
+    layout::{Alignment, BoxConstraints, Geometry, IntrinsicWidths, LayoutParams, Measurements},
     live_literal::live_literal,
     style::{Length, LengthOrPercentage, UnitExt},
     widget::Orientation,
-    window::Window,
+    window::{ResizeEdge, ResizeMode, SplashHandoff, Window, WindowGroup, WindowPart},
 };
 
+pub use kyute_api as api;
 pub use kyute_macros::{composable, Widget};
 pub use kyute_shell as shell;
 pub use kyute_shell::{graal, text};
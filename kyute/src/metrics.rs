@@ -0,0 +1,114 @@
+//! Usage metrics, for applications that collect telemetry.
+//!
+//! Metrics are disabled by default. An application that wants them installs a `MetricsSink` with `set_sink`, and
+//! the framework then calls the sink when:
+//! - a clickable widget (buttons, checkboxes...) is clicked,
+//! - a modal dialog is closed, with the time it was open,
+//! - a command is invoked with its key sequence.
+//!
+//! The framework doesn't aggregate, store or send anything itself: events are only forwarded to the sink, which is
+//! responsible for counting them and passing them to the telemetry service of the application (and for asking for
+//! the consent of the user). Without a sink, nothing is recorded.
+use crate::{command::CommandId, WidgetId};
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+/// Receives usage metrics. See the module documentation.
+///
+/// All methods do nothing by default. They are called on the UI thread, during event handling or composition, and
+/// should return quickly.
+pub trait MetricsSink {
+    /// Called when a clickable widget is clicked, with the pointer or the keyboard.
+    ///
+    /// `widget_name` is the debug name of the clicked widget (see `Widget::debug_name`), which can be used to count
+    /// clicks by kind of widget; `widget_id` identifies a particular widget for the lifetime of the application.
+    fn click(&self, widget_id: WidgetId, widget_name: &str) {
+        let _ = (widget_id, widget_name);
+    }
+
+    /// Called when a modal dialog is closed. `accepted` is false if it was cancelled.
+    fn dialog_closed(&self, title: &str, open_duration: Duration, accepted: bool) {
+        let _ = (title, open_duration, accepted);
+    }
+
+    /// Called when the key sequence of a command is entered, whether the command is handled by a widget or by its
+    /// global handler.
+    fn command_invoked(&self, command: &CommandId) {
+        let _ = command;
+    }
+}
+
+thread_local! {
+    static SINK: RefCell<Option<Rc<dyn MetricsSink>>> = RefCell::new(None);
+}
+
+/// Sets the sink that receives the metrics. `None` disables metrics (the default).
+pub fn set_sink(sink: Option<Rc<dyn MetricsSink>>) {
+    SINK.with(|s| *s.borrow_mut() = sink)
+}
+
+/// Returns whether a sink is installed.
+pub fn is_enabled() -> bool {
+    SINK.with(|s| s.borrow().is_some())
+}
+
+fn with_sink(f: impl FnOnce(&dyn MetricsSink)) {
+    // the sink may install another sink
+    let sink = SINK.with(|s| s.borrow().clone());
+    if let Some(sink) = sink {
+        f(&*sink)
+    }
+}
+
+pub(crate) fn click(widget_id: WidgetId, widget_name: &str) {
+    with_sink(|sink| sink.click(widget_id, widget_name))
+}
+
+pub(crate) fn dialog_closed(title: &str, open_duration: Duration, accepted: bool) {
+    with_sink(|sink| sink.dialog_closed(title, open_duration, accepted))
+}
+
+pub(crate) fn command_invoked(command: &CommandId) {
+    with_sink(|sink| sink.command_invoked(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        commands: RefCell<Vec<CommandId>>,
+        dialogs: RefCell<Vec<(String, bool)>>,
+    }
+
+    impl MetricsSink for Recorder {
+        fn dialog_closed(&self, title: &str, _open_duration: Duration, accepted: bool) {
+            self.dialogs.borrow_mut().push((title.to_string(), accepted));
+        }
+
+        fn command_invoked(&self, command: &CommandId) {
+            self.commands.borrow_mut().push(command.clone());
+        }
+    }
+
+    #[test]
+    fn sink() {
+        const COPY: CommandId = CommandId::new("edit.copy");
+
+        // nothing is recorded without a sink
+        assert!(!is_enabled());
+        command_invoked(&COPY);
+
+        let recorder = Rc::new(Recorder::default());
+        set_sink(Some(recorder.clone()));
+        assert!(is_enabled());
+        command_invoked(&COPY);
+        dialog_closed("Preferences", Duration::from_secs(3), true);
+
+        set_sink(None);
+        command_invoked(&COPY);
+
+        assert_eq!(*recorder.commands.borrow(), vec![COPY]);
+        assert_eq!(*recorder.dialogs.borrow(), vec![("Preferences".to_string(), true)]);
+    }
+}
@@ -0,0 +1,66 @@
+//! Widget plugins.
+//!
+//! Plugins are dynamic libraries built against the stable plugin interface in `kyute::api` (the `kyute-api` crate)
+//! instead of kyute itself. Loading the library is left to the application (e.g. with `libloading`): look up the
+//! entry point named `api::PLUGIN_ENTRY_SYMBOL`, pass it to `Plugin::from_entry`, then show the widgets of the plugin
+//! with `widget::PluginWidget`.
+use crate::api::{self, ApiVersion, PluginDescriptor, PluginEntry, WidgetFactory};
+use thiserror::Error;
+
+/// Errors returned when loading a plugin.
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("plugin `{name}` was built against plugin API {plugin_version}, incompatible with {host_version}")]
+    IncompatibleVersion {
+        name: String,
+        plugin_version: ApiVersion,
+        host_version: ApiVersion,
+    },
+}
+
+/// A loaded plugin.
+#[derive(Copy, Clone)]
+pub struct Plugin {
+    descriptor: &'static PluginDescriptor,
+}
+
+impl Plugin {
+    /// Calls the entry point of a plugin, and checks that the plugin is compatible with this version of the plugin API.
+    ///
+    /// # Safety
+    /// `entry` must be the entry point of a plugin (see `api::export_plugin!`). The library of the plugin must stay
+    /// loaded as long as the returned `Plugin` and the widgets created from it exist.
+    pub unsafe fn from_entry(entry: PluginEntry) -> Result<Plugin, PluginError> {
+        let descriptor = entry();
+        if !descriptor.api_version.is_compatible_with(api::API_VERSION) {
+            return Err(PluginError::IncompatibleVersion {
+                name: descriptor.name.as_str().to_string(),
+                plugin_version: descriptor.api_version,
+                host_version: api::API_VERSION,
+            });
+        }
+        Ok(Plugin { descriptor })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.descriptor.name.as_str()
+    }
+
+    /// Returns the version of the plugin API that the plugin was built against.
+    pub fn api_version(&self) -> ApiVersion {
+        self.descriptor.api_version
+    }
+
+    /// Returns the names of the widgets provided by the plugin.
+    pub fn widget_names(&self) -> impl Iterator<Item = &'static str> {
+        self.descriptor
+            .widgets
+            .as_slice()
+            .iter()
+            .map(|factory| factory.name.as_str())
+    }
+
+    pub(crate) fn widget_factory(&self, name: &str) -> Option<&'static WidgetFactory> {
+        self.descriptor.widget(name)
+    }
+}
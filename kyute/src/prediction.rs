@@ -0,0 +1,142 @@
+//! Prediction of pointer positions, to hide the latency of drawing and dragging.
+//!
+//! The effects of a pointer event are displayed some time after the OS produced the event (see
+//! `EventCtx::input_latency`): strokes and dragged objects lag behind a fast moving pen or mouse. `PointerPredictor`
+//! extrapolates the position of the pointer at the time the frame will be displayed from its recent velocity.
+//! Predictions are not accumulated: each real sample corrects the previous prediction.
+use crate::{EventCtx, Offset, Point};
+use kyute_shell::animation::frame_timing;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Maximum time ahead of the last sample that positions are predicted. Further ahead, extrapolations are wrong more
+/// often than not.
+pub const MAX_PREDICTION: Duration = Duration::from_millis(50);
+/// Samples older than this, relative to the last sample, are not used to estimate the velocity.
+const VELOCITY_WINDOW: Duration = Duration::from_millis(60);
+/// Maximum number of samples kept.
+const MAX_SAMPLES: usize = 8;
+
+/// Predicts the position of a pointer from its recent positions.
+///
+/// Samples must all be in the same coordinate space (e.g. window positions): local positions change when the widget
+/// moves, as a dragged widget does.
+#[derive(Clone, Debug, Default)]
+pub struct PointerPredictor {
+    /// Recent samples, oldest first.
+    samples: VecDeque<(Instant, Point)>,
+}
+
+impl PointerPredictor {
+    pub fn new() -> PointerPredictor {
+        PointerPredictor::default()
+    }
+
+    /// Adds a sample of the real position of the pointer.
+    ///
+    /// Timestamps of input events have the resolution of the system timer: a sample with the same timestamp as the
+    /// last one replaces it.
+    pub fn add_sample(&mut self, timestamp: Instant, position: Point) {
+        if let Some(&(last, _)) = self.samples.back() {
+            if timestamp < last {
+                // out of order, or from another stroke
+                self.samples.clear();
+            } else if timestamp == last {
+                self.samples.pop_back();
+            }
+        }
+        self.samples.push_back((timestamp, position));
+        while self.samples.len() > MAX_SAMPLES
+            || timestamp.duration_since(self.samples.front().unwrap().0) > VELOCITY_WINDOW
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Forgets the previous samples, e.g. at the start of a stroke.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Returns the average velocity of the pointer over the recent samples, in units per second.
+    ///
+    /// Returns `None` if there are not enough samples.
+    pub fn velocity(&self) -> Option<Offset> {
+        let &(t0, p0) = self.samples.front()?;
+        let &(t1, p1) = self.samples.back()?;
+        let dt = t1.duration_since(t0).as_secs_f64();
+        if dt > 0.0 {
+            Some((p1 - p0) / dt)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the predicted position of the pointer `ahead` of the last sample (at most `MAX_PREDICTION`).
+    ///
+    /// Returns the last sample if the velocity is unknown, or `None` if there are no samples.
+    pub fn predict(&self, ahead: Duration) -> Option<Point> {
+        let &(_, last) = self.samples.back()?;
+        match self.velocity() {
+            Some(velocity) => Some(last + velocity * ahead.min(MAX_PREDICTION).as_secs_f64()),
+            None => Some(last),
+        }
+    }
+}
+
+/// Returns how far ahead of the input event being delivered the pointer position should be predicted: the measured
+/// input latency of the window, or one frame if it hasn't been measured yet.
+pub fn prediction_time(ctx: &EventCtx) -> Duration {
+    ctx.input_latency().unwrap_or_else(|| frame_timing().frame_interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn constant_velocity() {
+        let t0 = Instant::now();
+        let mut predictor = PointerPredictor::new();
+        assert_eq!(predictor.predict(ms(10)), None);
+        for i in 0..4 {
+            predictor.add_sample(t0 + ms(i * 10), Point::new(i as f64 * 10.0, 0.0));
+        }
+        // 1000 units per second
+        let predicted = predictor.predict(ms(20)).unwrap();
+        assert!((predicted.x - 50.0).abs() < 1e-6);
+        // clamped to MAX_PREDICTION
+        let predicted = predictor.predict(ms(500)).unwrap();
+        assert!((predicted.x - 80.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn same_timestamp_replaces_sample() {
+        let t0 = Instant::now();
+        let mut predictor = PointerPredictor::new();
+        predictor.add_sample(t0, Point::new(0.0, 0.0));
+        predictor.add_sample(t0, Point::new(5.0, 0.0));
+        // a single sample: no velocity, the prediction is the last position
+        assert_eq!(predictor.velocity(), None);
+        assert_eq!(predictor.predict(ms(20)), Some(Point::new(5.0, 0.0)));
+    }
+
+    #[test]
+    fn old_samples_are_ignored() {
+        let t0 = Instant::now();
+        let mut predictor = PointerPredictor::new();
+        predictor.add_sample(t0, Point::new(-100.0, 0.0));
+        // stopped for a while, then moves
+        predictor.add_sample(t0 + ms(500), Point::new(0.0, 0.0));
+        predictor.add_sample(t0 + ms(510), Point::new(0.0, 10.0));
+        let velocity = predictor.velocity().unwrap();
+        assert!(velocity.x.abs() < 1e-6);
+        assert!((velocity.y - 1000.0).abs() < 1e-6);
+    }
+}
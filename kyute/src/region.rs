@@ -1,4 +1,10 @@
-use crate::Rect;
+use crate::{Rect, Transform};
+
+/// Number of rectangles above which a region is simplified to its bounds.
+///
+/// Damage regions are usually made of a few small rectangles (a caret, a hovered item...): beyond that, tracking
+/// them individually costs more than repainting the area between them.
+const MAX_RECTS: usize = 8;
 
 #[derive(Clone, Debug)]
 pub struct Region {
@@ -13,8 +19,21 @@ impl Region {
 
     /// Adds a rectangle to this region.
     pub fn add_rect(&mut self, rect: Rect) {
-        if rect.area() > 0.0 {
-            self.rects.push(rect)
+        if rect.area() > 0.0 && !self.rects.iter().any(|r| r.contains_rect(&rect)) {
+            self.rects.retain(|r| !rect.contains_rect(r));
+            self.rects.push(rect);
+            if self.rects.len() > MAX_RECTS {
+                let bounds = self.bounds().unwrap();
+                self.rects.clear();
+                self.rects.push(bounds);
+            }
+        }
+    }
+
+    /// Adds the rectangles of another region to this region.
+    pub fn add_region(&mut self, other: &Region) {
+        for rect in other.rects.iter() {
+            self.add_rect(*rect);
         }
     }
 
@@ -23,6 +42,43 @@ impl Region {
         self.rects.iter().any(|r| r.intersects(&rect))
     }
 
+    /// Returns the rectangles of this region. They may overlap.
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    /// Returns the bounding rectangle of this region, or `None` if it is empty.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.rects.iter().copied().reduce(|a, b| a.union(&b))
+    }
+
+    /// Returns this region transformed by the specified transform.
+    ///
+    /// Each rectangle is replaced by the bounding box of its transformed corners.
+    pub fn transformed(&self, transform: &Transform) -> Region {
+        let mut region = Region::new();
+        for rect in self.rects.iter() {
+            region.add_rect(transform.outer_transformed_rect(rect));
+        }
+        region
+    }
+
+    /// Returns the part of this region inside the specified rectangle.
+    pub fn clipped(&self, clip: Rect) -> Region {
+        let mut region = Region::new();
+        for rect in self.rects.iter() {
+            if let Some(rect) = rect.intersection(&clip) {
+                region.add_rect(rect);
+            }
+        }
+        region
+    }
+
+    /// Removes all rectangles from this region.
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+
     /// Returns `true` if this region is empty.
     pub fn is_empty(&self) -> bool {
         self.rects.is_empty()
@@ -34,3 +90,46 @@ impl Default for Region {
         Region::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point, Size};
+
+    fn rect(x: f64, y: f64, w: f64, h: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(w, h))
+    }
+
+    #[test]
+    fn add_rect() {
+        let mut region = Region::new();
+        region.add_rect(rect(0.0, 0.0, 10.0, 10.0));
+        // contained: ignored
+        region.add_rect(rect(2.0, 2.0, 4.0, 4.0));
+        // empty: ignored
+        region.add_rect(rect(50.0, 50.0, 0.0, 10.0));
+        assert_eq!(region.rects(), &[rect(0.0, 0.0, 10.0, 10.0)]);
+
+        // replaces the rectangles that it contains
+        region.add_rect(rect(-10.0, -10.0, 30.0, 30.0));
+        assert_eq!(region.rects(), &[rect(-10.0, -10.0, 30.0, 30.0)]);
+    }
+
+    #[test]
+    fn simplified_to_bounds() {
+        let mut region = Region::new();
+        for i in 0..=MAX_RECTS {
+            region.add_rect(rect(i as f64 * 20.0, 0.0, 10.0, 10.0));
+        }
+        assert_eq!(region.rects(), &[rect(0.0, 0.0, MAX_RECTS as f64 * 20.0 + 10.0, 10.0)]);
+    }
+
+    #[test]
+    fn clipped() {
+        let mut region = Region::new();
+        region.add_rect(rect(0.0, 0.0, 10.0, 10.0));
+        region.add_rect(rect(100.0, 0.0, 10.0, 10.0));
+        let clipped = region.clipped(rect(5.0, 5.0, 50.0, 50.0));
+        assert_eq!(clipped.rects(), &[rect(5.0, 5.0, 5.0, 5.0)]);
+    }
+}
@@ -0,0 +1,429 @@
+//! Scripted UI panels (requires the `scripting` feature).
+//!
+//! Panels are written in [Rhai](https://rhai.rs). The script defines a `ui` function that returns the widgets of
+//! the panel, and functions that are called in response to user input. The state of the panel is the `this`
+//! object map, which persists across recompositions and script reloads:
+//!
+//!     fn init() {
+//!         this.count = 0;
+//!         this.name = "";
+//!     }
+//!
+//!     fn ui() {
+//!         column([
+//!             text(`Count: ${this.count}`),
+//!             row([button("+", Fn("add")), button("-", Fn("subtract"))]),
+//!             text_field("Name", this.name, Fn("rename")),
+//!         ]).padding(8.0)
+//!     }
+//!
+//!     fn add() { this.count += 1; }
+//!     fn subtract() { this.count -= 1; }
+//!     fn rename(name) { this.name = name; }
+//!
+//! `init` is optional: it's called once, before the first call to `ui`. The functions available to build widgets are:
+//! - `text(text)`
+//! - `button(label)`, `button(label, on_click)`
+//! - `checkbox(label, checked, on_toggled)`: `on_toggled` receives the new state
+//! - `text_field(label, text, on_changed)`: `on_changed` receives the new text
+//! - `column(widgets)`, `row(widgets)`
+//! - `widget.padding(size)`: padding in DIPs
+//! - `widget.class(classes)`: style classes (see `WidgetExt::class`)
+//!
+//! Host applications can expose their own functions to scripts with `ScriptPanel::watch_with_engine`.
+//!
+//! Scripts run on the UI thread, so the engine limits the resources that they can use: a script that runs for too
+//! long (e.g. an infinite loop), recurses too deeply or builds huge strings, arrays or maps is stopped, and the error
+//! is displayed in the panel. The limits can be changed in the `setup` function of `watch_with_engine`.
+use crate::{
+    cache, composable,
+    util::fs_watch::watch_path,
+    widget::{
+        grid::{Area, TrackBreadth},
+        Button, CheckboxField, Grid, Text, TextField, WidgetExt, WidgetPod,
+    },
+    Color, Environment, Event, EventCtx, Geometry, LayoutCtx, LayoutParams, PaintCtx, UnitExt, Widget, WidgetId,
+};
+use rhai::{Array, Dynamic, Engine, FnPtr, Map, Scope, AST, FLOAT, INT};
+use std::{fs, path::Path, rc::Rc, sync::Arc};
+
+pub use rhai;
+
+/// A widget built by a script.
+#[derive(Clone, Debug)]
+enum Node {
+    Text(String),
+    Button {
+        label: String,
+        on_click: Option<FnPtr>,
+    },
+    Checkbox {
+        label: String,
+        checked: bool,
+        on_toggled: FnPtr,
+    },
+    TextField {
+        label: String,
+        text: String,
+        on_changed: FnPtr,
+    },
+    Column(Vec<Node>),
+    Row(Vec<Node>),
+    Padding(f64, Box<Node>),
+    Class(String, Box<Node>),
+}
+
+/// Converts the elements of a script array to widgets, skipping the values that aren't widgets.
+fn nodes(array: Array) -> Vec<Node> {
+    array
+        .into_iter()
+        .filter_map(|item| {
+            let type_name = item.type_name();
+            let node = item.try_cast::<Node>();
+            if node.is_none() {
+                warn!("script: expected a widget, got a value of type `{}`", type_name);
+            }
+            node
+        })
+        .collect()
+}
+
+/// Maximum number of operations that a call to a script function can perform.
+const MAX_OPERATIONS: u64 = 1_000_000;
+/// Maximum depth of nested function calls.
+const MAX_CALL_LEVELS: usize = 64;
+/// Maximum depth of nested expressions, at the global level and in functions.
+const MAX_EXPR_DEPTH: usize = 64;
+/// Maximum length of strings, in bytes.
+const MAX_STRING_SIZE: usize = 1 << 20;
+/// Maximum number of elements of arrays and maps.
+const MAX_COLLECTION_SIZE: usize = 10_000;
+
+/// Creates a script engine with the widget construction functions.
+fn create_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.register_type_with_name::<Node>("Widget");
+    engine.register_fn("text", |text: &str| Node::Text(text.to_string()));
+    engine.register_fn("button", |label: &str| Node::Button {
+        label: label.to_string(),
+        on_click: None,
+    });
+    engine.register_fn("button", |label: &str, on_click: FnPtr| Node::Button {
+        label: label.to_string(),
+        on_click: Some(on_click),
+    });
+    engine.register_fn("checkbox", |label: &str, checked: bool, on_toggled: FnPtr| {
+        Node::Checkbox {
+            label: label.to_string(),
+            checked,
+            on_toggled,
+        }
+    });
+    engine.register_fn("text_field", |label: &str, text: &str, on_changed: FnPtr| {
+        Node::TextField {
+            label: label.to_string(),
+            text: text.to_string(),
+            on_changed,
+        }
+    });
+    engine.register_fn("column", |items: Array| Node::Column(nodes(items)));
+    engine.register_fn("row", |items: Array| Node::Row(nodes(items)));
+    engine.register_fn("padding", |node: Node, size: FLOAT| Node::Padding(size, Box::new(node)));
+    engine.register_fn("padding", |node: Node, size: INT| {
+        Node::Padding(size as f64, Box::new(node))
+    });
+    engine.register_fn("class", |node: Node, classes: &str| {
+        Node::Class(classes.to_string(), Box::new(node))
+    });
+    engine
+}
+
+/// Returns whether the script defines a function with the specified name.
+fn has_function(ast: &AST, name: &str) -> bool {
+    ast.iter_functions().any(|f| f.name == name)
+}
+
+/// Runs the functions of a script on the state of a panel.
+struct ScriptCtx<'a> {
+    engine: &'a Engine,
+    ast: &'a AST,
+    /// State of the panel, passed as `this` to the script functions.
+    this: Dynamic,
+    /// Whether a function that may have modified the state has been called.
+    modified: bool,
+    /// The last error raised by a script function.
+    error: Option<String>,
+}
+
+impl<'a> ScriptCtx<'a> {
+    fn call(&mut self, name: &str, args: Vec<Dynamic>) -> Option<Dynamic> {
+        let mut scope = Scope::new();
+        match self
+            .engine
+            .call_fn_raw(&mut scope, self.ast, false, false, name, Some(&mut self.this), args)
+        {
+            Ok(result) => Some(result),
+            Err(err) => {
+                let message = format!("`{}`: {}", name, err);
+                error!("script error in {}", message);
+                self.error = Some(message);
+                None
+            }
+        }
+    }
+
+    /// Calls an event handler. Curried arguments are passed before `args`.
+    fn call_handler(&mut self, handler: &FnPtr, args: Vec<Dynamic>) {
+        let args = handler.curry().iter().cloned().chain(args).collect();
+        self.call(handler.fn_name(), args);
+        self.modified = true;
+    }
+
+    #[composable]
+    fn build(&mut self, node: &Node) -> Arc<WidgetPod> {
+        match node {
+            Node::Text(text) => Arc::new(WidgetPod::new(Text::new(text.clone()))),
+            Node::Button { label, on_click } => {
+                let button = Button::new(label.clone());
+                if button.clicked() {
+                    if let Some(on_click) = on_click {
+                        self.call_handler(on_click, vec![]);
+                    }
+                }
+                Arc::new(WidgetPod::new(button))
+            }
+            Node::Checkbox {
+                label,
+                checked,
+                on_toggled,
+            } => {
+                let checkbox = CheckboxField::new(label.clone(), *checked);
+                if let Some(checked) = checkbox.toggled() {
+                    self.call_handler(on_toggled, vec![Dynamic::from(checked)]);
+                }
+                Arc::new(WidgetPod::new(checkbox))
+            }
+            Node::TextField {
+                label,
+                text,
+                on_changed,
+            } => {
+                let text_field = TextField::new(label.clone(), text.clone());
+                if let Some(text) = text_field.text_changed() {
+                    self.call_handler(on_changed, vec![Dynamic::from(text.to_string())]);
+                }
+                Arc::new(WidgetPod::new(text_field))
+            }
+            Node::Column(items) => {
+                let mut grid = Grid::column(TrackBreadth::Flex(1.0));
+                for (i, item) in items.iter().enumerate() {
+                    let widget = cache::scoped(i, || self.build(item));
+                    grid.place(Area::default(), 0, widget);
+                }
+                Arc::new(WidgetPod::new(grid))
+            }
+            Node::Row(items) => {
+                let mut grid = Grid::row(TrackBreadth::Auto);
+                for (i, item) in items.iter().enumerate() {
+                    let widget = cache::scoped(i, || self.build(item));
+                    grid.place(Area::default(), 0, widget);
+                }
+                Arc::new(WidgetPod::new(grid))
+            }
+            Node::Padding(size, inner) => Arc::new(WidgetPod::new(self.build(inner).padding(size.dip()))),
+            Node::Class(classes, inner) => Arc::new(WidgetPod::new(self.build(inner).class(classes))),
+        }
+    }
+}
+
+/// A panel built by a script.
+///
+/// See the module documentation for the functions available to scripts.
+pub struct ScriptPanel {
+    content: Arc<WidgetPod>,
+}
+
+impl ScriptPanel {
+    /// Creates a panel from the source of a script.
+    #[composable]
+    pub fn from_source(source: &str) -> ScriptPanel {
+        let engine = cache::once(|| Rc::new(create_engine()));
+        let ast = cache::state(|| None::<AST>);
+        let compile_error = cache::state(|| None::<String>);
+        if cache::changed(source.to_string()) || (ast.get().is_none() && compile_error.get().is_none()) {
+            match engine.compile(source) {
+                Ok(new_ast) => {
+                    ast.set_without_invalidation(Some(new_ast));
+                    compile_error.set_without_invalidation(None);
+                }
+                Err(err) => {
+                    error!("failed to compile script: {}", err);
+                    compile_error.set_without_invalidation(Some(err.to_string()));
+                }
+            }
+        }
+        Self::run(&engine, ast.get(), compile_error.get())
+    }
+
+    /// Loads a panel from a script file, and reloads it when the file changes.
+    ///
+    /// The state of the panel is kept across reloads. If the new version of the script fails to load, the error is
+    /// displayed above the panel built with the last version that loaded successfully.
+    #[composable]
+    pub fn watch(path: impl AsRef<Path>) -> ScriptPanel {
+        Self::watch_with_engine(path, |_| {})
+    }
+
+    /// Same as `watch`, with additional types and functions registered by the host application.
+    ///
+    /// `setup` is called once, with an engine on which the widget construction functions are already registered.
+    #[composable]
+    pub fn watch_with_engine(path: impl AsRef<Path>, setup: impl FnOnce(&mut Engine)) -> ScriptPanel {
+        let path = path.as_ref();
+        let engine = cache::once(move || {
+            let mut engine = create_engine();
+            setup(&mut engine);
+            Rc::new(engine)
+        });
+        let changed = watch_path(path);
+        let ast = cache::state(|| None::<AST>);
+        let load_error = cache::state(|| None::<String>);
+        if changed || (ast.get().is_none() && load_error.get().is_none()) {
+            let result = fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| engine.compile(&source).map_err(|err| err.to_string()));
+            match result {
+                Ok(new_ast) => {
+                    ast.set_without_invalidation(Some(new_ast));
+                    load_error.set_without_invalidation(None);
+                }
+                Err(err) => {
+                    error!("failed to load script `{}`: {}", path.display(), err);
+                    load_error.set_without_invalidation(Some(err));
+                }
+            }
+        }
+        Self::run(&engine, ast.get(), load_error.get())
+    }
+
+    /// Builds the panel with the `ui` function of the script.
+    #[composable]
+    fn run(engine: &Engine, ast: Option<AST>, load_error: Option<String>) -> ScriptPanel {
+        let panel_state = cache::state(|| None::<Dynamic>);
+        let mut error = load_error;
+        let mut content = None;
+
+        if let Some(ref ast) = ast {
+            let initialized = panel_state.get().is_some();
+            let mut ctx = ScriptCtx {
+                engine,
+                ast,
+                this: panel_state.get().unwrap_or_else(|| Dynamic::from(Map::new())),
+                modified: false,
+                error: None,
+            };
+            if !initialized && has_function(ast, "init") {
+                ctx.call("init", vec![]);
+            }
+            let ui = ctx.call("ui", vec![]);
+            let node = ui.and_then(|ui| {
+                let type_name = ui.type_name();
+                let node = ui.try_cast::<Node>();
+                if node.is_none() {
+                    ctx.error = Some(format!(
+                        "`ui` must return a widget, got a value of type `{}`",
+                        type_name
+                    ));
+                }
+                node
+            });
+            if let Some(node) = node {
+                content = Some(cache::scoped(0, || ctx.build(&node)));
+            }
+
+            error = error.or(ctx.error);
+            if !initialized || ctx.modified {
+                // recompose with the new state
+                panel_state.set(Some(ctx.this));
+            }
+        }
+
+        // errors are displayed above the contents
+        let mut grid = Grid::column(TrackBreadth::Flex(1.0));
+        if let Some(error) = error {
+            let error_text = cache::scoped(1, || {
+                Text::new(format!("script error: {}", error)).text_color(Color::new(0.9, 0.3, 0.3, 1.0))
+            });
+            grid.place(Area::default(), 0, Arc::new(WidgetPod::new(error_text)));
+        }
+        if let Some(content) = content {
+            grid.place(Area::default(), 0, content);
+        }
+
+        ScriptPanel {
+            content: Arc::new(WidgetPod::new(grid)),
+        }
+    }
+}
+
+impl Widget for ScriptPanel {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.content.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.content.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.content.event(ctx, event, env)
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhai::EvalAltResult;
+
+    /// Evaluates a script that must fail, and returns the error raised, unwrapping the errors of function calls.
+    fn eval_error(engine: &Engine, script: &str) -> EvalAltResult {
+        let mut err = engine.eval::<()>(script).unwrap_err();
+        loop {
+            match *err {
+                EvalAltResult::ErrorInFunctionCall(_, _, inner, _) => err = inner,
+                err => return err,
+            }
+        }
+    }
+
+    #[test]
+    fn engine_limits() {
+        let engine = create_engine();
+        assert!(matches!(
+            eval_error(&engine, "loop {}"),
+            EvalAltResult::ErrorTooManyOperations(_)
+        ));
+        assert!(matches!(
+            eval_error(&engine, "fn f(x) { f(x + 1) } f(0)"),
+            EvalAltResult::ErrorStackOverflow(_)
+        ));
+        assert!(matches!(
+            eval_error(&engine, r#"let s = "x"; loop { s += s; }"#),
+            EvalAltResult::ErrorDataTooLarge(..)
+        ));
+        assert!(matches!(
+            eval_error(&engine, "let a = []; loop { a.push(0); }"),
+            EvalAltResult::ErrorDataTooLarge(..)
+        ));
+    }
+}
@@ -0,0 +1,538 @@
+//! Offscreen rendering and visual regression testing of widgets.
+//!
+//! `OffscreenRenderer` composes, lays out and paints a widget tree into an image without opening a window.
+//! `SnapshotTester` builds on it to compare renderings of named scenarios against stored golden images.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use kyute::{snapshot::SnapshotTester, widget::Button, Size};
+//!
+//! let tester = SnapshotTester::new("tests/goldens");
+//! tester.assert_matches("button/default", Size::new(200.0, 40.0), || Button::new("OK"));
+//! ```
+//!
+//! Set the `KYUTE_UPDATE_SNAPSHOTS` environment variable to overwrite the goldens with the current renderings.
+use crate::{
+    application::base_environment,
+    cache::{noop_waker, Cache},
+    style::WidgetState,
+    widget::WidgetPod,
+    window::create_skia_vulkan_backend_context,
+    Environment, LayoutCtx, LayoutParams, Measurements, PaintCtx, RoundToPixel, Size, SizeI, Widget,
+};
+use kyute_shell::{animation::Layer, application::Application};
+use palette::{ColorDifference, FromColor, Lab, Srgb};
+use skia_safe as sk;
+use std::{
+    cell::RefCell,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Name of the environment variable that makes `SnapshotTester` overwrite the goldens.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "KYUTE_UPDATE_SNAPSHOTS";
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Offscreen rendering
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Renders widgets to images, without a window.
+///
+/// Contents painted on native composition layers (see `WidgetPod::with_native_layer`) are not captured.
+pub struct OffscreenRenderer {
+    // must be dropped before the backend context
+    skia_direct_context: sk::gpu::DirectContext,
+    _skia_backend_context: sk::gpu::vk::BackendContext<'static>,
+}
+
+impl OffscreenRenderer {
+    /// Creates a renderer on the GPU device of the application.
+    pub fn new() -> OffscreenRenderer {
+        let device = Application::instance().gpu_device().clone();
+        let skia_backend_context = unsafe { create_skia_vulkan_backend_context(&device) };
+        let skia_direct_context =
+            sk::gpu::DirectContext::new_vulkan(&skia_backend_context, &sk::gpu::ContextOptions::new())
+                .expect("failed to create skia context");
+        OffscreenRenderer {
+            skia_direct_context,
+            _skia_backend_context: skia_backend_context,
+        }
+    }
+
+    /// Composes the widget returned by `ui`, lays it out in a box of the specified size (in DIPs), and renders it.
+    ///
+    /// `ui` is composed in a fresh cache, so the widget is rendered in its initial state.
+    /// The returned image is a raster image of `size * scale_factor` pixels, with a transparent background.
+    pub fn render<W: Widget + 'static>(
+        &mut self,
+        env: &Environment,
+        size: Size,
+        scale_factor: f64,
+        ui: impl Fn() -> W,
+    ) -> sk::Image {
+        let mut cache = Cache::new(noop_waker());
+        let content = cache.recompose(env, || WidgetPod::new(ui()));
+
+        // --- layout ---
+        let mut layout_ctx = LayoutCtx::new(scale_factor);
+        let geometry = content.layout(
+            &mut layout_ctx,
+            &LayoutParams {
+                widget_state: WidgetState::default(),
+                scale_factor,
+                min: Size::zero(),
+                max: size,
+            },
+            env,
+        );
+        let offset = geometry
+            .place_into(&Measurements::new(size))
+            .round_to_pixel(scale_factor);
+        content.set_offset(offset);
+
+        // --- paint ---
+        let physical_size = SizeI::new(
+            (size.width * scale_factor).ceil() as i32,
+            (size.height * scale_factor).ceil() as i32,
+        );
+        let image_info = sk::ImageInfo::new(
+            (physical_size.width.max(1), physical_size.height.max(1)),
+            sk::ColorType::RGBA8888,
+            sk::AlphaType::Premul,
+            None,
+        );
+        let mut surface = sk::Surface::new_render_target(
+            &mut self.skia_direct_context,
+            sk::Budgeted::No,
+            &image_info,
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("failed to create skia surface");
+        surface.canvas().clear(sk::Color4f::new(0.0, 0.0, 0.0, 0.0));
+
+        // the layer is only used to determine the bounds of the painting context, it is never composited
+        let layer = Layer::new();
+        layer.set_size(physical_size);
+        {
+            let mut paint_ctx = PaintCtx::new(&mut surface, &layer, scale_factor, &mut self.skia_direct_context);
+            content.paint(&mut paint_ctx);
+        }
+
+        // --- read back ---
+        let row_bytes = image_info.min_row_bytes();
+        let mut pixels = vec![0u8; row_bytes * image_info.height() as usize];
+        if !surface.read_pixels(&image_info, &mut pixels, row_bytes, (0, 0)) {
+            panic!("failed to read back the rendered image");
+        }
+        sk::Image::from_raster_data(&image_info, sk::Data::new_copy(&pixels), row_bytes)
+            .expect("failed to create raster image")
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Image comparison
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Tolerances used when comparing a rendering with its golden image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DiffTolerance {
+    /// Maximum perceptual color difference between two pixels considered identical (CIEDE2000).
+    ///
+    /// The default (`2.3`) is about the smallest difference noticeable by a human observer.
+    pub max_color_difference: f32,
+    /// Maximum difference of alpha (between 0 and 1) between two pixels considered identical.
+    pub max_alpha_difference: f32,
+    /// Fraction of pixels (between 0 and 1) that may differ before the images are considered different.
+    ///
+    /// Allows for minor antialiasing differences between GPUs or drivers.
+    pub max_differing_pixels: f64,
+}
+
+impl Default for DiffTolerance {
+    fn default() -> Self {
+        DiffTolerance {
+            max_color_difference: 2.3,
+            max_alpha_difference: 0.02,
+            max_differing_pixels: 0.001,
+        }
+    }
+}
+
+impl DiffTolerance {
+    /// Requires the images to be identical.
+    pub fn exact() -> DiffTolerance {
+        DiffTolerance {
+            max_color_difference: 0.0,
+            max_alpha_difference: 0.0,
+            max_differing_pixels: 0.0,
+        }
+    }
+}
+
+/// Result of the comparison of two images of the same size.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DiffStats {
+    /// Number of pixels that differ beyond the tolerances.
+    pub differing_pixels: usize,
+    /// Total number of pixels.
+    pub total_pixels: usize,
+    /// Largest color difference found (CIEDE2000).
+    pub max_color_difference: f32,
+}
+
+impl DiffStats {
+    /// Returns the fraction of pixels that differ.
+    pub fn differing_fraction(&self) -> f64 {
+        if self.total_pixels == 0 {
+            0.0
+        } else {
+            self.differing_pixels as f64 / self.total_pixels as f64
+        }
+    }
+}
+
+/// Pixels of an image in unpremultiplied RGBA8 format.
+struct Pixels {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+impl Pixels {
+    fn read(image: &sk::Image) -> Option<Pixels> {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+        let info = sk::ImageInfo::new(
+            (image.width(), image.height()),
+            sk::ColorType::RGBA8888,
+            sk::AlphaType::Unpremul,
+            None,
+        );
+        let mut data = vec![0u8; width * height * 4];
+        if !image.read_pixels(&info, &mut data, width * 4, (0, 0), sk::image::CachingHint::Disallow) {
+            return None;
+        }
+        Some(Pixels { width, height, data })
+    }
+
+    fn to_image(&self) -> sk::Image {
+        let info = sk::ImageInfo::new(
+            (self.width as i32, self.height as i32),
+            sk::ColorType::RGBA8888,
+            sk::AlphaType::Unpremul,
+            None,
+        );
+        sk::Image::from_raster_data(&info, sk::Data::new_copy(&self.data), self.width * 4)
+            .expect("failed to create raster image")
+    }
+}
+
+fn to_lab(rgba: &[u8]) -> Lab {
+    let srgb = Srgb::new(rgba[0], rgba[1], rgba[2]).into_format::<f32>();
+    Lab::from_color(srgb.into_linear())
+}
+
+/// Compares two images of the same size, and returns the statistics and an image highlighting the differences.
+///
+/// In the diff image, differing pixels are drawn in red (brighter means larger difference) over a faded
+/// grayscale version of `expected`.
+fn diff_images(actual: &Pixels, expected: &Pixels, tolerance: &DiffTolerance) -> (DiffStats, Pixels) {
+    let mut stats = DiffStats {
+        differing_pixels: 0,
+        total_pixels: actual.width * actual.height,
+        max_color_difference: 0.0,
+    };
+    let mut diff = Pixels {
+        width: actual.width,
+        height: actual.height,
+        data: vec![0u8; actual.data.len()],
+    };
+
+    for ((a, e), d) in actual
+        .data
+        .chunks_exact(4)
+        .zip(expected.data.chunks_exact(4))
+        .zip(diff.data.chunks_exact_mut(4))
+    {
+        let color_difference = if a[..3] == e[..3] {
+            0.0
+        } else {
+            to_lab(a).get_color_difference(&to_lab(e))
+        };
+        let alpha_difference = (a[3] as f32 - e[3] as f32).abs() / 255.0;
+        stats.max_color_difference = stats.max_color_difference.max(color_difference);
+
+        let differs =
+            color_difference > tolerance.max_color_difference || alpha_difference > tolerance.max_alpha_difference;
+        if differs {
+            stats.differing_pixels += 1;
+            let intensity = (128.0 + 127.0 * (color_difference / 100.0).max(alpha_difference).min(1.0)) as u8;
+            d.copy_from_slice(&[intensity, 0, 0, 255]);
+        } else {
+            let luma = (0.299 * e[0] as f32 + 0.587 * e[1] as f32 + 0.114 * e[2] as f32) as u8;
+            let faded = 192 + luma / 4;
+            d.copy_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+
+    (stats, diff)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Snapshot testing
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Errors returned by `SnapshotTester::check`.
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("snapshot `{name}` differs from its golden ({} of {} pixels, max color difference {:.2}); diff image written to `{}`",
+        .stats.differing_pixels, .stats.total_pixels, .stats.max_color_difference, .diff_path.display())]
+    Mismatch {
+        name: String,
+        stats: DiffStats,
+        /// Path of the rendering.
+        actual_path: PathBuf,
+        /// Path of the image highlighting the differences.
+        diff_path: PathBuf,
+    },
+    #[error("snapshot `{name}` has size {actual:?}, but its golden has size {expected:?}; rendering written to `{}`", .actual_path.display())]
+    SizeMismatch {
+        name: String,
+        actual: SizeI,
+        expected: SizeI,
+        actual_path: PathBuf,
+    },
+    #[error("snapshot `{name}` has no golden at `{}`; rendering written to `{}`", .golden_path.display(), .actual_path.display())]
+    MissingGolden {
+        name: String,
+        golden_path: PathBuf,
+        actual_path: PathBuf,
+    },
+    #[error("failed to decode image `{}`", .0.display())]
+    Decode(PathBuf),
+    #[error("failed to encode image `{}`", .0.display())]
+    Encode(PathBuf),
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+}
+
+/// Successful outcome of `SnapshotTester::check`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SnapshotOutcome {
+    /// The rendering matches the golden, within the tolerances.
+    Matched(DiffStats),
+    /// The golden has been written (update mode).
+    Updated,
+}
+
+/// Renders named widget scenarios and compares them against golden images stored in a directory.
+///
+/// The golden of scenario `name` is stored at `<golden dir>/<name>.png`. When a rendering doesn't match,
+/// the rendering and a diff image are written to the output directory (`<golden dir>/failures` by default) as
+/// `<name>.actual.png` and `<name>.diff.png`.
+///
+/// In update mode (enabled by `update_goldens` or by setting the `KYUTE_UPDATE_SNAPSHOTS` environment variable),
+/// renderings are written as the new goldens instead of being compared.
+pub struct SnapshotTester {
+    golden_dir: PathBuf,
+    output_dir: PathBuf,
+    env: Environment,
+    scale_factor: f64,
+    tolerance: DiffTolerance,
+    update_goldens: bool,
+    renderer: RefCell<Option<OffscreenRenderer>>,
+}
+
+impl SnapshotTester {
+    /// Creates a tester with goldens in the specified directory.
+    ///
+    /// The scenarios are rendered at scale factor 1 in the default style, ignoring the accessibility preferences
+    /// of the system, so that renderings don't depend on the machine running the tests.
+    pub fn new(golden_dir: impl Into<PathBuf>) -> SnapshotTester {
+        let golden_dir = golden_dir.into();
+        SnapshotTester {
+            output_dir: golden_dir.join("failures"),
+            golden_dir,
+            env: base_environment(),
+            scale_factor: 1.0,
+            tolerance: DiffTolerance::default(),
+            update_goldens: env::var_os(UPDATE_SNAPSHOTS_VAR).is_some(),
+            renderer: Default::default(),
+        }
+    }
+
+    /// Sets the directory where renderings and diff images of failed scenarios are written.
+    pub fn output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = output_dir.into();
+        self
+    }
+
+    /// Merges the specified environment into the one used to compose the scenarios.
+    pub fn env(mut self, env: Environment) -> Self {
+        self.env = self.env.merged(env);
+        self
+    }
+
+    /// Sets the scale factor of the renderings.
+    pub fn scale_factor(mut self, scale_factor: f64) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
+    /// Sets the tolerances used to compare renderings with goldens.
+    pub fn tolerance(mut self, tolerance: DiffTolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Whether to overwrite the goldens with the renderings instead of comparing them.
+    pub fn update_goldens(mut self, update_goldens: bool) -> Self {
+        self.update_goldens = update_goldens;
+        self
+    }
+
+    /// Returns the path of the golden of a scenario.
+    pub fn golden_path(&self, name: &str) -> PathBuf {
+        self.golden_dir.join(format!("{}.png", name))
+    }
+
+    /// Renders a scenario and returns the image.
+    pub fn render<W: Widget + 'static>(&self, size: Size, ui: impl Fn() -> W) -> sk::Image {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer
+            .get_or_insert_with(OffscreenRenderer::new)
+            .render(&self.env, size, self.scale_factor, ui)
+    }
+
+    /// Renders a scenario in a box of the specified size, and compares it with its golden.
+    pub fn check<W: Widget + 'static>(
+        &self,
+        name: &str,
+        size: Size,
+        ui: impl Fn() -> W,
+    ) -> Result<SnapshotOutcome, SnapshotError> {
+        let image = self.render(size, ui);
+        self.check_image(name, &image)
+    }
+
+    /// Compares an image rendered by other means with the golden of a scenario (e.g. a contact sheet of
+    /// `gallery::Gallery`).
+    pub fn check_image(&self, name: &str, image: &sk::Image) -> Result<SnapshotOutcome, SnapshotError> {
+        let golden_path = self.golden_path(name);
+
+        if self.update_goldens {
+            write_png(image, &golden_path)?;
+            return Ok(SnapshotOutcome::Updated);
+        }
+
+        let actual_path = self.output_dir.join(format!("{}.actual.png", name));
+        if !golden_path.exists() {
+            write_png(image, &actual_path)?;
+            return Err(SnapshotError::MissingGolden {
+                name: name.to_string(),
+                golden_path,
+                actual_path,
+            });
+        }
+
+        let golden = read_png(&golden_path)?;
+        let actual_size = SizeI::new(image.width(), image.height());
+        let expected_size = SizeI::new(golden.width(), golden.height());
+        if actual_size != expected_size {
+            write_png(image, &actual_path)?;
+            return Err(SnapshotError::SizeMismatch {
+                name: name.to_string(),
+                actual: actual_size,
+                expected: expected_size,
+                actual_path,
+            });
+        }
+
+        let actual = Pixels::read(image).ok_or_else(|| SnapshotError::Decode(actual_path.clone()))?;
+        let expected = Pixels::read(&golden).ok_or_else(|| SnapshotError::Decode(golden_path.clone()))?;
+        let (stats, diff) = diff_images(&actual, &expected, &self.tolerance);
+        if stats.differing_fraction() <= self.tolerance.max_differing_pixels {
+            return Ok(SnapshotOutcome::Matched(stats));
+        }
+
+        let diff_path = self.output_dir.join(format!("{}.diff.png", name));
+        write_png(image, &actual_path)?;
+        write_png(&diff.to_image(), &diff_path)?;
+        Err(SnapshotError::Mismatch {
+            name: name.to_string(),
+            stats,
+            actual_path,
+            diff_path,
+        })
+    }
+
+    /// Like `check`, but panics if the rendering doesn't match its golden. Intended for use in tests.
+    #[track_caller]
+    pub fn assert_matches<W: Widget + 'static>(&self, name: &str, size: Size, ui: impl Fn() -> W) {
+        if let Err(err) = self.check(name, size, ui) {
+            panic!("{}", err)
+        }
+    }
+}
+
+fn read_png(path: &Path) -> Result<sk::Image, SnapshotError> {
+    let bytes = fs::read(path)?;
+    sk::Image::from_encoded(sk::Data::new_copy(&bytes)).ok_or_else(|| SnapshotError::Decode(path.to_path_buf()))
+}
+
+pub(crate) fn write_png(image: &sk::Image, path: &Path) -> Result<(), SnapshotError> {
+    let data = image
+        .encode_to_data(sk::EncodedImageFormat::PNG)
+        .ok_or_else(|| SnapshotError::Encode(path.to_path_buf()))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: usize, height: usize, rgba: [u8; 4]) -> Pixels {
+        Pixels {
+            width,
+            height,
+            data: rgba.iter().copied().cycle().take(width * height * 4).collect(),
+        }
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let a = solid(4, 4, [10, 20, 30, 255]);
+        let (stats, _) = diff_images(&a, &a, &DiffTolerance::exact());
+        assert_eq!(stats.differing_pixels, 0);
+        assert_eq!(stats.total_pixels, 16);
+    }
+
+    #[test]
+    fn imperceptible_differences_are_tolerated() {
+        let a = solid(4, 4, [100, 100, 100, 255]);
+        let b = solid(4, 4, [101, 100, 100, 255]);
+        let (stats, _) = diff_images(&a, &b, &DiffTolerance::default());
+        assert_eq!(stats.differing_pixels, 0);
+        let (stats, _) = diff_images(&a, &b, &DiffTolerance::exact());
+        assert_eq!(stats.differing_pixels, 16);
+    }
+
+    #[test]
+    fn visible_differences_are_reported() {
+        let a = solid(2, 2, [255, 255, 255, 255]);
+        let b = solid(2, 2, [255, 0, 0, 255]);
+        let (stats, diff) = diff_images(&a, &b, &DiffTolerance::default());
+        assert_eq!(stats.differing_pixels, 4);
+        assert!(stats.max_color_difference > 10.0);
+        assert_eq!(diff.data[1..4], [0, 0, 255]);
+    }
+}
@@ -1,5 +1,8 @@
 //! Parser utilities for box styles.
-use crate::{css::parse_css_length_percentage, Atom, EnvKey, LengthOrPercentage};
+use crate::{
+    color::{self, ColorInterpolation, ColorSpace, HueInterpolation},
+    Atom,
+};
 use cssparser::{ParseError, Parser, Token};
 use kyute::Environment;
 use std::f32::consts::PI;
@@ -13,6 +16,20 @@ pub enum Color {
     Value(crate::Color),
     /// Color in environment,
     Env(Atom),
+    /// Mix of two colors (`color-mix()`), computed once the colors are known.
+    Mix(Box<ColorMix>),
+}
+
+/// Arguments of `color-mix()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorMix {
+    pub interpolation: ColorInterpolation,
+    pub first: Color,
+    pub second: Color,
+    /// Proportion of the second color, between 0.0 and 1.0.
+    pub t: f32,
+    /// Multiplier of the alpha of the result, less than 1.0 if the percentages add up to less than 100%.
+    pub alpha: f32,
 }
 
 impl Default for Color {
@@ -34,6 +51,15 @@ impl Color {
                     crate::Color::default()
                 }
             }
+            Color::Mix(ref mix) => {
+                let color = color::mix(
+                    mix.first.compute(env),
+                    mix.second.compute(env),
+                    mix.t,
+                    mix.interpolation,
+                );
+                color.with_alpha(color.alpha() * mix.alpha)
+            }
         }
     }
 }
@@ -42,76 +68,228 @@ impl Color {
 // colors
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Parses a color component: a number, a percentage of `percent_ref`, or `none` (zero).
+fn component<'i>(input: &mut Parser<'i, '_>, percent_ref: f32) -> Result<f32, ParseError<'i, ()>> {
+    let location = input.current_source_location();
+    match input.next()? {
+        Token::Number { value, .. } => Ok(*value),
+        Token::Percentage { unit_value, .. } => Ok(*unit_value * percent_ref),
+        Token::Ident(ident) if ident.eq_ignore_ascii_case("none") => Ok(0.0),
+        t => Err(location.new_unexpected_token_error(t.clone())),
+    }
+}
+
+/// Parses a hue, in degrees.
+fn hue<'i>(input: &mut Parser<'i, '_>) -> Result<f32, ParseError<'i, ()>> {
+    let location = input.current_source_location();
+    match input.next()? {
+        Token::Number { value, .. } => Ok(*value),
+        Token::Dimension { value, unit, .. } => match &**unit {
+            "deg" => Ok(*value),
+            "grad" => Ok(*value * 360. / 400.),
+            "rad" => Ok(*value * 360. / (2. * PI)),
+            "turn" => Ok(*value * 360.),
+            _ => Err(location.new_unexpected_token_error(Token::Ident(unit.clone()))),
+        },
+        Token::Ident(ident) if ident.eq_ignore_ascii_case("none") => Ok(0.0),
+        t => Err(location.new_unexpected_token_error(t.clone())),
+    }
+}
+
+fn alpha_value<'i>(input: &mut Parser<'i, '_>) -> Result<f32, ParseError<'i, ()>> {
+    Ok(component(input, 1.0)?.clamp(0.0, 1.0))
+}
+
+/// Parses the optional alpha of a color function in the modern syntax (`/ <alpha>`).
 fn alpha<'i>(input: &mut Parser<'i, '_>) -> Result<f32, ParseError<'i, ()>> {
     if !input.is_exhausted() {
         input.expect_delim('/')?;
-        let location = input.current_source_location();
-        let alpha = match input.next()? {
-            Token::Number { value, .. } => *value / 255.0,
-            Token::Percentage { unit_value, .. } => *unit_value,
-            t => return Err(location.new_unexpected_token_error(t.clone())),
-        };
-        Ok(alpha)
+        alpha_value(input)
     } else {
         Ok(1.0)
     }
 }
 
-fn rgb_color<'i>(input: &mut Parser<'i, '_>) -> Result<Color, ParseError<'i, ()>> {
-    let location = input.current_source_location();
-    let (r, is_number) = match input.next()? {
-        Token::Number { value, .. } => (*value / 255.0, true),
-        Token::Percentage { unit_value, .. } => (*unit_value, false),
-        t => return Err(location.new_unexpected_token_error(t.clone())),
-    };
-
-    let g;
-    let b;
-    if is_number {
-        g = input.expect_number()? / 255.0;
-        b = input.expect_number()? / 255.0;
-    } else {
-        g = input.expect_percentage()?;
-        b = input.expect_percentage()?;
+/// Parses the three components and the alpha of a color function.
+///
+/// Components are separated by spaces, with an optional alpha after a slash (e.g. `rgb(255 0 0 / 50%)`). If
+/// `legacy` is true, the components and the alpha may also be separated by commas (e.g. `rgba(255, 0, 0, 0.5)`).
+fn components<'i>(
+    input: &mut Parser<'i, '_>,
+    legacy: bool,
+    mut parse_component: impl FnMut(usize, &mut Parser<'i, '_>) -> Result<f32, ParseError<'i, ()>>,
+) -> Result<([f32; 3], f32), ParseError<'i, ()>> {
+    let c0 = parse_component(0, input)?;
+    let commas = legacy && input.try_parse(Parser::expect_comma).is_ok();
+    let c1 = parse_component(1, input)?;
+    if commas {
+        input.expect_comma()?;
     }
-
-    let alpha = alpha(input)?;
+    let c2 = parse_component(2, input)?;
+    let alpha = if commas {
+        if input.try_parse(Parser::expect_comma).is_ok() {
+            alpha_value(input)?
+        } else {
+            1.0
+        }
+    } else {
+        alpha(input)?
+    };
     input.expect_exhausted()?;
+    Ok(([c0, c1, c2], alpha))
+}
 
+fn rgb_color<'i>(input: &mut Parser<'i, '_>) -> Result<Color, ParseError<'i, ()>> {
+    let ([r, g, b], alpha) = components(input, true, |_, input| {
+        Ok((component(input, 255.0)? / 255.0).clamp(0.0, 1.0))
+    })?;
     Ok(Color::Value(crate::Color::new(r, g, b, alpha)))
 }
 
 fn hsl_color<'i>(input: &mut Parser<'i, '_>) -> Result<Color, ParseError<'i, ()>> {
-    let location = input.current_source_location();
-    let hue_degrees = match input.next()? {
-        Token::Number { value, .. } => *value,
-        Token::Dimension { value, unit, .. } => match &**unit {
-            "deg" => *value,
-            "grad" => *value * 360. / 400.,
-            "rad" => *value * 360. / (2. * PI),
-            "turn" => *value * 360.,
-            _ => return Err(location.new_unexpected_token_error(Token::Ident(unit.clone()))),
-        },
-        t => return Err(location.new_unexpected_token_error(t.clone())),
-    };
-
-    let saturation = input.expect_percentage()?;
-    let brightness = input.expect_percentage()?;
-    let alpha = alpha(input)?;
-    input.expect_exhausted()?;
+    let ([hue_degrees, saturation, lightness], alpha) = components(input, true, |i, input| {
+        if i == 0 {
+            hue(input)
+        } else {
+            Ok(component(input, 100.0)? / 100.0)
+        }
+    })?;
     Ok(Color::Value(crate::Color::hsla(
         hue_degrees,
         saturation,
-        brightness,
+        lightness,
         alpha,
     )))
 }
 
+/// Parses the arguments of the functions of CSS Color 4 that specify a color in a color space other than sRGB.
+///
+/// `percent_refs` are the values corresponding to 100% for each component; the hue, if any, is the first
+/// component in `hwb()` and the last in `lch()` and `oklch()`.
+fn space_color<'i>(
+    input: &mut Parser<'i, '_>,
+    space: ColorSpace,
+    percent_refs: [f32; 3],
+) -> Result<Color, ParseError<'i, ()>> {
+    let hue_index = match space {
+        ColorSpace::Hwb => Some(0),
+        ColorSpace::Lch | ColorSpace::Oklch => Some(2),
+        _ => None,
+    };
+    let (mut c, alpha) = components(input, false, |i, input| {
+        if Some(i) == hue_index {
+            hue(input)
+        } else {
+            component(input, percent_refs[i])
+        }
+    })?;
+    if space == ColorSpace::Hwb {
+        // whiteness and blackness are percentages, or numbers in the same unit
+        c[1] /= 100.0;
+        c[2] /= 100.0;
+    }
+    Ok(Color::Value(space.color_from_components(c, alpha)))
+}
+
+/// Parses the arguments of `color()`: a color space and three components between 0.0 and 1.0.
+fn predefined_color<'i>(input: &mut Parser<'i, '_>) -> Result<Color, ParseError<'i, ()>> {
+    let location = input.current_source_location();
+    let name = input.expect_ident()?.clone();
+    let space = match ColorSpace::from_name(&name) {
+        Some(space @ (ColorSpace::Srgb | ColorSpace::SrgbLinear | ColorSpace::XyzD50 | ColorSpace::XyzD65)) => space,
+        _ => return Err(location.new_unexpected_token_error(Token::Ident(name))),
+    };
+    let (c, alpha) = components(input, false, |_, input| component(input, 1.0))?;
+    Ok(Color::Value(space.color_from_components(c, alpha)))
+}
+
+/// Parses a `<color-interpolation-method>`: `in <color-space> [<hue-interpolation-method> hue]?`.
+pub(crate) fn color_interpolation_method<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<ColorInterpolation, ParseError<'i, ()>> {
+    input.expect_ident_matching("in")?;
+    let location = input.current_source_location();
+    let name = input.expect_ident()?.clone();
+    let space =
+        ColorSpace::from_name(&name).ok_or_else(|| location.new_unexpected_token_error(Token::Ident(name.clone())))?;
+
+    let hue = if space.is_polar() {
+        input
+            .try_parse(|input| {
+                let location = input.current_source_location();
+                let method = input.expect_ident()?.clone();
+                let method = match &*method {
+                    "shorter" => HueInterpolation::Shorter,
+                    "longer" => HueInterpolation::Longer,
+                    "increasing" => HueInterpolation::Increasing,
+                    "decreasing" => HueInterpolation::Decreasing,
+                    _ => return Err(location.new_unexpected_token_error(Token::Ident(method))),
+                };
+                input.expect_ident_matching("hue")?;
+                Ok(method)
+            })
+            .unwrap_or_default()
+    } else {
+        HueInterpolation::Shorter
+    };
+
+    Ok(ColorInterpolation { space, hue })
+}
+
+/// Parses a color of `color-mix()`, followed or preceded by an optional percentage.
+fn mix_component<'i>(input: &mut Parser<'i, '_>) -> Result<(Color, Option<f32>), ParseError<'i, ()>> {
+    if let Ok(percentage) = input.try_parse(Parser::expect_percentage) {
+        Ok((css_color(input)?, Some(percentage)))
+    } else {
+        let color = css_color(input)?;
+        Ok((color, input.try_parse(Parser::expect_percentage).ok()))
+    }
+}
+
+/// Parses the arguments of `color-mix()`.
+///
+/// See https://www.w3.org/TR/css-color-5/#color-mix
+fn color_mix<'i>(input: &mut Parser<'i, '_>) -> Result<Color, ParseError<'i, ()>> {
+    let interpolation = color_interpolation_method(input)?;
+    input.expect_comma()?;
+    let (first, p1) = mix_component(input)?;
+    input.expect_comma()?;
+    let (second, p2) = mix_component(input)?;
+    input.expect_exhausted()?;
+
+    // missing percentages are the complement of the other one; then, percentages are normalized to add up to 100%
+    let (p1, p2) = match (p1, p2) {
+        (None, None) => (0.5, 0.5),
+        (Some(p1), None) => (p1, 1.0 - p1),
+        (None, Some(p2)) => (1.0 - p2, p2),
+        (Some(p1), Some(p2)) => (p1, p2),
+    };
+    let sum = p1 + p2;
+    if p1 < 0.0 || p2 < 0.0 || sum <= 0.0 {
+        return Err(input.new_custom_error(()));
+    }
+
+    Ok(Color::Mix(Box::new(ColorMix {
+        interpolation,
+        first,
+        second,
+        t: p2 / sum,
+        alpha: sum.min(1.0),
+    })))
+}
+
 fn color_function<'i>(name: &str, input: &mut Parser<'i, '_>) -> Result<Color, ParseError<'i, ()>> {
     let location = input.current_source_location();
     match name {
-        "rgb" => rgb_color(input),
-        "hsl" => hsl_color(input),
+        "rgb" | "rgba" => rgb_color(input),
+        "hsl" | "hsla" => hsl_color(input),
+        "hwb" => space_color(input, ColorSpace::Hwb, [1.0, 100.0, 100.0]),
+        "lab" => space_color(input, ColorSpace::Lab, [100.0, 125.0, 125.0]),
+        "lch" => space_color(input, ColorSpace::Lch, [100.0, 150.0, 1.0]),
+        "oklab" => space_color(input, ColorSpace::Oklab, [1.0, 0.4, 0.4]),
+        "oklch" => space_color(input, ColorSpace::Oklch, [1.0, 0.4, 1.0]),
+        "color" => predefined_color(input),
+        "color-mix" => color_mix(input),
         _ => Err(location.new_unexpected_token_error(Token::Ident(name.to_owned().into()))),
     }
 }
@@ -122,7 +300,7 @@ pub(crate) fn css_color<'i>(input: &mut Parser<'i, '_>) -> Result<Color, ParseEr
         Token::Function(ref name) => {
             let name = name.clone();
             input.parse_nested_block(|input| {
-                let color = color_function(&*name, input)?;
+                let color = color_function(&name.to_ascii_lowercase(), input)?;
                 Ok(color)
             })
         }
@@ -135,6 +313,58 @@ pub(crate) fn css_color<'i>(input: &mut Parser<'i, '_>) -> Result<Color, ParseEr
             Ok(color) => Ok(Color::Value(color)),
             Err(_) => Err(location.new_unexpected_token_error(t.clone())),
         },
+        // named colors, and `transparent`
+        t @ Token::Ident(ref name) => match cssparser::parse_color_keyword(name) {
+            Ok(cssparser::Color::RGBA(rgba)) => Ok(Color::Value(crate::Color::from_rgba_u8(
+                rgba.red, rgba.green, rgba.blue, rgba.alpha,
+            ))),
+            _ => Err(location.new_unexpected_token_error(t.clone())),
+        },
         t => Err(location.new_unexpected_token_error(t.clone())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_from_str;
+
+    fn parse(css: &str) -> crate::Color {
+        parse_from_str(css, css_color).unwrap().compute(&Environment::new())
+    }
+
+    fn assert_same(a: crate::Color, b: crate::Color) {
+        let (a, b) = (a.to_rgba(), b.to_rgba());
+        let close = |x: f32, y: f32| (x - y).abs() < 2e-3;
+        assert!(
+            close(a.0, b.0) && close(a.1, b.1) && close(a.2, b.2) && close(a.3, b.3),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn css_colors() {
+        let red = crate::Color::new(1.0, 0.0, 0.0, 1.0);
+        assert_same(parse("rgb(255 0 0)"), red);
+        assert_same(parse("rgba(255, 0, 0, 0.5)"), red.with_alpha(0.5));
+        assert_same(parse("rgb(100% 0% 0% / 50%)"), red.with_alpha(0.5));
+        assert_same(parse("hsl(0deg 100% 50%)"), red);
+        assert_same(parse("hwb(0 0% 0%)"), red);
+        assert_same(parse("red"), red);
+        assert_same(parse("lab(54.29 80.8 69.89)"), red);
+        assert_same(parse("oklch(62.8% 0.2577 29.23)"), red);
+        assert_same(parse("color(srgb 1 0 none)"), red);
+        assert_same(
+            parse("color-mix(in srgb, red, blue)"),
+            crate::Color::new(0.5, 0.0, 0.5, 1.0),
+        );
+        assert_same(
+            parse("color-mix(in srgb, red 20%, blue 20%)"),
+            crate::Color::new(0.5, 0.0, 0.5, 0.4),
+        );
+        assert!(parse_from_str("rgb(255, 0 0)", css_color).is_err());
+        assert!(parse_from_str("lab(50, 0, 0)", css_color).is_err());
+    }
+}
@@ -1,5 +1,11 @@
 //! Description of paints.
-use crate::{css::parse_from_str, drawing, style, style::color::css_color, Color, EnvKey, Environment, UnitExt};
+use crate::{
+    color::ColorInterpolation,
+    css::parse_from_str,
+    drawing, style,
+    style::color::{color_interpolation_method, css_color},
+    Color, EnvKey, Environment, UnitExt,
+};
 use cssparser::{ParseError, Parser, Token};
 use kyute_common::Angle;
 use std::{convert::TryFrom, f32::consts::PI};
@@ -26,6 +32,8 @@ pub struct LinearGradient {
     pub angle: Angle,
     /// List of color stops.
     pub stops: Vec<ColorStop>,
+    /// How colors are interpolated between stops.
+    pub interpolation: ColorInterpolation,
 }
 
 impl LinearGradient {
@@ -40,6 +48,7 @@ impl LinearGradient {
                     color: stop.color.compute(env),
                 })
                 .collect(),
+            interpolation: self.interpolation,
         }
     }
 }
@@ -182,12 +191,16 @@ fn color_stop<'i>(input: &mut Parser<'i, '_>) -> Result<ColorStop, ParseError<'i
 fn linear_gradient<'i>(input: &mut Parser<'i, '_>) -> Result<LinearGradient, ParseError<'i, ()>> {
     input.expect_function_matching("linear-gradient")?;
     input.parse_nested_block(|input| {
-        let direction = if let Some(line_direction) = input.try_parse(LineDirection::parse).ok() {
+        // direction and interpolation method, in any order
+        let mut interpolation = input.try_parse(color_interpolation_method).ok();
+        let direction = input.try_parse(LineDirection::parse).ok();
+        if interpolation.is_none() {
+            interpolation = input.try_parse(color_interpolation_method).ok();
+        }
+        if direction.is_some() || interpolation.is_some() {
             input.expect_comma()?;
-            line_direction
-        } else {
-            LineDirection { angle: 180.0 }
-        };
+        }
+        let direction = direction.unwrap_or(LineDirection { angle: 180.0 });
 
         let mut stops = Vec::new();
         stops.push(color_stop(input)?);
@@ -199,6 +212,7 @@ fn linear_gradient<'i>(input: &mut Parser<'i, '_>) -> Result<LinearGradient, Par
         Ok(LinearGradient {
             angle: direction.angle.degrees(),
             stops,
+            interpolation: interpolation.unwrap_or_default(),
         })
     })
 }
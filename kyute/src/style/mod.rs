@@ -2,9 +2,10 @@
 
 use crate::{css, drawing, theme, LayoutParams};
 use bitflags::bitflags;
-use cssparser::{parse_one_declaration, ParseError, Parser, Token};
+use cssparser::{parse_one_declaration, ParseError, ParseErrorKind, Parser, Token};
 use once_cell::sync::Lazy;
 use std::{convert::TryFrom, sync::Arc};
+use thiserror::Error;
 
 mod border;
 mod box_shadow;
@@ -13,19 +14,21 @@ mod image;
 mod length;
 mod predicate;
 mod shape;
+mod stylesheet;
 mod utils;
 
 pub use crate::drawing::vector_icon::VectorIcon;
 use crate::{css::parse_from_str, drawing::Paint, style::predicate::parse_optional_predicate_block};
 pub use border::Border;
 pub use box_shadow::{BoxShadow, BoxShadows};
-pub use color::Color;
+pub use color::{Color, ColorMix};
 pub use image::Image;
 use kyute::Environment;
 use kyute_common::Atom;
 pub use length::{Length, LengthOrPercentage, UnitExt};
 use predicate::{parse_predicate, Predicate, Pseudoclass};
 pub use shape::Shape;
+pub use stylesheet::{with_stylesheet, StyleSheet, StyleSheetLoadError, STYLESHEET};
 
 bitflags! {
     /// Encodes the active states of a widgets.
@@ -52,6 +55,8 @@ bitflags! {
     }
 }
 
+impl_env_value!(WidgetState);
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Computed values
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -266,6 +271,7 @@ struct StyleInner {
     declarations: Vec<PredicatedPropertyDeclaration>,
 }
 
+#[derive(Clone)]
 struct PredicatedPropertyDeclaration {
     predicate: Option<Arc<Predicate>>,
     declaration: PropertyDeclaration,
@@ -296,6 +302,21 @@ impl Style {
     pub fn variant_states(&self) -> WidgetState {
         self.0.variant_states
     }
+
+    /// Returns a style with the declarations of this style followed by those of `other`.
+    pub(crate) fn merged(&self, other: &Style) -> Style {
+        let declarations = self
+            .0
+            .declarations
+            .iter()
+            .chain(other.0.declarations.iter())
+            .cloned()
+            .collect();
+        Style(Arc::new(StyleInner {
+            variant_states: self.0.variant_states | other.0.variant_states,
+            declarations,
+        }))
+    }
 }
 
 fn parse_property_remainder<'i, T, F, E>(input: &mut Parser<'i, '_>, f: F) -> Result<T, ParseError<'i, E>>
@@ -461,14 +482,34 @@ impl Style {
     }
 }
 
+/// Error returned when a style fails to parse.
+#[derive(Clone, Debug, Error)]
+#[error("CSS error at {line}:{column}: {message}")]
+pub struct StyleError {
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl<'i, E> From<ParseError<'i, E>> for StyleError {
+    fn from(err: ParseError<'i, E>) -> Self {
+        let message = match err.kind {
+            ParseErrorKind::Basic(kind) => format!("{:?}", kind),
+            ParseErrorKind::Custom(_) => "unrecognized property or value".to_string(),
+        };
+        StyleError {
+            line: err.location.line,
+            column: err.location.column,
+            message,
+        }
+    }
+}
+
 /// From CSS value.
 impl TryFrom<&str> for Style {
-    type Error = ();
-    fn try_from(css: &str) -> Result<Self, ()> {
-        Style::parse(css).map_err(|err| {
-            warn!("CSS syntax error: {:?}", err);
-            ()
-        })
+    type Error = StyleError;
+    fn try_from(css: &str) -> Result<Self, StyleError> {
+        Ok(Style::parse(css)?)
     }
 }
 
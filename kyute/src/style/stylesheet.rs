@@ -0,0 +1,131 @@
+//! Stylesheets: style rules applied to widgets by class name.
+use crate::{
+    cache, composable,
+    css::parse_from_str,
+    style::{Style, StyleError},
+    util::fs_watch::watch_path,
+    EnvKey, Environment,
+};
+use cssparser::{ParseError, Parser};
+use std::{fs, io, path::Path, sync::Arc};
+use thiserror::Error;
+
+/// The stylesheet used to resolve the classes of widgets (see `WidgetExt::class`).
+pub const STYLESHEET: EnvKey<StyleSheet> = builtin_env_key!("kyute.stylesheet");
+
+/// A style rule: a style applied to the widgets with any of the specified classes.
+struct Rule {
+    classes: Vec<String>,
+    style: Style,
+}
+
+/// A list of style rules, selected by class name:
+///
+///     .sidebar {
+///         background: #333;
+///         padding: 4px;
+///     }
+///
+///     .sidebar, .panel {
+///         [:hover] { background: #444; }
+///     }
+///
+/// The contents of the rules use the same syntax as `Style`.
+#[derive(Clone, Default)]
+pub struct StyleSheet(Arc<Vec<Rule>>);
+
+impl_env_value!(StyleSheet);
+
+/// Error returned when a stylesheet fails to load.
+#[derive(Debug, Error)]
+pub enum StyleSheetLoadError {
+    #[error("could not read the stylesheet")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] StyleError),
+}
+
+/// Parses a comma-separated list of class selectors (`.a, .b`).
+fn parse_selectors<'i>(input: &mut Parser<'i, '_>) -> Result<Vec<String>, ParseError<'i, ()>> {
+    let mut classes = Vec::new();
+    loop {
+        input.expect_delim('.')?;
+        classes.push(input.expect_ident()?.to_string());
+        if input.try_parse(|input| input.expect_comma()).is_err() {
+            break;
+        }
+    }
+    Ok(classes)
+}
+
+fn parse_rules<'i>(input: &mut Parser<'i, '_>) -> Result<Vec<Rule>, ParseError<'i, ()>> {
+    let mut rules = Vec::new();
+    while !input.is_exhausted() {
+        let classes = parse_selectors(input)?;
+        input.expect_curly_bracket_block()?;
+        let style = input.parse_nested_block(Style::parse_impl)?;
+        rules.push(Rule { classes, style });
+    }
+    Ok(rules)
+}
+
+impl StyleSheet {
+    /// Creates an empty stylesheet.
+    pub fn new() -> StyleSheet {
+        StyleSheet::default()
+    }
+
+    /// Parses a stylesheet.
+    pub fn parse(css: &str) -> Result<StyleSheet, StyleError> {
+        let rules = parse_from_str(css, parse_rules)?;
+        Ok(StyleSheet(Arc::new(rules)))
+    }
+
+    /// Loads a stylesheet from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<StyleSheet, StyleSheetLoadError> {
+        let css = fs::read_to_string(path)?;
+        Ok(StyleSheet::parse(&css)?)
+    }
+
+    /// Loads a stylesheet from a file, and reloads it when the file changes.
+    ///
+    /// The scope calling this function is recomposed when the file changes, so that widgets are restyled with
+    /// the new version of the stylesheet. If the file fails to load, an error is emitted and the last version that
+    /// loaded successfully is returned (an empty stylesheet if there isn't any).
+    #[composable]
+    pub fn watch(path: impl AsRef<Path>) -> StyleSheet {
+        let path = path.as_ref();
+        let changed = watch_path(path);
+        let stylesheet = cache::state(|| None::<StyleSheet>);
+        if changed || stylesheet.get().is_none() {
+            match StyleSheet::load(path) {
+                Ok(s) => stylesheet.set_without_invalidation(Some(s)),
+                Err(err) => {
+                    error!("failed to load stylesheet `{}`: {}", path.display(), err);
+                    if stylesheet.get().is_none() {
+                        stylesheet.set_without_invalidation(Some(StyleSheet::new()));
+                    }
+                }
+            }
+        }
+        stylesheet.get().unwrap()
+    }
+
+    /// Returns the style of widgets with the specified classes.
+    ///
+    /// `classes` is a space-separated list of class names. The styles of all the rules that match any of the
+    /// classes are combined in the order of the stylesheet: declarations of later rules take precedence.
+    pub fn class_style(&self, classes: &str) -> Style {
+        let classes = classes.split_whitespace();
+        self.0
+            .iter()
+            .filter(|rule| classes.clone().any(|class| rule.classes.iter().any(|c| c == class)))
+            .fold(Style::new(), |style, rule| style.merged(&rule.style))
+    }
+}
+
+/// Calls `f` with the specified stylesheet used to resolve widget classes.
+#[track_caller]
+pub fn with_stylesheet<R>(stylesheet: StyleSheet, f: impl FnOnce() -> R) -> R {
+    cache::with_environment(Environment::new().add(STYLESHEET, stylesheet), f)
+}
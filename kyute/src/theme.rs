@@ -1,6 +1,16 @@
 //! Environment keys that control the visual aspect (theme) of common widgets.
-use crate::{style::Style, Color, EnvKey, Environment, Font, Length, SideOffsets, UnitExt};
+use crate::{
+    animation::Interpolate,
+    style::{Style, WidgetState},
+    text::{FontWeight, TextLayoutMode},
+    Color, EnvKey, Environment, Font, Length, SideOffsets, UnitExt,
+};
+use kyute_shell::{
+    application::{AccessibilityPreferences, Application},
+    input::ScrollSettings,
+};
 use once_cell::sync::Lazy;
+use std::{sync::Arc, time::Duration};
 
 macro_rules! theme_key {
     ($name:tt) => {
@@ -23,6 +33,30 @@ pub const WINDOW_BACKGROUND_COLOR: EnvKey<Color> = theme_key!("window-background
 pub const TEXT_BACKGROUND_COLOR: EnvKey<Color> = theme_key!("text-background-color");
 pub const CONTENT_BACKGROUND_COLOR: EnvKey<Color> = theme_key!("content-background-color");
 pub const ALTERNATE_CONTENT_BACKGROUND_COLOR: EnvKey<Color> = theme_key!("alternate-content-background-color");
+/// Color of highlighted or selected elements.
+pub const ACCENT_COLOR: EnvKey<Color> = theme_key!("accent-color");
+/// Whether animations and transitions should be disabled (see `animation_duration`).
+pub const REDUCED_MOTION: EnvKey<bool> = theme_key!("reduced-motion");
+/// Whether a high-contrast theme is in use.
+pub const HIGH_CONTRAST: EnvKey<bool> = theme_key!("high-contrast");
+/// Scale factor applied to the size of text, set by the user in the OS settings.
+pub const TEXT_SCALE_FACTOR: EnvKey<f64> = theme_key!("text-scale-factor");
+/// Overrides the scroll settings of the OS (see `scroll_settings`).
+pub const SCROLL_SETTINGS: EnvKey<ScrollSettings> = theme_key!("scroll-settings");
+/// Color of less prominent text, e.g. captions and placeholders.
+pub const SECONDARY_TEXT_COLOR: EnvKey<Color> = theme_key!("secondary-text-color");
+/// Color of borders and separators.
+pub const BORDER_COLOR: EnvKey<Color> = theme_key!("border-color");
+/// Color of errors and destructive actions.
+pub const ERROR_COLOR: EnvKey<Color> = theme_key!("error-color");
+/// Tokens of the current theme (see `ThemeTokens` and `tokens`).
+pub const THEME_TOKENS: EnvKey<Arc<ThemeTokens>> = theme_key!("theme-tokens");
+/// How text is laid out and rendered. Set to `TextLayoutMode::Print` in views whose text must match print or export
+/// output exactly (e.g. the canvas of a design tool).
+pub const TEXT_LAYOUT_MODE: EnvKey<TextLayoutMode> = theme_key!("text-layout-mode");
+/// States forced on all widgets, in addition to the ones resulting from user interaction. Used to preview the
+/// appearance of widgets in each state (see `gallery`).
+pub const FORCED_WIDGET_STATE: EnvKey<WidgetState> = theme_key!("forced-widget-state");
 
 pub mod palette {
     use crate::Color;
@@ -294,25 +328,309 @@ pub mod palette {
     pub const BLUE_GREY_A700: Color = Color::from_hex("#455a64"); // #455a64;
 }
 
-static DARK_THEME: Lazy<Environment> = Lazy::new(|| {
-    let mut env = Environment::new();
-    env.set(&DARK_MODE, true);
-    env.set(&TEXT_COLOR, Color::from_hex("#c8c8c8"));
-    env.set(&WINDOW_BACKGROUND_COLOR, Color::from_hex("#515151"));
-    env.set(&TEXT_BACKGROUND_COLOR, Color::from_hex("#1e1e1e"));
-    env.set(&CONTENT_BACKGROUND_COLOR, Color::from_hex("#212121"));
-    env.set(&ALTERNATE_CONTENT_BACKGROUND_COLOR, Color::from_hex("#424242"));
+//--------------------------------------------------------------------------------------------------
+// Theme tokens
+
+/// Semantic colors of a theme.
+///
+/// Each color is also available in the environment under its own key, for use in `EnvRef`s.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorTokens {
+    /// Background of windows (`WINDOW_BACKGROUND_COLOR`).
+    pub window_background: Color,
+    /// Background of text fields (`TEXT_BACKGROUND_COLOR`).
+    pub text_background: Color,
+    /// Background of content areas, e.g. lists and tables (`CONTENT_BACKGROUND_COLOR`).
+    pub content_background: Color,
+    /// Background of every other row of lists and tables (`ALTERNATE_CONTENT_BACKGROUND_COLOR`).
+    pub alternate_content_background: Color,
+    /// Text (`TEXT_COLOR`).
+    pub text: Color,
+    /// Less prominent text (`SECONDARY_TEXT_COLOR`).
+    pub secondary_text: Color,
+    /// Highlighted or selected elements (`ACCENT_COLOR`).
+    pub accent: Color,
+    /// Borders and separators (`BORDER_COLOR`).
+    pub border: Color,
+    /// Errors and destructive actions (`ERROR_COLOR`).
+    pub error: Color,
+}
+
+impl Interpolate for ColorTokens {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        ColorTokens {
+            window_background: self.window_background.interpolate(&other.window_background, t),
+            text_background: self.text_background.interpolate(&other.text_background, t),
+            content_background: self.content_background.interpolate(&other.content_background, t),
+            alternate_content_background: self
+                .alternate_content_background
+                .interpolate(&other.alternate_content_background, t),
+            text: self.text.interpolate(&other.text, t),
+            secondary_text: self.secondary_text.interpolate(&other.secondary_text, t),
+            accent: self.accent.interpolate(&other.accent, t),
+            border: self.border.interpolate(&other.border, t),
+            error: self.error.interpolate(&other.error, t),
+        }
+    }
+}
+
+/// Spacing scale of a theme, in DIPs: margins, paddings and gaps between widgets should be taken from it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpacingScale {
+    pub xsmall: f64,
+    pub small: f64,
+    pub medium: f64,
+    pub large: f64,
+    pub xlarge: f64,
+}
+
+impl Default for SpacingScale {
+    fn default() -> Self {
+        SpacingScale {
+            xsmall: 2.0,
+            small: 4.0,
+            medium: 8.0,
+            large: 16.0,
+            xlarge: 24.0,
+        }
+    }
+}
+
+impl Interpolate for SpacingScale {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        SpacingScale {
+            xsmall: self.xsmall.interpolate(&other.xsmall, t),
+            small: self.small.interpolate(&other.small, t),
+            medium: self.medium.interpolate(&other.medium, t),
+            large: self.large.interpolate(&other.large, t),
+            xlarge: self.xlarge.interpolate(&other.xlarge, t),
+        }
+    }
+}
+
+/// Size and weight of a level of the typography ramp.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TypeStyle {
+    /// Font size in DIPs, before scaling by `TEXT_SCALE_FACTOR` (see `TypeStyle::font_size`).
+    pub size: f64,
+    pub weight: FontWeight,
+}
+
+impl TypeStyle {
+    pub const fn new(size: f64, weight: FontWeight) -> TypeStyle {
+        TypeStyle { size, weight }
+    }
+
+    /// Returns the font size scaled by the text scale factor set by the user.
+    pub fn font_size(&self, env: &Environment) -> f64 {
+        self.size * env.get(&TEXT_SCALE_FACTOR).unwrap_or(1.0)
+    }
+}
+
+impl Interpolate for TypeStyle {
+    /// Interpolates the size; the weight switches halfway.
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        TypeStyle {
+            size: self.size.interpolate(&other.size, t),
+            weight: if t < 0.5 { self.weight } else { other.weight },
+        }
+    }
+}
+
+/// Typography ramp of a theme, from the smallest to the largest text.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TypeRamp {
+    pub caption: TypeStyle,
+    pub body: TypeStyle,
+    pub subtitle: TypeStyle,
+    pub title: TypeStyle,
+    pub headline: TypeStyle,
+}
+
+impl Default for TypeRamp {
+    fn default() -> Self {
+        TypeRamp {
+            caption: TypeStyle::new(11.0, FontWeight::NORMAL),
+            body: TypeStyle::new(14.0, FontWeight::NORMAL),
+            subtitle: TypeStyle::new(16.0, FontWeight::SEMI_BOLD),
+            title: TypeStyle::new(20.0, FontWeight::SEMI_BOLD),
+            headline: TypeStyle::new(28.0, FontWeight::BOLD),
+        }
+    }
+}
+
+impl Interpolate for TypeRamp {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        TypeRamp {
+            caption: self.caption.interpolate(&other.caption, t),
+            body: self.body.interpolate(&other.body, t),
+            subtitle: self.subtitle.interpolate(&other.subtitle, t),
+            title: self.title.interpolate(&other.title, t),
+            headline: self.headline.interpolate(&other.headline, t),
+        }
+    }
+}
+
+/// The tokens of a theme: semantic colors, spacing scale and typography ramp.
+///
+/// The builtin themes are available as `ThemeTokens::dark()`, `ThemeTokens::light()` and their high-contrast variants.
+/// Use them as a starting point for custom themes. To apply a theme to widgets and switch it at runtime, use
+/// `ThemeProvider`.
+///
+/// [`ThemeProvider`]: crate::widget::ThemeProvider
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThemeTokens {
+    /// Whether this is a dark theme (`DARK_MODE`).
+    pub dark: bool,
+    pub colors: ColorTokens,
+    pub spacing: SpacingScale,
+    pub typography: TypeRamp,
+}
+
+impl ThemeTokens {
+    /// The default dark theme.
+    pub fn dark() -> ThemeTokens {
+        ThemeTokens {
+            dark: true,
+            colors: ColorTokens {
+                window_background: Color::from_hex("#515151"),
+                text_background: Color::from_hex("#1e1e1e"),
+                content_background: Color::from_hex("#212121"),
+                alternate_content_background: Color::from_hex("#424242"),
+                text: Color::from_hex("#c8c8c8"),
+                secondary_text: Color::from_hex("#8c8c8c"),
+                accent: Color::from_hex("#3875d7"),
+                border: Color::from_hex("#2c2c2c"),
+                error: palette::RED_400,
+            },
+            spacing: SpacingScale::default(),
+            typography: TypeRamp::default(),
+        }
+    }
+
+    /// The default light theme.
+    pub fn light() -> ThemeTokens {
+        ThemeTokens {
+            dark: false,
+            colors: ColorTokens {
+                window_background: Color::from_hex("#f2f2f2"),
+                text_background: Color::from_hex("#ffffff"),
+                content_background: Color::from_hex("#212121"),
+                alternate_content_background: Color::from_hex("#424242"),
+                text: Color::from_hex("#272727"),
+                secondary_text: Color::from_hex("#6e6e6e"),
+                accent: Color::from_hex("#3875d7"),
+                border: Color::from_hex("#c4c4c4"),
+                error: palette::RED_700,
+            },
+            spacing: SpacingScale::default(),
+            typography: TypeRamp::default(),
+        }
+    }
+
+    /// High-contrast variant of the dark theme.
+    pub fn high_contrast_dark() -> ThemeTokens {
+        ThemeTokens {
+            dark: true,
+            colors: ColorTokens {
+                window_background: Color::from_hex("#000000"),
+                text_background: Color::from_hex("#000000"),
+                content_background: Color::from_hex("#000000"),
+                alternate_content_background: Color::from_hex("#1a1a1a"),
+                text: Color::from_hex("#ffffff"),
+                secondary_text: Color::from_hex("#ffffff"),
+                accent: Color::from_hex("#1aebff"),
+                border: Color::from_hex("#ffffff"),
+                error: Color::from_hex("#ff6b6b"),
+            },
+            spacing: SpacingScale::default(),
+            typography: TypeRamp::default(),
+        }
+    }
+
+    /// High-contrast variant of the light theme.
+    pub fn high_contrast_light() -> ThemeTokens {
+        ThemeTokens {
+            dark: false,
+            colors: ColorTokens {
+                window_background: Color::from_hex("#ffffff"),
+                text_background: Color::from_hex("#ffffff"),
+                content_background: Color::from_hex("#ffffff"),
+                alternate_content_background: Color::from_hex("#e6e6e6"),
+                text: Color::from_hex("#000000"),
+                secondary_text: Color::from_hex("#000000"),
+                accent: Color::from_hex("#37006e"),
+                border: Color::from_hex("#000000"),
+                error: Color::from_hex("#b00000"),
+            },
+            spacing: SpacingScale::default(),
+            typography: TypeRamp::default(),
+        }
+    }
+
+    /// Returns the tokens of the builtin theme.
+    pub fn builtin(theme: Theme) -> ThemeTokens {
+        match theme {
+            Theme::Dark => ThemeTokens::dark(),
+            Theme::Light => ThemeTokens::light(),
+        }
+    }
+
+    /// Returns an environment containing the tokens, and the keys derived from them.
+    ///
+    /// The font size (`FONT_SIZE`) is left untouched, since it's also affected by the accessibility preferences.
+    pub fn to_environment(&self) -> Environment {
+        let mut env = Environment::new();
+        env.set(&DARK_MODE, self.dark);
+        env.set(&WINDOW_BACKGROUND_COLOR, self.colors.window_background);
+        env.set(&TEXT_BACKGROUND_COLOR, self.colors.text_background);
+        env.set(&CONTENT_BACKGROUND_COLOR, self.colors.content_background);
+        env.set(
+            &ALTERNATE_CONTENT_BACKGROUND_COLOR,
+            self.colors.alternate_content_background,
+        );
+        env.set(&TEXT_COLOR, self.colors.text);
+        env.set(&SECONDARY_TEXT_COLOR, self.colors.secondary_text);
+        env.set(&ACCENT_COLOR, self.colors.accent);
+        env.set(&BORDER_COLOR, self.colors.border);
+        env.set(&ERROR_COLOR, self.colors.error);
+        env.set(&THEME_TOKENS, Arc::new(self.clone()));
+        env
+    }
+}
+
+impl Interpolate for ThemeTokens {
+    /// Interpolates the colors, sizes and spacings; `dark` switches halfway.
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        ThemeTokens {
+            dark: if t < 0.5 { self.dark } else { other.dark },
+            colors: self.colors.interpolate(&other.colors, t),
+            spacing: self.spacing.interpolate(&other.spacing, t),
+            typography: self.typography.interpolate(&other.typography, t),
+        }
+    }
+}
+
+/// Returns the tokens of the current theme.
+///
+/// Falls back to the tokens of the dark theme if no theme has been set in the environment.
+pub fn tokens(env: &Environment) -> Arc<ThemeTokens> {
+    env.get(&THEME_TOKENS).unwrap_or_else(|| Arc::new(ThemeTokens::dark()))
+}
+
+static DARK_THEME: Lazy<Environment> = Lazy::new(|| ThemeTokens::dark().to_environment());
+
+static LIGHT_THEME: Lazy<Environment> = Lazy::new(|| ThemeTokens::light().to_environment());
+
+static HIGH_CONTRAST_DARK_THEME: Lazy<Environment> = Lazy::new(|| {
+    let mut env = ThemeTokens::high_contrast_dark().to_environment();
+    env.set(&HIGH_CONTRAST, true);
     env
 });
 
-static LIGHT_THEME: Lazy<Environment> = Lazy::new(|| {
-    let mut env = Environment::new();
-    env.set(&DARK_MODE, false);
-    env.set(&TEXT_COLOR, Color::from_hex("#272727"));
-    env.set(&WINDOW_BACKGROUND_COLOR, Color::from_hex("#f2f2f2"));
-    env.set(&TEXT_BACKGROUND_COLOR, Color::from_hex("#ffffff"));
-    env.set(&CONTENT_BACKGROUND_COLOR, Color::from_hex("#212121"));
-    env.set(&ALTERNATE_CONTENT_BACKGROUND_COLOR, Color::from_hex("#424242"));
+static HIGH_CONTRAST_LIGHT_THEME: Lazy<Environment> = Lazy::new(|| {
+    let mut env = ThemeTokens::high_contrast_light().to_environment();
+    env.set(&HIGH_CONTRAST, true);
     env
 });
 
@@ -322,7 +640,55 @@ pub fn dark_theme() -> Environment {
 pub fn light_theme() -> Environment {
     LIGHT_THEME.clone()
 }
+pub fn high_contrast_dark_theme() -> Environment {
+    HIGH_CONTRAST_DARK_THEME.clone()
+}
+pub fn high_contrast_light_theme() -> Environment {
+    HIGH_CONTRAST_LIGHT_THEME.clone()
+}
 
 pub fn setup_default_style(env: &mut Environment) {
     *env = env.merged(dark_theme());
 }
+
+/// Applies the accessibility preferences of the user to the environment.
+///
+/// Sets `REDUCED_MOTION`, `HIGH_CONTRAST` and `TEXT_SCALE_FACTOR`, switches to the high-contrast variant
+/// of the current theme if requested, and scales `FONT_SIZE`.
+pub fn apply_accessibility_preferences(env: &mut Environment, prefs: &AccessibilityPreferences) {
+    if prefs.high_contrast {
+        let theme = if env.get(&DARK_MODE).unwrap_or(true) {
+            high_contrast_dark_theme()
+        } else {
+            high_contrast_light_theme()
+        };
+        *env = env.merged(theme);
+    }
+    env.set(&REDUCED_MOTION, prefs.reduced_motion);
+    env.set(&HIGH_CONTRAST, prefs.high_contrast);
+    env.set(&TEXT_SCALE_FACTOR, prefs.text_scale_factor);
+    if prefs.text_scale_factor != 1.0 {
+        let font_size = env.get(&FONT_SIZE).unwrap_or(16.0);
+        env.set(&FONT_SIZE, font_size * prefs.text_scale_factor);
+    }
+}
+
+/// Returns how the mouse wheel should scroll views.
+///
+/// These are the settings of the OS, unless overridden by `SCROLL_SETTINGS` in the environment.
+pub fn scroll_settings(env: &Environment) -> ScrollSettings {
+    env.get(&SCROLL_SETTINGS)
+        .unwrap_or_else(|| Application::instance().scroll_settings())
+}
+
+/// Returns the duration that an animation or transition should have, given its nominal duration.
+///
+/// Returns zero if `REDUCED_MOTION` is set in the environment: animations should jump directly to their final
+/// state. All animations of built-in widgets go through this function.
+pub fn animation_duration(env: &Environment, duration: Duration) -> Duration {
+    if env.get(&REDUCED_MOTION).unwrap_or(false) {
+        Duration::ZERO
+    } else {
+        duration
+    }
+}
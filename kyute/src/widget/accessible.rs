@@ -0,0 +1,63 @@
+//! Semantic descriptions for widgets.
+use crate::{
+    accessibility::Semantics,
+    widget::{focus_policy::route_outside_focus_chain, prelude::*},
+};
+
+/// Describes the wrapped widget to assistive technologies.
+///
+/// The wrapped widget must have an ID (e.g. `Clickable`) to be part of the accessibility tree.
+pub struct Accessible<Inner> {
+    inner: Inner,
+    semantics: Semantics,
+}
+
+impl<Inner: Widget + 'static> Accessible<Inner> {
+    pub fn new(inner: Inner, semantics: Semantics) -> Accessible<Inner> {
+        Accessible { inner, semantics }
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for Accessible<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.inner.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    // `route_event` is not forwarded to the inner widget: the default implementation is what exposes the semantics
+    // when the accessibility tree is built.
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if self.semantics.disabled {
+            // disabled widgets can't be focused
+            route_outside_focus_chain(&self.inner, true, ctx, event, env)
+        } else {
+            self.inner.route_event(ctx, event, env)
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+
+    fn semantics(&self) -> Option<Semantics> {
+        Some(self.semantics.clone())
+    }
+
+    fn is_focusable(&self) -> bool {
+        !self.semantics.disabled && (self.semantics.role.is_interactive() || self.inner.is_focusable())
+    }
+}
@@ -0,0 +1,297 @@
+//! Floating widgets positioned relative to an anchor widget.
+use crate::widget::prelude::*;
+use std::cell::Cell;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Placement
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Side of the anchor on which a floating widget is placed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    /// Returns the opposite side.
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Top => Side::Bottom,
+            Side::Bottom => Side::Top,
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    fn is_vertical(self) -> bool {
+        matches!(self, Side::Top | Side::Bottom)
+    }
+}
+
+/// Parameters for placing a floating rectangle relative to an anchor rectangle.
+///
+/// Also used to place popup windows next to their anchor on the desktop (see `Popup`).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Placement {
+    pub(crate) side: Side,
+    /// Alignment along the side (0.0 aligns the start edges of the anchor and the floating widget, 1.0 the end edges).
+    pub(crate) align: f64,
+    pub(crate) gap: f64,
+    pub(crate) offset: Offset,
+    pub(crate) flip: bool,
+    pub(crate) shift: bool,
+}
+
+impl Placement {
+    /// Places the floating rectangle at the start of the specified side, flipped and shifted to stay in bounds.
+    pub(crate) fn new(side: Side) -> Placement {
+        Placement {
+            side,
+            align: 0.0,
+            gap: 0.0,
+            offset: Offset::zero(),
+            flip: true,
+            shift: true,
+        }
+    }
+
+    /// Returns the placement with the gap and offset multiplied by the specified factor (e.g. to convert them
+    /// from DIPs to physical pixels).
+    pub(crate) fn scaled(&self, factor: f64) -> Placement {
+        Placement {
+            gap: self.gap * factor,
+            offset: self.offset * factor,
+            ..*self
+        }
+    }
+
+    /// Returns the position of the floating rectangle on the specified side of the anchor.
+    fn position_on_side(&self, side: Side, anchor: &Rect, size: Size) -> Point {
+        let x = anchor.min_x() + self.align * (anchor.width() - size.width);
+        let y = anchor.min_y() + self.align * (anchor.height() - size.height);
+        let pos = match side {
+            Side::Top => Point::new(x, anchor.min_y() - self.gap - size.height),
+            Side::Bottom => Point::new(x, anchor.max_y() + self.gap),
+            Side::Left => Point::new(anchor.min_x() - self.gap - size.width, y),
+            Side::Right => Point::new(anchor.max_x() + self.gap, y),
+        };
+        pos + self.offset
+    }
+
+    /// Returns the space available between the anchor and the bounds on the specified side.
+    fn available_space(side: Side, anchor: &Rect, bounds: &Rect) -> f64 {
+        match side {
+            Side::Top => anchor.min_y() - bounds.min_y(),
+            Side::Bottom => bounds.max_y() - anchor.max_y(),
+            Side::Left => anchor.min_x() - bounds.min_x(),
+            Side::Right => bounds.max_x() - anchor.max_x(),
+        }
+    }
+
+    /// Returns whether the floating rectangle overflows the bounds on the main axis.
+    fn overflows(side: Side, rect: &Rect, bounds: &Rect) -> bool {
+        match side {
+            Side::Top => rect.min_y() < bounds.min_y(),
+            Side::Bottom => rect.max_y() > bounds.max_y(),
+            Side::Left => rect.min_x() < bounds.min_x(),
+            Side::Right => rect.max_x() > bounds.max_x(),
+        }
+    }
+
+    /// Computes the side and position of a floating rectangle of the specified size.
+    ///
+    /// `bounds` is the area in which the floating rectangle should stay, if known (usually, the window bounds).
+    pub(crate) fn place(&self, anchor: &Rect, size: Size, bounds: Option<&Rect>) -> (Side, Point) {
+        let mut side = self.side;
+        let mut pos = self.position_on_side(side, anchor, size);
+
+        let bounds = if let Some(bounds) = bounds {
+            bounds
+        } else {
+            return (side, pos);
+        };
+
+        // flip to the opposite side if there's not enough space, and if there's more space on the other side
+        if self.flip && Self::overflows(side, &Rect::new(pos, size), bounds) {
+            let opposite = side.opposite();
+            if Self::available_space(opposite, anchor, bounds) > Self::available_space(side, anchor, bounds) {
+                side = opposite;
+                pos = self.position_on_side(side, anchor, size);
+            }
+        }
+
+        // shift along the side to stay in bounds
+        if self.shift {
+            if side.is_vertical() {
+                pos.x = pos.x.min(bounds.max_x() - size.width).max(bounds.min_x());
+            } else {
+                pos.y = pos.y.min(bounds.max_y() - size.height).max(bounds.min_y());
+            }
+        }
+
+        (side, pos)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Widget definition
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Shows a floating widget next to an anchor widget.
+///
+/// The floating widget is placed on one side of the anchor, aligned along this side. If the floating widget
+/// would overflow the window, it can be flipped to the opposite side, and shifted along the side to stay in
+/// the window.
+///
+/// Like `Overlay`, the layout and identity of the widget is defined by the anchor. The floating widget is
+/// painted above the anchor, but not above the widgets painted after this one. Note that it only receives
+/// pointer events inside the bounds of the anchor (unless it captures the pointer).
+///
+/// This is the base for tooltips, drop-down lists and popovers.
+pub struct AnchoredOverlay<A, F> {
+    anchor: A,
+    floating: WidgetPod<F>,
+    placement: Placement,
+    /// Transform from local to window coordinates and window size, as of the last event.
+    window_placement: Cell<Option<(Transform, Size)>>,
+    /// Side on which the floating widget has been placed during the last layout.
+    placed_side: Cell<Side>,
+}
+
+impl<A: Widget + 'static, F: Widget + 'static> AnchoredOverlay<A, F> {
+    /// Creates a new `AnchoredOverlay` that places `floating` on the specified side of `anchor`.
+    ///
+    /// By default, the floating widget is aligned to the start of the side, and is flipped and shifted
+    /// to stay inside the window.
+    #[composable]
+    pub fn new(anchor: A, floating: F, side: Side) -> AnchoredOverlay<A, F> {
+        AnchoredOverlay {
+            anchor,
+            floating: WidgetPod::new(floating),
+            placement: Placement::new(side),
+            window_placement: Cell::new(None),
+            placed_side: Cell::new(side),
+        }
+    }
+
+    /// Sets the alignment of the floating widget along the side of the anchor.
+    ///
+    /// `Alignment::START` aligns the left (or top) edges of both widgets, `Alignment::END` the right (or bottom) edges.
+    /// Baseline alignments are not supported and are treated as `Alignment::START`.
+    #[must_use]
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.placement.align = match alignment {
+            Alignment::Relative(align) => align,
+            _ => 0.0,
+        };
+        self
+    }
+
+    /// Sets the space between the anchor and the floating widget.
+    #[must_use]
+    pub fn gap(mut self, gap: f64) -> Self {
+        self.placement.gap = gap;
+        self
+    }
+
+    /// Sets an additional offset applied to the position of the floating widget.
+    #[must_use]
+    pub fn offset(mut self, offset: Offset) -> Self {
+        self.placement.offset = offset;
+        self
+    }
+
+    /// Sets whether the floating widget is moved to the opposite side of the anchor if it would overflow the window.
+    #[must_use]
+    pub fn flip(mut self, flip: bool) -> Self {
+        self.placement.flip = flip;
+        self
+    }
+
+    /// Sets whether the floating widget is moved along the side of the anchor to stay inside the window.
+    #[must_use]
+    pub fn shift(mut self, shift: bool) -> Self {
+        self.placement.shift = shift;
+        self
+    }
+
+    /// Returns the side on which the floating widget has been placed during the last layout.
+    ///
+    /// This can differ from the requested side if the floating widget was flipped.
+    pub fn placed_side(&self) -> Side {
+        self.placed_side.get()
+    }
+
+    /// Returns a reference to the anchor widget.
+    pub fn anchor(&self) -> &A {
+        &self.anchor
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// impl Widget
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl<A: Widget + 'static, F: Widget + 'static> Widget for AnchoredOverlay<A, F> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.anchor.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let geometry = self.anchor.layout(ctx, constraints, env);
+        let anchor_rect = geometry.measurements.local_bounds();
+
+        // window bounds in local coordinates
+        let window_bounds = self.window_placement.get().and_then(|(transform, window_size)| {
+            let inverse = transform.inverse()?;
+            Some(inverse.outer_transformed_rect(&Rect::new(Point::origin(), window_size)))
+        });
+
+        let floating_constraints = LayoutParams {
+            min: Size::zero(),
+            max: window_bounds
+                .map(|bounds| bounds.size)
+                .unwrap_or(Size::new(f64::INFINITY, f64::INFINITY)),
+            ..*constraints
+        };
+        let floating_geometry = self.floating.layout(ctx, &floating_constraints, env);
+        let (side, pos) = self.placement.place(
+            &anchor_rect,
+            floating_geometry.padding_box_size(),
+            window_bounds.as_ref(),
+        );
+        self.floating.set_offset(Offset::new(
+            pos.x + floating_geometry.padding_left,
+            pos.y + floating_geometry.padding_top,
+        ));
+        self.placed_side.set(side);
+
+        geometry
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        // track the position of the anchor in the window, so that the floating widget can be kept inside
+        // the window when the anchor moves (or is scrolled)
+        if let Some(window_size) = ctx.window_size() {
+            let window_placement = Some((*ctx.window_transform(), window_size));
+            if self.window_placement.get() != window_placement {
+                self.window_placement.set(window_placement);
+                ctx.request_relayout();
+            }
+        }
+
+        // the floating widget is above the anchor: it receives events first
+        self.floating.route_event(ctx, event, env);
+        if !ctx.handled() {
+            self.anchor.route_event(ctx, event, env);
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.anchor.paint(ctx);
+        self.floating.paint(ctx);
+    }
+}
@@ -0,0 +1,104 @@
+//! Opacity and transform animations running on the compositor.
+use crate::{
+    animation::{Transition, TransitionState},
+    cache,
+    widget::prelude::*,
+};
+use parking_lot::Mutex;
+use std::{sync::Arc, time::Instant};
+
+/// Shows a widget on its own compositor layer, and animates the opacity and the transform of the layer.
+///
+/// Unlike values animated with `Animated`, these animations run on the compositor: they don't cause
+/// recompositions or repaints, and stay smooth even if the application is busy.
+pub struct AnimatedLayer<Inner> {
+    content: WidgetPod<Inner>,
+    opacity: Option<(TransitionState<f32>, Transition)>,
+    transform: Option<(TransitionState<Transform>, Transition)>,
+    /// Generation of the opacity transition last sent to the compositor.
+    sent_opacity_generation: Arc<Mutex<Option<u64>>>,
+}
+
+impl<Inner: Widget + 'static> AnimatedLayer<Inner> {
+    /// Creates a new `AnimatedLayer`, without animations.
+    #[composable]
+    pub fn new(inner: Inner) -> AnimatedLayer<Inner> {
+        AnimatedLayer {
+            content: WidgetPod::with_native_layer(inner),
+            opacity: None,
+            transform: None,
+            sent_opacity_generation: cache::once(|| Arc::new(Mutex::new(None))),
+        }
+    }
+
+    /// Transitions the opacity of the widget to `opacity` each time it changes.
+    #[must_use]
+    #[composable]
+    pub fn opacity(mut self, opacity: f32, transition: Transition) -> Self {
+        let transition = transition.for_environment();
+        self.opacity = Some((TransitionState::update(opacity, &transition), transition));
+        self
+    }
+
+    /// Transitions the transform of the widget to `transform` each time it changes.
+    ///
+    /// The transform is applied in the local coordinate space of the widget, in DIPs, and doesn't affect layout.
+    #[must_use]
+    #[composable]
+    pub fn transform(mut self, transform: Transform, transition: Transition) -> Self {
+        let transition = transition.for_environment();
+        self.transform = Some((TransitionState::update(transform, &transition), transition));
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        self.content.inner()
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for AnimatedLayer<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.content.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.content.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.content.route_event(ctx, event, env)
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx);
+
+        let layer = if let Some(layer) = self.content.layer() {
+            layer
+        } else {
+            return;
+        };
+        let now = Instant::now();
+
+        if let Some((ref state, ref transition)) = self.opacity {
+            // the opacity of the layer is left untouched by repaints: send the animation only when it's restarted
+            let mut sent_generation = self.sent_opacity_generation.lock();
+            if *sent_generation != Some(state.generation) {
+                layer.animate_opacity(&state.compositor_keyframes(now, transition));
+                *sent_generation = Some(state.generation);
+            }
+        }
+
+        if let Some((ref state, ref transition)) = self.transform {
+            // the layer transform has just been reset to its position in the parent layer: apply the
+            // remainder of the animation on top of it
+            let base = *ctx.layer_transform();
+            let keyframes: Vec<_> = state
+                .compositor_keyframes(now, transition)
+                .into_iter()
+                .map(|(t, transform)| (t, transform.then(&base)))
+                .collect();
+            layer.animate_transform(&keyframes);
+        }
+    }
+}
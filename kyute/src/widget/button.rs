@@ -1,9 +1,10 @@
 use crate::{
+    accessibility::{Role, Semantics},
     event::PointerEventKind,
     layout::Alignment,
     style,
     style::WidgetState,
-    widget::{prelude::*, Clickable, Label, WidgetExt},
+    widget::{prelude::*, Accessible, Clickable, Label, WidgetExt},
     Color, Signal, UnitExt,
 };
 use std::cell::Cell;
@@ -60,26 +61,30 @@ fn button_inner(label: String) -> ButtonInner {
 /// `Clickable` wrapper.
 #[derive(Widget)]
 pub struct Button {
-    inner: Clickable<ButtonInner>,
+    inner: Accessible<Clickable<ButtonInner>>,
 }
 
 impl Button {
     /// Creates a new button with the specified label.
     #[composable]
     pub fn new(label: impl Into<String>) -> Button {
-        let inner = button_inner(label.into()).clickable();
+        let label = label.into();
+        let inner = button_inner(label.clone())
+            .clickable()
+            .accessible(Semantics::new(Role::Button).name(label));
         Button { inner }
     }
 
     /// Returns whether this button has been clicked.
     pub fn clicked(&self) -> bool {
-        self.inner.clicked()
+        self.inner.inner().clicked()
     }
 
     /// Runs the function when the button has been clicked.
     pub fn on_click(self, f: impl FnOnce()) -> Self {
-        Button {
-            inner: self.inner.on_click(f),
+        if self.clicked() {
+            f();
         }
+        self
     }
 }
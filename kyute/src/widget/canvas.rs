@@ -1,4 +1,7 @@
-use crate::{widget::prelude::*, Length, Transform};
+use crate::{
+    widget::{prelude::*, widget_pod::sort_by_z_index},
+    Length, Transform,
+};
 use kyute::style::WidgetState;
 use std::sync::Arc;
 
@@ -140,13 +143,14 @@ impl Widget for Canvas {
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
-        for item in self.items.iter() {
+        // topmost items first
+        for item in sort_by_z_index(&self.items, |item| item.widget.z_index()).rev() {
             item.widget.route_event(ctx, event, env)
         }
     }
 
     fn paint(&self, ctx: &mut PaintCtx) {
-        for item in self.items.iter() {
+        for item in sort_by_z_index(&self.items, |item| item.widget.z_index()) {
             item.widget.paint(ctx)
         }
     }
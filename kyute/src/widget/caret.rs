@@ -0,0 +1,94 @@
+//! Text caret, shared by the text editors.
+use crate::{cache, core::LayerPaintCtx, drawing::ToSkia, widget::prelude::*, Color, SizeI};
+use kyute_shell::{animation::Layer, application::Application};
+use parking_lot::Mutex;
+use std::{sync::Arc, time::Duration};
+
+/// Time after the caret last moved before it starts blinking again: it stays visible while the user is typing.
+const TYPING_PAUSE: Duration = Duration::from_millis(500);
+
+/// The caret, as last shown on the compositor.
+#[derive(Copy, Clone, PartialEq)]
+struct ShownCaret {
+    /// Transform of the caret layer.
+    transform: Transform,
+    size: SizeI,
+    color: Color,
+}
+
+/// Blinking caret of a text editor, on its own compositor layer.
+///
+/// The caret blinks at the rate set in the OS settings. The blinking is an opacity animation running on the
+/// compositor: it doesn't repaint the editor. The animation restarts each time the caret moves, so that the caret
+/// stays visible while the user types.
+pub(crate) struct Caret {
+    layer: Layer,
+    shown: Arc<Mutex<Option<ShownCaret>>>,
+}
+
+impl Caret {
+    #[composable]
+    pub(crate) fn new() -> Caret {
+        Caret {
+            layer: cache::once(Layer::new),
+            shown: cache::once(|| Arc::new(Mutex::new(None))),
+        }
+    }
+
+    /// Shows the caret, with the specified bounds in the local coordinates of the painting context.
+    pub(crate) fn paint(&self, ctx: &mut PaintCtx, bounds: Rect, color: Color) {
+        // compositor layers are positioned in pixels
+        let t = Transform::translation(bounds.origin.x, bounds.origin.y).then(ctx.layer_transform());
+        let s = ctx.scale_factor;
+        let caret = ShownCaret {
+            transform: Transform::new(t.m11, t.m12, t.m21, t.m22, (t.m31 * s).round(), (t.m32 * s).round()),
+            size: SizeI::new(
+                ((bounds.size.width * s).round() as i32).max(1),
+                ((bounds.size.height * s).ceil() as i32).max(1),
+            ),
+            color,
+        };
+
+        let mut shown = self.shown.lock();
+        if shown.map(|shown| (shown.size, shown.color)) != Some((caret.size, caret.color)) {
+            self.layer.set_size(caret.size);
+            let mut layer_paint_ctx = LayerPaintCtx {
+                skia_gpu_context: ctx.skia_direct_context,
+                damage: None,
+            };
+            layer_paint_ctx.paint_layer(&self.layer, s, |ctx| {
+                ctx.surface.canvas().clear(color.to_skia());
+            });
+        }
+        if *shown != Some(caret) {
+            self.layer.set_transform(&caret.transform);
+            self.restart_blinking();
+            *shown = Some(caret);
+        }
+        ctx.parent_layer().add_child(&self.layer);
+    }
+
+    /// Hides the caret. It starts visible the next time it's painted.
+    pub(crate) fn hide(&self) {
+        *self.shown.lock() = None;
+    }
+
+    fn restart_blinking(&self) {
+        match Application::instance().caret_blink_time() {
+            Some(blink_time) => {
+                // visible until the typing pause is over, then alternately hidden and visible
+                let hide = TYPING_PAUSE.max(blink_time);
+                self.layer.animate_opacity_repeat(
+                    &[
+                        (Duration::ZERO, 1.0),
+                        (hide, 1.0),
+                        (hide, 0.0),
+                        (hide + blink_time, 0.0),
+                    ],
+                    2 * blink_time,
+                );
+            }
+            None => self.layer.set_opacity(1.0),
+        }
+    }
+}
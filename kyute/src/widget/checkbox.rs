@@ -1,10 +1,11 @@
 //! Checkboxes.
 use crate::{
+    accessibility::{Role, Semantics},
     drawing::{PaintCtxExt, ToSkia},
     style::VectorIcon,
     text::FormattedText,
     theme,
-    widget::{form, prelude::*, Clickable, Drawable, Label, Null, StyledBox, Text},
+    widget::{form, prelude::*, Accessible, Clickable, Drawable, Label, Null, StyledBox, Text},
     Color, Font,
 };
 use once_cell::sync::Lazy;
@@ -67,7 +68,7 @@ border-radius: 5px;
 
 #[derive(Widget)]
 pub struct Checkbox {
-    inner: Clickable<CheckboxInner>,
+    inner: Accessible<Clickable<CheckboxInner>>,
     state: bool,
 }
 
@@ -75,7 +76,9 @@ impl Checkbox {
     #[composable]
     pub fn new(state: bool) -> Checkbox {
         Checkbox {
-            inner: checkbox_inner(state).clickable(),
+            inner: checkbox_inner(state)
+                .clickable()
+                .accessible(Semantics::new(Role::CheckBox).checked(state)),
             state,
         }
     }
@@ -88,7 +91,7 @@ impl Checkbox {
     }
 
     pub fn toggled(&self) -> Option<bool> {
-        if self.inner.clicked() {
+        if self.inner.inner().clicked() {
             Some(!self.state)
         } else {
             None
@@ -1,14 +1,27 @@
 //! Clickable widget wrapper
-use crate::{cache, event::PointerEventKind, widget::prelude::*, Signal, State};
+use crate::{
+    cache,
+    event::{PointerButton, PointerEventKind},
+    metrics, theme,
+    widget::{
+        prelude::*,
+        press_and_hold::{touch_event, PressAndHold},
+    },
+    Signal, State,
+};
 use keyboard_types::{Key, KeyState, Modifiers};
 use kyute::style::WidgetState;
 
 /// Wraps an inner widget and allows the user to respond to clicks on it.
+///
+/// A right-click, or a press-and-hold of a finger on a touch screen (see `secondary_clicked`), is not a click.
 #[derive(Clone)]
 pub struct Clickable<Inner> {
     id: WidgetId,
     inner: Inner,
     clicked: Signal<()>,
+    secondary_clicked: Signal<()>,
+    press_and_hold: PressAndHold,
     active: State<bool>,
     focus: State<bool>,
     activated: Signal<bool>,
@@ -25,6 +38,8 @@ impl<Inner: Widget + 'static> Clickable<Inner> {
             active: cache::state(|| false),
             focus: cache::state(|| false),
             clicked: Signal::new(),
+            secondary_clicked: Signal::new(),
+            press_and_hold: PressAndHold::new(),
             activated: Signal::new(),
             hovered: Signal::new(),
             focused: Signal::new(),
@@ -45,6 +60,19 @@ impl<Inner: Widget + 'static> Clickable<Inner> {
         self.clicked.signalled()
     }
 
+    #[must_use]
+    pub fn on_secondary_click(self, f: impl FnOnce()) -> Self {
+        if self.secondary_clicked.signalled() {
+            f();
+        }
+        self
+    }
+
+    /// Returns whether this button has been right-clicked, or pressed and held with a finger.
+    pub fn secondary_clicked(&self) -> bool {
+        self.secondary_clicked.signalled()
+    }
+
     /// Returns whether this button is active (holding the mouse button over it).
     pub fn activated(&self) -> bool {
         self.activated.value() == Some(true)
@@ -149,6 +177,7 @@ impl<Inner: Widget + 'static> Widget for Clickable<Inner> {
         let mut widget_state = params.widget_state;
         widget_state.set(WidgetState::ACTIVE, self.active.get());
         widget_state.set(WidgetState::FOCUS, self.focus.get());
+        widget_state |= theme::FORCED_WIDGET_STATE.get(env).unwrap_or_default();
         self.inner.layout(
             ctx,
             &LayoutParams {
@@ -160,12 +189,16 @@ impl<Inner: Widget + 'static> Widget for Clickable<Inner> {
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        // the finger may be held over a descendant that captured it
+        let press_and_hold = touch_event(event).map_or(false, |p| self.press_and_hold.event(p));
+
         match event {
-            Event::BuildFocusChain { chain } => {
-                // clickable items are by default focusable
-                chain.push(self.id);
-            }
             Event::Pointer(p) => match p.kind {
+                // secondary button: not handled, so that context menus around the widget can open
+                PointerEventKind::PointerDown if p.button == Some(PointerButton::RIGHT) => {}
+                PointerEventKind::PointerUp if p.button == Some(PointerButton::RIGHT) => {
+                    self.secondary_clicked.signal(());
+                }
                 PointerEventKind::PointerDown => {
                     ctx.request_focus();
                     ctx.set_handled();
@@ -177,7 +210,12 @@ impl<Inner: Widget + 'static> Widget for Clickable<Inner> {
                 PointerEventKind::PointerUp => {
                     self.active.set(false);
                     self.activated.signal(false);
-                    self.clicked.signal(());
+                    if press_and_hold {
+                        self.secondary_clicked.signal(());
+                    } else {
+                        self.clicked.signal(());
+                        metrics::click(self.id, self.inner.debug_name());
+                    }
                     ctx.request_relayout();
                 }
                 PointerEventKind::PointerOver => {
@@ -203,6 +241,7 @@ impl<Inner: Widget + 'static> Widget for Clickable<Inner> {
                         self.active.set(true);
                         self.activated.signal(true);
                         self.clicked.signal(());
+                        metrics::click(self.id, self.inner.debug_name());
                         ctx.request_relayout();
                     }
 
@@ -240,6 +279,12 @@ impl<Inner: Widget + 'static> Widget for Clickable<Inner> {
     }
 
     fn paint(&self, ctx: &mut PaintCtx) {
-        self.inner.paint(ctx)
+        self.inner.paint(ctx);
+        self.press_and_hold.paint(ctx);
+    }
+
+    // clickable items are by default focusable
+    fn is_focusable(&self) -> bool {
+        true
     }
 }
@@ -0,0 +1,288 @@
+//! Clipping to arbitrary shapes and alpha masks.
+//!
+//! `ClipPath` clips a widget to a shape (rounded rectangle, ellipse or SVG path), e.g. for circular avatars or
+//! custom-shaped panels, and `Mask` multiplies the opacity of a widget by the alpha channel of another widget, e.g. an
+//! image or a gradient to fade out the edge of a list. Both are anti-aliased.
+//!
+//! Clips and masks are applied on the canvas of the parent, so they compose with transforms and with the opacity of
+//! `AnimatedLayer`s containing them. However, the contents of child widgets that paint in their own native layer
+//! (`WidgetPod::with_native_layer`) are composited separately and aren't clipped or masked.
+use crate::{drawing::ToSkia, style, widget::prelude::*};
+use skia_safe as sk;
+use std::cell::RefCell;
+
+#[derive(Clone, Debug)]
+enum ClipShapeKind {
+    RoundedRect { radii: [Length; 4] },
+    Ellipse,
+    Path { path: sk::Path, view_box: Rect },
+}
+
+/// Shape of a `ClipPath`, scaled to the bounds of the clipped widget.
+#[derive(Clone, Debug)]
+pub struct ClipShape(ClipShapeKind);
+
+impl ClipShape {
+    /// Rounded rectangle with the same radius for all corners.
+    pub fn rounded_rect(radius: impl Into<Length>) -> ClipShape {
+        ClipShape::rounded_rect_corners([radius.into(); 4])
+    }
+
+    /// Rounded rectangle, with radii in the order top-left, top-right, bottom-right, bottom-left.
+    pub fn rounded_rect_corners(radii: [Length; 4]) -> ClipShape {
+        ClipShape(ClipShapeKind::RoundedRect { radii })
+    }
+
+    /// Ellipse inscribed in the bounds of the widget (a circle if the widget is square).
+    pub fn ellipse() -> ClipShape {
+        ClipShape(ClipShapeKind::Ellipse)
+    }
+
+    /// Shape described by SVG path data, in the coordinate space of `view_box`, which is stretched to the bounds of
+    /// the widget.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `svg_path` is not valid SVG path data.
+    pub fn svg_path(svg_path: &str, view_box: Rect) -> ClipShape {
+        ClipShape(ClipShapeKind::Path {
+            path: crate::drawing::svg_path_to_skia(svg_path).expect("invalid path syntax"),
+            view_box,
+        })
+    }
+
+    /// Returns the outline of the shape in a box of the specified size.
+    fn to_skia_path(&self, size: Size, constraints: &LayoutParams, env: &Environment) -> sk::Path {
+        let bounds = Rect::new(Point::origin(), size).to_skia();
+        match self.0 {
+            ClipShapeKind::RoundedRect { radii } => {
+                let radii = radii.map(|r| {
+                    let r = r.compute(constraints, env) as sk::scalar;
+                    sk::Vector::new(r, r)
+                });
+                let mut path = sk::Path::new();
+                path.add_rrect(sk::RRect::new_rect_radii(bounds, &radii), None);
+                path
+            }
+            ClipShapeKind::Ellipse => {
+                let mut path = sk::Path::new();
+                path.add_oval(bounds, None);
+                path
+            }
+            ClipShapeKind::Path { ref path, view_box } => {
+                match sk::Matrix::rect_to_rect(view_box.to_skia(), bounds, sk::matrix::ScaleToFit::Fill) {
+                    Some(matrix) => path.with_transform(&matrix),
+                    // empty view box
+                    None => sk::Path::new(),
+                }
+            }
+        }
+    }
+}
+
+impl From<style::Shape> for ClipShape {
+    fn from(shape: style::Shape) -> Self {
+        match shape {
+            style::Shape::RoundedRect { radii } => ClipShape::rounded_rect_corners(radii),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// ClipPath
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Clips a widget to a shape, with anti-aliased edges. See `WidgetExt::clip_path`.
+///
+/// The shape covers the padding box of the widget. Only painting is clipped: the widget still receives the pointer
+/// events in its rectangular bounds.
+pub struct ClipPath<Inner> {
+    inner: WidgetPod<Inner>,
+    shape: ClipShape,
+    computed_path: RefCell<sk::Path>,
+}
+
+impl<Inner: Widget + 'static> ClipPath<Inner> {
+    #[composable]
+    pub fn new(shape: impl Into<ClipShape>, inner: Inner) -> ClipPath<Inner> {
+        ClipPath {
+            inner: WidgetPod::new(inner),
+            shape: shape.into(),
+            computed_path: RefCell::new(sk::Path::new()),
+        }
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        self.inner.inner()
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        self.inner.inner_mut()
+    }
+}
+
+impl<Inner: Widget> Widget for ClipPath<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.inner.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let sublayout = self.inner.layout(ctx, constraints, env);
+        let size = sublayout.padding_box_size();
+        if !ctx.speculative {
+            self.inner
+                .set_offset(Offset::new(sublayout.padding_left, sublayout.padding_top));
+            self.computed_path
+                .replace(self.shape.to_skia_path(size, constraints, env));
+        }
+        Geometry {
+            padding_left: 0.0,
+            padding_top: 0.0,
+            padding_right: 0.0,
+            padding_bottom: 0.0,
+            measurements: Measurements {
+                size,
+                clip_bounds: None,
+                baseline: sublayout.padding_box_baseline(),
+            },
+            ..sublayout
+        }
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.inner.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let canvas = ctx.surface.canvas();
+        canvas.save();
+        canvas.clip_path(&self.computed_path.borrow(), sk::ClipOp::Intersect, true);
+        self.inner.paint(ctx);
+        ctx.surface.canvas().restore();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Mask
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Multiplies the opacity of a widget by the alpha channel of another widget. See `WidgetExt::mask`.
+///
+/// The mask widget is laid out with the size of the padding box of the masked widget, and doesn't receive pointer
+/// events. Where the mask is transparent, the widget is invisible.
+pub struct Mask<Inner, M> {
+    inner: WidgetPod<Inner>,
+    mask: WidgetPod<M>,
+}
+
+impl<Inner: Widget + 'static, M: Widget + 'static> Mask<Inner, M> {
+    #[composable]
+    pub fn new(mask: M, inner: Inner) -> Mask<Inner, M> {
+        Mask {
+            inner: WidgetPod::new(inner),
+            mask: WidgetPod::new(mask),
+        }
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        self.inner.inner()
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        self.inner.inner_mut()
+    }
+}
+
+impl<Inner: Widget, M: Widget> Widget for Mask<Inner, M> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.inner.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let sublayout = self.inner.layout(ctx, constraints, env);
+        let size = sublayout.padding_box_size();
+        if !ctx.speculative {
+            self.inner
+                .set_offset(Offset::new(sublayout.padding_left, sublayout.padding_top));
+            self.mask.layout(
+                ctx,
+                &LayoutParams {
+                    min: size,
+                    max: size,
+                    ..*constraints
+                },
+                env,
+            );
+        }
+        Geometry {
+            padding_left: 0.0,
+            padding_top: 0.0,
+            padding_right: 0.0,
+            padding_bottom: 0.0,
+            measurements: Measurements {
+                size,
+                clip_bounds: None,
+                baseline: sublayout.padding_box_baseline(),
+            },
+            ..sublayout
+        }
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.inner.route_event(ctx, event, env);
+        if event.pointer_event().is_none() {
+            self.mask.route_event(ctx, event, env);
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let bounds = ctx.bounds.to_skia();
+        // paint the widget in a layer, then the mask in a layer composited with DstIn, which keeps the pixels of the
+        // widget where the mask is opaque
+        ctx.surface
+            .canvas()
+            .save_layer(&sk::canvas::SaveLayerRec::default().bounds(&bounds));
+        self.inner.paint(ctx);
+        let mut mask_paint = sk::Paint::default();
+        mask_paint.set_blend_mode(sk::BlendMode::DstIn);
+        ctx.surface
+            .canvas()
+            .save_layer(&sk::canvas::SaveLayerRec::default().bounds(&bounds).paint(&mask_paint));
+        self.mask.paint(ctx);
+        let canvas = ctx.surface.canvas();
+        canvas.restore();
+        canvas.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_shape_bounds() {
+        let constraints = LayoutParams::default();
+        let env = Environment::new();
+        let size = Size::new(100.0, 50.0);
+        let expected = sk::Rect::new(0.0, 0.0, 100.0, 50.0);
+
+        // the view box is stretched to the bounds of the widget
+        let triangle = ClipShape::svg_path(
+            "M 5 0 L 10 10 L 0 10 Z",
+            Rect::new(Point::origin(), Size::new(10.0, 10.0)),
+        );
+        assert_eq!(*triangle.to_skia_path(size, &constraints, &env).bounds(), expected);
+        let ellipse = ClipShape::ellipse().to_skia_path(size, &constraints, &env);
+        assert_eq!(*ellipse.bounds(), expected);
+        assert!(!ellipse.contains((2.0, 2.0)));
+        assert!(ellipse.contains((50.0, 25.0)));
+
+        // empty view box
+        let empty = ClipShape::svg_path("M 0 0 L 10 10", Rect::zero());
+        assert!(empty.to_skia_path(size, &constraints, &env).is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+//! Handling commands in a subtree.
+use crate::{command::CommandId, widget::prelude::*, InternalEvent};
+
+/// Handles commands sent to the wrapped widget or to one of its descendants.
+///
+/// Commands (see `command::CommandMap`) are sent to the focused widget. The wrapped widget gets the first chance to
+/// handle them; if it doesn't, and the command is one of those handled by the scope, the scope handles it and
+/// signals it (see `CommandScope::on_command`). Otherwise, the command continues up to the enclosing scopes, and
+/// eventually to the global handler of the command.
+pub struct CommandScope<Inner> {
+    inner: Inner,
+    commands: Vec<CommandId>,
+    triggered: Signal<CommandId>,
+}
+
+impl<Inner: Widget + 'static> CommandScope<Inner> {
+    /// Creates a new scope that handles the specified commands.
+    #[composable]
+    pub fn new(commands: impl IntoIterator<Item = CommandId>, inner: Inner) -> CommandScope<Inner> {
+        CommandScope {
+            inner,
+            commands: commands.into_iter().collect(),
+            triggered: Signal::new(),
+        }
+    }
+
+    /// Returns the command that was handled by this scope, if any.
+    pub fn triggered(&self) -> Option<CommandId> {
+        self.triggered.value()
+    }
+
+    /// Runs the function if the specified command was handled by this scope.
+    #[must_use]
+    pub fn on_command(self, id: &CommandId, f: impl FnOnce()) -> Self {
+        if self.triggered.value().as_ref() == Some(id) {
+            f()
+        }
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for CommandScope<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.inner.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.inner.route_event(ctx, event, env);
+        if ctx.handled() {
+            return;
+        }
+
+        // the command is unwrapped if the wrapped widget is the focused widget
+        let command = match *event {
+            Event::Command(ref id) => Some(id),
+            Event::Internal(InternalEvent::RouteEvent { ref event, .. }) => match **event {
+                Event::Command(ref id) => Some(id),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(id) = command {
+            if self.commands.contains(id) {
+                self.triggered.signal(id.clone());
+                ctx.set_handled();
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+}
@@ -0,0 +1,149 @@
+//! Pointer cursors rendered from widgets.
+use crate::{
+    drawing::{Image, ToSkia},
+    widget::prelude::*,
+    PointI, PointerEventKind,
+};
+use kyute_shell::{animation, window::CursorImage, winit::window::CursorIcon};
+use skia_safe as sk;
+
+/// Converts an image to the format of cursor images.
+fn to_cursor_image(image: &Image, hotspot: PointI) -> Option<CursorImage> {
+    let image = image.to_skia();
+    let (width, height) = (image.width(), image.height());
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    let info = sk::ImageInfo::new((width, height), sk::ColorType::BGRA8888, sk::AlphaType::Premul, None);
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    if !image.read_pixels(
+        &info,
+        &mut pixels,
+        width as usize * 4,
+        (0, 0),
+        sk::image::CachingHint::Disallow,
+    ) {
+        warn!("failed to read the pixels of the cursor image");
+        return None;
+    }
+    Some(CursorImage {
+        width: width as u32,
+        height: height as u32,
+        pixels,
+        hotspot_x: hotspot.x,
+        hotspot_y: hotspot.y,
+    })
+}
+
+/// Replaces the pointer cursor with a widget when the pointer is over the contents.
+///
+/// The cursor widget is painted on a compositing layer that follows the pointer: moving the pointer only moves
+/// the layer, without relayout or repaint, so the cursor keeps up with the compositor frame rate.
+/// This is intended for cursors that reflect some state of the application, like the brush size in a paint program.
+///
+/// On platforms without compositing layers (see `kyute_shell::animation::layers_supported`), the cursor widget
+/// isn't used and a static image is shown as the cursor instead (see `fallback_image`). Without a fallback image,
+/// the regular cursor is shown.
+pub struct CustomCursor<W, C> {
+    id: WidgetId,
+    inner: W,
+    cursor: Option<WidgetPod<C>>,
+    hotspot: Offset,
+    fallback_image: Option<CursorImage>,
+}
+
+impl<W: Widget + 'static, C: Widget + 'static> CustomCursor<W, C> {
+    #[composable]
+    pub fn new(inner: W, cursor: C) -> CustomCursor<W, C> {
+        let cursor = if animation::layers_supported() {
+            Some(WidgetPod::with_native_layer(cursor))
+        } else {
+            None
+        };
+        CustomCursor {
+            id: WidgetId::here(),
+            inner,
+            cursor,
+            hotspot: Offset::zero(),
+            fallback_image: None,
+        }
+    }
+
+    /// Sets the position of the pointer within the cursor widget, in DIPs.
+    ///
+    /// By default, the top-left corner of the cursor widget is at the pointer position.
+    pub fn hotspot(mut self, hotspot: Offset) -> Self {
+        self.hotspot = hotspot;
+        self
+    }
+
+    /// Sets the image shown as the cursor on platforms that don't support compositing layers.
+    ///
+    /// `hotspot` is the position of the pointer in the image, in pixels.
+    pub fn fallback_image(mut self, image: &Image, hotspot: PointI) -> Self {
+        self.fallback_image = to_cursor_image(image, hotspot);
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Widget + 'static, C: Widget + 'static> Widget for CustomCursor<W, C> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        if let Some(ref cursor) = self.cursor {
+            // the cursor widget takes its preferred size
+            cursor.layout(
+                ctx,
+                &LayoutParams {
+                    min: Size::zero(),
+                    max: Size::new(f64::INFINITY, f64::INFINITY),
+                    ..*constraints
+                },
+                env,
+            );
+        }
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Event::Pointer(p) = event {
+            match (p.kind, &self.cursor) {
+                (PointerEventKind::PointerOver, Some(cursor)) => ctx.set_cursor_layer(cursor.layer(), self.hotspot),
+                (PointerEventKind::PointerOut, Some(_)) => ctx.set_cursor_layer(None, self.hotspot),
+                (PointerEventKind::PointerOver, None) => {
+                    if let Some(ref image) = self.fallback_image {
+                        ctx.set_cursor_image(image)
+                    }
+                }
+                (PointerEventKind::PointerOut, None) => ctx.set_cursor_icon(CursorIcon::Default),
+                _ => {}
+            }
+        }
+
+        self.inner.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        if let Some(ref cursor) = self.cursor {
+            // the cursor layer isn't part of the layer tree of the window contents, repaint it separately
+            cursor.repaint_layer(ctx.skia_direct_context);
+        }
+        self.inner.paint(ctx)
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new(format!("hotspot: {:?}", self.hotspot))
+    }
+}
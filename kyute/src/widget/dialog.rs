@@ -0,0 +1,151 @@
+//! Modal dialogs.
+use crate::{cache, metrics, widget::prelude::*, Window};
+use kyute_shell::winit::{dpi::LogicalSize, window::WindowBuilder};
+use std::time::Instant;
+
+/// How a dialog was closed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DialogResult<T> {
+    /// The dialog was accepted with a value (see `DialogHandle::accept`).
+    Accepted(T),
+    /// The dialog was cancelled, or closed with the close button of the title bar.
+    Cancelled,
+}
+
+impl<T> DialogResult<T> {
+    /// Returns the value of an accepted dialog.
+    pub fn accepted(self) -> Option<T> {
+        match self {
+            DialogResult::Accepted(value) => Some(value),
+            DialogResult::Cancelled => None,
+        }
+    }
+}
+
+/// Closes a dialog. Passed to the function that creates the contents of the dialog.
+#[derive(Clone)]
+pub struct DialogHandle<T> {
+    open: cache::State<bool>,
+    result: Signal<DialogResult<T>>,
+}
+
+impl<T: Clone + 'static> DialogHandle<T> {
+    /// Closes the dialog and returns `value` to the caller.
+    pub fn accept(&self, value: T) {
+        self.close(DialogResult::Accepted(value));
+    }
+
+    /// Closes the dialog without a value.
+    pub fn cancel(&self) {
+        self.close(DialogResult::Cancelled);
+    }
+
+    fn close(&self, result: DialogResult<T>) {
+        self.result.signal(result);
+        self.open.set(false);
+    }
+}
+
+/// A modal dialog window.
+///
+/// The dialog is hidden until `show` is called. While it is open, it blocks input to the window containing it
+/// (see `Window::show_modal`). The contents close it with a result through a `DialogHandle`, and the result
+/// is available on the next recomposition with `result` or `on_result`.
+pub struct Dialog<T> {
+    id: WidgetId,
+    open: cache::State<bool>,
+    result: Signal<DialogResult<T>>,
+    window: Option<Window>,
+}
+
+impl<T: Clone + 'static> Dialog<T> {
+    /// Creates a dialog with the specified title and size.
+    ///
+    /// `content` is called to create the contents of the dialog only while it is open.
+    #[composable]
+    pub fn new<W: Widget + 'static>(title: &str, size: Size, content: impl FnOnce(&DialogHandle<T>) -> W) -> Dialog<T> {
+        let open = cache::state(|| false);
+        let result: Signal<DialogResult<T>> = Signal::new();
+
+        // time the dialog is open, only if metrics are enabled
+        let opened_at = cache::state(|| None);
+        if metrics::is_enabled() {
+            if open.get() && opened_at.get().is_none() {
+                opened_at.set_without_invalidation(Some(Instant::now()));
+            }
+            if let (Some(result), Some(opened_at_time)) = (result.value(), opened_at.get()) {
+                let accepted = matches!(result, DialogResult::Accepted(_));
+                metrics::dialog_closed(title, opened_at_time.elapsed(), accepted);
+                opened_at.set_without_invalidation(None);
+            }
+        }
+
+        let window = if open.get() {
+            let handle = DialogHandle {
+                open: open.clone(),
+                result: result.clone(),
+            };
+            let content = content(&handle);
+            let window_builder = WindowBuilder::new()
+                .with_title(title)
+                .with_inner_size(LogicalSize::new(size.width, size.height))
+                .with_resizable(false);
+            Some(
+                Window::new(window_builder, content, None)
+                    .show_modal()
+                    .on_close_requested(move || handle.cancel()),
+            )
+        } else {
+            None
+        };
+
+        Dialog {
+            id: WidgetId::here(),
+            open,
+            result,
+            window,
+        }
+    }
+
+    /// Shows the dialog.
+    pub fn show(&self) {
+        // will trigger a recomp
+        self.open.set(true);
+    }
+
+    /// Returns whether the dialog is open.
+    pub fn is_open(&self) -> bool {
+        self.window.is_some()
+    }
+
+    /// Returns the result of the dialog if it has just been closed.
+    pub fn result(&self) -> Option<DialogResult<T>> {
+        self.result.value()
+    }
+
+    /// Calls `f` with the result of the dialog if it has just been closed.
+    pub fn on_result(self, f: impl FnOnce(DialogResult<T>)) -> Self {
+        self.result.map(f);
+        self
+    }
+}
+
+impl<T: Clone + 'static> Widget for Dialog<T> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, _ctx: &mut LayoutCtx, _constraints: &LayoutParams, _env: &Environment) -> Geometry {
+        Geometry::default()
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Some(ref window) = self.window {
+            window.route_event(ctx, event, env);
+        }
+    }
+
+    fn paint(&self, _ctx: &mut PaintCtx) {
+        // nothing to paint: the contents are in a separate window
+    }
+}
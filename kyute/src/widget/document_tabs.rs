@@ -0,0 +1,648 @@
+//! Document tabs for editor-style applications.
+//!
+//! `DocumentManager` tracks the documents open in a window: which one is active, which ones have unsaved changes,
+//! and the documents that were recently closed (so that they can be reopened). Closing a document with unsaved
+//! changes asks the user whether to save it first. `DocumentTabs` shows the documents in a `TabView`, with the
+//! contents of the active document below the tabs, and the confirmation dialog.
+//!
+//! To ask for confirmation when the window is closed, call `DocumentManager::request_close_window` from the
+//! close request handler of the window (see `Window::on_close_requested`), and remove the window from the UI once
+//! all documents are closed (see `DocumentManager::on_all_closed`).
+use crate::{
+    cache,
+    cache::State,
+    widget::{prelude::*, Action, Button, Dialog, DialogHandle, Grid, Menu, MenuItem, Tab, TabView, Text},
+};
+use std::{cell::RefCell, collections::VecDeque};
+
+/// Maximum number of closed documents that can be reopened.
+const MAX_RECENTLY_CLOSED: usize = 16;
+
+/// A document managed by a `DocumentManager`.
+pub trait Document: Clone + 'static {
+    /// Returns the title shown in the tab of the document.
+    fn title(&self) -> String;
+
+    /// Returns whether the document has unsaved changes.
+    fn is_dirty(&self) -> bool;
+
+    /// Saves the document before closing it, when the user chooses to save it.
+    ///
+    /// Returns whether the document was saved. If it wasn't (e.g. the user cancelled the file dialog), the document
+    /// stays open and the pending close requests are cancelled.
+    fn save(&mut self) -> bool;
+}
+
+/// Identifies a document in a `DocumentManager`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DocumentId(u64);
+
+/// Answer of the user to the confirmation dialog shown before closing a document with unsaved changes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CloseChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+#[derive(Clone)]
+struct Session<D> {
+    documents: Vec<(DocumentId, D)>,
+    active: Option<DocumentId>,
+    /// Closed documents, the most recently closed last.
+    recently_closed: Vec<D>,
+    /// Documents with unsaved changes waiting for the confirmation of the user before being closed.
+    pending_close: VecDeque<DocumentId>,
+    /// Whether the pending close requests come from a request to close the window.
+    closing_window: bool,
+    next_id: u64,
+}
+
+impl<D> Default for Session<D> {
+    fn default() -> Self {
+        Session {
+            documents: vec![],
+            active: None,
+            recently_closed: vec![],
+            pending_close: VecDeque::new(),
+            closing_window: false,
+            next_id: 0,
+        }
+    }
+}
+
+impl<D: Document> Session<D> {
+    fn position(&self, id: DocumentId) -> Option<usize> {
+        self.documents.iter().position(|(doc_id, _)| *doc_id == id)
+    }
+
+    fn insert(&mut self, document: D) -> DocumentId {
+        let id = DocumentId(self.next_id);
+        self.next_id += 1;
+        self.documents.push((id, document));
+        self.active = Some(id);
+        id
+    }
+
+    /// Closes a document without confirmation. The next document (or the previous one if it was the last) becomes
+    /// active.
+    fn close(&mut self, id: DocumentId) {
+        if let Some(pos) = self.position(id) {
+            let (_, document) = self.documents.remove(pos);
+            self.recently_closed.push(document);
+            if self.recently_closed.len() > MAX_RECENTLY_CLOSED {
+                self.recently_closed.remove(0);
+            }
+            if self.active == Some(id) {
+                self.active = self
+                    .documents
+                    .get(pos)
+                    .or_else(|| self.documents.last())
+                    .map(|(id, _)| *id);
+            }
+        }
+        self.pending_close.retain(|&pending| pending != id);
+    }
+
+    /// Closes the documents without unsaved changes, and queues the others for confirmation.
+    fn request_close(&mut self, ids: Vec<DocumentId>) {
+        for id in ids {
+            match self.position(id) {
+                Some(pos) if self.documents[pos].1.is_dirty() => {
+                    if !self.pending_close.contains(&id) {
+                        self.pending_close.push_back(id);
+                    }
+                }
+                Some(_) => self.close(id),
+                None => {}
+            }
+        }
+    }
+
+    fn cancel_pending(&mut self) {
+        self.pending_close.clear();
+        self.closing_window = false;
+    }
+
+    /// Moves the document at index `from` to index `to`.
+    fn move_document(&mut self, from: usize, to: usize) {
+        if from < self.documents.len() && to < self.documents.len() {
+            let document = self.documents.remove(from);
+            self.documents.insert(to, document);
+        }
+    }
+
+    /// Reopens the most recently closed document.
+    fn reopen(&mut self) -> Option<DocumentId> {
+        let document = self.recently_closed.pop()?;
+        Some(self.insert(document))
+    }
+
+    /// Returns whether the window can be closed after a request to close it: this is the case once the documents
+    /// with unsaved changes have all been confirmed.
+    fn window_close_done(&mut self) -> bool {
+        if self.closing_window && self.pending_close.is_empty() {
+            self.closing_window = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Closes all documents before closing the window.
+    ///
+    /// Returns whether all documents are closed, or false if some documents wait for confirmation.
+    fn request_close_window(&mut self) -> bool {
+        self.closing_window = true;
+        let all = self.documents.iter().map(|(id, _)| *id).collect();
+        self.request_close(all);
+        self.window_close_done()
+    }
+
+    /// Closes (or keeps) the document waiting for confirmation, depending on the answer of the user.
+    ///
+    /// Returns whether this closed the last document of a request to close the window.
+    fn resolve_pending_close(&mut self, choice: CloseChoice) -> bool {
+        let id = match self.pending_close.front() {
+            Some(&id) => id,
+            None => return false,
+        };
+        match choice {
+            CloseChoice::Save => {
+                let saved = self.position(id).map_or(false, |pos| self.documents[pos].1.save());
+                if saved {
+                    self.close(id);
+                } else {
+                    self.cancel_pending();
+                }
+            }
+            CloseChoice::Discard => self.close(id),
+            CloseChoice::Cancel => self.cancel_pending(),
+        }
+        self.window_close_done()
+    }
+}
+
+/// Tracks the documents open in a window.
+///
+/// Documents are added with `open` and modified with `update`. Requests to close documents with unsaved changes
+/// are confirmed by the user in a dialog shown by `DocumentTabs`.
+#[derive(Clone)]
+pub struct DocumentManager<D> {
+    session: State<Session<D>>,
+    all_closed: Signal<()>,
+}
+
+impl<D: Document> DocumentManager<D> {
+    #[composable]
+    pub fn new() -> DocumentManager<D> {
+        DocumentManager {
+            session: cache::state(Session::default),
+            all_closed: Signal::new(),
+        }
+    }
+
+    fn modify<R>(&self, f: impl FnOnce(&mut Session<D>) -> R) -> R {
+        let mut session = self.session.get();
+        let result = f(&mut session);
+        self.session.set(session);
+        result
+    }
+
+    /// Adds a document and makes it the active document.
+    pub fn open(&self, document: D) -> DocumentId {
+        self.modify(|session| session.insert(document))
+    }
+
+    /// Returns the open documents, in the order of their tabs.
+    pub fn documents(&self) -> Vec<(DocumentId, D)> {
+        self.session.get().documents
+    }
+
+    /// Returns the specified document, if it's open.
+    pub fn document(&self, id: DocumentId) -> Option<D> {
+        let session = self.session.get();
+        session.position(id).map(|pos| session.documents[pos].1.clone())
+    }
+
+    /// Returns the active document.
+    pub fn active(&self) -> Option<DocumentId> {
+        self.session.get().active
+    }
+
+    /// Makes a document the active document.
+    pub fn activate(&self, id: DocumentId) {
+        self.modify(|session| {
+            if session.position(id).is_some() {
+                session.active = Some(id);
+            }
+        })
+    }
+
+    /// Modifies a document.
+    pub fn update(&self, id: DocumentId, f: impl FnOnce(&mut D)) {
+        self.modify(|session| {
+            if let Some(pos) = session.position(id) {
+                f(&mut session.documents[pos].1);
+            }
+        })
+    }
+
+    /// Returns whether any open document has unsaved changes.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.session
+            .get()
+            .documents
+            .iter()
+            .any(|(_, document)| document.is_dirty())
+    }
+
+    /// Closes a document, asking for confirmation if it has unsaved changes.
+    pub fn request_close(&self, id: DocumentId) {
+        self.modify(|session| session.request_close(vec![id]))
+    }
+
+    /// Closes all documents except the specified one.
+    pub fn request_close_others(&self, id: DocumentId) {
+        self.modify(|session| {
+            let others = session
+                .documents
+                .iter()
+                .map(|(doc_id, _)| *doc_id)
+                .filter(|&other| other != id)
+                .collect();
+            session.request_close(others)
+        })
+    }
+
+    /// Closes the documents whose tabs are after the tab of the specified document.
+    pub fn request_close_to_the_right(&self, id: DocumentId) {
+        self.modify(|session| {
+            if let Some(pos) = session.position(id) {
+                let right = session.documents[pos + 1..].iter().map(|(doc_id, _)| *doc_id).collect();
+                session.request_close(right)
+            }
+        })
+    }
+
+    /// Closes all documents.
+    pub fn request_close_all(&self) {
+        self.modify(|session| {
+            let all = session.documents.iter().map(|(id, _)| *id).collect();
+            session.request_close(all)
+        })
+    }
+
+    /// Closes all documents before the window is closed.
+    ///
+    /// Once all documents are closed, `all_closed` returns true. If the user cancels the confirmation of
+    /// a document, the documents with unsaved changes stay open and the window should stay open as well.
+    pub fn request_close_window(&self) {
+        if self.modify(|session| session.request_close_window()) {
+            self.all_closed.signal(());
+        }
+    }
+
+    /// Returns whether all documents have just been closed following a call to `request_close_window`.
+    pub fn all_closed(&self) -> bool {
+        self.all_closed.signalled()
+    }
+
+    /// Calls `f` if all documents have just been closed following a call to `request_close_window`.
+    pub fn on_all_closed(self, f: impl FnOnce()) -> Self {
+        if self.all_closed() {
+            f();
+        }
+        self
+    }
+
+    /// Returns whether there's a closed document that can be reopened.
+    pub fn can_reopen(&self) -> bool {
+        !self.session.get().recently_closed.is_empty()
+    }
+
+    /// Reopens the most recently closed document.
+    pub fn reopen_closed(&self) -> Option<DocumentId> {
+        self.modify(|session| session.reopen())
+    }
+
+    /// Moves the tab of the document at index `from` to index `to`.
+    pub fn move_document(&self, from: usize, to: usize) {
+        self.modify(|session| session.move_document(from, to))
+    }
+
+    /// Returns the document waiting for confirmation before being closed.
+    fn pending_close(&self) -> Option<(DocumentId, D)> {
+        let session = self.session.get();
+        let id = *session.pending_close.front()?;
+        session.position(id).map(|pos| session.documents[pos].clone())
+    }
+
+    /// Closes (or keeps) the document waiting for confirmation, depending on the answer of the user.
+    fn resolve_pending_close(&self, choice: CloseChoice) {
+        if self.modify(|session| session.resolve_pending_close(choice)) {
+            self.all_closed.signal(());
+        }
+    }
+}
+
+/// Tabs showing the documents of a `DocumentManager`, above the contents of the active document.
+///
+/// The tabs are shown in a `TabView`. Tabs of documents with unsaved changes show a dot instead of the close button.
+/// The context menu of a tab has commands to close the document, the other documents, the documents to the right of
+/// it, and to reopen the last closed document.
+#[derive(Widget)]
+pub struct DocumentTabs {
+    inner: Grid,
+}
+
+impl DocumentTabs {
+    /// Creates the tabs.
+    ///
+    /// `content` is called to create the contents of the active document.
+    #[composable]
+    pub fn new<D: Document, W: Widget + 'static>(
+        documents: &DocumentManager<D>,
+        content: impl FnOnce(DocumentId, &D) -> W,
+    ) -> DocumentTabs {
+        let mut grid = Grid::with_template("1fr / 1fr");
+
+        // confirmation dialog
+        let pending = documents.pending_close();
+        let title = pending
+            .as_ref()
+            .map(|(_, document)| document.title())
+            .unwrap_or_default();
+        let dialog = Dialog::new(
+            "Unsaved changes",
+            Size::new(400.0, 120.0),
+            |handle: &DialogHandle<CloseChoice>| {
+                let mut dialog_grid = Grid::with_template("1fr auto / 1fr auto auto auto");
+                dialog_grid.set_column_gap(4.dip());
+                let message = Text::new(format!("Save the changes made to \"{}\" before closing?", title));
+                dialog_grid.place((0, ..), 0, message.vertical_alignment(Alignment::CENTER).arc_dyn_pod());
+                let save = Button::new("Save").on_click(|| handle.accept(CloseChoice::Save));
+                let discard = Button::new("Don't Save").on_click(|| handle.accept(CloseChoice::Discard));
+                let cancel = Button::new("Cancel").on_click(|| handle.cancel());
+                dialog_grid.place((1, 1), 0, save.arc_dyn_pod());
+                dialog_grid.place((1, 2), 0, discard.arc_dyn_pod());
+                dialog_grid.place((1, 3), 0, cancel.arc_dyn_pod());
+                dialog_grid.padding(8.dip())
+            },
+        )
+        .on_result(|result| documents.resolve_pending_close(result.accepted().unwrap_or(CloseChoice::Cancel)));
+        if pending.is_some() && !dialog.is_open() && dialog.result().is_none() {
+            dialog.show();
+        }
+        grid.place((0, 0), 1, dialog.arc_dyn_pod());
+
+        // tabs; only the tab of the active document calls `content`
+        let open_documents = documents.documents();
+        let active = documents.active();
+        let selected = open_documents
+            .iter()
+            .position(|(id, _)| Some(*id) == active)
+            .unwrap_or(0);
+        let content = RefCell::new(Some(content));
+        let can_reopen = documents.can_reopen();
+        let mut tabs = Vec::with_capacity(open_documents.len());
+        for (id, document) in open_documents.iter().cloned() {
+            let menu = cache::scoped(id, || {
+                let close_action = Action::new().on_triggered(|| documents.request_close(id));
+                let close_others_action = Action::new().on_triggered(|| documents.request_close_others(id));
+                let close_right_action = Action::new().on_triggered(|| documents.request_close_to_the_right(id));
+                let reopen_action = Action::new().on_triggered(|| {
+                    documents.reopen_closed();
+                });
+                let mut menu_items = vec![
+                    MenuItem::new("Close", close_action),
+                    MenuItem::new("Close Others", close_others_action),
+                    MenuItem::new("Close Tabs to the Right", close_right_action),
+                ];
+                if can_reopen {
+                    menu_items.push(MenuItem::Separator);
+                    menu_items.push(MenuItem::new("Reopen Closed Tab", reopen_action));
+                }
+                Menu::new(menu_items)
+            });
+            let title = document.title();
+            let dirty = document.is_dirty();
+            let content = &content;
+            tabs.push(
+                Tab::new(id, title, move || {
+                    let content = content
+                        .borrow_mut()
+                        .take()
+                        .expect("content of the active document created twice");
+                    content(id, &document)
+                })
+                .closable(true)
+                .modified(dirty)
+                .context_menu(menu),
+            );
+        }
+        let tab_view = TabView::new(selected, tabs);
+
+        // closing and moving tabs also change the selected index, but the active document is tracked by ID
+        if let Some(index) = tab_view.tab_closed() {
+            documents.request_close(open_documents[index].0);
+        } else if let Some((from, to)) = tab_view.tab_moved() {
+            documents.move_document(from, to);
+        } else if let Some(index) = tab_view.selection_changed() {
+            documents.activate(open_documents[index].0);
+        }
+        grid.place((0, 0), 0, tab_view.arc_dyn_pod());
+
+        DocumentTabs { inner: grid }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestDocument {
+        name: &'static str,
+        dirty: bool,
+        /// Whether `save` succeeds.
+        can_save: bool,
+    }
+
+    impl TestDocument {
+        fn clean(name: &'static str) -> TestDocument {
+            TestDocument {
+                name,
+                dirty: false,
+                can_save: true,
+            }
+        }
+
+        fn dirty(name: &'static str) -> TestDocument {
+            TestDocument {
+                name,
+                dirty: true,
+                can_save: true,
+            }
+        }
+    }
+
+    impl Document for TestDocument {
+        fn title(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn is_dirty(&self) -> bool {
+            self.dirty
+        }
+
+        fn save(&mut self) -> bool {
+            if self.can_save {
+                self.dirty = false;
+            }
+            self.can_save
+        }
+    }
+
+    fn names(session: &Session<TestDocument>) -> Vec<&'static str> {
+        session.documents.iter().map(|(_, document)| document.name).collect()
+    }
+
+    #[test]
+    fn close_activates_neighbour() {
+        let mut session = Session::default();
+        let a = session.insert(TestDocument::clean("a"));
+        let b = session.insert(TestDocument::clean("b"));
+        let c = session.insert(TestDocument::clean("c"));
+        assert_eq!(session.active, Some(c));
+
+        // closing the last tab activates the previous one
+        session.close(c);
+        assert_eq!(session.active, Some(b));
+        // closing an inactive tab keeps the active one
+        session.close(a);
+        assert_eq!(session.active, Some(b));
+        session.close(b);
+        assert_eq!(session.active, None);
+
+        let d = session.insert(TestDocument::clean("d"));
+        let e = session.insert(TestDocument::clean("e"));
+        session.active = Some(d);
+        // closing the active tab activates the next one
+        session.close(d);
+        assert_eq!(session.active, Some(e));
+    }
+
+    #[test]
+    fn request_close_confirms_dirty_documents() {
+        let mut session = Session::default();
+        let a = session.insert(TestDocument::clean("a"));
+        let b = session.insert(TestDocument::dirty("b"));
+        let c = session.insert(TestDocument::dirty("c"));
+
+        session.request_close(vec![a, b, c]);
+        assert_eq!(names(&session), ["b", "c"]);
+        assert_eq!(session.pending_close, [b, c]);
+        // requesting again doesn't queue twice
+        session.request_close(vec![b]);
+        assert_eq!(session.pending_close, [b, c]);
+
+        assert!(!session.resolve_pending_close(CloseChoice::Discard));
+        assert_eq!(names(&session), ["c"]);
+        assert!(!session.resolve_pending_close(CloseChoice::Save));
+        assert!(session.documents.is_empty());
+        assert!(session.pending_close.is_empty());
+        // the saved document is reopened without unsaved changes
+        assert_eq!(session.recently_closed.last(), Some(&TestDocument::clean("c")));
+    }
+
+    #[test]
+    fn cancel_keeps_documents_open() {
+        let mut session = Session::default();
+        let a = session.insert(TestDocument::dirty("a"));
+        let b = session.insert(TestDocument {
+            can_save: false,
+            ..TestDocument::dirty("b")
+        });
+        let c = session.insert(TestDocument::dirty("c"));
+
+        session.request_close(vec![a, b, c]);
+        session.resolve_pending_close(CloseChoice::Cancel);
+        assert_eq!(names(&session), ["a", "b", "c"]);
+        assert!(session.pending_close.is_empty());
+
+        // a failed save cancels the remaining requests
+        session.request_close(vec![b, c]);
+        session.resolve_pending_close(CloseChoice::Save);
+        assert_eq!(names(&session), ["a", "b", "c"]);
+        assert!(session.pending_close.is_empty());
+    }
+
+    #[test]
+    fn close_window() {
+        let mut session = Session::default();
+        session.insert(TestDocument::clean("a"));
+        assert!(session.request_close_window());
+        assert!(session.documents.is_empty());
+
+        session.insert(TestDocument::clean("b"));
+        session.insert(TestDocument::dirty("c"));
+        session.insert(TestDocument::dirty("d"));
+        assert!(!session.request_close_window());
+        assert_eq!(names(&session), ["c", "d"]);
+        assert!(!session.resolve_pending_close(CloseChoice::Discard));
+        assert!(session.resolve_pending_close(CloseChoice::Discard));
+        assert!(!session.closing_window);
+
+        // cancelling keeps the window open
+        session.insert(TestDocument::dirty("e"));
+        assert!(!session.request_close_window());
+        assert!(!session.resolve_pending_close(CloseChoice::Cancel));
+        assert!(!session.closing_window);
+        assert_eq!(names(&session), ["e"]);
+    }
+
+    #[test]
+    fn reopen() {
+        let mut session = Session::default();
+        let ids: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|&name| session.insert(TestDocument::clean(name)))
+            .collect();
+        session.close(ids[0]);
+        session.close(ids[2]);
+
+        // the most recently closed document is reopened first, at the end, and becomes active
+        let reopened = session.reopen();
+        assert!(reopened.is_some());
+        assert_ne!(reopened, Some(ids[2]));
+        assert_eq!(session.active, reopened);
+        assert_eq!(names(&session), ["b", "c"]);
+        session.reopen();
+        assert_eq!(names(&session), ["b", "c", "a"]);
+        assert_eq!(session.reopen(), None);
+
+        // only the last closed documents are kept
+        for _ in 0..MAX_RECENTLY_CLOSED + 2 {
+            let id = session.insert(TestDocument::clean("x"));
+            session.close(id);
+        }
+        assert_eq!(session.recently_closed.len(), MAX_RECENTLY_CLOSED);
+    }
+
+    #[test]
+    fn move_document() {
+        let mut session = Session::default();
+        session.insert(TestDocument::clean("a"));
+        let b = session.insert(TestDocument::clean("b"));
+        session.insert(TestDocument::clean("c"));
+        session.active = Some(b);
+
+        session.move_document(0, 2);
+        assert_eq!(names(&session), ["b", "c", "a"]);
+        assert_eq!(session.active, Some(b));
+        // out of range
+        session.move_document(1, 3);
+        assert_eq!(names(&session), ["b", "c", "a"]);
+    }
+}
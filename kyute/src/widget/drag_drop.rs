@@ -1,10 +1,23 @@
 //! Drag & drop widgets.
 
-use crate::{shell::TypedData, widget::prelude::*};
+use crate::{
+    cache,
+    event::{PointerButton, PointerEventKind},
+    shell::{DragImage, DropEffect, DropEffects, TypedData},
+    widget::prelude::*,
+};
+use parking_lot::Mutex;
+use skia_safe as sk;
+use std::sync::Arc;
 
+/// Receives data dropped on the inner widget, e.g. files dragged from the file explorer.
+///
+/// Drop targets nested in the inner widget receive the data first. The data has already been checked against the
+/// `DataTransferPolicy` of the application (see `shell::set_data_transfer_policy`): rejected data is never delivered.
 pub struct DropTarget<Content> {
     id: WidgetId,
     content: Content,
+    dropped: Signal<TypedData>,
 }
 
 impl<Content> DropTarget<Content> {
@@ -13,13 +26,32 @@ impl<Content> DropTarget<Content> {
         DropTarget {
             id: WidgetId::here(),
             content,
+            dropped: Signal::new(),
         }
     }
 
+    /// Returns the data if data has just been dropped on the widget.
+    pub fn dropped(&self) -> Option<TypedData> {
+        self.dropped.value()
+    }
+
+    /// Runs the function when data has been dropped on the widget.
     pub fn on_drop(self, f: impl FnOnce(&TypedData)) -> Self {
-        // TODO
+        if let Some(data) = self.dropped() {
+            f(&data)
+        }
         self
     }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Content {
+        &self.content
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Content {
+        &mut self.content
+    }
 }
 
 impl<Content: Widget + 'static> Widget for DropTarget<Content> {
@@ -32,14 +64,194 @@ impl<Content: Widget + 'static> Widget for DropTarget<Content> {
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
-        // when a file or anything else is dragged onto the window, the window receives a native event
-        // containing a pointer to the payload.
-        //
+        self.content.route_event(ctx, event, env);
 
-        todo!()
+        // the window delivers the dropped data once it has gone through `shell::filter_incoming_data`
+        if let Event::Drop(ref drop_event) = *event {
+            if !ctx.handled() {
+                self.dropped.signal(drop_event.data.clone());
+                ctx.set_handled();
+            }
+        }
     }
 
     fn paint(&self, ctx: &mut PaintCtx) {
         self.content.paint(ctx)
     }
 }
+
+/// Distance that the pointer must travel with the button pressed before a drag operation starts, in DIPs.
+const DRAG_THRESHOLD: f64 = 4.0;
+
+/// State of a press that may turn into a drag operation.
+struct PendingDrag {
+    /// Position of the press, in local coordinates.
+    position: Point,
+    /// Snapshot of the contents, taken during the first repaint after the press.
+    image: Option<DragImage>,
+}
+
+/// Lets the user drag the inner widget with the left mouse button to start an OS drag and drop operation
+/// carrying the specified data.
+///
+/// Presses that the inner widget handles (e.g. on a button) don't start a drag. The drag image is a snapshot of
+/// the inner widget as it was last painted. Once the data is dropped (or the operation is cancelled), the effect
+/// chosen by the drop target is reported with `on_drag_finished`.
+pub struct DragSource<Content> {
+    id: WidgetId,
+    content: Content,
+    data: Vec<TypedData>,
+    allowed_effects: DropEffects,
+    pending: Arc<Mutex<Option<PendingDrag>>>,
+    finished: Signal<DropEffect>,
+}
+
+impl<Content: Widget + 'static> DragSource<Content> {
+    #[composable]
+    pub fn new(content: Content, data: Vec<TypedData>) -> DragSource<Content> {
+        DragSource {
+            id: WidgetId::here(),
+            content,
+            data,
+            allowed_effects: DropEffects::default(),
+            pending: cache::once(|| Arc::new(Mutex::new(None))),
+            finished: Signal::new(),
+        }
+    }
+
+    /// Sets the effects that drop targets may choose from. The default is `DropEffects::COPY`.
+    pub fn allowed_effects(mut self, allowed_effects: DropEffects) -> Self {
+        self.allowed_effects = allowed_effects;
+        self
+    }
+
+    /// Returns the effect chosen by the drop target if a drag operation has just finished.
+    ///
+    /// `DropEffect::None` is returned if the operation was cancelled.
+    pub fn drag_finished(&self) -> Option<DropEffect> {
+        self.finished.value()
+    }
+
+    /// Runs the function when a drag operation has finished.
+    pub fn on_drag_finished(self, f: impl FnOnce(DropEffect)) -> Self {
+        if let Some(effect) = self.drag_finished() {
+            f(effect)
+        }
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Content {
+        &self.content
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Content {
+        &mut self.content
+    }
+}
+
+/// Reads back the pixels of the surface under `bounds` (in local coordinates).
+///
+/// `hotspot` is the position of the cursor in the image, in local coordinates.
+fn snapshot(ctx: &mut PaintCtx, bounds: Rect, hotspot: Point) -> Option<DragImage> {
+    let scale_factor = ctx.scale_factor;
+    let device_bounds = ctx.layer_transform().outer_transformed_rect(&bounds);
+    let device_bounds = Rect::new(
+        (device_bounds.origin.to_vector() * scale_factor).to_point(),
+        device_bounds.size * scale_factor,
+    )
+    .round_out();
+    let hotspot = ctx.layer_transform().transform_point(hotspot) * scale_factor;
+
+    let width = device_bounds.size.width as i32;
+    let height = device_bounds.size.height as i32;
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    let info = sk::ImageInfo::new((width, height), sk::ColorType::BGRA8888, sk::AlphaType::Premul, None);
+    let row_bytes = width as usize * 4;
+    let mut pixels = vec![0u8; row_bytes * height as usize];
+    let src = (device_bounds.origin.x as i32, device_bounds.origin.y as i32);
+    if !ctx.surface.read_pixels(&info, &mut pixels, row_bytes, src) {
+        warn!("failed to read back the drag image");
+        return None;
+    }
+
+    Some(DragImage {
+        width: width as u32,
+        height: height as u32,
+        pixels,
+        hotspot_x: (hotspot.x - device_bounds.origin.x) as i32,
+        hotspot_y: (hotspot.y - device_bounds.origin.y) as i32,
+    })
+}
+
+impl<Content: Widget + 'static> Widget for DragSource<Content> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, params: &LayoutParams, env: &Environment) -> Geometry {
+        self.content.layout(ctx, params, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        let mut pending = self.pending.lock();
+
+        if let Event::Pointer(p) = event {
+            match p.kind {
+                PointerEventKind::PointerMove if pending.is_some() => {
+                    let moved = (p.position - pending.as_ref().unwrap().position).length();
+                    if moved > DRAG_THRESHOLD {
+                        let image = pending.take().unwrap().image;
+                        let finished = self.finished.clone();
+                        ctx.start_drag(self.data.clone(), self.allowed_effects, image, move |effect| {
+                            finished.signal(effect)
+                        });
+                    }
+                    ctx.set_handled();
+                    return;
+                }
+                PointerEventKind::PointerUp if pending.is_some() => {
+                    // released without moving enough: not a drag
+                    *pending = None;
+                    ctx.release_pointer();
+                    ctx.set_handled();
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        self.content.route_event(ctx, event, env);
+
+        if let Event::Pointer(p) = event {
+            if p.kind == PointerEventKind::PointerDown && p.button == Some(PointerButton::LEFT) && !ctx.handled() {
+                *pending = Some(PendingDrag {
+                    position: p.position,
+                    image: None,
+                });
+                ctx.capture_pointer();
+                // take the snapshot of the contents
+                ctx.request_repaint();
+                ctx.set_handled();
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx);
+
+        if let Some(pending) = self.pending.lock().as_mut() {
+            if pending.image.is_none() {
+                let bounds = ctx.bounds;
+                pending.image = snapshot(ctx, bounds, pending.position);
+            }
+        }
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new(format!("drag source ({} items)", self.data.len()))
+    }
+}
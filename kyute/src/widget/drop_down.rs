@@ -1,13 +1,26 @@
 use crate::{
+    cache,
     event::{PointerButton, PointerEventKind},
-    widget::{prelude::*, Label},
-    Signal, UnitExt,
+    theme,
+    widget::{prelude::*, Clickable, Grid, Label, Null, Popup, ScrollAlignment, Side, Text, TextEdit, VirtualList},
+    InternalEvent, Signal, State, UnitExt,
 };
+use keyboard_types::{Key, KeyState};
 use std::{
     convert::TryInto,
     fmt::{Debug, Display},
+    sync::Arc,
 };
 
+/// Width of the list of choices (see `DropDown::with_list`), in DIPs.
+const LIST_WIDTH: f64 = 240.0;
+/// Maximum height of the list of choices, in DIPs. Longer lists are scrolled.
+const MAX_LIST_HEIGHT: f64 = 320.0;
+/// Height of the rows of the list of choices, in DIPs.
+const LIST_ROW_HEIGHT: f64 = 24.0;
+/// Height of the filter field above the list of choices, in DIPs.
+const FILTER_HEIGHT: f64 = 34.0;
+
 #[derive(Clone, Debug)]
 struct DropDownChoice<T> {
     value: T,
@@ -15,6 +28,17 @@ struct DropDownChoice<T> {
     item_id: u16,
 }
 
+/// An entry in the choices of a drop-down (see `DropDown::with_entries`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Entry<T> {
+    /// A choice.
+    Item(T),
+    /// A line between two groups of choices.
+    Separator,
+    /// Title of the choices that follow, up to the next separator or header. Headers can't be selected.
+    Header(String),
+}
+
 /// Formatter for drop-down options.
 pub trait Formatter<T> {
     fn format(&self, value: &T) -> String;
@@ -38,14 +62,284 @@ impl<T: Debug> Formatter<T> for DebugFormatter {
     }
 }
 
+/// Separates the choices from the layout of the entries. In the returned entries, items are replaced by the
+/// index of the choice.
+fn split_entries<T>(
+    entries: Vec<Entry<T>>,
+    formatter: &impl Formatter<T>,
+) -> (Vec<DropDownChoice<T>>, Vec<Entry<usize>>) {
+    let mut choices = Vec::new();
+    let mut layout = Vec::new();
+    for entry in entries {
+        match entry {
+            Entry::Item(value) => {
+                let index = choices.len();
+                choices.push(DropDownChoice {
+                    name: formatter.format(&value),
+                    value,
+                    item_id: index.try_into().unwrap(),
+                });
+                layout.push(Entry::Item(index));
+            }
+            Entry::Separator => layout.push(Entry::Separator),
+            Entry::Header(title) => layout.push(Entry::Header(title)),
+        }
+    }
+    (choices, layout)
+}
+
+/// Returns the entries shown in the list for the specified filter.
+///
+/// The choices whose name contains the filter, ignoring case, are shown. Headers are shown if one of the choices of
+/// their group is, and separators only between groups that are shown.
+fn filter_rows<'a, T>(
+    entries: &'a [Entry<usize>],
+    choices: &[DropDownChoice<T>],
+    filter: &str,
+) -> Vec<&'a Entry<usize>> {
+    let filter = filter.to_lowercase();
+    let mut rows = Vec::new();
+    let mut header = None;
+    let mut separator = false;
+    for entry in entries {
+        match entry {
+            Entry::Item(index) => {
+                if filter.is_empty() || choices[*index].name.to_lowercase().contains(&filter) {
+                    if separator && !rows.is_empty() {
+                        rows.push(&Entry::Separator);
+                    }
+                    rows.extend(header.take());
+                    rows.push(entry);
+                    separator = false;
+                }
+            }
+            Entry::Separator => {
+                separator = true;
+                header = None;
+            }
+            Entry::Header(_) => header = Some(entry),
+        }
+    }
+    rows
+}
+
+/// List of the choices of a drop-down, in its popup window.
+///
+/// Handles the navigation keys before the filter field, which has the focus.
+struct ItemList {
+    id: WidgetId,
+    content: WidgetPod,
+    searchable: bool,
+    /// Choices shown in the list, in order.
+    items: Vec<usize>,
+    /// Highlighted choice, if any is shown.
+    current: Option<usize>,
+    highlighted: State<usize>,
+    /// Choice clicked in the list.
+    clicked: Option<usize>,
+    /// Signalled with the highlighted choice when `Enter` is pressed.
+    chosen: Signal<usize>,
+    /// Signalled when `Escape` is pressed.
+    dismissed: Signal<()>,
+    /// Signalled when the highlight has been moved with the keyboard.
+    moved: Signal<()>,
+}
+
+impl ItemList {
+    #[composable]
+    fn new<T, W>(
+        choices: &[DropDownChoice<T>],
+        entries: &[Entry<usize>],
+        filter: &State<String>,
+        highlighted: &State<usize>,
+        searchable: bool,
+        item: impl Fn(&T) -> W,
+    ) -> ItemList
+    where
+        T: Clone + 'static,
+        W: Widget + 'static,
+    {
+        let chosen = Signal::new();
+        let dismissed = Signal::new();
+        let moved = Signal::new();
+
+        let filter_text = filter.get();
+        let rows = filter_rows(entries, choices, &filter_text);
+        let items: Vec<usize> = rows
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Item(index) => Some(*index),
+                _ => None,
+            })
+            .collect();
+        // when the filter hides the highlighted choice, the first one shown is highlighted instead
+        let current = Some(highlighted.get())
+            .filter(|index| items.contains(index))
+            .or_else(|| items.first().copied());
+
+        let colors = theme::tokens(&cache::environment()).colors;
+        let mut clicked = None;
+        let mut list = VirtualList::new(rows.len(), LIST_ROW_HEIGHT, |i| match *rows[i] {
+            Entry::Item(index) => {
+                let row = item(&choices[index].value)
+                    .vertical_alignment(Alignment::CENTER)
+                    .padding_left(8.dip())
+                    .frame(100.percent(), LIST_ROW_HEIGHT.dip());
+                let row = if current == Some(index) {
+                    row.background(colors.accent)
+                } else {
+                    row.background(colors.content_background)
+                };
+                let row = Clickable::new(row);
+                if row.clicked() {
+                    clicked = Some(index);
+                }
+                // the focus stays on the filter field
+                row.focusable(false).arc_dyn_pod()
+            }
+            Entry::Separator => Null
+                .frame(100.percent(), 1.dip())
+                .background(colors.border)
+                .padding_top(4.dip())
+                .padding_bottom(4.dip())
+                .arc_dyn_pod(),
+            Entry::Header(ref title) => Text::new(title.clone())
+                .color(theme::SECONDARY_TEXT_COLOR)
+                .vertical_alignment(Alignment::CENTER)
+                .padding_left(4.dip())
+                .frame(100.percent(), LIST_ROW_HEIGHT.dip())
+                .arc_dyn_pod(),
+        });
+        if moved.signalled() {
+            // keep the highlighted choice visible
+            let row = current.and_then(|current| rows.iter().position(|entry| **entry == Entry::Item(current)));
+            if let Some(row) = row {
+                list = list.scroll_to_index(row, ScrollAlignment::Nearest);
+            }
+        }
+
+        let mut grid = Grid::with_template(if searchable { "auto 1fr / 1fr" } else { "1fr / 1fr" });
+        if searchable {
+            let filter = filter.clone();
+            grid.insert(
+                TextEdit::new(filter_text)
+                    .on_text_changed(move |text| filter.set(text.to_string()))
+                    .padding(4.dip()),
+            );
+        }
+        grid.insert(list);
+
+        ItemList {
+            id: WidgetId::here(),
+            content: WidgetPod::new(
+                grid.frame(100.percent(), 100.percent())
+                    .background(colors.content_background),
+            ),
+            searchable,
+            items,
+            current,
+            highlighted: highlighted.clone(),
+            clicked,
+            chosen,
+            dismissed,
+            moved,
+        }
+    }
+
+    /// Returns the choice that was clicked, or selected with the keyboard.
+    fn chosen(&self) -> Option<usize> {
+        self.chosen.value().or(self.clicked)
+    }
+
+    /// Handles a navigation key. Returns whether the key was handled.
+    fn navigate(&self, key: &Key) -> bool {
+        match key {
+            Key::ArrowDown | Key::ArrowUp => {
+                if let Some(last) = self.items.len().checked_sub(1) {
+                    let position = self
+                        .current
+                        .and_then(|current| self.items.iter().position(|&index| index == current));
+                    let next = match (key, position) {
+                        (Key::ArrowDown, Some(position)) => (position + 1).min(last),
+                        (Key::ArrowUp, Some(position)) => position.saturating_sub(1),
+                        _ => 0,
+                    };
+                    self.highlighted.set(self.items[next]);
+                    self.moved.signal(());
+                }
+                true
+            }
+            Key::Enter => {
+                if let Some(current) = self.current {
+                    self.chosen.signal(current);
+                }
+                true
+            }
+            Key::Escape => {
+                self.dismissed.signal(());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Widget for ItemList {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.content.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        let key = match *event {
+            Event::Keyboard(ref key) => Some(key),
+            // keyboard events are sent to the focused widget: see them before the filter field
+            Event::Internal(InternalEvent::RouteEvent { event: ref inner, .. }) => match **inner {
+                Event::Keyboard(ref key) => Some(key),
+                _ => None,
+            },
+            _ => None,
+        };
+        if key.map_or(false, |key| key.state == KeyState::Down && self.navigate(&key.key)) {
+            ctx.set_handled();
+            return;
+        }
+        self.content.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx)
+    }
+
+    // without a filter field, the list itself receives the keyboard events
+    fn is_focusable(&self) -> bool {
+        !self.searchable
+    }
+}
+
 type DropDownInner = impl Widget;
 
+/// How the choices of a drop-down are shown.
+enum Presentation {
+    /// In a native popup menu.
+    Menu(DropDownInner),
+    /// In a list in a popup window (see `DropDown::with_list`).
+    List { popup: Popup, opened: Signal<()> },
+}
+
 /// Selects one option among choices with a drop-down menu.
 pub struct DropDown<T> {
     id: WidgetId,
     choices: Vec<DropDownChoice<T>>,
+    /// Choices, separators and headers, in order. Choices are referred to by index.
+    entries: Vec<Entry<usize>>,
     selected_item_changed: Signal<(usize, T)>,
-    inner: DropDownInner,
+    /// Choice selected in the list.
+    chosen: Option<usize>,
+    presentation: Presentation,
 }
 
 fn drop_down_inner(choice: String) -> DropDownInner {
@@ -68,30 +362,92 @@ impl<T: Clone + 'static> DropDown<T> {
     /// Creates a new drop down with the specified choices.
     #[composable]
     pub fn with_selected_index(selected_index: usize, choices: Vec<T>, formatter: impl Formatter<T>) -> DropDown<T> {
-        let inner = drop_down_inner(formatter.format(&choices[selected_index]));
-
-        // create menu IDs for each choice
-        let mut choices_with_ids = Vec::new();
-        for (i, choice) in choices.into_iter().enumerate() {
-            let name = formatter.format(&choice);
-            choices_with_ids.push(DropDownChoice {
-                value: choice,
-                name,
-                item_id: i.try_into().unwrap(),
-            })
+        DropDown::with_entries(
+            selected_index,
+            choices.into_iter().map(Entry::Item).collect(),
+            formatter,
+        )
+    }
+
+    /// Creates a new drop down with choices in groups, separated by separators or headers.
+    ///
+    /// `selected_index` is the index of the selected choice among the `Entry::Item`s.
+    #[composable]
+    pub fn with_entries(selected_index: usize, entries: Vec<Entry<T>>, formatter: impl Formatter<T>) -> DropDown<T> {
+        let (choices, entries) = split_entries(entries, &formatter);
+        let inner = drop_down_inner(choices[selected_index].name.clone());
+        DropDown {
+            id: WidgetId::here(),
+            choices,
+            entries,
+            selected_item_changed: Signal::new(),
+            chosen: None,
+            presentation: Presentation::Menu(inner),
+        }
+    }
+
+    /// Creates a new drop down that shows its choices in a list in a popup window, instead of a native menu.
+    ///
+    /// `item` creates the widget showing a choice in the list. If `searchable` is true, a text field above the list
+    /// filters the choices: only those whose formatted name contains the text, ignoring case, are shown. The arrow
+    /// keys move the highlight in the list, `Enter` selects the highlighted choice and `Escape` closes the list.
+    ///
+    /// `selected_index` is the index of the selected choice among the `Entry::Item`s.
+    #[composable]
+    pub fn with_list<W: Widget + 'static>(
+        selected_index: usize,
+        entries: Vec<Entry<T>>,
+        formatter: impl Formatter<T>,
+        searchable: bool,
+        item: impl Fn(&T) -> W,
+    ) -> DropDown<T> {
+        let (choices, entries) = split_entries(entries, &formatter);
+        let opened = Signal::new();
+        let filter = cache::state(String::new);
+        let highlighted = cache::state(|| selected_index);
+
+        let list = ItemList::new(&choices, &entries, &filter, &highlighted, searchable, item);
+        let chosen = list.chosen();
+        let dismissed = list.dismissed.signalled();
+
+        // sized for all the choices, since the window isn't resized when filtering
+        let filter_height = if searchable { FILTER_HEIGHT } else { 0.0 };
+        let height = (filter_height + LIST_ROW_HEIGHT * entries.len() as f64).min(MAX_LIST_HEIGHT);
+        let popup = Popup::anchored(
+            drop_down_inner(choices[selected_index].name.clone()),
+            list,
+            Size::new(LIST_WIDTH, height),
+            Side::Bottom,
+        );
+        if opened.signalled() {
+            if popup.is_shown() {
+                popup.hide();
+            } else {
+                // start from the selected choice, with all choices shown
+                filter.set(String::new());
+                highlighted.set(selected_index);
+                popup.show();
+            }
+        } else if chosen.is_some() || dismissed {
+            popup.hide();
         }
 
         DropDown {
             id: WidgetId::here(),
-            choices: choices_with_ids,
-            inner,
+            choices,
+            entries,
             selected_item_changed: Signal::new(),
+            chosen,
+            presentation: Presentation::List { popup, opened },
         }
     }
 
-    /// Returns whether TODO.
+    /// Returns the newly selected choice, if the user has selected one.
     pub fn selected_item_changed(&self) -> Option<T> {
-        self.selected_item_changed.value().map(|x| x.1)
+        self.selected_item_changed
+            .value()
+            .map(|x| x.1)
+            .or_else(|| self.chosen.map(|index| self.choices[index].value.clone()))
     }
 
     pub fn on_selected_item_changed(self, f: impl FnOnce(T)) -> Self {
@@ -103,8 +459,16 @@ impl<T: Clone + 'static> DropDown<T> {
 
     fn create_context_menu(&self) -> kyute_shell::Menu {
         let mut menu = kyute_shell::Menu::new_popup();
-        for choice in self.choices.iter() {
-            menu.add_item(&choice.name, choice.item_id as usize, None, false, false);
+        for (i, entry) in self.entries.iter().enumerate() {
+            match *entry {
+                Entry::Item(index) => {
+                    let choice = &self.choices[index];
+                    menu.add_item(&choice.name, choice.item_id as usize, None, false, false);
+                }
+                Entry::Separator => menu.add_separator(),
+                // disabled, with an ID that isn't one of a choice
+                Entry::Header(ref title) => menu.add_item(title, self.choices.len() + i, None, false, true),
+            }
         }
         menu
     }
@@ -116,10 +480,27 @@ impl<T: Clone + 'static> Widget for DropDown<T> {
     }
 
     fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
-        self.inner.layout(ctx, constraints, env)
+        match self.presentation {
+            Presentation::Menu(ref inner) => inner.layout(ctx, constraints, env),
+            Presentation::List { ref popup, .. } => popup.layout(ctx, constraints, env),
+        }
     }
 
-    fn event(&self, ctx: &mut EventCtx, event: &mut Event, _env: &Environment) {
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Presentation::List { ref popup, ref opened } = self.presentation {
+            match event {
+                Event::Pointer(p)
+                    if p.kind == PointerEventKind::PointerDown && p.button == Some(PointerButton::LEFT) =>
+                {
+                    // show or hide the list
+                    opened.signal(());
+                    ctx.set_handled();
+                }
+                _ => popup.event(ctx, event, env),
+            }
+            return;
+        }
+
         match event {
             Event::Pointer(p) => match p.kind {
                 PointerEventKind::PointerDown if p.button == Some(PointerButton::LEFT) => {
@@ -134,15 +515,70 @@ impl<T: Clone + 'static> Widget for DropDown<T> {
             },
             Event::MenuCommand(id) => {
                 trace!("menu command: {}", *id);
-                self.selected_item_changed
-                    .signal((*id, self.choices[*id].value.clone()));
-                ctx.set_handled();
+                if let Some(choice) = self.choices.get(*id) {
+                    self.selected_item_changed.signal((*id, choice.value.clone()));
+                    ctx.set_handled();
+                }
             }
             _ => {}
         }
     }
 
     fn paint(&self, ctx: &mut PaintCtx) {
-        self.inner.paint(ctx)
+        match self.presentation {
+            Presentation::Menu(ref inner) => inner.paint(ctx),
+            Presentation::List { ref popup, .. } => popup.paint(ctx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choices(names: &[&str]) -> Vec<DropDownChoice<()>> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| DropDownChoice {
+                value: (),
+                name: name.to_string(),
+                item_id: i as u16,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn filter_rows_keeps_groups() {
+        let choices = choices(&["Red", "Green", "Circle", "Square"]);
+        let entries = vec![
+            Entry::Header("Colors".to_string()),
+            Entry::Item(0),
+            Entry::Item(1),
+            Entry::Separator,
+            Entry::Header("Shapes".to_string()),
+            Entry::Item(2),
+            Entry::Item(3),
+        ];
+        assert_eq!(filter_rows(&entries, &choices, "").len(), entries.len());
+
+        // the header of a group is shown with its matching choices, case-insensitively
+        let rows = filter_rows(&entries, &choices, "RE");
+        assert_eq!(
+            rows,
+            vec![
+                &Entry::Header("Colors".to_string()),
+                &Entry::Item(0),
+                &Entry::Item(1),
+                &Entry::Separator,
+                &Entry::Header("Shapes".to_string()),
+                &Entry::Item(3),
+            ]
+        );
+
+        // no separator before the first group shown
+        let rows = filter_rows(&entries, &choices, "circle");
+        assert_eq!(rows, vec![&Entry::Header("Shapes".to_string()), &Entry::Item(2)]);
+        assert!(filter_rows(&entries, &choices, "triangle").is_empty());
     }
 }
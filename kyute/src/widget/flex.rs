@@ -1,5 +1,8 @@
 //! (deprecated) flex containers
-use crate::{widget::prelude::*, RoundToPixel};
+use crate::{
+    widget::{prelude::*, widget_pod::sort_by_z_index},
+    LengthOrPercentage, RoundToPixel,
+};
 use std::sync::Arc;
 
 pub fn main_axis_length(orientation: Orientation, size: Size) -> f64 {
@@ -41,11 +44,153 @@ pub enum MainAxisSize {
     Max,
 }
 
+/// Whether the items of a flex container are laid out on a single line or on multiple lines.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FlexWrap {
+    /// All items on a single line; they are shrunk if they don't fit.
+    NoWrap,
+    /// Items that don't fit are moved to a new line. Lines are stacked in the cross axis direction.
+    Wrap,
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// FlexItem
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Flexibility of an item in a flex container.
+#[derive(Copy, Clone, Debug)]
+struct Flexibility {
+    grow: f64,
+    shrink: f64,
+    basis: Option<LengthOrPercentage>,
+}
+
+impl Default for Flexibility {
+    fn default() -> Self {
+        Flexibility {
+            grow: 0.0,
+            shrink: 1.0,
+            basis: None,
+        }
+    }
+}
+
+/// Wrapper around a widget that specifies how it grows or shrinks in a flex container.
+pub struct FlexItem<W> {
+    flexibility: Flexibility,
+    widget: W,
+}
+
+impl<W> FlexItem<W> {
+    pub fn new(widget: W) -> FlexItem<W> {
+        FlexItem {
+            flexibility: Default::default(),
+            widget,
+        }
+    }
+
+    /// Sets the proportion of the free space on the line that this item receives. The default is 0.
+    pub fn flex_grow(mut self, grow: f64) -> Self {
+        self.flexibility.grow = grow;
+        self
+    }
+
+    /// Sets how much this item shrinks, relative to the other items of the line, when they don't fit on it.
+    ///
+    /// Items shrink in proportion to this factor multiplied by their base size. The default is 1;
+    /// 0 prevents the item from shrinking.
+    pub fn flex_shrink(mut self, shrink: f64) -> Self {
+        self.flexibility.shrink = shrink;
+        self
+    }
+
+    /// Sets the size of the item along the main axis before free space is distributed.
+    ///
+    /// Percentages are relative to the available length of the container along the main axis.
+    /// By default, the base size is the size of the contents.
+    pub fn flex_basis(mut self, basis: impl Into<LengthOrPercentage>) -> Self {
+        self.flexibility.basis = Some(basis.into());
+        self
+    }
+}
+
+/// Items that can be inserted into a flex container: widgets or `FlexItem` wrappers.
+pub trait FlexInsertable {
+    fn insert(self, flex: &mut Flex);
+}
+
+impl<W> FlexInsertable for W
+where
+    W: Widget + Sized + 'static,
+{
+    fn insert(self, flex: &mut Flex) {
+        flex.insert_item(Flexibility::default(), Arc::new(WidgetPod::new(self)));
+    }
+}
+
+impl<W> FlexInsertable for FlexItem<W>
+where
+    W: Widget + 'static,
+{
+    fn insert(self, flex: &mut Flex) {
+        flex.insert_item(self.flexibility, Arc::new(WidgetPod::new(self.widget)));
+    }
+}
+
+pub trait FlexItemExt: Widget + Sized {
+    fn flex_grow(self, grow: f64) -> FlexItem<Self> {
+        FlexItem::new(self).flex_grow(grow)
+    }
+
+    fn flex_shrink(self, shrink: f64) -> FlexItem<Self> {
+        FlexItem::new(self).flex_shrink(shrink)
+    }
+
+    fn flex_basis(self, basis: impl Into<LengthOrPercentage>) -> FlexItem<Self> {
+        FlexItem::new(self).flex_basis(basis)
+    }
+}
+
+impl<W> FlexItemExt for W where W: Widget + Sized {}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Flex
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+struct Item {
+    flexibility: Flexibility,
+    widget: Arc<WidgetPod>,
+}
+
+/// Layout of an item on a line.
+struct ItemLayout {
+    index: usize,
+    /// Size along the main axis before free space is distributed.
+    base: f64,
+    /// Final size along the main axis.
+    main: f64,
+    geometry: Geometry,
+}
+
+/// A line of items.
+struct Line {
+    items: Vec<ItemLayout>,
+    /// Size along the cross axis.
+    cross: f64,
+    /// Offset of the baseline from the start of the line, for baseline alignment.
+    baseline: Option<f64>,
+}
+
 #[derive(Clone)]
 pub struct Flex {
     id: WidgetId,
     axis_orientation: Orientation,
-    items: Vec<Arc<WidgetPod>>,
+    wrap: FlexWrap,
+    main_axis_alignment: MainAxisAlignment,
+    cross_axis_alignment: CrossAxisAlignment,
+    main_axis_size: MainAxisSize,
+    items: Vec<Item>,
 }
 
 impl Flex {
@@ -55,19 +200,86 @@ impl Flex {
         Flex {
             id: WidgetId::here(),
             axis_orientation,
+            wrap: FlexWrap::NoWrap,
+            main_axis_alignment: MainAxisAlignment::Start,
+            cross_axis_alignment: CrossAxisAlignment::Start,
+            main_axis_size: MainAxisSize::Min,
             items: vec![],
         }
     }
 
     #[composable]
-    pub fn with(mut self, widget: impl Widget + 'static) -> Self {
-        self.push(widget);
+    pub fn with(mut self, item: impl FlexInsertable) -> Self {
+        self.push(item);
         self
     }
 
     #[composable]
-    pub fn push(&mut self, widget: impl Widget + 'static) {
-        self.items.push(Arc::new(WidgetPod::new(widget)));
+    pub fn push(&mut self, item: impl FlexInsertable) {
+        item.insert(self);
+    }
+
+    fn insert_item(&mut self, flexibility: Flexibility, widget: Arc<WidgetPod>) {
+        self.items.push(Item { flexibility, widget });
+    }
+
+    pub fn set_wrap(&mut self, wrap: FlexWrap) {
+        self.wrap = wrap;
+    }
+
+    pub fn set_main_axis_alignment(&mut self, alignment: MainAxisAlignment) {
+        self.main_axis_alignment = alignment;
+    }
+
+    /// Sets how items are aligned on their line in the cross axis direction.
+    ///
+    /// With `CrossAxisAlignment::Baseline`, the items of each line are aligned on their first baseline. It only
+    /// applies to horizontal containers; vertical containers align the items at the start instead.
+    pub fn set_cross_axis_alignment(&mut self, alignment: CrossAxisAlignment) {
+        self.cross_axis_alignment = alignment;
+    }
+
+    pub fn set_main_axis_size(&mut self, size: MainAxisSize) {
+        self.main_axis_size = size;
+    }
+
+    fn size(&self, main: f64, cross: f64) -> Size {
+        match self.axis_orientation {
+            Orientation::Vertical => Size::new(cross, main),
+            Orientation::Horizontal => Size::new(main, cross),
+        }
+    }
+
+    fn offset(&self, main: f64, cross: f64) -> Offset {
+        match self.axis_orientation {
+            Orientation::Vertical => Offset::new(cross, main),
+            Orientation::Horizontal => Offset::new(main, cross),
+        }
+    }
+
+    /// Returns the constraints for an item, given its size along the main axis if it's fixed,
+    /// and its size along the cross axis if it's stretched.
+    fn item_constraints(&self, constraints: &LayoutParams, main: Option<f64>, cross: Option<f64>) -> LayoutParams {
+        let max = self.size(
+            main.unwrap_or(main_axis_length(self.axis_orientation, constraints.max)),
+            cross.unwrap_or(cross_axis_length(self.axis_orientation, constraints.max)),
+        );
+        let min = self.size(main.unwrap_or(0.0), cross.unwrap_or(0.0));
+        LayoutParams {
+            min,
+            max,
+            ..*constraints
+        }
+    }
+
+    /// Returns the baseline of an item, if it is aligned on its baseline.
+    fn item_baseline(&self, geometry: &Geometry) -> Option<f64> {
+        if self.cross_axis_alignment == CrossAxisAlignment::Baseline && self.axis_orientation == Orientation::Horizontal
+        {
+            geometry.measurements.baseline
+        } else {
+            None
+        }
     }
 }
 
@@ -77,59 +289,207 @@ impl Widget for Flex {
     }
 
     fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
-        let item_layouts: Vec<Geometry> = self
-            .items
-            .iter()
-            .map(|item| item.layout(ctx, &constraints, env))
+        let orientation = self.axis_orientation;
+        //let spacing = env.get(theme::FlexSpacing);
+        let spacing = 1.0;
+        let available_main = main_axis_length(orientation, constraints.max);
+
+        // --- measure items at their base size ---
+        let mut measured: Vec<ItemLayout> = Vec::with_capacity(self.items.len());
+        for (index, item) in self.items.iter().enumerate() {
+            let basis = item
+                .flexibility
+                .basis
+                .map(|basis| basis.compute(constraints, available_main, env))
+                .filter(|basis| basis.is_finite());
+            let geometry = item
+                .widget
+                .layout(ctx, &self.item_constraints(constraints, basis, None), env);
+            let base = basis.unwrap_or_else(|| main_axis_length(orientation, geometry.measurements.size));
+            measured.push(ItemLayout {
+                index,
+                base,
+                main: base,
+                geometry,
+            });
+        }
+
+        // --- break lines ---
+        let mut lines: Vec<Vec<ItemLayout>> = vec![];
+        let mut line_len = 0.0;
+        for item in measured {
+            let wraps = self.wrap == FlexWrap::Wrap && available_main.is_finite();
+            match lines.last_mut() {
+                Some(line) if !(wraps && line_len + spacing + item.base > available_main) => {
+                    line_len += spacing + item.base;
+                    line.push(item);
+                }
+                _ => {
+                    line_len = item.base;
+                    lines.push(vec![item]);
+                }
+            }
+        }
+
+        // --- distribute free space on each line, align items along the cross axis ---
+        let mut lines: Vec<Line> = lines
+            .into_iter()
+            .map(|mut items| {
+                let used = items.iter().map(|item| item.base).sum::<f64>() + spacing * (items.len() - 1) as f64;
+                let free = available_main - used;
+                if free.is_finite() && free > 0.0 {
+                    let total_grow: f64 = items.iter().map(|item| self.items[item.index].flexibility.grow).sum();
+                    if total_grow > 0.0 {
+                        for item in items.iter_mut() {
+                            item.main = item.base + free * self.items[item.index].flexibility.grow / total_grow;
+                        }
+                    }
+                } else if free < 0.0 {
+                    let total_shrink: f64 = items
+                        .iter()
+                        .map(|item| self.items[item.index].flexibility.shrink * item.base)
+                        .sum();
+                    if total_shrink > 0.0 {
+                        for item in items.iter_mut() {
+                            let shrink = self.items[item.index].flexibility.shrink * item.base;
+                            item.main = (item.base + free * shrink / total_shrink).max(0.0);
+                        }
+                    }
+                }
+
+                // relayout the items whose size has changed
+                for item in items.iter_mut() {
+                    if item.main != main_axis_length(orientation, item.geometry.measurements.size) {
+                        item.geometry = self.items[item.index].widget.layout(
+                            ctx,
+                            &self.item_constraints(constraints, Some(item.main), None),
+                            env,
+                        );
+                    }
+                }
+
+                let baseline = items
+                    .iter()
+                    .filter_map(|item| self.item_baseline(&item.geometry))
+                    .reduce(f64::max);
+                let cross = items
+                    .iter()
+                    .map(|item| {
+                        let size = cross_axis_length(orientation, item.geometry.measurements.size);
+                        match (baseline, self.item_baseline(&item.geometry)) {
+                            // space below the baseline, plus the max space above it
+                            (Some(max_baseline), Some(baseline)) => max_baseline + size - baseline,
+                            _ => size,
+                        }
+                    })
+                    .fold(0.0, f64::max);
+                Line { items, cross, baseline }
+            })
             .collect();
 
-        let max_cross_axis_len = item_layouts
+        // --- container size ---
+        let max_line_len = lines
             .iter()
-            .map(|m| cross_axis_length(self.axis_orientation, m.measurements.size))
+            .map(|line| {
+                line.items
+                    .iter()
+                    .map(|item| main_axis_length(orientation, item.geometry.measurements.size))
+                    .sum::<f64>()
+                    + spacing * (line.items.len() - 1) as f64
+            })
             .fold(0.0, f64::max);
-
-        // preferred size of this flex: max size in axis direction, max elem width in cross-axis direction
-        let cross_axis_len = match self.axis_orientation {
-            Orientation::Vertical => constraints.constrain_width(max_cross_axis_len),
-            Orientation::Horizontal => constraints.constrain_height(max_cross_axis_len),
+        let main_len = if self.main_axis_size == MainAxisSize::Max && available_main.is_finite() {
+            available_main
+        } else {
+            max_line_len
         };
+        let cross_len =
+            lines.iter().map(|line| line.cross).sum::<f64>() + spacing * lines.len().saturating_sub(1) as f64;
+        let size = self.size(main_len, cross_len);
+        let size = constraints.constrain(size).round_to_pixel(ctx.scale_factor);
+        let main_len = main_axis_length(orientation, size);
 
-        // distribute children
-        let mut d = 0.0;
-        //let spacing = env.get(theme::FlexSpacing);
-        let spacing = 1.0;
+        // a single line takes the cross size of the container
+        if let [line] = &mut lines[..] {
+            line.cross = line.cross.max(cross_axis_length(orientation, size));
+        }
 
-        for i in 0..self.items.len() {
-            //eprintln!("flex {:?} item pos {}", self.axis, d);
-            let len = main_axis_length(self.axis_orientation, item_layouts[i].measurements.size)
-                .round_to_pixel(ctx.scale_factor);
-            let offset = match self.axis_orientation {
-                Orientation::Vertical => Offset::new(0.0, d),
-                Orientation::Horizontal => Offset::new(d, 0.0),
+        // --- position items ---
+        let mut baseline = None;
+        let mut line_pos = 0.0;
+        for line in lines.iter_mut() {
+            if self.cross_axis_alignment == CrossAxisAlignment::Stretch {
+                for item in line.items.iter_mut() {
+                    if cross_axis_length(orientation, item.geometry.measurements.size) != line.cross {
+                        let main = main_axis_length(orientation, item.geometry.measurements.size);
+                        item.geometry = self.items[item.index].widget.layout(
+                            ctx,
+                            &self.item_constraints(constraints, Some(main), Some(line.cross)),
+                            env,
+                        );
+                    }
+                }
+            }
+
+            let n = line.items.len();
+            let used = line
+                .items
+                .iter()
+                .map(|item| main_axis_length(orientation, item.geometry.measurements.size))
+                .sum::<f64>()
+                + spacing * (n - 1) as f64;
+            let free = (main_len - used).max(0.0);
+            let (start, between) = match self.main_axis_alignment {
+                MainAxisAlignment::Start => (0.0, 0.0),
+                MainAxisAlignment::Center => (0.5 * free, 0.0),
+                MainAxisAlignment::End => (free, 0.0),
+                MainAxisAlignment::SpaceBetween if n > 1 => (0.0, free / (n - 1) as f64),
+                MainAxisAlignment::SpaceBetween => (0.0, 0.0),
+                MainAxisAlignment::SpaceEvenly => (free / (n + 1) as f64, free / (n + 1) as f64),
+                MainAxisAlignment::SpaceAround => (0.5 * free / n as f64, free / n as f64),
             };
-            if !ctx.speculative {
-                self.items[i].set_offset(offset);
+
+            if baseline.is_none() {
+                baseline = line.baseline.map(|b| line_pos + b);
             }
-            d += len + spacing;
-            d = d.ceil();
-        }
 
-        let size = match self.axis_orientation {
-            Orientation::Vertical => Size::new(cross_axis_len, constraints.constrain_height(d)),
-            Orientation::Horizontal => Size::new(constraints.constrain_width(d), cross_axis_len),
-        };
+            let mut d = start;
+            for item in line.items.iter() {
+                let item_cross = cross_axis_length(orientation, item.geometry.measurements.size);
+                let cross_pos = match (line.baseline, self.item_baseline(&item.geometry)) {
+                    (Some(max_baseline), Some(item_baseline)) => max_baseline - item_baseline,
+                    _ => match self.cross_axis_alignment {
+                        CrossAxisAlignment::Center => 0.5 * (line.cross - item_cross),
+                        CrossAxisAlignment::End => line.cross - item_cross,
+                        _ => 0.0,
+                    },
+                };
+                if !ctx.speculative {
+                    self.items[item.index].widget.set_offset(self.offset(
+                        d.round_to_pixel(ctx.scale_factor),
+                        (line_pos + cross_pos).round_to_pixel(ctx.scale_factor),
+                    ));
+                }
+                d += main_axis_length(orientation, item.geometry.measurements.size) + spacing + between;
+            }
+            line_pos += line.cross + spacing;
+        }
 
-        let size = size.round_to_pixel(ctx.scale_factor);
-        Geometry::new(size)
+        let mut geometry = Geometry::new(size);
+        geometry.measurements.baseline = baseline;
+        geometry
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
-        for item in self.items.iter() {
-            item.route_event(ctx, event, env);
+        // topmost items first
+        for item in sort_by_z_index(&self.items, |item| item.widget.z_index()).rev() {
+            item.widget.route_event(ctx, event, env);
         }
     }
 
-    fn paint(&self, _ctx: &mut PaintCtx) {
-        //ctx.draw_styled_box(ctx.bounds, &Style::new().background(theme::palette::GREY_500));
+    fn paint(&self, ctx: &mut PaintCtx) {
+        for item in sort_by_z_index(&self.items, |item| item.widget.z_index()) {
+            item.widget.paint(ctx);
+        }
     }
 }
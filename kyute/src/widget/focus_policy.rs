@@ -0,0 +1,137 @@
+//! Overriding the focus behavior of widgets.
+use crate::{accessibility::Semantics, widget::prelude::*};
+
+/// Overrides whether the wrapped widget can receive the keyboard focus, and whether it's part of the focus chain.
+///
+/// By default, widgets decide for themselves (see `Widget::is_focusable`): interactive widgets are focusable,
+/// disabled ones aren't. This wrapper overrides that decision without having to reimplement the event handling
+/// of the widget. The wrapped widget must have an ID.
+pub struct FocusPolicy<Inner> {
+    inner: Inner,
+    focusable: Option<bool>,
+    skip_focus_chain: bool,
+    tab_index: Option<i32>,
+}
+
+impl<Inner: Widget + 'static> FocusPolicy<Inner> {
+    /// Creates a new `FocusPolicy` that doesn't override anything.
+    pub fn new(inner: Inner) -> FocusPolicy<Inner> {
+        FocusPolicy {
+            inner,
+            focusable: None,
+            skip_focus_chain: false,
+            tab_index: None,
+        }
+    }
+
+    /// Sets whether the widget can receive the keyboard focus.
+    ///
+    /// Non-focusable widgets are not part of the focus chain, and their focus requests are ignored.
+    #[must_use]
+    pub fn focusable(mut self, focusable: bool) -> Self {
+        self.focusable = Some(focusable);
+        self
+    }
+
+    /// Sets whether the widget should be skipped when moving the focus with the keyboard.
+    ///
+    /// The widget can still be focused by clicking on it.
+    #[must_use]
+    pub fn skip_focus_chain(mut self, skip: bool) -> Self {
+        self.skip_focus_chain = skip;
+        self
+    }
+
+    /// Sets the position of the widget in the `Tab` navigation order (see `Widget::tab_order`).
+    ///
+    /// A negative tab index is equivalent to `skip_focus_chain(true)`.
+    #[must_use]
+    pub fn tab_index(mut self, tab_index: i32) -> Self {
+        self.tab_index = Some(tab_index);
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for FocusPolicy<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.inner.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    // `route_event` is not forwarded to the inner widget: the default implementation is what adds the widget
+    // to the focus chain.
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if self.is_in_focus_chain() {
+            self.inner.route_event(ctx, event, env);
+        } else {
+            route_outside_focus_chain(&self.inner, !self.is_focusable(), ctx, event, env);
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+
+    fn semantics(&self) -> Option<Semantics> {
+        self.inner.semantics()
+    }
+
+    fn is_focusable(&self) -> bool {
+        self.focusable.unwrap_or_else(|| self.inner.is_focusable())
+    }
+
+    fn is_in_focus_chain(&self) -> bool {
+        !self.skip_focus_chain && self.tab_order() >= 0 && self.is_focusable()
+    }
+
+    fn tab_order(&self) -> i32 {
+        self.tab_index.unwrap_or_else(|| self.inner.tab_order())
+    }
+}
+
+/// Routes an event to a widget, keeping it out of the focus chain.
+///
+/// The widget may have added itself to the focus chain (e.g. `Clickable`): it is removed afterwards.
+/// If `reject_focus` is true, requests of the widget to acquire the focus are also cancelled.
+pub(crate) fn route_outside_focus_chain<W: Widget>(
+    widget: &W,
+    reject_focus: bool,
+    ctx: &mut EventCtx,
+    event: &mut Event,
+    env: &Environment,
+) {
+    let id = widget.widget_id();
+    let chain_start = match event {
+        Event::BuildFocusChain { chain } => Some(chain.len()),
+        _ => None,
+    };
+
+    widget.route_event(ctx, event, env);
+
+    if let Some(id) = id {
+        match (event, chain_start) {
+            (Event::BuildFocusChain { chain }, Some(start)) => {
+                // only our own entry: the widgets below are not affected
+                if chain.get(start).map(|entry| entry.id) == Some(id) {
+                    chain.remove(start);
+                }
+            }
+            _ if reject_focus => ctx.cancel_focus_request(id),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Focus navigation within a part of the UI.
+use crate::{
+    focus::{FocusChain, FocusDirection},
+    widget::prelude::*,
+    InternalEvent,
+};
+use keyboard_types::{Key, KeyState, Modifiers};
+
+/// Controls keyboard focus navigation among the focusable widgets inside it.
+///
+/// A scope that traps the focus (`FocusScope::trap_focus`) keeps `Tab` navigation inside it once one of its
+/// widgets has the focus, as in a dialog. With `FocusScope::arrow_keys`, the arrow keys move the focus to the
+/// nearest widget of the scope in their direction, as in a grid of buttons or a toolbar. Arrow keys handled by the
+/// focused widget (e.g. to move the cursor in a text input) don't move the focus.
+pub struct FocusScope<Inner> {
+    id: WidgetId,
+    inner: Inner,
+    trap_focus: bool,
+    arrow_keys: bool,
+}
+
+impl<Inner: Widget + 'static> FocusScope<Inner> {
+    /// Creates a new focus scope, which by default doesn't change navigation.
+    #[composable]
+    pub fn new(inner: Inner) -> FocusScope<Inner> {
+        FocusScope {
+            id: WidgetId::here(),
+            inner,
+            trap_focus: false,
+            arrow_keys: false,
+        }
+    }
+
+    /// Sets whether `Tab` navigation stays inside this scope.
+    #[must_use]
+    pub fn trap_focus(mut self, trap_focus: bool) -> Self {
+        self.trap_focus = trap_focus;
+        self
+    }
+
+    /// Sets whether the arrow keys move the focus between the widgets of this scope.
+    #[must_use]
+    pub fn arrow_keys(mut self, arrow_keys: bool) -> Self {
+        self.arrow_keys = arrow_keys;
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+
+    /// Moves the focus from `focus` to the nearest widget of the scope in the specified direction.
+    fn move_focus(&self, ctx: &mut EventCtx, focus: WidgetId, direction: FocusDirection, env: &Environment) {
+        // collect the widgets of the scope, with their current bounds
+        let mut chain = FocusChain::new();
+        self.inner
+            .route_event(ctx, &mut Event::BuildFocusChain { chain: &mut chain }, env);
+        if let Some(target) = chain.nearest_in_direction(focus, direction) {
+            ctx.move_focus_to(target);
+            ctx.set_handled();
+        }
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for FocusScope<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Event::BuildFocusChain { ref mut chain } = *event {
+            if self.trap_focus {
+                chain.enter_scope(self.id);
+                self.inner.route_event(ctx, event, env);
+                if let Event::BuildFocusChain { ref mut chain } = *event {
+                    chain.exit_scope();
+                }
+                return;
+            }
+        }
+
+        self.inner.route_event(ctx, event, env);
+        if !self.arrow_keys || ctx.handled() {
+            return;
+        }
+
+        // keyboard events are sent to the focused widget
+        if let Event::Internal(InternalEvent::RouteEvent {
+            target,
+            event: ref inner,
+        }) = *event
+        {
+            if let Event::Keyboard(ref key) = **inner {
+                let modifiers =
+                    key.modifiers & (Modifiers::CONTROL | Modifiers::ALT | Modifiers::SHIFT | Modifiers::META);
+                if key.state == KeyState::Down && modifiers.is_empty() {
+                    let direction = match key.key {
+                        Key::ArrowLeft => Some(FocusDirection::Left),
+                        Key::ArrowRight => Some(FocusDirection::Right),
+                        Key::ArrowUp => Some(FocusDirection::Up),
+                        Key::ArrowDown => Some(FocusDirection::Down),
+                        _ => None,
+                    };
+                    if let Some(direction) = direction {
+                        self.move_focus(ctx, target, direction, env);
+                    }
+                }
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+}
@@ -1,5 +1,11 @@
-use crate::widget::{grid, prelude::*, Grid, ScrollArea, TableView};
-use std::sync::Arc;
+use crate::{
+    cache::{self, State},
+    theme,
+    widget::{grid, prelude::*, CheckboxField, Grid, ScrollArea, TableView, Text, TextField},
+};
+use std::{str::FromStr, sync::Arc};
+
+pub use kyute_macros::FormModel;
 
 pub trait LabeledContent {
     type Label: Widget + 'static;
@@ -71,6 +77,19 @@ impl Form {
 
         Form { inner: grid }
     }
+
+    /// Creates a form that edits the value stored in `state`.
+    ///
+    /// The form reflects the current value of the state, and edits made in the form are written back to it.
+    /// See `FormModel`.
+    #[composable]
+    pub fn from_model<T: FormModel>(state: &State<T>) -> Form {
+        let (rows, new_value) = T::form_rows(&state.get());
+        if let Some(new_value) = new_value {
+            state.set(new_value);
+        }
+        Form::new(rows)
+    }
 }
 
 pub struct Section<Title> {
@@ -98,3 +117,237 @@ where
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Form models
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Types that can be edited with a form.
+///
+/// Usually implemented with `#[derive(FormModel)]`, which generates one form row per field, with an editor chosen
+/// from the type of the field (see `FormField`). The following field attributes are supported:
+/// - `#[form(label = "...")]`: the label of the field (by default, derived from the field name)
+/// - `#[form(skip)]`: don't show the field in the form
+/// - `#[form(section)]`: the field is itself a `FormModel`, shown in a separate section
+/// - `#[form(min = 0, max = 100)]`: valid range of numeric fields (use strings for negative values: `min = "-1.0"`)
+/// - `#[form(validate = "path::to::fn")]`: the field is modified only if `fn(&value) -> bool` returns true
+///
+/// Use `Form::from_model` to create a form bound to a `State`.
+pub trait FormModel: Clone + 'static {
+    /// Returns the rows of a form that edits `value`, and the new value if it was edited since the last composition.
+    fn form_rows(value: &Self) -> (Vec<Row>, Option<Self>);
+}
+
+/// Options of a field editor in a form.
+#[derive(Clone, Debug, Default)]
+pub struct FieldOptions {
+    /// Minimum value of a numeric field.
+    pub min: Option<f64>,
+    /// Maximum value of a numeric field.
+    pub max: Option<f64>,
+}
+
+/// Types that can be edited by a field in a form.
+pub trait FormField: Clone + 'static {
+    /// Returns a form row that edits `value`, and the new value if it was edited since the last composition.
+    fn form_row(label: &str, value: &Self, options: &FieldOptions) -> (Row, Option<Self>);
+}
+
+impl FormField for bool {
+    #[composable]
+    fn form_row(label: &str, value: &Self, _options: &FieldOptions) -> (Row, Option<Self>) {
+        let field = CheckboxField::new(label.to_string(), *value);
+        let new_value = field.toggled();
+        (field.into(), new_value)
+    }
+}
+
+impl FormField for String {
+    #[composable]
+    fn form_row(label: &str, value: &Self, _options: &FieldOptions) -> (Row, Option<Self>) {
+        let field = TextField::new(label.to_string(), value.clone());
+        let new_value = field.editing_finished().map(|text| text.to_string());
+        (field.into(), new_value)
+    }
+}
+
+impl FormField for Arc<str> {
+    #[composable]
+    fn form_row(label: &str, value: &Self, _options: &FieldOptions) -> (Row, Option<Self>) {
+        let field = TextField::new(label.to_string(), value.clone());
+        let new_value = field.editing_finished();
+        (field.into(), new_value)
+    }
+}
+
+/// Parses the input of a numeric field, rejecting values outside of the range specified in the options.
+///
+/// Returns the message shown under the field if the input is invalid.
+fn parse_number<T: FromStr + Into<f64> + Copy>(text: &str, options: &FieldOptions) -> Result<T, String> {
+    let value: T = text.trim().parse().map_err(|_| "Not a valid number".to_string())?;
+    let v: f64 = value.into();
+    match (options.min, options.max) {
+        (Some(min), _) if v < min => Err(format!("Must be at least {}", min)),
+        (_, Some(max)) if v > max => Err(format!("Must be at most {}", max)),
+        _ => Ok(value),
+    }
+}
+
+/// Shows a validation error under the content of a field row.
+#[composable]
+fn with_validation_error(row: Row, error: Option<String>) -> Row {
+    match (row, error) {
+        (
+            Row::Field {
+                label,
+                content,
+                swap_content_and_label,
+            },
+            Some(error),
+        ) => {
+            let mut grid = Grid::column(grid::TrackBreadth::Flex(1.0));
+            grid.place((0, 0), 0, content);
+            grid.place(
+                (1, 0),
+                0,
+                Text::new(error).color(theme::ERROR_COLOR).font_size(0.8.em()).arc_pod(),
+            );
+            Row::Field {
+                label,
+                content: grid.arc_pod(),
+                swap_content_and_label,
+            }
+        }
+        (row, _) => row,
+    }
+}
+
+macro_rules! impl_numeric_form_field {
+    ($($t:ty),*) => {
+        $(impl FormField for $t {
+            #[composable]
+            fn form_row(label: &str, value: &Self, options: &FieldOptions) -> (Row, Option<Self>) {
+                // shown under the field until a valid value is entered
+                let error = cache::state(|| None::<String>);
+                let field = TextField::new(label.to_string(), value.to_string());
+                let mut new_value = None;
+                if let Some(text) = field.editing_finished() {
+                    match parse_number::<$t>(&text, options) {
+                        Ok(value) => {
+                            error.set_without_invalidation(None);
+                            new_value = Some(value);
+                        }
+                        Err(message) => error.set_without_invalidation(Some(message)),
+                    }
+                }
+                (with_validation_error(field.into(), error.get()), new_value)
+            }
+        })*
+    };
+}
+
+impl_numeric_form_field!(f32, f64, i8, i16, i32, u8, u16, u32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::{noop_waker, Cache},
+        widget::Null,
+    };
+    use std::cell::RefCell;
+
+    #[test]
+    fn parse_number_errors() {
+        let options = FieldOptions {
+            min: Some(0.0),
+            max: Some(10.0),
+        };
+        assert_eq!(parse_number::<f64>(" 2.5 ", &options), Ok(2.5));
+        assert_eq!(parse_number::<u8>("10", &options), Ok(10));
+        assert!(parse_number::<f64>("abc", &options).is_err());
+        assert!(parse_number::<u8>("2.5", &options).is_err());
+        assert_eq!(
+            parse_number::<i32>("-1", &options),
+            Err("Must be at least 0".to_string())
+        );
+        assert_eq!(
+            parse_number::<f32>("11", &options),
+            Err("Must be at most 10".to_string())
+        );
+        assert_eq!(parse_number::<i32>("-1", &FieldOptions::default()), Ok(-1));
+    }
+
+    thread_local! {
+        static LABELS: RefCell<Vec<String>> = RefCell::new(vec![]);
+    }
+
+    /// Field that is edited on each composition: the new value is the maximum of the field if there's one,
+    /// or the current value plus one.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Bump(f64);
+
+    impl FormField for Bump {
+        fn form_row(label: &str, value: &Self, options: &FieldOptions) -> (Row, Option<Self>) {
+            LABELS.with(|labels| labels.borrow_mut().push(label.to_string()));
+            let row = Row::Field {
+                label: Null.arc_pod(),
+                content: Null.arc_pod(),
+                swap_content_and_label: false,
+            };
+            (row, Some(Bump(options.max.unwrap_or(value.0 + 1.0))))
+        }
+    }
+
+    fn reject(_value: &Bump) -> bool {
+        false
+    }
+
+    #[derive(Clone, Debug, PartialEq, FormModel)]
+    struct Inner {
+        #[form(label = "Inner value")]
+        value: Bump,
+    }
+
+    #[derive(Clone, Debug, PartialEq, FormModel)]
+    struct Model {
+        line_height: Bump,
+        #[form(max = 10)]
+        clamped: Bump,
+        #[form(skip)]
+        skipped: Bump,
+        #[form(validate = "reject")]
+        rejected: Bump,
+        #[form(section)]
+        inner: Inner,
+    }
+
+    #[test]
+    fn derived_form_model() {
+        let model = Model {
+            line_height: Bump(0.0),
+            clamped: Bump(0.0),
+            skipped: Bump(0.0),
+            rejected: Bump(0.0),
+            inner: Inner { value: Bump(0.0) },
+        };
+        let mut cache = Cache::new(noop_waker());
+        let (rows, new_value) = cache.recompose(&Environment::new(), || Model::form_rows(&model));
+
+        LABELS.with(|labels| {
+            assert_eq!(*labels.borrow(), ["Line height", "Clamped", "Rejected", "Inner value"]);
+        });
+        assert_eq!(rows.len(), 4);
+        assert!(matches!(rows[3], Row::Section { ref rows, .. } if rows.len() == 1));
+        assert_eq!(
+            new_value,
+            Some(Model {
+                line_height: Bump(1.0),
+                clamped: Bump(10.0),
+                skipped: Bump(0.0),
+                rejected: Bump(0.0),
+                inner: Inner { value: Bump(1.0) },
+            })
+        );
+    }
+}
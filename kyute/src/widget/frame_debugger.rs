@@ -0,0 +1,244 @@
+//! Frame debugger view.
+use crate::{
+    cache,
+    drawing::ToSkia,
+    frame_debugger::{capture_next_frame, FrameCapture, LayerCapture},
+    theme,
+    widget::{
+        grid, prelude::*, Button, CheckboxField, Clickable, DisplayFormatter, DropDown, Grid, Null, ScrollArea, Text,
+    },
+    Color, RectI,
+};
+use skia_safe as sk;
+use std::sync::Arc;
+
+const SELECTED_BOUNDS_COLOR: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+const SELECTED_CLIP_COLOR: Color = Color::new(0.0, 1.0, 1.0, 1.0);
+const OVERDRAW_COLOR: Color = Color::new(1.0, 0.0, 0.0, 0.15);
+const BACKGROUND_COLOR: Color = Color::new(0.1, 0.1, 0.1, 1.0);
+
+fn to_skia_irect(rect: &RectI) -> sk::Rect {
+    sk::Rect::from_xywh(
+        rect.origin.x as f32,
+        rect.origin.y as f32,
+        rect.size.width as f32,
+        rect.size.height as f32,
+    )
+}
+
+/// Replays the paint commands of a layer up to (and including) the selected command.
+struct CapturePreview {
+    capture: Arc<FrameCapture>,
+    layer: usize,
+    step: usize,
+    overdraw: bool,
+}
+
+impl CapturePreview {
+    fn layer(&self) -> Option<&LayerCapture> {
+        self.capture.layers.get(self.layer)
+    }
+}
+
+impl Widget for CapturePreview {
+    fn widget_id(&self) -> Option<WidgetId> {
+        None
+    }
+
+    fn layout(&self, _ctx: &mut LayoutCtx, constraints: &LayoutParams, _env: &Environment) -> Geometry {
+        let width = if constraints.max.width.is_finite() {
+            constraints.max.width
+        } else {
+            constraints.min.width
+        };
+        let height = if constraints.max.height.is_finite() {
+            constraints.max.height
+        } else {
+            constraints.min.height
+        };
+        Geometry::new(Size::new(width, height))
+    }
+
+    fn event(&self, _ctx: &mut EventCtx, _event: &mut Event, _env: &Environment) {}
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let bounds = ctx.bounds;
+        let canvas = ctx.surface.canvas();
+        canvas.draw_rect(bounds.to_skia(), &sk::Paint::new(BACKGROUND_COLOR.to_skia(), None));
+
+        let layer = match self.layer() {
+            Some(layer) if !layer.size.is_empty() => layer,
+            _ => return,
+        };
+
+        // fit the layer in the bounds of the widget
+        let scale = (bounds.size.width / layer.size.width as f64).min(bounds.size.height / layer.size.height as f64);
+        canvas.save();
+        canvas.clip_rect(bounds.to_skia(), None, None);
+        canvas.translate(bounds.origin.to_skia());
+        canvas.scale((scale as f32, scale as f32));
+
+        // the snapshot of a command contains the state of the layer inside its bounds after it was executed:
+        // draw them in order to get the contents of the layer at the selected step
+        let mut image_paint = sk::Paint::default();
+        image_paint.set_blend_mode(sk::BlendMode::Src);
+        let commands = &layer.commands[..(self.step + 1).min(layer.commands.len())];
+        for command in commands {
+            if let Some((origin, ref image)) = command.snapshot {
+                canvas.draw_image(image, (origin.x as f32, origin.y as f32), Some(&image_paint));
+            }
+        }
+
+        if self.overdraw {
+            // overlapping commands accumulate
+            let overdraw_paint = sk::Paint::new(OVERDRAW_COLOR.to_skia(), None);
+            for command in commands {
+                let rect = match command.clip {
+                    Some(ref clip) => command.bounds.intersection(clip),
+                    None => Some(command.bounds),
+                };
+                if let Some(rect) = rect {
+                    canvas.draw_rect(to_skia_irect(&rect), &overdraw_paint);
+                }
+            }
+        }
+
+        if let Some(command) = commands.last() {
+            let mut stroke = sk::Paint::new(SELECTED_BOUNDS_COLOR.to_skia(), None);
+            stroke.set_style(sk::PaintStyle::Stroke);
+            stroke.set_stroke_width((1.0 / scale) as f32);
+            canvas.draw_rect(to_skia_irect(&command.bounds), &stroke);
+            if let Some(ref clip) = command.clip {
+                stroke.set_color4f(SELECTED_CLIP_COLOR.to_skia(), None);
+                canvas.draw_rect(to_skia_irect(clip), &stroke);
+            }
+        }
+
+        canvas.restore();
+    }
+}
+
+/// Captures a frame and steps through its paint commands.
+///
+/// The "Capture" button records the next frame of all windows (see `frame_debugger::capture_next_frame`).
+/// The commands of the selected layer are listed with the widget that issued them; selecting a command shows
+/// the contents of the layer right after it, with the bounds of the widget (yellow) and its clip rectangle (cyan).
+/// The "Overdraw" option shades the area covered by each command, so that areas painted many times stand out.
+#[derive(Widget)]
+pub struct FrameDebugger {
+    inner: Grid,
+}
+
+impl FrameDebugger {
+    #[composable]
+    pub fn new() -> FrameDebugger {
+        let capture = cache::state(|| None::<Arc<FrameCapture>>);
+        #[state]
+        let mut layer_index = 0usize;
+        #[state]
+        let mut step = 0usize;
+        #[state]
+        let mut overdraw = false;
+
+        let mut grid = Grid::with_template("auto 1fr / 320px 1fr");
+
+        // toolbar
+        let mut toolbar = Grid::with_template("auto / auto auto auto auto auto 1fr");
+        toolbar.set_column_gap(4.dip());
+        let capture_button = Button::new("Capture").on_click(|| {
+            let capture = capture.clone();
+            capture_next_frame(move |frame| capture.set(Some(Arc::new(frame))));
+            layer_index = 0;
+            step = 0;
+        });
+        let current_capture = capture.get();
+        let (layer_count, command_count) = match current_capture {
+            Some(ref capture) => (
+                capture.layers.len(),
+                capture.layers.get(layer_index).map_or(0, |layer| layer.commands.len()),
+            ),
+            None => (0, 0),
+        };
+        let previous = Button::new("◀").on_click(|| step = step.saturating_sub(1));
+        let next = Button::new("▶").on_click(|| step = (step + 1).min(command_count.saturating_sub(1)));
+        let overdraw_checkbox = CheckboxField::new("Overdraw", overdraw).on_toggled(|v| overdraw = v);
+        let layer_selector: Arc<WidgetPod> = if layer_count > 0 {
+            let choices: Vec<String> = current_capture
+                .iter()
+                .flat_map(|capture| capture.layers.iter())
+                .enumerate()
+                .map(|(i, layer)| format!("Layer {} ({}×{})", i, layer.size.width, layer.size.height))
+                .collect();
+            DropDown::with_selected_index(layer_index.min(layer_count - 1), choices.clone(), DisplayFormatter)
+                .on_selected_item_changed(|choice| {
+                    if let Some(i) = choices.iter().position(|c| *c == choice) {
+                        layer_index = i;
+                        step = 0;
+                    }
+                })
+                .arc_dyn_pod()
+        } else {
+            Null.arc_dyn_pod()
+        };
+        let status = Text::new(if command_count > 0 {
+            format!("{} / {}", step + 1, command_count)
+        } else {
+            "No capture".to_string()
+        })
+        .vertical_alignment(Alignment::CENTER);
+        toolbar.insert((
+            capture_button,
+            layer_selector,
+            previous,
+            next,
+            overdraw_checkbox,
+            status,
+        ));
+        grid.place((0, ..), 0, toolbar.padding(4.dip()).arc_dyn_pod());
+
+        // command list & preview
+        if let Some(capture) = current_capture {
+            let layer_index = layer_index.min(layer_count.saturating_sub(1));
+            let mut list = Grid::column(grid::TrackBreadth::Flex(1.0));
+            if let Some(layer) = capture.layers.get(layer_index) {
+                for (i, command) in layer.commands.iter().enumerate() {
+                    cache::scoped(i, || {
+                        let label = format!(
+                            "{}{} {:?} @ {},{} {}×{}",
+                            "  ".repeat(command.depth),
+                            command.widget_name,
+                            WidgetId::dbg_option(command.widget_id),
+                            command.bounds.origin.x,
+                            command.bounds.origin.y,
+                            command.bounds.size.width,
+                            command.bounds.size.height,
+                        );
+                        let mut text = Text::new(label);
+                        if i == step {
+                            text = text.color(theme::palette::BLUE_600);
+                        }
+                        let item = Clickable::new(text);
+                        if item.clicked() {
+                            step = i;
+                        }
+                        list.insert(item);
+                    });
+                }
+            }
+            grid.place((1, 0), 0, ScrollArea::new(list).fill().arc_dyn_pod());
+            grid.place(
+                (1, 1),
+                0,
+                CapturePreview {
+                    capture,
+                    layer: layer_index,
+                    step,
+                    overdraw,
+                }
+                .arc_dyn_pod(),
+            );
+        }
+
+        FrameDebugger { inner: grid }
+    }
+}
@@ -0,0 +1,68 @@
+//! Custom window decorations.
+use crate::{event::InternalEvent, widget::prelude::*};
+use kyute_shell::window::WindowPart;
+use std::cell::Cell;
+
+/// Declares that the wrapped widget acts as a part of the window decorations, e.g. the title bar or the
+/// window buttons.
+///
+/// This only has an effect in windows with custom decorations (see `Window::custom_frame`): when the pointer is
+/// over the widget, the system reacts as if it were over the corresponding part of a native frame. For instance,
+/// dragging a `WindowPart::Caption` region moves the window.
+///
+/// Regions nested in another take precedence over it: wrap the interactive widgets placed in a title bar
+/// with `WindowPart::Client` so that they still receive input.
+pub struct FrameRegion<Inner> {
+    inner: Inner,
+    part: WindowPart,
+    bounds: Cell<Rect>,
+}
+
+impl<Inner: Widget + 'static> FrameRegion<Inner> {
+    pub fn new(inner: Inner, part: WindowPart) -> FrameRegion<Inner> {
+        FrameRegion {
+            inner,
+            part,
+            bounds: Cell::new(Rect::zero()),
+        }
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for FrameRegion<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.inner.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let geometry = self.inner.layout(ctx, constraints, env);
+        self.bounds.set(geometry.measurements.local_bounds());
+        geometry
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Event::Internal(InternalEvent::CollectFrameRegions { regions }) = event {
+            // pushed before the descendants, so that their regions take precedence
+            let bounds = ctx.window_transform().outer_transformed_rect(&self.bounds.get());
+            regions.push((bounds, self.part));
+        }
+        self.inner.route_event(ctx, event, env)
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new(format!("window part: {:?}", self.part))
+    }
+}
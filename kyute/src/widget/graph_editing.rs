@@ -0,0 +1,515 @@
+//! Interaction controllers for node graph editors (see `NodeCanvas`).
+use crate::{
+    cache,
+    drawing::ToSkia,
+    event::{Modifiers, PointerButton, PointerEventKind},
+    theme,
+    widget::{
+        node_canvas::{paint_edge, Guide},
+        prelude::*,
+        Edge, EdgeRouting, NodeIndex,
+    },
+    Color, Signal,
+};
+use parking_lot::Mutex;
+use skia_safe as sk;
+use std::{collections::BTreeSet, sync::Arc};
+
+/// Default distance under which the pointer is considered to be over a port, in DIPs.
+const DEFAULT_PORT_HIT_RADIUS: f64 = 8.0;
+/// Distance around a node in which `snap_to_nodes` looks for nodes to align to, in DIPs.
+const SNAP_SEARCH_DISTANCE: f64 = 400.0;
+/// Tolerance when comparing snapped coordinates.
+const SNAP_EPSILON: f64 = 1e-6;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Rubber-band selection
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// How a rubber-band selection combines with the current selection.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SelectionMode {
+    /// Replace the current selection.
+    Replace,
+    /// Add the nodes to the current selection.
+    Add,
+    /// Toggle the selection state of the nodes.
+    Toggle,
+}
+
+impl SelectionMode {
+    /// Returns the selection mode corresponding to the modifiers held at the start of a selection:
+    /// Shift adds to the selection, Ctrl toggles.
+    pub fn from_modifiers(modifiers: Modifiers) -> SelectionMode {
+        if modifiers.contains(Modifiers::SHIFT) {
+            SelectionMode::Add
+        } else if modifiers.contains(Modifiers::CONTROL) {
+            SelectionMode::Toggle
+        } else {
+            SelectionMode::Replace
+        }
+    }
+}
+
+/// A completed rubber-band selection.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RubberBand {
+    /// The selection rectangle, in the local coordinates of the `RubberBandSelection` widget.
+    pub rect: Rect,
+    pub mode: SelectionMode,
+}
+
+impl RubberBand {
+    /// Updates `selection` with the nodes of `index` that intersect the selection rectangle.
+    pub fn apply(&self, index: &NodeIndex, selection: &mut BTreeSet<usize>) {
+        let nodes = index.query(&self.rect);
+        match self.mode {
+            SelectionMode::Replace => {
+                selection.clear();
+                selection.extend(nodes);
+            }
+            SelectionMode::Add => selection.extend(nodes),
+            SelectionMode::Toggle => {
+                for node in nodes {
+                    if !selection.remove(&node) {
+                        selection.insert(node);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// State of a rubber-band selection in progress.
+#[derive(Copy, Clone, Debug)]
+struct RubberBandDrag {
+    anchor: Point,
+    current: Point,
+    modifiers: Modifiers,
+}
+
+impl RubberBandDrag {
+    fn rect(&self) -> Rect {
+        Rect::from_points([self.anchor, self.current])
+    }
+}
+
+/// Lets the user select multiple items by dragging a rectangle with the left mouse button.
+///
+/// Dragging starts on pointer presses that aren't handled by the inner widget, e.g. on the background of a
+/// `NodeCanvas`. The rectangle is shown above the inner widget while dragging. Shift and Ctrl, when held at the
+/// start of the drag, respectively extend and toggle the selection (see `SelectionMode`).
+///
+/// When wrapping a `NodeCanvas` shown in a `ZoomView`, the selection rectangle is in canvas coordinates, so it
+/// can be applied directly to the `NodeIndex` of the canvas (see `RubberBand::apply`).
+pub struct RubberBandSelection<Inner> {
+    id: WidgetId,
+    inner: Inner,
+    drag: Arc<Mutex<Option<RubberBandDrag>>>,
+    selected: Signal<RubberBand>,
+}
+
+impl<Inner: Widget + 'static> RubberBandSelection<Inner> {
+    #[composable]
+    pub fn new(inner: Inner) -> RubberBandSelection<Inner> {
+        RubberBandSelection {
+            id: WidgetId::here(),
+            inner,
+            drag: cache::once(|| Arc::new(Mutex::new(None))),
+            selected: Signal::new(),
+        }
+    }
+
+    /// Returns the rubber-band selection that has just been completed, if any.
+    pub fn selected(&self) -> Option<RubberBand> {
+        self.selected.value()
+    }
+
+    pub fn on_selected(self, f: impl FnOnce(RubberBand)) -> Self {
+        self.selected.map(f);
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for RubberBandSelection<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        let mut drag = self.drag.lock();
+
+        if let Event::Pointer(p) = event {
+            match p.kind {
+                PointerEventKind::PointerMove if drag.is_some() => {
+                    drag.as_mut().unwrap().current = p.position;
+                    ctx.request_repaint();
+                    ctx.set_handled();
+                    return;
+                }
+                PointerEventKind::PointerUp if drag.is_some() => {
+                    let d = drag.take().unwrap();
+                    self.selected.signal(RubberBand {
+                        rect: d.rect(),
+                        mode: SelectionMode::from_modifiers(d.modifiers),
+                    });
+                    ctx.release_pointer();
+                    ctx.request_repaint();
+                    ctx.set_handled();
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        self.inner.route_event(ctx, event, env);
+
+        if let Event::Pointer(p) = event {
+            if p.kind == PointerEventKind::PointerDown && p.button == Some(PointerButton::LEFT) && !ctx.handled() {
+                *drag = Some(RubberBandDrag {
+                    anchor: p.position,
+                    current: p.position,
+                    modifiers: p.modifiers,
+                });
+                ctx.capture_pointer();
+                ctx.set_handled();
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx);
+
+        if let Some(drag) = *self.drag.lock() {
+            let rect = drag.rect().to_skia();
+            let color = theme::palette::BLUE_500.to_skia();
+            let canvas = ctx.surface.canvas();
+            let mut fill = sk::Paint::new(color, None);
+            fill.set_alpha_f(0.15);
+            canvas.draw_rect(rect, &fill);
+            let mut stroke = sk::Paint::new(color, None);
+            stroke.set_style(sk::PaintStyle::Stroke);
+            stroke.set_stroke_width(1.0);
+            canvas.draw_rect(rect, &stroke);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Edge dragging
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A point on which edges can be attached.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Port<K> {
+    /// Identifies the port in `EdgeDragController::connected`.
+    pub key: K,
+    /// Position of the port, in the local coordinates of the `EdgeDragController` widget.
+    pub position: Point,
+}
+
+impl<K> Port<K> {
+    pub fn new(key: K, position: Point) -> Port<K> {
+        Port { key, position }
+    }
+}
+
+/// State of an edge drag in progress.
+#[derive(Copy, Clone, Debug)]
+struct EdgeDrag {
+    /// Index of the port where the drag started.
+    source: usize,
+    /// Current pointer position.
+    position: Point,
+}
+
+/// Lets the user create edges by dragging from one port to another with the left mouse button.
+///
+/// Presses on a port start a drag before the inner widget sees them. While dragging, a preview of the edge is
+/// shown above the inner widget, snapping to the port under the pointer. Releasing the pointer over another port
+/// signals a connection between the two ports (see `connected`); it's up to the application to decide whether
+/// to create the edge.
+pub struct EdgeDragController<Inner, K> {
+    id: WidgetId,
+    inner: Inner,
+    ports: Vec<Port<K>>,
+    routing: EdgeRouting,
+    color: Color,
+    hit_radius: f64,
+    drag: Arc<Mutex<Option<EdgeDrag>>>,
+    connected: Signal<(K, K)>,
+}
+
+impl<Inner: Widget + 'static, K: Clone + 'static> EdgeDragController<Inner, K> {
+    #[composable]
+    pub fn new(inner: Inner, ports: Vec<Port<K>>) -> EdgeDragController<Inner, K> {
+        EdgeDragController {
+            id: WidgetId::here(),
+            inner,
+            ports,
+            routing: EdgeRouting::default(),
+            color: theme::palette::BLUE_500,
+            hit_radius: DEFAULT_PORT_HIT_RADIUS,
+            drag: cache::once(|| Arc::new(Mutex::new(None))),
+            connected: Signal::new(),
+        }
+    }
+
+    /// Sets how the preview edge is routed.
+    #[must_use]
+    pub fn routing(mut self, routing: EdgeRouting) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Sets the color of the preview edge.
+    #[must_use]
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the distance under which the pointer is considered to be over a port.
+    #[must_use]
+    pub fn hit_radius(mut self, radius: f64) -> Self {
+        self.hit_radius = radius;
+        self
+    }
+
+    /// Returns the keys of the source and target ports of the edge that has just been dropped, if any.
+    pub fn connected(&self) -> Option<(K, K)> {
+        self.connected.value()
+    }
+
+    pub fn on_connected(self, f: impl FnOnce(K, K)) -> Self {
+        self.connected.map(|(from, to)| f(from, to));
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+
+    /// Returns the index of the port nearest to `position` within the hit radius, other than `exclude`.
+    fn port_at(&self, position: Point, exclude: Option<usize>) -> Option<usize> {
+        self.ports
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| Some(i) != exclude)
+            .map(|(i, port)| (i, (port.position - position).length()))
+            .filter(|&(_, distance)| distance <= self.hit_radius)
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(i, _)| i)
+    }
+}
+
+impl<Inner: Widget + 'static, K: Clone + 'static> Widget for EdgeDragController<Inner, K> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        let mut drag = self.drag.lock();
+
+        if let Event::Pointer(p) = event {
+            match p.kind {
+                PointerEventKind::PointerDown if p.button == Some(PointerButton::LEFT) => {
+                    if let Some(source) = self.port_at(p.position, None) {
+                        *drag = Some(EdgeDrag {
+                            source,
+                            position: p.position,
+                        });
+                        ctx.capture_pointer();
+                        ctx.request_repaint();
+                        ctx.set_handled();
+                        return;
+                    }
+                }
+                PointerEventKind::PointerMove if drag.is_some() => {
+                    drag.as_mut().unwrap().position = p.position;
+                    ctx.request_repaint();
+                    ctx.set_handled();
+                    return;
+                }
+                PointerEventKind::PointerUp if drag.is_some() => {
+                    let d = drag.take().unwrap();
+                    if let Some(target) = self.port_at(p.position, Some(d.source)) {
+                        self.connected
+                            .signal((self.ports[d.source].key.clone(), self.ports[target].key.clone()));
+                    }
+                    ctx.release_pointer();
+                    ctx.request_repaint();
+                    ctx.set_handled();
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        self.inner.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx);
+
+        if let Some(drag) = *self.drag.lock() {
+            // the ports may have been removed by a recomposition during the drag
+            if let Some(source) = self.ports.get(drag.source) {
+                let to = match self.port_at(drag.position, Some(drag.source)) {
+                    Some(target) => self.ports[target].position,
+                    None => drag.position,
+                };
+                let edge = Edge::new(source.position, to, self.color).routing(self.routing);
+                paint_edge(ctx.surface.canvas(), &edge);
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Snapping
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Result of `snap_to_nodes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snap {
+    /// Offset to apply to the rectangle to align it.
+    pub offset: Offset,
+    /// Guide lines showing the alignments, to pass to `NodeCanvas::guides`.
+    pub guides: Vec<Guide>,
+}
+
+fn x_anchors(rect: &Rect) -> [f64; 3] {
+    [rect.min_x(), rect.center().x, rect.max_x()]
+}
+
+fn y_anchors(rect: &Rect) -> [f64; 3] {
+    [rect.min_y(), rect.center().y, rect.max_y()]
+}
+
+/// Returns the smallest offset, no larger than `threshold`, that aligns one of `anchors` with one of `others`.
+fn nearest_alignment(anchors: [f64; 3], others: [f64; 3], threshold: f64, best: &mut Option<f64>) {
+    for &a in anchors.iter() {
+        for &o in others.iter() {
+            let d = o - a;
+            if d.abs() <= threshold && best.map_or(true, |best| d.abs() < best.abs()) {
+                *best = Some(d);
+            }
+        }
+    }
+}
+
+/// Aligns the left, center or right, and the top, center or bottom of a node being moved with nearby nodes.
+///
+/// `rect` is the current bounds of the moved node, and `exclude` the nodes to ignore (typically, the nodes being
+/// moved). Alignments are made only if the distance is no more than `threshold`.
+pub fn snap_to_nodes(rect: Rect, index: &NodeIndex, exclude: &[usize], threshold: f64) -> Snap {
+    let candidates: Vec<Rect> = index
+        .query(&rect.inflate(SNAP_SEARCH_DISTANCE, SNAP_SEARCH_DISTANCE))
+        .into_iter()
+        .filter(|node| !exclude.contains(node))
+        .map(|node| index.bounds(node))
+        .collect();
+
+    let mut dx = None;
+    let mut dy = None;
+    for other in candidates.iter() {
+        nearest_alignment(x_anchors(&rect), x_anchors(other), threshold, &mut dx);
+        nearest_alignment(y_anchors(&rect), y_anchors(other), threshold, &mut dy);
+    }
+
+    let offset = Offset::new(dx.unwrap_or(0.0), dy.unwrap_or(0.0));
+    let snapped = rect.translate(offset);
+
+    // show a guide for every alignment, not only the one that determined the offset
+    let mut guides = vec![];
+    for other in candidates.iter() {
+        if dx.is_some() {
+            for &x in x_anchors(&snapped).iter() {
+                if x_anchors(other).iter().any(|&o| (o - x).abs() < SNAP_EPSILON) {
+                    guides.push(Guide {
+                        from: Point::new(x, snapped.min_y().min(other.min_y())),
+                        to: Point::new(x, snapped.max_y().max(other.max_y())),
+                    });
+                }
+            }
+        }
+        if dy.is_some() {
+            for &y in y_anchors(&snapped).iter() {
+                if y_anchors(other).iter().any(|&o| (o - y).abs() < SNAP_EPSILON) {
+                    guides.push(Guide {
+                        from: Point::new(snapped.min_x().min(other.min_x()), y),
+                        to: Point::new(snapped.max_x().max(other.max_x()), y),
+                    });
+                }
+            }
+        }
+    }
+
+    Snap { offset, guides }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rubber_band_modes() {
+        let mut index = NodeIndex::new();
+        let a = index.insert(Rect::new(Point::new(0.0, 0.0), Size::new(10.0, 10.0)));
+        let b = index.insert(Rect::new(Point::new(100.0, 0.0), Size::new(10.0, 10.0)));
+        let band = |mode| RubberBand {
+            rect: Rect::new(Point::new(-5.0, -5.0), Size::new(20.0, 20.0)),
+            mode,
+        };
+
+        let mut selection: BTreeSet<usize> = [b].iter().copied().collect();
+        band(SelectionMode::Add).apply(&index, &mut selection);
+        assert_eq!(selection, [a, b].iter().copied().collect());
+        band(SelectionMode::Toggle).apply(&index, &mut selection);
+        assert_eq!(selection, [b].iter().copied().collect());
+        band(SelectionMode::Replace).apply(&index, &mut selection);
+        assert_eq!(selection, [a].iter().copied().collect());
+    }
+
+    #[test]
+    fn snap_aligns_nearby_edges() {
+        let mut index = NodeIndex::new();
+        let other = index.insert(Rect::new(Point::new(0.0, 0.0), Size::new(100.0, 50.0)));
+        let moved = index.insert(Rect::new(Point::new(3.0, 200.0), Size::new(100.0, 50.0)));
+
+        let snap = snap_to_nodes(index.bounds(moved), &index, &[moved], 5.0);
+        assert_eq!(snap.offset, Offset::new(-3.0, 0.0));
+        // left, center and right edges are aligned
+        assert_eq!(snap.guides.len(), 3);
+        assert!(snap.guides.iter().all(|g| g.from.x == g.to.x));
+
+        let far = snap_to_nodes(index.bounds(moved), &index, &[moved, other], 5.0);
+        assert_eq!(far.offset, Offset::zero());
+        assert!(far.guides.is_empty());
+    }
+}
@@ -5,19 +5,24 @@ use crate::{
     css::parse_from_str,
     drawing,
     drawing::{Paint, PaintCtxExt, Shape, ToSkia},
-    style,
-    widget::prelude::*,
+    style, theme,
+    widget::{
+        prelude::*,
+        widget_pod::{sort_by_z_index, ZOrder},
+    },
     Color, Data, EnvKey, Length, RoundToPixel, State, WidgetId,
 };
 use cssparser::{ParseError, Parser, Token};
 use kyute::css::parse_css_length;
 use lazy_static::lazy_static;
+use parking_lot::Mutex;
 use std::{
     cell::Cell,
+    collections::HashMap,
     convert::{TryFrom, TryInto},
     mem,
     ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive},
-    sync::Arc,
+    sync::{Arc, Weak},
 };
 
 pub const SHOW_GRID_LAYOUT_LINES: EnvKey<bool> = builtin_env_key!("kyute.grid.show-layout-lines");
@@ -31,6 +36,8 @@ pub enum TrackBreadth {
     Fixed(Length),
     /// Proportion of remaining space.
     Flex(f64),
+    /// Size to content, up to the specified size (only valid as a maximum, see `TrackSize::fit_content`).
+    FitContent(Length),
 }
 
 impl Default for TrackBreadth {
@@ -88,6 +95,24 @@ impl TrackSize {
             max_size: max_size.into(),
         }
     }
+
+    /// Defines a track that is sized to content, but not larger than `limit` unless its contents can't shrink
+    /// (CSS `fit-content(<limit>)`).
+    pub fn fit_content(limit: impl Into<Length>) -> TrackSize {
+        TrackSize {
+            min_size: TrackBreadth::Auto,
+            max_size: TrackBreadth::FitContent(limit.into()),
+        }
+    }
+
+    /// Returns the fixed size used to compute the number of repetitions of `repeat(auto-fill, ...)`: the maximum size
+    /// if fixed, otherwise the minimum size.
+    fn fixed_size(&self, constraints: &LayoutParams, env: &Environment) -> Option<f64> {
+        match (self.max_size, self.min_size) {
+            (TrackBreadth::Fixed(size), _) | (_, TrackBreadth::Fixed(size)) => Some(size.compute(constraints, env)),
+            _ => None,
+        }
+    }
 }
 
 impl From<TrackBreadth> for TrackSize {
@@ -117,7 +142,28 @@ impl TrackBreadth {
 }
 
 impl TrackSize {
+    /// Parses a `<track-size>`: a track breadth, `minmax(<min>, <max>)` or `fit-content(<length>)`.
     pub(crate) fn parse_impl<'i>(input: &mut Parser<'i, '_>) -> Result<TrackSize, ParseError<'i, ()>> {
+        if let Ok(track_size) = input.try_parse(|input| {
+            let function = input.expect_function()?.clone();
+            input.parse_nested_block(|input| match &*function {
+                "minmax" => {
+                    let min = TrackBreadth::parse_impl(input)?;
+                    input.expect_comma()?;
+                    let max = TrackBreadth::parse_impl(input)?;
+                    // flexible minimums are not allowed
+                    if let TrackBreadth::Flex(_) = min {
+                        return Err(input.new_custom_error(()));
+                    }
+                    Ok(TrackSize::minmax(min, max))
+                }
+                "fit-content" => Ok(TrackSize::fit_content(parse_css_length(input)?)),
+                _ => Err(input.new_custom_error(())),
+            })
+        }) {
+            return Ok(track_size);
+        }
+
         let breadth = TrackBreadth::parse_impl(input)?;
         Ok(TrackSize {
             min_size: breadth,
@@ -126,6 +172,66 @@ impl TrackSize {
     }
 }
 
+/// Number of repetitions in `repeat()`.
+enum RepeatCount {
+    Count(usize),
+    AutoFill,
+}
+
+/// Parses `repeat(<count>, <track-list>)`.
+///
+/// Returns the number of repetitions, the tracks, and the line names relative to the first track.
+#[allow(clippy::type_complexity)]
+fn parse_repeat<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<(RepeatCount, Vec<TrackSize>, Vec<(usize, String)>), ParseError<'i, ()>> {
+    input.expect_function_matching("repeat")?;
+    input.parse_nested_block(|input| {
+        let count = if input
+            .try_parse(|input| input.expect_ident_matching("auto-fill"))
+            .is_ok()
+        {
+            RepeatCount::AutoFill
+        } else {
+            match input.expect_integer()? {
+                count if count > 0 => RepeatCount::Count(count as usize),
+                _ => return Err(input.new_custom_error(())),
+            }
+        };
+        input.expect_comma()?;
+
+        let mut sizes = vec![];
+        let mut line_names = vec![];
+        loop {
+            if let Ok(names) = input.try_parse(grid_line_names) {
+                let i = sizes.len();
+                for name in names {
+                    line_names.push((i, name));
+                }
+            }
+            match input.try_parse(TrackSize::parse_impl) {
+                Ok(track_size) => sizes.push(track_size),
+                Err(_) => break,
+            }
+        }
+        if sizes.is_empty() {
+            return Err(input.new_custom_error(()));
+        }
+        // the number of automatic repetitions is computed from the fixed sizes of the tracks
+        if let RepeatCount::AutoFill = count {
+            if sizes.iter().any(|size| {
+                !matches!(
+                    (size.min_size, size.max_size),
+                    (TrackBreadth::Fixed(_), _) | (_, TrackBreadth::Fixed(_))
+                )
+            }) {
+                return Err(input.new_custom_error(()));
+            }
+        }
+        Ok((count, sizes, line_names))
+    })
+}
+
 /// Orientation of a grid track.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 enum Axis {
@@ -153,11 +259,19 @@ fn size_across(axis: Axis, size: Size) -> f64 {
     }
 }
 
+/// Maximum number of repetitions of `repeat(auto-fill, ...)`.
+const MAX_AUTO_REPETITIONS: usize = 1000;
+
 /// List of tracks.
 #[derive(Clone, Debug, Default)]
 pub struct TrackList {
     pub sizes: Vec<TrackSize>,
     pub line_names: Vec<(usize, String)>,
+    /// Tracks in `sizes` that are repeated as many times as they fit in the grid (`repeat(auto-fill, ...)`).
+    ///
+    /// `sizes` and `line_names` contain one repetition: this is what line numbers and names refer to when placing
+    /// items.
+    pub auto_repeat: Option<Range<usize>>,
 }
 
 fn grid_line_names<'i>(input: &mut Parser<'i, '_>) -> Result<Vec<String>, ParseError<'i, ()>> {
@@ -170,8 +284,19 @@ fn grid_line_names<'i>(input: &mut Parser<'i, '_>) -> Result<Vec<String>, ParseE
 
 impl TrackList {
     pub(crate) fn parse_css<'i>(input: &mut Parser<'i, '_>) -> Result<TrackList, ParseError<'i, ()>> {
+        TrackList::parse_with_area_rows(input, None)
+    }
+
+    /// Parses a track list. If `area_rows` is specified, each track can be preceded by a string listing the names of the
+    /// grid areas in the track, as in the `grid-template` shorthand. Tracks with a string but no size are sized to
+    /// content.
+    fn parse_with_area_rows<'i>(
+        input: &mut Parser<'i, '_>,
+        mut area_rows: Option<&mut Vec<String>>,
+    ) -> Result<TrackList, ParseError<'i, ()>> {
         let mut line_names: Vec<(usize, String)> = vec![];
         let mut sizes = vec![];
+        let mut auto_repeat = None;
         loop {
             if let Ok(names) = input.try_parse(grid_line_names) {
                 let i = sizes.len();
@@ -180,6 +305,37 @@ impl TrackList {
                 }
             }
 
+            if let Ok((count, repeated_sizes, repeated_line_names)) = input.try_parse(parse_repeat) {
+                let count = match count {
+                    RepeatCount::Count(count) => count,
+                    RepeatCount::AutoFill => {
+                        // only one automatic repetition is allowed
+                        if auto_repeat.is_some() {
+                            return Err(input.new_custom_error(()));
+                        }
+                        auto_repeat = Some(sizes.len()..(sizes.len() + repeated_sizes.len()));
+                        1
+                    }
+                };
+                for _ in 0..count {
+                    let start = sizes.len();
+                    line_names.extend(repeated_line_names.iter().map(|(i, name)| (start + i, name.clone())));
+                    sizes.extend_from_slice(&repeated_sizes);
+                }
+                continue;
+            }
+
+            if let Some(ref mut area_rows) = area_rows {
+                if let Ok(row) = input.try_parse(|input| Ok::<_, ParseError<()>>(input.expect_string()?.to_string())) {
+                    area_rows.push(row);
+                    let track_size = input
+                        .try_parse(TrackSize::parse_impl)
+                        .unwrap_or_else(|_| TrackSize::new(TrackBreadth::Auto));
+                    sizes.push(track_size);
+                    continue;
+                }
+            }
+
             if let Ok(track_size) = input.try_parse(TrackSize::parse_impl) {
                 sizes.push(track_size);
             } else {
@@ -187,8 +343,111 @@ impl TrackList {
             }
         }
 
-        Ok(TrackList { sizes, line_names })
+        Ok(TrackList {
+            sizes,
+            line_names,
+            auto_repeat,
+        })
+    }
+
+    /// Returns the tracks, with the automatic repetitions (see `auto_repeat`) expanded to fill `available_space`.
+    ///
+    /// As in CSS, tracks are repeated as many times as possible without overflowing the available space, assuming
+    /// that they have their fixed sizes, and at least once.
+    fn expand(&self, available_space: f64, gap: f64, constraints: &LayoutParams, env: &Environment) -> Vec<TrackSize> {
+        let repeated = match self.auto_repeat {
+            Some(ref repeated) if available_space.is_finite() => repeated.clone(),
+            _ => return self.sizes.clone(),
+        };
+
+        let fixed_sizes = |tracks: &[TrackSize]| {
+            tracks
+                .iter()
+                .map(|track| track.fixed_size(constraints, env).unwrap_or(0.0))
+                .sum::<f64>()
+        };
+        let other_tracks = self.sizes.len() - repeated.len();
+        let other_size = fixed_sizes(&self.sizes[..repeated.start]) + fixed_sizes(&self.sizes[repeated.end..]);
+        let repetition_size = fixed_sizes(&self.sizes[repeated.clone()]) + gap * repeated.len() as f64;
+        // with N repetitions, the tracks take `other_size + N * repetition_size + gap * (other_tracks - 1)`
+        let count = if repetition_size > 0.0 {
+            ((available_space - other_size - gap * (other_tracks as f64 - 1.0)) / repetition_size).floor()
+        } else {
+            1.0
+        };
+        let count = (count.max(1.0) as usize).min(MAX_AUTO_REPETITIONS);
+
+        let mut sizes = Vec::with_capacity(other_tracks + count * repeated.len());
+        sizes.extend_from_slice(&self.sizes[..repeated.end]);
+        for _ in 1..count {
+            sizes.extend_from_slice(&self.sizes[repeated.clone()]);
+        }
+        sizes.extend_from_slice(&self.sizes[repeated.end..]);
+        sizes
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Named areas
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A named grid area, as defined in the grid template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamedArea {
+    pub name: String,
+    /// Rows covered by the area.
+    pub rows: Range<usize>,
+    /// Columns covered by the area.
+    pub columns: Range<usize>,
+}
+
+/// Parses the rows of a `grid-template-areas` value (one string per row) into named areas.
+///
+/// Returns the areas and the number of columns, or `None` if the rows don't have the same number of columns, or
+/// if an area isn't rectangular.
+fn parse_template_areas(rows: &[String]) -> Option<(Vec<NamedArea>, usize)> {
+    let cells: Vec<Vec<&str>> = rows.iter().map(|row| row.split_whitespace().collect()).collect();
+    let column_count = cells.first().map_or(0, |row| row.len());
+    if cells.iter().any(|row| row.len() != column_count) {
+        warn!("grid-template-areas: rows don't have the same number of columns");
+        return None;
+    }
+
+    let mut areas: Vec<NamedArea> = vec![];
+    for (i, row) in cells.iter().enumerate() {
+        for (j, &name) in row.iter().enumerate() {
+            // sequences of dots are null cells
+            if name.chars().all(|c| c == '.') {
+                continue;
+            }
+            if let Some(area) = areas.iter_mut().find(|area| area.name == name) {
+                area.rows.start = area.rows.start.min(i);
+                area.rows.end = area.rows.end.max(i + 1);
+                area.columns.start = area.columns.start.min(j);
+                area.columns.end = area.columns.end.max(j + 1);
+            } else {
+                areas.push(NamedArea {
+                    name: name.to_string(),
+                    rows: i..i + 1,
+                    columns: j..j + 1,
+                });
+            }
+        }
+    }
+
+    // the cells within the bounds of an area must all have its name
+    for area in areas.iter() {
+        if area
+            .rows
+            .clone()
+            .any(|i| area.columns.clone().any(|j| cells[i][j] != area.name))
+        {
+            warn!("grid-template-areas: area `{}` is not rectangular", area.name);
+            return None;
+        }
     }
+
+    Some((areas, column_count))
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -200,6 +459,8 @@ impl TrackList {
 pub struct GridTemplate {
     pub rows: TrackList,
     pub columns: TrackList,
+    /// Named areas (see `add_area`).
+    pub areas: Vec<NamedArea>,
 }
 
 impl GridTemplate {
@@ -207,6 +468,19 @@ impl GridTemplate {
         GridTemplate::default()
     }
 
+    /// Defines a named area covering the specified rows and columns.
+    ///
+    /// Like in CSS, this also defines the lines `<name>-start` and `<name>-end` in both directions. Widgets can be
+    /// placed into the area with `grid_area("<name>")`.
+    pub fn add_area(&mut self, name: impl Into<String>, rows: Range<usize>, columns: Range<usize>) {
+        let name = name.into();
+        self.rows.line_names.push((rows.start, format!("{}-start", name)));
+        self.rows.line_names.push((rows.end, format!("{}-end", name)));
+        self.columns.line_names.push((columns.start, format!("{}-start", name)));
+        self.columns.line_names.push((columns.end, format!("{}-end", name)));
+        self.areas.push(NamedArea { name, rows, columns });
+    }
+
     /*pub fn push_row(&mut self, size: impl Into<TrackSizePolicy>) {
         self.rows.push(size.into());
     }
@@ -225,12 +499,54 @@ impl GridTemplate {
 }
 
 impl GridTemplate {
+    /// Parses a grid template.
+    ///
+    /// The syntax is the one of the CSS `grid-template` shorthand: `<rows> / <columns>`. The rows can be preceded by
+    /// strings naming the grid areas, as in `grid-template-areas`, in which case the column list is optional:
+    ///
+    /// ```text
+    /// "header  header" auto
+    /// "sidebar main"   1fr
+    /// "footer  footer" 30px
+    /// / 200px 1fr
+    /// ```
     pub(crate) fn parse_css<'i>(input: &mut Parser<'i, '_>) -> Result<GridTemplate, ParseError<'i, ()>> {
-        // TODO
-        let rows = TrackList::parse_css(input)?;
-        input.expect_delim('/')?;
-        let columns = TrackList::parse_css(input)?;
-        Ok(GridTemplate { rows, columns })
+        let mut area_rows = vec![];
+        let rows = TrackList::parse_with_area_rows(input, Some(&mut area_rows))?;
+        if area_rows.is_empty() {
+            input.expect_delim('/')?;
+            let columns = TrackList::parse_css(input)?;
+            return Ok(GridTemplate {
+                rows,
+                columns,
+                areas: vec![],
+            });
+        }
+
+        // all rows must have a string
+        if area_rows.len() != rows.sizes.len() {
+            return Err(input.new_custom_error(()));
+        }
+        let (areas, column_count) = parse_template_areas(&area_rows).ok_or_else(|| input.new_custom_error(()))?;
+        let mut columns = if input.try_parse(|input| input.expect_delim('/')).is_ok() {
+            TrackList::parse_css(input)?
+        } else {
+            TrackList::default()
+        };
+        // columns covered by areas but not in the column list are sized to content
+        while columns.sizes.len() < column_count {
+            columns.sizes.push(TrackSize::new(TrackBreadth::Auto));
+        }
+
+        let mut template = GridTemplate {
+            rows,
+            columns,
+            areas: vec![],
+        };
+        for area in areas {
+            template.add_area(area.name, area.rows, area.columns);
+        }
+        Ok(template)
     }
 }
 
@@ -451,10 +767,13 @@ impl LineRange {
             let end = Line::parse_css(input)?;
             Ok(LineRange { start, end })
         } else {
-            Ok(LineRange {
-                start: start.clone(),
-                end: Line::Auto,
-            })
+            // a single name stands for the whole named area
+            let end = if let Line::Named(_) = start {
+                start.clone()
+            } else {
+                Line::Auto
+            };
+            Ok(LineRange { start, end })
         }
     }
 }
@@ -480,6 +799,19 @@ fn line_index(index: i32, line_count: usize) -> usize {
     }
 }
 
+/// Finds the line with the specified name.
+///
+/// As in CSS, if there's no line with this name, looks for the implicit line `<name>-start` or `<name>-end` of the
+/// named area, depending on `side`.
+fn find_named_line(named_lines: &[(usize, String)], ident: &str, side: &str) -> Option<usize> {
+    let find = |ident: &str| {
+        named_lines
+            .iter()
+            .find_map(|(line, name)| if name == ident { Some(*line) } else { None })
+    };
+    find(ident).or_else(|| find(&format!("{}-{}", ident, side)))
+}
+
 impl LineRange {
     fn resolve(&self, named_lines: &[(usize, String)], line_count: usize) -> (Option<usize>, usize) {
         if let (Line::Span(_), Line::Span(_)) = (&self.start, &self.end) {
@@ -496,10 +828,7 @@ impl LineRange {
                 //if let Line::
             }
             Line::Named(ref ident) => {
-                start = named_lines
-                    .iter()
-                    .find_map(|(line, name)| if name == ident { Some(line) } else { None })
-                    .cloned();
+                start = find_named_line(named_lines, ident, "start");
             }
             Line::Index(index) => {
                 start = Some(line_index(index, line_count));
@@ -514,10 +843,7 @@ impl LineRange {
                 //if let Line::
             }
             Line::Named(ref ident) => {
-                end = named_lines
-                    .iter()
-                    .find_map(|(line, name)| if name == ident { Some(line) } else { None })
-                    .cloned();
+                end = find_named_line(named_lines, ident, "end");
             }
             Line::Index(index) => {
                 end = Some(line_index(index, line_count));
@@ -561,13 +887,13 @@ pub struct Area {
     column: LineRange,
 }
 
-/*impl<'a> TryFrom<&'a str> for Area<'a> {
-    type Error = nom::error::Error<String>;
+impl<'a> TryFrom<&'a str> for Area {
+    type Error = ParseError<'a, ()>;
 
     fn try_from(input: &'a str) -> Result<Self, Self::Error> {
-        Area::parse(input)
+        parse_from_str(input, Area::parse_impl)
     }
-}*/
+}
 
 impl<Rows, Columns> From<(Rows, Columns)> for Area
 where
@@ -590,14 +916,25 @@ impl Area {
         let column_start = input.try_parse(Line::parse_css);
         let row_end = input.try_parse(Line::parse_css);
         let column_end = input.try_parse(Line::parse_css);
+        // as in CSS, omitted lines take the name of the area, if one was specified
+        let omitted = |line: &Line| {
+            if let Line::Named(_) = line {
+                line.clone()
+            } else {
+                Line::Auto
+            }
+        };
+        let column_start = column_start.unwrap_or_else(|_| omitted(&row_start));
+        let row_end = row_end.unwrap_or_else(|_| omitted(&row_start));
+        let column_end = column_end.unwrap_or_else(|_| omitted(&column_start));
         Ok(Area {
             row: LineRange {
                 start: row_start,
-                end: row_end.unwrap_or_default(),
+                end: row_end,
             },
             column: LineRange {
-                start: column_start.unwrap_or_default(),
-                end: column_end.unwrap_or_default(),
+                start: column_start,
+                end: column_end,
             },
         })
     }
@@ -819,6 +1156,12 @@ struct GridItem {
 }
 
 impl GridItem {
+    /// Returns the stacking order of the item: the z-order specified when placing the item in the grid,
+    /// plus the z-index of the widget.
+    fn z_index(&self) -> i32 {
+        self.z_order + self.widget.z_index()
+    }
+
     fn row_range(&self) -> Range<usize> {
         let (start, end) = self.row_range.get();
         start..end
@@ -853,6 +1196,75 @@ struct ComputeTrackSizeResult {
     size: f64,
 }
 
+/// Number of track sizing results kept by a grid (see `TrackSizingCache`).
+///
+/// Besides its final layout, a grid is often laid out speculatively by its parent, under other constraints, to get
+/// its natural size.
+const TRACK_SIZING_CACHE_ENTRIES: usize = 4;
+
+/// A grid item, as seen by the track sizing passes.
+#[derive(Clone, Debug)]
+struct TrackSizingItem {
+    widget: Weak<WidgetPod>,
+    layout_revision: u64,
+    row_range: (usize, usize),
+    column_range: (usize, usize),
+}
+
+impl PartialEq for TrackSizingItem {
+    fn eq(&self, other: &Self) -> bool {
+        Weak::ptr_eq(&self.widget, &other.widget)
+            && self.layout_revision == other.layout_revision
+            && self.row_range == other.row_range
+            && self.column_range == other.column_range
+    }
+}
+
+/// Inputs of the track sizing passes of `Grid::layout`.
+#[derive(Clone, Debug, PartialEq)]
+struct TrackSizingKey {
+    constraints: LayoutParams,
+    /// Font size, for track sizes specified in `em`.
+    font_size: f64,
+    row_tracks: Vec<TrackSize>,
+    column_tracks: Vec<TrackSize>,
+    implicit_row_size: TrackBreadth,
+    implicit_column_size: TrackBreadth,
+    row_gap: f64,
+    column_gap: f64,
+    items: Vec<TrackSizingItem>,
+}
+
+/// Results of the track sizing passes.
+#[derive(Clone, Debug)]
+struct TrackSizes {
+    row_layout: Vec<GridTrackLayout>,
+    column_layout: Vec<GridTrackLayout>,
+    width: f64,
+    height: f64,
+}
+
+/// Track sizes computed by the last layouts of a grid, most recently used first.
+#[derive(Debug, Default)]
+struct TrackSizingCache {
+    entries: Vec<(TrackSizingKey, TrackSizes)>,
+}
+
+impl TrackSizingCache {
+    fn get(&mut self, key: &TrackSizingKey) -> Option<TrackSizes> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index);
+        let sizes = entry.1.clone();
+        self.entries.insert(0, entry);
+        Some(sizes)
+    }
+
+    fn insert(&mut self, key: TrackSizingKey, sizes: TrackSizes) {
+        self.entries.insert(0, (key, sizes));
+        self.entries.truncate(TRACK_SIZING_CACHE_ENTRIES);
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 struct Computed {
     row_layout: Vec<GridTrackLayout>,
@@ -912,10 +1324,14 @@ pub struct Grid {
     implicit_row_size: TrackBreadth,
     implicit_column_size: TrackBreadth,
     auto_flow_dir: FlowDirection,
+    /// Whether auto-placed items fill the holes left by the previous items (see `FlowCursor`).
+    dense_auto_flow: bool,
     align_items: AlignItems,
     justify_items: JustifyItems,
     /// Computed layout & style values.
     computed: State<Arc<Computed>>,
+    /// Results of the track sizing passes, reused while their inputs stay the same.
+    track_sizing: Arc<Mutex<TrackSizingCache>>,
     cached_child_filter: Cell<Option<Bloom<WidgetId>>>,
 }
 
@@ -941,8 +1357,10 @@ impl Grid {
             align_items: AlignItems::Start,
             justify_items: JustifyItems::Start,
             auto_flow_dir: FlowDirection::Row,
+            dense_auto_flow: false,
             style: Arc::new(GridStyle::default()),
             computed: cache::state(|| Default::default()),
+            track_sizing: cache::once(|| Arc::new(Mutex::new(TrackSizingCache::default()))),
             cached_child_filter: Cell::new(None),
         }
     }
@@ -985,8 +1403,26 @@ impl Grid {
         items.insert(self);
     }
 
+    /// Returns the items in the order in which they are painted. Events are delivered in the reverse order.
+    fn items_in_paint_order(&self) -> ZOrder<GridItem> {
+        sort_by_z_index(&self.items, GridItem::z_index)
+    }
+
+    /// Inserts `count` items created by `f`, spreading their creation over several frames.
+    ///
+    /// `chunk_size` items are created at each frame: the grid fills progressively. See `cache::incremental`.
+    #[composable]
+    pub fn insert_incremental<I: Insertable>(&mut self, count: usize, chunk_size: usize, f: impl FnMut(usize) -> I) {
+        for item in cache::incremental(count, chunk_size, f) {
+            self.insert(item);
+        }
+    }
+
     /// Place an item at the specified location into the grid.
     ///
+    /// Items with a higher `z_order` are painted above the others, and receive events first. The z-index
+    /// of the widget pod (see `WidgetPod::z_index`) is added to `z_order`.
+    ///
     /// Does not affect the current insertion cursor.
     pub fn place(&mut self, area: impl Into<Area>, z_order: i32, widget: Arc<WidgetPod>) {
         let mut area = area.into().resolve(self);
@@ -1020,6 +1456,15 @@ impl Grid {
         self.auto_flow_dir = flow_direction;
     }
 
+    /// Sets whether items are packed densely, like CSS `grid-auto-flow: dense`.
+    ///
+    /// With dense packing, auto-placed items fill the holes left by the previous items (e.g. by items spanning
+    /// several tracks, or placed explicitly), so items may appear out of order. By default, items are placed in
+    /// order, after the previous auto-placed item.
+    pub fn set_dense_auto_flow(&mut self, dense: bool) {
+        self.dense_auto_flow = dense;
+    }
+
     /*/// Returns the grid layout computed during layout.
     ///
     /// Returns none if not calculated yet (called before layout).
@@ -1079,121 +1524,248 @@ pub enum FlowDirection {
     Column,
 }
 
-/// Helper to place items within a grid.
+/// Occupied cells of the grid during auto-placement, in flow-relative coordinates (see `FlowCursor`).
+#[derive(Debug, Default)]
+struct CellOccupancy {
+    rows: Vec<Vec<bool>>,
+}
+
+impl CellOccupancy {
+    /// Returns whether none of the cells in the specified area is occupied.
+    fn is_free(&self, rows: Range<usize>, columns: Range<usize>) -> bool {
+        rows.into_iter().all(|row| match self.rows.get(row) {
+            Some(cells) => columns
+                .clone()
+                .all(|column| !cells.get(column).copied().unwrap_or(false)),
+            None => true,
+        })
+    }
+
+    /// Marks the cells in the specified area as occupied.
+    fn occupy(&mut self, rows: Range<usize>, columns: Range<usize>) {
+        if self.rows.len() < rows.end {
+            self.rows.resize(rows.end, vec![]);
+        }
+        for cells in self.rows[rows].iter_mut() {
+            if cells.len() < columns.end {
+                cells.resize(columns.end, false);
+            }
+            cells[columns.clone()].fill(true);
+        }
+    }
+
+    /// Returns the number of columns covered by occupied cells.
+    fn column_count(&self) -> usize {
+        self.rows.iter().map(|cells| cells.len()).max().unwrap_or(0)
+    }
+}
+
+/// Places items in the grid, following the CSS grid auto-placement algorithm.
+///
+/// Positions are flow-relative: with `FlowDirection::Column`, the "rows" of the cursor are the columns of the grid,
+/// and vice versa. Items are placed in this order:
+/// 1. items with a definite row and column, where they are specified;
+/// 2. items with a definite row, in the first columns where they fit in their row;
+/// 3. the other items, at the first position where they fit after the cursor. Items with a definite column only
+///    move down rows.
+///
+/// With sparse packing (the default), the cursor only moves forward, and items are placed in order, possibly leaving
+/// holes. With dense packing, the search starts from the beginning of the grid (or row) for each item, so that
+/// smaller items fill the holes left by the previous ones.
 #[derive(Debug)]
 struct FlowCursor {
     row: usize,
     column: usize,
+    /// Number of columns that auto-placed items can use.
     row_len: usize,
     flow: FlowDirection,
+    dense: bool,
+    occupancy: CellOccupancy,
+    /// Sparse packing of items with a definite row: column after the last item placed in each row.
+    row_cursors: HashMap<usize, usize>,
 }
 
 impl FlowCursor {
-    /// Advances the cursor to the specified column, possibly going to the next row if necessary.
-    fn align(&mut self, column: usize) {
-        if self.column < column {
-            self.column = column;
-        } else if self.column > column {
-            self.row += 1;
-            self.column = column;
+    fn new(flow: FlowDirection, row_len: usize, dense: bool) -> FlowCursor {
+        FlowCursor {
+            row: 0,
+            column: 0,
+            row_len,
+            flow,
+            dense,
+            occupancy: CellOccupancy::default(),
+            row_cursors: HashMap::new(),
         }
     }
 
-    /// Advances the cursor by the specified row/column span.
-    fn next(&mut self, row_span: usize, column_span: usize) -> (usize, usize) {
-        let (row, column) = (self.row, self.column);
-        self.column += column_span;
-        if self.column >= self.row_len {
-            self.row += row_span;
-            self.column = 0;
-        }
-        (row, column)
+    /// Places an item with a definite row, in the first columns where it fits.
+    fn place_in_row(&mut self, row: usize, row_span: usize, column_span: usize) -> Range<usize> {
+        let start = if self.dense {
+            0
+        } else {
+            self.row_cursors.get(&row).copied().unwrap_or(0)
+        };
+        // there's always room after the last occupied column (implicit columns are created if necessary)
+        let column = (start..)
+            .find(|&column| {
+                self.occupancy
+                    .is_free(row..(row + row_span), column..(column + column_span))
+            })
+            .unwrap();
+        self.row_cursors.insert(row, column + column_span);
+        column..(column + column_span)
     }
 
-    /*fn place_helper(
-        &mut self,
-        row: usize,
-        row_span: usize,
-        column: usize,
-        column_span: usize,
-    ) -> (Range<usize>, Range<usize>) {
-        match self.flow {
-            FlowDirection::Row => (row..(row + row_span), column..(column + column_span)),
-            FlowDirection::Column => (column..(column + column_span), row..(row + row_span)),
+    /// Places an item with a definite column, in the first rows where it fits.
+    fn place_in_column(&mut self, column: usize, row_span: usize, column_span: usize) -> Range<usize> {
+        if self.dense {
+            self.row = 0;
+        } else if column < self.column {
+            self.row += 1;
         }
-    }*/
-
-    fn place(&mut self, area: DefiniteArea) -> (Range<usize>, Range<usize>) {
-        let mut row = area.row;
-        let mut column = area.column;
-        let mut row_span = area.row_span;
-        let mut column_span = area.column_span;
+        while !self
+            .occupancy
+            .is_free(self.row..(self.row + row_span), column..(column + column_span))
+        {
+            self.row += 1;
+        }
+        self.column = column;
+        self.row..(self.row + row_span)
+    }
 
-        if self.flow == FlowDirection::Column {
-            mem::swap(&mut row, &mut column);
-            mem::swap(&mut row_span, &mut column_span);
+    /// Places an item with an automatic position.
+    fn place_auto(&mut self, row_span: usize, column_span: usize) -> (Range<usize>, Range<usize>) {
+        if self.dense {
+            self.row = 0;
+            self.column = 0;
         }
+        // items wider than the grid are placed in the first column
+        let last_column = self.row_len.saturating_sub(column_span);
+        loop {
+            let row = self.row;
+            let found = (self.column..=last_column).find(|&column| {
+                self.occupancy
+                    .is_free(row..(row + row_span), column..(column + column_span))
+            });
+            if let Some(column) = found {
+                self.column = column;
+                return (row..(row + row_span), column..(column + column_span));
+            }
+            self.row += 1;
+            self.column = 0;
+        }
+    }
 
-        let mut rows;
-        let mut columns;
+    /// Places items, and returns their row and column ranges in the grid.
+    ///
+    /// The ranges of null areas are empty.
+    fn place_items(&mut self, areas: &[DefiniteArea]) -> Vec<(Range<usize>, Range<usize>)> {
+        // flow-relative areas
+        let areas: Vec<_> = areas
+            .iter()
+            .map(|area| {
+                let mut area = *area;
+                if self.flow == FlowDirection::Column {
+                    mem::swap(&mut area.row, &mut area.column);
+                    mem::swap(&mut area.row_span, &mut area.column_span);
+                }
+                area
+            })
+            .collect();
+        let mut placed: Vec<Option<(Range<usize>, Range<usize>)>> = vec![None; areas.len()];
 
-        match (row, column) {
-            (Some(row), Some(column)) => {
-                rows = row..(row + row_span);
-                columns = column..(column + column_span);
+        // 1. items with a definite position
+        for (i, area) in areas.iter().enumerate() {
+            if area.is_null() {
+                placed[i] = Some((0..0, 0..0));
+                continue;
             }
-            (Some(row), None) => {
-                // TODO packing
-                rows = row..(row + row_span);
-                columns = 0..column_span;
+            if let (Some(row), Some(column)) = (area.row, area.column) {
+                let rows = row..(row + area.row_span);
+                let columns = column..(column + area.column_span);
+                self.occupancy.occupy(rows.clone(), columns.clone());
+                placed[i] = Some((rows, columns));
             }
-            (None, col) => {
-                if let Some(column) = col {
-                    self.align(column);
-                }
-                let (row, column) = self.next(row_span, column_span);
-                rows = row..(row + row_span);
-                columns = column..(column + column_span);
+        }
+
+        // 2. items locked to a row
+        for (i, area) in areas.iter().enumerate() {
+            if let (None, Some(row), None) = (&placed[i], area.row, area.column) {
+                let rows = row..(row + area.row_span);
+                let columns = self.place_in_row(row, area.row_span, area.column_span);
+                self.occupancy.occupy(rows.clone(), columns.clone());
+                placed[i] = Some((rows, columns));
             }
         }
 
-        if self.flow == FlowDirection::Column {
-            mem::swap(&mut rows, &mut columns);
+        // the remaining items can use the columns created by the items placed so far
+        self.row_len = self.row_len.max(self.occupancy.column_count());
+        for (i, area) in areas.iter().enumerate() {
+            if placed[i].is_none() {
+                self.row_len = self.row_len.max(area.column.unwrap_or(0) + area.column_span);
+            }
         }
 
-        (rows, columns)
+        // 3. the other items, in order
+        for (i, area) in areas.iter().enumerate() {
+            if placed[i].is_some() {
+                continue;
+            }
+            let (rows, columns) = match area.column {
+                Some(column) => (
+                    self.place_in_column(column, area.row_span, area.column_span),
+                    column..(column + area.column_span),
+                ),
+                None => self.place_auto(area.row_span, area.column_span),
+            };
+            self.occupancy.occupy(rows.clone(), columns.clone());
+            placed[i] = Some((rows, columns));
+        }
+
+        placed
+            .into_iter()
+            .map(|ranges| {
+                let (mut rows, mut columns) = ranges.unwrap();
+                if self.flow == FlowDirection::Column {
+                    mem::swap(&mut rows, &mut columns);
+                }
+                (rows, columns)
+            })
+            .collect()
     }
 }
 
 impl Grid {
     /// Position items inside the grid.
-    fn position_items(&self) -> (usize, usize) {
+    ///
+    /// `row_count` and `column_count` are the numbers of explicit rows and columns, once automatic repetitions have
+    /// been expanded.
+    fn position_items(&self, row_count: usize, column_count: usize) -> (usize, usize) {
         trace!(
             "=== [{:?}] positioning {} items ===",
             self.widget_id(),
             self.items.len()
         );
         trace!(
-            "{} template rows, {} template columns, autoflow: {:?}",
-            self.template.rows.sizes.len(),
-            self.template.columns.sizes.len(),
-            self.auto_flow_dir
+            "{} template rows, {} template columns, autoflow: {:?} (dense: {})",
+            row_count,
+            column_count,
+            self.auto_flow_dir,
+            self.dense_auto_flow
         );
 
-        let mut final_row_count = self.template.rows.sizes.len();
-        let mut final_column_count = self.template.columns.sizes.len();
+        let mut final_row_count = row_count;
+        let mut final_column_count = column_count;
 
-        let mut flow_cursor = FlowCursor {
-            row: 0,
-            column: 0,
-            row_len: match self.auto_flow_dir {
-                FlowDirection::Row => self.template.columns.sizes.len(),
-                FlowDirection::Column => self.template.rows.sizes.len(),
-            },
-            flow: self.auto_flow_dir,
+        let row_len = match self.auto_flow_dir {
+            FlowDirection::Row => column_count,
+            FlowDirection::Column => row_count,
         };
+        let mut flow_cursor = FlowCursor::new(self.auto_flow_dir, row_len, self.dense_auto_flow);
+        let areas: Vec<_> = self.items.iter().map(|item| item.area).collect();
+        let placements = flow_cursor.place_items(&areas);
 
-        for item in self.items.iter() {
+        for (item, (row_range, column_range)) in self.items.iter().zip(placements) {
             if item.area.is_null() {
                 // this should not happen because we check for null areas when adding the item to
                 // the grid, but check it here as well for good measure
@@ -1201,19 +1773,17 @@ impl Grid {
                 continue;
             }
 
-            let (row_range, column_range) = flow_cursor.place(item.area);
             final_row_count = final_row_count.max(row_range.end);
             final_column_count = final_column_count.max(column_range.end);
 
             trace!(
-                "{:?}: rows {}..{} columns {}..{} (area = {:?}, cursor = {:?})",
+                "{:?}: rows {}..{} columns {}..{} (area = {:?})",
                 item.widget.widget_id(),
                 row_range.start,
                 row_range.end,
                 column_range.start,
                 column_range.end,
                 item.area,
-                flow_cursor
             );
 
             item.row_range.set((row_range.start, row_range.end));
@@ -1282,12 +1852,27 @@ impl Grid {
             // If automatic sizing is requested (for min or max), compute the items natural sizes (result of layout with unbounded boxconstraints)
             // Also, for rows (axis == TrackAxis::Row) with AlignItems::Baseline, compute the max baseline offset of all items in the track
             let track_size = get_track_size(i);
-            let auto_sized = track_size.min_size == TrackBreadth::Auto || track_size.max_size == TrackBreadth::Auto;
+            let auto_sized = track_size.min_size == TrackBreadth::Auto
+                || matches!(track_size.max_size, TrackBreadth::Auto | TrackBreadth::FitContent(_));
             let mut max_natural_size = 0.0f64;
+            // smallest size that the items of the track can take: items that report intrinsic widths can shrink
+            // down to their min-content width, the others to their natural size
+            let mut min_natural_size = 0.0f64;
 
             if auto_sized {
                 let mut natural_layouts = Vec::new();
                 for item in self.items_in_track(axis, i) {
+                    // when sizing columns, ask for the intrinsic widths first: it's cheaper than a speculative
+                    // layout under unbounded constraints (e.g. text is shaped once, at its final width)
+                    if axis == Axis::Column {
+                        if let Some(widths) = item.widget.intrinsic_widths(layout_ctx, env) {
+                            trace!("intrinsic widths={:?}", widths);
+                            min_natural_size = min_natural_size.max(widths.min);
+                            max_natural_size = max_natural_size.max(widths.max);
+                            continue;
+                        }
+                    }
+
                     // setup "unbounded" constraints, so that the child widget returns its "natural" size ...
                     let mut constraints = *parent_layout_constraints;
                     constraints.min.width = 0.0;
@@ -1326,6 +1911,7 @@ impl Grid {
                         // adjust the returned size with additional padding to account for baseline alignment
                         size += max_baseline - layout.padding_box_baseline().unwrap_or(0.0);
                     }
+                    min_natural_size = min_natural_size.max(size);
                     max_natural_size = max_natural_size.max(size);
                 }
 
@@ -1344,8 +1930,8 @@ impl Grid {
                         Axis::Column => min.compute(parent_layout_constraints, env),
                     };
                 }
-                TrackBreadth::Auto => {
-                    base_size[i] = max_natural_size;
+                TrackBreadth::Auto | TrackBreadth::FitContent(_) => {
+                    base_size[i] = min_natural_size;
                 }
                 TrackBreadth::Flex(_) => {}
             };
@@ -1359,9 +1945,13 @@ impl Grid {
                     };
                 }
                 TrackBreadth::Auto => {
-                    // same as min size constraint
+                    // the track grows up to the natural size of its items, if there's enough space
                     growth_limit[i] = max_natural_size;
                 }
+                TrackBreadth::FitContent(limit) => {
+                    // same, but not beyond the limit (the base size still wins if the items can't shrink)
+                    growth_limit[i] = max_natural_size.min(limit.compute(parent_layout_constraints, env));
+                }
                 TrackBreadth::Flex(_) => growth_limit[i] = f64::INFINITY,
             };
 
@@ -1427,6 +2017,44 @@ impl Grid {
         ComputeTrackSizeResult { layout, size: pos }
     }
 
+    /// Returns the inputs of the track sizing passes, or `None` if an item needs a relayout (its natural size may
+    /// have changed).
+    ///
+    /// Must be called after `position_items`.
+    fn track_sizing_key(
+        &self,
+        constraints: &LayoutParams,
+        env: &Environment,
+        row_tracks: &[TrackSize],
+        column_tracks: &[TrackSize],
+        row_gap: f64,
+        column_gap: f64,
+    ) -> Option<TrackSizingKey> {
+        let items = self
+            .items
+            .iter()
+            .map(|item| {
+                Some(TrackSizingItem {
+                    widget: Arc::downgrade(&item.widget),
+                    layout_revision: item.widget.layout_revision()?,
+                    row_range: item.row_range.get(),
+                    column_range: item.column_range.get(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(TrackSizingKey {
+            constraints: *constraints,
+            font_size: env.get(&theme::FONT_SIZE).unwrap_or(16.0),
+            row_tracks: row_tracks.to_vec(),
+            column_tracks: column_tracks.to_vec(),
+            implicit_row_size: self.implicit_row_size,
+            implicit_column_size: self.implicit_column_size,
+            row_gap,
+            column_gap,
+            items,
+        })
+    }
+
     /// Invalidates the cached child widget filter.
     fn invalidate_child_filter(&self) {
         self.cached_child_filter.set(None);
@@ -1454,53 +2082,89 @@ impl Widget for Grid {
         // TODO the actual direction of rows and columns depends on the writing mode
         // When (or if) we support other writing modes, rewrite this. Layout is complicated!
 
-        // first, place items in the grid (i.e. resolve their grid areas into "definite areas")
-        let (row_count, column_count) = self.position_items();
-
         // resolve styles
         let column_gap = self.style.column_gap.compute(constraints, env);
         let row_gap = self.style.row_gap.compute(constraints, env);
+
+        // expand `repeat(auto-fill, ...)` tracks, then place items in the grid (i.e. resolve their grid areas into
+        // "definite areas")
+        let column_tracks = self
+            .template
+            .columns
+            .expand(constraints.max.width, column_gap, constraints, env);
+        let row_tracks = self
+            .template
+            .rows
+            .expand(constraints.max.height, row_gap, constraints, env);
+        let (row_count, column_count) = self.position_items(row_tracks.len(), column_tracks.len());
         let row_background = self.style.row_background.compute_paint(env);
         let alternate_row_background = self.style.alternate_row_background.compute_paint(env);
         let row_gap_background = self.style.row_gap_background.compute_paint(env);
         let column_gap_background = self.style.column_gap_background.compute_paint(env);
 
-        // first measure the width of the columns
-        let ComputeTrackSizeResult {
-            layout: column_layout,
-            size: width,
-        } = self.compute_track_sizes(
-            ctx,
-            constraints,
-            env,
-            Axis::Column,
-            &self.template.columns.sizes[..],
-            column_count,
-            TrackSize::new(self.implicit_column_size),
-            constraints.max.width,
-            row_gap,
-            column_gap,
-            None,
-        );
-        // then measure the height of the rows, which may depend on the width of the columns
-        // Note: it may go the other way around (width of columns that depend on the height of the rows)
-        // but we choose to do it like this
-        let ComputeTrackSizeResult {
-            layout: row_layout,
-            size: height,
-        } = self.compute_track_sizes(
-            ctx,
-            constraints,
-            env,
-            Axis::Row,
-            &self.template.rows.sizes[..],
-            row_count,
-            TrackSize::new(self.implicit_row_size),
-            constraints.max.height,
-            row_gap,
-            column_gap,
-            Some(&column_layout[..]),
-        );
+        // the track sizing passes lay out the items speculatively, skip them if none of their inputs has changed
+        let key = self.track_sizing_key(constraints, env, &row_tracks, &column_tracks, row_gap, column_gap);
+        let cached = key.as_ref().and_then(|key| self.track_sizing.lock().get(key));
+        let TrackSizes {
+            row_layout,
+            column_layout,
+            width,
+            height,
+        } = if let Some(sizes) = cached {
+            sizes
+        } else {
+            trace!("grid: recomputing track sizes");
+            // first measure the width of the columns
+            let ComputeTrackSizeResult {
+                layout: column_layout,
+                size: width,
+            } = self.compute_track_sizes(
+                ctx,
+                constraints,
+                env,
+                Axis::Column,
+                &column_tracks[..],
+                column_count,
+                TrackSize::new(self.implicit_column_size),
+                constraints.max.width,
+                row_gap,
+                column_gap,
+                None,
+            );
+            // then measure the height of the rows, which may depend on the width of the columns
+            // Note: it may go the other way around (width of columns that depend on the height of the rows)
+            // but we choose to do it like this
+            let ComputeTrackSizeResult {
+                layout: row_layout,
+                size: height,
+            } = self.compute_track_sizes(
+                ctx,
+                constraints,
+                env,
+                Axis::Row,
+                &row_tracks[..],
+                row_count,
+                TrackSize::new(self.implicit_row_size),
+                constraints.max.height,
+                row_gap,
+                column_gap,
+                Some(&column_layout[..]),
+            );
+
+            let sizes = TrackSizes {
+                row_layout,
+                column_layout,
+                width,
+                height,
+            };
+            let mut track_sizing = self.track_sizing.lock();
+            match key {
+                Some(key) => track_sizing.insert(key, sizes.clone()),
+                // an item will be laid out again, so the previous results are stale
+                None => track_sizing.entries.clear(),
+            }
+            sizes
+        };
 
         trace!("final row layout {:?}", row_layout);
         trace!("final column layout {:?}", column_layout);
@@ -1615,7 +2279,7 @@ impl Widget for Grid {
 
         // run the events through the items in reverse order
         // in order to give priority to topmost items
-        for item in self.items.iter().rev() {
+        for item in self.items_in_paint_order().rev() {
             item.widget.route_event(ctx, event, env);
         }
 
@@ -1674,8 +2338,8 @@ impl Widget for Grid {
             }
         }
 
-        // draw elements
-        for item in self.items.iter() {
+        // draw elements, topmost last
+        for item in self.items_in_paint_order() {
             item.widget.paint(ctx);
         }
 
@@ -1707,3 +2371,213 @@ impl Widget for Grid {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auto(row_span: usize, column_span: usize) -> DefiniteArea {
+        DefiniteArea {
+            row: None,
+            column: None,
+            row_span,
+            column_span,
+        }
+    }
+
+    fn at(row: Option<usize>, column: Option<usize>) -> DefiniteArea {
+        DefiniteArea {
+            row,
+            column,
+            row_span: 1,
+            column_span: 1,
+        }
+    }
+
+    fn place(flow: FlowDirection, row_len: usize, dense: bool, areas: &[DefiniteArea]) -> Vec<(usize, usize)> {
+        FlowCursor::new(flow, row_len, dense)
+            .place_items(areas)
+            .into_iter()
+            .map(|(rows, columns)| (rows.start, columns.start))
+            .collect()
+    }
+
+    #[test]
+    fn sparse_auto_placement() {
+        // the wide item doesn't fit after the first one: it goes to the next row, leaving a hole
+        let areas = [auto(1, 1), auto(1, 3), auto(1, 1), auto(1, 1)];
+        assert_eq!(
+            place(FlowDirection::Row, 3, false, &areas),
+            [(0, 0), (1, 0), (2, 0), (2, 1)]
+        );
+        // explicit items are placed first, auto-placed items go around them
+        let areas = [auto(1, 1), at(Some(0), Some(1)), auto(1, 1), auto(1, 1)];
+        assert_eq!(
+            place(FlowDirection::Row, 3, false, &areas),
+            [(0, 0), (0, 1), (0, 2), (1, 0)]
+        );
+    }
+
+    #[test]
+    fn dense_auto_placement() {
+        // the items after the wide one fill the hole in the first row
+        let areas = [auto(1, 1), auto(1, 3), auto(1, 1), auto(1, 1)];
+        assert_eq!(
+            place(FlowDirection::Row, 3, true, &areas),
+            [(0, 0), (1, 0), (0, 1), (0, 2)]
+        );
+        // an item spanning two rows leaves a hole below the first item in sparse mode
+        let areas = [auto(1, 1), auto(2, 1), auto(1, 2), auto(1, 1)];
+        assert_eq!(
+            place(FlowDirection::Row, 2, false, &areas),
+            [(0, 0), (0, 1), (2, 0), (3, 0)]
+        );
+        assert_eq!(
+            place(FlowDirection::Row, 2, true, &areas),
+            [(0, 0), (0, 1), (2, 0), (1, 0)]
+        );
+    }
+
+    #[test]
+    fn partial_placement() {
+        // items with a definite row are placed in that row, after the explicit items
+        let areas = [at(Some(1), None), at(Some(1), Some(0)), at(Some(1), None), auto(1, 1)];
+        assert_eq!(
+            place(FlowDirection::Row, 3, false, &areas),
+            [(1, 1), (1, 0), (1, 2), (0, 0)]
+        );
+
+        // items with a definite column move down rows; in sparse mode, the cursor doesn't go back
+        let areas = [auto(1, 1), auto(1, 1), at(None, Some(0)), auto(1, 1)];
+        assert_eq!(
+            place(FlowDirection::Row, 2, false, &areas),
+            [(0, 0), (0, 1), (1, 0), (1, 1)]
+        );
+        let areas = [at(Some(0), Some(0)), at(None, Some(1)), at(None, Some(0)), auto(1, 1)];
+        assert_eq!(
+            place(FlowDirection::Row, 2, false, &areas),
+            [(0, 0), (0, 1), (1, 0), (1, 1)]
+        );
+
+        // in dense mode, an item with a definite column takes the first free row in that column
+        let areas = [
+            at(Some(2), Some(0)),
+            at(None, Some(0)),
+            at(None, Some(1)),
+            at(None, Some(0)),
+        ];
+        assert_eq!(
+            place(FlowDirection::Row, 2, true, &areas),
+            [(2, 0), (0, 0), (0, 1), (1, 0)]
+        );
+    }
+
+    #[test]
+    fn track_functions() {
+        let template =
+            GridTemplate::try_from("repeat(2, [a] 100px 1fr) / minmax(50px, 1fr) fit-content(200px)").unwrap();
+        assert_eq!(template.rows.sizes.len(), 4);
+        assert_eq!(template.rows.sizes[2], TrackSize::new(Length::Dip(100.0)));
+        assert_eq!(template.rows.line_names, [(0, "a".to_string()), (2, "a".to_string())]);
+        assert_eq!(
+            template.columns.sizes,
+            [
+                TrackSize::minmax(Length::Dip(50.0), TrackBreadth::Flex(1.0)),
+                TrackSize::fit_content(Length::Dip(200.0))
+            ]
+        );
+        // flexible minimums and automatic repetitions of tracks without a fixed size are invalid
+        assert!(GridTemplate::try_from("minmax(1fr, 100px) / auto").is_err());
+        assert!(GridTemplate::try_from("repeat(auto-fill, auto) / auto").is_err());
+    }
+
+    #[test]
+    fn auto_fill() {
+        let template = GridTemplate::try_from("auto / 50px repeat(auto-fill, minmax(100px, 1fr))").unwrap();
+        assert_eq!(template.columns.sizes.len(), 2);
+        assert_eq!(template.columns.auto_repeat, Some(1..2));
+
+        let constraints = LayoutParams::default();
+        let env = Environment::new();
+        // three repetitions take 50 + 3 * 100 + 3 gaps of 10 = 380
+        assert_eq!(template.columns.expand(380.0, 10.0, &constraints, &env).len(), 4);
+        assert_eq!(template.columns.expand(379.0, 10.0, &constraints, &env).len(), 3);
+        // at least one repetition
+        assert_eq!(template.columns.expand(0.0, 10.0, &constraints, &env).len(), 2);
+        assert_eq!(
+            template.columns.expand(f64::INFINITY, 10.0, &constraints, &env).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn column_flow() {
+        // items fill columns first; an item with a definite row goes to the next column where it fits
+        let areas = [auto(1, 1), auto(1, 1), auto(1, 1), at(Some(0), None)];
+        assert_eq!(
+            place(FlowDirection::Column, 2, false, &areas),
+            [(0, 0), (1, 0), (0, 1), (0, 2)]
+        );
+    }
+
+    #[test]
+    fn track_sizing_cache() {
+        let key = |max_width: f64| TrackSizingKey {
+            constraints: LayoutParams {
+                max: Size::new(max_width, f64::INFINITY),
+                ..Default::default()
+            },
+            font_size: 16.0,
+            row_tracks: vec![],
+            column_tracks: vec![],
+            implicit_row_size: TrackBreadth::Auto,
+            implicit_column_size: TrackBreadth::Auto,
+            row_gap: 0.0,
+            column_gap: 0.0,
+            items: vec![],
+        };
+        let sizes = |width: f64| TrackSizes {
+            row_layout: vec![],
+            column_layout: vec![],
+            width,
+            height: 0.0,
+        };
+
+        let mut cache = TrackSizingCache::default();
+        for i in 0..TRACK_SIZING_CACHE_ENTRIES {
+            cache.insert(key(i as f64), sizes(i as f64));
+        }
+        // the least recently used entry is evicted
+        assert_eq!(cache.get(&key(0.0)).map(|sizes| sizes.width), Some(0.0));
+        cache.insert(key(100.0), sizes(100.0));
+        assert!(cache.get(&key(1.0)).is_none());
+        assert_eq!(cache.get(&key(0.0)).map(|sizes| sizes.width), Some(0.0));
+        assert_eq!(cache.get(&key(100.0)).map(|sizes| sizes.width), Some(100.0));
+    }
+
+    #[test]
+    fn z_index_order() {
+        use crate::{
+            cache::{noop_waker, Cache},
+            widget::Null,
+        };
+
+        let mut cache = Cache::new(noop_waker());
+        let grid = cache.recompose(&Environment::new(), || {
+            let mut grid = Grid::row(TrackBreadth::Auto);
+            grid.insert(Null.z_index(5));
+            grid.insert(Null);
+            grid.insert(Null.z_index(-1));
+            // re-wrapped pods keep their z-index
+            grid.insert(Null.z_index(2).arc_dyn_pod());
+            grid.insert(Null.z_index(3).padding(4.0));
+            grid
+        });
+
+        let paint_order: Vec<i32> = grid.items_in_paint_order().map(GridItem::z_index).collect();
+        assert_eq!(paint_order, [-1, 0, 2, 3, 5]);
+        // events are delivered to the topmost items first
+        let event_order: Vec<i32> = grid.items_in_paint_order().rev().map(GridItem::z_index).collect();
+        assert_eq!(event_order, [5, 3, 2, 0, -1]);
+    }
+}
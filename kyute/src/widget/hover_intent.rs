@@ -0,0 +1,197 @@
+//! Delayed hover detection.
+use crate::{
+    cache,
+    event::PointerEventKind,
+    widget::{prelude::*, press_and_hold::touch_event},
+    State,
+};
+use parking_lot::Mutex;
+use std::{sync::Arc, time::Duration};
+
+/// Default time the pointer must stay over the widget before hover intent is detected.
+const DEFAULT_DELAY: Duration = Duration::from_millis(400);
+/// Default distance (in DIPs) that the pointer can move without restarting the delay.
+const DEFAULT_TOLERANCE: f64 = 4.0;
+
+#[derive(Default)]
+struct Tracking {
+    /// Position of the pointer (in window coordinates) when the delay was last (re)started,
+    /// `None` if the pointer is not over the widget.
+    anchor: Option<Point>,
+    /// Incremented each time the delay is restarted, to discard stale timers.
+    generation: u64,
+    /// Whether hover intent has been detected, and the pointer hasn't left the widget since.
+    active: bool,
+}
+
+/// Detects when the pointer stays over a widget for some time without moving significantly ("hover intent").
+///
+/// Unlike `Clickable::pointer_entered`, hover intent isn't triggered when the pointer just passes over
+/// the widget. Use this to trigger expensive hover previews (thumbnails, prefetching, tooltips...).
+///
+/// Fingers on a touch screen don't hover: for them, hover intent is detected when a finger stays pressed over the widget
+/// without moving, and ends when it is lifted.
+pub struct HoverIntent<Inner> {
+    inner: Inner,
+    delay: Duration,
+    tolerance: f64,
+    tracking: Arc<Mutex<Tracking>>,
+    /// Set to `Some(true)` when hover intent is detected, and to `Some(false)` when the pointer leaves
+    /// the widget afterwards. Consumed during composition.
+    transition: State<Option<bool>>,
+    /// Transition consumed during this composition.
+    changed: Option<bool>,
+}
+
+impl<Inner: Widget + 'static> HoverIntent<Inner> {
+    /// Creates a new `HoverIntent` with the default delay (400ms) and movement tolerance (4 DIPs).
+    #[composable]
+    pub fn new(inner: Inner) -> HoverIntent<Inner> {
+        let transition = cache::state(|| None);
+        let changed = transition.take_without_invalidation();
+        HoverIntent {
+            inner,
+            delay: DEFAULT_DELAY,
+            tolerance: DEFAULT_TOLERANCE,
+            tracking: cache::once(|| Arc::new(Mutex::new(Tracking::default()))),
+            transition,
+            changed,
+        }
+    }
+
+    /// Sets the time the pointer must stay over the widget before hover intent is detected.
+    #[must_use]
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the distance that the pointer can move over the widget without restarting the delay.
+    #[must_use]
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Returns whether hover intent has been detected.
+    pub fn hover_intent(&self) -> bool {
+        self.changed == Some(true)
+    }
+
+    /// Returns whether the pointer left the widget after hover intent was detected.
+    pub fn hover_intent_ended(&self) -> bool {
+        self.changed == Some(false)
+    }
+
+    #[must_use]
+    pub fn on_hover_intent(self, f: impl FnOnce()) -> Self {
+        if self.hover_intent() {
+            f();
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn on_hover_intent_ended(self, f: impl FnOnce()) -> Self {
+        if self.hover_intent_ended() {
+            f();
+        }
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+
+    /// (Re)starts the delay with the pointer at the specified position.
+    fn start(&self, position: Point) {
+        let generation = {
+            let mut tracking = self.tracking.lock();
+            tracking.anchor = Some(position);
+            tracking.generation += 1;
+            tracking.generation
+        };
+
+        let tracking = self.tracking.clone();
+        let transition = self.transition.clone();
+        let delay = self.delay;
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let mut tracking = tracking.lock();
+            if tracking.generation == generation && tracking.anchor.is_some() {
+                tracking.active = true;
+                transition.set(Some(true));
+            }
+        });
+    }
+
+    fn stop(&self) {
+        let mut tracking = self.tracking.lock();
+        tracking.anchor = None;
+        tracking.generation += 1;
+        if tracking.active {
+            tracking.active = false;
+            self.transition.set(Some(false));
+        }
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for HoverIntent<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.inner.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        // the finger may be held over a descendant that captured it
+        if let Some(p) = touch_event(event) {
+            match p.kind {
+                PointerEventKind::PointerDown => self.start(p.window_position),
+                PointerEventKind::PointerMove => {
+                    let anchor = self.tracking.lock().anchor;
+                    if matches!(anchor, Some(anchor) if (p.window_position - anchor).length() > self.tolerance) {
+                        // the finger is dragging
+                        self.stop()
+                    }
+                }
+                PointerEventKind::PointerUp | PointerEventKind::PointerOut => self.stop(),
+                _ => {}
+            }
+        } else if let Event::Pointer(p) = event {
+            match p.kind {
+                PointerEventKind::PointerOver => self.start(p.window_position),
+                PointerEventKind::PointerMove => {
+                    let (anchor, active) = {
+                        let tracking = self.tracking.lock();
+                        (tracking.anchor, tracking.active)
+                    };
+                    match anchor {
+                        // the pointer moved too much, wait again
+                        Some(anchor) if !active && (p.window_position - anchor).length() > self.tolerance => {
+                            self.start(p.window_position)
+                        }
+                        // we may have missed PointerOver
+                        None => self.start(p.window_position),
+                        _ => {}
+                    }
+                }
+                PointerEventKind::PointerOut => self.stop(),
+                _ => {}
+            }
+        }
+        self.inner.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+}
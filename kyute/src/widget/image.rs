@@ -2,19 +2,41 @@ use crate::{
     cache,
     core::DebugNode,
     drawing,
-    drawing::ToSkia,
+    drawing::{ImageCache, ToSkia, IMAGE_CACHE},
     util::fs_watch::watch_path,
     widget::{prelude::*, Null},
-    AssetLoader, SizeI,
+    SizeI,
 };
 use kyute_common::Color;
+use kyute_shell::clipboard;
 use skia_safe as sk;
-use std::task::Poll;
-use tracing::trace;
+use std::{cell::RefCell, task::Poll};
+
+/// Versions of an image at different resolutions (see `Image::from_uri_variants`).
+#[derive(Clone)]
+struct Variants {
+    /// Scale factor and URI of each version, by increasing scale factor.
+    uris: Vec<(f64, String)>,
+    /// Index of the version loaded for the current scale factor, and its image.
+    loaded: RefCell<Option<(usize, drawing::Image)>>,
+}
+
+impl Variants {
+    /// Returns the index of the version to show at the specified scale factor: the smallest one with a scale
+    /// factor greater or equal, or the largest one.
+    fn select(&self, scale_factor: f64) -> usize {
+        self.uris
+            .iter()
+            .position(|&(scale, _)| scale >= scale_factor)
+            .unwrap_or(self.uris.len() - 1)
+    }
+}
 
 #[derive(Clone)]
 enum ImageContents<Placeholder> {
     Image { uri: Option<String>, image: drawing::Image },
+    /// Resolved during layout, when the scale factor is known.
+    Variants(Variants),
     Placeholder(Placeholder),
 }
 
@@ -23,9 +45,9 @@ impl<Placeholder: Widget> ImageContents<Placeholder> {
         ImageContents::Image { uri, image }
     }
 
-    /*pub fn placeholder(placeholder: Placeholder) -> ImageContents<Placeholder> {
+    pub fn placeholder(placeholder: Placeholder) -> ImageContents<Placeholder> {
         ImageContents::Placeholder(placeholder)
-    }*/
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -36,11 +58,20 @@ pub enum Scaling {
     Cover,
 }
 
+/// Returns the image cache in the current environment.
+fn image_cache() -> ImageCache {
+    cache::environment()
+        .get(&IMAGE_CACHE)
+        .expect("could not find an ImageCache instance in the current environment")
+}
+
 #[derive(Clone)]
 pub struct Image<Placeholder> {
     contents: ImageContents<Placeholder>,
     scaling: Scaling,
     colorize: Option<Color>,
+    /// Cache that the image was loaded from, used to get GPU textures for the image when painting.
+    image_cache: Option<ImageCache>,
 }
 
 impl<Placeholder> Image<Placeholder> {
@@ -48,24 +79,114 @@ impl<Placeholder> Image<Placeholder> {
         self.colorize = Some(color);
         self
     }
+
+    /// Returns whether the image is loaded (i.e. the placeholder isn't shown).
+    pub fn is_loaded(&self) -> bool {
+        matches!(self.contents, ImageContents::Image { .. } | ImageContents::Variants(_))
+    }
+
+    /// Returns the size of the image in DIPs given its natural size, according to the scaling mode.
+    fn scaled_size(&self, size: Size, constraints: &LayoutParams) -> Size {
+        // aspect ratio of the loaded image
+        let image_aspect_ratio = size.width / size.height;
+        // aspect ratio of the available space, may be infinite or zero
+        // FIXME: NaN if constraints both infinite
+        let available_space_aspect_ratio = constraints.max.width / constraints.max.height;
+
+        let image_wider_than_available_space = image_aspect_ratio > available_space_aspect_ratio;
+
+        match (self.scaling, image_wider_than_available_space) {
+            (Scaling::Contain, false) | (Scaling::Cover, true) => {
+                if constraints.max.height.is_finite() {
+                    Size::new(constraints.max.height * image_aspect_ratio, constraints.max.height)
+                } else {
+                    size
+                }
+            }
+            (Scaling::Contain, true) | (Scaling::Cover, false) => {
+                if constraints.max.width.is_finite() {
+                    Size::new(constraints.max.width, constraints.max.width / image_aspect_ratio)
+                } else {
+                    size
+                }
+            }
+            (Scaling::None, _) => size,
+        }
+    }
 }
 
 impl Image<Null> {
     /// Creates an image widget that displays the image from a specified asset URI.
     #[composable]
     pub fn from_uri(uri: &str, scaling: Scaling) -> Image<Null> {
-        let image: drawing::Image = AssetLoader::instance().load(uri).expect("failed to load image");
+        let image_cache = image_cache();
+        let image = image_cache.load(uri).expect("failed to load image");
         Image {
             contents: ImageContents::new(uri.to_string().into(), image),
             scaling,
             colorize: None,
+            image_cache: Some(image_cache),
+        }
+    }
+
+    /// Creates an image widget that displays an image that isn't loaded from an asset URI (e.g. generated, or
+    /// pasted from the clipboard).
+    pub fn from_image(image: sk::Image, scaling: Scaling) -> Image<Null> {
+        Image {
+            contents: ImageContents::new(None, image.into()),
+            scaling,
+            colorize: None,
+            image_cache: None,
+        }
+    }
+
+    /// Creates an image widget that displays the image on the clipboard (e.g. a screenshot).
+    ///
+    /// Returns `None` if the clipboard doesn't contain an image, or if it was rejected by the data transfer policy
+    /// (see `shell::DataTransferPolicy`).
+    pub fn from_clipboard(scaling: Scaling) -> Option<Image<Null>> {
+        Some(Image::from_image(clipboard::image()?, scaling))
+    }
+
+    /// Creates an image widget that displays the version of an image that best matches the resolution of the
+    /// screen.
+    ///
+    /// `variants` contains the URIs of versions of the same image, with the scale factor that they are intended for
+    /// (e.g. `[(1.0, "icon.png"), (2.0, "icon@2x.png")]`). During layout, the widget picks the smallest version whose
+    /// scale factor is greater or equal to the scale factor of the window (or the largest version), and loads it.
+    /// The version is selected again when the window moves to a monitor with a different scale factor.
+    ///
+    /// The size of the image in DIPs is the size of the selected version divided by its scale factor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `variants` is empty.
+    #[composable]
+    pub fn from_uri_variants(variants: &[(f64, &str)], scaling: Scaling) -> Image<Null> {
+        assert!(!variants.is_empty(), "no image variants specified");
+        let mut uris: Vec<_> = variants.iter().map(|&(scale, uri)| (scale, uri.to_string())).collect();
+        uris.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Image {
+            contents: ImageContents::Variants(Variants {
+                uris,
+                loaded: RefCell::new(None),
+            }),
+            scaling,
+            colorize: None,
+            image_cache: Some(image_cache()),
         }
     }
 
     /// Returns the size of the image in pixels.
+    ///
+    /// For images created with `from_uri_variants`, this is the size of the version selected during the last layout.
     pub fn pixel_size(&self) -> SizeI {
         match self.contents {
             ImageContents::Image { ref image, .. } => image.size(),
+            ImageContents::Variants(ref variants) => match *variants.loaded.borrow() {
+                Some((_, ref image)) => image.size(),
+                None => SizeI::new(0, 0),
+            },
             ImageContents::Placeholder(_) => {
                 // FIXME: cannot know the size of a placeholder before layout; use LayoutInspector? ensure fixed size?
                 SizeI::new(0, 0)
@@ -75,55 +196,49 @@ impl Image<Null> {
 
     /// Creates an image widget that loads the image at the specified URI asynchronously,
     /// and displays the image once it is loaded.
+    ///
+    /// The image is decoded on a worker thread and cached by URI. Until it is loaded, the placeholder is shown
+    /// (nothing by default, see `placeholder`). The image is reloaded when the file changes.
     #[composable]
     pub fn from_uri_async(uri: &str, scaling: Scaling) -> Image<Null> {
-        let image_future = AssetLoader::instance().load_async::<drawing::Image>(uri);
+        let image_cache = image_cache();
         let reload = watch_path(uri);
-        let uri_owned = uri.to_owned();
-
-        let image = cache::run_async(
-            async move {
-                let image_result = image_future.await;
-                match image_result {
-                    Ok(image) => {
-                        trace!("image `{}` successfully loaded", uri_owned);
-                        Some(image)
-                    }
-                    Err(err) => {
-                        trace!("failed to load image `{}`: {}", uri_owned, err);
-                        None
-                    }
-                }
-            },
-            reload,
-        );
+        if reload {
+            image_cache.invalidate(uri);
+        }
+        // the widget is recomposed when the task completes
+        let image = cache::run_async(image_cache.load_async(uri), reload);
 
         match image {
             Poll::Ready(Some(image)) => Image {
                 contents: ImageContents::new(Some(uri.to_string()), image),
                 scaling,
                 colorize: None,
+                image_cache: Some(image_cache),
             },
             _ => Image {
-                contents: ImageContents::Placeholder(Null),
+                contents: ImageContents::placeholder(Null),
                 scaling,
                 colorize: None,
+                image_cache: Some(image_cache),
             },
         }
     }
 
-    /*pub fn placeholder<Placeholder: Widget>(self, placeholder: Placeholder) -> Image<Placeholder> {
-        match self.contents.into_inner() {
-            ImageContents::Image(image) => Image {
-                contents: ImageContents::Image(image),
-                scaling: Scaling::Cover,
-            },
-            ImageContents::Placeholder(_) => Image {
-                contents: ImageContents::Placeholder(placeholder),
-                scaling: Scaling::Cover,
-            },
+    /// Sets the widget shown while the image is loading, or if it failed to load.
+    pub fn placeholder<Placeholder: Widget>(self, placeholder: Placeholder) -> Image<Placeholder> {
+        let contents = match self.contents {
+            ImageContents::Image { uri, image } => ImageContents::Image { uri, image },
+            ImageContents::Variants(variants) => ImageContents::Variants(variants),
+            ImageContents::Placeholder(_) => ImageContents::placeholder(placeholder),
+        };
+        Image {
+            contents,
+            scaling: self.scaling,
+            colorize: self.colorize,
+            image_cache: self.image_cache,
         }
-    }*/
+    }
 }
 
 impl<Placeholder: Widget> Widget for Image<Placeholder> {
@@ -136,47 +251,42 @@ impl<Placeholder: Widget> Widget for Image<Placeholder> {
             ImageContents::Image { ref image, .. } => {
                 let size_i = image.size();
                 let size = Size::new(size_i.width as f64, size_i.height as f64) / ctx.scale_factor;
-
-                // layout behavior:
-                // -
-
-                // aspect ratio of the loaded image
-                let image_aspect_ratio = size.width / size.height;
-                // aspect ratio of the available space, may be infinite or zero
-                // FIXME: NaN if constraints both infinite
-                let available_space_aspect_ratio = constraints.max.width / constraints.max.height;
-
-                let image_wider_than_available_space = image_aspect_ratio > available_space_aspect_ratio;
-
-                let scaled_size = match (self.scaling, image_wider_than_available_space) {
-                    (Scaling::Contain, false) | (Scaling::Cover, true) => {
-                        if constraints.max.height.is_finite() {
-                            Size::new(constraints.max.height * image_aspect_ratio, constraints.max.height)
-                        } else {
-                            size
-                        }
-                    }
-                    (Scaling::Contain, true) | (Scaling::Cover, false) => {
-                        if constraints.max.width.is_finite() {
-                            Size::new(constraints.max.width, constraints.max.width / image_aspect_ratio)
-                        } else {
-                            size
-                        }
-                    }
-                    (Scaling::None, _) => size,
-                };
-
-                Geometry::new(scaled_size)
+                Geometry::new(self.scaled_size(size, constraints))
+            }
+            ImageContents::Variants(ref variants) => {
+                let index = variants.select(constraints.scale_factor);
+                let mut loaded = variants.loaded.borrow_mut();
+                if !matches!(*loaded, Some((loaded_index, _)) if loaded_index == index) {
+                    let image_cache = self.image_cache.as_ref().unwrap();
+                    let image = image_cache.load(&variants.uris[index].1).expect("failed to load image");
+                    *loaded = Some((index, image));
+                }
+                let (_, ref image) = *loaded.as_ref().unwrap();
+                let scale = variants.uris[index].0;
+                let size_i = image.size();
+                let size = Size::new(size_i.width as f64, size_i.height as f64) / scale;
+                Geometry::new(self.scaled_size(size, constraints))
             }
             ImageContents::Placeholder(ref placeholder) => placeholder.layout(ctx, constraints, env),
         }
     }
 
-    fn event(&self, _ctx: &mut EventCtx, _event: &mut Event, _env: &Environment) {}
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let ImageContents::Placeholder(ref placeholder) = self.contents {
+            placeholder.route_event(ctx, event, env);
+        }
+    }
 
     fn paint(&self, ctx: &mut PaintCtx) {
         match self.contents {
-            ImageContents::Image { ref image, .. } => {
+            ImageContents::Image { ref image, ref uri } => {
+                // draw from the GPU texture if possible, to avoid uploading the image on every repaint
+                let texture = match (uri, &self.image_cache) {
+                    (Some(uri), Some(image_cache)) => image_cache.texture(uri, ctx.skia_direct_context),
+                    _ => None,
+                };
+                let image = texture.as_ref().unwrap_or(image);
+
                 let mut paint;
                 let paint = if let Some(color) = self.colorize {
                     paint = sk::Paint::default();
@@ -193,6 +303,31 @@ impl<Placeholder: Widget> Widget for Image<Placeholder> {
                     .canvas()
                     .draw_image(image.to_skia(), Point::origin().to_skia(), paint);
             }
+            ImageContents::Variants(ref variants) => {
+                let loaded = variants.loaded.borrow();
+                let (index, ref image) = *loaded.as_ref().expect("paint called before layout");
+                let texture = self
+                    .image_cache
+                    .as_ref()
+                    .and_then(|image_cache| image_cache.texture(&variants.uris[index].1, ctx.skia_direct_context));
+                let image = texture.as_ref().unwrap_or(image);
+
+                let mut paint = sk::Paint::default();
+                if let Some(color) = self.colorize {
+                    paint.set_color_filter(sk::color_filters::blend(
+                        color.to_skia().to_color(),
+                        sk::BlendMode::SrcIn,
+                    ));
+                }
+                // the version is drawn at its size in DIPs: it's not resampled if the scale factor matches
+                ctx.surface.canvas().draw_image_rect_with_sampling_options(
+                    image.to_skia(),
+                    None,
+                    ctx.bounds.to_skia(),
+                    sk::SamplingOptions::new(sk::FilterMode::Linear, sk::MipmapMode::None),
+                    &paint,
+                );
+            }
             ImageContents::Placeholder(ref placeholder) => placeholder.paint(ctx),
         }
     }
@@ -206,6 +341,16 @@ impl<Placeholder: Widget> Widget for Image<Placeholder> {
                 }
                 msg
             }
+            ImageContents::Variants(ref variants) => match *variants.loaded.borrow() {
+                Some((index, ref image)) => format!(
+                    "{}px x {}px image ({} @ {}x)",
+                    image.size().width,
+                    image.size().height,
+                    variants.uris[index].1,
+                    variants.uris[index].0
+                ),
+                None => format!("image ({} variants)", variants.uris.len()),
+            },
             ImageContents::Placeholder(_) => "placeholder".to_string(),
         })
     }
@@ -0,0 +1,123 @@
+//! Input zones: control how a subtree takes part in pointer hit-testing.
+use crate::{event::InternalEvent, widget::prelude::*};
+use std::cell::Cell;
+
+/// Determines whether a subtree can be the target of pointer events.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PointerEvents {
+    /// Pointer events are delivered to the subtree as usual.
+    Auto,
+    /// Pointer and wheel events pass through the subtree, as if it wasn't there.
+    ///
+    /// Widgets in the subtree never become hot or hovered. Events targeting a specific widget
+    /// (e.g. `PointerOut`) are still delivered, so that widgets can clean up their hover states.
+    None,
+}
+
+impl Default for PointerEvents {
+    fn default() -> Self {
+        PointerEvents::Auto
+    }
+}
+
+/// Wraps a widget and controls how it participates in hit-testing.
+///
+/// An input zone can:
+/// - let pointer input pass through its contents (see `PointerEvents::None`);
+/// - have a hit-test priority: when the pointer is over a zone with a positive priority, pointer events
+///   are delivered exclusively to the contents of the zone with the highest priority, regardless of the
+///   order in which siblings are traversed. This is intended for HUDs and overlays that should capture input
+///   in some places without blocking the rest of the window.
+pub struct InputZone<W> {
+    id: WidgetId,
+    inner: W,
+    pointer_events: PointerEvents,
+    priority: i32,
+    bounds: Cell<Rect>,
+}
+
+impl<W: Widget + 'static> InputZone<W> {
+    #[composable]
+    pub fn new(inner: W) -> InputZone<W> {
+        InputZone {
+            id: WidgetId::here(),
+            inner,
+            pointer_events: PointerEvents::Auto,
+            priority: 0,
+            bounds: Cell::new(Rect::zero()),
+        }
+    }
+
+    /// Sets whether pointer events are delivered to the contents.
+    pub fn pointer_events(mut self, pointer_events: PointerEvents) -> Self {
+        self.pointer_events = pointer_events;
+        self
+    }
+
+    /// Sets the hit-test priority of the zone.
+    ///
+    /// Zones with a priority of zero or less don't capture input.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Widget + 'static> Widget for InputZone<W> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let geometry = self.inner.layout(ctx, constraints, env);
+        self.bounds.set(geometry.measurements.local_bounds());
+        geometry
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        let pass_through = self.pointer_events == PointerEvents::None;
+
+        match event {
+            Event::Pointer(p) if pass_through && p.target.is_none() => {
+                ctx.hit_test_pass = false;
+                return;
+            }
+            Event::Wheel(_) if pass_through => {
+                return;
+            }
+            Event::Internal(InternalEvent::CollectInputZones { zones }) => {
+                if pass_through {
+                    return;
+                }
+                if self.priority > 0 {
+                    let bounds = ctx.window_transform().outer_transformed_rect(&self.bounds.get());
+                    zones.push((bounds, self.priority, self.id));
+                }
+            }
+            _ => {}
+        }
+
+        self.inner.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new(format!(
+            "pointer-events: {:?}, priority: {}",
+            self.pointer_events, self.priority
+        ))
+    }
+}
@@ -0,0 +1,100 @@
+//! Jank monitor view.
+use crate::{
+    cache,
+    jank::{self, JankReport},
+    theme,
+    widget::{grid, prelude::*, Button, Grid, ScrollArea, Text},
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn report_item(report: &JankReport) -> Grid {
+    let mut item = Grid::column(grid::TrackBreadth::Flex(1.0));
+    item.insert(Text::new(report.summary()).color(theme::palette::RED_600));
+    for timing in report.slowest.iter() {
+        item.insert(Text::new(format!(
+            "    {:<6} {} {:?}: {:.2} ms self, {:.2} ms total, {}×",
+            timing.phase.to_string(),
+            timing.widget_name,
+            WidgetId::dbg_option(timing.widget_id),
+            millis(timing.self_time),
+            millis(timing.total_time),
+            timing.count
+        )));
+    }
+    item
+}
+
+/// Lists the frames that exceeded the frame budget, and the widgets that took the longest to lay out and paint in
+/// each of them (see `jank`).
+///
+/// The list is updated as soon as a new frame is reported. The "Export" button writes the reports to a JSON file in
+/// the temporary directory.
+///
+/// Jank detection must be enabled with `jank::set_frame_budget`.
+#[derive(Widget)]
+pub struct JankMonitor {
+    inner: Grid,
+}
+
+impl JankMonitor {
+    #[composable]
+    pub fn new() -> JankMonitor {
+        // incremented on each new report, to recompose the monitor
+        let generation = cache::state(|| 0usize);
+        cache::once(|| {
+            let generation = generation.clone();
+            jank::on_report(move |_| generation.set(generation.get() + 1))
+        });
+        generation.get();
+        #[state]
+        let mut status = String::new();
+
+        let reports = jank::reports();
+
+        let mut grid = Grid::with_template("auto 1fr / 1fr");
+
+        // toolbar
+        let mut toolbar = Grid::with_template("auto / auto auto 1fr");
+        toolbar.set_column_gap(4.dip());
+        let clear_button = Button::new("Clear").on_click(|| {
+            jank::clear_reports();
+            generation.set(generation.get() + 1);
+            status = String::new();
+        });
+        let export_button = Button::new("Export").on_click(|| {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_secs());
+            let path = std::env::temp_dir().join(format!("jank-{}.json", timestamp));
+            status = match jank::save_reports_json(&reports, &path) {
+                Ok(()) => format!("Exported {} report(s) to {}", reports.len(), path.display()),
+                Err(err) => format!("Export failed: {}", err),
+            };
+        });
+        let status_text = if !status.is_empty() {
+            status.clone()
+        } else if let Some(budget) = jank::frame_budget() {
+            format!("{} frame(s) over {:.1} ms", reports.len(), millis(budget))
+        } else {
+            "Jank detection is disabled".to_string()
+        };
+        toolbar.insert((
+            clear_button,
+            export_button,
+            Text::new(status_text).vertical_alignment(Alignment::CENTER),
+        ));
+        grid.insert(toolbar.padding(4.dip()));
+
+        // reports, most recent first
+        let mut list = Grid::column(grid::TrackBreadth::Flex(1.0));
+        list.set_row_gap(8.dip());
+        for (i, report) in reports.iter().enumerate().rev() {
+            cache::scoped(i, || list.insert(report_item(report)));
+        }
+        grid.insert(ScrollArea::new(list).fill());
+
+        JankMonitor { inner: grid }
+    }
+}
@@ -36,6 +36,10 @@ impl Widget for Label {
         self.text.layout(ctx, &constraints, env)
     }
 
+    fn intrinsic_widths(&self, ctx: &mut LayoutCtx, env: &Environment) -> Option<IntrinsicWidths> {
+        self.text.intrinsic_widths(ctx, env)
+    }
+
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
         self.text.route_event(ctx, event, env)
     }
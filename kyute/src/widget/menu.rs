@@ -1,7 +1,20 @@
-use crate::{composable, event::PointerButton, widget::prelude::*, Data, PointerEventKind, WidgetId};
-use std::cell::Cell;
+use crate::{
+    composable,
+    drawing::{Image, ToSkia},
+    event::PointerButton,
+    widget::{
+        prelude::*,
+        press_and_hold::{touch_event, PressAndHold},
+    },
+    Data, PointerEventKind, WidgetId,
+};
+use skia_safe as sk;
+use std::{cell::Cell, fmt, rc::Rc};
 
-pub use kyute_shell::Shortcut;
+pub use kyute_shell::{MenuItemState, Shortcut};
+
+/// Size in pixels of the icons of native menu items.
+const MENU_ICON_SIZE: i32 = 16;
 
 #[derive(Clone, Debug, Data)]
 pub struct Action {
@@ -51,11 +64,42 @@ impl Action {
     }
 }
 
+/// Custom rendering of a menu item (see `MenuItem::with_custom_draw`).
+#[derive(Clone)]
+pub struct MenuItemDraw {
+    size: Size,
+    paint: Rc<dyn Fn(&mut sk::Canvas, Size, MenuItemState)>,
+}
+
+impl Data for MenuItemDraw {
+    fn same(&self, other: &Self) -> bool {
+        self.size == other.size && Rc::ptr_eq(&self.paint, &other.paint)
+    }
+}
+
+impl fmt::Debug for MenuItemDraw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MenuItemDraw")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Clone, Debug, Data)]
 pub enum MenuItem {
-    Action { text: String, action: Action },
+    Action {
+        text: String,
+        action: Action,
+        /// Icon displayed next to the text.
+        icon: Option<Image>,
+        /// Replaces the native rendering of the item.
+        custom_draw: Option<MenuItemDraw>,
+    },
     Separator,
-    Submenu { text: String, menu: Menu },
+    Submenu {
+        text: String,
+        menu: Menu,
+    },
 }
 
 impl MenuItem {
@@ -64,9 +108,43 @@ impl MenuItem {
         MenuItem::Action {
             text: text.into(),
             action,
+            icon: None,
+            custom_draw: None,
         }
     }
 
+    /// Sets the icon displayed next to the text of an action item.
+    ///
+    /// The image is scaled to the size of menu icons. Has no effect on separators and submenus.
+    pub fn with_icon(mut self, image: Image) -> MenuItem {
+        if let MenuItem::Action { ref mut icon, .. } = self {
+            *icon = Some(image);
+        }
+        self
+    }
+
+    /// Replaces the rendering of an action item by custom drawing (e.g. color swatches).
+    ///
+    /// `size` is the size of the item in DIPs. `paint` is called with a canvas scaled to DIPs, the size of the item,
+    /// and its state (highlighted, checked, disabled); the menu background is already drawn. The text of the item
+    /// is only used for accessibility. Has no effect on separators and submenus.
+    pub fn with_custom_draw(
+        mut self,
+        size: Size,
+        paint: impl Fn(&mut sk::Canvas, Size, MenuItemState) + 'static,
+    ) -> MenuItem {
+        if let MenuItem::Action {
+            ref mut custom_draw, ..
+        } = self
+        {
+            *custom_draw = Some(MenuItemDraw {
+                size,
+                paint: Rc::new(paint),
+            });
+        }
+        self
+    }
+
     /// Creates a new separator item.
     pub fn separator() -> MenuItem {
         MenuItem::Separator
@@ -102,6 +180,16 @@ impl Menu {
         Menu { items }
     }
 
+    /// Creates a menu outside of composition (e.g. a menu built in an event handler).
+    pub(crate) fn from_items(items: Vec<MenuItem>) -> Menu {
+        Menu { items }
+    }
+
+    /// Returns the items in this menu.
+    pub fn items(&self) -> &[MenuItem] {
+        &self.items
+    }
+
     pub(crate) fn to_shell_menu(&self, popup: bool) -> kyute_shell::Menu {
         let mut menu = if popup {
             kyute_shell::Menu::new_popup()
@@ -110,14 +198,25 @@ impl Menu {
         };
         for item in self.items.iter() {
             match item {
-                MenuItem::Action { action, text } => {
-                    menu.add_item(
-                        text,
-                        action.index.get() as usize,
-                        action.shortcut.as_ref(),
-                        false,
-                        false,
-                    );
+                MenuItem::Action {
+                    action,
+                    text,
+                    icon,
+                    custom_draw,
+                } => {
+                    let id = action.index.get() as usize;
+                    if let Some(custom_draw) = custom_draw {
+                        let draw = kyute_shell::MenuItemDraw {
+                            size: custom_draw.size,
+                            paint: custom_draw.paint.clone(),
+                        };
+                        menu.add_custom_item(draw, id, false, false);
+                    } else {
+                        menu.add_item(text, id, action.shortcut.as_ref(), false, false);
+                        if let Some(icon) = icon.as_ref().and_then(menu_icon) {
+                            menu.set_item_icon(id, &icon);
+                        }
+                    }
                 }
                 MenuItem::Separator => {
                     menu.add_separator();
@@ -185,11 +284,71 @@ impl Menu {
     }
 }
 
+/// Converts an image to a native menu icon.
+fn menu_icon(image: &Image) -> Option<kyute_shell::MenuIcon> {
+    let info = sk::ImageInfo::new(
+        (MENU_ICON_SIZE, MENU_ICON_SIZE),
+        sk::ColorType::BGRA8888,
+        sk::AlphaType::Premul,
+        None,
+    );
+    let mut surface = sk::Surface::new_raster(&info, None, None)?;
+    let canvas = surface.canvas();
+    canvas.clear(sk::Color::TRANSPARENT);
+    let dst = sk::Rect::from_wh(MENU_ICON_SIZE as f32, MENU_ICON_SIZE as f32);
+    canvas.draw_image_rect_with_sampling_options(
+        image.to_skia(),
+        None,
+        dst,
+        sk::SamplingOptions::new(sk::FilterMode::Linear, sk::MipmapMode::None),
+        &sk::Paint::default(),
+    );
+    let row_bytes = MENU_ICON_SIZE as usize * 4;
+    let mut pixels = vec![0u8; row_bytes * MENU_ICON_SIZE as usize];
+    if !surface.read_pixels(&info, &mut pixels, row_bytes, (0, 0)) {
+        return None;
+    }
+    Some(kyute_shell::MenuIcon {
+        width: MENU_ICON_SIZE as u32,
+        height: MENU_ICON_SIZE as u32,
+        pixels,
+    })
+}
+
+/// Splits the mnemonic marker from the text of a menu item.
+///
+/// As with native menus, the character following `&` is the mnemonic, and `&&` is a literal ampersand.
+/// Returns the text to display and the byte position of the mnemonic character in it.
+pub(crate) fn split_mnemonic(text: &str) -> (String, Option<usize>) {
+    let mut display = String::with_capacity(text.len());
+    let mut mnemonic = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '&' {
+            match chars.next() {
+                Some('&') => display.push('&'),
+                Some(next) => {
+                    if mnemonic.is_none() {
+                        mnemonic = Some(display.len());
+                    }
+                    display.push(next);
+                }
+                None => {}
+            }
+        } else {
+            display.push(c);
+        }
+    }
+    (display, mnemonic)
+}
+
+/// Shows a menu when the content is right-clicked, or pressed and held with a finger on a touch screen.
 #[derive(Clone)]
 pub struct ContextMenu<Content> {
     id: WidgetId,
     menu: Menu,
     content: Content,
+    press_and_hold: PressAndHold,
 }
 
 impl<Content> ContextMenu<Content> {
@@ -199,8 +358,15 @@ impl<Content> ContextMenu<Content> {
             id: WidgetId::here(),
             menu,
             content,
+            press_and_hold: PressAndHold::new(),
         }
     }
+
+    fn show(&self, ctx: &mut EventCtx, window_position: Point) {
+        let menu = self.menu.to_shell_menu(true);
+        self.menu.assign_menu_item_indices();
+        ctx.track_popup_menu(menu, window_position);
+    }
 }
 
 impl<Content: Widget + 'static> Widget for ContextMenu<Content> {
@@ -213,16 +379,24 @@ impl<Content: Widget + 'static> Widget for ContextMenu<Content> {
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        // the finger may be held over a descendant that captured it
+        let press_and_hold = touch_event(event)
+            .filter(|p| self.press_and_hold.event(p))
+            .map(|p| p.window_position);
+
         self.content.route_event(ctx, event, env);
         if !ctx.handled {
+            if let Some(window_position) = press_and_hold {
+                self.show(ctx, window_position);
+                ctx.set_handled();
+                return;
+            }
             match *event {
                 Event::Pointer(ref pointer_event)
                     if pointer_event.kind == PointerEventKind::PointerDown
                         && pointer_event.button == Some(PointerButton::RIGHT) =>
                 {
-                    let menu = self.menu.to_shell_menu(true);
-                    self.menu.assign_menu_item_indices();
-                    ctx.track_popup_menu(menu, pointer_event.window_position);
+                    self.show(ctx, pointer_event.window_position);
                 }
                 Event::MenuCommand(index) => {
                     if let Some(action) = self.menu.find_action_by_index(index) {
@@ -235,6 +409,7 @@ impl<Content: Widget + 'static> Widget for ContextMenu<Content> {
     }
 
     fn paint(&self, ctx: &mut PaintCtx) {
-        self.content.paint(ctx)
+        self.content.paint(ctx);
+        self.press_and_hold.paint(ctx);
     }
 }
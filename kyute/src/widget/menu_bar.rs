@@ -0,0 +1,940 @@
+//! In-window menu bar.
+use crate::{
+    cache,
+    core::FocusChange,
+    drawing::{Paint, PaintCtxExt, Shape},
+    event::{Key, KeyboardEvent, Modifiers, PointerEvent},
+    theme,
+    widget::{menu::split_mnemonic, prelude::*, Menu, MenuItem, Placement, Side, Text},
+    Color, InternalEvent, PointerEventKind, RoundToPixel, State,
+};
+use keyboard_types::KeyState;
+use std::cell::{Cell, RefCell};
+
+/// Horizontal padding around the labels of the menu bar and of menu items.
+const ITEM_PADDING_X: f64 = 8.0;
+/// Vertical padding around the labels of the menu bar and of menu items.
+const ITEM_PADDING_Y: f64 = 3.0;
+/// Height of separators in menus.
+const SEPARATOR_HEIGHT: f64 = 7.0;
+/// Minimum space between the label and the shortcut of a menu item.
+const SHORTCUT_GAP: f64 = 24.0;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// MnemonicLabel
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Label of a menu item, with the mnemonic character underlined when mnemonics are shown.
+struct MnemonicLabel {
+    prefix: WidgetPod<Text>,
+    /// Mnemonic character and the rest of the text.
+    mnemonic: Option<(WidgetPod<Text>, WidgetPod<Text>)>,
+    show_mnemonic: bool,
+    underline: Cell<(Rect, Color)>,
+}
+
+impl MnemonicLabel {
+    #[composable]
+    fn new(text: &str, show_mnemonic: bool) -> MnemonicLabel {
+        let (text, mnemonic) = split_mnemonic(text);
+        let (prefix, mnemonic) = match mnemonic {
+            Some(pos) => {
+                let end = pos + text[pos..].chars().next().map(char::len_utf8).unwrap_or(0);
+                (
+                    Text::new(text[..pos].to_string()),
+                    Some((
+                        WidgetPod::new(Text::new(text[pos..end].to_string())),
+                        WidgetPod::new(Text::new(text[end..].to_string())),
+                    )),
+                )
+            }
+            None => (Text::new(text), None),
+        };
+        MnemonicLabel {
+            prefix: WidgetPod::new(prefix),
+            mnemonic,
+            show_mnemonic,
+            underline: Cell::new((Rect::zero(), Color::from_hex("#000000"))),
+        }
+    }
+}
+
+impl Widget for MnemonicLabel {
+    fn widget_id(&self) -> Option<WidgetId> {
+        None
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let unconstrained = LayoutParams {
+            min: Size::zero(),
+            max: Size::new(f64::INFINITY, f64::INFINITY),
+            ..*constraints
+        };
+
+        let mut x = 0.0;
+        let mut height: f64 = 0.0;
+        let mut place = |ctx: &mut LayoutCtx, text: &WidgetPod<Text>| {
+            let size = text.layout(ctx, &unconstrained, env).measurements.size;
+            if !ctx.speculative {
+                text.set_offset(Offset::new(x, 0.0));
+            }
+            let rect = Rect::new(Point::new(x, 0.0), size);
+            x += size.width;
+            height = height.max(size.height);
+            rect
+        };
+
+        place(ctx, &self.prefix);
+        if let Some((ref mnemonic, ref rest)) = self.mnemonic {
+            let rect = place(ctx, mnemonic);
+            place(ctx, rest);
+            let color = env
+                .get(&theme::TEXT_COLOR)
+                .unwrap_or_else(|| Color::from_hex("#000000"));
+            self.underline.set((
+                Rect::new(
+                    Point::new(rect.min_x(), rect.max_y() - 1.0),
+                    Size::new(rect.width(), 1.0),
+                ),
+                color,
+            ));
+        }
+
+        Geometry::new(constraints.constrain(Size::new(x, height)))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.prefix.route_event(ctx, event, env);
+        if let Some((ref mnemonic, ref rest)) = self.mnemonic {
+            mnemonic.route_event(ctx, event, env);
+            rest.route_event(ctx, event, env);
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.prefix.paint(ctx);
+        if let Some((ref mnemonic, ref rest)) = self.mnemonic {
+            mnemonic.paint(ctx);
+            rest.paint(ctx);
+            if self.show_mnemonic {
+                let (rect, color) = self.underline.get();
+                ctx.fill_shape(&Shape::from(rect), &Paint::Color(color));
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// State
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the next (or previous) item that isn't a separator, wrapping around.
+fn step(items: &[MenuItem], from: Option<usize>, forward: bool) -> Option<usize> {
+    let n = items.len();
+    if n == 0 {
+        return None;
+    }
+    let mut i = from.unwrap_or(if forward { n - 1 } else { 0 });
+    for _ in 0..n {
+        i = if forward { (i + 1) % n } else { (i + n - 1) % n };
+        if !matches!(items[i], MenuItem::Separator) {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Returns the item with the given mnemonic character.
+fn find_mnemonic(items: &[MenuItem], key: &str) -> Option<usize> {
+    let key = key.to_lowercase();
+    items.iter().position(|item| match item {
+        MenuItem::Action { text, .. } | MenuItem::Submenu { text, .. } => {
+            let (text, mnemonic) = split_mnemonic(text);
+            mnemonic
+                .and_then(|pos| text[pos..].chars().next())
+                .map_or(false, |c| c.to_lowercase().eq(key.chars()))
+        }
+        MenuItem::Separator => false,
+    })
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct MenuBarState {
+    /// Highlighted top-level item, if the menu bar is active.
+    active: Option<usize>,
+    /// Whether the menu of the highlighted top-level item is open.
+    open: bool,
+    /// Highlighted item in each open menu.
+    ///
+    /// The first entry is for the menu of the active top-level item; each following entry
+    /// is for the submenu of the highlighted item in the previous one.
+    path: Vec<Option<usize>>,
+    /// Whether to underline mnemonics (when the menu bar was activated with the keyboard).
+    show_mnemonics: bool,
+    /// Whether Alt was pressed with no other key since.
+    alt_pressed: bool,
+    /// Widget that had the focus before the menu bar was activated.
+    prev_focus: Option<WidgetId>,
+}
+
+impl MenuBarState {
+    /// Returns the open menus, from the top-level one to the innermost submenu.
+    fn open_menus<'a>(&self, menu: &'a Menu) -> Vec<&'a Menu> {
+        let mut menus = Vec::new();
+        if !self.open {
+            return menus;
+        }
+        let mut current = match self.active.and_then(|active| menu.items().get(active)) {
+            Some(MenuItem::Submenu { menu, .. }) => menu,
+            _ => return menus,
+        };
+        menus.push(current);
+        for highlighted in self.path.iter().take(self.path.len().saturating_sub(1)) {
+            match highlighted.and_then(|i| current.items().get(i)) {
+                Some(MenuItem::Submenu { menu, .. }) => {
+                    menus.push(menu);
+                    current = menu;
+                }
+                _ => break,
+            }
+        }
+        menus
+    }
+
+    /// Highlights a top-level item, and optionally opens its menu.
+    fn activate(&mut self, menu: &Menu, index: usize, open: bool, highlight_first: bool) {
+        self.active = Some(index);
+        self.open = false;
+        self.path.clear();
+        if open {
+            if let Some(MenuItem::Submenu { menu: submenu, .. }) = menu.items().get(index) {
+                self.open = true;
+                self.path.push(if highlight_first {
+                    step(submenu.items(), None, true)
+                } else {
+                    None
+                });
+            }
+        }
+    }
+
+    /// Highlights an item in an open menu, opening its submenu if it has one.
+    fn hover(&mut self, menu: &Menu, level: usize, item: Option<usize>) {
+        let menus = self.open_menus(menu);
+        if level >= menus.len() || (self.path.get(level) == Some(&item) && self.path.len() > level + 1) {
+            return;
+        }
+        let items = menus[level].items();
+        let item = item.filter(|&i| !matches!(items.get(i), Some(MenuItem::Separator) | None));
+        self.path.truncate(level + 1);
+        self.path[level] = item;
+        if let Some(MenuItem::Submenu { .. }) = item.and_then(|i| items.get(i)) {
+            self.path.push(None);
+        }
+    }
+
+    /// Invokes the highlighted item of the innermost open menu: triggers actions, opens submenus.
+    fn invoke(&mut self, menu: &Menu) {
+        let menus = self.open_menus(menu);
+        let (items, highlighted) = match menus.last() {
+            Some(last) => (last.items(), self.path.last().copied().flatten()),
+            None => (menu.items(), self.active),
+        };
+        match highlighted.and_then(|i| items.get(i)) {
+            Some(MenuItem::Action { action, .. }) => {
+                action.triggered.signal(());
+                self.close();
+            }
+            Some(MenuItem::Submenu { menu: submenu, .. }) => {
+                let first = step(submenu.items(), None, true);
+                if menus.is_empty() {
+                    self.open = true;
+                    self.path = vec![first];
+                } else {
+                    self.path.push(first);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self) {
+        *self = MenuBarState::default();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// MenuBar
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+enum PopupItem {
+    Separator,
+    Item {
+        label: WidgetPod<MnemonicLabel>,
+        /// Shortcut text, or submenu arrow.
+        shortcut: Option<WidgetPod<Text>>,
+    },
+}
+
+/// Colors of the menu bar and of the open menus, from the theme.
+#[derive(Copy, Clone, Default)]
+struct MenuColors {
+    background: Option<Color>,
+    popup_background: Option<Color>,
+    highlight: Option<Color>,
+    border: Option<Color>,
+}
+
+impl MenuColors {
+    fn from_env(env: &Environment) -> MenuColors {
+        MenuColors {
+            background: env.get(&theme::WINDOW_BACKGROUND_COLOR),
+            popup_background: env.get(&theme::CONTENT_BACKGROUND_COLOR),
+            highlight: env.get(&theme::ACCENT_COLOR),
+            border: env.get(&theme::BORDER_COLOR),
+        }
+    }
+}
+
+/// An open menu, floating over the content of the menu bar.
+struct MenuPopup {
+    items: Vec<PopupItem>,
+    highlighted: Option<usize>,
+    /// Bounds of the items in local coordinates, as of the last layout.
+    item_rects: RefCell<Vec<Rect>>,
+    colors: Cell<MenuColors>,
+}
+
+impl MenuPopup {
+    fn new(items: Vec<PopupItem>, highlighted: Option<usize>) -> MenuPopup {
+        MenuPopup {
+            items,
+            highlighted,
+            item_rects: RefCell::new(Vec::new()),
+            colors: Cell::new(MenuColors::default()),
+        }
+    }
+}
+
+impl Widget for MenuPopup {
+    fn widget_id(&self) -> Option<WidgetId> {
+        None
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let unconstrained = LayoutParams {
+            min: Size::zero(),
+            max: Size::new(f64::INFINITY, f64::INFINITY),
+            ..*constraints
+        };
+        let sizes: Vec<(Size, Size)> = self
+            .items
+            .iter()
+            .map(|item| match item {
+                PopupItem::Separator => (Size::new(0.0, SEPARATOR_HEIGHT), Size::zero()),
+                PopupItem::Item { label, shortcut } => (
+                    label.layout(ctx, &unconstrained, env).measurements.size,
+                    shortcut
+                        .as_ref()
+                        .map(|shortcut| shortcut.layout(ctx, &unconstrained, env).measurements.size)
+                        .unwrap_or_else(Size::zero),
+                ),
+            })
+            .collect();
+        let label_width = sizes.iter().map(|(label, _)| label.width).fold(0.0, f64::max);
+        let shortcut_width = sizes.iter().map(|(_, shortcut)| shortcut.width).fold(0.0, f64::max);
+        let width = label_width
+            + if shortcut_width > 0.0 {
+                SHORTCUT_GAP + shortcut_width
+            } else {
+                0.0
+            }
+            + 2.0 * ITEM_PADDING_X;
+
+        let mut y = ITEM_PADDING_Y;
+        let mut item_rects = Vec::with_capacity(self.items.len());
+        for (item, (label_size, shortcut_size)) in self.items.iter().zip(sizes.iter()) {
+            let height = match item {
+                PopupItem::Separator => SEPARATOR_HEIGHT,
+                PopupItem::Item { label, shortcut } => {
+                    if !ctx.speculative {
+                        label.set_offset(Offset::new(ITEM_PADDING_X, y + ITEM_PADDING_Y));
+                        if let Some(shortcut) = shortcut {
+                            shortcut.set_offset(Offset::new(
+                                width - ITEM_PADDING_X - shortcut_size.width,
+                                y + ITEM_PADDING_Y,
+                            ));
+                        }
+                    }
+                    label_size.height.max(shortcut_size.height) + 2.0 * ITEM_PADDING_Y
+                }
+            };
+            item_rects.push(Rect::new(Point::new(0.0, y), Size::new(width, height)));
+            y += height;
+        }
+
+        if !ctx.speculative {
+            self.item_rects.replace(item_rects);
+            self.colors.set(MenuColors::from_env(env));
+        }
+        Geometry::new(Size::new(width, y + ITEM_PADDING_Y))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        for item in self.items.iter() {
+            if let PopupItem::Item { label, shortcut } = item {
+                label.route_event(ctx, event, env);
+                if let Some(shortcut) = shortcut {
+                    shortcut.route_event(ctx, event, env);
+                }
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let colors = self.colors.get();
+        let item_rects = self.item_rects.borrow();
+        let bounds = ctx.bounds;
+        if let Some(border) = colors.border {
+            ctx.fill_shape(&Shape::from(bounds), &Paint::Color(border));
+        }
+        if let Some(background) = colors.popup_background {
+            ctx.fill_shape(&Shape::from(bounds.inflate(-1.0, -1.0)), &Paint::Color(background));
+        }
+        if let (Some(highlight), Some(rect)) = (colors.highlight, self.highlighted.and_then(|i| item_rects.get(i))) {
+            ctx.fill_shape(&Shape::from(rect.inflate(-1.0, 0.0)), &Paint::Color(highlight));
+        }
+        for (item, rect) in self.items.iter().zip(item_rects.iter()) {
+            match item {
+                PopupItem::Separator => {
+                    if let Some(border) = colors.border {
+                        let line = Rect::new(
+                            Point::new(rect.min_x() + ITEM_PADDING_X, rect.center().y.floor()),
+                            Size::new(rect.width() - 2.0 * ITEM_PADDING_X, 1.0),
+                        );
+                        ctx.fill_shape(&Shape::from(line), &Paint::Color(border));
+                    }
+                }
+                PopupItem::Item { label, shortcut } => {
+                    label.paint(ctx);
+                    if let Some(shortcut) = shortcut {
+                        shortcut.paint(ctx);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct MenuBarLayout {
+    bar_height: f64,
+    /// Bounds of the top-level items.
+    bar_items: Vec<Rect>,
+    /// Bounds of each open menu, and of their items.
+    popups: Vec<(Rect, Vec<Rect>)>,
+    colors: MenuColors,
+}
+
+/// Menu bar drawn inside the window, above the content.
+///
+/// Use this instead of the native window menu (`Window::new(.., Some(menu))`) for windows with custom decorations.
+/// It uses the same `Menu` and `Action` model and reproduces the behavior of native menu bars:
+/// - pressing and releasing `Alt` activates the menu bar, and shows the mnemonics (`&File` => `Alt+F`);
+/// - the arrow keys move between menus and items, `Enter` invokes the highlighted item and `Escape` closes menus;
+/// - the open menus float over the content, like `AnchoredOverlay`s: they are placed below their item in the bar
+///   (submenus to the right of their item), and flipped or shifted to stay inside the window.
+///
+/// There should be at most one menu bar per window. Since the open menus only receive pointer events inside the bounds
+/// of the menu bar, it should cover the whole window.
+pub struct MenuBar<Content> {
+    id: WidgetId,
+    menu: Menu,
+    state: State<MenuBarState>,
+    current: MenuBarState,
+    /// Labels of the top-level items (`None` for separators).
+    bar_items: Vec<Option<WidgetPod<MnemonicLabel>>>,
+    /// The open menus.
+    popups: Vec<WidgetPod<MenuPopup>>,
+    content: WidgetPod<Content>,
+    layout: RefCell<MenuBarLayout>,
+    /// Transform from local to window coordinates and window size, as of the last event.
+    window_placement: Cell<Option<(Transform, Size)>>,
+}
+
+impl<Content: Widget + 'static> MenuBar<Content> {
+    /// Creates a menu bar above the specified content.
+    ///
+    /// The top-level items of `menu` are shown in the bar: they should be submenus, but actions are also accepted.
+    #[composable]
+    pub fn new(menu: Menu, content: Content) -> MenuBar<Content> {
+        let state = cache::state(MenuBarState::default);
+        let current = state.get();
+        let show_mnemonics = current.show_mnemonics;
+
+        let bar_items = menu
+            .items()
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                cache::scoped(i, || match item {
+                    MenuItem::Action { text, .. } | MenuItem::Submenu { text, .. } => {
+                        Some(WidgetPod::new(MnemonicLabel::new(text, show_mnemonics)))
+                    }
+                    MenuItem::Separator => None,
+                })
+            })
+            .collect();
+
+        let popups = current
+            .open_menus(&menu)
+            .into_iter()
+            .enumerate()
+            .map(|(level, open_menu)| {
+                cache::scoped(level, || {
+                    let items = open_menu
+                        .items()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, item)| {
+                            cache::scoped(i, || match item {
+                                MenuItem::Action { text, action, .. } => PopupItem::Item {
+                                    label: WidgetPod::new(MnemonicLabel::new(text, show_mnemonics)),
+                                    shortcut: action
+                                        .shortcut
+                                        .as_ref()
+                                        .map(|shortcut| WidgetPod::new(Text::new(shortcut.to_string()))),
+                                },
+                                MenuItem::Submenu { text, .. } => PopupItem::Item {
+                                    label: WidgetPod::new(MnemonicLabel::new(text, show_mnemonics)),
+                                    shortcut: Some(WidgetPod::new(Text::new("▸"))),
+                                },
+                                MenuItem::Separator => PopupItem::Separator,
+                            })
+                        })
+                        .collect();
+                    WidgetPod::new(MenuPopup::new(items, current.path.get(level).copied().flatten()))
+                })
+            })
+            .collect();
+
+        MenuBar {
+            id: WidgetId::here(),
+            menu,
+            state,
+            current,
+            bar_items,
+            popups,
+            content: WidgetPod::new(content),
+            layout: RefCell::new(MenuBarLayout::default()),
+            window_placement: Cell::new(None),
+        }
+    }
+
+    /// Returns a reference to the content widget.
+    pub fn content(&self) -> &Content {
+        self.content.inner()
+    }
+
+    /// Updates the state of the menu bar, and moves the focus when the menu bar is activated or deactivated.
+    fn update_state(&self, ctx: &mut EventCtx, mut new_state: MenuBarState) {
+        let was_active = self.current.active.is_some();
+        let is_active = new_state.active.is_some();
+        if !was_active && is_active {
+            // keyboard navigation: take the focus, and give it back when the menu bar is deactivated
+            new_state.prev_focus = ctx
+                .window_state
+                .as_deref()
+                .and_then(|window_state| window_state.focus_state.focus)
+                .filter(|&focus| focus != self.id);
+            ctx.request_focus();
+        } else if was_active && !is_active {
+            if let Some(prev_focus) = self.current.prev_focus {
+                ctx.focus_change = Some(FocusChange::MoveTo(prev_focus));
+            }
+        }
+        if new_state != self.current {
+            self.state.set(new_state);
+        }
+    }
+
+    fn keyboard_event(&self, ctx: &mut EventCtx, event: &KeyboardEvent) {
+        let mut state = self.current.clone();
+        let items = self.menu.items();
+
+        // pressing and releasing Alt alone toggles the menu bar
+        if event.key == Key::Alt {
+            match event.state {
+                KeyState::Down => state.alt_pressed = true,
+                KeyState::Up if state.alt_pressed => {
+                    if state.active.is_some() {
+                        state.close();
+                    } else if let Some(first) = step(items, None, true) {
+                        state.active = Some(first);
+                        state.show_mnemonics = true;
+                        state.alt_pressed = false;
+                    }
+                }
+                KeyState::Up => {}
+            }
+            self.update_state(ctx, state);
+            ctx.set_handled();
+            return;
+        }
+
+        if event.state != KeyState::Down {
+            return;
+        }
+        state.alt_pressed = false;
+
+        // Alt+mnemonic opens the corresponding menu
+        if event.modifiers.contains(Modifiers::ALT) {
+            if let Key::Character(ref c) = event.key {
+                if let Some(index) = find_mnemonic(items, c) {
+                    state.show_mnemonics = true;
+                    state.activate(&self.menu, index, true, true);
+                    if let Some(MenuItem::Action { .. }) = items.get(index) {
+                        state.invoke(&self.menu);
+                    }
+                    ctx.set_handled();
+                }
+            }
+            self.update_state(ctx, state);
+            return;
+        }
+
+        let active = match state.active {
+            Some(active) => active,
+            None => {
+                self.update_state(ctx, state);
+                return;
+            }
+        };
+
+        let menus = state.open_menus(&self.menu);
+        match event.key {
+            Key::Escape => {
+                if state.path.len() > 1 {
+                    state.path.pop();
+                } else if state.open {
+                    state.open = false;
+                    state.path.clear();
+                } else {
+                    state.close();
+                }
+            }
+            Key::ArrowLeft => {
+                if state.path.len() > 1 {
+                    state.path.pop();
+                } else {
+                    let prev = step(items, Some(active), false).unwrap_or(active);
+                    state.activate(&self.menu, prev, state.open, true);
+                }
+            }
+            Key::ArrowRight => {
+                // open the submenu of the highlighted item, or move to the next menu
+                let highlighted = menus
+                    .last()
+                    .zip(state.path.last().copied().flatten())
+                    .and_then(|(menu, i)| menu.items().get(i));
+                if let Some(MenuItem::Submenu { menu: submenu, .. }) = highlighted {
+                    state.path.push(step(submenu.items(), None, true));
+                } else {
+                    let next = step(items, Some(active), true).unwrap_or(active);
+                    state.activate(&self.menu, next, state.open, true);
+                }
+            }
+            Key::ArrowDown | Key::ArrowUp => {
+                if let Some(innermost) = menus.last() {
+                    let level = state.path.len() - 1;
+                    state.path[level] = step(innermost.items(), state.path[level], event.key == Key::ArrowDown);
+                } else {
+                    state.activate(&self.menu, active, true, true);
+                }
+            }
+            Key::Enter => state.invoke(&self.menu),
+            Key::Character(ref c) => {
+                // mnemonic in the innermost menu
+                if let Some(innermost) = menus.last() {
+                    if let Some(index) = find_mnemonic(innermost.items(), c) {
+                        let level = state.path.len() - 1;
+                        state.path[level] = Some(index);
+                        state.invoke(&self.menu);
+                    }
+                } else if let Some(index) = find_mnemonic(items, c) {
+                    state.activate(&self.menu, index, true, true);
+                    if let Some(MenuItem::Action { .. }) = items.get(index) {
+                        state.invoke(&self.menu);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        self.update_state(ctx, state);
+        ctx.set_handled();
+    }
+
+    /// Handles a pointer event. Returns false if the event should be forwarded to the content.
+    fn pointer_event(&self, ctx: &mut EventCtx, event: &PointerEvent) -> bool {
+        let layout = self.layout.borrow();
+        let bar_hit = layout.bar_items.iter().position(|r| r.contains(event.position));
+        let mut state = self.current.clone();
+
+        if state.open {
+            // the open menus capture all pointer events; search from the innermost menu
+            let popup_hit = layout
+                .popups
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(_, (bounds, _))| bounds.contains(event.position))
+                .map(|(level, (_, items))| (level, items.iter().position(|r| r.contains(event.position))));
+
+            match (event.kind, popup_hit, bar_hit) {
+                (PointerEventKind::PointerMove, Some((level, item)), _) => state.hover(&self.menu, level, item),
+                (PointerEventKind::PointerMove, None, Some(index)) if state.active != Some(index) => {
+                    state.activate(&self.menu, index, true, false)
+                }
+                (PointerEventKind::PointerUp, Some((level, Some(item))), _) => {
+                    state.hover(&self.menu, level, Some(item));
+                    state.invoke(&self.menu);
+                }
+                (PointerEventKind::PointerDown, None, Some(index)) if state.active != Some(index) => {
+                    state.activate(&self.menu, index, true, false)
+                }
+                (PointerEventKind::PointerDown, None, _) => state.close(),
+                _ => {}
+            }
+            drop(layout);
+            self.update_state(ctx, state);
+            ctx.set_handled();
+            return true;
+        }
+
+        match bar_hit {
+            Some(index) => {
+                if event.kind == PointerEventKind::PointerDown {
+                    state.activate(&self.menu, index, true, false);
+                    if let Some(MenuItem::Action { .. }) = self.menu.items().get(index) {
+                        state.invoke(&self.menu);
+                    }
+                    drop(layout);
+                    self.update_state(ctx, state);
+                }
+                ctx.set_handled();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<Content: Widget + 'static> Widget for MenuBar<Content> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let unconstrained = LayoutParams {
+            min: Size::zero(),
+            max: Size::new(f64::INFINITY, f64::INFINITY),
+            ..*constraints
+        };
+        let mut layout = MenuBarLayout {
+            colors: MenuColors::from_env(env),
+            ..Default::default()
+        };
+
+        // --- menu bar ---
+        let label_sizes: Vec<Option<Size>> = self
+            .bar_items
+            .iter()
+            .map(|label| {
+                label
+                    .as_ref()
+                    .map(|label| label.layout(ctx, &unconstrained, env).measurements.size)
+            })
+            .collect();
+        let bar_height = label_sizes
+            .iter()
+            .flatten()
+            .map(|size| size.height + 2.0 * ITEM_PADDING_Y)
+            .fold(0.0, f64::max)
+            .round_to_pixel(ctx.scale_factor);
+        let mut x = 0.0;
+        for (label, size) in self.bar_items.iter().zip(label_sizes.iter()) {
+            match (label, size) {
+                (Some(label), Some(size)) => {
+                    if !ctx.speculative {
+                        label.set_offset(Offset::new(x + ITEM_PADDING_X, ITEM_PADDING_Y));
+                    }
+                    let width = size.width + 2.0 * ITEM_PADDING_X;
+                    layout
+                        .bar_items
+                        .push(Rect::new(Point::new(x, 0.0), Size::new(width, bar_height)));
+                    x += width;
+                }
+                _ => {
+                    // separator
+                    layout.bar_items.push(Rect::zero());
+                    x += ITEM_PADDING_X;
+                }
+            }
+        }
+        layout.bar_height = bar_height;
+
+        // --- content ---
+        let content_constraints = LayoutParams {
+            min: Size::new(constraints.min.width, (constraints.min.height - bar_height).max(0.0)),
+            max: Size::new(constraints.max.width, (constraints.max.height - bar_height).max(0.0)),
+            ..*constraints
+        };
+        let content_size = self.content.layout(ctx, &content_constraints, env).measurements.size;
+        if !ctx.speculative {
+            self.content.set_offset(Offset::new(0.0, bar_height));
+        }
+
+        // --- open menus ---
+        // Placed like floating widgets of `AnchoredOverlay`s, inside the window: the first menu below the active item,
+        // submenus to the right of their parent item.
+        let window_bounds = self.window_placement.get().and_then(|(transform, window_size)| {
+            let inverse = transform.inverse()?;
+            Some(inverse.outer_transformed_rect(&Rect::new(Point::origin(), window_size)))
+        });
+        let mut anchor = self
+            .current
+            .active
+            .and_then(|active| layout.bar_items.get(active))
+            .copied()
+            .unwrap_or_else(|| Rect::new(Point::origin(), Size::new(0.0, bar_height)));
+        let mut placement = Placement::new(Side::Bottom);
+        for (level, popup) in self.popups.iter().enumerate() {
+            let size = popup.layout(ctx, &unconstrained, env).measurements.size;
+            let (_, pos) = placement.place(&anchor, size, window_bounds.as_ref());
+            let offset = Offset::new(pos.x, pos.y).round_to_pixel(ctx.scale_factor);
+            if !ctx.speculative {
+                popup.set_offset(offset);
+            }
+            let item_rects: Vec<Rect> = popup
+                .inner()
+                .item_rects
+                .borrow()
+                .iter()
+                .map(|rect| rect.translate(offset))
+                .collect();
+            if let Some(rect) = self
+                .current
+                .path
+                .get(level)
+                .copied()
+                .flatten()
+                .and_then(|i| item_rects.get(i))
+            {
+                anchor = *rect;
+            }
+            placement = Placement {
+                offset: Offset::new(0.0, -ITEM_PADDING_Y),
+                ..Placement::new(Side::Right)
+            };
+            layout.popups.push((Rect::new(offset.to_point(), size), item_rects));
+        }
+
+        if !ctx.speculative {
+            *self.layout.borrow_mut() = layout;
+        }
+
+        let size = Size::new(
+            constraints.constrain_width(content_size.width.max(x)),
+            constraints.constrain_height(bar_height + content_size.height),
+        );
+        Geometry::new(size.round_to_pixel(ctx.scale_factor))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        // track the position of the menu bar in the window, so that the open menus can be kept inside the window
+        if let Some(window_size) = ctx.window_size() {
+            let window_placement = Some((*ctx.window_transform(), window_size));
+            if self.window_placement.get() != window_placement {
+                self.window_placement.set(window_placement);
+                ctx.request_relayout();
+            }
+        }
+
+        match event {
+            Event::Internal(InternalEvent::FindMenuBar { menu_bar }) => {
+                if menu_bar.is_none() {
+                    **menu_bar = Some(self.id);
+                }
+                return;
+            }
+            Event::Keyboard(keyboard_event) => {
+                // sent to the menu bar because it has the focus, or because it's a menu activation key
+                let keyboard_event = keyboard_event.clone();
+                self.keyboard_event(ctx, &keyboard_event);
+                return;
+            }
+            Event::FocusLost => {
+                // focus moved elsewhere (e.g. clicked on the content): deactivate without restoring the focus
+                if self.current.active.is_some() {
+                    self.state.set(MenuBarState::default());
+                }
+                return;
+            }
+            Event::Pointer(pointer_event) => {
+                let pointer_event = *pointer_event;
+                if self.pointer_event(ctx, &pointer_event) {
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        for label in self.bar_items.iter().flatten() {
+            label.route_event(ctx, event, env);
+        }
+        for popup in self.popups.iter() {
+            popup.route_event(ctx, event, env);
+        }
+        self.content.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let layout = self.layout.borrow();
+
+        // --- menu bar ---
+        if let Some(background) = layout.colors.background {
+            ctx.fill_shape(
+                &Shape::from(Rect::new(
+                    Point::origin(),
+                    Size::new(ctx.bounds.size.width, layout.bar_height),
+                )),
+                &Paint::Color(background),
+            );
+        }
+        if let (Some(highlight), Some(rect)) = (
+            layout.colors.highlight,
+            self.current.active.and_then(|active| layout.bar_items.get(active)),
+        ) {
+            ctx.fill_shape(&Shape::from(*rect), &Paint::Color(highlight));
+        }
+        for label in self.bar_items.iter().flatten() {
+            label.paint(ctx);
+        }
+
+        self.content.paint(ctx);
+
+        // --- open menus, over the content ---
+        for popup in self.popups.iter() {
+            popup.paint(ctx);
+        }
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new(format!(
+            "active: {:?}, open menus: {}",
+            self.current.active,
+            self.popups.len()
+        ))
+    }
+}
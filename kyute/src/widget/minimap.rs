@@ -0,0 +1,554 @@
+//! Scaled-down overview of a large content area.
+use crate::{
+    cache,
+    cache::Restartable,
+    core::PaintDamage,
+    drawing::{overdraw, ToSkia},
+    event::{PointerEvent, PointerEventKind},
+    frame_debugger,
+    region::Region,
+    widget::prelude::*,
+    Color, Data, InternalEvent, SizeI,
+};
+use kyute_shell::animation::Layer;
+use parking_lot::Mutex;
+use skia_safe as sk;
+use std::{
+    cell::Cell,
+    mem,
+    sync::{Arc, Weak},
+};
+
+/// Width of the minimap if the constraints don't specify one, in DIPs.
+const DEFAULT_WIDTH: f64 = 120.0;
+/// Default maximum scale of the contents in the minimap.
+const DEFAULT_MAX_SCALE: f64 = 0.2;
+const VIEWPORT_FILL_COLOR: Color = Color::new(0.5, 0.5, 0.5, 0.2);
+const VIEWPORT_STROKE_COLOR: Color = Color::new(0.5, 0.5, 0.5, 0.6);
+
+/// Position of the contents in the minimap.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Mapping {
+    /// Scale of the contents.
+    scale: f64,
+    /// Offset of the top of the minimap in the scaled contents, in DIPs.
+    scroll: f64,
+}
+
+impl Mapping {
+    /// Scales the contents to the width of the minimap, at most by `max_scale`.
+    ///
+    /// If the scaled contents are taller than the minimap, they're scrolled proportionally to the position of the
+    /// viewport, so that the viewport is always visible.
+    fn new(content_size: Size, viewport: Rect, size: Size, max_scale: f64) -> Mapping {
+        let scale = if content_size.width > 0.0 {
+            (size.width / content_size.width).min(max_scale)
+        } else {
+            max_scale
+        };
+        let overflow = content_size.height * scale - size.height;
+        let range = content_size.height - viewport.height();
+        let scroll = if overflow > 0.0 && range > 0.0 {
+            overflow * (viewport.min_y() / range).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Mapping { scale, scroll }
+    }
+
+    /// Transform from content coordinates to minimap coordinates.
+    fn to_minimap(&self) -> Transform {
+        Transform::scale(self.scale, self.scale).then(&Offset::new(0.0, -self.scroll).to_transform())
+    }
+
+    /// Converts a position in the minimap to content coordinates.
+    fn to_content(&self, position: Point) -> Point {
+        Point::new(position.x / self.scale, (position.y + self.scroll) / self.scale)
+    }
+}
+
+/// Rendering of the contents, kept across frames.
+struct Snapshot {
+    surface: sk::Surface,
+    /// Scale of the contents on the snapshot.
+    scale: f64,
+    scale_factor: f64,
+    /// Scroll offset of the snapshot, in pixels.
+    scroll: i32,
+    /// Whether the snapshot must be repainted entirely.
+    invalid: bool,
+}
+
+impl Snapshot {
+    fn new(skia_gpu_context: &mut sk::gpu::DirectContext, size: SizeI, scale: f64, scale_factor: f64) -> Snapshot {
+        let surface = sk::Surface::new_render_target(
+            skia_gpu_context,
+            sk::Budgeted::No,
+            &sk::ImageInfo::new(
+                (size.width, size.height),
+                sk::ColorType::RGBA8888,
+                sk::AlphaType::Premul,
+                None,
+            ),
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("failed to create skia surface");
+        Snapshot {
+            surface,
+            scale,
+            scale_factor,
+            scroll: 0,
+            invalid: true,
+        }
+    }
+
+    fn size(&self) -> SizeI {
+        SizeI::new(self.surface.width(), self.surface.height())
+    }
+}
+
+/// State of a `Minimap`, kept across recompositions.
+struct MinimapState {
+    snapshot: Option<Snapshot>,
+    /// Parts of the contents damaged since the snapshot was last updated, in content coordinates.
+    damaged: Region,
+    /// Contents rendered on the snapshot.
+    content: Weak<WidgetPod>,
+    /// Position of the pointer relative to the origin of the viewport during a drag, in content coordinates.
+    drag: Option<Offset>,
+    /// Parent layer of the contents while they're painted on the snapshot. It's never shown: widgets of the contents
+    /// with their own compositor layer don't appear in the minimap.
+    layer: Layer,
+}
+
+impl MinimapState {
+    fn invalidate(&mut self) {
+        if let Some(snapshot) = self.snapshot.as_mut() {
+            snapshot.invalid = true;
+        }
+        self.damaged.clear();
+    }
+}
+
+/// Shows a scaled-down overview of a large content area (e.g. a code editor or a node canvas), with the visible
+/// part of it.
+///
+/// The contents are created in a restartable scope, like with `Subtree`: they should be a cheap rendering of the
+/// document, separate from the widgets of the main view. They're rendered once on a cached snapshot, which is
+/// updated incrementally: only the parts of the contents that request a repaint with `EventCtx::request_repaint_rect`
+/// are rendered again, and only the part of the contents that fits in the minimap is rendered. The snapshot is
+/// rendered entirely when the contents are recomposed, when they request a full repaint or a relayout, or when the
+/// size of the minimap changes.
+///
+/// The contents don't receive input events. Clicking on the minimap or dragging the viewport rectangle signals the
+/// new position of the viewport (see `Minimap::on_navigated`): the application should scroll the main view to it.
+pub struct Minimap {
+    id: WidgetId,
+    content: Restartable<Arc<WidgetPod>>,
+    /// Visible part of the contents in the main view, in content coordinates.
+    viewport: Rect,
+    content_width: Option<f64>,
+    max_scale: f64,
+    state: Arc<Mutex<MinimapState>>,
+    navigated: Signal<Point>,
+    /// Size of the minimap during the last layout.
+    size: Cell<Size>,
+    /// Size of the contents during the last layout.
+    content_size: Cell<Size>,
+}
+
+impl Minimap {
+    /// Creates a minimap of the widgets returned by `f`, showing the specified viewport (in content coordinates).
+    ///
+    /// `f` is called again when `args` change, or when a state variable read in `f` changes.
+    #[composable]
+    pub fn new<Args, W>(args: Args, viewport: Rect, f: impl Fn(&Args) -> W + 'static) -> Minimap
+    where
+        Args: Data,
+        W: Widget + 'static,
+    {
+        let content = cache::restartable(args, move |args| f(args).arc_dyn_pod());
+        Minimap {
+            id: WidgetId::from_call_id(content.call_id()),
+            content,
+            viewport,
+            content_width: None,
+            max_scale: DEFAULT_MAX_SCALE,
+            state: cache::once(|| {
+                Arc::new(Mutex::new(MinimapState {
+                    snapshot: None,
+                    damaged: Region::new(),
+                    content: Weak::new(),
+                    drag: None,
+                    layer: Layer::new(),
+                }))
+            }),
+            navigated: Signal::new(),
+            size: Cell::new(Size::zero()),
+            content_size: Cell::new(Size::zero()),
+        }
+    }
+
+    /// Sets the width of the contents, in DIPs. By default, the contents are laid out without width constraints.
+    pub fn content_width(mut self, width: f64) -> Self {
+        self.content_width = Some(width);
+        self
+    }
+
+    /// Sets the maximum scale of the contents in the minimap (0.2 by default).
+    pub fn max_scale(mut self, max_scale: f64) -> Self {
+        self.max_scale = max_scale;
+        self
+    }
+
+    /// Returns the new origin of the viewport, in content coordinates, if the user navigated with the minimap.
+    pub fn navigated(&self) -> Option<Point> {
+        self.navigated.value()
+    }
+
+    /// Calls the given closure with the new origin of the viewport if the user navigated with the minimap.
+    pub fn on_navigated(self, f: impl FnOnce(Point)) -> Self {
+        self.navigated.map(f);
+        self
+    }
+
+    fn mapping(&self) -> Mapping {
+        Mapping::new(self.content_size.get(), self.viewport, self.size.get(), self.max_scale)
+    }
+
+    /// Moves the viewport so that its origin is at the specified position, in content coordinates.
+    fn navigate(&self, origin: Point) {
+        let content_size = self.content_size.get();
+        let origin = Point::new(
+            origin
+                .x
+                .clamp(0.0, (content_size.width - self.viewport.width()).max(0.0)),
+            origin
+                .y
+                .clamp(0.0, (content_size.height - self.viewport.height()).max(0.0)),
+        );
+        if origin != self.viewport.origin {
+            self.navigated.signal(origin);
+        }
+    }
+
+    fn pointer_event(&self, ctx: &mut EventCtx, p: &PointerEvent) {
+        let mut state = self.state.lock();
+        let position = self.mapping().to_content(p.position);
+        match p.kind {
+            PointerEventKind::PointerDown => {
+                // drag the viewport from where it was grabbed, or center it on the pointer
+                let grab = if self.viewport.contains(position) {
+                    position - self.viewport.origin
+                } else {
+                    Offset::new(self.viewport.width() / 2.0, self.viewport.height() / 2.0)
+                };
+                state.drag = Some(grab);
+                self.navigate(position - grab);
+                ctx.capture_pointer();
+                ctx.set_handled();
+            }
+            PointerEventKind::PointerMove => {
+                if let Some(grab) = state.drag {
+                    self.navigate(position - grab);
+                    ctx.set_handled();
+                }
+            }
+            PointerEventKind::PointerUp => {
+                if state.drag.take().is_some() {
+                    ctx.set_handled();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Renders the specified part of the snapshot, in pixels.
+    fn render(
+        &self,
+        snapshot: &mut Snapshot,
+        layer: &Layer,
+        content: &WidgetPod,
+        rect: sk::IRect,
+        skia_gpu_context: &mut sk::gpu::DirectContext,
+    ) {
+        let size = snapshot.size();
+        let canvas = snapshot.surface.canvas();
+        canvas.save();
+        canvas.clip_irect(rect, sk::ClipOp::Intersect);
+        canvas.clear(sk::Color4f::new(0.0, 0.0, 0.0, 0.0));
+
+        frame_debugger::set_layer_owner(self.widget_id(), content.debug_name());
+        frame_debugger::begin_layer(size, snapshot.scale_factor);
+        {
+            let mut ctx = PaintCtx::new(&mut snapshot.surface, layer, snapshot.scale_factor, skia_gpu_context);
+            let transform = Transform::scale(snapshot.scale, snapshot.scale)
+                .then(&Offset::new(0.0, -snapshot.scroll as f64 / snapshot.scale_factor).to_transform());
+            ctx.with_transform_and_clip(
+                &transform,
+                Rect::new(Point::origin(), self.content_size.get()),
+                None,
+                |ctx| content.paint(ctx),
+            );
+        }
+        frame_debugger::end_layer(&mut snapshot.surface);
+        snapshot.surface.canvas().restore();
+    }
+
+    /// Brings the snapshot up to date with the contents, and returns it.
+    fn update_snapshot(&self, ctx: &mut PaintCtx, mapping: Mapping, size: SizeI) -> sk::Image {
+        let content = self.content.get();
+        let scale_factor = ctx.scale_factor;
+        let mut state = self.state.lock();
+        let state = &mut *state;
+
+        if !Weak::ptr_eq(&state.content, &Arc::downgrade(&content)) {
+            // the contents have been recomposed
+            state.content = Arc::downgrade(&content);
+            state.invalidate();
+        }
+        if state.snapshot.as_ref().map_or(false, |snapshot| {
+            snapshot.size() != size || snapshot.scale != mapping.scale || snapshot.scale_factor != scale_factor
+        }) {
+            state.snapshot = None;
+            state.damaged.clear();
+        }
+        let snapshot = state
+            .snapshot
+            .get_or_insert_with(|| Snapshot::new(ctx.skia_direct_context, size, mapping.scale, scale_factor));
+        if state.layer.size() != size {
+            state.layer.set_size(size);
+        }
+
+        let scroll = (mapping.scroll * scale_factor).round() as i32;
+        let all = sk::IRect::new(0, 0, size.width, size.height);
+        let mut dirty = vec![];
+        if snapshot.invalid || overdraw::repaint_all() || (snapshot.scroll - scroll).abs() >= size.height {
+            dirty.push(all);
+        } else {
+            // pixels of the previous snapshot move down by `shift`
+            let shift = snapshot.scroll - scroll;
+            if shift != 0 {
+                let image = snapshot.surface.image_snapshot();
+                let canvas = snapshot.surface.canvas();
+                canvas.clear(sk::Color4f::new(0.0, 0.0, 0.0, 0.0));
+                canvas.draw_image(&image, (0.0, shift as f32), None);
+                dirty.push(if shift > 0 {
+                    sk::IRect::new(0, 0, size.width, shift)
+                } else {
+                    sk::IRect::new(0, size.height + shift, size.width, size.height)
+                });
+            }
+            let to_pixels = mapping.to_minimap().then_scale(scale_factor, scale_factor);
+            for rect in state.damaged.rects() {
+                let rect = to_pixels.outer_transformed_rect(rect);
+                let mut rect = sk::IRect::new(
+                    rect.min_x().floor() as i32,
+                    rect.min_y().floor() as i32,
+                    rect.max_x().ceil() as i32,
+                    rect.max_y().ceil() as i32,
+                );
+                if rect.intersect(all) {
+                    dirty.push(rect);
+                }
+            }
+        }
+        state.damaged.clear();
+        snapshot.scroll = scroll;
+        snapshot.invalid = false;
+
+        for rect in dirty {
+            self.render(snapshot, &state.layer, &content, rect, ctx.skia_direct_context);
+        }
+        snapshot.surface.image_snapshot()
+    }
+}
+
+impl Widget for Minimap {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let content_constraints = LayoutParams {
+            min: Size::zero(),
+            max: Size::new(self.content_width.unwrap_or(f64::INFINITY), f64::INFINITY),
+            ..*constraints
+        };
+        let content_size = self
+            .content
+            .get()
+            .layout(ctx, &content_constraints, env)
+            .measurements
+            .size;
+        assert!(
+            content_size.width.is_finite() && content_size.height.is_finite(),
+            "the content widget of a Minimap should have finite dimensions"
+        );
+
+        // take the available width, and the height of the scaled contents if the height is unconstrained
+        let width = constraints.finite_max_width().unwrap_or(DEFAULT_WIDTH);
+        let scale = Mapping::new(content_size, self.viewport, Size::new(width, 0.0), self.max_scale).scale;
+        let height = constraints.finite_max_height().unwrap_or(content_size.height * scale);
+        let size = constraints.constrain(Size::new(width, height));
+
+        if !ctx.speculative {
+            self.size.set(size);
+            self.content_size.set(content_size);
+        }
+        Geometry::new(size)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        match event {
+            Event::Internal(InternalEvent::SubtreeRecomposed) => {
+                ctx.request_relayout();
+                return;
+            }
+            Event::Pointer(p) => {
+                self.pointer_event(ctx, p);
+                return;
+            }
+            // the contents are only displayed: they don't receive input, and their layers aren't shown
+            Event::Wheel(_)
+            | Event::Keyboard(_)
+            | Event::Composition(_)
+            | Event::Drop(_)
+            | Event::FocusGained
+            | Event::FocusLost
+            | Event::MenuCommand(_)
+            | Event::Command(_)
+            | Event::BuildFocusChain { .. }
+            | Event::Internal(
+                InternalEvent::UpdateLayers { .. }
+                | InternalEvent::HitTest { .. }
+                | InternalEvent::CollectInputZones { .. }
+                | InternalEvent::RoutePointerEvent { .. }
+                | InternalEvent::RouteWheelEvent { .. }
+                | InternalEvent::FindMenuBar { .. }
+                | InternalEvent::BuildAccessibilityTree { .. }
+                | InternalEvent::CollectFrameRegions { .. }
+                | InternalEvent::CollectScrollLinked { .. },
+            ) => return,
+            _ => {}
+        }
+
+        let paint_damage = mem::take(&mut ctx.paint_damage);
+        let damaged = mem::take(&mut ctx.damaged);
+        self.content.get().route_event(ctx, event, env);
+        let content_damage = mem::replace(&mut ctx.paint_damage, paint_damage);
+        let content_damaged = mem::replace(&mut ctx.damaged, damaged);
+
+        // repaints of the contents update the snapshot
+        let partial = match content_damage {
+            PaintDamage::Partial => ctx
+                .window_transform
+                .inverse()
+                .map(|inverse| content_damaged.transformed(&inverse)),
+            _ => None,
+        };
+        let mut state = self.state.lock();
+        if ctx.relayout || ctx.child_relayout || content_damage == PaintDamage::Repaint {
+            state.invalidate();
+            ctx.request_repaint();
+        } else if let Some(partial) = partial {
+            let to_minimap = self.mapping().to_minimap();
+            for rect in partial.rects() {
+                ctx.request_repaint_rect(to_minimap.outer_transformed_rect(rect));
+            }
+            state.damaged.add_region(&partial);
+        } else if content_damage == PaintDamage::Partial {
+            state.invalidate();
+            ctx.request_repaint();
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let size = self.size.get();
+        let scale_factor = ctx.scale_factor;
+        let pixel_size = SizeI::new(
+            (size.width * scale_factor).ceil() as i32,
+            (size.height * scale_factor).ceil() as i32,
+        );
+        if pixel_size.is_empty() {
+            return;
+        }
+
+        let mapping = self.mapping();
+        let image = self.update_snapshot(ctx, mapping, pixel_size);
+        // drawn at its size in pixels, without resampling
+        let dest = Rect::new(
+            Point::origin(),
+            Size::new(
+                pixel_size.width as f64 / scale_factor,
+                pixel_size.height as f64 / scale_factor,
+            ),
+        );
+        ctx.surface.canvas().draw_image_rect_with_sampling_options(
+            &image,
+            None,
+            dest.to_skia(),
+            sk::SamplingOptions::new(sk::FilterMode::Nearest, sk::MipmapMode::None),
+            &sk::Paint::default(),
+        );
+
+        let viewport = mapping.to_minimap().outer_transformed_rect(&self.viewport).round_out();
+        let canvas = ctx.surface.canvas();
+        canvas.draw_rect(viewport.to_skia(), &sk::Paint::new(VIEWPORT_FILL_COLOR.to_skia(), None));
+        let mut stroke = sk::Paint::new(VIEWPORT_STROKE_COLOR.to_skia(), None);
+        stroke.set_style(sk::PaintStyle::Stroke);
+        stroke.set_stroke_width((1.0 / scale_factor) as sk::scalar);
+        canvas.draw_rect(
+            viewport.inflate(-0.5 / scale_factor, -0.5 / scale_factor).to_skia(),
+            &stroke,
+        );
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new(format!("viewport: {:?}", self.viewport))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapping() {
+        let content = Size::new(1000.0, 10000.0);
+        let size = Size::new(100.0, 200.0);
+        let viewport = |y: f64| Rect::new(Point::new(0.0, y), Size::new(1000.0, 500.0));
+
+        // scaled to the width, and scrolled with the viewport
+        let mapping = Mapping::new(content, viewport(0.0), size, 0.2);
+        assert_eq!(
+            mapping,
+            Mapping {
+                scale: 0.1,
+                scroll: 0.0
+            }
+        );
+        let mapping = Mapping::new(content, viewport(9500.0), size, 0.2);
+        assert_eq!(
+            mapping,
+            Mapping {
+                scale: 0.1,
+                scroll: 800.0
+            }
+        );
+        assert_eq!(mapping.to_content(Point::new(50.0, 0.0)), Point::new(500.0, 8000.0));
+
+        // small contents: no scrolling
+        let mapping = Mapping::new(Size::new(100.0, 100.0), viewport(0.0), size, 0.2);
+        assert_eq!(
+            mapping,
+            Mapping {
+                scale: 0.2,
+                scroll: 0.0
+            }
+        );
+    }
+}
@@ -1,21 +1,36 @@
 //! built-in widgets.
+mod accessible;
 mod align;
+mod anchored_overlay;
+mod animated_layer;
 mod button;
+mod caret;
 mod clickable;
+mod clip;
+mod command_scope;
 mod constrained;
 //mod container;
 pub mod drop_down;
 mod flex;
+mod focus_policy;
+mod focus_scope;
+mod graph_editing;
 pub mod grid;
 mod image;
+mod input_zone;
 mod label;
 mod layout_wrapper;
 mod menu;
+mod menu_bar;
+mod minimap;
+mod node_canvas;
 mod null;
 mod padding;
 mod separator;
+pub mod settings;
 mod slider;
 mod text;
+pub mod text_bindings;
 mod text_edit;
 //mod text_v1;
 mod border;
@@ -25,77 +40,141 @@ mod canvas;
 mod env_override;
 mod formatter;
 mod frame;
+mod frame_debugger;
+mod frame_region;
+mod jank_monitor;
 mod popup;
+mod portal;
+mod press_and_hold;
+mod progress;
+mod radial_menu;
 mod scroll_area;
+mod scroll_linked;
 //mod selectable;
 mod checkbox;
 mod cursor;
+mod custom_cursor;
 mod debug;
+mod dialog;
+mod document_tabs;
 mod drag_drop;
 mod drawable;
 mod font_size;
 pub mod form;
 mod group_box;
+mod hover_intent;
 mod overlay;
+mod paste_special;
 mod placeholder;
 mod placement;
+mod plugin_widget;
+mod property_grid;
 mod shape;
 mod stateful;
 mod stepper;
 mod styled_box;
+mod subtree;
+mod tab_view;
 pub mod table;
+mod tappable;
 mod text_input;
+mod theme_provider;
 mod thumb;
 mod titled_pane;
+mod toolbar;
+mod tree_view;
+mod virtual_list;
 mod widget_pod;
+mod zoom_view;
 
 //pub use align::Align;
 //pub use baseline::Baseline;
+pub use accessible::Accessible;
+pub(crate) use anchored_overlay::Placement;
+pub use anchored_overlay::{AnchoredOverlay, Side};
+pub use animated_layer::AnimatedLayer;
 pub use border::Border;
 pub use button::Button;
 pub use canvas::{Canvas, Viewport};
 pub use checkbox::{Checkbox, CheckboxField};
 pub use clickable::Clickable;
+pub use clip::{ClipPath, ClipShape, Mask};
+pub use command_scope::CommandScope;
+pub use custom_cursor::CustomCursor;
 pub use debug::{Debug, DebugFlags, DebugName};
 pub use drawable::Drawable;
 //pub use color_picker::{ColorPaletteItem, ColorPicker, ColorPickerMode, ColorPickerParams, HsvColorSquare};
 //pub use constrained::ConstrainedBox;
 pub use drop_down::DropDown;
 pub use env_override::EnvOverride;
-pub use flex::{CrossAxisAlignment, Flex, MainAxisAlignment, MainAxisSize};
+pub use flex::{
+    CrossAxisAlignment, Flex, FlexInsertable, FlexItem, FlexItemExt, FlexWrap, MainAxisAlignment, MainAxisSize,
+};
+pub use focus_policy::FocusPolicy;
+pub use focus_scope::FocusScope;
 pub use form::Form;
 pub use formatter::{DisplayFormatter, FloatingPointNumberFormatter, Formatter, ValidationResult};
 pub use frame::Frame;
+pub use frame_debugger::FrameDebugger;
+pub use frame_region::FrameRegion;
 pub use grid::Grid;
+pub use hover_intent::HoverIntent;
 pub use image::{Image, Scaling};
+pub use input_zone::{InputZone, PointerEvents};
+pub use jank_monitor::JankMonitor;
 pub use label::Label;
 pub use placement::Adjacent;
+pub use property_grid::{PropertyDesc, PropertyGrid, PropertyKind, PropertySchema, PropertyValue};
 //pub use layer_widget::LayerWidget;
+pub use graph_editing::{
+    snap_to_nodes, EdgeDragController, Port, RubberBand, RubberBandSelection, SelectionMode, Snap,
+};
 pub use layout_wrapper::LayoutInspector;
-pub use menu::{Action, ContextMenu, Menu, MenuItem, Shortcut};
+pub use menu::{Action, ContextMenu, Menu, MenuItem, MenuItemDraw, MenuItemState, Shortcut};
+pub use menu_bar::MenuBar;
+pub use minimap::Minimap;
+pub use node_canvas::{Edge, EdgeRouting, Guide, NodeCanvas, NodeIndex};
 pub use null::Null;
 pub use padding::Padding;
 pub use popup::Popup;
+pub use portal::{with_portal_scope, Portal, PortalExit, PortalSlot};
+pub use progress::{ProgressBar, ProgressBarStyle, Spinner};
+pub use radial_menu::RadialMenu;
 pub use scroll_area::ScrollArea;
-pub use slider::SliderBase;
+pub use scroll_linked::{ScrollLinked, ScrollLinkedElement, ScrollPosition};
+pub use settings::{Setting, SettingsCategory, SettingsStore, SettingsView};
+pub use slider::{RangeSlider, Slider, SliderBase};
 pub use stepper::Stepper;
 pub use styled_box::StyledBox;
-pub use table::{TableSelection, TableView, TableViewParams};
+pub use subtree::Subtree;
+pub use tab_view::{Tab, TabView};
+pub use table::{TableColumnsState, TableSelection, TableView, TableViewParams};
+pub use tappable::Tappable;
 pub use text::Text;
 pub use text_edit::{BaseTextEdit, TextEdit, TextField};
+pub use theme_provider::{ThemeHandle, ThemeProvider};
 //pub use text_input::{StepperTextInput, TextInput};
-pub use drag_drop::DropTarget;
+pub use dialog::{Dialog, DialogHandle, DialogResult};
+pub use document_tabs::{Document, DocumentId, DocumentManager, DocumentTabs};
+pub use drag_drop::{DragSource, DropTarget};
 pub use group_box::GroupBox;
 pub use overlay::{Overlay, ZOrder};
+pub use paste_special::{PasteContents, PasteTransform, PasteTransforms, PASTE_TRANSFORMS};
 pub use placeholder::Placeholder;
+pub use plugin_widget::PluginWidget;
 pub use shape::Shape;
 pub use stateful::{Retained, RetainedWidget};
 pub use thumb::{DragController, Thumb};
 pub use titled_pane::TitledPane;
-pub use widget_pod::WidgetPod;
+pub use toolbar::{Toolbar, ToolbarItem};
+pub use tree_view::{TreeModel, TreeView};
+pub use virtual_list::{ScrollAlignment, VirtualList};
+pub use widget_pod::{Containment, WidgetPod};
+pub use zoom_view::ZoomView;
 
 use crate::{
-    composable,
+    accessibility::Semantics,
+    cache, composable,
     core::DebugNode,
     drawing::PaintCtx,
     layout::Alignment,
@@ -109,9 +188,9 @@ use crate::{
         font_size::FontSize,
     },
     Color, EnvKey, EnvValue, Environment, Event, EventCtx, Geometry, LayoutCtx, LayoutParams, Length,
-    LengthOrPercentage, UnitExt, Widget, WidgetId,
+    LengthOrPercentage, Transform, UnitExt, Widget, WidgetId,
 };
-use kyute_shell::{winit, TypedData};
+use kyute_shell::{window::WindowPart, winit, TypedData};
 use std::{
     convert::TryInto,
     fmt,
@@ -192,6 +271,10 @@ where
     fn debug_node(&self) -> DebugNode {
         self.0.debug_node()
     }
+
+    fn z_order(&self) -> i32 {
+        self.1.z_order()
+    }
 }
 
 /*impl<W> WidgetWrapper for Modified<(), W>
@@ -309,12 +392,75 @@ pub trait WidgetExt: Widget + Sized + 'static {
         )
     }
 
+    /// Clips the widget to a shape, with anti-aliased edges.
+    ///
+    /// The shape can be a `style::Shape` or a `ClipShape`, e.g. `ClipShape::ellipse()` for a circular avatar.
+    #[must_use]
+    #[composable]
+    fn clip_path(self, shape: impl Into<ClipShape>) -> ClipPath<Self> {
+        ClipPath::new(shape, self)
+    }
+
+    /// Masks the widget with the alpha channel of another widget, like an image or a gradient.
+    #[must_use]
+    #[composable]
+    fn mask<M: Widget + 'static>(self, mask: M) -> Mask<Self, M> {
+        Mask::new(mask, self)
+    }
+
     /// Changes the cursor icon when the mouse hovers the inner widget.
     #[must_use]
     fn cursor_icon(self, icon: winit::window::CursorIcon) -> CursorIcon<Self> {
         CursorIcon::new(self, icon)
     }
 
+    /// Replaces the pointer cursor with the specified widget when the mouse hovers the inner widget.
+    ///
+    /// See `CustomCursor`.
+    #[must_use]
+    #[composable]
+    fn custom_cursor<C: Widget + 'static>(self, cursor: C) -> CustomCursor<Self, C> {
+        CustomCursor::new(self, cursor)
+    }
+
+    /// Sets whether pointer events are delivered to this widget and its descendants.
+    #[must_use]
+    #[composable]
+    fn pointer_events(self, pointer_events: PointerEvents) -> InputZone<Self> {
+        InputZone::new(self).pointer_events(pointer_events)
+    }
+
+    /// Marks this widget as an input zone that only receives pointer input when `active` is true.
+    ///
+    /// When inactive, all pointer events pass through to the widgets below.
+    #[must_use]
+    #[composable]
+    fn input_zone(self, active: bool) -> InputZone<Self> {
+        InputZone::new(self).pointer_events(if active {
+            PointerEvents::Auto
+        } else {
+            PointerEvents::None
+        })
+    }
+
+    /// Overrides the hit-test priority of this widget.
+    ///
+    /// When the pointer is over this widget, pointer events are delivered exclusively to it
+    /// if no other zone under the pointer has a higher priority. See `InputZone`.
+    #[must_use]
+    #[composable]
+    fn hit_test_priority(self, priority: i32) -> InputZone<Self> {
+        InputZone::new(self).priority(priority)
+    }
+
+    /// Makes this widget act as a part of the window decorations, in windows with custom decorations.
+    ///
+    /// See `FrameRegion`.
+    #[must_use]
+    fn frame_region(self, part: WindowPart) -> FrameRegion<Self> {
+        FrameRegion::new(self, part)
+    }
+
     /// Assigns a debug name to a widget.
     #[must_use]
     fn debug_name(self, name: impl Into<String>) -> DebugName<Self> {
@@ -334,32 +480,45 @@ pub trait WidgetExt: Widget + Sized + 'static {
     }
 
     /// Sets the background paint of the widget.
+    ///
+    /// If the paint fails to parse, a warning is emitted and, in debug builds, the widget is marked with an error
+    /// badge. See `try_background` to handle the error instead.
     #[must_use]
     fn background(self, image: impl TryInto<style::Image>) -> Overlay<Self, Shape> {
-        let image = image.try_into().unwrap_or_else(|_| {
-            warn!("invalid CSS image value");
-            style::Image::default()
-        });
-        Overlay::new(self, Shape::new(style::Shape::rectangle(), image), ZOrder::Below)
+        let shape = match image.try_into() {
+            Ok(image) => Shape::new(style::Shape::rectangle(), image),
+            Err(_) => {
+                warn!("invalid CSS image value");
+                Shape::new(style::Shape::rectangle(), style::Image::default()).invalid()
+            }
+        };
+        Overlay::new(self, shape, ZOrder::Below)
+    }
+
+    /// Sets the background paint of the widget, or returns an error if the paint fails to parse.
+    fn try_background<I: TryInto<style::Image>>(self, image: I) -> Result<Overlay<Self, Shape>, I::Error> {
+        let image = image.try_into()?;
+        Ok(Overlay::new(
+            self,
+            Shape::new(style::Shape::rectangle(), image),
+            ZOrder::Below,
+        ))
     }
 
     /// Sets the background paint of the widget.
     #[must_use]
     fn rounded_background(self, image: impl TryInto<style::Image>, radius: impl Into<Length>) -> Overlay<Self, Shape> {
-        let image = image.try_into().unwrap_or_else(|_| {
-            warn!("invalid CSS image value");
-            style::Image::default()
-        });
-        Overlay::new(
-            self,
-            Shape::new(
-                style::Shape::RoundedRect {
-                    radii: [radius.into(); 4],
-                },
-                image,
-            ),
-            ZOrder::Below,
-        )
+        let shape = style::Shape::RoundedRect {
+            radii: [radius.into(); 4],
+        };
+        let shape = match image.try_into() {
+            Ok(image) => Shape::new(shape, image),
+            Err(_) => {
+                warn!("invalid CSS image value");
+                Shape::new(shape, style::Image::default()).invalid()
+            }
+        };
+        Overlay::new(self, shape, ZOrder::Below)
     }
 
     /// Sets the minimum width of the widget.
@@ -542,9 +701,37 @@ pub trait WidgetExt: Widget + Sized + 'static {
         Modified(Padding::new(top, right, bottom, left), self)
     }
 
+    /// Applies a style to the widget.
+    ///
+    /// If the style fails to parse, a warning is emitted and, in debug builds, the widget is marked with an error
+    /// badge. See `try_style` to handle the error instead.
+    #[must_use]
+    #[composable]
+    fn style<S>(self, style: S) -> StyledBox<Self>
+    where
+        S: TryInto<Style>,
+        S::Error: fmt::Display,
+    {
+        StyledBox::new(self, style)
+    }
+
+    /// Applies a style to the widget, or returns an error if the style fails to parse.
+    #[composable]
+    fn try_style<S: TryInto<Style>>(self, style: S) -> Result<StyledBox<Self>, S::Error> {
+        StyledBox::try_new(self, style)
+    }
+
+    /// Styles the widget with the rules of the current stylesheet that match the specified classes.
+    ///
+    /// `class` is a space-separated list of class names. The stylesheet is set with `style::with_stylesheet`;
+    /// classes that don't match any rule are ignored.
     #[must_use]
     #[composable]
-    fn style(self, style: impl TryInto<Style>) -> StyledBox<Self> {
+    fn class(self, class: &str) -> StyledBox<Self> {
+        let style = cache::environment()
+            .get(&style::STYLESHEET)
+            .map(|stylesheet| stylesheet.class_style(class))
+            .unwrap_or_default();
         StyledBox::new(self, style)
     }
 
@@ -557,6 +744,101 @@ pub trait WidgetExt: Widget + Sized + 'static {
         Clickable::new(self)
     }
 
+    /// Describes this widget to assistive technologies.
+    ///
+    /// See `Accessible`.
+    #[must_use]
+    fn accessible(self, semantics: Semantics) -> Accessible<Self> {
+        Accessible::new(self, semantics)
+    }
+
+    /// Overrides whether this widget can receive the keyboard focus.
+    ///
+    /// See `FocusPolicy`.
+    #[must_use]
+    fn focusable(self, focusable: bool) -> FocusPolicy<Self> {
+        FocusPolicy::new(self).focusable(focusable)
+    }
+
+    /// Excludes this widget from keyboard focus navigation (tab stops), but keeps it focusable by clicking on it.
+    ///
+    /// See `FocusPolicy`.
+    #[must_use]
+    fn skip_focus_chain(self, skip: bool) -> FocusPolicy<Self> {
+        FocusPolicy::new(self).skip_focus_chain(skip)
+    }
+
+    /// Sets the position of this widget in the `Tab` navigation order.
+    ///
+    /// See `Widget::tab_order` and `FocusPolicy`.
+    #[must_use]
+    fn tab_index(self, tab_index: i32) -> FocusPolicy<Self> {
+        FocusPolicy::new(self).tab_index(tab_index)
+    }
+
+    /// Shows this widget on its own compositor layer, so that its opacity and transform can be animated.
+    ///
+    /// See `AnimatedLayer`.
+    #[must_use]
+    #[composable]
+    fn animated_layer(self) -> AnimatedLayer<Self> {
+        AnimatedLayer::new(self)
+    }
+
+    /// Lets the user select items by dragging a rectangle over this widget.
+    ///
+    /// See `RubberBandSelection`.
+    #[must_use]
+    #[composable]
+    fn rubber_band_selection(self) -> RubberBandSelection<Self> {
+        RubberBandSelection::new(self)
+    }
+
+    /// Lets the user drag edges between the specified ports, over this widget.
+    ///
+    /// See `EdgeDragController`.
+    #[must_use]
+    #[composable]
+    fn edge_drag_controller<K: Clone + 'static>(self, ports: Vec<Port<K>>) -> EdgeDragController<Self, K> {
+        EdgeDragController::new(self, ports)
+    }
+
+    /// Detects when the pointer stays over this widget for some time without moving.
+    ///
+    /// See `HoverIntent`.
+    #[must_use]
+    #[composable]
+    fn hover_intent(self) -> HoverIntent<Self> {
+        HoverIntent::new(self)
+    }
+
+    /// Recognizes taps and long presses on this widget.
+    ///
+    /// See `Tappable`.
+    #[must_use]
+    #[composable]
+    fn tappable(self) -> Tappable<Self> {
+        Tappable::new(self)
+    }
+
+    /// Pins this widget to the top edge of the enclosing `ScrollArea` while its section is visible.
+    ///
+    /// See `ScrollLinked::sticky`.
+    #[must_use]
+    #[composable]
+    fn sticky(self) -> ScrollLinked {
+        ScrollLinked::sticky(self)
+    }
+
+    /// Transforms this widget according to the scroll position of the enclosing `ScrollArea`.
+    ///
+    /// See `ScrollLinked::effect`.
+    #[must_use]
+    #[composable]
+    fn scroll_effect(self, effect: impl Fn(&ScrollPosition) -> Transform + 'static) -> ScrollLinked {
+        ScrollLinked::effect(self, effect)
+    }
+
     /// Overrides an environment value.
     #[must_use]
     fn env_override<T: EnvValue>(self, key: EnvKey<T>, value: T) -> Modified<EnvironmentOverride<T>, Self> {
@@ -604,6 +886,27 @@ pub trait WidgetExt: Widget + Sized + 'static {
         Arc::new(WidgetPod::new(self))
     }
 
+    /// Wraps this widget in a `WidgetPod` with the specified containment hints.
+    ///
+    /// Use this to isolate parts of the UI that change often (e.g. an editor pane) from the rest of the tree,
+    /// so that relayouts within them don't propagate to ancestors.
+    #[must_use]
+    #[composable]
+    fn contain(self, containment: Containment) -> WidgetPod<Self> {
+        WidgetPod::new(self).contain(containment)
+    }
+
+    /// Wraps this widget in a `WidgetPod` with the specified z-index.
+    ///
+    /// Containers paint children with a higher z-index above the others, and deliver events to them first.
+    /// The z-index is kept when the pod is wrapped again, e.g. by `arc_dyn_pod` or when it's inserted in a
+    /// container. See `WidgetPod::z_index`.
+    #[must_use]
+    #[composable]
+    fn z_index(self, z_index: i32) -> WidgetPod<Self> {
+        WidgetPod::new(self).with_z_index(z_index)
+    }
+
     /// Applies the dark theme on the child widgets.
     #[must_use]
     #[composable]
@@ -636,6 +939,13 @@ pub trait WidgetExt: Widget + Sized + 'static {
     {
         DropTarget::new(self).on_drop(f)
     }
+
+    /// Lets the user drag this widget to start a drag and drop operation carrying the specified data.
+    #[must_use]
+    #[composable]
+    fn drag_source(self, data: Vec<TypedData>) -> DragSource<Self> {
+        DragSource::new(self, data)
+    }
 }
 
 impl<W: Widget + 'static> WidgetExt for W {}
@@ -647,8 +957,8 @@ pub mod prelude {
         composable,
         drawing::PaintCtx,
         widget::{WidgetExt, WidgetPod},
-        Alignment, BoxConstraints, DebugNode, Environment, Event, EventCtx, Geometry, LayoutCache, LayoutCtx,
-        LayoutParams, Length, Measurements, Offset, Orientation, Point, Rect, Size, Transform, UnitExt, Widget,
-        WidgetId,
+        Alignment, BoxConstraints, DebugNode, Environment, Event, EventCtx, Geometry, IntrinsicWidths, LayoutCache,
+        LayoutCtx, LayoutParams, Length, Measurements, Offset, Orientation, Point, Rect, Size, Transform, UnitExt,
+        Widget, WidgetId,
     };
 }
@@ -0,0 +1,449 @@
+//! Virtualized canvas for node graph editors.
+use crate::{
+    cache,
+    drawing::ToSkia,
+    style::WidgetState,
+    theme,
+    widget::{prelude::*, widget_pod::sort_by_z_index, ZoomView},
+    Color,
+};
+use parking_lot::Mutex;
+use skia_safe as sk;
+use std::{collections::HashMap, sync::Arc};
+
+/// Default size of the cells of a `NodeIndex`, in DIPs.
+const DEFAULT_CELL_SIZE: f64 = 256.0;
+/// Default width of edges, in DIPs.
+const EDGE_WIDTH: f64 = 1.5;
+/// Margin around the edges in the recorded edge picture, in DIPs.
+const EDGE_MARGIN: f64 = 8.0;
+/// Horizontal distance between ports and the first or last turn of orthogonal edges, in DIPs.
+const ORTHOGONAL_EDGE_MARGIN: f64 = 20.0;
+/// Width of snapping guides, in DIPs.
+const GUIDE_WIDTH: f64 = 1.0;
+
+/// Spatial index over the bounds of the nodes of a `NodeCanvas`.
+///
+/// Nodes are identified by their index of insertion. Internally, this is a uniform grid: each cell
+/// lists the nodes that overlap it.
+#[derive(Clone)]
+pub struct NodeIndex {
+    cell_size: f64,
+    bounds: Vec<Rect>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl NodeIndex {
+    /// Creates an empty index with the default cell size.
+    pub fn new() -> NodeIndex {
+        NodeIndex::with_cell_size(DEFAULT_CELL_SIZE)
+    }
+
+    /// Creates an empty index with the specified cell size.
+    ///
+    /// The cell size should be in the order of the size of the nodes.
+    pub fn with_cell_size(cell_size: f64) -> NodeIndex {
+        assert!(cell_size > 0.0, "invalid cell size");
+        NodeIndex {
+            cell_size,
+            bounds: vec![],
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of nodes in the index.
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    /// Returns whether the index is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Adds a node with the specified bounds, and returns its index.
+    pub fn insert(&mut self, bounds: Rect) -> usize {
+        let node = self.bounds.len();
+        self.bounds.push(bounds);
+        for cell in self.cells_in(&bounds) {
+            self.cells.entry(cell).or_default().push(node);
+        }
+        node
+    }
+
+    /// Returns the bounds of a node.
+    pub fn bounds(&self, node: usize) -> Rect {
+        self.bounds[node]
+    }
+
+    /// Updates the bounds of a node (e.g. when it's moved).
+    pub fn set_bounds(&mut self, node: usize, bounds: Rect) {
+        for cell in self.cells_in(&self.bounds[node]) {
+            if let Some(nodes) = self.cells.get_mut(&cell) {
+                nodes.retain(|&n| n != node);
+                if nodes.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+        self.bounds[node] = bounds;
+        for cell in self.cells_in(&bounds) {
+            self.cells.entry(cell).or_default().push(node);
+        }
+    }
+
+    /// Returns the nodes whose bounds intersect the specified rectangle, in ascending order.
+    pub fn query(&self, rect: &Rect) -> Vec<usize> {
+        let mut nodes = vec![];
+        for cell in self.cells_in(rect) {
+            if let Some(cell_nodes) = self.cells.get(&cell) {
+                nodes.extend(cell_nodes.iter().copied().filter(|&n| self.bounds[n].intersects(rect)));
+            }
+        }
+        nodes.sort_unstable();
+        nodes.dedup();
+        nodes
+    }
+
+    /// Returns the grid cells overlapped by a rectangle.
+    fn cells_in(&self, rect: &Rect) -> impl Iterator<Item = (i32, i32)> {
+        let x0 = (rect.min_x() / self.cell_size).floor() as i32;
+        let y0 = (rect.min_y() / self.cell_size).floor() as i32;
+        let x1 = (rect.max_x() / self.cell_size).floor() as i32;
+        let y1 = (rect.max_y() / self.cell_size).floor() as i32;
+        (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+    }
+}
+
+impl Default for NodeIndex {
+    fn default() -> Self {
+        NodeIndex::new()
+    }
+}
+
+/// How edges are routed between their end points.
+///
+/// Edges leave their start point towards the right, and enter their end point from the left.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EdgeRouting {
+    /// A bezier curve with horizontal tangents at both ends.
+    Bezier,
+    /// Horizontal and vertical segments.
+    Orthogonal,
+}
+
+impl Default for EdgeRouting {
+    fn default() -> Self {
+        EdgeRouting::Bezier
+    }
+}
+
+impl EdgeRouting {
+    /// Returns the control points of the bezier curve between `from` and `to`.
+    pub fn bezier_control_points(from: Point, to: Point) -> [Point; 4] {
+        let dx = 0.5 * (to.x - from.x).abs().max(40.0);
+        [from, Point::new(from.x + dx, from.y), Point::new(to.x - dx, to.y), to]
+    }
+
+    /// Returns the corners of the orthogonal route between `from` and `to`, including both end points.
+    pub fn orthogonal_route(from: Point, to: Point) -> Vec<Point> {
+        if to.x - from.x >= 2.0 * ORTHOGONAL_EDGE_MARGIN {
+            let mid_x = 0.5 * (from.x + to.x);
+            vec![from, Point::new(mid_x, from.y), Point::new(mid_x, to.y), to]
+        } else {
+            // the end point is behind the start point: go around
+            let mid_y = 0.5 * (from.y + to.y);
+            let x0 = from.x + ORTHOGONAL_EDGE_MARGIN;
+            let x1 = to.x - ORTHOGONAL_EDGE_MARGIN;
+            vec![
+                from,
+                Point::new(x0, from.y),
+                Point::new(x0, mid_y),
+                Point::new(x1, mid_y),
+                Point::new(x1, to.y),
+                to,
+            ]
+        }
+    }
+
+    /// Returns the path of an edge between `from` and `to`.
+    pub fn path(&self, from: Point, to: Point) -> sk::Path {
+        let mut path = sk::Path::new();
+        match *self {
+            EdgeRouting::Bezier => {
+                let [p0, p1, p2, p3] = Self::bezier_control_points(from, to);
+                path.move_to(p0.to_skia());
+                path.cubic_to(p1.to_skia(), p2.to_skia(), p3.to_skia());
+            }
+            EdgeRouting::Orthogonal => {
+                let route = Self::orthogonal_route(from, to);
+                path.move_to(route[0].to_skia());
+                for p in &route[1..] {
+                    path.line_to(p.to_skia());
+                }
+            }
+        }
+        path
+    }
+
+    /// Returns a rectangle that contains the path of an edge between `from` and `to`.
+    pub fn bounds(&self, from: Point, to: Point) -> Rect {
+        match *self {
+            EdgeRouting::Bezier => Rect::from_points(Self::bezier_control_points(from, to)),
+            EdgeRouting::Orthogonal => Rect::from_points(Self::orthogonal_route(from, to)),
+        }
+    }
+}
+
+/// A connection between two points of a `NodeCanvas`, usually ports of two nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edge {
+    /// Start point, in canvas coordinates.
+    pub from: Point,
+    /// End point, in canvas coordinates.
+    pub to: Point,
+    pub color: Color,
+    pub routing: EdgeRouting,
+}
+
+impl Edge {
+    pub fn new(from: Point, to: Point, color: Color) -> Edge {
+        Edge {
+            from,
+            to,
+            color,
+            routing: EdgeRouting::default(),
+        }
+    }
+
+    /// Sets how the edge is routed.
+    #[must_use]
+    pub fn routing(mut self, routing: EdgeRouting) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Returns the control points of the bezier curve between the end points of this edge.
+    pub fn bezier_control_points(&self) -> [Point; 4] {
+        EdgeRouting::bezier_control_points(self.from, self.to)
+    }
+
+    /// Returns a rectangle that contains the path of this edge.
+    pub fn bounds(&self) -> Rect {
+        self.routing.bounds(self.from, self.to)
+    }
+}
+
+/// A guide line shown on a `NodeCanvas`, e.g. when snapping a node to others (see `snap_to_nodes`).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Guide {
+    /// Start point, in canvas coordinates.
+    pub from: Point,
+    /// End point, in canvas coordinates.
+    pub to: Point,
+}
+
+/// Function that draws an edge.
+type EdgePainter = Arc<dyn Fn(&mut sk::Canvas, &Edge)>;
+
+/// Draws an edge along its route.
+pub(crate) fn paint_edge(canvas: &mut sk::Canvas, edge: &Edge) {
+    let path = edge.routing.path(edge.from, edge.to);
+    let mut paint = sk::Paint::new(edge.color.to_skia(), None);
+    paint.set_anti_alias(true);
+    paint.set_style(sk::PaintStyle::Stroke);
+    paint.set_stroke_width(EDGE_WIDTH as sk::scalar);
+    canvas.draw_path(&path, &paint);
+}
+
+/// Edges recorded in a picture.
+struct RecordedEdges {
+    edges: Vec<Edge>,
+    picture: sk::Picture,
+}
+
+struct CanvasNode {
+    bounds: Rect,
+    widget: WidgetPod,
+}
+
+/// An unbounded canvas of nodes, connected by edges.
+///
+/// Only the nodes that intersect the visible region of the canvas are composed and laid out, which makes it
+/// possible to have tens of thousands of nodes. The nodes are placed according to their bounds in a `NodeIndex`.
+/// Use `NodeCanvas::zoomable` to show the canvas in a scrollable and zoomable `ZoomView`.
+///
+/// Edges are all drawn below the nodes, in a single pass recorded once and replayed as long as the edges don't
+/// change. By default, edges are drawn along their route (see `EdgeRouting`); use `edge_painter` to draw them
+/// differently. The edge painter is expected to draw within `Edge::bounds`, and to depend only on the edge:
+/// changing the painter alone doesn't cause the edges to be redrawn.
+///
+/// Guide lines (see `guides`) are drawn above the nodes. For interactions, see `RubberBandSelection`,
+/// `EdgeDragController` and `snap_to_nodes`.
+pub struct NodeCanvas {
+    id: WidgetId,
+    nodes: Vec<CanvasNode>,
+    edges: Vec<Edge>,
+    edge_painter: Option<EdgePainter>,
+    recorded_edges: Arc<Mutex<Option<RecordedEdges>>>,
+    guides: Vec<Guide>,
+}
+
+impl NodeCanvas {
+    /// Creates a canvas that shows the nodes of `index` that intersect the `visible` region.
+    ///
+    /// `node` is called to create the widget of each visible node, with the index of the node.
+    #[composable]
+    pub fn new<W: Widget + 'static>(index: &NodeIndex, visible: Rect, mut node: impl FnMut(usize) -> W) -> NodeCanvas {
+        let nodes = index
+            .query(&visible)
+            .into_iter()
+            .map(|i| {
+                // the state of the node widgets follows the node, not its position in the visible set
+                cache::scoped(i, || CanvasNode {
+                    bounds: index.bounds(i),
+                    widget: WidgetPod::new(node(i)),
+                })
+            })
+            .collect();
+
+        NodeCanvas {
+            id: WidgetId::here(),
+            nodes,
+            edges: vec![],
+            edge_painter: None,
+            recorded_edges: cache::once(|| Arc::new(Mutex::new(None))),
+            guides: vec![],
+        }
+    }
+
+    /// Creates a canvas in a `ZoomView`, so that it can be scrolled and zoomed.
+    #[composable]
+    pub fn zoomable<W: Widget + 'static>(
+        index: &NodeIndex,
+        min_zoom: f64,
+        max_zoom: f64,
+        edges: Vec<Edge>,
+        node: impl FnMut(usize) -> W,
+    ) -> ZoomView<NodeCanvas> {
+        ZoomView::infinite(min_zoom, max_zoom, |visible| {
+            NodeCanvas::new(index, visible, node).edges(edges)
+        })
+    }
+
+    /// Sets the edges between nodes.
+    #[must_use]
+    pub fn edges(mut self, edges: Vec<Edge>) -> Self {
+        self.edges = edges;
+        self
+    }
+
+    /// Sets the function used to draw each edge, in canvas coordinates.
+    #[must_use]
+    pub fn edge_painter(mut self, painter: impl Fn(&mut sk::Canvas, &Edge) + 'static) -> Self {
+        self.edge_painter = Some(Arc::new(painter));
+        self
+    }
+
+    /// Sets the guide lines shown above the nodes.
+    #[must_use]
+    pub fn guides(mut self, guides: Vec<Guide>) -> Self {
+        self.guides = guides;
+        self
+    }
+
+    /// Returns the number of nodes that are currently composed.
+    pub fn visible_node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Records the edges in a picture.
+    fn record_edges(&self) -> Option<sk::Picture> {
+        if self.edges.is_empty() {
+            return None;
+        }
+
+        let bounds = self
+            .edges
+            .iter()
+            .map(Edge::bounds)
+            .reduce(|a, b| a.union(&b))
+            .unwrap()
+            .inflate(EDGE_MARGIN, EDGE_MARGIN);
+
+        let mut recorder = sk::PictureRecorder::new();
+        let canvas = recorder.begin_recording(bounds.to_skia(), None);
+        for edge in self.edges.iter() {
+            match self.edge_painter {
+                Some(ref painter) => painter(canvas, edge),
+                None => paint_edge(canvas, edge),
+            }
+        }
+        recorder.finish_recording_as_picture(None)
+    }
+}
+
+impl Widget for NodeCanvas {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        // nodes are laid out unconstrained, at their position in the canvas
+        let node_constraints = LayoutParams {
+            widget_state: WidgetState::default(),
+            scale_factor: constraints.scale_factor,
+            min: Size::zero(),
+            max: Size::new(f64::INFINITY, f64::INFINITY),
+        };
+        for node in self.nodes.iter() {
+            node.widget.layout(ctx, &node_constraints, env);
+            node.widget.set_offset(node.bounds.origin.to_vector());
+        }
+
+        // like `Canvas`, take the maximum available space
+        let width = constraints.finite_max_width().unwrap_or(0.0);
+        let height = constraints.finite_max_height().unwrap_or(0.0);
+        Geometry::new(Size::new(width, height))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        // topmost nodes first
+        for node in sort_by_z_index(&self.nodes, |node| node.widget.z_index()).rev() {
+            node.widget.route_event(ctx, event, env);
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        {
+            let mut recorded_edges = self.recorded_edges.lock();
+            let up_to_date = match *recorded_edges {
+                Some(ref recorded) => recorded.edges == self.edges,
+                None => self.edges.is_empty(),
+            };
+            if !up_to_date {
+                *recorded_edges = self.record_edges().map(|picture| RecordedEdges {
+                    edges: self.edges.clone(),
+                    picture,
+                });
+            }
+            if let Some(ref recorded) = *recorded_edges {
+                ctx.surface.canvas().draw_picture(&recorded.picture, None, None);
+            }
+        }
+
+        for node in sort_by_z_index(&self.nodes, |node| node.widget.z_index()) {
+            node.widget.paint(ctx);
+        }
+
+        if !self.guides.is_empty() {
+            let mut paint = sk::Paint::new(theme::palette::PINK_500.to_skia(), None);
+            paint.set_style(sk::PaintStyle::Stroke);
+            paint.set_stroke_width(GUIDE_WIDTH as sk::scalar);
+            let canvas = ctx.surface.canvas();
+            for guide in self.guides.iter() {
+                canvas.draw_line(guide.from.to_skia(), guide.to.to_skia(), &paint);
+            }
+        }
+    }
+}
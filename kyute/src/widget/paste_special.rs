@@ -0,0 +1,205 @@
+//! Transformations offered by "paste special" in text editors.
+use crate::{EnvKey, Environment};
+use kyute_shell::clipboard;
+use std::{fmt, sync::Arc};
+
+/// Transformations offered by text editors on "paste special" (see `TextEditAction::PasteSpecial`).
+pub const PASTE_TRANSFORMS: EnvKey<PasteTransforms> = builtin_env_key!("kyute.paste-transforms");
+
+/// Contents of the clipboard, passed to paste transformations.
+#[derive(Clone, Debug, Default)]
+pub struct PasteContents {
+    /// Plain text (`text/plain`).
+    pub text: Option<String>,
+    /// HTML fragment (`text/html`), without the clipboard headers.
+    pub html: Option<String>,
+}
+
+impl PasteContents {
+    /// Reads the current contents of the clipboard.
+    pub fn from_clipboard() -> PasteContents {
+        PasteContents {
+            text: clipboard::text(),
+            html: clipboard::html(),
+        }
+    }
+
+    /// Returns the plain text, or the text content of the HTML fragment if there's no plain text.
+    pub fn plain_text(&self) -> Option<String> {
+        self.text.clone().or_else(|| self.html.as_deref().map(html_to_text))
+    }
+}
+
+/// Converts HTML to text: removes the tags, ends lines at block elements, separates table cells with tabs,
+/// and decodes the common character references.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => {
+                // unterminated tag
+                rest = "";
+                break;
+            }
+        };
+        let tag = rest[start + 1..end].trim().to_ascii_lowercase();
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .find(|s| !s.is_empty());
+        match (tag.starts_with('/'), name) {
+            (_, Some("br")) | (true, Some("p" | "div" | "tr" | "li" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6")) => {
+                text.push('\n')
+            }
+            (true, Some("td" | "th")) => text.push('\t'),
+            _ => {}
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+
+    text.replace("&nbsp;", "\u{a0}")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Removes invisible formatting characters (zero-width and bidirectional controls, soft hyphens), replaces
+/// non-breaking spaces with spaces, collapses runs of spaces and tabs, and trims each line.
+fn strip_formatting(text: &str) -> String {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let line: String = line
+                .chars()
+                .filter(|c| {
+                    !matches!(c, '\u{ad}' | '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{2069}' | '\u{feff}')
+                })
+                .map(|c| if c == '\u{a0}' { ' ' } else { c })
+                .collect();
+            line.split_whitespace().collect::<Vec<_>>().join(" ")
+        })
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Extracts the values of tabular data (as copied from spreadsheets: cells separated by tabs, one row per line):
+/// each cell is stripped of formatting and unquoted, empty cells are dropped, and the values are separated by spaces.
+fn values_only(text: &str) -> String {
+    let rows: Vec<String> = text
+        .lines()
+        .map(|row| {
+            row.split('\t')
+                .map(|cell| {
+                    let cell = strip_formatting(cell);
+                    cell.strip_prefix('"')
+                        .and_then(|cell| cell.strip_suffix('"'))
+                        .map(|cell| cell.replace("\"\"", "\""))
+                        .unwrap_or_else(|| cell.clone())
+                })
+                .filter(|cell| !cell.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|row| !row.is_empty())
+        .collect();
+    rows.join("\n")
+}
+
+type TransformFn = Arc<dyn Fn(&PasteContents) -> Option<String> + Send + Sync>;
+
+/// A named transformation of the clipboard contents, that returns the text to paste.
+#[derive(Clone)]
+pub struct PasteTransform {
+    name: String,
+    transform: TransformFn,
+}
+
+impl fmt::Debug for PasteTransform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PasteTransform").field(&self.name).finish()
+    }
+}
+
+impl PasteTransform {
+    /// Creates a transformation. `transform` returns `None` if it doesn't apply to the clipboard contents.
+    pub fn new(
+        name: impl Into<String>,
+        transform: impl Fn(&PasteContents) -> Option<String> + Send + Sync + 'static,
+    ) -> PasteTransform {
+        PasteTransform {
+            name: name.into(),
+            transform: Arc::new(transform),
+        }
+    }
+
+    /// Returns the name of the transformation, displayed in the menu.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Applies the transformation.
+    pub fn apply(&self, contents: &PasteContents) -> Option<String> {
+        (self.transform)(contents)
+    }
+}
+
+/// The list of transformations offered by "paste special".
+///
+/// Set in the environment with the `PASTE_TRANSFORMS` key to provide custom transformations. The default contains:
+/// - "Plain text": the plain text on the clipboard, or the text content of HTML data;
+/// - "Without formatting": the plain text without invisible formatting characters and repeated whitespace;
+/// - "Values only": the values of tabular data (e.g. cells copied from a spreadsheet), separated by spaces.
+#[derive(Clone, Debug)]
+pub struct PasteTransforms(Arc<Vec<PasteTransform>>);
+
+impl_env_value!(PasteTransforms);
+
+impl Default for PasteTransforms {
+    fn default() -> Self {
+        PasteTransforms::new()
+            .add("Plain text", PasteContents::plain_text)
+            .add("Without formatting", |contents| {
+                contents.plain_text().map(|text| strip_formatting(&text))
+            })
+            .add("Values only", |contents| {
+                contents.plain_text().map(|text| values_only(&text))
+            })
+    }
+}
+
+impl PasteTransforms {
+    /// Creates an empty list of transformations.
+    pub fn new() -> PasteTransforms {
+        PasteTransforms(Arc::new(Vec::new()))
+    }
+
+    /// Adds a transformation to the list.
+    pub fn add(
+        mut self,
+        name: impl Into<String>,
+        transform: impl Fn(&PasteContents) -> Option<String> + Send + Sync + 'static,
+    ) -> PasteTransforms {
+        Arc::make_mut(&mut self.0).push(PasteTransform::new(name, transform));
+        self
+    }
+
+    /// Returns the transformation at the specified index.
+    pub fn get(&self, index: usize) -> Option<&PasteTransform> {
+        self.0.get(index)
+    }
+
+    /// Returns an iterator over the transformations.
+    pub fn iter(&self) -> impl Iterator<Item = &PasteTransform> {
+        self.0.iter()
+    }
+
+    /// Returns the transformations in the environment, or the default ones.
+    pub(crate) fn resolve_in_env(env: &Environment) -> PasteTransforms {
+        env.get(&PASTE_TRANSFORMS).unwrap_or_default()
+    }
+}
@@ -0,0 +1,459 @@
+//! Widgets provided by plugins.
+use crate::{
+    api, cache,
+    drawing::ToSkia,
+    event::{Modifiers, PointerEventKind, WheelDeltaMode},
+    plugin::Plugin,
+    widget::prelude::*,
+};
+use skia_safe as sk;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ffi::c_void,
+    ptr,
+    rc::Rc,
+};
+
+/// Height of a line, for wheel events that scroll by lines.
+const WHEEL_LINE_HEIGHT_DIP: f64 = 20.0;
+
+/// The phase in which the host functions are called.
+enum Phase<'a, 'b> {
+    Layout(&'a mut LayoutCtx),
+    Event(&'a mut EventCtx<'b>),
+    Paint,
+}
+
+/// Context of the host functions (see `HOST_VTABLE`).
+struct HostCtx<'a, 'b> {
+    phase: Phase<'a, 'b>,
+    instance: &'a Instance,
+}
+
+unsafe fn host_ctx<'a>(ctx: *mut c_void) -> &'a mut HostCtx<'a, 'a> {
+    &mut *(ctx as *mut HostCtx)
+}
+
+unsafe extern "C" fn scale_factor(ctx: *mut c_void) -> f64 {
+    host_ctx(ctx).instance.scale_factor.get()
+}
+
+unsafe extern "C" fn request_repaint(ctx: *mut c_void) {
+    match host_ctx(ctx).phase {
+        Phase::Layout(ref mut ctx) => ctx.request_repaint(),
+        Phase::Event(ref mut ctx) => ctx.request_repaint(),
+        Phase::Paint => {}
+    }
+}
+
+unsafe extern "C" fn request_relayout(ctx: *mut c_void) {
+    if let Phase::Event(ref mut ctx) = host_ctx(ctx).phase {
+        ctx.request_relayout();
+    }
+}
+
+unsafe extern "C" fn set_handled(ctx: *mut c_void) {
+    if let Phase::Event(ref mut ctx) = host_ctx(ctx).phase {
+        ctx.set_handled();
+    }
+}
+
+unsafe extern "C" fn capture_pointer(ctx: *mut c_void) {
+    if let Phase::Event(ref mut ctx) = host_ctx(ctx).phase {
+        ctx.capture_pointer();
+    }
+}
+
+unsafe extern "C" fn release_pointer(ctx: *mut c_void) {
+    if let Phase::Event(ref mut ctx) = host_ctx(ctx).phase {
+        ctx.release_pointer();
+    }
+}
+
+unsafe extern "C" fn load_state(
+    ctx: *mut c_void,
+    key: api::Str,
+    reader: *mut c_void,
+    read: unsafe extern "C" fn(reader: *mut c_void, value: api::Slice<u8>),
+) -> bool {
+    let state = host_ctx(ctx).instance.state.borrow();
+    if let Some(value) = state.get(key.as_str()) {
+        read(reader, api::Slice::new(value));
+        true
+    } else {
+        false
+    }
+}
+
+unsafe extern "C" fn store_state(ctx: *mut c_void, key: api::Str, value: api::Slice<u8>) {
+    let ctx = host_ctx(ctx);
+    ctx.instance
+        .state
+        .borrow_mut()
+        .insert(key.as_str().to_string(), value.as_slice().to_vec());
+    if let Phase::Event(ref mut ctx) = ctx.phase {
+        ctx.request_relayout();
+        ctx.request_repaint();
+    }
+}
+
+static HOST_VTABLE: api::HostVTable = api::HostVTable {
+    header: api::VTableHeader::CURRENT,
+    scale_factor,
+    request_repaint,
+    request_relayout,
+    set_handled,
+    capture_pointer,
+    release_pointer,
+    load_state,
+    store_state,
+};
+
+unsafe fn canvas<'a>(ctx: *mut c_void) -> &'a mut sk::Canvas {
+    &mut *(ctx as *mut sk::Canvas)
+}
+
+fn to_skia_rect(rect: api::Rect) -> sk::Rect {
+    sk::Rect::from_xywh(
+        rect.origin.x as f32,
+        rect.origin.y as f32,
+        rect.size.width as f32,
+        rect.size.height as f32,
+    )
+}
+
+fn to_skia_paint(color: api::Color) -> sk::Paint {
+    let color = sk::Color4f::new(color.red, color.green, color.blue, color.alpha);
+    let mut paint = sk::Paint::new(color, None);
+    paint.set_anti_alias(true);
+    paint
+}
+
+unsafe extern "C" fn save(ctx: *mut c_void) {
+    canvas(ctx).save();
+}
+
+unsafe extern "C" fn restore(ctx: *mut c_void) {
+    canvas(ctx).restore();
+}
+
+unsafe extern "C" fn translate(ctx: *mut c_void, x: f64, y: f64) {
+    canvas(ctx).translate((x as f32, y as f32));
+}
+
+unsafe extern "C" fn clip_rect(ctx: *mut c_void, rect: api::Rect) {
+    canvas(ctx).clip_rect(to_skia_rect(rect), None, Some(true));
+}
+
+unsafe extern "C" fn fill_rect(ctx: *mut c_void, rect: api::Rect, color: api::Color) {
+    canvas(ctx).draw_rect(to_skia_rect(rect), &to_skia_paint(color));
+}
+
+unsafe extern "C" fn stroke_rect(ctx: *mut c_void, rect: api::Rect, width: f64, color: api::Color) {
+    let mut paint = to_skia_paint(color);
+    paint.set_style(sk::PaintStyle::Stroke);
+    paint.set_stroke_width(width as f32);
+    canvas(ctx).draw_rect(to_skia_rect(rect), &paint);
+}
+
+unsafe extern "C" fn fill_rounded_rect(ctx: *mut c_void, rect: api::Rect, radius: f64, color: api::Color) {
+    let rrect = sk::RRect::new_rect_xy(to_skia_rect(rect), radius as f32, radius as f32);
+    canvas(ctx).draw_rrect(rrect, &to_skia_paint(color));
+}
+
+unsafe extern "C" fn draw_line(ctx: *mut c_void, from: api::Point, to: api::Point, width: f64, color: api::Color) {
+    let mut paint = to_skia_paint(color);
+    paint.set_style(sk::PaintStyle::Stroke);
+    paint.set_stroke_width(width as f32);
+    canvas(ctx).draw_line((from.x as f32, from.y as f32), (to.x as f32, to.y as f32), &paint);
+}
+
+unsafe extern "C" fn draw_text(
+    ctx: *mut c_void,
+    text: api::Str,
+    baseline_origin: api::Point,
+    size: f64,
+    color: api::Color,
+) {
+    let mut font = sk::Font::default();
+    font.set_size(size as f32);
+    canvas(ctx).draw_str(
+        text.as_str(),
+        (baseline_origin.x as f32, baseline_origin.y as f32),
+        &font,
+        &to_skia_paint(color),
+    );
+}
+
+static CANVAS_VTABLE: api::CanvasVTable = api::CanvasVTable {
+    header: api::VTableHeader::CURRENT,
+    save,
+    restore,
+    translate,
+    clip_rect,
+    fill_rect,
+    stroke_rect,
+    fill_rounded_rect,
+    draw_line,
+    draw_text,
+};
+
+fn to_api_modifiers(modifiers: Modifiers) -> api::Modifiers {
+    let mut result = 0;
+    for (modifier, bit) in [
+        (Modifiers::SHIFT, api::Modifiers::SHIFT),
+        (Modifiers::CONTROL, api::Modifiers::CONTROL),
+        (Modifiers::ALT, api::Modifiers::ALT),
+        (Modifiers::META, api::Modifiers::META),
+    ] {
+        if modifiers.contains(modifier) {
+            result |= bit.0;
+        }
+    }
+    api::Modifiers(result)
+}
+
+/// Converts an event to its equivalent in the plugin API, if there's one.
+///
+/// Events added in later minor versions of the API must only be sent to widgets whose vtable supports them.
+fn to_api_event(event: &Event) -> Option<api::Event> {
+    match event {
+        Event::Pointer(p) => {
+            let kind = match p.kind {
+                PointerEventKind::PointerDown => api::PointerEventKind::PointerDown,
+                PointerEventKind::PointerUp => api::PointerEventKind::PointerUp,
+                PointerEventKind::PointerMove => api::PointerEventKind::PointerMove,
+                PointerEventKind::PointerOver => api::PointerEventKind::PointerEnter,
+                PointerEventKind::PointerOut => api::PointerEventKind::PointerExit,
+                _ => return None,
+            };
+            Some(api::Event::Pointer(api::PointerEvent {
+                kind,
+                position: api::Point::new(p.position.x, p.position.y),
+                modifiers: to_api_modifiers(p.modifiers),
+                buttons: api::PointerButtons(p.buttons.0),
+                button: p
+                    .button
+                    .map_or(api::PointerButton::NONE, |button| api::PointerButton(button.0)),
+                repeat_count: p.repeat_count,
+            }))
+        }
+        Event::Wheel(wheel) => {
+            let (delta_x, delta_y) = match wheel.delta_mode {
+                WheelDeltaMode::Pixel => (wheel.delta_x, wheel.delta_y),
+                WheelDeltaMode::Line => (
+                    WHEEL_LINE_HEIGHT_DIP * wheel.delta_x,
+                    WHEEL_LINE_HEIGHT_DIP * wheel.delta_y,
+                ),
+                WheelDeltaMode::Page => return None,
+            };
+            Some(api::Event::Wheel(api::WheelEvent {
+                position: api::Point::new(wheel.pointer.position.x, wheel.pointer.position.y),
+                delta_x,
+                delta_y,
+                modifiers: to_api_modifiers(wheel.pointer.modifiers),
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// The plugin widget, and the state that it stores in the host.
+struct Instance {
+    /// The widget, with the factory and the properties it was created with.
+    object: RefCell<Option<(&'static api::WidgetFactory, String, api::WidgetObject)>>,
+    state: RefCell<HashMap<String, Vec<u8>>>,
+    /// Scale factor at the last layout.
+    scale_factor: Cell<f64>,
+}
+
+impl Instance {
+    /// Calls `f` with the plugin widget, and the host functions for the specified phase.
+    fn with_host<R>(&self, phase: Phase, f: impl FnOnce(&mut api::WidgetObject, &api::Host) -> R) -> R {
+        let mut object = self.object.borrow_mut();
+        let (_, _, object) = object.as_mut().unwrap();
+        let mut ctx = HostCtx { phase, instance: self };
+        // SAFETY: `ctx` outlives `host`
+        let host = unsafe { api::Host::from_raw(&mut ctx as *mut HostCtx as *mut c_void, &HOST_VTABLE) };
+        f(object, &host)
+    }
+}
+
+/// Shows a widget provided by a plugin (see `plugin`).
+///
+/// The widget is recreated when its properties change. The state that it stores in the host (see `api::Host`) is
+/// kept as long as the `PluginWidget` stays in the UI.
+pub struct PluginWidget {
+    id: WidgetId,
+    instance: Rc<Instance>,
+}
+
+impl PluginWidget {
+    /// Creates the widget named `name` of a plugin, with the specified properties, in the format defined by the plugin.
+    ///
+    /// Returns `None` if the plugin doesn't have a widget with this name.
+    #[composable]
+    pub fn new(plugin: &Plugin, name: &str, props: &str) -> Option<PluginWidget> {
+        let instance = cache::once(|| {
+            Rc::new(Instance {
+                object: RefCell::new(None),
+                state: RefCell::new(HashMap::new()),
+                scale_factor: Cell::new(1.0),
+            })
+        });
+
+        let factory = match plugin.widget_factory(name) {
+            Some(factory) => factory,
+            None => {
+                warn!("plugin `{}` has no widget named `{}`", plugin.name(), name);
+                return None;
+            }
+        };
+
+        let mut object = instance.object.borrow_mut();
+        let up_to_date = matches!(&*object, Some((f, p, _)) if ptr::eq(*f, factory) && p == props);
+        if !up_to_date {
+            // drop the previous widget first
+            *object = None;
+            let widget = unsafe { (factory.create)(api::Str::new(props)) };
+            *object = Some((factory, props.to_string(), widget));
+        }
+        drop(object);
+
+        Some(PluginWidget {
+            id: WidgetId::here(),
+            instance,
+        })
+    }
+}
+
+impl Widget for PluginWidget {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, _env: &Environment) -> Geometry {
+        self.instance.scale_factor.set(ctx.scale_factor);
+        let constraints = api::Constraints {
+            min: api::Size::new(constraints.min.width, constraints.min.height),
+            max: api::Size::new(constraints.max.width, constraints.max.height),
+        };
+        let size = self
+            .instance
+            .with_host(Phase::Layout(ctx), |object, host| object.layout(host, constraints));
+        Geometry::new(Size::new(size.width, size.height))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, _env: &Environment) {
+        if let Some(event) = to_api_event(event) {
+            self.instance
+                .with_host(Phase::Event(ctx), |object, host| object.event(host, &event));
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let bounds = ctx.bounds;
+        let size = api::Size::new(bounds.size.width, bounds.size.height);
+        let canvas = ctx.surface.canvas();
+        // restore the canvas even if the plugin doesn't
+        let save_count = canvas.save();
+        canvas.translate(bounds.origin.to_skia());
+        // SAFETY: the canvas outlives the handle
+        let mut api_canvas = unsafe { api::Canvas::from_raw(canvas as *mut sk::Canvas as *mut c_void, &CANVAS_VTABLE) };
+        self.instance
+            .with_host(Phase::Paint, |object, host| object.paint(host, &mut api_canvas, size));
+        ctx.surface.canvas().restore_to_count(save_count);
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        let object = self.instance.object.borrow();
+        match &*object {
+            Some((factory, _, _)) => DebugNode::new(format!("plugin widget `{}`", factory.name.as_str())),
+            None => DebugNode::new("plugin widget"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{noop_waker, Cache};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Square widget whose side is given by its properties, and which counts its layouts in the host state.
+    struct Square(f64);
+
+    impl api::Widget for Square {
+        fn layout(&mut self, host: &api::Host, constraints: api::Constraints) -> api::Size {
+            let layouts = host.load_state("layouts").map_or(0, |value| value[0]) + 1;
+            host.store_state("layouts", &[layouts]);
+            let side = self.0 * host.scale_factor();
+            constraints.constrain(api::Size::new(side, side + layouts as f64))
+        }
+
+        fn event(&mut self, _host: &api::Host, _event: &api::Event) {}
+
+        fn paint(&mut self, _host: &api::Host, _canvas: &mut api::Canvas, _size: api::Size) {}
+    }
+
+    impl Drop for Square {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn square(props: &str) -> api::WidgetObject {
+        api::WidgetObject::new(Square(props.parse().unwrap()))
+    }
+
+    api::export_plugin!("test-plugin", ["square" => square]);
+
+    fn layout(widget: &PluginWidget, env: &Environment) -> Size {
+        let mut ctx = LayoutCtx::new(2.0);
+        let constraints = LayoutParams {
+            scale_factor: 2.0,
+            min: Size::zero(),
+            max: Size::new(100.0, 100.0),
+            ..Default::default()
+        };
+        widget.layout(&mut ctx, &constraints, env).measurements.size
+    }
+
+    #[test]
+    fn widget_object_round_trip() {
+        let plugin = unsafe { Plugin::from_entry(kyute_plugin_entry) }.unwrap();
+        assert_eq!(plugin.name(), "test-plugin");
+        assert_eq!(plugin.widget_names().collect::<Vec<_>>(), ["square"]);
+
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let props = RefCell::new("10");
+        let ui = || PluginWidget::new(&plugin, "square", *props.borrow());
+
+        let widget = cache.recompose(&env, ui).unwrap();
+        assert_eq!(layout(&widget, &env), Size::new(20.0, 21.0));
+
+        // same properties: the plugin widget and its state are kept
+        let widget = cache.recompose(&env, ui).unwrap();
+        assert_eq!(layout(&widget, &env), Size::new(20.0, 22.0));
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+
+        // new properties: the plugin widget is recreated, the state stored in the host is kept
+        props.replace("30");
+        let widget = cache.recompose(&env, ui).unwrap();
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+        assert_eq!(layout(&widget, &env), Size::new(60.0, 63.0));
+
+        drop(widget);
+        drop(cache);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+
+        let mut cache = Cache::new(noop_waker());
+        assert!(cache
+            .recompose(&env, || PluginWidget::new(&plugin, "unknown", ""))
+            .is_none());
+    }
+}
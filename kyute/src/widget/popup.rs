@@ -1,11 +1,31 @@
-use crate::{cache, widget::prelude::*, Window};
-use kyute_shell::winit::window::WindowBuilder;
+use crate::{
+    cache,
+    widget::{prelude::*, Placement, Side},
+    RectI, Window,
+};
+use kyute_shell::winit::{dpi::LogicalSize, window::WindowBuilder};
+use std::{cell::Cell, sync::Arc};
 
 /// Pop-up window with contents.
+///
+/// An anchored popup (see `Popup::anchored`) is positioned next to its anchor widget on the desktop:
+/// it is placed on one side of the anchor, flipped to the opposite side if it would overflow the work area of
+/// the monitor, and shifted along the side to stay inside it.
+///
+/// Popups can be nested: a popup in the contents of another popup is anchored relative to the window
+/// of the enclosing popup, and is owned by it (e.g. a menu opened from a combo box list).
 #[derive(Clone)]
 pub struct Popup {
     id: WidgetId,
     shown: cache::State<bool>,
+    anchor: Option<Arc<WidgetPod>>,
+    /// Bounds of the anchor in local coordinates, as of the last layout.
+    anchor_bounds: Cell<Option<Rect>>,
+    /// Bounds of the anchor in physical pixels relative to the desktop, as of the last event.
+    ///
+    /// Kept across recompositions: the popup window is created before the new anchor is laid out.
+    anchor_rect: cache::State<Option<RectI>>,
+    placement: Placement,
     window: Option<Window>,
 }
 
@@ -13,10 +33,38 @@ impl Popup {
     /// Creates a new popup window.
     #[composable]
     pub fn new(content: impl Widget + 'static) -> Popup {
+        Self::new_inner(
+            None,
+            content,
+            WindowBuilder::new().with_decorations(false),
+            Side::Bottom,
+        )
+    }
+
+    /// Creates a popup window of the specified size, shown on the specified side of `anchor`.
+    ///
+    /// The popup takes the place of the anchor in the layout.
+    #[composable]
+    pub fn anchored(anchor: impl Widget + 'static, content: impl Widget + 'static, size: Size, side: Side) -> Popup {
+        let window_builder = WindowBuilder::new()
+            .with_decorations(false)
+            .with_resizable(false)
+            .with_inner_size(LogicalSize::new(size.width, size.height));
+        Self::new_inner(Some(Arc::new(WidgetPod::new(anchor))), content, window_builder, side)
+    }
+
+    #[composable]
+    fn new_inner(
+        anchor: Option<Arc<WidgetPod>>,
+        content: impl Widget + 'static,
+        window_builder: WindowBuilder,
+        side: Side,
+    ) -> Popup {
         let shown = cache::state(|| false);
+        let anchor_rect = cache::state(|| None);
 
         let window = if shown.get() {
-            Some(Window::new(WindowBuilder::new().with_decorations(false), content, None))
+            Some(Window::new(window_builder, content, None))
         } else {
             None
         };
@@ -24,16 +72,83 @@ impl Popup {
         Popup {
             id: WidgetId::here(),
             shown,
+            anchor,
+            anchor_bounds: Cell::new(None),
+            anchor_rect,
+            placement: Placement::new(side),
             window,
         }
     }
 
+    /// Sets the alignment of the popup along the side of the anchor.
+    ///
+    /// `Alignment::START` aligns the left (or top) edges of the popup and the anchor, `Alignment::END` the right
+    /// (or bottom) edges. Baseline alignments are treated as `Alignment::START`.
+    #[must_use]
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.placement.align = match alignment {
+            Alignment::Relative(align) => align,
+            _ => 0.0,
+        };
+        self
+    }
+
+    /// Sets the space between the anchor and the popup, in DIPs.
+    #[must_use]
+    pub fn gap(mut self, gap: f64) -> Self {
+        self.placement.gap = gap;
+        self
+    }
+
+    /// Sets an additional offset applied to the position of the popup, in DIPs.
+    #[must_use]
+    pub fn offset(mut self, offset: Offset) -> Self {
+        self.placement.offset = offset;
+        self
+    }
+
+    /// Sets whether the popup is moved to the opposite side of the anchor if it would overflow the screen.
+    #[must_use]
+    pub fn flip(mut self, flip: bool) -> Self {
+        self.placement.flip = flip;
+        self
+    }
+
+    /// Sets whether the popup is moved along the side of the anchor to stay on the screen.
+    #[must_use]
+    pub fn shift(mut self, shift: bool) -> Self {
+        self.placement.shift = shift;
+        self
+    }
+
     /// Shows the popup.
     #[composable]
     pub fn show(&self) {
         // will trigger a recomp
         self.shown.set(true);
     }
+
+    /// Hides the popup.
+    pub fn hide(&self) {
+        self.shown.set(false);
+    }
+
+    /// Returns whether the popup is shown.
+    pub fn is_shown(&self) -> bool {
+        self.window.is_some()
+    }
+
+    /// Tracks the position of the anchor on the desktop.
+    fn update_anchor_rect(&self, ctx: &EventCtx) {
+        if let Some(bounds) = self.anchor_bounds.get() {
+            if let Some(rect) = ctx.local_to_screen(&bounds) {
+                if self.anchor_rect.get() != Some(rect) {
+                    // the popup window is moved directly, no need to recompose
+                    self.anchor_rect.set_without_invalidation(Some(rect));
+                }
+            }
+        }
+    }
 }
 
 impl Widget for Popup {
@@ -41,17 +156,33 @@ impl Widget for Popup {
         Some(self.id)
     }
 
-    fn layout(&self, _ctx: &mut LayoutCtx, _constraints: &LayoutParams, _env: &Environment) -> Geometry {
-        Geometry::default()
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        if let Some(ref anchor) = self.anchor {
+            let geometry = anchor.layout(ctx, constraints, env);
+            self.anchor_bounds.set(Some(geometry.measurements.local_bounds()));
+            geometry
+        } else {
+            Geometry::default()
+        }
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Some(ref anchor) = self.anchor {
+            self.update_anchor_rect(ctx);
+            anchor.route_event(ctx, event, env);
+        }
         if let Some(ref window) = self.window {
+            if let Some(anchor_rect) = self.anchor_rect.get() {
+                window.set_popup_anchor(anchor_rect, self.placement);
+            }
             window.route_event(ctx, event, env);
         }
     }
 
-    fn paint(&self, _ctx: &mut PaintCtx) {
-        // nothing to paint
+    fn paint(&self, ctx: &mut PaintCtx) {
+        // the contents are in a separate window
+        if let Some(ref anchor) = self.anchor {
+            anchor.paint(ctx);
+        }
     }
 }
@@ -0,0 +1,378 @@
+//! Portals: widgets composed in one part of the UI and shown in another.
+//!
+//! Target names are scoped: portals only reach the portal exits composed within the same `with_portal_scope` call,
+//! so that several windows (or several instances of a component) can use the same target names without mixing
+//! their contents.
+use crate::{cache, call_id::CallId, widget::prelude::*, EnvKey, State};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Identifies a set of portal targets (see `with_portal_scope`).
+///
+/// Portals composed outside of any `with_portal_scope` call share the default scope.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+struct PortalScope(u64);
+
+impl_env_value!(PortalScope);
+
+const PORTAL_SCOPE: EnvKey<PortalScope> = builtin_env_key!("kyute.portal-scope");
+
+impl PortalScope {
+    /// Returns the scope of the portals composed at the call site.
+    fn current() -> PortalScope {
+        cache::environment().get(&PORTAL_SCOPE).unwrap_or_default()
+    }
+}
+
+/// Composes `f` in a new set of portal targets.
+///
+/// `Portal`s composed in `f` are only shown by the `PortalExit`s composed in `f`, even if portal exits with the same
+/// target names exist elsewhere. Use this around the contents of each window.
+#[composable]
+pub fn with_portal_scope<R>(f: impl FnOnce() -> R) -> R {
+    static NEXT_SCOPE: AtomicU64 = AtomicU64::new(1);
+    let scope = cache::once(|| PortalScope(NEXT_SCOPE.fetch_add(1, Ordering::Relaxed)));
+    let mut env = Environment::new();
+    env.set(&PORTAL_SCOPE, scope);
+    cache::with_environment(env, f)
+}
+
+/// The name of a portal target, and the scope it belongs to.
+type TargetKey = (PortalScope, String);
+
+/// Contents of a `Portal`.
+struct Contribution {
+    id: CallId,
+    /// Replaced each time the portal is recomposed.
+    content: Rc<RefCell<Arc<WidgetPod>>>,
+}
+
+/// Contents sent to a named target, and the `PortalExit` showing them.
+#[derive(Default)]
+struct PortalTarget {
+    contributions: Vec<Contribution>,
+    /// Call ID of the portal exit, and the state variable that its scope depends on.
+    exit: Option<(CallId, State<()>)>,
+}
+
+thread_local! {
+    static TARGETS: RefCell<HashMap<TargetKey, PortalTarget>> = RefCell::new(HashMap::new());
+}
+
+/// Modifies the target with the specified name, and recomposes its portal exit if `f` returns true.
+///
+/// The portal exit only needs to be recomposed when contents are added or removed: `PortalSlot`s read the current
+/// contents when they are laid out. Recomposing it whenever a portal is recomposed would never end, since
+/// the widgets in the portal are new each time.
+fn update_target(key: &TargetKey, f: impl FnOnce(&mut PortalTarget) -> bool) {
+    let exit = TARGETS.with(|targets| {
+        let mut targets = targets.borrow_mut();
+        let target = targets.entry(key.clone()).or_default();
+        let changed = f(target);
+        let exit = if changed {
+            target.exit.as_ref().map(|(_, state)| state.clone())
+        } else {
+            None
+        };
+        if target.contributions.is_empty() && target.exit.is_none() {
+            targets.remove(key);
+        }
+        exit
+    });
+    // don't make the calling scope depend on the portal exit: it would be recomposed along with it
+    if let Some(exit) = exit {
+        exit.set_untracked(());
+    }
+}
+
+/// Removes the contents of a portal from its target when the portal is removed from the cache.
+struct PortalRegistration {
+    id: CallId,
+    target: RefCell<TargetKey>,
+}
+
+impl Drop for PortalRegistration {
+    fn drop(&mut self) {
+        let id = self.id;
+        update_target(&self.target.borrow(), |target| {
+            let len = target.contributions.len();
+            target.contributions.retain(|c| c.id != id);
+            target.contributions.len() != len
+        });
+    }
+}
+
+/// Unregisters a portal exit when it is removed from the cache.
+struct ExitRegistration {
+    id: CallId,
+    target: RefCell<TargetKey>,
+}
+
+impl Drop for ExitRegistration {
+    fn drop(&mut self) {
+        let id = self.id;
+        update_target(&self.target.borrow(), |target| {
+            if matches!(target.exit, Some((exit_id, _)) if exit_id == id) {
+                target.exit = None;
+            }
+            false
+        });
+    }
+}
+
+/// Shows the contents of a portal with the environment of the portal's call site.
+struct PortalContent {
+    content: Arc<WidgetPod>,
+    env: Environment,
+}
+
+impl Widget for PortalContent {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.content.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.content.layout(ctx, constraints, &env.merged(self.env.clone()))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.content.route_event(ctx, event, &env.merged(self.env.clone()))
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx)
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new("portal content")
+    }
+}
+
+/// Sends a widget to the `PortalExit` with the specified target name, wherever it is in the UI.
+///
+/// Use this to contribute widgets to a shared part of the UI from a deeply nested composable (e.g. buttons in the
+/// window toolbar, or items in a status bar). The widget is composed at the call site of the portal: its state lives
+/// there, and it sees the environment of the call site. It is removed from the portal exit when the portal is not
+/// composed anymore.
+///
+/// The portal itself takes no space. If there's no portal exit with the target name in the same portal scope (see
+/// `with_portal_scope`), the widget isn't shown.
+pub struct Portal;
+
+impl Portal {
+    #[composable]
+    pub fn new(target: impl Into<String>, content: impl Widget + 'static) -> Portal {
+        let target = (PortalScope::current(), target.into());
+        let id = cache::current_call_id();
+        let registration = cache::once(|| {
+            Rc::new(PortalRegistration {
+                id,
+                target: RefCell::new(target.clone()),
+            })
+        });
+
+        // move the contents if the target has changed
+        let previous_target = registration.target.replace(target.clone());
+        if previous_target != target {
+            update_target(&previous_target, |previous| {
+                previous.contributions.retain(|c| c.id != id);
+                true
+            });
+        }
+
+        let content = PortalContent {
+            content: content.arc_dyn_pod(),
+            env: cache::environment(),
+        }
+        .arc_dyn_pod();
+        update_target(&target, |target| {
+            if let Some(contribution) = target.contributions.iter().find(|c| c.id == id) {
+                *contribution.content.borrow_mut() = content;
+                false
+            } else {
+                target.contributions.push(Contribution {
+                    id,
+                    content: Rc::new(RefCell::new(content)),
+                });
+                true
+            }
+        });
+
+        Portal
+    }
+}
+
+impl Widget for Portal {
+    fn widget_id(&self) -> Option<WidgetId> {
+        None
+    }
+
+    fn layout(&self, _ctx: &mut LayoutCtx, constraints: &LayoutParams, _env: &Environment) -> Geometry {
+        Geometry::new(constraints.min)
+    }
+
+    fn event(&self, _ctx: &mut EventCtx, _event: &mut Event, _env: &Environment) {}
+
+    fn paint(&self, _ctx: &mut PaintCtx) {}
+}
+
+/// Shows the contents of a portal in a `PortalExit`.
+///
+/// The slot shows the latest contents of the portal when it is laid out.
+pub struct PortalSlot {
+    content: Rc<RefCell<Arc<WidgetPod>>>,
+    /// Contents at the last layout.
+    current: RefCell<Option<Arc<WidgetPod>>>,
+}
+
+impl PortalSlot {
+    fn current(&self) -> Arc<WidgetPod> {
+        self.current
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| self.content.borrow().clone())
+    }
+}
+
+impl Widget for PortalSlot {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.current().widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let content = self.content.borrow().clone();
+        let geometry = content.layout(ctx, constraints, env);
+        if !ctx.speculative {
+            self.current.replace(Some(content));
+        }
+        geometry
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.current().route_event(ctx, event, env)
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.current().paint(ctx)
+    }
+}
+
+/// Shows the widgets sent by the `Portal`s with the specified target name.
+///
+/// `layout` receives a slot for each portal, in the order in which the portals were first composed, and returns the
+/// widget that lays them out (e.g. a `Grid` row for a toolbar). The portal exit is recomposed when portals are added
+/// or removed. When a portal is recomposed, its slot shows the new contents on the next layout of the portal exit.
+///
+/// There should be only one portal exit for a given target name in a portal scope at a time: the last one composed
+/// shows the widgets.
+pub struct PortalExit {
+    inner: Arc<WidgetPod>,
+}
+
+impl PortalExit {
+    #[composable]
+    pub fn new<W: Widget + 'static>(
+        target: impl Into<String>,
+        layout: impl FnOnce(Vec<PortalSlot>) -> W,
+    ) -> PortalExit {
+        let target = (PortalScope::current(), target.into());
+        let id = cache::current_call_id();
+        let state = cache::state(|| ());
+        let registration = cache::once(|| {
+            Rc::new(ExitRegistration {
+                id,
+                target: RefCell::new(target.clone()),
+            })
+        });
+        let previous_target = registration.target.replace(target.clone());
+        if previous_target != target {
+            update_target(&previous_target, |previous| {
+                previous.exit = None;
+                false
+            });
+        }
+
+        // recomposed when the contents of the target change
+        state.get();
+        let mut contents = vec![];
+        update_target(&target, |target| {
+            target.exit = Some((id, state.clone()));
+            contents = target
+                .contributions
+                .iter()
+                .map(|c| PortalSlot {
+                    content: c.content.clone(),
+                    current: RefCell::new(None),
+                })
+                .collect();
+            false
+        });
+
+        PortalExit {
+            inner: layout(contents).arc_dyn_pod(),
+        }
+    }
+}
+
+impl Widget for PortalExit {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.inner.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.inner.route_event(ctx, event, env)
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::{noop_waker, Cache},
+        widget::Null,
+    };
+
+    /// Composes a portal exit for the `toolbar` target, preceded by `portals` portals to it, and returns the
+    /// number of slots of the exit.
+    #[composable]
+    fn toolbar(portals: usize) -> usize {
+        for i in 0..portals {
+            cache::scoped(i, || Portal::new("toolbar", Null));
+        }
+        let mut slots = 0;
+        PortalExit::new("toolbar", |contents| {
+            slots = contents.len();
+            Null
+        });
+        slots
+    }
+
+    #[test]
+    fn portal_scopes() {
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let slots = cache.recompose(&env, || {
+            (
+                with_portal_scope(|| toolbar(1)),
+                with_portal_scope(|| toolbar(2)),
+                with_portal_scope(|| toolbar(0)),
+            )
+        });
+        assert_eq!(slots, (1, 2, 0));
+    }
+}
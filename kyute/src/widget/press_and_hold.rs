@@ -0,0 +1,138 @@
+//! Press-and-hold on touch screens, as the equivalent of a right-click.
+//!
+//! Fingers can't hover or right-click. Instead, holding a finger still over a widget for some time, then lifting it,
+//! is a secondary activation: it opens context menus, and is reported by `Clickable::secondary_clicked`. A ring is
+//! drawn around the finger while it is held, and is completed when lifting the finger activates.
+use crate::{
+    animation::request_animation_frame,
+    cache,
+    drawing::ToSkia,
+    event::{PointerEvent, PointerEventKind, PointerId, PointerType},
+    widget::prelude::*,
+    Color, InternalEvent, State,
+};
+use skia_safe as sk;
+use std::time::{Duration, Instant};
+
+/// Time a finger must stay pressed before lifting it is a secondary activation.
+pub(crate) const HOLD_DELAY: Duration = Duration::from_millis(500);
+/// Distance (in DIPs) that a held finger can move before the gesture is cancelled.
+const TOLERANCE: f64 = 8.0;
+/// Radius of the indicator ring, in DIPs. Large enough to be seen around the finger.
+const INDICATOR_RADIUS: f64 = 28.0;
+const INDICATOR_WIDTH: f64 = 4.0;
+const INDICATOR_COLOR: Color = Color::from_hex("#3875d7");
+/// Color of the disc under the completed ring (`INDICATOR_COLOR`, translucent).
+const INDICATOR_FILL_COLOR: Color = Color::new(0.22, 0.46, 0.84, 0.25);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Hold {
+    pointer_id: PointerId,
+    /// Position of the finger in local coordinates, where the indicator is drawn.
+    position: Point,
+    /// Position of the finger in window coordinates, to detect movement across event deliveries.
+    window_position: Point,
+    start: Instant,
+}
+
+/// Returns the pointer event carried by `event`, if it is a touch event.
+///
+/// Pointer events captured by a descendant are delivered as `InternalEvent::RoutePointerEvent` to the widgets on the
+/// way to the descendant: those are returned as well, so that wrappers can follow the finger whichever widget
+/// handles it.
+pub(crate) fn touch_event<'a>(event: &'a Event) -> Option<&'a PointerEvent> {
+    let pointer_event = match event {
+        Event::Pointer(p) => p,
+        Event::Internal(InternalEvent::RoutePointerEvent { event, .. }) => event,
+        _ => return None,
+    };
+    (pointer_event.pointer_id.pointer_type() == PointerType::Touch).then(|| pointer_event)
+}
+
+/// Recognizes a press-and-hold of a finger, and paints its indicator.
+///
+/// Created during composition with `new`, fed with touch events with `event`, and painted after the contents of the
+/// widget with `paint`.
+#[derive(Clone)]
+pub(crate) struct PressAndHold {
+    hold: State<Option<Hold>>,
+    /// The hold in progress at composition, and how far along it was (from 0 to 1).
+    current: Option<(Hold, f64)>,
+}
+
+impl PressAndHold {
+    #[composable]
+    pub(crate) fn new() -> PressAndHold {
+        let hold = cache::state(|| None);
+        let current = hold.get().map(|hold: Hold| {
+            let progress = hold.start.elapsed().as_secs_f64() / HOLD_DELAY.as_secs_f64();
+            if progress < 1.0 {
+                // the indicator is recomposed at each frame until the hold is complete
+                request_animation_frame();
+            }
+            (hold, progress.min(1.0))
+        });
+        PressAndHold { hold, current }
+    }
+
+    /// Follows the finger, and returns true if the event ends a press-and-hold: this is a secondary activation.
+    ///
+    /// Events of other pointers are ignored. A finger pressed while another is held restarts the gesture.
+    pub(crate) fn event(&self, event: &PointerEvent) -> bool {
+        if event.pointer_id.pointer_type() != PointerType::Touch {
+            return false;
+        }
+        let held = self.hold.get().filter(|hold| hold.pointer_id == event.pointer_id);
+        match (event.kind, held) {
+            (PointerEventKind::PointerDown, _) => {
+                self.hold.set(Some(Hold {
+                    pointer_id: event.pointer_id,
+                    position: event.position,
+                    window_position: event.window_position,
+                    start: event.timestamp,
+                }));
+                false
+            }
+            (PointerEventKind::PointerMove, Some(hold))
+                if (event.window_position - hold.window_position).length() > TOLERANCE =>
+            {
+                // the finger is dragging, not holding
+                self.hold.set(None);
+                false
+            }
+            (PointerEventKind::PointerUp, Some(hold)) => {
+                self.hold.set(None);
+                event.timestamp.saturating_duration_since(hold.start) >= HOLD_DELAY
+            }
+            _ => false,
+        }
+    }
+
+    /// Paints the indicator of the hold in progress: a ring around the finger that fills up until the delay is
+    /// reached, and is then drawn over a disc to show that lifting the finger will activate.
+    pub(crate) fn paint(&self, ctx: &mut PaintCtx) {
+        let (hold, progress) = match self.current {
+            Some(current) => current,
+            None => return,
+        };
+        let oval = sk::Rect::new(
+            (hold.position.x - INDICATOR_RADIUS) as f32,
+            (hold.position.y - INDICATOR_RADIUS) as f32,
+            (hold.position.x + INDICATOR_RADIUS) as f32,
+            (hold.position.y + INDICATOR_RADIUS) as f32,
+        );
+        let canvas = ctx.surface.canvas();
+        if progress >= 1.0 {
+            let mut fill = sk::Paint::new(INDICATOR_FILL_COLOR.to_skia(), None);
+            fill.set_anti_alias(true);
+            canvas.draw_oval(oval, &fill);
+        }
+        let mut stroke = sk::Paint::new(INDICATOR_COLOR.to_skia(), None);
+        stroke.set_anti_alias(true);
+        stroke.set_style(sk::PaintStyle::Stroke);
+        stroke.set_stroke_width(INDICATOR_WIDTH as f32);
+        stroke.set_stroke_cap(sk::PaintCap::Round);
+        // clockwise from the top
+        canvas.draw_arc(oval, -90.0, 360.0 * progress as f32, false, &stroke);
+    }
+}
@@ -0,0 +1,272 @@
+//! Progress bars and activity indicators.
+//!
+//! `ProgressBar` shows the progress of a task that reports it, and `Spinner` (or `ProgressBar::indeterminate`) shows
+//! that something is happening when the progress is unknown, e.g. while waiting for a `cache::run_async` task.
+//! Both are animated with the animation clock, and stand still when the user prefers reduced motion.
+use crate::{
+    accessibility::{Role, Semantics},
+    animation::{animate, Animated, Easing, Keyframes, Transition},
+    cache,
+    drawing::ToSkia,
+    style::Style,
+    theme,
+    widget::{prelude::*, Null, StyledBox},
+    Color,
+};
+use skia_safe as sk;
+use std::time::Duration;
+
+/// Width of progress bars when the constraints are unbounded.
+const DEFAULT_WIDTH: f64 = 160.0;
+/// Duration of the transition of the fill when the progress changes.
+const PROGRESS_TRANSITION: Duration = Duration::from_millis(150);
+/// Width of the moving fill of indeterminate progress bars, relative to the track.
+const INDETERMINATE_FILL_WIDTH: f64 = 0.3;
+/// Time taken by the fill of indeterminate progress bars to cross the track.
+const INDETERMINATE_PERIOD: Duration = Duration::from_millis(1500);
+/// Default diameter of spinners.
+const SPINNER_SIZE: f64 = 16.0;
+/// Time of a full turn of a spinner.
+const SPINNER_PERIOD: Duration = Duration::from_millis(1000);
+/// Angle covered by the arc of a spinner, in degrees.
+const SPINNER_ARC: f64 = 270.0;
+
+/// Style of a progress bar.
+#[derive(Clone)]
+pub struct ProgressBarStyle {
+    /// Style of the track, which takes the whole width of the progress bar. Its height is the height of the bar.
+    pub track: Style,
+    /// Style of the filled part of the track.
+    pub fill: Style,
+}
+
+impl Default for ProgressBarStyle {
+    fn default() -> Self {
+        ProgressBarStyle {
+            track: Style::parse("height: 6px; border-radius: 3px; background: $border-color;").unwrap(),
+            fill: Style::parse("border-radius: 3px; background: $accent-color;").unwrap(),
+        }
+    }
+}
+
+/// Returns the start and end of the fill of an indeterminate progress bar, relative to the track, at the specified
+/// progress of its animation.
+///
+/// The fill enters the track on the left and leaves it on the right.
+fn indeterminate_fill(t: f64) -> (f64, f64) {
+    let start = -INDETERMINATE_FILL_WIDTH + (1.0 + INDETERMINATE_FILL_WIDTH) * t;
+    let end = start + INDETERMINATE_FILL_WIDTH;
+    (start.max(0.0), end.min(1.0))
+}
+
+/// Horizontal bar that fills up as a task progresses.
+pub struct ProgressBar {
+    id: WidgetId,
+    /// Progress between 0 and 1, or `None` if the progress is unknown.
+    progress: Option<f64>,
+    /// Start and end of the fill, relative to the width of the track.
+    fill_range: (f64, f64),
+    track: WidgetPod<StyledBox<Null>>,
+    fill: WidgetPod<StyledBox<Null>>,
+}
+
+impl ProgressBar {
+    /// Creates a progress bar showing the specified progress, between 0 and 1.
+    #[composable]
+    pub fn new(progress: f64) -> ProgressBar {
+        ProgressBar::with_style(Some(progress), ProgressBarStyle::default())
+    }
+
+    /// Creates a progress bar for a task whose progress is unknown: the fill sweeps the track repeatedly.
+    #[composable]
+    pub fn indeterminate() -> ProgressBar {
+        ProgressBar::with_style(None, ProgressBarStyle::default())
+    }
+
+    /// Creates a progress bar with the specified style. If `progress` is `None`, the progress bar is indeterminate.
+    #[composable]
+    pub fn with_style(progress: Option<f64>, style: ProgressBarStyle) -> ProgressBar {
+        let progress = progress.map(|p| p.max(0.0).min(1.0));
+        let fill_range = match progress {
+            Some(progress) => {
+                let end = animate(progress, Transition::new(PROGRESS_TRANSITION).easing(Easing::EaseOut));
+                (0.0, end)
+            }
+            None => {
+                let animation = Animated::repeat(
+                    Keyframes::new(0.0).then(1.0, 1.0, Easing::EaseInOut),
+                    Transition::new(INDETERMINATE_PERIOD),
+                );
+                // with reduced motion, the animation doesn't run: show the fill in the middle of the track
+                if animation.is_running() {
+                    indeterminate_fill(animation.value())
+                } else {
+                    indeterminate_fill(0.5)
+                }
+            }
+        };
+        ProgressBar {
+            id: WidgetId::here(),
+            progress,
+            fill_range,
+            track: WidgetPod::new(Null.style(style.track)),
+            fill: WidgetPod::new(Null.style(style.fill)),
+        }
+    }
+
+    /// Returns the progress shown by the bar, or `None` if it is indeterminate.
+    pub fn progress(&self) -> Option<f64> {
+        self.progress
+    }
+}
+
+impl Widget for ProgressBar {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let width = if constraints.max.width.is_finite() {
+            constraints.max.width
+        } else {
+            DEFAULT_WIDTH.max(constraints.min.width)
+        };
+        let track_constraints = LayoutParams {
+            min: Size::new(width, constraints.min.height),
+            max: Size::new(width, constraints.max.height),
+            ..*constraints
+        };
+        let size = self.track.layout(ctx, &track_constraints, env).measurements.size;
+
+        let (start, end) = self.fill_range;
+        let fill_size = Size::new((end - start).max(0.0) * size.width, size.height);
+        self.fill.layout(
+            ctx,
+            &LayoutParams {
+                min: fill_size,
+                max: fill_size,
+                ..*constraints
+            },
+            env,
+        );
+        if !ctx.speculative {
+            self.fill.set_offset(Offset::new(start * size.width, 0.0));
+        }
+        Geometry::new(size)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.track.route_event(ctx, event, env);
+        self.fill.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.track.paint(ctx);
+        let (start, end) = self.fill_range;
+        if end > start {
+            self.fill.paint(ctx);
+        }
+    }
+
+    fn semantics(&self) -> Option<Semantics> {
+        let semantics = Semantics::new(Role::ProgressIndicator);
+        match self.progress {
+            Some(progress) => Some(semantics.numeric_value(progress, 0.0, 1.0)),
+            None => Some(semantics),
+        }
+    }
+}
+
+/// Rotating arc that shows that a task of unknown duration is in progress.
+pub struct Spinner {
+    size: f64,
+    /// Rotation of the arc, in degrees.
+    angle: f64,
+    color: Color,
+}
+
+impl Spinner {
+    /// Creates a spinner with the accent color of the theme.
+    #[composable]
+    pub fn new() -> Spinner {
+        let animation = Animated::repeat(
+            Keyframes::new(0.0).then(1.0, 360.0, Easing::Linear),
+            Transition::new(SPINNER_PERIOD),
+        );
+        let color = cache::environment()
+            .get(&theme::ACCENT_COLOR)
+            .unwrap_or(theme::palette::BLUE_500);
+        Spinner {
+            size: SPINNER_SIZE,
+            angle: if animation.is_running() { animation.value() } else { 0.0 },
+            color,
+        }
+    }
+
+    /// Sets the diameter of the spinner, in DIPs.
+    pub fn size(mut self, size: f64) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Sets the color of the arc.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+impl Widget for Spinner {
+    fn widget_id(&self) -> Option<WidgetId> {
+        None
+    }
+
+    fn layout(&self, _ctx: &mut LayoutCtx, constraints: &LayoutParams, _env: &Environment) -> Geometry {
+        Geometry::new(constraints.constrain(Size::new(self.size, self.size)))
+    }
+
+    fn event(&self, _ctx: &mut EventCtx, _event: &mut Event, _env: &Environment) {}
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let stroke_width = (0.125 * self.size).max(1.0);
+        let oval = ctx.bounds.inflate(-0.5 * stroke_width, -0.5 * stroke_width).to_skia();
+        let mut paint = sk::Paint::new(self.color.to_skia(), None);
+        paint.set_anti_alias(true);
+        paint.set_style(sk::PaintStyle::Stroke);
+        paint.set_stroke_width(stroke_width as sk::scalar);
+        paint.set_stroke_cap(sk::PaintCap::Round);
+        // clockwise from the top
+        ctx.surface.canvas().draw_arc(
+            oval,
+            (self.angle - 90.0) as sk::scalar,
+            SPINNER_ARC as sk::scalar,
+            false,
+            &paint,
+        );
+    }
+
+    fn semantics(&self) -> Option<Semantics> {
+        Some(Semantics::new(Role::ProgressIndicator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indeterminate_fill_range() {
+        // enters on the left
+        assert_eq!(indeterminate_fill(0.0), (0.0, 0.0));
+        let (start, end) = indeterminate_fill(0.1);
+        assert_eq!(start, 0.0);
+        assert!(end > 0.0 && end < INDETERMINATE_FILL_WIDTH);
+        // fully inside in the middle
+        let (start, end) = indeterminate_fill(0.5);
+        assert!((end - start - INDETERMINATE_FILL_WIDTH).abs() < 1e-9);
+        // leaves on the right
+        let (start, end) = indeterminate_fill(1.0);
+        assert!(start > 1.0 - 1e-9);
+        assert_eq!(end, 1.0);
+    }
+}
@@ -0,0 +1,282 @@
+//! Inspector-style property editor.
+use crate::{
+    cache,
+    widget::{prelude::*, Button, Checkbox, Clickable, DisplayFormatter, DropDown, Grid, Null, Text, TextEdit},
+};
+use std::sync::Arc;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Schema
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Value of a property.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(Arc<str>),
+    /// Index of the selected choice of an enumeration.
+    Enum(usize),
+}
+
+/// Type of a property, which determines the editor used for it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyKind {
+    Bool,
+    Int {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+    },
+    String,
+    /// An enumeration: one value among a list of choices.
+    Enum(Vec<String>),
+}
+
+/// Describes a property shown in a `PropertyGrid`.
+#[derive(Clone, Debug)]
+pub struct PropertyDesc {
+    pub name: String,
+    pub category: String,
+    pub kind: PropertyKind,
+    /// Value restored by the "reset to default" button.
+    pub default: PropertyValue,
+}
+
+impl PropertyDesc {
+    /// Creates a new property description, in the default ("General") category.
+    pub fn new(name: impl Into<String>, kind: PropertyKind, default: PropertyValue) -> PropertyDesc {
+        PropertyDesc {
+            name: name.into(),
+            category: "General".to_string(),
+            kind,
+            default,
+        }
+    }
+
+    /// Sets the category (group) of the property.
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = category.into();
+        self
+    }
+}
+
+/// Describes the properties of an object edited in a `PropertyGrid`.
+#[derive(Clone, Debug, Default)]
+pub struct PropertySchema {
+    properties: Vec<PropertyDesc>,
+}
+
+impl PropertySchema {
+    pub fn new() -> PropertySchema {
+        PropertySchema::default()
+    }
+
+    /// Adds a property to the schema.
+    pub fn property(mut self, property: PropertyDesc) -> Self {
+        self.properties.push(property);
+        self
+    }
+
+    /// Returns the properties in the schema.
+    pub fn properties(&self) -> &[PropertyDesc] {
+        &self.properties
+    }
+
+    /// Returns the default values of all properties.
+    pub fn default_values(&self) -> Vec<PropertyValue> {
+        self.properties.iter().map(|p| p.default.clone()).collect()
+    }
+
+    /// Returns the categories of the properties, in order of first appearance.
+    fn categories(&self) -> Vec<&str> {
+        let mut categories: Vec<&str> = Vec::new();
+        for p in self.properties.iter() {
+            if !categories.contains(&p.category.as_str()) {
+                categories.push(&p.category);
+            }
+        }
+        categories
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Editors
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Creates the editor widget for a property. Returns the widget and the new value if it was edited.
+#[composable]
+fn property_editor(kind: &PropertyKind, value: &PropertyValue) -> (Arc<WidgetPod>, Option<PropertyValue>) {
+    match (kind, value) {
+        (PropertyKind::Bool, PropertyValue::Bool(v)) => {
+            let checkbox = Checkbox::new(*v);
+            let new_value = checkbox.toggled().map(PropertyValue::Bool);
+            (checkbox.arc_dyn_pod(), new_value)
+        }
+        (PropertyKind::Int { min, max }, PropertyValue::Int(v)) => {
+            let edit = TextEdit::new(v.to_string());
+            let new_value = edit
+                .editing_finished()
+                .and_then(|text| text.trim().parse::<i64>().ok())
+                .map(|v| PropertyValue::Int(v.clamp(*min, *max)));
+            (edit.arc_dyn_pod(), new_value)
+        }
+        (PropertyKind::Float { min, max }, PropertyValue::Float(v)) => {
+            let edit = TextEdit::new(v.to_string());
+            let new_value = edit
+                .editing_finished()
+                .and_then(|text| text.trim().parse::<f64>().ok())
+                .map(|v| PropertyValue::Float(v.clamp(*min, *max)));
+            (edit.arc_dyn_pod(), new_value)
+        }
+        (PropertyKind::String, PropertyValue::String(v)) => {
+            let edit = TextEdit::new(v.clone());
+            let new_value = edit.editing_finished().map(PropertyValue::String);
+            (edit.arc_dyn_pod(), new_value)
+        }
+        (PropertyKind::Enum(choices), PropertyValue::Enum(index)) if *index < choices.len() => {
+            let drop_down = DropDown::with_selected_index(*index, choices.clone(), DisplayFormatter);
+            let new_value = drop_down
+                .selected_item_changed()
+                .and_then(|choice| choices.iter().position(|c| *c == choice))
+                .map(PropertyValue::Enum);
+            (drop_down.arc_dyn_pod(), new_value)
+        }
+        _ => {
+            warn!("property value {:?} doesn't match the property type {:?}", value, kind);
+            (Text::new(format!("{:?}", value)).arc_dyn_pod(), None)
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// PropertyGrid
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Inspector-style editor for a set of properties described by a `PropertySchema`.
+///
+/// Properties are shown in two columns (name | editor), grouped by collapsible categories.
+/// The grid has a search box that filters properties by name, and a button to reset each modified property
+/// to its default value.
+#[derive(Widget)]
+pub struct PropertyGrid {
+    inner: Grid,
+    property_changed: Option<(usize, PropertyValue)>,
+}
+
+impl PropertyGrid {
+    /// Creates a new property grid.
+    ///
+    /// `values` are the current values of the properties, in the order of `schema.properties()`.
+    #[composable]
+    pub fn new(schema: &PropertySchema, values: &[PropertyValue]) -> PropertyGrid {
+        #[state]
+        let mut filter = String::new();
+
+        assert_eq!(
+            schema.properties().len(),
+            values.len(),
+            "the number of values doesn't match the number of properties in the schema"
+        );
+
+        let mut grid = Grid::with_template("/ 1fr 2fr auto");
+        grid.set_row_gap(2.dip());
+        grid.set_column_gap(4.dip());
+
+        let mut property_changed = None;
+        let mut row = 0;
+
+        // search box
+        let search = TextEdit::new(filter.clone()).on_text_changed(|text| filter = text.to_string());
+        grid.place((row, ..), 0, search.arc_dyn_pod());
+        row += 1;
+
+        let filter_lowercase = filter.to_lowercase();
+        let matches_filter =
+            |p: &PropertyDesc| filter_lowercase.is_empty() || p.name.to_lowercase().contains(&filter_lowercase);
+
+        for category in schema.categories() {
+            let properties: Vec<_> = schema
+                .properties()
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.category == category && matches_filter(p))
+                .collect();
+            if properties.is_empty() {
+                continue;
+            }
+
+            cache::scoped(category, || {
+                let collapsed_state = cache::state(|| false);
+                let collapsed = collapsed_state.get();
+
+                // category header
+                let header = Clickable::new(
+                    Text::new(format!("{} {}", if collapsed { "▸" } else { "▾" }, category)).padding_top(4.dip()),
+                );
+                if header.clicked() {
+                    collapsed_state.set(!collapsed);
+                }
+                grid.place((row, ..), 0, header.arc_dyn_pod());
+                row += 1;
+
+                if collapsed {
+                    return;
+                }
+
+                for (index, property) in properties {
+                    cache::scoped(index, || {
+                        let value = &values[index];
+                        let (editor, new_value) = property_editor(&property.kind, value);
+                        if new_value.is_some() {
+                            property_changed = new_value.map(|v| (index, v));
+                        }
+
+                        let reset: Arc<WidgetPod> = if *value != property.default {
+                            let button = Button::new("↺");
+                            if button.clicked() {
+                                property_changed = Some((index, property.default.clone()));
+                            }
+                            button.arc_dyn_pod()
+                        } else {
+                            Null.arc_dyn_pod()
+                        };
+
+                        grid.place(
+                            (row, 0),
+                            0,
+                            Text::new(property.name.clone())
+                                .vertical_alignment(Alignment::CENTER)
+                                .arc_dyn_pod(),
+                        );
+                        grid.place((row, 1), 0, editor);
+                        grid.place((row, 2), 0, reset);
+                        row += 1;
+                    });
+                }
+            });
+        }
+
+        PropertyGrid {
+            inner: grid,
+            property_changed,
+        }
+    }
+
+    /// Returns the index (in the schema) and new value of a property modified since the last composition.
+    pub fn property_changed(&self) -> Option<(usize, PropertyValue)> {
+        self.property_changed.clone()
+    }
+
+    /// Calls the given closure with the index and new value of a property modified since the last composition.
+    pub fn on_property_changed(self, f: impl FnOnce(usize, PropertyValue)) -> Self {
+        if let Some((index, value)) = self.property_changed.clone() {
+            f(index, value);
+        }
+        self
+    }
+}
@@ -0,0 +1,532 @@
+//! Radial (pie) menus.
+use crate::{
+    cache,
+    core::FocusChange,
+    drawing::ToSkia,
+    event::{Key, KeyboardEvent, PointerButton, PointerEvent},
+    theme,
+    widget::{menu::split_mnemonic, prelude::*, Menu, MenuItem, Text},
+    Color, InternalEvent, PointerEventKind, State,
+};
+use keyboard_types::KeyState;
+use skia_safe as sk;
+use std::{cell::Cell, f64::consts::PI};
+
+/// Radius of the center of the menu, in DIPs. Releasing the pointer there closes the menu.
+const DEAD_ZONE_RADIUS: f64 = 24.0;
+/// Width of each ring, in DIPs.
+const RING_WIDTH: f64 = 72.0;
+const DEFAULT_HIGHLIGHT_COLOR: Color = Color::from_hex("#3875d7");
+const DEFAULT_BACKGROUND_COLOR: Color = Color::from_hex("#212121");
+const BORDER_COLOR: Color = Color::from_hex("#808080");
+
+/// Returns the indices of the items shown in the ring of a menu. Separators are not shown.
+fn ring_items(menu: &Menu) -> Vec<usize> {
+    menu.items()
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| !matches!(item, MenuItem::Separator))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Returns the direction of the center of the sector of the `position`-th item of a ring of `count` items,
+/// in radians, clockwise from the positive X axis. The first item is at the top.
+fn sector_angle(position: usize, count: usize) -> f64 {
+    -PI / 2.0 + 2.0 * PI * position as f64 / count as f64
+}
+
+/// Returns the position of the sector that contains the direction `angle` (see `sector_angle`), in a ring of `count`
+/// items.
+fn sector_at(angle: f64, count: usize) -> usize {
+    let turns = (angle + PI / 2.0) / (2.0 * PI) + 0.5 / count as f64;
+    ((turns.rem_euclid(1.0) * count as f64) as usize).min(count - 1)
+}
+
+/// Returns the outline of a sector of a ring. Angles are in degrees.
+fn sector_path(center: Point, inner_radius: f64, outer_radius: f64, start: f64, sweep: f64) -> sk::Path {
+    let oval = |radius: f64| {
+        sk::Rect::new(
+            (center.x - radius) as f32,
+            (center.y - radius) as f32,
+            (center.x + radius) as f32,
+            (center.y + radius) as f32,
+        )
+    };
+    let mut path = sk::Path::new();
+    path.arc_to(oval(outer_radius), start as f32, sweep as f32, true);
+    path.arc_to(oval(inner_radius), (start + sweep) as f32, -sweep as f32, false);
+    path.close();
+    path
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct RadialMenuState {
+    /// Center of the rings in local coordinates, if the menu is open.
+    center: Option<Point>,
+    /// Highlighted item in each open ring.
+    ///
+    /// The first entry is for the ring of the top-level menu; each following entry is for the ring of the submenu
+    /// of the highlighted item in the previous one.
+    path: Vec<Option<usize>>,
+    /// Whether the pointer that opened the menu is still pressed: releasing it invokes the highlighted item.
+    dragging: bool,
+    /// Whether the pointer has left the center of the menu since it was opened.
+    moved: bool,
+    /// Widget that had the focus before the menu was opened.
+    prev_focus: Option<WidgetId>,
+}
+
+impl RadialMenuState {
+    /// Returns the menus of the open rings, from the innermost to the outermost.
+    fn open_menus<'a>(&self, menu: &'a Menu) -> Vec<&'a Menu> {
+        let mut menus = Vec::new();
+        if self.center.is_none() {
+            return menus;
+        }
+        let mut current = menu;
+        menus.push(current);
+        for highlighted in self.path.iter().take(self.path.len().saturating_sub(1)) {
+            match highlighted.and_then(|i| current.items().get(i)) {
+                Some(MenuItem::Submenu { menu, .. }) => {
+                    menus.push(menu);
+                    current = menu;
+                }
+                _ => break,
+            }
+        }
+        menus
+    }
+
+    fn open(&mut self, center: Point, dragging: bool, highlighted: Option<usize>) {
+        self.center = Some(center);
+        self.path = vec![highlighted];
+        self.dragging = dragging;
+        self.moved = false;
+    }
+
+    /// Highlights an item in an open ring, opening the ring of its submenu if it has one.
+    fn hover(&mut self, menu: &Menu, level: usize, item: Option<usize>) {
+        let menus = self.open_menus(menu);
+        if level >= menus.len() || (self.path.get(level) == Some(&item) && self.path.len() > level + 1) {
+            return;
+        }
+        self.path.truncate(level + 1);
+        self.path[level] = item;
+        if let Some(MenuItem::Submenu { .. }) = item.and_then(|i| menus[level].items().get(i)) {
+            self.path.push(None);
+        }
+    }
+
+    /// Invokes the highlighted item of the outermost ring: triggers actions, opens submenus.
+    fn invoke(&mut self, menu: &Menu) {
+        let menus = self.open_menus(menu);
+        let highlighted = menus
+            .last()
+            .zip(self.path.last().copied().flatten())
+            .and_then(|(menu, i)| menu.items().get(i));
+        match highlighted {
+            Some(MenuItem::Action { action, .. }) => {
+                action.triggered.signal(());
+                self.close();
+            }
+            Some(MenuItem::Submenu { menu: submenu, .. }) => {
+                self.path.push(ring_items(submenu).first().copied());
+            }
+            _ => {}
+        }
+    }
+
+    /// Invokes the highlighted item when the pointer is released. Closes the menu if nothing is highlighted.
+    fn release(&mut self, menu: &Menu) {
+        match self.path.last().copied().flatten() {
+            Some(_) => self.invoke(menu),
+            // the ring of a submenu was opened: keep it open, its items are selected by clicking
+            None if self.path.len() > 1 => {}
+            None => self.close(),
+        }
+    }
+
+    fn close(&mut self) {
+        *self = RadialMenuState::default();
+    }
+}
+
+#[derive(Copy, Clone)]
+struct RingColors {
+    background: Color,
+    highlight: Color,
+}
+
+/// Shows a menu with its items arranged in rings around the point where it was invoked.
+///
+/// Designed for pen and touch input, it's opened by pressing a pointer button (the right button by default) over the
+/// content. Items are selected by direction: drag the pointer towards an item and release it to invoke the item.
+/// Hovering a submenu opens it as a ring around the current one, and moving the pointer further away from the center
+/// selects in that ring. Releasing the pointer in the center closes the menu. After a click without moving, the menu
+/// stays open and items are invoked by clicking on them.
+///
+/// With the keyboard, the `ContextMenu` key opens the menu in the middle of the content; the arrow keys move around
+/// the outermost ring, `Enter` invokes the highlighted item (opening submenus), and `Escape` closes the outermost
+/// ring.
+///
+/// The rings are drawn over the content, within the bounds of the widget: wrap a large area, like the canvas of
+/// a drawing application. Their colors come from the theme (`theme::CONTENT_BACKGROUND_COLOR`,
+/// `theme::ACCENT_COLOR` and `theme::TEXT_COLOR`).
+pub struct RadialMenu<Content> {
+    id: WidgetId,
+    menu: Menu,
+    button: PointerButton,
+    state: State<RadialMenuState>,
+    current: RadialMenuState,
+    /// Labels of the items of each open ring, with the index of the item in its menu.
+    rings: Vec<Vec<(usize, WidgetPod<Text>)>>,
+    content: WidgetPod<Content>,
+    size: Cell<Size>,
+    colors: Cell<RingColors>,
+}
+
+impl<Content: Widget + 'static> RadialMenu<Content> {
+    /// Creates a radial menu over the specified content.
+    #[composable]
+    pub fn new(menu: Menu, content: Content) -> RadialMenu<Content> {
+        let state = cache::state(RadialMenuState::default);
+        let current = state.get();
+
+        let rings = current
+            .open_menus(&menu)
+            .into_iter()
+            .enumerate()
+            .map(|(level, ring_menu)| {
+                cache::scoped(level, || {
+                    ring_items(ring_menu)
+                        .into_iter()
+                        .map(|i| {
+                            cache::scoped(i, || {
+                                let text = match ring_menu.items()[i] {
+                                    MenuItem::Action { ref text, .. } => split_mnemonic(text).0,
+                                    MenuItem::Submenu { ref text, .. } => format!("{} ▸", split_mnemonic(text).0),
+                                    MenuItem::Separator => String::new(),
+                                };
+                                (i, WidgetPod::new(Text::new(text)))
+                            })
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+
+        RadialMenu {
+            id: WidgetId::here(),
+            menu,
+            button: PointerButton::RIGHT,
+            state,
+            current,
+            rings,
+            content: WidgetPod::new(content),
+            size: Cell::new(Size::zero()),
+            colors: Cell::new(RingColors {
+                background: DEFAULT_BACKGROUND_COLOR,
+                highlight: DEFAULT_HIGHLIGHT_COLOR,
+            }),
+        }
+    }
+
+    /// Sets the pointer button that opens the menu.
+    #[must_use]
+    pub fn button(mut self, button: PointerButton) -> Self {
+        self.button = button;
+        self
+    }
+
+    /// Returns a reference to the content widget.
+    pub fn content(&self) -> &Content {
+        self.content.inner()
+    }
+
+    /// Returns whether the menu is open.
+    pub fn is_open(&self) -> bool {
+        self.current.center.is_some()
+    }
+
+    /// Updates the state of the menu, and moves the focus when the menu is opened or closed.
+    fn update_state(&self, ctx: &mut EventCtx, mut new_state: RadialMenuState) {
+        let was_open = self.current.center.is_some();
+        let is_open = new_state.center.is_some();
+        if !was_open && is_open {
+            // take the focus for keyboard navigation, and give it back when the menu is closed
+            new_state.prev_focus = ctx
+                .window_state
+                .as_deref()
+                .and_then(|window_state| window_state.focus_state.focus)
+                .filter(|&focus| focus != self.id);
+            ctx.request_focus();
+        } else if was_open && !is_open {
+            if let Some(prev_focus) = self.current.prev_focus {
+                ctx.focus_change = Some(FocusChange::MoveTo(prev_focus));
+            }
+        }
+        if new_state != self.current {
+            self.state.set(new_state);
+        }
+    }
+
+    fn keyboard_event(&self, ctx: &mut EventCtx, event: &KeyboardEvent) {
+        if event.state != KeyState::Down {
+            return;
+        }
+        let mut state = self.current.clone();
+        let menus = state.open_menus(&self.menu);
+        let (level, outermost) = match menus.last() {
+            Some(menu) => (menus.len() - 1, ring_items(menu)),
+            None => return,
+        };
+
+        match event.key {
+            Key::ArrowRight | Key::ArrowDown | Key::ArrowLeft | Key::ArrowUp if !outermost.is_empty() => {
+                let clockwise = matches!(event.key, Key::ArrowRight | Key::ArrowDown);
+                let n = outermost.len();
+                let next = match state.path[level].and_then(|i| outermost.iter().position(|&item| item == i)) {
+                    Some(position) if clockwise => (position + 1) % n,
+                    Some(position) => (position + n - 1) % n,
+                    None => 0,
+                };
+                state.path.truncate(level + 1);
+                state.path[level] = Some(outermost[next]);
+            }
+            Key::Enter => state.invoke(&self.menu),
+            Key::Escape | Key::Backspace => {
+                if state.path.len() > 1 {
+                    state.path.pop();
+                } else {
+                    state.close();
+                }
+            }
+            _ => {}
+        }
+
+        self.update_state(ctx, state);
+        ctx.set_handled();
+    }
+
+    /// Handles a pointer event while the menu is open.
+    fn pointer_event(&self, ctx: &mut EventCtx, event: &PointerEvent) {
+        let center = match self.current.center {
+            Some(center) => center,
+            None => return,
+        };
+        let mut state = self.current.clone();
+        let menus = state.open_menus(&self.menu);
+        let direction = event.position - center;
+        let distance = direction.length();
+
+        if matches!(event.kind, PointerEventKind::PointerMove | PointerEventKind::PointerUp) {
+            if distance < DEAD_ZONE_RADIUS {
+                state.hover(&self.menu, 0, None);
+            } else {
+                state.moved = true;
+                // beyond the outermost ring, items are still selected by direction
+                let level = (((distance - DEAD_ZONE_RADIUS) / RING_WIDTH) as usize).min(menus.len() - 1);
+                let items = ring_items(menus[level]);
+                let item = (!items.is_empty()).then(|| items[sector_at(direction.y.atan2(direction.x), items.len())]);
+                state.hover(&self.menu, level, item);
+            }
+        }
+
+        if event.kind == PointerEventKind::PointerUp {
+            if state.dragging {
+                state.dragging = false;
+                if state.moved {
+                    state.release(&self.menu);
+                }
+            } else {
+                let outer_radius = DEAD_ZONE_RADIUS + RING_WIDTH * menus.len() as f64;
+                if distance > outer_radius + RING_WIDTH {
+                    state.close();
+                } else {
+                    state.release(&self.menu);
+                }
+            }
+        }
+
+        self.update_state(ctx, state);
+        ctx.set_handled();
+    }
+}
+
+impl<Content: Widget + 'static> Widget for RadialMenu<Content> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let geometry = self.content.layout(ctx, constraints, env);
+
+        if let Some(center) = self.current.center {
+            let unconstrained = LayoutParams {
+                min: Size::zero(),
+                max: Size::new(f64::INFINITY, f64::INFINITY),
+                ..*constraints
+            };
+            for (level, labels) in self.rings.iter().enumerate() {
+                // labels are centered in their sector
+                let radius = DEAD_ZONE_RADIUS + RING_WIDTH * (level as f64 + 0.5);
+                for (position, (_, label)) in labels.iter().enumerate() {
+                    let size = label.layout(ctx, &unconstrained, env).measurements.size;
+                    if !ctx.speculative {
+                        let angle = sector_angle(position, labels.len());
+                        label.set_offset(Offset::new(
+                            center.x + radius * angle.cos() - 0.5 * size.width,
+                            center.y + radius * angle.sin() - 0.5 * size.height,
+                        ));
+                    }
+                }
+            }
+        }
+
+        if !ctx.speculative {
+            self.size.set(geometry.measurements.size);
+            self.colors.set(RingColors {
+                background: env
+                    .get(&theme::CONTENT_BACKGROUND_COLOR)
+                    .unwrap_or(DEFAULT_BACKGROUND_COLOR),
+                highlight: env.get(&theme::ACCENT_COLOR).unwrap_or(DEFAULT_HIGHLIGHT_COLOR),
+            });
+        }
+        geometry
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if self.current.center.is_some() {
+            match event {
+                Event::Keyboard(keyboard_event) => {
+                    let keyboard_event = keyboard_event.clone();
+                    self.keyboard_event(ctx, &keyboard_event);
+                    return;
+                }
+                Event::FocusLost => {
+                    // focus moved elsewhere: close without restoring the focus
+                    self.state.set(RadialMenuState::default());
+                    return;
+                }
+                Event::Pointer(pointer_event) => {
+                    // the open menu receives all pointer events
+                    let pointer_event = *pointer_event;
+                    self.pointer_event(ctx, &pointer_event);
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        for (_, label) in self.rings.iter().flatten() {
+            label.route_event(ctx, event, env);
+        }
+        self.content.route_event(ctx, event, env);
+        if ctx.handled() {
+            return;
+        }
+
+        let mut state = self.current.clone();
+        match *event {
+            Event::Pointer(ref p) if p.kind == PointerEventKind::PointerDown && p.button == Some(self.button) => {
+                state.open(p.position, true, None);
+                ctx.capture_pointer();
+            }
+            Event::Keyboard(ref k) if k.state == KeyState::Down && k.key == Key::ContextMenu => {
+                let first = ring_items(&self.menu).first().copied();
+                state.open(Rect::new(Point::origin(), self.size.get()).center(), false, first);
+            }
+            Event::Internal(InternalEvent::RouteEvent { ref event, .. }) => match **event {
+                Event::Keyboard(ref k) if k.state == KeyState::Down && k.key == Key::ContextMenu => {
+                    let first = ring_items(&self.menu).first().copied();
+                    state.open(Rect::new(Point::origin(), self.size.get()).center(), false, first);
+                }
+                _ => return,
+            },
+            _ => return,
+        }
+        self.update_state(ctx, state);
+        ctx.set_handled();
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx);
+
+        let center = match self.current.center {
+            Some(center) => center,
+            None => return,
+        };
+        let colors = self.colors.get();
+        let mut border = sk::Paint::new(BORDER_COLOR.to_skia(), None);
+        border.set_style(sk::PaintStyle::Stroke);
+        border.set_anti_alias(true);
+        border.set_stroke_width(1.0);
+
+        for (level, labels) in self.rings.iter().enumerate() {
+            if labels.is_empty() {
+                continue;
+            }
+            let inner_radius = DEAD_ZONE_RADIUS + RING_WIDTH * level as f64;
+            let sweep = 360.0 / labels.len() as f64;
+            let highlighted = self.current.path.get(level).copied().flatten();
+            {
+                let canvas = ctx.surface.canvas();
+                for (position, &(index, _)) in labels.iter().enumerate() {
+                    let start = sector_angle(position, labels.len()).to_degrees() - 0.5 * sweep;
+                    let path = sector_path(center, inner_radius, inner_radius + RING_WIDTH, start, sweep);
+                    let color = if highlighted == Some(index) {
+                        colors.highlight
+                    } else {
+                        colors.background
+                    };
+                    let mut fill = sk::Paint::new(color.to_skia(), None);
+                    fill.set_anti_alias(true);
+                    canvas.draw_path(&path, &fill);
+                    canvas.draw_path(&path, &border);
+                }
+            }
+            for (_, label) in labels {
+                label.paint(ctx);
+            }
+        }
+
+        // center of the menu
+        let canvas = ctx.surface.canvas();
+        let mut fill = sk::Paint::new(colors.background.to_skia(), None);
+        fill.set_anti_alias(true);
+        let center = sk::Point::new(center.x as f32, center.y as f32);
+        canvas.draw_circle(center, DEAD_ZONE_RADIUS as f32, &fill);
+        canvas.draw_circle(center, DEAD_ZONE_RADIUS as f32, &border);
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new(format!(
+            "open: {}, path: {:?}",
+            self.current.center.is_some(),
+            self.current.path
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sectors() {
+        // the first item is at the top, and items go clockwise (the Y axis points down)
+        for count in 1..8 {
+            for position in 0..count {
+                assert_eq!(sector_at(sector_angle(position, count), count), position);
+            }
+        }
+        assert_eq!(sector_at(-PI / 2.0, 4), 0);
+        assert_eq!(sector_at(0.0, 4), 1);
+        assert_eq!(sector_at(PI / 2.0, 4), 2);
+        assert_eq!(sector_at(PI, 4), 3);
+        // boundary between the first and the last item
+        assert_eq!(sector_at(-PI / 2.0 - 0.1, 4), 0);
+        assert_eq!(sector_at(-PI / 2.0 - PI / 4.0 - 0.1, 4), 3);
+    }
+}
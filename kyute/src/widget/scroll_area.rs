@@ -1,135 +1,584 @@
+//! Scrollable container.
 use crate::{
-    drawing::ToSkia,
-    event::WheelDeltaMode,
-    widget::{grid::GridLayoutExt, prelude::*, DragController, Grid, LayoutInspector, Null, Viewport},
+    cache,
+    core::{send_utility_event, PaintDamage},
+    drawing::{overdraw, ToSkia},
+    event::{PointerEvent, PointerEventKind},
+    frame_debugger, theme,
+    widget::{
+        prelude::*,
+        scroll_linked::{ScrollBehavior, ScrollLinkedElement, ScrollPosition},
+    },
+    Color, InternalEvent, LayerPaintCtx, PointI, RectI, SizeI,
 };
+use kyute_shell::animation::Layer;
+use skia_safe as sk;
+use std::{
+    cell::{Cell, RefCell},
+    mem,
+    ops::Range,
+    sync::Arc,
+};
+
+const DEFAULT_LINE_HEIGHT_DIP: f64 = 20.0;
+const THUMB_WIDTH_DIP: f64 = 5.0;
+const MIN_THUMB_HEIGHT_DIP: f64 = 30.0;
+const THUMB_RADIUS_DIP: f64 = 2.0;
+/// #FF7F31
+const THUMB_COLOR: Color = Color::new(1.0, 0.498, 0.192, 1.0);
+
+/// Part of the contents rasterized on the content layer.
+struct Band {
+    /// Pixels of the band, kept to move them when the band moves.
+    surface: sk::Surface,
+    /// Position of the top of the band in the contents, in pixels.
+    top: i32,
+    /// Whether the band must be repainted entirely.
+    invalid: bool,
+}
+
+impl Band {
+    fn new(skia_gpu_context: &mut sk::gpu::DirectContext, size: SizeI) -> Band {
+        let surface = sk::Surface::new_render_target(
+            skia_gpu_context,
+            sk::Budgeted::No,
+            &sk::ImageInfo::new(
+                (size.width, size.height),
+                sk::ColorType::RGBA8888,
+                sk::AlphaType::Premul,
+                None,
+            ),
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("failed to create skia surface");
+        Band {
+            surface,
+            top: 0,
+            invalid: true,
+        }
+    }
+
+    fn size(&self) -> SizeI {
+        SizeI::new(self.surface.width(), self.surface.height())
+    }
+}
 
+/// Scroll position and compositor layers of a `ScrollArea`, kept across recompositions.
+struct ScrollState {
+    /// Offset of the top of the viewport in the contents, in DIPs.
+    offset: Cell<f64>,
+    /// Pointer position and scroll offset at the start of a drag of the thumb.
+    drag_start: Cell<Option<(f64, f64)>>,
+    /// Clips the contents to the viewport.
+    viewport_layer: Layer,
+    /// Shows the band of the contents, moved to scroll.
+    content_layer: Layer,
+    thumb_layer: Layer,
+    /// Size of the thumb last painted on the thumb layer, in pixels.
+    thumb_size: Cell<SizeI>,
+    band: RefCell<Option<Band>>,
+}
+
+impl ScrollState {
+    fn new() -> ScrollState {
+        ScrollState {
+            offset: Cell::new(0.0),
+            drag_start: Cell::new(None),
+            viewport_layer: Layer::new(),
+            content_layer: Layer::new(),
+            thumb_layer: Layer::new(),
+            thumb_size: Cell::new(SizeI::zero()),
+            band: RefCell::new(None),
+        }
+    }
+
+    /// Forces a repaint of the band on the next layer update.
+    fn invalidate(&self) {
+        if let Some(band) = self.band.borrow_mut().as_mut() {
+            band.invalid = true;
+        }
+    }
+}
+
+/// Shows a widget taller than the available space, with a vertical scroll bar.
+///
+/// The contents are rasterized on a compositor layer that is larger than the visible area: they're scrolled by moving
+/// the layer, without recomposing or repainting anything. Only the parts of the contents that are scrolled into this
+/// band are painted. The band is repainted entirely when the scroll area is recomposed, or when the contents request
+/// a repaint or a relayout.
+///
+/// Like other widgets with their own compositor layer, the scroll area is shown above everything painted by its
+/// ancestors, including siblings painted after it.
+///
+/// The positions of `ScrollLinked` widgets in the contents (sticky headers, scroll effects) are computed when the
+/// layers are updated, without relayouts.
 pub struct ScrollArea {
-    inner: LayoutInspector<Grid>,
+    id: WidgetId,
+    content: Arc<WidgetPod>,
+    state: Arc<ScrollState>,
     line_height_dip: f64,
-    scroll: Signal<f64>,
+    /// Size of the visible area during the last layout.
+    viewport_size: Cell<Size>,
+    /// Height of the contents during the last layout.
+    content_height: Cell<f64>,
+    scale_factor: Cell<f64>,
+    /// Scroll-linked widgets in the contents, sorted by position.
+    linked: RefCell<Vec<ScrollLinkedElement>>,
 }
 
-const DEFAULT_LINE_HEIGHT_DIP: f64 = 20.0;
-
 impl ScrollArea {
     #[composable]
     pub fn new(contents: impl Widget + 'static) -> ScrollArea {
-        #[state]
-        let mut tmp_pos = 0.0;
-        #[state]
-        let mut content_pos: f64 = 0.0;
+        let state = cache::once(|| Arc::new(ScrollState::new()));
+        // the contents may have changed since the last composition
+        state.invalidate();
 
-        // wheel scroll
-        let scroll = Signal::new();
+        let content = contents.arc_dyn_pod();
+        content.set_offset(Offset::new(0.0, -state.offset.get()));
 
-        // container grid: one row
-        let mut grid_container = LayoutInspector::new(Grid::with_template("1fr / 1fr 5px"));
+        ScrollArea {
+            id: WidgetId::here(),
+            content,
+            state,
+            line_height_dip: DEFAULT_LINE_HEIGHT_DIP,
+            viewport_size: Cell::new(Size::zero()),
+            content_height: Cell::new(0.0),
+            scale_factor: Cell::new(1.0),
+            linked: RefCell::new(Vec::new()),
+        }
+    }
 
-        // HACK: even if the content already fits in the grid container, we still have to wrap
-        // the content widget in a Viewport, because otherwise the size returned by the LayoutInspector
-        // will always be clamped to the size of the grid.
-        let mut content_viewport = Viewport::new(LayoutInspector::new(contents)).constrain_width();
+    /*pub fn line_height(mut self, line_height: Length) -> Self {
+        self.line_height = line_height.into();
+        self
+    }*/
 
-        // apply scroll to content pos
-        if let Some(scroll) = scroll.value() {
-            content_pos += scroll;
-        }
+    /// Returns the maximum scroll offset.
+    fn max_offset(&self) -> f64 {
+        (self.content_height.get() - self.viewport_size.get().height).max(0.0)
+    }
 
-        assert!(
-            content_viewport.content().size().is_finite(),
-            "the content widget of a ScrollArea should have finite dimensions"
+    /// Returns the bounds of the thumb, and the ratio between thumb and content movements, or `None` if the contents
+    /// fit in the viewport.
+    fn thumb(&self) -> Option<(Rect, f64)> {
+        let viewport = self.viewport_size.get();
+        let content_height = self.content_height.get();
+        if content_height <= viewport.height {
+            return None;
+        }
+        let thumb_height = (viewport.height * viewport.height / content_height).max(MIN_THUMB_HEIGHT_DIP);
+        let content_to_thumb = (viewport.height - thumb_height) / (content_height - viewport.height);
+        let bounds = Rect::new(
+            Point::new(
+                viewport.width - THUMB_WIDTH_DIP,
+                self.state.offset.get() * content_to_thumb,
+            ),
+            Size::new(THUMB_WIDTH_DIP, thumb_height),
         );
+        Some((bounds, content_to_thumb))
+    }
 
-        // v_height = viewport height
-        // c_height = content height
-        // t = thumb height
-        // t_p = thumb position
-        let viewport_height = grid_container.size().height;
-        let content_height = content_viewport.content().size().height;
-
-        if content_height <= viewport_height {
-            content_viewport.set_transform(Offset::new(0.0, 0.0).to_transform());
-            grid_container.inner_mut().insert(content_viewport.grid_area((0, ..)));
-            return ScrollArea {
-                inner: grid_container,
-                line_height_dip: DEFAULT_LINE_HEIGHT_DIP,
-                scroll,
-            };
+    fn scroll_to(&self, ctx: &mut EventCtx, offset: f64) {
+        let offset = offset.clamp(0.0, self.max_offset());
+        if offset != self.state.offset.get() {
+            self.state.offset.set(offset);
+            self.content.set_offset(Offset::new(0.0, -offset));
+            ctx.request_layer_update();
+        }
+    }
+
+    fn scroll_by(&self, ctx: &mut EventCtx, delta: f64) {
+        self.scroll_to(ctx, self.state.offset.get() + delta);
+    }
+
+    /// Handles drags of the thumb. Returns whether the event was handled.
+    fn thumb_event(&self, ctx: &mut EventCtx, p: &PointerEvent) -> bool {
+        match p.kind {
+            PointerEventKind::PointerDown => {
+                if let Some((bounds, _)) = self.thumb() {
+                    if bounds.contains(p.position) {
+                        self.state.drag_start.set(Some((p.position.y, self.state.offset.get())));
+                        ctx.capture_pointer();
+                        ctx.set_handled();
+                        return true;
+                    }
+                }
+            }
+            PointerEventKind::PointerMove => {
+                if let (Some((start_y, start_offset)), Some((_, content_to_thumb))) =
+                    (self.state.drag_start.get(), self.thumb())
+                {
+                    self.scroll_to(ctx, start_offset + (p.position.y - start_y) / content_to_thumb);
+                    ctx.set_handled();
+                    return true;
+                }
+            }
+            PointerEventKind::PointerUp => {
+                if self.state.drag_start.take().is_some() {
+                    ctx.set_handled();
+                    return true;
+                }
+            }
+            _ => {}
         }
+        false
+    }
 
-        let min_thumb_size = 30.0;
-        let thumb_size = (viewport_height * viewport_height / content_height).max(min_thumb_size);
-        let content_to_thumb = (viewport_height - thumb_size) / (content_height - viewport_height);
-        let thumb_pos = content_pos * content_to_thumb;
-        let content_max = content_height - viewport_height;
+    /// Paints the specified rows (in pixels) of the band.
+    fn paint_band(
+        &self,
+        band: &mut Band,
+        rows: Range<i32>,
+        skia_gpu_context: &mut sk::gpu::DirectContext,
+        scale_factor: f64,
+    ) {
+        let size = band.size();
+        let canvas = band.surface.canvas();
+        canvas.save();
+        canvas.clip_irect(
+            sk::IRect::new(0, rows.start, size.width, rows.end),
+            sk::ClipOp::Intersect,
+        );
+        canvas.clear(sk::Color4f::new(0.0, 0.0, 0.0, 0.0));
 
-        trace!("viewport_height={viewport_height}, content_height={content_height}, content_to_thumb={content_to_thumb}, thumb_pos={thumb_pos}, content_max={content_max}, thumb_size={thumb_size}");
+        frame_debugger::set_layer_owner(self.widget_id(), self.content.debug_name());
+        frame_debugger::begin_layer(size, scale_factor);
+        {
+            let mut ctx = PaintCtx::new(
+                &mut band.surface,
+                &self.state.content_layer,
+                scale_factor,
+                skia_gpu_context,
+            );
+            let bounds = Rect::new(
+                Point::origin(),
+                Size::new(self.viewport_size.get().width, self.content_height.get()),
+            );
+            // the contents are painted at their position in the band, not at the scroll offset used for events
+            let offset = self.content.transform();
+            self.content.set_transform(Transform::identity());
+            ctx.with_transform_and_clip(
+                &Offset::new(0.0, -band.top as f64 / scale_factor).to_transform(),
+                bounds,
+                None,
+                |ctx| self.content.paint(ctx),
+            );
+            self.content.set_transform(offset);
+            ctx.draw_overdraw_heatmap();
+        }
+        frame_debugger::end_layer(&mut band.surface);
+        band.surface.canvas().restore();
+    }
 
-        //.box_style(Style::new().radius(2.dip()).background(Color::from_hex("#FF7F31"))),
-        let scroll_thumb = DragController::new(content_pos, Null.fix_width(5.0).fix_height(thumb_size))
-            .on_delta(|start_pos, offset| {
-                content_pos = start_pos + offset.y / content_to_thumb;
-            })
-            .style("border-radius: 2px; background: #FF7F31;");
+    /// Returns the transform of a scroll-linked widget at the current scroll offset, relative to its position in
+    /// the layout.
+    fn linked_transform(&self, linked: &[ScrollLinkedElement], index: usize) -> Transform {
+        let element = &linked[index];
+        let offset = self.state.offset.get();
+        match element.behavior {
+            ScrollBehavior::Sticky => {
+                // the section of a sticky widget ends where the next one starts
+                let section_end = linked[index + 1..]
+                    .iter()
+                    .find(|next| matches!(next.behavior, ScrollBehavior::Sticky))
+                    .map_or(self.content_height.get(), |next| next.bounds.min_y());
+                let shift =
+                    (offset - element.bounds.min_y()).clamp(0.0, (section_end - element.bounds.max_y()).max(0.0));
+                Transform::translation(0.0, shift)
+            }
+            ScrollBehavior::Effect(ref effect) => effect(&ScrollPosition {
+                offset,
+                max_offset: self.max_offset(),
+                viewport: Rect::new(Point::new(0.0, offset), self.viewport_size.get()),
+                bounds: element.bounds,
+            }),
+        }
+    }
 
-        content_pos = content_pos.clamp(0.0, content_max);
-        content_viewport.set_transform(Offset::new(0.0, -content_pos).to_transform());
+    /// Shows the scroll-linked widgets on their layers, above the contents.
+    ///
+    /// If `repaint` is true, the layers are repainted even if their size hasn't changed.
+    fn update_linked_layers(&self, skia_gpu_context: &mut sk::gpu::DirectContext, repaint: bool) {
+        let scale_factor = self.scale_factor.get();
+        let offset = self.state.offset.get();
+        let linked = self.linked.borrow();
+        for (i, element) in linked.iter().enumerate() {
+            let transform = self.linked_transform(&linked, i);
+            // events are delivered to the widget where it is shown
+            element.content.set_transform(transform);
 
-        let scroll_bar = Viewport::new(scroll_thumb).transform(Offset::new(0.0, thumb_pos).to_transform());
+            let size = SizeI::new(
+                (element.bounds.width() * scale_factor).ceil() as i32,
+                (element.bounds.height() * scale_factor).ceil() as i32,
+            );
+            if size.is_empty() {
+                continue;
+            }
+            if repaint || element.layer.size() != size {
+                element.layer.set_size(size);
+                let mut layer_paint_ctx = LayerPaintCtx {
+                    skia_gpu_context: &mut *skia_gpu_context,
+                    damage: None,
+                };
+                layer_paint_ctx.paint_layer(&element.layer, scale_factor, |ctx| {
+                    element.content.set_transform(Transform::identity());
+                    element.content.paint(ctx);
+                    element.content.set_transform(transform);
+                });
+            }
 
-        grid_container.inner_mut().insert(content_viewport.grid_area((0, ..)));
-        grid_container.inner_mut().insert(scroll_bar.grid_area((0, 1)));
-        ScrollArea {
-            inner: grid_container,
-            scroll,
-            line_height_dip: DEFAULT_LINE_HEIGHT_DIP,
+            let t = transform
+                .then(&Offset::new(element.bounds.min_x(), element.bounds.min_y() - offset).to_transform())
+                .then_scale(scale_factor, scale_factor);
+            element.layer.set_transform(&Transform::new(
+                t.m11,
+                t.m12,
+                t.m21,
+                t.m22,
+                t.m31.round(),
+                t.m32.round(),
+            ));
+            self.state.viewport_layer.add_child(&element.layer);
         }
     }
 
-    /*pub fn line_height(mut self, line_height: Length) -> Self {
-        self.line_height = line_height.into();
-        self
-    }*/
+    /// Positions the layers at the current scroll offset, and paints the parts of the contents that are scrolled into
+    /// the band.
+    fn update_layers(&self, skia_gpu_context: &mut sk::gpu::DirectContext) {
+        let state = &*self.state;
+        let scale_factor = self.scale_factor.get();
+        let viewport = self.viewport_size.get();
+        let width = (viewport.width * scale_factor).ceil() as i32;
+        let height = (viewport.height * scale_factor).ceil() as i32;
+        let content_height = (self.content_height.get() * scale_factor).ceil() as i32;
+
+        state.viewport_layer.remove_all_children();
+        if width <= 0 || height <= 0 || content_height <= 0 {
+            return;
+        }
+        state
+            .viewport_layer
+            .set_clip(Some(RectI::new(PointI::origin(), SizeI::new(width, height))));
+
+        // --- contents ---
+        // the band covers the viewport, and one viewport height above and below it
+        let band_size = SizeI::new(width, content_height.min(3 * height));
+        let offset = ((state.offset.get() * scale_factor).round() as i32).clamp(0, (content_height - height).max(0));
+        let repaint_all = frame_debugger::is_capturing() || overdraw::repaint_all();
+
+        let mut band = state.band.borrow_mut();
+        if band.as_ref().map_or(false, |band| band.size() != band_size) {
+            *band = None;
+        }
+        let band = band.get_or_insert_with(|| Band::new(skia_gpu_context, band_size));
+
+        let visible = band.top <= offset && offset + height <= band.top + band_size.height;
+        let repaint_linked = band.invalid || repaint_all;
+        if band.invalid || repaint_all || !visible {
+            if state.content_layer.size() != band_size {
+                state.content_layer.set_size(band_size);
+            }
+
+            let top = (offset - height).clamp(0, content_height - band_size.height);
+            // pixels of the previous band move down by `shift`
+            let shift = band.top - top;
+            let rows = if band.invalid || repaint_all || shift.abs() >= band_size.height {
+                0..band_size.height
+            } else {
+                // move the pixels that are still valid, and repaint only the newly exposed rows
+                let snapshot = band.surface.image_snapshot();
+                let canvas = band.surface.canvas();
+                canvas.clear(sk::Color4f::new(0.0, 0.0, 0.0, 0.0));
+                canvas.draw_image(&snapshot, (0.0, shift as f32), None);
+                if shift > 0 {
+                    0..shift
+                } else {
+                    band_size.height + shift..band_size.height
+                }
+            };
+            band.top = top;
+            band.invalid = false;
+            self.paint_band(band, rows, skia_gpu_context, scale_factor);
+
+            let surface = &mut band.surface;
+            let mut layer_paint_ctx = LayerPaintCtx {
+                skia_gpu_context: &mut *skia_gpu_context,
+                damage: None,
+            };
+            layer_paint_ctx.paint_layer(&state.content_layer, scale_factor, |ctx| {
+                surface.draw(
+                    ctx.surface.canvas(),
+                    (0, 0),
+                    sk::SamplingOptions::new(sk::FilterMode::Nearest, sk::MipmapMode::None),
+                    None,
+                );
+            });
+        }
+
+        state
+            .content_layer
+            .set_transform(&Offset::new(0.0, (band.top - offset) as f64).to_transform());
+        state.viewport_layer.add_child(&state.content_layer);
+        self.update_linked_layers(skia_gpu_context, repaint_linked);
+
+        // --- scroll bar ---
+        if let Some((thumb, _)) = self.thumb() {
+            let thumb_size = SizeI::new(
+                ((thumb.width() * scale_factor).round() as i32).max(1),
+                ((thumb.height() * scale_factor).round() as i32).max(1),
+            );
+            if state.thumb_size.get() != thumb_size || repaint_all {
+                state.thumb_layer.set_size(thumb_size);
+                state.thumb_size.set(thumb_size);
+                let mut layer_paint_ctx = LayerPaintCtx {
+                    skia_gpu_context: &mut *skia_gpu_context,
+                    damage: None,
+                };
+                layer_paint_ctx.paint_layer(&state.thumb_layer, scale_factor, |ctx| {
+                    let mut paint = sk::Paint::new(THUMB_COLOR.to_skia(), None);
+                    paint.set_anti_alias(true);
+                    let radius = (THUMB_RADIUS_DIP * scale_factor) as f32;
+                    ctx.surface.canvas().draw_round_rect(
+                        sk::Rect::from_wh(thumb_size.width as f32, thumb_size.height as f32),
+                        radius,
+                        radius,
+                        &paint,
+                    );
+                });
+            }
+            let position = Offset::new(
+                (thumb.min_x() * scale_factor).round(),
+                (thumb.min_y() * scale_factor).round(),
+            );
+            state.thumb_layer.set_transform(&position.to_transform());
+            state.viewport_layer.add_child(&state.thumb_layer);
+        }
+    }
 }
 
 impl Widget for ScrollArea {
     fn widget_id(&self) -> Option<WidgetId> {
-        Widget::widget_id(&self.inner)
+        Some(self.id)
     }
 
     fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
-        Widget::layout(&self.inner, ctx, constraints, env)
+        // take all the available space; the width of the contents is constrained, but not their height
+        let size = Size::new(
+            constraints.finite_max_width().unwrap_or(0.0),
+            constraints.finite_max_height().unwrap_or(0.0),
+        );
+        let content_constraints = LayoutParams {
+            min: Size::zero(),
+            max: Size::new(size.width, f64::INFINITY),
+            ..*constraints
+        };
+        let content_height = self
+            .content
+            .layout(ctx, &content_constraints, env)
+            .measurements
+            .size
+            .height;
+        assert!(
+            content_height.is_finite(),
+            "the content widget of a ScrollArea should have finite dimensions"
+        );
+
+        if !ctx.speculative {
+            if size != self.viewport_size.get()
+                || content_height != self.content_height.get()
+                || ctx.scale_factor != self.scale_factor.get()
+            {
+                self.state.invalidate();
+            }
+            self.viewport_size.set(size);
+            self.content_height.set(content_height);
+            self.scale_factor.set(ctx.scale_factor);
+
+            // the contents may have shrunk
+            let offset = self.state.offset.get().clamp(0.0, self.max_offset());
+            self.state.offset.set(offset);
+
+            // find the scroll-linked widgets, at their position in the contents
+            let mut linked = Vec::new();
+            self.content.set_transform(Transform::identity());
+            send_utility_event(
+                &*self.content,
+                &mut Event::Internal(InternalEvent::CollectScrollLinked { elements: &mut linked }),
+                env,
+            );
+            self.content.set_offset(Offset::new(0.0, -offset));
+            linked.sort_by(|a, b| a.bounds.min_y().total_cmp(&b.bounds.min_y()));
+            *self.linked.borrow_mut() = linked;
+        }
+
+        Geometry::new(size)
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
-        Widget::route_event(&self.inner, ctx, event, env);
+        // scroll-linked widgets in the contents move with this scroll area, not with the enclosing one
+        if let Event::Internal(InternalEvent::CollectScrollLinked { .. }) = *event {
+            return;
+        }
 
-        if !ctx.handled {
-            if let Event::Wheel(wheel) = event {
-                match wheel.delta_mode {
-                    WheelDeltaMode::Pixel => {
-                        self.scroll.signal(-wheel.delta_y);
-                    }
-                    WheelDeltaMode::Line => {
-                        self.scroll.signal(-self.line_height_dip * wheel.delta_y);
-                    }
-                    WheelDeltaMode::Page => {
-                        // TODO
-                        warn!("WheelDeltaMode::Page unimplemented");
-                    }
+        // the thumb is above the contents
+        if let Event::Pointer(ref p) = *event {
+            if self.thumb_event(ctx, p) {
+                return;
+            }
+        }
+
+        // repaints and relayouts requested by the contents invalidate the band
+        let paint_damage = mem::take(&mut ctx.paint_damage);
+        self.content.route_event(ctx, event, env);
+        if ctx.paint_damage != PaintDamage::None || ctx.relayout || ctx.child_relayout {
+            self.state.invalidate();
+            ctx.request_layer_update();
+        }
+        ctx.paint_damage.merge_up(paint_damage);
+
+        if let Event::Internal(InternalEvent::UpdateLayers {
+            ref mut skia_direct_context,
+        }) = *event
+        {
+            self.update_layers(skia_direct_context);
+            return;
+        }
+
+        // scroll to make a rectangle visible if requested by the contents
+        if let Some(rect) = ctx.take_scroll_into_view_request() {
+            let viewport_height = self.viewport_size.get().height;
+            if rect.min_y() < 0.0 {
+                self.scroll_by(ctx, rect.min_y());
+            } else if rect.max_y() > viewport_height {
+                self.scroll_by(ctx, (rect.max_y() - viewport_height).min(rect.min_y()));
+            }
+        }
+
+        if !ctx.handled() {
+            if let Event::Wheel(ref wheel) = *event {
+                let delta = wheel.scroll_delta(
+                    &theme::scroll_settings(env),
+                    Size::new(self.line_height_dip, self.line_height_dip),
+                    self.viewport_size.get(),
+                );
+                // horizontal movements are left to the ancestors
+                if delta.y != 0.0 {
+                    self.scroll_by(ctx, delta.y);
                 }
             }
         }
     }
 
     fn paint(&self, ctx: &mut PaintCtx) {
-        let bounds = ctx.bounds;
-        ctx.surface.canvas().save();
-        ctx.surface
-            .canvas()
-            .clip_rect(bounds.to_skia(), skia_safe::ClipOp::Intersect, false);
-        self.inner.paint(ctx);
-        ctx.surface.canvas().restore();
+        // the layer transform is in DIPs, but compositor layers are positioned in pixels
+        let t = *ctx.layer_transform();
+        let s = ctx.scale_factor;
+        let transform = Transform::new(t.m11, t.m12, t.m21, t.m22, (t.m31 * s).round(), (t.m32 * s).round());
+        self.state.viewport_layer.set_transform(&transform);
+        ctx.parent_layer().add_child(&self.state.viewport_layer);
+        self.update_layers(ctx.skia_direct_context);
     }
 }
@@ -0,0 +1,152 @@
+//! Widgets that move with the scroll position of the enclosing `ScrollArea`: sticky headers, parallax effects.
+use crate::{cache, event::InternalEvent, widget::prelude::*};
+use kyute_shell::animation::Layer;
+use std::{cell::Cell, fmt, sync::Arc};
+
+/// Scroll position of a `ScrollArea`, as seen by a scroll effect (see `ScrollLinked::effect`).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScrollPosition {
+    /// Offset of the top of the viewport in the contents, in DIPs.
+    pub offset: f64,
+    /// Maximum scroll offset.
+    pub max_offset: f64,
+    /// Visible part of the contents, in the coordinates of the contents.
+    pub viewport: Rect,
+    /// Bounds of the widget at its position in the layout, in the coordinates of the contents.
+    pub bounds: Rect,
+}
+
+impl ScrollPosition {
+    /// Returns how far the scroll area is scrolled, from 0.0 (top) to 1.0 (bottom).
+    pub fn progress(&self) -> f64 {
+        if self.max_offset > 0.0 {
+            (self.offset / self.max_offset).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns how far the widget has moved through the viewport, from 0.0 when its top edge enters at the bottom of
+    /// the viewport, to 1.0 when its bottom edge leaves at the top.
+    pub fn element_progress(&self) -> f64 {
+        let distance = self.viewport.height() + self.bounds.height();
+        if distance > 0.0 {
+            ((self.viewport.max_y() - self.bounds.min_y()) / distance).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum ScrollBehavior {
+    Sticky,
+    Effect(Arc<dyn Fn(&ScrollPosition) -> Transform>),
+}
+
+/// A `ScrollLinked` widget in the contents of a `ScrollArea`.
+///
+/// See `InternalEvent::CollectScrollLinked`.
+pub struct ScrollLinkedElement {
+    pub(crate) content: Arc<WidgetPod>,
+    pub(crate) layer: Layer,
+    pub(crate) behavior: ScrollBehavior,
+    /// Position in the layout, in the coordinates of the contents of the scroll area.
+    pub(crate) bounds: Rect,
+}
+
+impl fmt::Debug for ScrollLinkedElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScrollLinkedElement")
+            .field("id", &self.content.widget_id())
+            .field("bounds", &self.bounds)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A widget whose position depends on the scroll offset of the enclosing `ScrollArea`.
+///
+/// The scroll area shows the widget on its own compositor layer, above the rest of the contents, and computes its
+/// transform each time it scrolls: this doesn't cause recompositions, relayouts or repaints. Pointer events are
+/// delivered to the widget where it is shown.
+///
+/// Outside of a scroll area, the widget is shown at its position in the layout.
+pub struct ScrollLinked {
+    content: Arc<WidgetPod>,
+    layer: Layer,
+    behavior: ScrollBehavior,
+    size: Cell<Size>,
+    /// Whether the enclosing scroll area shows the widget on its layer, instead of painting it with the rest of the
+    /// contents.
+    lifted: Cell<bool>,
+}
+
+impl ScrollLinked {
+    /// Pins the widget to the top edge of the viewport while its section is visible.
+    ///
+    /// The section of a sticky widget extends from its position in the layout to the next sticky widget of the scroll
+    /// area, or to the end of the contents: when the section scrolls out of view, the widget is pushed up by the next one.
+    #[composable]
+    pub fn sticky(inner: impl Widget + 'static) -> ScrollLinked {
+        ScrollLinked::with_behavior(inner, ScrollBehavior::Sticky)
+    }
+
+    /// Applies a transform computed from the scroll position, e.g. for parallax or collapsing header effects.
+    ///
+    /// The transform is in the local coordinate space of the widget, in DIPs, and doesn't affect layout.
+    #[composable]
+    pub fn effect(
+        inner: impl Widget + 'static,
+        effect: impl Fn(&ScrollPosition) -> Transform + 'static,
+    ) -> ScrollLinked {
+        ScrollLinked::with_behavior(inner, ScrollBehavior::Effect(Arc::new(effect)))
+    }
+
+    #[composable]
+    fn with_behavior(inner: impl Widget + 'static, behavior: ScrollBehavior) -> ScrollLinked {
+        ScrollLinked {
+            content: inner.arc_dyn_pod(),
+            layer: cache::once(Layer::new),
+            behavior,
+            size: Cell::new(Size::zero()),
+            lifted: Cell::new(false),
+        }
+    }
+}
+
+impl Widget for ScrollLinked {
+    fn widget_id(&self) -> Option<WidgetId> {
+        self.content.widget_id()
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let geometry = self.content.layout(ctx, constraints, env);
+        if !ctx.speculative {
+            self.size.set(geometry.measurements.size);
+        }
+        geometry
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Event::Internal(InternalEvent::CollectScrollLinked { ref mut elements }) = *event {
+            elements.push(ScrollLinkedElement {
+                content: self.content.clone(),
+                layer: self.layer.clone(),
+                behavior: self.behavior.clone(),
+                bounds: ctx
+                    .window_transform()
+                    .outer_transformed_rect(&Rect::new(Point::origin(), self.size.get())),
+            });
+            self.lifted.set(true);
+            // scroll-linked widgets nested in this one move with it
+            return;
+        }
+        self.content.route_event(ctx, event, env)
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        if !self.lifted.get() {
+            self.content.paint(ctx)
+        }
+    }
+}
@@ -0,0 +1,418 @@
+//! Application settings window scaffold.
+//!
+//! `SettingsView` shows settings grouped in categories (listed in a sidebar) and sections, with a search box
+//! that filters settings by title. Settings are edited on a draft copy of the values stored in a `SettingsStore`;
+//! the draft is written back (and persisted) only when the user clicks "Apply", or discarded with "Revert".
+use crate::{
+    cache,
+    cache::State,
+    theme,
+    widget::{
+        form::{FieldOptions, FormField, Row, Section},
+        grid::TrackBreadth,
+        prelude::*,
+        Button, Clickable, Form, Grid, Null, ScrollArea, Text, TextEdit,
+    },
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use thiserror::Error;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Store
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Errors returned by `SettingsStore::apply`.
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("invalid settings: {0}")]
+    Validation(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("the settings file {} could not be loaded and won't be overwritten: {message}", .path.display())]
+    LoadFailed { path: PathBuf, message: String },
+}
+
+/// Settings values, persisted to a JSON file, and a draft copy being edited.
+pub struct SettingsStore<T> {
+    path: Option<PathBuf>,
+    applied: State<T>,
+    draft: State<T>,
+    validator: Option<Arc<dyn Fn(&T) -> Result<(), String>>>,
+    /// Set if the settings file exists but couldn't be loaded.
+    load_error: Option<String>,
+}
+
+impl<T> SettingsStore<T>
+where
+    T: Clone + PartialEq + Serialize + DeserializeOwned + Default + 'static,
+{
+    /// Creates a store for settings persisted in the specified file.
+    ///
+    /// The settings are read from the file the first time this function is called at this position in the call tree.
+    /// If the file doesn't exist, default values are used. If it can't be read or parsed, default values are used
+    /// as well, but the file is left untouched: `apply` returns `SettingsError::LoadFailed` instead of
+    /// overwriting it.
+    #[composable]
+    pub fn load(path: impl Into<PathBuf>) -> SettingsStore<T> {
+        let path = path.into();
+        let mut load_error = None;
+        let applied = cache::state(|| match read_settings_file(&path) {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(err) => {
+                warn!("failed to load settings file {}: {}", path.display(), err);
+                load_error = Some(err.to_string());
+                T::default()
+            }
+        });
+        let load_error = cache::once(move || load_error);
+        let draft = cache::state(|| applied.get());
+        SettingsStore {
+            path: Some(path),
+            applied,
+            draft,
+            validator: None,
+            load_error,
+        }
+    }
+
+    /// Creates a store for settings that aren't persisted.
+    #[composable]
+    pub fn in_memory(init: impl FnOnce() -> T) -> SettingsStore<T> {
+        let applied = cache::state(init);
+        let draft = cache::state(|| applied.get());
+        SettingsStore {
+            path: None,
+            applied,
+            draft,
+            validator: None,
+            load_error: None,
+        }
+    }
+
+    /// Sets a function that checks the draft before it is applied.
+    ///
+    /// The function returns an error message if the settings are invalid.
+    pub fn validator(mut self, validator: impl Fn(&T) -> Result<(), String> + 'static) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Returns the current (applied) settings.
+    pub fn applied(&self) -> T {
+        self.applied.get()
+    }
+
+    /// Returns the state holding the draft settings, edited by the settings view.
+    pub fn draft(&self) -> &State<T> {
+        &self.draft
+    }
+
+    /// Returns whether the draft has changes that haven't been applied.
+    pub fn is_modified(&self) -> bool {
+        self.draft.get() != self.applied.get()
+    }
+
+    /// Validates the draft, and if it is valid, makes it the current settings and writes it to the settings file.
+    pub fn apply(&self) -> Result<(), SettingsError> {
+        let draft = self.draft.get();
+        if let Some(ref validator) = self.validator {
+            validator(&draft).map_err(SettingsError::Validation)?;
+        }
+        if let Some(ref path) = self.path {
+            if let Some(ref message) = self.load_error {
+                return Err(SettingsError::LoadFailed {
+                    path: path.clone(),
+                    message: message.clone(),
+                });
+            }
+            let json = serde_json::to_string_pretty(&draft)?;
+            fs::write(path, json)?;
+        }
+        self.applied.set(draft);
+        Ok(())
+    }
+
+    /// Discards the changes made to the draft.
+    pub fn revert(&self) {
+        self.draft.set(self.applied.get());
+    }
+}
+
+/// Reads settings from a JSON file. Returns `None` if the file doesn't exist.
+fn read_settings_file<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, SettingsError> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Settings
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A setting shown in a `SettingsView`: a form row, with a title used for searching.
+pub struct Setting {
+    title: String,
+    row: Row,
+}
+
+impl Setting {
+    /// Creates a setting from an arbitrary form row.
+    pub fn new(title: impl Into<String>, row: impl Into<Row>) -> Setting {
+        Setting {
+            title: title.into(),
+            row: row.into(),
+        }
+    }
+
+    /// Creates a setting that edits a field of the draft settings.
+    ///
+    /// `field` returns the edited field in the settings value. The editor is chosen from the type of the field
+    /// (see `FormField`).
+    #[composable]
+    pub fn field<T, F>(title: &str, draft: &State<T>, field: impl Fn(&mut T) -> &mut F) -> Setting
+    where
+        T: Clone + 'static,
+        F: FormField,
+    {
+        Self::field_with_options(title, draft, &FieldOptions::default(), field)
+    }
+
+    /// Same as `field`, but with options for the editor (e.g. the valid range of numeric values).
+    #[composable]
+    pub fn field_with_options<T, F>(
+        title: &str,
+        draft: &State<T>,
+        options: &FieldOptions,
+        field: impl Fn(&mut T) -> &mut F,
+    ) -> Setting
+    where
+        T: Clone + 'static,
+        F: FormField,
+    {
+        let mut value = draft.get();
+        let (row, new_value) = F::form_row(title, field(&mut value), options);
+        if let Some(new_value) = new_value {
+            *field(&mut value) = new_value;
+            draft.set(value);
+        }
+        Setting {
+            title: title.to_string(),
+            row,
+        }
+    }
+}
+
+/// A group of settings with a title.
+pub struct SettingsSection {
+    title: String,
+    settings: Vec<Setting>,
+}
+
+impl SettingsSection {
+    pub fn new(title: impl Into<String>, settings: impl IntoIterator<Item = Setting>) -> SettingsSection {
+        SettingsSection {
+            title: title.into(),
+            settings: settings.into_iter().collect(),
+        }
+    }
+}
+
+/// A category of settings, shown in the sidebar of a `SettingsView`.
+pub struct SettingsCategory {
+    name: String,
+    sections: Vec<SettingsSection>,
+}
+
+impl SettingsCategory {
+    pub fn new(name: impl Into<String>) -> SettingsCategory {
+        SettingsCategory {
+            name: name.into(),
+            sections: vec![],
+        }
+    }
+
+    /// Adds a section to the category.
+    pub fn section(mut self, title: impl Into<String>, settings: impl IntoIterator<Item = Setting>) -> Self {
+        self.sections.push(SettingsSection::new(title, settings));
+        self
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// SettingsView
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Settings window contents.
+///
+/// Layout:
+/// - a search box at the top
+/// - a sidebar with the list of categories on the left
+/// - the sections of the selected category (or the settings matching the search, in all categories) in a scroll area
+/// - "Revert" and "Apply" buttons at the bottom
+#[derive(Widget)]
+pub struct SettingsView {
+    inner: Grid,
+}
+
+impl SettingsView {
+    /// Creates the settings view.
+    ///
+    /// The settings in `categories` should edit the draft of `store` (see `Setting::field`).
+    #[composable]
+    pub fn new<T>(store: &SettingsStore<T>, categories: Vec<SettingsCategory>) -> SettingsView
+    where
+        T: Clone + PartialEq + Serialize + DeserializeOwned + Default + 'static,
+    {
+        #[state]
+        let mut selected_category = 0usize;
+        #[state]
+        let mut filter = String::new();
+        #[state]
+        let mut error: Option<String> = None;
+
+        let mut grid = Grid::with_template("auto 1fr auto / 180px 1fr");
+
+        // search box
+        let search = TextEdit::new(filter.clone()).on_text_changed(|text| filter = text.to_string());
+        grid.place((0, ..), 0, search.padding(4.dip()).arc_dyn_pod());
+
+        // sidebar
+        let mut sidebar = Grid::column(TrackBreadth::Flex(1.0));
+        sidebar.set_implicit_row_size(24.dip());
+        for (i, category) in categories.iter().enumerate() {
+            cache::scoped(i, || {
+                let text = Text::new(category.name.clone()).vertical_alignment(Alignment::CENTER);
+                let item = if i == selected_category && filter.is_empty() {
+                    Clickable::new(text.text_color(theme::palette::BLUE_600)).arc_dyn_pod()
+                } else {
+                    Clickable::new(text)
+                        .on_click(|| {
+                            selected_category = i;
+                            filter.clear();
+                        })
+                        .arc_dyn_pod()
+                };
+                sidebar.insert(item);
+            });
+        }
+        grid.place((1, 0), 0, sidebar.padding(4.dip()).arc_dyn_pod());
+
+        // sections
+        let filter_lowercase = filter.to_lowercase();
+        let searching = !filter_lowercase.is_empty();
+        let mut rows: Vec<Row> = vec![];
+        for (i, category) in categories.into_iter().enumerate() {
+            if !searching && i != selected_category {
+                continue;
+            }
+            for section in category.sections {
+                let settings: Vec<Row> = section
+                    .settings
+                    .into_iter()
+                    .filter(|s| !searching || s.title.to_lowercase().contains(&filter_lowercase))
+                    .map(|s| s.row)
+                    .collect();
+                if settings.is_empty() {
+                    continue;
+                }
+                // show the category in the section title when showing search results from all categories
+                let title = if searching {
+                    format!("{} › {}", category.name, section.title)
+                } else {
+                    section.title
+                };
+                rows.push(Section::new(Text::new(title), settings).into());
+            }
+        }
+        let content: Arc<WidgetPod> = if rows.is_empty() {
+            Text::new("No matching settings").centered().arc_dyn_pod()
+        } else {
+            ScrollArea::new(Form::new(rows).padding(8.dip())).fill().arc_dyn_pod()
+        };
+        grid.place((1, 1), 0, content);
+
+        // apply / revert
+        let mut buttons = Grid::with_template("auto / 1fr auto auto");
+        buttons.set_column_gap(4.dip());
+        let status: Arc<WidgetPod> = if let Some(ref error) = error {
+            Text::new(error.clone())
+                .color(theme::palette::RED_700)
+                .vertical_alignment(Alignment::CENTER)
+                .arc_dyn_pod()
+        } else if store.is_modified() {
+            Text::new("Unsaved changes")
+                .vertical_alignment(Alignment::CENTER)
+                .arc_dyn_pod()
+        } else {
+            Null.arc_dyn_pod()
+        };
+        buttons.place((0, 0), 0, status);
+        let revert = Button::new("Revert").on_click(|| {
+            store.revert();
+            error = None;
+        });
+        buttons.place((0, 1), 0, revert.arc_dyn_pod());
+        let apply = Button::new("Apply").on_click(|| match store.apply() {
+            Ok(()) => error = None,
+            Err(SettingsError::Validation(message)) => error = Some(message),
+            Err(err) => {
+                warn!("failed to save settings: {}", err);
+                error = Some(err.to_string())
+            }
+        });
+        buttons.place((0, 2), 0, apply.arc_dyn_pod());
+        grid.place((2, ..), 0, buttons.padding(4.dip()).arc_dyn_pod());
+
+        SettingsView { inner: grid }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{noop_waker, Cache};
+
+    #[test]
+    fn unreadable_settings_file_is_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("kyute-settings-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let valid = dir.join("valid.json");
+        let invalid = dir.join("invalid.json");
+        let missing = dir.join("missing.json");
+        fs::write(&valid, "[1, 2]").unwrap();
+        fs::write(&invalid, "[1, ").unwrap();
+        let _ = fs::remove_file(&missing);
+
+        let env = Environment::new();
+        let mut cache = Cache::new(noop_waker());
+        let (valid_store, invalid_store, missing_store) = cache.recompose(&env, || {
+            (
+                SettingsStore::<Vec<i32>>::load(&valid),
+                SettingsStore::<Vec<i32>>::load(&invalid),
+                SettingsStore::<Vec<i32>>::load(&missing),
+            )
+        });
+        assert_eq!(valid_store.applied(), vec![1, 2]);
+        assert_eq!(invalid_store.applied(), Vec::<i32>::new());
+        assert_eq!(missing_store.applied(), Vec::<i32>::new());
+
+        invalid_store.draft().set(vec![3]);
+        assert!(matches!(invalid_store.apply(), Err(SettingsError::LoadFailed { .. })));
+        assert_eq!(fs::read_to_string(&invalid).unwrap(), "[1, ");
+
+        missing_store.draft().set(vec![4]);
+        missing_store.apply().unwrap();
+        assert_eq!(read_settings_file::<Vec<i32>>(&missing).unwrap(), Some(vec![4]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
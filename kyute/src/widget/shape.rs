@@ -1,4 +1,10 @@
-use crate::{drawing, drawing::PaintCtxExt, style, widget::prelude::*, LengthOrPercentage, Rect};
+use crate::{
+    drawing,
+    drawing::PaintCtxExt,
+    style,
+    widget::{prelude::*, styled_box::paint_style_error_badge},
+    LengthOrPercentage, Rect,
+};
 use std::{
     cell::{Cell, RefCell},
     convert::TryInto,
@@ -10,6 +16,8 @@ pub struct Shape {
     paint: style::Image,
     computed_shape: Cell<drawing::Shape>,
     computed_paint: RefCell<drawing::Paint>,
+    /// Whether the paint failed to parse (see `WidgetExt::background`).
+    invalid: bool,
 }
 
 impl Shape {
@@ -19,8 +27,15 @@ impl Shape {
             paint,
             computed_shape: Default::default(),
             computed_paint: Default::default(),
+            invalid: false,
         }
     }
+
+    /// Marks the shape as having an invalid paint, which paints an error badge in debug builds.
+    pub(crate) fn invalid(mut self) -> Shape {
+        self.invalid = true;
+        self
+    }
 }
 
 impl Widget for Shape {
@@ -69,5 +84,8 @@ impl Widget for Shape {
     fn paint(&self, ctx: &mut PaintCtx) {
         let paint = self.computed_paint.borrow();
         ctx.fill_shape(&self.computed_shape.get(), &*paint);
+        if self.invalid {
+            paint_style_error_badge(ctx);
+        }
     }
 }
@@ -1,6 +1,19 @@
 //! Sliders provide a way to make a value vary linearly between two bounds by dragging a knob along
 //! a line.
-use crate::{event::PointerEventKind, widget::prelude::*, Signal};
+//!
+//! `Slider` and `RangeSlider` are complete widgets with tick marks and keyboard support. `SliderBase` only handles
+//! the dragging of a custom knob over a custom background.
+use crate::{
+    accessibility::{Role, Semantics},
+    cache,
+    drawing::ToSkia,
+    event::{PointerButton, PointerEventKind},
+    theme,
+    widget::{prelude::*, Text},
+    Color, Signal, State,
+};
+use keyboard_types::{Key, KeyState};
+use skia_safe as sk;
 use std::{cell::Cell, sync::Arc};
 
 /// Utility class representing a slider track on which a knob can move.
@@ -330,4 +343,615 @@ impl Widget for SliderBase {
         self.background.paint(ctx);
         self.knob.paint(ctx);
     }
+
+    fn semantics(&self) -> Option<Semantics> {
+        Some(Semantics::new(Role::Slider).numeric_value(self.position, 0.0, 1.0))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+
+/// Diameter of the thumbs.
+const THUMB_SIZE: f64 = 16.0;
+/// Thickness of the track line.
+const TRACK_THICKNESS: f64 = 4.0;
+/// Length of the tick marks.
+const TICK_LENGTH: f64 = 4.0;
+/// Space between the thumbs and the tick marks, and between the tick marks and their labels.
+const TICK_SPACING: f64 = 2.0;
+/// Length of a slider when the constraints are unbounded in the direction of the track.
+const DEFAULT_LENGTH: f64 = 160.0;
+/// Maximum number of tick marks, to avoid creating millions of them by mistake with a small interval.
+const MAX_TICKS: usize = 1000;
+/// Number of increments by which PageUp and PageDown move a thumb.
+const PAGE_INCREMENTS: f64 = 10.0;
+
+/// Bounds and step of the values of a slider.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct SliderRange {
+    min: f64,
+    max: f64,
+    step: Option<f64>,
+}
+
+impl SliderRange {
+    /// Maps a value to the [0,1] interval.
+    fn normalize(&self, value: f64) -> f64 {
+        if self.max > self.min {
+            ((value - self.min) / (self.max - self.min)).max(0.0).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Maps a value of the [0,1] interval to the range.
+    fn denormalize(&self, t: f64) -> f64 {
+        self.min + (self.max - self.min) * t
+    }
+
+    /// Clamps a value to the range and rounds it to the nearest step.
+    fn snap(&self, value: f64) -> f64 {
+        let value = value.max(self.min).min(self.max);
+        match self.step {
+            Some(step) if step > 0.0 => (self.min + ((value - self.min) / step).round() * step).min(self.max),
+            _ => value,
+        }
+    }
+
+    /// Amount by which the arrow keys move a thumb: the step, or a hundredth of the range if there's none.
+    fn increment(&self) -> f64 {
+        match self.step {
+            Some(step) if step > 0.0 => step,
+            _ => 0.01 * (self.max - self.min),
+        }
+    }
+
+    /// Returns the value resulting from pressing a key when a thumb is at `value`, or `None` if the key doesn't move
+    /// thumbs.
+    fn key_value(&self, value: f64, key: &Key) -> Option<f64> {
+        let increment = self.increment();
+        let new_value = match key {
+            Key::ArrowRight | Key::ArrowUp => value + increment,
+            Key::ArrowLeft | Key::ArrowDown => value - increment,
+            Key::PageUp => value + PAGE_INCREMENTS * increment,
+            Key::PageDown => value - PAGE_INCREMENTS * increment,
+            Key::Home => self.min,
+            Key::End => self.max,
+            _ => return None,
+        };
+        Some(self.snap(new_value))
+    }
+
+    /// Returns the values of the tick marks placed every `interval`, starting from the minimum.
+    fn ticks(&self, interval: f64) -> Vec<f64> {
+        if !(interval > 0.0) || !(self.max >= self.min) {
+            return vec![];
+        }
+        // tolerate rounding errors on the last tick
+        let count = ((self.max - self.min) / interval + 1e-9).floor() as usize + 1;
+        (0..count.min(MAX_TICKS))
+            .map(|i| self.min + i as f64 * interval)
+            .collect()
+    }
+}
+
+/// Returns the thumb closest to a position on the track. `positions` are the positions of the thumbs, in
+/// increasing order.
+///
+/// When several thumbs are at the same position, returns the one that can move towards the pointer.
+fn nearest_thumb(positions: &[f64], position: f64) -> usize {
+    let mut nearest = 0;
+    let mut nearest_distance = f64::INFINITY;
+    for (i, &p) in positions.iter().enumerate() {
+        let distance = (p - position).abs();
+        if distance < nearest_distance || (distance == nearest_distance && position > p) {
+            nearest = i;
+            nearest_distance = distance;
+        }
+    }
+    nearest
+}
+
+/// Clamps the new value of a thumb between the values of its neighbors, so that thumbs can't cross each other.
+fn constrain_thumb(values: &[f64], thumb: usize, value: f64) -> f64 {
+    let low = if thumb > 0 {
+        values[thumb - 1]
+    } else {
+        f64::NEG_INFINITY
+    };
+    let high = values.get(thumb + 1).copied().unwrap_or(f64::INFINITY);
+    value.max(low).min(high)
+}
+
+/// Colors of the parts of a slider, taken from the theme.
+#[derive(Copy, Clone, Debug)]
+struct SliderColors {
+    track: Color,
+    fill: Color,
+    thumb: Color,
+    thumb_border: Color,
+    tick: Color,
+}
+
+impl SliderColors {
+    fn from_env(env: &Environment) -> SliderColors {
+        SliderColors {
+            track: env.get(&theme::BORDER_COLOR).unwrap_or(theme::palette::GREY_500),
+            fill: env.get(&theme::ACCENT_COLOR).unwrap_or(theme::palette::BLUE_500),
+            thumb: env
+                .get(&theme::CONTENT_BACKGROUND_COLOR)
+                .unwrap_or(Color::new(1.0, 1.0, 1.0, 1.0)),
+            thumb_border: env.get(&theme::BORDER_COLOR).unwrap_or(theme::palette::GREY_500),
+            tick: env
+                .get(&theme::SECONDARY_TEXT_COLOR)
+                .unwrap_or(theme::palette::GREY_500),
+        }
+    }
+}
+
+/// Track, thumbs and tick marks of `Slider` and `RangeSlider`.
+struct SliderBody {
+    id: WidgetId,
+    orientation: Orientation,
+    range: SliderRange,
+    /// Values of the thumbs, in increasing order.
+    values: Vec<f64>,
+    ticks: Vec<f64>,
+    /// Labels of the tick marks: either empty or one for each tick.
+    tick_labels: Vec<Arc<WidgetPod>>,
+    track: Cell<SliderTrack>,
+    /// Thumb being dragged, or moved with the keyboard.
+    active_thumb: State<usize>,
+    focused: State<bool>,
+    /// Index and new value of the moved thumb.
+    thumb_moved: Signal<(usize, f64)>,
+    colors: SliderColors,
+}
+
+impl SliderBody {
+    #[composable]
+    fn new(min: f64, max: f64, values: Vec<f64>) -> SliderBody {
+        SliderBody {
+            id: WidgetId::here(),
+            orientation: Orientation::Horizontal,
+            range: SliderRange { min, max, step: None },
+            values,
+            ticks: vec![],
+            tick_labels: vec![],
+            track: Default::default(),
+            active_thumb: cache::state(|| 0),
+            focused: cache::state(|| false),
+            thumb_moved: Signal::new(),
+            colors: SliderColors::from_env(&cache::environment()),
+        }
+    }
+
+    fn set_ticks(&mut self, interval: f64) {
+        self.ticks = self.range.ticks(interval);
+        self.tick_labels.clear();
+    }
+
+    #[composable]
+    fn set_labeled_ticks(&mut self, interval: f64, label: impl Fn(f64) -> String) {
+        self.ticks = self.range.ticks(interval);
+        self.tick_labels = self
+            .ticks
+            .iter()
+            .enumerate()
+            .map(|(i, &tick)| cache::scoped(i, || Text::new(label(tick)).arc_pod()))
+            .collect();
+    }
+
+    /// Returns the new value of a thumb, if the user has moved it.
+    fn thumb_moved(&self, thumb: usize) -> Option<f64> {
+        match self.thumb_moved.value() {
+            Some((moved, value)) if moved == thumb => Some(value),
+            _ => None,
+        }
+    }
+
+    fn move_thumb(&self, thumb: usize, value: f64) {
+        let value = constrain_thumb(&self.values, thumb, self.range.snap(value));
+        if value != self.values[thumb] {
+            self.thumb_moved.signal((thumb, value));
+        }
+    }
+
+    /// Moves a thumb under the pointer.
+    fn drag_thumb(&self, thumb: usize, position: Point) {
+        let t = self.track.get().value_from_position(position, 0.0, 1.0);
+        self.move_thumb(thumb, self.range.denormalize(t));
+    }
+
+    /// Returns the direction, perpendicular to the track, in which the tick marks and labels are placed.
+    fn tick_direction(&self) -> Offset {
+        match self.orientation {
+            Orientation::Horizontal => Offset::new(0.0, 1.0),
+            Orientation::Vertical => Offset::new(1.0, 0.0),
+        }
+    }
+}
+
+fn fill_paint(color: Color) -> sk::Paint {
+    let mut paint = sk::Paint::new(color.to_skia(), None);
+    paint.set_anti_alias(true);
+    paint
+}
+
+fn stroke_paint(color: Color, width: f64) -> sk::Paint {
+    let mut paint = fill_paint(color);
+    paint.set_style(sk::PaintStyle::Stroke);
+    paint.set_stroke_width(width as sk::scalar);
+    paint
+}
+
+impl Widget for SliderBody {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let label_constraints = LayoutParams {
+            min: Size::zero(),
+            max: Size::new(f64::INFINITY, f64::INFINITY),
+            ..*constraints
+        };
+        let label_sizes: Vec<Size> = self
+            .tick_labels
+            .iter()
+            .map(|label| label.layout(ctx, &label_constraints, env).measurements.size)
+            .collect();
+
+        // size across the track
+        let mut thickness = THUMB_SIZE;
+        if !self.ticks.is_empty() {
+            thickness += TICK_SPACING + TICK_LENGTH;
+        }
+        if !label_sizes.is_empty() {
+            let label_thickness = label_sizes
+                .iter()
+                .map(|size| match self.orientation {
+                    Orientation::Horizontal => size.height,
+                    Orientation::Vertical => size.width,
+                })
+                .fold(0.0, f64::max);
+            thickness += TICK_SPACING + label_thickness;
+        }
+
+        // the track takes all the available length; the minimum value is on the left, or at the bottom
+        let (min_length, max_length) = match self.orientation {
+            Orientation::Horizontal => (constraints.min.width, constraints.max.width),
+            Orientation::Vertical => (constraints.min.height, constraints.max.height),
+        };
+        let length = if max_length.is_finite() {
+            max_length
+        } else {
+            DEFAULT_LENGTH.max(min_length)
+        };
+        let h = 0.5 * THUMB_SIZE;
+        let track = match self.orientation {
+            Orientation::Horizontal => SliderTrack {
+                start: Point::new(h, h),
+                end: Point::new(length - h, h),
+            },
+            Orientation::Vertical => SliderTrack {
+                start: Point::new(h, length - h),
+                end: Point::new(h, h),
+            },
+        };
+        self.track.set(track);
+
+        // center the labels on their tick marks, without overflowing the ends of the slider
+        if !ctx.speculative {
+            let label_start = THUMB_SIZE + TICK_SPACING + TICK_LENGTH + TICK_SPACING;
+            for ((label, size), &tick) in self.tick_labels.iter().zip(label_sizes.iter()).zip(self.ticks.iter()) {
+                let p = track.knob_position(self.range.normalize(tick));
+                let offset = match self.orientation {
+                    Orientation::Horizontal => {
+                        Offset::new((p.x - 0.5 * size.width).min(length - size.width).max(0.0), label_start)
+                    }
+                    Orientation::Vertical => Offset::new(
+                        label_start,
+                        (p.y - 0.5 * size.height).min(length - size.height).max(0.0),
+                    ),
+                };
+                label.set_offset(offset);
+            }
+        }
+
+        let size = match self.orientation {
+            Orientation::Horizontal => Size::new(length, thickness),
+            Orientation::Vertical => Size::new(thickness, length),
+        };
+        Geometry::new(constraints.constrain(size))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        match event {
+            Event::Pointer(p) => match p.kind {
+                PointerEventKind::PointerDown if p.button == Some(PointerButton::LEFT) => {
+                    // grab the thumb closest to the pointer, and move it there
+                    let t = self.track.get().value_from_position(p.position, 0.0, 1.0);
+                    let positions: Vec<f64> = self.values.iter().map(|&v| self.range.normalize(v)).collect();
+                    let thumb = nearest_thumb(&positions, t);
+                    self.active_thumb.set_without_invalidation(thumb);
+                    self.drag_thumb(thumb, p.position);
+                    ctx.capture_pointer();
+                    ctx.request_focus();
+                    ctx.request_repaint();
+                    ctx.set_handled();
+                }
+                PointerEventKind::PointerMove if ctx.is_capturing_pointer() => {
+                    self.drag_thumb(self.active_thumb.get(), p.position);
+                    ctx.set_handled();
+                }
+                PointerEventKind::PointerUp if ctx.is_capturing_pointer() => {
+                    ctx.release_pointer();
+                    ctx.set_handled();
+                }
+                _ => {}
+            },
+            Event::Keyboard(k) if k.state == KeyState::Down => {
+                let thumb = self.active_thumb.get().min(self.values.len() - 1);
+                if let Some(value) = self.range.key_value(self.values[thumb], &k.key) {
+                    self.move_thumb(thumb, value);
+                    ctx.set_handled();
+                }
+            }
+            Event::FocusGained | Event::FocusLost => {
+                self.focused
+                    .set_without_invalidation(matches!(event, Event::FocusGained));
+                ctx.request_repaint();
+            }
+            _ => {
+                for label in self.tick_labels.iter() {
+                    label.route_event(ctx, event, env);
+                }
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let track = self.track.get();
+        let positions: Vec<Point> = self
+            .values
+            .iter()
+            .map(|&v| track.knob_position(self.range.normalize(v)))
+            .collect();
+        let canvas = ctx.surface.canvas();
+
+        // track, and the filled part up to the thumb, or between the thumbs
+        let mut track_paint = stroke_paint(self.colors.track, TRACK_THICKNESS);
+        track_paint.set_stroke_cap(sk::PaintCap::Round);
+        canvas.draw_line(track.start.to_skia(), track.end.to_skia(), &track_paint);
+        let (fill_start, fill_end) = match positions.len() {
+            0 => (track.start, track.start),
+            1 => (track.start, positions[0]),
+            n => (positions[0], positions[n - 1]),
+        };
+        let mut fill = stroke_paint(self.colors.fill, TRACK_THICKNESS);
+        fill.set_stroke_cap(sk::PaintCap::Round);
+        canvas.draw_line(fill_start.to_skia(), fill_end.to_skia(), &fill);
+
+        // tick marks
+        let direction = self.tick_direction();
+        let tick_paint = stroke_paint(self.colors.tick, 1.0);
+        for &tick in self.ticks.iter() {
+            let p = track.knob_position(self.range.normalize(tick));
+            let start = p + direction * (0.5 * THUMB_SIZE + TICK_SPACING);
+            let end = start + direction * TICK_LENGTH;
+            canvas.draw_line(start.to_skia(), end.to_skia(), &tick_paint);
+        }
+
+        // thumbs, with a focus ring around the one moved by the keyboard
+        let radius = 0.5 * THUMB_SIZE - 1.0;
+        let active_thumb = self.active_thumb.get();
+        for (i, p) in positions.iter().enumerate() {
+            canvas.draw_circle(p.to_skia(), radius as sk::scalar, &fill_paint(self.colors.thumb));
+            let border = if self.focused.get() && i == active_thumb {
+                stroke_paint(self.colors.fill, 2.0)
+            } else {
+                stroke_paint(self.colors.thumb_border, 1.0)
+            };
+            canvas.draw_circle(p.to_skia(), radius as sk::scalar, &border);
+        }
+
+        for label in self.tick_labels.iter() {
+            label.paint(ctx);
+        }
+    }
+
+    fn semantics(&self) -> Option<Semantics> {
+        let thumb = self.active_thumb.get().min(self.values.len() - 1);
+        let semantics = Semantics::new(Role::Slider).numeric_value(self.values[thumb], self.range.min, self.range.max);
+        if self.values.len() > 1 {
+            let values: Vec<String> = self.values.iter().map(|v| v.to_string()).collect();
+            Some(semantics.value(values.join(" to ")))
+        } else {
+            Some(semantics)
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+/// Slider to pick a value between two bounds, by dragging a thumb along a track or with the keyboard.
+///
+/// The arrow keys move the thumb by one step (or a hundredth of the range), PageUp and PageDown by ten, and Home
+/// and End move it to the bounds.
+#[derive(Widget)]
+pub struct Slider {
+    body: SliderBody,
+}
+
+impl Slider {
+    /// Creates a horizontal slider.
+    ///
+    /// # Arguments
+    /// * `min` - lower bound of the slider range
+    /// * `max` - upper bound of the slider range
+    /// * `value` - current value of the slider
+    #[composable]
+    pub fn new(min: f64, max: f64, value: f64) -> Slider {
+        Slider {
+            body: SliderBody::new(min, max, vec![value]),
+        }
+    }
+
+    /// Sets the direction of the track. The minimum value of vertical sliders is at the bottom.
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.body.orientation = orientation;
+        self
+    }
+
+    /// Restricts the values to multiples of `step` from the minimum.
+    pub fn step(mut self, step: f64) -> Self {
+        self.body.range.step = Some(step);
+        self
+    }
+
+    /// Shows tick marks every `interval`, starting from the minimum.
+    pub fn ticks(mut self, interval: f64) -> Self {
+        self.body.set_ticks(interval);
+        self
+    }
+
+    /// Shows tick marks every `interval`, with a label returned by `label` for the value of each tick.
+    #[composable]
+    pub fn labeled_ticks(mut self, interval: f64, label: impl Fn(f64) -> String) -> Self {
+        self.body.set_labeled_ticks(interval, label);
+        self
+    }
+
+    /// Returns the current value of the slider.
+    pub fn current_value(&self) -> f64 {
+        self.body.values[0]
+    }
+
+    /// Returns the new value, if the user has moved the thumb.
+    pub fn value_changed(&self) -> Option<f64> {
+        self.body.thumb_moved(0)
+    }
+
+    pub fn on_value_changed(self, f: impl FnOnce(f64)) -> Self {
+        if let Some(value) = self.value_changed() {
+            f(value);
+        }
+        self
+    }
+}
+
+/// Slider with two thumbs to pick a range of values.
+///
+/// The thumbs can't cross each other. The keyboard moves the thumb that was last dragged.
+#[derive(Widget)]
+pub struct RangeSlider {
+    body: SliderBody,
+}
+
+impl RangeSlider {
+    /// Creates a horizontal range slider.
+    ///
+    /// # Arguments
+    /// * `min` - lower bound of the slider range
+    /// * `max` - upper bound of the slider range
+    /// * `range` - current lower and upper values, swapped if not in order
+    #[composable]
+    pub fn new(min: f64, max: f64, range: (f64, f64)) -> RangeSlider {
+        let (low, high) = range;
+        RangeSlider {
+            body: SliderBody::new(min, max, vec![low.min(high), low.max(high)]),
+        }
+    }
+
+    /// Sets the direction of the track. The minimum value of vertical sliders is at the bottom.
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.body.orientation = orientation;
+        self
+    }
+
+    /// Restricts the values to multiples of `step` from the minimum.
+    pub fn step(mut self, step: f64) -> Self {
+        self.body.range.step = Some(step);
+        self
+    }
+
+    /// Shows tick marks every `interval`, starting from the minimum.
+    pub fn ticks(mut self, interval: f64) -> Self {
+        self.body.set_ticks(interval);
+        self
+    }
+
+    /// Shows tick marks every `interval`, with a label returned by `label` for the value of each tick.
+    #[composable]
+    pub fn labeled_ticks(mut self, interval: f64, label: impl Fn(f64) -> String) -> Self {
+        self.body.set_labeled_ticks(interval, label);
+        self
+    }
+
+    /// Returns the current lower and upper values.
+    pub fn current_range(&self) -> (f64, f64) {
+        (self.body.values[0], self.body.values[1])
+    }
+
+    /// Returns the new range, if the user has moved one of the thumbs.
+    pub fn range_changed(&self) -> Option<(f64, f64)> {
+        let (low, high) = self.current_range();
+        if let Some(low) = self.body.thumb_moved(0) {
+            Some((low, high))
+        } else {
+            self.body.thumb_moved(1).map(|high| (low, high))
+        }
+    }
+
+    pub fn on_range_changed(self, f: impl FnOnce((f64, f64))) -> Self {
+        if let Some(range) = self.range_changed() {
+            f(range);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slider_range() {
+        let range = SliderRange {
+            min: 0.0,
+            max: 10.0,
+            step: Some(2.0),
+        };
+        assert_eq!(range.snap(3.1), 4.0);
+        assert_eq!(range.snap(-5.0), 0.0);
+        assert_eq!(range.snap(11.0), 10.0);
+        assert_eq!(range.key_value(4.0, &Key::ArrowRight), Some(6.0));
+        assert_eq!(range.key_value(4.0, &Key::ArrowDown), Some(2.0));
+        assert_eq!(range.key_value(4.0, &Key::PageUp), Some(10.0));
+        assert_eq!(range.key_value(4.0, &Key::Home), Some(0.0));
+        assert_eq!(range.key_value(4.0, &Key::Tab), None);
+        assert_eq!(range.ticks(2.5), vec![0.0, 2.5, 5.0, 7.5, 10.0]);
+        assert_eq!(range.ticks(0.0), Vec::<f64>::new());
+
+        let continuous = SliderRange { step: None, ..range };
+        assert_eq!(continuous.snap(3.1), 3.1);
+        assert_eq!(continuous.key_value(5.0, &Key::PageDown), Some(4.0));
+        assert_eq!(continuous.normalize(2.5), 0.25);
+    }
+
+    #[test]
+    fn range_thumbs() {
+        assert_eq!(nearest_thumb(&[0.2, 0.6], 0.3), 0);
+        assert_eq!(nearest_thumb(&[0.2, 0.6], 0.5), 1);
+        // overlapping thumbs: pick the one that can move towards the pointer
+        assert_eq!(nearest_thumb(&[0.5, 0.5], 0.4), 0);
+        assert_eq!(nearest_thumb(&[0.5, 0.5], 0.6), 1);
+
+        assert_eq!(constrain_thumb(&[2.0, 6.0], 0, 7.0), 6.0);
+        assert_eq!(constrain_thumb(&[2.0, 6.0], 1, 1.0), 2.0);
+        assert_eq!(constrain_thumb(&[2.0, 6.0], 1, 8.0), 8.0);
+    }
 }
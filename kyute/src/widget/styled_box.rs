@@ -3,39 +3,108 @@ use crate::{
     drawing::{BlendMode, Paint, PaintCtxExt, RoundedRect, Shape, ToSkia},
     style,
     style::{Style, WidgetState},
+    theme,
     widget::prelude::*,
-    PointerEventKind, SideOffsets, State,
+    InternalEvent, PointerEventKind, SideOffsets, State,
 };
 use skia_safe as sk;
 use std::{
     convert::TryInto,
+    fmt,
     ops::{Deref, DerefMut},
 };
 
+/// Size of the error badge painted on widgets with an invalid style.
+const ERROR_BADGE_SIZE: f64 = 8.0;
+
+/// Paints a badge over a widget whose style failed to parse, so that the error is noticed during development.
+///
+/// Does nothing in release builds.
+pub(crate) fn paint_style_error_badge(ctx: &mut PaintCtx) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    let bounds = ctx.bounds;
+    let canvas = ctx.surface.canvas();
+    let mut paint = sk::Paint::new(theme::palette::RED_500.to_skia(), None);
+    paint.set_anti_alias(true);
+
+    // outline the widget
+    paint.set_style(sk::PaintStyle::Stroke);
+    paint.set_stroke_width(1.0);
+    canvas.draw_rect(bounds.inflate(-0.5, -0.5).to_skia(), &paint);
+
+    // and mark its top-left corner
+    let x = bounds.origin.x as sk::scalar;
+    let y = bounds.origin.y as sk::scalar;
+    let size = ERROR_BADGE_SIZE as sk::scalar;
+    let mut path = sk::Path::new();
+    path.move_to(sk::Point::new(x, y));
+    path.line_to(sk::Point::new(x + size, y));
+    path.line_to(sk::Point::new(x, y + size));
+    path.close();
+    paint.set_style(sk::PaintStyle::Fill);
+    canvas.draw_path(&path, &paint);
+}
+
 pub struct StyledBox<Inner> {
     // we need an ID because we track pointer hover events
     id: WidgetId,
     style: Style,
+    /// Parse error of the style, if it failed to parse (in which case the default style is used).
+    error: Option<String>,
     computed: LayoutCache<style::ComputedStyle>,
     inner: WidgetPod<Inner>,
     hovered: State<bool>,
 }
 
 impl<Inner: Widget + 'static> StyledBox<Inner> {
+    /// Creates a new `StyledBox`.
+    ///
+    /// If the style fails to parse, a warning is emitted, the default style is used instead and, in debug builds,
+    /// the widget is marked with an error badge. See `try_new` to handle the error instead.
     #[composable]
-    pub fn new(inner: Inner, style: impl TryInto<Style>) -> Self {
+    pub fn new<S>(inner: Inner, style: S) -> Self
+    where
+        S: TryInto<Style>,
+        S::Error: fmt::Display,
+    {
+        let (style, error) = match style.try_into() {
+            Ok(style) => (style, None),
+            Err(err) => {
+                warn!("failed to parse style: {}", err);
+                (Style::default(), Some(err.to_string()))
+            }
+        };
         StyledBox {
             id: WidgetId::here(),
-            style: style.try_into().unwrap_or_else(|_| {
-                warn!("Failed to parse style");
-                Style::default()
-            }),
+            style,
+            error,
             computed: Default::default(),
             inner: WidgetPod::new(inner),
             hovered: cache::state(|| false),
         }
     }
 
+    /// Creates a new `StyledBox`, or returns an error if the style fails to parse.
+    #[composable]
+    pub fn try_new<S: TryInto<Style>>(inner: Inner, style: S) -> Result<Self, S::Error> {
+        let style = style.try_into()?;
+        Ok(StyledBox {
+            id: WidgetId::here(),
+            style,
+            error: None,
+            computed: Default::default(),
+            inner: WidgetPod::new(inner),
+            hovered: cache::state(|| false),
+        })
+    }
+
+    /// Returns the parse error of the style, if it failed to parse.
+    pub fn style_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
     pub fn inner(&self) -> &Inner {
         self.inner.inner()
     }
@@ -69,6 +138,7 @@ impl<Inner: Widget + 'static> Widget for StyledBox<Inner> {
 
         let mut widget_state = params.widget_state;
         widget_state.set(WidgetState::HOVER, self.hovered.get());
+        widget_state |= theme::FORCED_WIDGET_STATE.get(env).unwrap_or_default();
 
         // TODO layout cache not enough here (doesn't take into account widget state)
         let computed = if ctx.speculative {
@@ -222,6 +292,8 @@ impl<Inner: Widget + 'static> Widget for StyledBox<Inner> {
                 }
                 _ => {}
             },
+            // the computed style depends on the environment
+            Event::Internal(InternalEvent::EnvironmentChanged) => self.computed.invalidate(),
             _ => {}
         }
 
@@ -295,5 +367,16 @@ impl<Inner: Widget + 'static> Widget for StyledBox<Inner> {
             .clip_rrect(inner_border_rrect.to_skia(), sk::ClipOp::Intersect, true);
         self.inner.paint(ctx);
         ctx.surface.canvas().restore();
+
+        if self.error.is_some() {
+            paint_style_error_badge(ctx);
+        }
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        match self.error {
+            Some(ref error) => DebugNode::new(format!("invalid style: {}", error)),
+            None => DebugNode::default(),
+        }
     }
 }
@@ -0,0 +1,58 @@
+//! Widgets recomposed independently of the rest of the UI.
+use crate::{cache, cache::Restartable, widget::prelude::*, Data, InternalEvent};
+use std::sync::Arc;
+
+/// Shows widgets created in a restartable scope (see `cache::restartable`).
+///
+/// When a state variable read while creating the contents changes, only the contents are recomposed:
+/// the enclosing composable functions aren't called again. Use this to isolate parts of the UI that change often,
+/// or large parts of the UI with state that is independent of the rest of the application.
+pub struct Subtree {
+    id: WidgetId,
+    content: Restartable<Arc<WidgetPod>>,
+}
+
+impl Subtree {
+    /// Creates the widgets returned by `f` in a restartable scope.
+    ///
+    /// `f` is called again when `args` change, or when a state variable read in `f` changes.
+    #[composable]
+    pub fn new<Args, W>(args: Args, f: impl Fn(&Args) -> W + 'static) -> Subtree
+    where
+        Args: Data,
+        W: Widget + 'static,
+    {
+        let content = cache::restartable(args, move |args| f(args).arc_dyn_pod());
+        Subtree {
+            id: WidgetId::from_call_id(content.call_id()),
+            content,
+        }
+    }
+}
+
+impl Widget for Subtree {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.content.get().layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        match event {
+            Event::Internal(InternalEvent::SubtreeRecomposed) => {
+                ctx.request_relayout();
+            }
+            _ => self.content.get().route_event(ctx, event, env),
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.get().paint(ctx)
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new(format!("{:?}", self.content))
+    }
+}
@@ -0,0 +1,797 @@
+//! Tab views.
+//!
+//! `TabView` shows a strip of tabs above the contents of the selected tab. Tabs can be selected with the pointer or
+//! the arrow keys, closed with their close button, and reordered by dragging them along the strip. When the tabs
+//! don't fit, the strip scrolls horizontally with the wheel or the arrow buttons at its ends. Each tab can have a
+//! context menu, shown when the tab is right-clicked.
+//!
+//! Like other controlled widgets, the tab view doesn't change the tabs or the selection by itself: it reports what the
+//! user did (`selection_changed`, `tab_closed`, `tab_moved`), and the application updates the list of tabs and the
+//! selected index in the next composition.
+use crate::{
+    accessibility::{Role, Semantics},
+    cache,
+    drawing::ToSkia,
+    event::{PointerButton, PointerEventKind},
+    theme,
+    widget::{prelude::*, table::drop_position, Grid, Menu, Null, Text},
+    Color, Signal,
+};
+use keyboard_types::{Key, KeyState};
+use skia_safe as sk;
+use std::{
+    cell::{Cell, RefCell},
+    hash::Hash,
+    sync::Arc,
+};
+
+/// Horizontal padding inside tabs.
+const TAB_HORIZONTAL_PADDING: f64 = 8.0;
+/// Vertical padding inside tabs.
+const TAB_VERTICAL_PADDING: f64 = 4.0;
+/// Space between the title of a tab and its close button.
+const CLOSE_BUTTON_SPACING: f64 = 6.0;
+/// Space between tabs.
+const TAB_SPACING: f64 = 2.0;
+/// Height of the line under the selected tab.
+const SELECTION_INDICATOR_HEIGHT: f64 = 2.0;
+/// Width of the buttons at the ends of the strip that scroll the tabs when they don't fit.
+const SCROLL_BUTTON_WIDTH: f64 = 16.0;
+/// Distance scrolled by a click on a scroll button.
+const SCROLL_STEP: f64 = 80.0;
+/// Distance scrolled by a line of the wheel.
+const WHEEL_LINE_SIZE: f64 = 16.0;
+/// Distance that the pointer must move after pressing a tab to start dragging it.
+const TAB_DRAG_THRESHOLD: f64 = 4.0;
+
+/// A tab of a `TabView`.
+pub struct Tab<'a, K> {
+    key: K,
+    title: String,
+    closable: bool,
+    modified: bool,
+    context_menu: Option<Menu>,
+    content: Box<dyn FnOnce() -> Arc<WidgetPod> + 'a>,
+}
+
+impl<'a, K: Hash> Tab<'a, K> {
+    /// Creates a tab.
+    ///
+    /// `key` identifies the tab across recompositions, even if it's moved: it should be unique among the tabs of the
+    /// view. `content` is called to create the contents of the tab, only when the tab is selected.
+    pub fn new<W: Widget + 'static>(key: K, title: impl Into<String>, content: impl FnOnce() -> W + 'a) -> Tab<'a, K> {
+        Tab {
+            key,
+            title: title.into(),
+            closable: false,
+            modified: false,
+            context_menu: None,
+            content: Box::new(move || content().arc_dyn_pod()),
+        }
+    }
+
+    /// Sets whether the tab has a close button. Tabs aren't closable by default.
+    #[must_use]
+    pub fn closable(mut self, closable: bool) -> Self {
+        self.closable = closable;
+        self
+    }
+
+    /// Sets whether the contents of the tab have unsaved changes. The close button of a modified tab shows a dot
+    /// instead of a cross.
+    #[must_use]
+    pub fn modified(mut self, modified: bool) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    /// Sets the menu shown when the tab is right-clicked.
+    #[must_use]
+    pub fn context_menu(mut self, menu: Menu) -> Self {
+        self.context_menu = Some(menu);
+        self
+    }
+}
+
+/// Returns the index of the selected tab after the tab at index `closed` is closed, or `None` if no tab is left.
+///
+/// `len` is the number of tabs before closing. When the selected tab is closed, the next tab is selected, or the
+/// previous one if it was the last.
+fn selection_after_close(selected: usize, closed: usize, len: usize) -> Option<usize> {
+    if len <= 1 {
+        None
+    } else if closed < selected {
+        Some(selected - 1)
+    } else {
+        Some(selected.min(len - 2))
+    }
+}
+
+/// Returns the index of the selected tab after the tab at index `from` is moved to index `to`.
+fn selection_after_move(selected: usize, from: usize, to: usize) -> usize {
+    if selected == from {
+        to
+    } else if from < selected && to >= selected {
+        selected - 1
+    } else if from > selected && to <= selected {
+        selected + 1
+    } else {
+        selected
+    }
+}
+
+/// Returns the scroll offset that reveals the interval `start..end` in a viewport of the specified width, scrolling as
+/// little as possible.
+fn scroll_to_reveal(scroll: f64, viewport_width: f64, start: f64, end: f64) -> f64 {
+    if start < scroll {
+        start
+    } else if end > scroll + viewport_width {
+        (end - viewport_width).min(start)
+    } else {
+        scroll
+    }
+}
+
+struct TabColors {
+    selected_background: Color,
+    hover_background: Color,
+    indicator: Color,
+    separator: Color,
+    arrow: Color,
+}
+
+impl TabColors {
+    fn from_env(env: &Environment) -> TabColors {
+        TabColors {
+            selected_background: env
+                .get(&theme::CONTENT_BACKGROUND_COLOR)
+                .unwrap_or(theme::palette::GREY_50),
+            hover_background: env
+                .get(&theme::ALTERNATE_CONTENT_BACKGROUND_COLOR)
+                .unwrap_or(theme::palette::GREY_200),
+            indicator: env.get(&theme::ACCENT_COLOR).unwrap_or(theme::palette::BLUE_500),
+            separator: env.get(&theme::BORDER_COLOR).unwrap_or(theme::palette::GREY_500),
+            arrow: env.get(&theme::TEXT_COLOR).unwrap_or(theme::palette::GREY_800),
+        }
+    }
+}
+
+/// State of the tab strip, kept across recompositions.
+#[derive(Default)]
+struct TabStripState {
+    /// Scroll offset of the tabs, when they don't fit in the strip.
+    scroll: Cell<f64>,
+    /// Index of the pressed tab, and horizontal position of the pointer in the window when it was pressed.
+    pressed: Cell<Option<(usize, f64)>>,
+    /// Whether the close button of the pressed tab was pressed, instead of the tab itself.
+    close_pressed: Cell<bool>,
+    /// Whether the pressed tab is being dragged.
+    dragging: Cell<bool>,
+    /// Current distance from the start of the drag.
+    drag_offset: Cell<f64>,
+    /// Tab under the pointer.
+    hovered: Cell<Option<usize>>,
+    /// The selected tab, the last time it was scrolled into view.
+    revealed: Cell<Option<usize>>,
+    /// Tab whose context menu is shown.
+    menu_tab: Cell<Option<usize>>,
+    focused: Cell<bool>,
+}
+
+/// Position of the parts of a tab, computed during layout.
+#[derive(Copy, Clone, Debug)]
+struct TabGeometry {
+    /// Horizontal position of the tab, relative to the start of the first tab, and width.
+    x: f64,
+    width: f64,
+    /// Position of the title, relative to the tab.
+    title: Offset,
+    /// Position of the close button, relative to the tab.
+    close: Option<Rect>,
+}
+
+/// Tabs of a `TabView`.
+struct TabStrip {
+    id: WidgetId,
+    selected: usize,
+    selected_title: String,
+    titles: Vec<WidgetPod<Text>>,
+    close_buttons: Vec<Option<WidgetPod<Text>>>,
+    menus: Vec<Option<Menu>>,
+    state: Arc<TabStripState>,
+    colors: TabColors,
+    tabs: RefCell<Vec<TabGeometry>>,
+    /// Total width of the tabs.
+    content_width: Cell<f64>,
+    /// Part of the strip where the tabs are shown: the whole strip, minus the scroll buttons when the tabs don't fit.
+    viewport: Cell<Rect>,
+    overflowing: Cell<bool>,
+    selection_changed: Signal<usize>,
+    tab_closed: Signal<usize>,
+    tab_moved: Signal<(usize, usize)>,
+}
+
+impl TabStrip {
+    /// Returns the horizontal position of a tab in the strip, taking the scroll and drag offsets into account.
+    fn tab_x(&self, index: usize) -> f64 {
+        let mut x = self.viewport.get().min_x() + self.tabs.borrow()[index].x - self.state.scroll.get();
+        if self.state.dragging.get() && self.state.pressed.get().map(|(pressed, _)| pressed) == Some(index) {
+            x += self.state.drag_offset.get();
+        }
+        x
+    }
+
+    /// Returns the bounds of a tab in the strip.
+    fn tab_bounds(&self, index: usize) -> Rect {
+        let height = self.viewport.get().height();
+        Rect::new(
+            Point::new(self.tab_x(index), 0.0),
+            Size::new(self.tabs.borrow()[index].width, height),
+        )
+    }
+
+    /// Moves the titles and close buttons to the current position of their tab.
+    fn place_tabs(&self) {
+        let tabs = self.tabs.borrow();
+        for (i, tab) in tabs.iter().enumerate() {
+            let x = self.tab_x(i);
+            self.titles[i].set_offset(Offset::new(x, 0.0) + tab.title);
+            if let (Some(close_button), Some(close)) = (&self.close_buttons[i], tab.close) {
+                close_button.set_offset(Offset::new(x, 0.0) + close.origin.to_vector());
+            }
+        }
+    }
+
+    /// Returns the tab at the specified position, and whether the position is over its close button.
+    fn hit_test(&self, position: Point) -> Option<(usize, bool)> {
+        if !self.viewport.get().contains(position) {
+            return None;
+        }
+        let tabs = self.tabs.borrow();
+        (0..tabs.len()).find_map(|i| {
+            let bounds = self.tab_bounds(i);
+            if bounds.contains(position) {
+                let on_close = tabs[i].close.map_or(false, |close| {
+                    close.translate(bounds.origin.to_vector()).contains(position)
+                });
+                Some((i, on_close))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn max_scroll(&self) -> f64 {
+        (self.content_width.get() - self.viewport.get().width()).max(0.0)
+    }
+
+    fn scroll_by(&self, ctx: &mut EventCtx, delta: f64) {
+        let scroll = (self.state.scroll.get() + delta).max(0.0).min(self.max_scroll());
+        if scroll != self.state.scroll.get() {
+            self.state.scroll.set(scroll);
+            self.place_tabs();
+            ctx.request_repaint();
+        }
+    }
+
+    fn scroll_buttons(&self) -> (Rect, Rect) {
+        let viewport = self.viewport.get();
+        let size = Size::new(SCROLL_BUTTON_WIDTH, viewport.height());
+        (
+            Rect::new(Point::origin(), size),
+            Rect::new(Point::new(viewport.max_x(), 0.0), size),
+        )
+    }
+
+    fn select(&self, index: usize) {
+        if index != self.selected {
+            self.selection_changed.signal(index);
+        }
+    }
+
+    fn close(&self, index: usize) {
+        self.tab_closed.signal(index);
+        if let Some(selected) = selection_after_close(self.selected, index, self.titles.len()) {
+            if selected != self.selected {
+                self.selection_changed.signal(selected);
+            }
+        }
+    }
+
+    fn show_menu(&self, ctx: &mut EventCtx, index: usize, window_position: Point) {
+        if let Some(ref menu) = self.menus[index] {
+            let shell_menu = menu.to_shell_menu(true);
+            menu.assign_menu_item_indices();
+            self.state.menu_tab.set(Some(index));
+            ctx.track_popup_menu(shell_menu, window_position);
+        }
+    }
+
+    fn drop_tab(&self, index: usize, offset: f64) {
+        let widths: Vec<f64> = self.tabs.borrow().iter().map(|tab| tab.width + TAB_SPACING).collect();
+        let to = drop_position(&widths, index, offset);
+        if to != index {
+            self.tab_moved.signal((index, to));
+            self.select(selection_after_move(self.selected, index, to));
+        }
+    }
+
+    fn paint_arrow(&self, ctx: &mut PaintCtx, bounds: Rect, left: bool, color: Color) {
+        let center = bounds.center();
+        let (base_x, tip_x) = if left {
+            (center.x + 2.0, center.x - 2.0)
+        } else {
+            (center.x - 2.0, center.x + 2.0)
+        };
+        let mut path = sk::Path::new();
+        path.move_to(Point::new(base_x, center.y - 4.0).to_skia());
+        path.line_to(Point::new(tip_x, center.y).to_skia());
+        path.line_to(Point::new(base_x, center.y + 4.0).to_skia());
+        path.close();
+        let mut paint = sk::Paint::new(color.to_skia(), None);
+        paint.set_anti_alias(true);
+        ctx.surface.canvas().draw_path(&path, &paint);
+    }
+}
+
+impl Widget for TabStrip {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let child_constraints = LayoutParams {
+            min: Size::zero(),
+            max: Size::new(f64::INFINITY, constraints.max.height),
+            ..*constraints
+        };
+
+        // measure the titles and close buttons
+        let mut sizes = Vec::with_capacity(self.titles.len());
+        let mut content_height: f64 = 0.0;
+        for (title, close_button) in self.titles.iter().zip(self.close_buttons.iter()) {
+            let title_size = title.layout(ctx, &child_constraints, env).measurements.size;
+            let close_size = close_button
+                .as_ref()
+                .map(|close_button| close_button.layout(ctx, &child_constraints, env).measurements.size);
+            content_height = content_height
+                .max(title_size.height)
+                .max(close_size.map_or(0.0, |size| size.height));
+            sizes.push((title_size, close_size));
+        }
+        let height = (content_height + 2.0 * TAB_VERTICAL_PADDING).max(constraints.min.height);
+
+        let mut tabs = Vec::with_capacity(sizes.len());
+        let mut x = 0.0;
+        for (title_size, close_size) in sizes {
+            let mut width = 2.0 * TAB_HORIZONTAL_PADDING + title_size.width;
+            let close = close_size.map(|size| {
+                let close = Rect::new(
+                    Point::new(
+                        width - TAB_HORIZONTAL_PADDING + CLOSE_BUTTON_SPACING,
+                        0.5 * (height - size.height),
+                    ),
+                    size,
+                );
+                width += CLOSE_BUTTON_SPACING + size.width;
+                close
+            });
+            tabs.push(TabGeometry {
+                x,
+                width,
+                title: Offset::new(TAB_HORIZONTAL_PADDING, 0.5 * (height - title_size.height)),
+                close,
+            });
+            x += width + TAB_SPACING;
+        }
+        let content_width = (x - TAB_SPACING).max(0.0);
+
+        let width = if constraints.max.width.is_finite() {
+            constraints.max.width
+        } else {
+            content_width.max(constraints.min.width)
+        };
+        let overflowing = content_width > width;
+        let viewport = if overflowing {
+            Rect::new(
+                Point::new(SCROLL_BUTTON_WIDTH, 0.0),
+                Size::new((width - 2.0 * SCROLL_BUTTON_WIDTH).max(0.0), height),
+            )
+        } else {
+            Rect::new(Point::origin(), Size::new(width, height))
+        };
+
+        if !ctx.speculative {
+            // scroll the selected tab into view when the selection changes
+            let mut scroll = self.state.scroll.get();
+            if let Some(selected) = tabs.get(self.selected) {
+                if self.state.revealed.get() != Some(self.selected) {
+                    scroll = scroll_to_reveal(scroll, viewport.width(), selected.x, selected.x + selected.width);
+                    self.state.revealed.set(Some(self.selected));
+                }
+            }
+            let max_scroll = (content_width - viewport.width()).max(0.0);
+            self.state.scroll.set(scroll.max(0.0).min(max_scroll));
+
+            self.tabs.replace(tabs);
+            self.content_width.set(content_width);
+            self.viewport.set(viewport);
+            self.overflowing.set(overflowing);
+            self.place_tabs();
+        }
+
+        Geometry::new(Size::new(width, height))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        match event {
+            Event::Pointer(p) => match p.kind {
+                PointerEventKind::PointerDown if p.button == Some(PointerButton::LEFT) => {
+                    if self.overflowing.get() {
+                        let (scroll_left, scroll_right) = self.scroll_buttons();
+                        if scroll_left.contains(p.position) {
+                            self.scroll_by(ctx, -SCROLL_STEP);
+                            ctx.set_handled();
+                            return;
+                        }
+                        if scroll_right.contains(p.position) {
+                            self.scroll_by(ctx, SCROLL_STEP);
+                            ctx.set_handled();
+                            return;
+                        }
+                    }
+                    if let Some((index, on_close)) = self.hit_test(p.position) {
+                        self.state.pressed.set(Some((index, p.window_position.x)));
+                        self.state.close_pressed.set(on_close);
+                        if !on_close {
+                            self.select(index);
+                        }
+                        ctx.capture_pointer();
+                        ctx.request_focus();
+                        ctx.set_handled();
+                    }
+                }
+                PointerEventKind::PointerDown if p.button == Some(PointerButton::RIGHT) => {
+                    if let Some((index, _)) = self.hit_test(p.position) {
+                        self.show_menu(ctx, index, p.window_position);
+                        ctx.set_handled();
+                    }
+                }
+                PointerEventKind::PointerMove => {
+                    if let Some((_, start)) = self.state.pressed.get() {
+                        let offset = p.window_position.x - start;
+                        if !self.state.close_pressed.get()
+                            && (self.state.dragging.get() || offset.abs() > TAB_DRAG_THRESHOLD)
+                        {
+                            self.state.dragging.set(true);
+                            self.state.drag_offset.set(offset);
+                            self.place_tabs();
+                            ctx.request_repaint();
+                        }
+                        ctx.set_handled();
+                    } else {
+                        let hovered = self.hit_test(p.position).map(|(index, _)| index);
+                        if hovered != self.state.hovered.get() {
+                            self.state.hovered.set(hovered);
+                            ctx.request_repaint();
+                        }
+                    }
+                }
+                PointerEventKind::PointerUp if p.button == Some(PointerButton::LEFT) => {
+                    if let Some((index, start)) = self.state.pressed.take() {
+                        if self.state.dragging.take() {
+                            self.state.drag_offset.set(0.0);
+                            self.drop_tab(index, p.window_position.x - start);
+                            self.place_tabs();
+                            ctx.request_repaint();
+                        } else if self.state.close_pressed.get() && self.hit_test(p.position) == Some((index, true)) {
+                            self.close(index);
+                        }
+                        self.state.close_pressed.set(false);
+                        ctx.release_pointer();
+                        ctx.set_handled();
+                    }
+                }
+                PointerEventKind::PointerOut => {
+                    if self.state.hovered.take().is_some() {
+                        ctx.request_repaint();
+                    }
+                }
+                _ => {}
+            },
+            Event::Wheel(wheel) => {
+                if self.overflowing.get() {
+                    let delta = wheel.scroll_delta(
+                        &theme::scroll_settings(env),
+                        Size::new(WHEEL_LINE_SIZE, WHEEL_LINE_SIZE),
+                        self.viewport.get().size,
+                    );
+                    // the vertical wheel scrolls the tabs as well
+                    self.scroll_by(ctx, if delta.x != 0.0 { delta.x } else { delta.y });
+                    ctx.set_handled();
+                }
+            }
+            Event::Keyboard(k) if k.state == KeyState::Down => {
+                let count = self.titles.len();
+                let index = match k.key {
+                    Key::ArrowLeft if self.selected > 0 => Some(self.selected - 1),
+                    Key::ArrowRight if self.selected + 1 < count => Some(self.selected + 1),
+                    Key::Home if count > 0 => Some(0),
+                    Key::End if count > 0 => Some(count - 1),
+                    _ => None,
+                };
+                if let Some(index) = index {
+                    self.select(index);
+                    ctx.set_handled();
+                }
+            }
+            Event::MenuCommand(index) => {
+                let menu = self
+                    .state
+                    .menu_tab
+                    .take()
+                    .and_then(|tab| self.menus.get(tab))
+                    .and_then(|menu| menu.as_ref());
+                if let Some(action) = menu.and_then(|menu| menu.find_action_by_index(*index)) {
+                    action.triggered.signal(());
+                }
+                ctx.set_handled();
+            }
+            Event::FocusGained | Event::FocusLost => {
+                self.state.focused.set(matches!(event, Event::FocusGained));
+                ctx.request_repaint();
+            }
+            _ => {
+                for title in self.titles.iter() {
+                    title.route_event(ctx, event, env);
+                }
+                for close_button in self.close_buttons.iter().flatten() {
+                    close_button.route_event(ctx, event, env);
+                }
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let colors = &self.colors;
+        let viewport = self.viewport.get();
+        let bounds = ctx.bounds;
+
+        // line under the tabs
+        let separator = Rect::new(
+            Point::new(bounds.min_x(), bounds.max_y() - 1.0),
+            Size::new(bounds.width(), 1.0),
+        );
+        ctx.surface
+            .canvas()
+            .draw_rect(separator.to_skia(), &sk::Paint::new(colors.separator.to_skia(), None));
+
+        ctx.surface.canvas().save();
+        ctx.surface
+            .canvas()
+            .clip_rect(viewport.to_skia(), sk::ClipOp::Intersect, false);
+        // the dragged tab is painted above the others
+        let dragged = if self.state.dragging.get() {
+            self.state.pressed.get().map(|(index, _)| index)
+        } else {
+            None
+        };
+        let order = (0..self.titles.len()).filter(|&i| Some(i) != dragged).chain(dragged);
+        for i in order {
+            let tab_bounds = self.tab_bounds(i);
+            let background = if i == self.selected {
+                Some(colors.selected_background)
+            } else if Some(i) == self.state.hovered.get() || Some(i) == dragged {
+                Some(colors.hover_background)
+            } else {
+                None
+            };
+            if let Some(background) = background {
+                ctx.surface
+                    .canvas()
+                    .draw_rect(tab_bounds.to_skia(), &sk::Paint::new(background.to_skia(), None));
+            }
+            if i == self.selected {
+                let indicator = Rect::new(
+                    Point::new(tab_bounds.min_x(), tab_bounds.max_y() - SELECTION_INDICATOR_HEIGHT),
+                    Size::new(tab_bounds.width(), SELECTION_INDICATOR_HEIGHT),
+                );
+                ctx.surface
+                    .canvas()
+                    .draw_rect(indicator.to_skia(), &sk::Paint::new(colors.indicator.to_skia(), None));
+                if self.state.focused.get() {
+                    let mut focus_paint = sk::Paint::new(colors.indicator.to_skia(), None);
+                    focus_paint.set_style(sk::PaintStyle::Stroke);
+                    focus_paint.set_stroke_width(1.0);
+                    ctx.surface
+                        .canvas()
+                        .draw_rect(tab_bounds.inflate(-0.5, -0.5).to_skia(), &focus_paint);
+                }
+            }
+            self.titles[i].paint(ctx);
+            if let Some(ref close_button) = self.close_buttons[i] {
+                close_button.paint(ctx);
+            }
+        }
+        ctx.surface.canvas().restore();
+
+        if self.overflowing.get() {
+            let (scroll_left, scroll_right) = self.scroll_buttons();
+            let scroll = self.state.scroll.get();
+            let disabled = colors.arrow.with_alpha(0.3);
+            let left_color = if scroll > 0.0 { colors.arrow } else { disabled };
+            let right_color = if scroll < self.max_scroll() {
+                colors.arrow
+            } else {
+                disabled
+            };
+            self.paint_arrow(ctx, scroll_left, true, left_color);
+            self.paint_arrow(ctx, scroll_right, false, right_color);
+        }
+    }
+
+    fn semantics(&self) -> Option<Semantics> {
+        Some(Semantics::new(Role::TabList).value(self.selected_title.clone()))
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+/// A strip of tabs above the contents of the selected tab.
+///
+/// Only the contents of the selected tab are composed and laid out: inactive tabs cost nothing but their title.
+/// Note that the state of the contents of a tab (e.g. created with `cache::state`) is discarded when another tab is
+/// selected; keep it outside of the tab contents if it must be preserved.
+///
+/// The tab view is controlled: see the module documentation.
+#[derive(Widget)]
+pub struct TabView {
+    inner: Grid,
+    selected: usize,
+    selection_changed: Signal<usize>,
+    tab_closed: Signal<usize>,
+    tab_moved: Signal<(usize, usize)>,
+}
+
+impl TabView {
+    /// Creates a tab view with the specified tabs, and the tab at index `selected` selected.
+    #[composable]
+    pub fn new<K: Hash>(selected: usize, tabs: Vec<Tab<K>>) -> TabView {
+        let selection_changed = Signal::new();
+        let tab_closed = Signal::new();
+        let tab_moved = Signal::new();
+        let selected = selected.min(tabs.len().saturating_sub(1));
+
+        let mut titles = Vec::with_capacity(tabs.len());
+        let mut close_buttons = Vec::with_capacity(tabs.len());
+        let mut menus = Vec::with_capacity(tabs.len());
+        let mut selected_title = String::new();
+        let mut content = None;
+        for (i, tab) in tabs.into_iter().enumerate() {
+            let Tab {
+                key,
+                title,
+                closable,
+                modified,
+                context_menu,
+                content: tab_content,
+            } = tab;
+            cache::scoped(&key, || {
+                if i == selected {
+                    selected_title = title.clone();
+                    content = Some(tab_content());
+                }
+                titles.push(WidgetPod::new(Text::new(title)));
+                close_buttons.push(if closable {
+                    Some(WidgetPod::new(Text::new(if modified { "●" } else { "×" })))
+                } else {
+                    None
+                });
+            });
+            menus.push(context_menu);
+        }
+
+        let strip = TabStrip {
+            id: WidgetId::here(),
+            selected,
+            selected_title,
+            titles,
+            close_buttons,
+            menus,
+            state: cache::once(|| Arc::new(TabStripState::default())),
+            colors: TabColors::from_env(&cache::environment()),
+            tabs: RefCell::new(vec![]),
+            content_width: Cell::new(0.0),
+            viewport: Cell::new(Rect::zero()),
+            overflowing: Cell::new(false),
+            selection_changed: selection_changed.clone(),
+            tab_closed: tab_closed.clone(),
+            tab_moved: tab_moved.clone(),
+        };
+
+        let mut grid = Grid::with_template("auto 1fr / 1fr");
+        grid.place((0, 0), 0, strip.arc_dyn_pod());
+        grid.place((1, 0), 0, content.unwrap_or_else(|| Null.arc_dyn_pod()));
+
+        TabView {
+            inner: grid,
+            selected,
+            selection_changed,
+            tab_closed,
+            tab_moved,
+        }
+    }
+
+    /// Returns the index of the selected tab.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Returns the index of the newly selected tab, if the user has selected another tab.
+    ///
+    /// The index is relative to the tabs after the changes reported by `tab_closed` and `tab_moved`: when closing or
+    /// moving a tab changes the index of the selected tab, the new index is reported here as well.
+    pub fn selection_changed(&self) -> Option<usize> {
+        self.selection_changed.value()
+    }
+
+    pub fn on_selection_changed(self, f: impl FnOnce(usize)) -> Self {
+        self.selection_changed.map(f);
+        self
+    }
+
+    /// Returns the index of the tab whose close button has been clicked.
+    pub fn tab_closed(&self) -> Option<usize> {
+        self.tab_closed.value()
+    }
+
+    pub fn on_tab_closed(self, f: impl FnOnce(usize)) -> Self {
+        self.tab_closed.map(f);
+        self
+    }
+
+    /// Returns the original and new index of a tab that the user has moved by dragging it.
+    pub fn tab_moved(&self) -> Option<(usize, usize)> {
+        self.tab_moved.value()
+    }
+
+    pub fn on_tab_moved(self, f: impl FnOnce(usize, usize)) -> Self {
+        self.tab_moved.map(|(from, to)| f(from, to));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_after_close_and_move() {
+        // closing a tab before the selection shifts it
+        assert_eq!(selection_after_close(2, 0, 4), Some(1));
+        // closing the selected tab selects the next one, or the previous one at the end
+        assert_eq!(selection_after_close(1, 1, 4), Some(1));
+        assert_eq!(selection_after_close(3, 3, 4), Some(2));
+        assert_eq!(selection_after_close(1, 3, 4), Some(1));
+        assert_eq!(selection_after_close(0, 0, 1), None);
+
+        assert_eq!(selection_after_move(1, 1, 3), 3);
+        assert_eq!(selection_after_move(2, 0, 3), 1);
+        assert_eq!(selection_after_move(2, 3, 0), 3);
+        assert_eq!(selection_after_move(2, 0, 1), 2);
+    }
+
+    #[test]
+    fn reveal() {
+        // already visible
+        assert_eq!(scroll_to_reveal(10.0, 100.0, 20.0, 50.0), 10.0);
+        // before the viewport
+        assert_eq!(scroll_to_reveal(30.0, 100.0, 20.0, 50.0), 20.0);
+        // after the viewport
+        assert_eq!(scroll_to_reveal(0.0, 100.0, 120.0, 150.0), 50.0);
+        // wider than the viewport: show its start
+        assert_eq!(scroll_to_reveal(0.0, 100.0, 120.0, 300.0), 120.0);
+    }
+}
@@ -1,7 +1,8 @@
 //! Tree views.
 use crate::{
     cache,
-    drawing::Paint,
+    drawing::{Paint, ToSkia},
+    event::{KeyboardEvent, PointerButton, PointerEventKind},
     style,
     style::Style,
     theme,
@@ -10,18 +11,24 @@ use crate::{
         grid,
         grid::{GridLayoutExt, GridTemplate, TrackBreadth, TrackSize},
         prelude::*,
-        Clickable, DebugFlags, DragController, Grid, Image, Null, Placeholder, Scaling,
+        Clickable, DragController, Grid, Image, Null, Scaling,
     },
-    Data, Length, State, UnitExt,
+    Color, Data, Length, State, UnitExt,
 };
+use keyboard_types::{Code, Key, KeyState, Modifiers};
 use kyute_common::imbl;
 use kyute_shell::winit;
-use std::{cmp, convert::TryFrom, hash::Hash, sync::Arc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use skia_safe as sk;
+use std::{cell::Cell, cmp, convert::TryFrom, hash::Hash, mem, ops::Range, sync::Arc};
 
 /// Represents a set of selected table rows.
 #[derive(Default, Clone, Data)]
 pub struct TableSelection<Id> {
     set: imbl::HashSet<Id>,
+    /// Row where range selections start (see `select`).
+    anchor: Option<Id>,
 }
 
 impl<Id: Clone + Hash + Eq> TableSelection<Id> {
@@ -29,6 +36,26 @@ impl<Id: Clone + Hash + Eq> TableSelection<Id> {
         self.set.contains(id)
     }
 
+    /// Returns the number of selected rows.
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Returns the selected rows, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &Id> {
+        self.set.iter()
+    }
+
+    /// Deselects all rows.
+    pub fn clear(&mut self) {
+        self.set = imbl::HashSet::new();
+        self.anchor = None;
+    }
+
     pub fn insert(&mut self, id: Id) {
         self.set.insert(id);
     }
@@ -38,6 +65,313 @@ impl<Id: Clone + Hash + Eq> TableSelection<Id> {
             self.set.remove(&id);
         }
     }
+
+    /// Updates the selection after a row has been clicked, or reached with the keyboard.
+    ///
+    /// `rows` are the displayed rows, in order, and `index` is the position of the row in it.
+    ///
+    /// - without modifiers, only the row is selected;
+    /// - with Ctrl, the row is added to the selection, or removed from it;
+    /// - with Shift, the rows between the anchor (the last row selected without Shift) and this row are selected,
+    ///   replacing the selection, or adding to it with Ctrl+Shift.
+    pub fn select(&mut self, rows: &[Id], index: usize, modifiers: Modifiers) {
+        let id = rows[index].clone();
+        let ctrl = modifiers.contains(Modifiers::CONTROL);
+        if modifiers.contains(Modifiers::SHIFT) {
+            // without an anchor among the rows, the range starts at the row itself
+            let anchor = self
+                .anchor
+                .as_ref()
+                .and_then(|anchor| rows.iter().position(|row| row == anchor))
+                .unwrap_or(index);
+            if !ctrl {
+                self.set = imbl::HashSet::new();
+            }
+            for row in &rows[cmp::min(anchor, index)..=cmp::max(anchor, index)] {
+                self.set.insert(row.clone());
+            }
+            self.anchor = Some(rows[anchor].clone());
+        } else if ctrl {
+            self.flip(id.clone());
+            self.anchor = Some(id);
+        } else {
+            self.set = imbl::HashSet::new();
+            self.set.insert(id.clone());
+            self.anchor = Some(id);
+        }
+    }
+}
+
+/// Position of a table cell: index of the displayed row, and index of the column.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Data)]
+pub struct CellIndex {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl CellIndex {
+    pub fn new(row: usize, column: usize) -> CellIndex {
+        CellIndex { row, column }
+    }
+}
+
+/// A rectangular range of selected table cells, as in spreadsheets.
+///
+/// The range spans from the anchor cell, where the selection started, to the active cell, which is moved by the
+/// keyboard. Extending the selection (e.g. with Shift+arrows) moves the active cell and keeps the anchor in place.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Data)]
+pub struct CellSelection {
+    anchor: CellIndex,
+    active: CellIndex,
+}
+
+impl CellSelection {
+    /// Selects a single cell.
+    pub fn new(cell: CellIndex) -> CellSelection {
+        CellSelection {
+            anchor: cell,
+            active: cell,
+        }
+    }
+
+    /// Selects the range of cells between `anchor` and `active`, inclusive.
+    pub fn range(anchor: CellIndex, active: CellIndex) -> CellSelection {
+        CellSelection { anchor, active }
+    }
+
+    /// Selects all cells of a table with the specified number of rows and columns.
+    ///
+    /// Returns `None` if the table is empty.
+    pub fn all(row_count: usize, column_count: usize) -> Option<CellSelection> {
+        if row_count == 0 || column_count == 0 {
+            return None;
+        }
+        Some(CellSelection::range(
+            CellIndex::new(0, 0),
+            CellIndex::new(row_count - 1, column_count - 1),
+        ))
+    }
+
+    /// Returns the cell where the selection started.
+    pub fn anchor(&self) -> CellIndex {
+        self.anchor
+    }
+
+    /// Returns the active cell, at the opposite corner of the range from the anchor.
+    pub fn active(&self) -> CellIndex {
+        self.active
+    }
+
+    /// Returns the range of selected rows.
+    pub fn rows(&self) -> Range<usize> {
+        cmp::min(self.anchor.row, self.active.row)..cmp::max(self.anchor.row, self.active.row) + 1
+    }
+
+    /// Returns the range of selected columns.
+    pub fn columns(&self) -> Range<usize> {
+        cmp::min(self.anchor.column, self.active.column)..cmp::max(self.anchor.column, self.active.column) + 1
+    }
+
+    /// Returns whether the specified cell is selected.
+    pub fn contains(&self, cell: CellIndex) -> bool {
+        self.rows().contains(&cell.row) && self.columns().contains(&cell.column)
+    }
+
+    /// Returns the selected cells, row by row.
+    pub fn cells(&self) -> impl Iterator<Item = CellIndex> {
+        let columns = self.columns();
+        self.rows()
+            .flat_map(move |row| columns.clone().map(move |column| CellIndex::new(row, column)))
+    }
+
+    /// Moves the active cell to `cell`, keeping the anchor in place.
+    pub fn extend_to(self, cell: CellIndex) -> CellSelection {
+        CellSelection::range(self.anchor, cell)
+    }
+
+    /// Extends the selection to the whole rows that it spans, in a table with the specified number of columns.
+    pub fn whole_rows(self, column_count: usize) -> CellSelection {
+        let last_column = column_count.saturating_sub(1);
+        let (first, last) = if self.anchor.column <= self.active.column {
+            (0, last_column)
+        } else {
+            (last_column, 0)
+        };
+        CellSelection::range(
+            CellIndex::new(self.anchor.row, first),
+            CellIndex::new(self.active.row, last),
+        )
+    }
+
+    /// Extends the selection to the whole columns that it spans, in a table with the specified number of rows.
+    pub fn whole_columns(self, row_count: usize) -> CellSelection {
+        let last_row = row_count.saturating_sub(1);
+        let (first, last) = if self.anchor.row <= self.active.row {
+            (0, last_row)
+        } else {
+            (last_row, 0)
+        };
+        CellSelection::range(
+            CellIndex::new(first, self.anchor.column),
+            CellIndex::new(last, self.active.column),
+        )
+    }
+
+    /// Returns the selection resulting from a key press in a table with the specified number of rows and columns,
+    /// or `None` if the key doesn't change the selection.
+    ///
+    /// - arrows move the active cell and collapse the selection to it; with Shift, they extend the selection;
+    /// - Ctrl+Space selects the whole columns, Shift+Space the whole rows;
+    /// - Ctrl+A selects all cells.
+    ///
+    /// If there's no current selection, arrows select the first cell.
+    pub fn after_key(
+        selection: Option<CellSelection>,
+        event: &KeyboardEvent,
+        row_count: usize,
+        column_count: usize,
+    ) -> Option<CellSelection> {
+        if row_count == 0 || column_count == 0 || event.state != KeyState::Down {
+            return None;
+        }
+
+        let ctrl = event.modifiers.contains(Modifiers::CONTROL);
+        let shift = event.modifiers.contains(Modifiers::SHIFT);
+
+        match event.code {
+            Code::KeyA if ctrl && !shift => return CellSelection::all(row_count, column_count),
+            Code::Space if ctrl && !shift => return selection.map(|s| s.whole_columns(row_count)),
+            Code::Space if shift && !ctrl => return selection.map(|s| s.whole_rows(column_count)),
+            _ => {}
+        }
+
+        let active = match selection {
+            Some(selection) => selection.active,
+            None => {
+                return match event.key {
+                    Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight => {
+                        Some(CellSelection::new(CellIndex::new(0, 0)))
+                    }
+                    _ => None,
+                }
+            }
+        };
+        let moved = match event.key {
+            Key::ArrowUp => CellIndex::new(active.row.saturating_sub(1), active.column),
+            Key::ArrowDown => CellIndex::new(cmp::min(active.row + 1, row_count - 1), active.column),
+            Key::ArrowLeft => CellIndex::new(active.row, active.column.saturating_sub(1)),
+            Key::ArrowRight => CellIndex::new(active.row, cmp::min(active.column + 1, column_count - 1)),
+            _ => return None,
+        };
+        match selection {
+            Some(selection) if shift => Some(selection.extend_to(moved)),
+            _ => Some(CellSelection::new(moved)),
+        }
+    }
+}
+
+/// Direction in which the rows of a table are sorted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    /// Returns the opposite order.
+    pub fn reversed(self) -> SortOrder {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
+}
+
+/// Order, widths and sorting of the columns of a table, as changed by the user with the column headers.
+///
+/// Columns are identified by their index in `TableViewParams::columns`. The state is serializable, so that the
+/// layout of a table can be saved with the settings of the application and restored in the next session.
+///
+/// The table doesn't sort the rows itself: the collection should return the rows in the order specified by `sort`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableColumnsState {
+    /// Indices of the columns, from left to right.
+    pub order: Vec<usize>,
+    /// Widths of the columns resized by the user in DIPs, by column index.
+    pub widths: Vec<Option<f64>>,
+    /// Index of the column by which the rows are sorted, and the direction.
+    pub sort: Option<(usize, SortOrder)>,
+}
+
+impl TableColumnsState {
+    /// Adapts the state to a table with the specified number of columns, returns whether it was modified.
+    ///
+    /// Columns that don't exist anymore are removed, and new columns are added on the right. A restored state
+    /// may have been saved by a version of the application with different columns.
+    pub fn fit(&mut self, column_count: usize) -> bool {
+        let original = self.clone();
+        let mut seen = vec![false; column_count];
+        self.order
+            .retain(|&column| column < column_count && !mem::replace(&mut seen[column], true));
+        self.order.extend((0..column_count).filter(|&column| !seen[column]));
+        self.widths.resize(column_count, None);
+        if matches!(self.sort, Some((column, _)) if column >= column_count) {
+            self.sort = None;
+        }
+        *self != original
+    }
+
+    /// Returns the position from the left of the specified column.
+    pub fn position(&self, column: usize) -> Option<usize> {
+        self.order.iter().position(|&c| c == column)
+    }
+
+    /// Moves the column displayed at position `from` to position `to`, shifting the columns in between.
+    pub fn move_column(&mut self, from: usize, to: usize) {
+        let column = self.order.remove(from);
+        self.order.insert(to, column);
+    }
+
+    /// Returns the width of a column set by the user.
+    pub fn width(&self, column: usize) -> Option<f64> {
+        self.widths.get(column).copied().flatten()
+    }
+
+    /// Sets the width of a column.
+    pub fn set_width(&mut self, column: usize, width: f64) {
+        if self.widths.len() <= column {
+            self.widths.resize(column + 1, None);
+        }
+        self.widths[column] = Some(width);
+    }
+
+    /// Sorts the rows by the specified column, in ascending order, or reverses the order if they're already
+    /// sorted by this column.
+    pub fn toggle_sort(&mut self, column: usize) {
+        self.sort = match self.sort {
+            Some((c, order)) if c == column => Some((column, order.reversed())),
+            _ => Some((column, SortOrder::Ascending)),
+        };
+    }
+}
+
+/// Returns the position where a column dragged horizontally by `offset` is dropped.
+///
+/// `widths` are the widths of the displayed columns, from left to right, and `position` is the position of the dragged
+/// column. The column takes the place of the columns whose centers it has passed.
+pub(crate) fn drop_position(widths: &[f64], position: usize, offset: f64) -> usize {
+    let mut left = 0.0;
+    let mut centers = Vec::with_capacity(widths.len());
+    for width in widths {
+        centers.push(left + 0.5 * width);
+        left += width;
+    }
+    let center = centers[position] + offset;
+    centers
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| i != position && c < center)
+        .count()
 }
 
 /// Data model trait for the contents of a table column.
@@ -157,13 +491,13 @@ impl<Id> Row<Id> {
 
 /// A column with a clickable header.
 pub struct Column<'a, Row> {
-    /// The contents of the column header, made clickable. Usually a text element.
-    inner: Clickable<Arc<WidgetPod>>,
+    /// The contents of the column header. Usually a text element.
+    header: Arc<WidgetPod>,
     delegate: &'a dyn Fn(&Row) -> Arc<WidgetPod>,
     /// Requested size of the column.
     size: TrackSize,
-    /// Current size of the column. `None` if the column is not resizable.
-    current_size: Option<State<f64>>,
+    /// Initial width of the column. `None` if the column is not resizable.
+    initial_width: Option<f64>,
     /// Whether this is the outline column.
     outline: bool,
     moved: Signal<()>,
@@ -174,13 +508,12 @@ impl<'a, Row> Column<'a, Row> {
     /// Creates a new column with a fixed size.
     #[composable]
     pub fn new(header: impl Widget + 'static, delegate: &'a dyn Fn(&Row) -> Arc<WidgetPod>) -> Column<'a, Row> {
-        let inner: Clickable<Arc<WidgetPod>> = Clickable::new(header.arc_pod());
         let moved = Signal::new();
         let clicked = Signal::new();
         Column {
-            inner,
+            header: header.arc_pod(),
             size: TrackSize::new(TrackBreadth::Auto),
-            current_size: None,
+            initial_width: None,
             outline: false,
             moved,
             delegate,
@@ -202,16 +535,16 @@ impl<'a, Row> Column<'a, Row> {
 
     /// Makes this column resizable.
     ///
+    /// The width set by the user is stored in the `TableColumnsState` of the table.
+    ///
     /// # Arguments
     /// * initial_size the initial size of the column
-    #[composable]
     pub fn resizable(mut self, initial_size: f64) -> Self {
-        let size = cache::state(|| initial_size);
-        self.current_size = Some(size);
+        self.initial_width = Some(initial_size);
         self
     }
 
-    /// Invokes the provided closure when the column has been moved.
+    /// Invokes the provided closure when the column has been moved by dragging its header.
     pub fn on_move(self, f: impl FnOnce()) -> Self {
         if self.moved.signalled() {
             f()
@@ -228,7 +561,7 @@ impl<'a, Row> Column<'a, Row> {
     }
 
     fn is_resizable(&self) -> bool {
-        self.current_size.is_some()
+        self.initial_width.is_some()
     }
 
     /*#[composable]
@@ -261,6 +594,12 @@ pub struct TableViewStyle {
     /// Style of selected items.
     pub selected_style: Style,
 
+    /// Background of the selected range of cells.
+    pub cell_selection_background: style::Image,
+
+    /// Background of the selected rows (see `TreeView`).
+    pub selected_row_background: style::Image,
+
     /// Expanded indicator image URI.
     /// TODO make this a VectorIcon
     pub expanded_row_marker_uri: String,
@@ -271,6 +610,9 @@ pub struct TableViewStyle {
 
     /// Row indentation.
     pub indentation: Length,
+
+    /// Background of the column headers.
+    pub header_background: style::Image,
 }
 
 impl Default for TableViewStyle {
@@ -283,9 +625,12 @@ impl Default for TableViewStyle {
             row_separator_background: theme::TEXT_COLOR.into(),
             column_separator_background: theme::TEXT_COLOR.into(),
             selected_style: Default::default(),
+            cell_selection_background: Color::new(0.0, 0.5, 1.0, 0.25).into(),
+            selected_row_background: Color::new(0.0, 0.5, 1.0, 0.25).into(),
             expanded_row_marker_uri: "data/icons/chevron.png".to_string(),
             collapsed_row_marker_uri: "data/icons/chevron-collapsed.png".to_string(),
             indentation: 16.dip(),
+            header_background: theme::WINDOW_BACKGROUND_COLOR.into(),
         }
     }
 }
@@ -300,12 +645,28 @@ where
     /// If None, selection is disabled.
     pub selection: Option<&'a mut TableSelection<Item::Id>>,
 
+    /// Current range of selected cells, modified with the keyboard (see `CellSelection::after_key`).
+    ///
+    /// If None, cell selection is disabled.
+    pub cell_selection: Option<State<Option<CellSelection>>>,
+
     /// Column headers.
     pub columns: Vec<Column<'a, Item>>,
 
+    /// Order, widths and sorting of the columns.
+    ///
+    /// If None, the state is kept by the table, and is lost when the table is removed.
+    pub columns_state: Option<State<TableColumnsState>>,
+
     pub show_expand_buttons: bool,
 
-    /// Whether the columns are resizable by the user.
+    /// Whether to show a row of column headers above the rows.
+    pub show_headers: bool,
+
+    /// Whether clicking a column header sorts the rows by this column (see `TableColumnsState::sort`).
+    pub sortable_columns: bool,
+
+    /// Whether all columns are resizable by the user, not only those made resizable with `Column::resizable`.
     pub resizeable_columns: bool,
 
     /// Whether the rows are reorderable by the user.
@@ -313,9 +674,9 @@ where
     /// If true, the table will issue `on_reorder` events.
     pub reorderable_rows: bool,
 
-    /// Whether the columns are reorderable by the user.
+    /// Whether the columns are reorderable by the user, by dragging their header.
     ///
-    /// If true, the table will issue `on_column_reorder` events.
+    /// The new order is stored in the `TableColumnsState`, and reported by `Column::on_move`.
     pub reorderable_columns: bool,
 
     /// Table style.
@@ -326,8 +687,12 @@ impl<'a, Item: Identifiable> Default for TableViewParams<'a, Item> {
     fn default() -> Self {
         TableViewParams {
             selection: None,
+            cell_selection: None,
             columns: vec![],
+            columns_state: None,
             show_expand_buttons: true,
+            show_headers: true,
+            sortable_columns: false,
             resizeable_columns: false,
             reorderable_rows: false,
             reorderable_columns: false,
@@ -343,6 +708,18 @@ impl<'a, Item: Identifiable> TableViewParams<'a, Item> {
         self
     }
 
+    /// Stores the order, widths and sorting of the columns in the specified state.
+    pub fn columns_state(mut self, state: State<TableColumnsState>) -> Self {
+        self.columns_state = Some(state);
+        self
+    }
+
+    /// Enables keyboard selection of ranges of cells, stored in the specified state.
+    pub fn cell_selection(mut self, selection: State<Option<CellSelection>>) -> Self {
+        self.cell_selection = Some(selection);
+        self
+    }
+
     /// Sets whether to display the row expand buttons.
     pub fn show_expand_buttons(mut self, show: bool) -> Self {
         self.show_expand_buttons = show;
@@ -350,6 +727,156 @@ impl<'a, Item: Identifiable> TableViewParams<'a, Item> {
     }
 }
 
+/// Minimum width of a column resized by the user.
+const MIN_COLUMN_WIDTH: f64 = 16.0;
+/// Space reserved on the right of the column headers for the sort indicator.
+const SORT_INDICATOR_SPACE: f64 = 16.0;
+/// Width of the sort indicator.
+const SORT_INDICATOR_SIZE: f64 = 8.0;
+/// Distance that the pointer must move after pressing a column header to start dragging the column.
+const HEADER_DRAG_THRESHOLD: f64 = 4.0;
+
+/// Action on a column header, applied in the next composition of the table.
+#[derive(Copy, Clone, Debug)]
+enum HeaderAction {
+    /// The header at this position was clicked.
+    Click(usize),
+    /// The header at `position` was dragged by `offset` and dropped.
+    Drop { position: usize, offset: f64 },
+}
+
+/// State of a drag of a column header.
+#[derive(Default)]
+struct HeaderDrag {
+    /// Horizontal position of the pointer in the window when the header was pressed.
+    start: Cell<Option<f64>>,
+    /// Whether the pointer has moved far enough to drag the column.
+    dragging: Cell<bool>,
+    /// Current distance from the start of the drag.
+    offset: Cell<f64>,
+}
+
+/// Header of a column.
+///
+/// Clicks sort the rows, and dragging the header moves the column. The header follows the pointer while it's dragged,
+/// and the columns are reordered in the next composition, when it's dropped.
+struct HeaderCell {
+    content: Arc<WidgetPod>,
+    position: usize,
+    sort: Option<SortOrder>,
+    indicator_color: Color,
+    reorderable: bool,
+    drag: Arc<HeaderDrag>,
+    /// Widths of the headers during the last layout, by position. Shared by all headers of the table.
+    widths: Arc<Mutex<Vec<f64>>>,
+    actions: Signal<HeaderAction>,
+    clicked: Signal<()>,
+    moved: Signal<()>,
+}
+
+impl Widget for HeaderCell {
+    fn widget_id(&self) -> Option<WidgetId> {
+        None
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let content_constraints = LayoutParams {
+            min: Size::new(
+                (constraints.min.width - SORT_INDICATOR_SPACE).max(0.0),
+                constraints.min.height,
+            ),
+            max: Size::new(
+                (constraints.max.width - SORT_INDICATOR_SPACE).max(0.0),
+                constraints.max.height,
+            ),
+            ..*constraints
+        };
+        let content = self.content.layout(ctx, &content_constraints, env).measurements.size;
+        // take the whole width of the column
+        let width = if constraints.max.width.is_finite() {
+            constraints.max.width
+        } else {
+            content.width + SORT_INDICATOR_SPACE
+        };
+        let mut widths = self.widths.lock();
+        if let Some(w) = widths.get_mut(self.position) {
+            *w = width;
+        }
+        Geometry::new(Size::new(width, content.height.max(constraints.min.height)))
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.content.route_event(ctx, event, env);
+        if ctx.handled() {
+            return;
+        }
+        if let Event::Pointer(p) = event {
+            match p.kind {
+                PointerEventKind::PointerDown if p.button == Some(PointerButton::LEFT) => {
+                    self.drag.start.set(Some(p.window_position.x));
+                    ctx.capture_pointer();
+                    ctx.set_handled();
+                }
+                PointerEventKind::PointerMove => {
+                    if let Some(start) = self.drag.start.get() {
+                        let offset = p.window_position.x - start;
+                        if self.reorderable && (self.drag.dragging.get() || offset.abs() > HEADER_DRAG_THRESHOLD) {
+                            self.drag.dragging.set(true);
+                            self.drag.offset.set(offset);
+                            self.content.set_offset(Offset::new(offset, 0.0));
+                            ctx.request_repaint();
+                        }
+                        ctx.set_handled();
+                    }
+                }
+                PointerEventKind::PointerUp if p.button == Some(PointerButton::LEFT) => {
+                    if let Some(start) = self.drag.start.take() {
+                        if self.drag.dragging.take() {
+                            self.drag.offset.set(0.0);
+                            self.content.set_offset(Offset::zero());
+                            self.actions.signal(HeaderAction::Drop {
+                                position: self.position,
+                                offset: p.window_position.x - start,
+                            });
+                            self.moved.signal(());
+                            ctx.request_repaint();
+                        } else {
+                            self.actions.signal(HeaderAction::Click(self.position));
+                            self.clicked.signal(());
+                        }
+                        ctx.release_pointer();
+                        ctx.set_handled();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx);
+        if let Some(sort) = self.sort {
+            // a triangle pointing up for ascending order, down for descending order
+            let x = ctx.bounds.max_x() - 0.5 * SORT_INDICATOR_SPACE + self.drag.offset.get();
+            let y = ctx.bounds.center().y;
+            let half_width = 0.5 * SORT_INDICATOR_SIZE;
+            let half_height = 0.25 * SORT_INDICATOR_SIZE;
+            let (base_y, tip_y) = match sort {
+                SortOrder::Ascending => (y + half_height, y - half_height),
+                SortOrder::Descending => (y - half_height, y + half_height),
+            };
+            let mut path = sk::Path::new();
+            path.move_to(Point::new(x - half_width, base_y).to_skia());
+            path.line_to(Point::new(x + half_width, base_y).to_skia());
+            path.line_to(Point::new(x, tip_y).to_skia());
+            path.close();
+            let mut paint = sk::Paint::new(self.indicator_color.to_skia(), None);
+            paint.set_anti_alias(true);
+            ctx.surface.canvas().draw_path(&path, &paint);
+        }
+    }
+}
+
 // Tables: collection models VS procedural?
 //
 // Collection models:
@@ -360,34 +887,57 @@ impl<'a, Item: Identifiable> TableViewParams<'a, Item> {
 // - user creates all rows by hand, places all widgets manually
 
 pub struct TableView {
+    id: WidgetId,
     grid: Grid,
+    cell_selection: Option<State<Option<CellSelection>>>,
+    columns_state: State<TableColumnsState>,
+    row_count: usize,
+    column_count: usize,
 }
 
 impl TableView {
     /// Creates a new tree grid.
     #[composable]
     pub fn new<Item: Identifiable>(mut params: TableViewParams<Item>, collection: impl Collection<Item>) -> TableView {
-        // create the main grid
+        let column_count = params.columns.len();
+
+        // column order, widths and sorting, modified by the header actions
+        let internal_columns_state = cache::state(TableColumnsState::default);
+        let columns_state = params.columns_state.take().unwrap_or(internal_columns_state);
+        let mut columns = columns_state.get();
+        let fitted = columns.fit(column_count);
+        let original_columns = columns.clone();
+
+        let header_widths: Arc<Mutex<Vec<f64>>> = cache::once(|| Arc::new(Mutex::new(Vec::new())));
+        header_widths.lock().resize(column_count, 0.0);
+        let header_actions = Signal::new();
+        match header_actions.value() {
+            Some(HeaderAction::Click(position)) if params.sortable_columns => {
+                columns.toggle_sort(columns.order[position]);
+            }
+            Some(HeaderAction::Drop { position, offset }) => {
+                let target = drop_position(&header_widths.lock(), position, offset);
+                columns.move_column(position, target);
+            }
+            _ => {}
+        }
 
-        // TODO identify columns by name
+        // create the main grid
         let mut grid_template = GridTemplate::new();
-        for p in params.columns.iter() {
-            if let Some(current_size) = p.current_size.as_ref() {
-                grid_template
-                    .columns
-                    .sizes
-                    .push(TrackSize::new(current_size.get().dip()));
-            } else {
-                grid_template.columns.sizes.push(p.size);
+        for &column_index in columns.order.iter() {
+            let column = &params.columns[column_index];
+            match columns.width(column_index).or(column.initial_width) {
+                Some(width) => grid_template.columns.sizes.push(TrackSize::new(width.dip())),
+                None => grid_template.columns.sizes.push(column.size),
             }
         }
 
         let mut grid = Grid::new(Arc::new(grid_template));
 
-        //let num_columns = grid.column_count();
-
         // row counter
         let mut row_index = 0;
+        // grid row of the first table row, after the headers
+        let first_row = if params.show_headers { 1 } else { 0 };
 
         grid.set_row_background(params.style.background);
         grid.set_alternate_row_background(params.style.alternate_background);
@@ -396,6 +946,48 @@ impl TableView {
         grid.set_row_gap(params.style.row_separator_width);
         grid.set_column_gap(params.style.column_separator_width);
 
+        // column headers
+        if params.show_headers {
+            let indicator_color = cache::environment()
+                .get(&theme::TEXT_COLOR)
+                .unwrap_or(Color::new(1.0, 1.0, 1.0, 1.0));
+            for (position, &column_index) in columns.order.iter().enumerate() {
+                let column = &params.columns[column_index];
+                let sort = match columns.sort {
+                    Some((sorted_column, order)) if sorted_column == column_index => Some(order),
+                    _ => None,
+                };
+                let header = cache::scoped(column_index, || HeaderCell {
+                    content: column.header.clone(),
+                    position,
+                    sort,
+                    indicator_color,
+                    reorderable: params.reorderable_columns,
+                    drag: cache::once(|| Arc::new(HeaderDrag::default())),
+                    widths: header_widths.clone(),
+                    actions: header_actions.clone(),
+                    clicked: column.clicked.clone(),
+                    moved: column.moved.clone(),
+                });
+                grid.insert(
+                    header
+                        .background(params.style.header_background.clone())
+                        .grid_area((0, position)),
+                );
+            }
+        }
+
+        // cell selection highlight, below the cells
+        let cell_selection = params.cell_selection.as_ref().and_then(|s| s.get());
+        if let Some(selection) = cell_selection {
+            let rows = selection.rows();
+            grid.insert(
+                Null.fill()
+                    .background(params.style.cell_selection_background.clone())
+                    .grid_area((first_row + rows.start..first_row + rows.end, selection.columns())),
+            );
+        }
+
         // insert rows
         {
             let icon_size = params.style.indentation;
@@ -417,6 +1009,7 @@ impl TableView {
             // depth-order traversal of the row hierarchy
             while let Some((indent_level, row)) = visit.pop() {
                 let id = row.id();
+                let grid_row = first_row + row_index;
 
                 cache::enter(&id);
                 // row selection highlight
@@ -425,7 +1018,7 @@ impl TableView {
                         // draw a filled rect with the selection style that spans the whole row
 
                         // .box_style(params.selected_style.clone())
-                        grid.insert(Null.fill().grid_area((row_index, ..)));
+                        grid.insert(Null.fill().grid_area((grid_row, ..)));
                     }
                     // also add a clickable rect, and clicking it adds the row to the selection
                     /*grid.insert(
@@ -441,8 +1034,9 @@ impl TableView {
                 let expanded_state = cache::state(|| false);
                 let expanded = expanded_state.get();
 
-                // add row cells to the grid
-                for (column_index, column) in params.columns.iter().enumerate() {
+                // add row cells to the grid, in the display order of the columns
+                for (position, &column_index) in columns.order.iter().enumerate() {
+                    let column = &params.columns[column_index];
                     cache::enter((id.clone(), column_index));
                     let cell_widget = (column.delegate)(&row);
                     let child_count = collection.child_count(&row);
@@ -466,7 +1060,7 @@ impl TableView {
                                 expand_button
                                     .left_of(cell_widget.clone(), Alignment::CENTER)
                                     .padding_left((indent_level as f64) * params.style.indentation)
-                                    .grid_area((row_index, column_index)),
+                                    .grid_area((grid_row, position)),
                             );
                         } else {
                             // no expand button
@@ -476,11 +1070,11 @@ impl TableView {
                                     .clone()
                                     .padding_left(icon_size)
                                     .padding_left((indent_level as f64) * params.style.indentation)
-                                    .grid_area((row_index, column_index)),
+                                    .grid_area((grid_row, position)),
                             );
                         }
                     } else {
-                        grid.insert(cell_widget.clone().grid_area((row_index, column_index)));
+                        grid.insert(cell_widget.clone().grid_area((grid_row, position)));
                     }
                     cache::exit();
                 }
@@ -498,50 +1092,89 @@ impl TableView {
 
         //------------------------------------------
         // column resizing
-        for i_col_split in 1..params.columns.len() {
-            let (left_cols, right_cols) = params.columns.split_at_mut(i_col_split);
-            let left_column = left_cols.last_mut().unwrap();
-            let right_column = right_cols.first_mut().unwrap();
-
-            // insert an invisible resize handle between two resizable columns
-            if left_column.is_resizable() && right_column.is_resizable() {
-                let left_column_size = left_column.current_size.as_ref().unwrap().get();
-                let right_column_size = right_column.current_size.as_ref().unwrap().get();
-
-                let resize_handle = DragController::new(
-                    (left_column_size, right_column_size),
-                    Placeholder
-                        .frame(4.dip(), 50.dip())
-                        .left(-2.dip())
-                        .cursor_icon(winit::window::CursorIcon::ColResize)
-                        .debug(DebugFlags::DUMP_GEOMETRY | DebugFlags::DUMP_CONSTRAINTS),
+        let mut resized = None;
+        for (position, &column_index) in columns.order.iter().enumerate() {
+            let column = &params.columns[column_index];
+            if !(params.resizeable_columns || column.is_resizable()) {
+                continue;
+            }
+
+            // columns that have not been resized yet start from their current width
+            let width = columns
+                .width(column_index)
+                .or(column.initial_width)
+                .unwrap_or_else(|| header_widths.lock()[position]);
+
+            // insert an invisible resize handle on the right edge of the column
+            let resize_handle = cache::scoped(column_index, || {
+                DragController::new(
+                    width,
+                    Null.frame(4.dip(), 100.percent())
+                        .right(-2.dip())
+                        .cursor_icon(winit::window::CursorIcon::ColResize),
                 )
-                .on_delta(|(left, right), offset| {
+                .on_delta(|width, offset| {
                     trace!("column resize drag offset={offset:?}");
-                    left_column.current_size.as_mut().unwrap().set(left + offset.x);
-                    right_column
-                        .current_size
-                        .as_mut()
-                        .unwrap()
-                        .set((right - offset.x).max(0.0));
+                    resized = Some((column_index, (width + offset.x).max(MIN_COLUMN_WIDTH)));
                 })
-                .debug_name("table resize handle");
+                .debug_name("table resize handle")
+            });
+
+            grid.place(
+                (0..first_row + row_index, position), // span all rows, but only the current column
+                99,                                   // place it over all other grid items
+                resize_handle.arc_pod(),
+            );
+        }
+        if let Some((column_index, width)) = resized {
+            columns.set_width(column_index, width);
+        }
 
-                grid.place(
-                    (0..row_index, i_col_split), // span all rows, but only the current column
-                    99,                          // place it over all other grid items
-                    resize_handle.arc_pod(),
-                );
+        // the rows may have been removed since the selection was made
+        if let Some(selection) = cell_selection {
+            if selection.rows().end > row_index || selection.columns().end > column_count {
+                let cell_selection = params.cell_selection.as_ref().unwrap();
+                cell_selection.set_without_invalidation(None);
             }
         }
 
-        TableView { grid }
+        // changes made by the user must be seen by the composition of the rows (which may depend on the sort order)
+        // and the grid template, so recompose
+        if columns != original_columns {
+            columns_state.set(columns);
+        } else if fitted {
+            columns_state.set_without_invalidation(columns);
+        }
+
+        TableView {
+            id: WidgetId::here(),
+            grid,
+            cell_selection: params.cell_selection,
+            columns_state,
+            row_count: row_index,
+            column_count,
+        }
+    }
+
+    /// Returns the current range of selected cells.
+    pub fn cell_selection(&self) -> Option<CellSelection> {
+        self.cell_selection.as_ref().and_then(|s| s.get())
+    }
+
+    /// Returns the order, widths and sorting of the columns.
+    pub fn columns_state(&self) -> TableColumnsState {
+        self.columns_state.get()
+    }
+
+    /// Returns the index of the column by which the rows should be sorted, and the direction.
+    pub fn sort(&self) -> Option<(usize, SortOrder)> {
+        self.columns_state.get().sort
     }
 }
 
 impl Widget for TableView {
     fn widget_id(&self) -> Option<WidgetId> {
-        self.grid.widget_id()
+        Some(self.id)
     }
 
     fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
@@ -549,10 +1182,75 @@ impl Widget for TableView {
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Some(ref cell_selection) = self.cell_selection {
+            match event {
+                Event::Pointer(p) if p.kind == PointerEventKind::PointerDown => {
+                    // receive keyboard events
+                    ctx.request_focus();
+                }
+                Event::Keyboard(k) => {
+                    if let Some(selection) =
+                        CellSelection::after_key(cell_selection.get(), k, self.row_count, self.column_count)
+                    {
+                        cell_selection.set(Some(selection));
+                        ctx.set_handled();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
         self.grid.route_event(ctx, event, env);
     }
 
     fn paint(&self, ctx: &mut PaintCtx) {
         self.grid.paint(ctx)
     }
+
+    fn is_focusable(&self) -> bool {
+        self.cell_selection.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_state() {
+        let mut state = TableColumnsState {
+            order: vec![3, 1, 1, 0],
+            widths: vec![Some(100.0)],
+            sort: Some((3, SortOrder::Descending)),
+        };
+        // a column was removed since the state was saved
+        assert!(state.fit(3));
+        assert_eq!(state.order, vec![1, 0, 2]);
+        assert_eq!(state.widths, vec![Some(100.0), None, None]);
+        assert_eq!(state.sort, None);
+        assert!(!state.fit(3));
+
+        state.move_column(0, 2);
+        assert_eq!(state.order, vec![0, 2, 1]);
+        assert_eq!(state.position(1), Some(2));
+
+        state.toggle_sort(2);
+        assert_eq!(state.sort, Some((2, SortOrder::Ascending)));
+        state.toggle_sort(2);
+        assert_eq!(state.sort, Some((2, SortOrder::Descending)));
+        state.toggle_sort(0);
+        assert_eq!(state.sort, Some((0, SortOrder::Ascending)));
+    }
+
+    #[test]
+    fn column_drop_position() {
+        let widths = [100.0, 50.0, 200.0];
+        assert_eq!(drop_position(&widths, 0, 10.0), 0);
+        // past the center of the second column
+        assert_eq!(drop_position(&widths, 0, 80.0), 1);
+        assert_eq!(drop_position(&widths, 0, 500.0), 2);
+        assert_eq!(drop_position(&widths, 2, -150.0), 1);
+        assert_eq!(drop_position(&widths, 2, -210.0), 0);
+        assert_eq!(drop_position(&widths, 1, -20.0), 1);
+    }
 }
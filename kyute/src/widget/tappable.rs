@@ -0,0 +1,186 @@
+//! Tap and long-press recognition.
+use crate::{
+    cache,
+    event::{PointerEventKind, PointerId},
+    widget::prelude::*,
+    Signal, State,
+};
+use parking_lot::Mutex;
+use std::{sync::Arc, time::Duration};
+
+/// Default time a pointer must stay pressed before a long press is recognized.
+const DEFAULT_LONG_PRESS_DELAY: Duration = Duration::from_millis(500);
+/// Default distance (in DIPs) that a pressed pointer can move before the gesture is cancelled.
+const DEFAULT_TOLERANCE: f64 = 8.0;
+
+#[derive(Default)]
+struct Tracking {
+    /// Pointer being tracked, and its position (in window coordinates) when it was pressed.
+    pointer: Option<(PointerId, Point)>,
+    /// Incremented each time a gesture starts or is cancelled, to discard stale timers.
+    generation: u64,
+    /// Whether a long press has been recognized for the tracked pointer.
+    long_pressed: bool,
+}
+
+/// Recognizes taps and long presses on the inner widget, with the mouse or on a touch screen.
+///
+/// A tap is a press and release of a pointer that doesn't move more than a small distance, and is released
+/// before the long press delay. A long press is recognized when the pointer stays pressed for the delay without moving;
+/// its release is not a tap. Only the last pointer pressed over the widget is tracked: pressing another one (e.g. a
+/// second finger) restarts the gesture.
+///
+/// The pointer is captured, unless the inner widget captures it first (e.g. a button): in this case, the gesture is
+/// only recognized if the pointer is released over the widget.
+pub struct Tappable<Inner> {
+    id: WidgetId,
+    inner: Inner,
+    long_press_delay: Duration,
+    tolerance: f64,
+    tracking: Arc<Mutex<Tracking>>,
+    tapped: Signal<()>,
+    /// Set when a long press is recognized (from a timer). Consumed during composition.
+    long_press: State<bool>,
+    long_pressed: bool,
+}
+
+impl<Inner: Widget + 'static> Tappable<Inner> {
+    /// Creates a new `Tappable` with the default long press delay (500ms) and movement tolerance (8 DIPs).
+    #[composable]
+    pub fn new(inner: Inner) -> Tappable<Inner> {
+        let long_press = cache::state(|| false);
+        let long_pressed = long_press.take_without_invalidation();
+        Tappable {
+            id: WidgetId::here(),
+            inner,
+            long_press_delay: DEFAULT_LONG_PRESS_DELAY,
+            tolerance: DEFAULT_TOLERANCE,
+            tracking: cache::once(|| Arc::new(Mutex::new(Tracking::default()))),
+            tapped: Signal::new(),
+            long_press,
+            long_pressed,
+        }
+    }
+
+    /// Sets the time a pointer must stay pressed before a long press is recognized.
+    #[must_use]
+    pub fn long_press_delay(mut self, delay: Duration) -> Self {
+        self.long_press_delay = delay;
+        self
+    }
+
+    /// Sets the distance that a pressed pointer can move before the gesture is cancelled.
+    #[must_use]
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Returns whether the widget has been tapped.
+    pub fn tapped(&self) -> bool {
+        self.tapped.signalled()
+    }
+
+    /// Returns whether a long press has been recognized.
+    pub fn long_pressed(&self) -> bool {
+        self.long_pressed
+    }
+
+    #[must_use]
+    pub fn on_tap(self, f: impl FnOnce()) -> Self {
+        if self.tapped() {
+            f();
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn on_long_press(self, f: impl FnOnce()) -> Self {
+        if self.long_pressed() {
+            f();
+        }
+        self
+    }
+
+    /// Returns a reference to the inner widget.
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner widget.
+    pub fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+
+    /// Starts tracking a pressed pointer, and the long press delay.
+    fn start(&self, pointer_id: PointerId, position: Point) {
+        let generation = {
+            let mut tracking = self.tracking.lock();
+            tracking.pointer = Some((pointer_id, position));
+            tracking.generation += 1;
+            tracking.long_pressed = false;
+            tracking.generation
+        };
+
+        let tracking = self.tracking.clone();
+        let long_press = self.long_press.clone();
+        let delay = self.long_press_delay;
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let mut tracking = tracking.lock();
+            if tracking.generation == generation && tracking.pointer.is_some() {
+                tracking.long_pressed = true;
+                long_press.set(true);
+            }
+        });
+    }
+
+    /// Stops tracking the pointer. Returns whether the gesture was a tap.
+    fn stop(&self) -> bool {
+        let mut tracking = self.tracking.lock();
+        tracking.generation += 1;
+        tracking.pointer.take().is_some() && !tracking.long_pressed
+    }
+}
+
+impl<Inner: Widget + 'static> Widget for Tappable<Inner> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.inner.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.inner.route_event(ctx, event, env);
+
+        if let Event::Pointer(p) = event {
+            let tracked = self.tracking.lock().pointer;
+            match (p.kind, tracked) {
+                (PointerEventKind::PointerDown, _) => {
+                    self.start(p.pointer_id, p.window_position);
+                    if ctx.pointer_capture(p.pointer_id).is_none() {
+                        ctx.capture_pointer();
+                    }
+                }
+                (PointerEventKind::PointerMove, Some((pointer_id, start)))
+                    if pointer_id == p.pointer_id && (p.window_position - start).length() > self.tolerance =>
+                {
+                    // moved too much: this is a drag, not a tap
+                    self.stop();
+                }
+                (PointerEventKind::PointerUp, Some((pointer_id, _))) if pointer_id == p.pointer_id => {
+                    if self.stop() {
+                        self.tapped.signal(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.inner.paint(ctx)
+    }
+}
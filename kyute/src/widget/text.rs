@@ -1,19 +1,39 @@
 use crate::{
-    composable,
+    cache, composable,
     core::DebugNode,
     drawing::{PaintCtx, ToSkia},
-    make_uniform_data, theme, Color, Data, EnvRef, Environment, Event, EventCtx, Font, Geometry, LayoutCache,
-    LayoutCtx, LayoutParams, Measurements, Point, RectI, RoundToPixel, Transform, Widget, WidgetId,
+    event::{InternalEvent, Modifiers, PointerButton, PointerEvent, PointerEventKind},
+    make_uniform_data, theme,
+    widget::{
+        text_bindings::{Movement, TextEditAction, TextEditBindings},
+        text_edit::{next_grapheme_cluster, next_word_end, prev_grapheme_cluster, prev_word_start},
+    },
+    Color, Data, EnvRef, Environment, Event, EventCtx, Font, Geometry, IntrinsicWidths, LayoutCache, LayoutCtx,
+    LayoutParams, Measurements, Offset, Point, Rect, RectI, RoundToPixel, Signal, Size, State, Transform, Widget,
+    WidgetId,
 };
-use kyute_shell::text::{
-    FormattedText, GlyphMaskData, GlyphMaskFormat, GlyphRun, GlyphRunDrawingEffects, Paragraph, ParagraphStyle,
-    RasterizationOptions,
+use keyboard_types::KeyState;
+use kyute_shell::{
+    text::{
+        FormattedText, GlyphMaskData, GlyphMaskFormat, GlyphRun, GlyphRunDrawingEffects, LineStyle, Paragraph,
+        ParagraphStyle, RasterizationOptions, Selection, TextAffinity, TextOutline, TextPosition, TextShadow,
+    },
+    winit::window::CursorIcon,
 };
 use lazy_static::lazy_static;
 use skia_safe as sk;
-use std::{cell::Ref, ptr};
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    ops::Range,
+    ptr,
+    sync::Arc,
+};
 use threadbound::ThreadBound;
 
+/// Color of the selection highlight.
+// TODO color from environment or theme
+pub(crate) const SELECTION_COLOR: Color = Color::new(0.0, 0.8, 0.8, 0.5);
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
 struct GlyphMaskImage {
@@ -113,6 +133,68 @@ lazy_static! {
         ThreadBound::new(sk::RuntimeEffect::make_for_blender(LCD_MASK_BLENDER_SKSL, None).unwrap());
 }
 
+impl<'a, 'b> Renderer<'a, 'b> {
+    /// Draws a glyph mask with the specified color.
+    ///
+    /// `offset` is in physical pixels. `image_filter` is applied to the mask before blending (used for shadow blur and
+    /// outlines).
+    fn draw_glyph_mask(
+        &mut self,
+        mask_image: &GlyphMaskImage,
+        color: Color,
+        offset: sk::Vector,
+        image_filter: Option<sk::ImageFilter>,
+    ) {
+        let apply_mask_effect = APPLY_MASK_EFFECT.get_ref().unwrap();
+
+        let mask_blender = {
+            let (r, g, b, a) = color.to_rgba();
+            let uniform_data = make_uniform_data!([apply_mask_effect]
+                color: [f32; 4] = [r, g, b, a];
+            );
+            apply_mask_effect
+                .make_blender(uniform_data.0, None)
+                .expect("make_blender failed")
+        };
+
+        let mut paint = sk::Paint::new(color.to_skia(), None);
+        paint.set_blender(mask_blender);
+        if let Some(image_filter) = image_filter {
+            paint.set_image_filter(image_filter);
+        }
+
+        let _span = trace_span!("Draw glyph mask image").entered();
+        let bounds = mask_image.bounds;
+        let canvas = self.ctx.surface.canvas();
+        canvas.save();
+        canvas.reset_matrix();
+        canvas.draw_image(
+            &mask_image.mask,
+            sk::Point::new(
+                bounds.origin.x as sk::scalar + offset.x,
+                bounds.origin.y as sk::scalar + offset.y,
+            ),
+            Some(&paint),
+        );
+        canvas.restore();
+    }
+
+    /// Fills the boxes of a range of text. Called before drawing the glyphs, so that they appear above the highlight.
+    fn draw_selection(&mut self, paragraph: &Paragraph, range: Range<usize>, color: Color) {
+        let paint = sk::Paint::new(color.to_skia(), None);
+        let canvas = self.ctx.surface.canvas();
+        for hit in paragraph.hit_test_text_range(range, Point::origin()) {
+            canvas.draw_rect(hit.bounds.to_skia(), &paint);
+        }
+    }
+
+    /// Converts a length in DIPs to physical pixels, taking into account the current layer transform.
+    fn to_physical(&self, offset: Offset) -> sk::Vector {
+        let v = self.ctx.layer_transform().transform_vector(offset) * self.ctx.scale_factor;
+        sk::Vector::new(v.x as sk::scalar, v.y as sk::scalar)
+    }
+}
+
 impl<'a, 'b> kyute_shell::text::Renderer for Renderer<'a, 'b> {
     fn draw_glyph_run(&mut self, glyph_run: &GlyphRun, drawing_effects: &GlyphRunDrawingEffects) {
         let analysis = {
@@ -127,60 +209,77 @@ impl<'a, 'b> kyute_shell::text::Renderer for Renderer<'a, 'b> {
         };
         if let Some(mask) = mask {
             let mask_image = GlyphMaskImage::new(bounds, mask);
-            let color = drawing_effects.color;
-
-            let apply_mask_effect = APPLY_MASK_EFFECT.get_ref().unwrap();
-
-            let mask_blender = {
-                let (r, g, b, a) = color.to_rgba();
-                let uniform_data = make_uniform_data!([apply_mask_effect]
-                    color: [f32; 4] = [r, g, b, a];
-                );
-                apply_mask_effect
-                    .make_blender(uniform_data.0, None)
-                    .expect("make_blender failed")
-                /*// set color uniform
-                let mut u_offset = None;
-                let mut u_size = None;
-                for u in apply_mask_effect.uniforms() {
-                    if u.name() == "color" {
-                        u_offset = Some(u.offset());
-                        u_size = Some(u.size_in_bytes());
-                    }
-                }
-                let u_offset = u_offset.unwrap();
-                let u_size = u_size.unwrap();
-
-                let uniform_size = apply_mask_effect.uniform_size();
-                assert!(u_offset < uniform_size);
-                let mut uniform_data: Vec<u8> = Vec::with_capacity(uniform_size);
-                unsafe {
-                    let uniform_ptr = uniform_data.as_mut_ptr();
-                    let (r, g, b, a) = color.to_rgba();
-                    ptr::write(uniform_ptr.add(u_offset).cast::<[f32; 4]>(), [r, g, b, a]);
-                    uniform_data.set_len(uniform_size);
+
+            // shadow, then outline, then the glyphs themselves
+            if let Some(shadow) = drawing_effects.shadow {
+                let offset = self.to_physical(shadow.offset);
+                let blur = if shadow.blur_radius > 0.0 {
+                    // same convention as box shadows: the standard deviation is half the blur radius
+                    let sigma = (0.5 * shadow.blur_radius * self.ctx.scale_factor) as sk::scalar;
+                    sk::image_filters::blur((sigma, sigma), None, None, None)
+                } else {
+                    None
+                };
+                self.draw_glyph_mask(&mask_image, shadow.color, offset, blur);
+            }
+
+            if let Some(outline) = drawing_effects.outline {
+                let radius = (outline.width * self.ctx.scale_factor) as sk::scalar;
+                let dilate = sk::image_filters::dilate((radius, radius), None, None);
+                self.draw_glyph_mask(&mask_image, outline.color, sk::Vector::new(0.0, 0.0), dilate);
+            }
+
+            self.draw_glyph_mask(&mask_image, drawing_effects.color, sk::Vector::new(0.0, 0.0), None);
+        }
+    }
+
+    fn draw_line(&mut self, rect: Rect, style: LineStyle, color: Color) {
+        let mut paint = sk::Paint::new(color.to_skia(), None);
+        let canvas = self.ctx.surface.canvas();
+        if style == LineStyle::Solid {
+            canvas.draw_rect(rect.to_skia(), &paint);
+            return;
+        }
+
+        // stroke along the middle of the line; keep dots and waves visible at small sizes
+        let thickness = rect.height().max(1.0);
+        paint.set_anti_alias(true);
+        paint.set_style(sk::PaintStyle::Stroke);
+        paint.set_stroke_width(thickness as sk::scalar);
+        let y = rect.center().y as sk::scalar;
+        let mut path = sk::Path::new();
+        path.move_to((rect.min_x() as sk::scalar, y));
+        match style {
+            LineStyle::Wavy => {
+                // waves of about 4 line thicknesses, adjusted to end exactly at the end of the line
+                let count = (rect.width() / (2.0 * thickness)).ceil().max(1.0);
+                let half_period = rect.width() / count;
+                for i in 0..count as usize {
+                    let x = rect.min_x() + i as f64 * half_period;
+                    let peak = if i % 2 == 0 { -thickness } else { thickness };
+                    path.quad_to(
+                        ((x + 0.5 * half_period) as sk::scalar, y + 2.0 * peak as sk::scalar),
+                        ((x + half_period) as sk::scalar, y),
+                    );
                 }
-                let uniform_data = sk::Data::new_copy(&uniform_data);*/
-            };
-
-            let mut paint = sk::Paint::new(color.to_skia(), None);
-            paint.set_blender(mask_blender);
-
-            {
-                let _span = trace_span!("Draw glyph mask image").entered();
-                let canvas = self.ctx.surface.canvas();
-                canvas.save();
-                //let inv_scale_factor = 1.0 / self.ctx.scale_factor as f32;
-                //self.ctx.canvas.scale((inv_scale_factor, inv_scale_factor));
-                canvas.reset_matrix();
-                canvas.draw_image(
-                    &mask_image.mask,
-                    sk::Point::new(bounds.origin.x as sk::scalar, bounds.origin.y as sk::scalar),
-                    Some(&paint),
-                );
-                canvas.restore();
+            }
+            _ => {
+                path.line_to((rect.max_x() as sk::scalar, y));
+                let thickness = thickness as sk::scalar;
+                let intervals = if style == LineStyle::Dotted {
+                    [thickness, thickness]
+                } else {
+                    [3.0 * thickness, 2.0 * thickness]
+                };
+                paint.set_path_effect(sk::PathEffect::dash(&intervals, 0.0));
             }
         }
+        canvas.draw_path(&path, &paint);
+    }
+
+    fn draw_background(&mut self, rect: Rect, color: Color) {
+        let paint = sk::Paint::new(color.to_skia(), None);
+        self.ctx.surface.canvas().draw_rect(rect.to_skia(), &paint);
     }
 
     fn transform(&self) -> Transform {
@@ -195,15 +294,62 @@ impl<'a, 'b> kyute_shell::text::Renderer for Renderer<'a, 'b> {
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Text widget
 ////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A paragraph shaped with a particular style.
+///
+/// Shaping is the expensive part of text layout. The shaped paragraph is kept across layouts with different
+/// constraints (e.g. the speculative layouts of grid auto tracks, followed by the final layout): only the line
+/// breaks are recomputed.
+struct ShapedParagraph {
+    style: ParagraphStyle,
+    paragraph: Paragraph,
+    /// Current layout box size of the paragraph.
+    max_size: Size,
+    /// Intrinsic widths, in DIPs, not rounded to pixels.
+    intrinsic_widths: Option<IntrinsicWidths>,
+}
+
+impl ShapedParagraph {
+    fn set_max_size(&mut self, max_size: Size) {
+        if self.max_size != max_size {
+            self.paragraph.set_max_size(max_size);
+            self.max_size = max_size;
+        }
+    }
+}
+
 #[derive(Clone)]
 struct TextLayoutResult {
-    paragraph: Paragraph,
+    /// Layout box size of the paragraph for this layout.
+    max_size: Size,
     measurements: Measurements,
     font: Font,
     color: Color,
 }
 
+/// State of selectable text.
+struct Selectable {
+    id: WidgetId,
+    selection: State<Selection>,
+}
+
+/// Links in the text (see `FormattedText::set_link`).
+struct Links {
+    id: WidgetId,
+    /// Ranges of the links in the text, and their targets.
+    ranges: Vec<(Range<usize>, Arc<str>)>,
+    /// Index of the link being pressed with the pointer.
+    pressed: State<Option<usize>>,
+    activated: Signal<Arc<str>>,
+}
+
 /// Displays formatted text.
+///
+/// The text can be made selectable with `selectable`: the user can then select a range of text with the mouse
+/// (double-click selects a word, triple-click everything) or with the usual keyboard shortcuts, and copy it to
+/// the clipboard.
+///
+/// Ranges of the text can be links (see `FormattedText::set_link`): the cursor changes to a hand over them, and
+/// clicking on one activates it (see `on_link_activated`).
 pub struct Text {
     /// Input formatted text.
     formatted_text: FormattedText,
@@ -211,8 +357,18 @@ pub struct Text {
     font: EnvRef<Font>,
     /// Text color.
     color: EnvRef<Color>,
+    /// Shadow drawn behind the text.
+    shadow: Option<TextShadow>,
+    /// Outline drawn around the glyphs.
+    outline: Option<TextOutline>,
     /// The formatted paragraph, calculated during layout. `None` if not yet calculated.
     cached_layout: LayoutCache<TextLayoutResult>,
+    /// The shaped paragraph, `None` if not yet shaped.
+    shaped: RefCell<Option<ShapedParagraph>>,
+    /// `None` if the text is not selectable.
+    selectable: Option<Selectable>,
+    /// `None` if the text has no links.
+    links: Option<Links>,
 }
 
 impl Text {
@@ -221,11 +377,27 @@ impl Text {
     pub fn new(formatted_text: impl Into<FormattedText>) -> Text {
         let formatted_text = formatted_text.into();
         //trace!("Text::new {:?}", formatted_text.plain_text);
+        let link_ranges = formatted_text.links();
+        let links = if link_ranges.is_empty() {
+            None
+        } else {
+            Some(Links {
+                id: WidgetId::here(),
+                ranges: link_ranges,
+                pressed: cache::state(|| None),
+                activated: Signal::new(),
+            })
+        };
         Text {
             formatted_text,
             font: EnvRef::Env(theme::DEFAULT_FONT),
             color: EnvRef::Env(theme::TEXT_COLOR),
+            shadow: None,
+            outline: None,
             cached_layout: Default::default(),
+            shaped: RefCell::new(None),
+            selectable: None,
+            links,
         }
     }
 
@@ -239,16 +411,314 @@ impl Text {
         self
     }
 
+    /// Draws a shadow behind the text.
+    ///
+    /// `offset` and `blur_radius` are in DIPs.
+    pub fn shadow(mut self, offset: Offset, blur_radius: f64, color: Color) -> Self {
+        self.shadow = Some(TextShadow {
+            offset,
+            blur_radius,
+            color,
+        });
+        self
+    }
+
+    /// Draws an outline of the specified width (in DIPs) around the glyphs.
+    ///
+    /// Useful to keep text readable over images or busy backgrounds.
+    pub fn outline(mut self, width: f64, color: Color) -> Self {
+        self.outline = Some(TextOutline { width, color });
+        self
+    }
+
+    /// Makes the text selectable.
+    #[composable]
+    pub fn selectable(mut self) -> Self {
+        self.selectable = Some(Selectable {
+            id: WidgetId::here(),
+            selection: cache::state(Selection::default),
+        });
+        self
+    }
+
+    /// Returns the target of the link that was clicked, if any.
+    pub fn link_activated(&self) -> Option<Arc<str>> {
+        self.links.as_ref()?.activated.value()
+    }
+
+    /// Calls `f` with the target of the link that was clicked, if any.
+    #[must_use]
+    pub fn on_link_activated(self, f: impl FnOnce(&str)) -> Self {
+        if let Some(target) = self.link_activated() {
+            f(&target);
+        }
+        self
+    }
+
     /// Returns a reference to the formatted text paragraph.
     pub fn paragraph(&self) -> Ref<kyute_shell::text::Paragraph> {
-        Ref::map(self.cached_layout.get_cached(), |layout| &layout.paragraph)
+        let max_size = self.cached_layout.get_cached().max_size;
+        // speculative layouts may have changed the line breaks since the last layout
+        let resize = self
+            .shaped
+            .borrow()
+            .as_ref()
+            .map_or(false, |shaped| shaped.max_size != max_size);
+        if resize {
+            self.shaped.borrow_mut().as_mut().unwrap().set_max_size(max_size);
+        }
+        Ref::map(self.shaped.borrow(), |shaped| {
+            &shaped.as_ref().expect("layout not calculated").paragraph
+        })
+    }
+
+    /// Returns the paragraph style resolved from the environment.
+    fn paragraph_style(&self, env: &Environment) -> ParagraphStyle {
+        let font = self.font.resolve_or_default(env);
+        let font_size = env.get(&theme::FONT_SIZE).unwrap_or(16.0);
+        ParagraphStyle {
+            text_alignment: None,
+            font_style: Some(font.style),
+            font_weight: Some(font.weight),
+            font_size: Some(font_size),
+            font_family: Some(font.family.to_string()),
+            layout_mode: env.get(&theme::TEXT_LAYOUT_MODE),
+        }
+    }
+
+    /// Returns the shaped paragraph, shaping the text again if the style has changed.
+    fn shape(&self, style: ParagraphStyle) -> RefMut<ShapedParagraph> {
+        let mut shaped = self.shaped.borrow_mut();
+        if !matches!(*shaped, Some(ref shaped) if shaped.style.same(&style)) {
+            trace!("Text::shape {:?}", self.formatted_text.plain_text);
+            let max_size = Size::new(f64::INFINITY, f64::INFINITY);
+            *shaped = Some(ShapedParagraph {
+                paragraph: Paragraph::new(&self.formatted_text, max_size, &style),
+                style,
+                max_size,
+                intrinsic_widths: None,
+            });
+        }
+        RefMut::map(shaped, |shaped| shaped.as_mut().unwrap())
+    }
+
+    /// Returns the current selection.
+    ///
+    /// Returns `None` if the text is not selectable, or if the selection is not valid in the current text
+    /// (e.g. if the text has changed since the selection was made).
+    pub fn selection(&self) -> Option<Selection> {
+        let selection = self.selectable.as_ref()?.selection.get();
+        let text = &self.formatted_text.plain_text;
+        if text.is_char_boundary(selection.start) && text.is_char_boundary(selection.end) {
+            Some(selection)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the currently selected text.
+    pub fn selected_text(&self) -> Option<&str> {
+        let selection = self.selection()?;
+        Some(&self.formatted_text.plain_text[selection.min()..selection.max()])
+    }
+
+    fn set_selection(&self, ctx: &mut EventCtx, selection: Selection) {
+        if let Some(ref selectable) = self.selectable {
+            if selectable.selection.get() != selection {
+                // only affects painting, inside the text
+                selectable.selection.set_without_invalidation(selection);
+                ctx.request_repaint_rect(self.paragraph().metrics().bounds);
+            }
+        }
+    }
+
+    /// Returns the text position under the specified point.
+    fn text_position(&self, point: Point) -> usize {
+        self.paragraph().hit_test_point(point).idx
+    }
+
+    /// Returns the index of the link under the specified point.
+    ///
+    /// Unlike `text_position`, only points over the glyphs of a link are considered.
+    fn link_at(&self, point: Point) -> Option<usize> {
+        let links = self.links.as_ref()?;
+        let paragraph = self.paragraph();
+        links.ranges.iter().position(|(range, _)| {
+            paragraph
+                .hit_test_text_range(range.clone(), Point::origin())
+                .iter()
+                .any(|hit| hit.bounds.contains(point))
+        })
+    }
+
+    /// Returns the cursor to show at the specified point.
+    fn cursor_icon(&self, point: Point) -> CursorIcon {
+        if self.link_at(point).is_some() {
+            CursorIcon::Hand
+        } else if self.selectable.is_some() {
+            CursorIcon::Text
+        } else {
+            CursorIcon::Default
+        }
+    }
+
+    /// Activates the links clicked with the pointer. Returns whether the event was consumed.
+    fn link_pointer_event(&self, ctx: &mut EventCtx, p: &PointerEvent) -> bool {
+        let links = match self.links {
+            Some(ref links) => links,
+            None => return false,
+        };
+        match p.kind {
+            PointerEventKind::PointerDown if p.button == Some(PointerButton::LEFT) => {
+                let link = self.link_at(p.position);
+                if link.is_none() {
+                    return false;
+                }
+                links.pressed.set_without_invalidation(link);
+                ctx.capture_pointer();
+                ctx.set_handled();
+                true
+            }
+            PointerEventKind::PointerUp if links.pressed.get().is_some() => {
+                // activate the link if the pointer is released over it
+                let pressed = links.pressed.replace_without_invalidation(None);
+                if let Some(index) = pressed.filter(|&index| self.link_at(p.position) == Some(index)) {
+                    links.activated.signal(links.ranges[index].1.clone());
+                }
+                ctx.set_handled();
+                true
+            }
+            // don't select text while a link is pressed
+            PointerEventKind::PointerMove => links.pressed.get().is_some(),
+            _ => false,
+        }
+    }
+
+    /// Returns the position of a line boundary on the same visual line as `position`.
+    fn line_boundary(&self, position: usize, end: bool) -> usize {
+        let paragraph = self.paragraph();
+        let hit = paragraph.hit_test_text_position(TextPosition {
+            position,
+            affinity: TextAffinity::Downstream,
+        });
+        let y = hit.point.y + 0.5 * hit.metrics.bounds.size.height;
+        if !end {
+            return paragraph.hit_test_point(Point::new(-1.0e6, y)).idx;
+        }
+        let text = &self.formatted_text.plain_text;
+        let mut boundary = paragraph.hit_test_point(Point::new(1.0e6, y)).idx;
+        if boundary > position {
+            // stop before the line break
+            if text[..boundary].ends_with("\r\n") {
+                boundary -= 2;
+            } else if text[..boundary].ends_with('\n') {
+                boundary -= 1;
+            }
+        }
+        boundary
+    }
+
+    /// Returns the position on the previous or next line, at the same horizontal position.
+    fn adjacent_line(&self, position: usize, down: bool) -> usize {
+        let paragraph = self.paragraph();
+        let hit = paragraph.hit_test_text_position(TextPosition {
+            position,
+            affinity: TextAffinity::Downstream,
+        });
+        let height = hit.metrics.bounds.size.height;
+        let y = if down {
+            hit.point.y + 1.5 * height
+        } else {
+            hit.point.y - 0.5 * height
+        };
+        if y < 0.0 {
+            0
+        } else if y > paragraph.metrics().bounds.size.height {
+            self.formatted_text.plain_text.len()
+        } else {
+            paragraph.hit_test_point(Point::new(hit.point.x, y)).idx
+        }
+    }
+
+    /// Returns the destination of a movement of the end of the selection.
+    fn move_position(&self, position: usize, movement: Movement) -> Option<usize> {
+        let text = &self.formatted_text.plain_text;
+        let destination = match movement {
+            Movement::Left => prev_grapheme_cluster(text, position).unwrap_or(position),
+            Movement::Right => next_grapheme_cluster(text, position).unwrap_or(position),
+            Movement::LeftWord => prev_word_start(text, position),
+            Movement::RightWord => next_word_end(text, position),
+            Movement::LineStart => self.line_boundary(position, false),
+            Movement::LineEnd => self.line_boundary(position, true),
+            Movement::DocumentStart => 0,
+            Movement::DocumentEnd => text.len(),
+            Movement::LineUp => self.adjacent_line(position, false),
+            Movement::LineDown => self.adjacent_line(position, true),
+            Movement::PageUp | Movement::PageDown => return None,
+        };
+        Some(destination)
+    }
+
+    /// Returns the range of the word at the specified position.
+    fn word_at(&self, position: usize) -> Selection {
+        let text = &self.formatted_text.plain_text;
+        let next = next_grapheme_cluster(text, position).unwrap_or(position);
+        Selection {
+            start: prev_word_start(text, next),
+            end: next_word_end(text, position),
+        }
+    }
+
+    /// Handles a text selection action. Returns `false` if the action doesn't apply to static text.
+    fn perform_action(&self, ctx: &mut EventCtx, selection: Selection, action: TextEditAction) -> bool {
+        match action {
+            TextEditAction::Move(movement) => match self.move_position(selection.end, movement) {
+                Some(position) => self.set_selection(ctx, Selection::empty(position)),
+                None => return false,
+            },
+            TextEditAction::Select(movement) => match self.move_position(selection.end, movement) {
+                Some(position) => self.set_selection(
+                    ctx,
+                    Selection {
+                        start: selection.start,
+                        end: position,
+                    },
+                ),
+                None => return false,
+            },
+            TextEditAction::SelectAll => self.set_selection(
+                ctx,
+                Selection {
+                    start: 0,
+                    end: self.formatted_text.plain_text.len(),
+                },
+            ),
+            TextEditAction::Copy => match self.selected_text() {
+                Some(text) if !text.is_empty() => {
+                    if let Err(err) = kyute_shell::clipboard::set_text(text) {
+                        warn!("failed to copy text to the clipboard: {}", err);
+                    }
+                }
+                _ => return false,
+            },
+            TextEditAction::Delete(_)
+            | TextEditAction::Transpose
+            | TextEditAction::Validate
+            | TextEditAction::Paste
+            | TextEditAction::PasteSpecial => return false,
+        }
+        true
     }
 }
 
 impl Widget for Text {
     fn widget_id(&self) -> Option<WidgetId> {
-        // no need for a stable identity
-        None
+        // selectable text and links need an identity to receive the focus, hover events and capture the pointer,
+        // otherwise there's no need for a stable identity
+        self.selectable
+            .as_ref()
+            .map(|selectable| selectable.id)
+            .or_else(|| self.links.as_ref().map(|links| links.id))
     }
 
     fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
@@ -257,18 +727,11 @@ impl Widget for Text {
 
             let font = self.font.resolve_or_default(env);
             let color = self.color.resolve_or_default(env);
-            let font_size = env.get(&theme::FONT_SIZE).unwrap_or(16.0);
-
-            let paragraph_style = ParagraphStyle {
-                text_alignment: None,
-                font_style: Some(font.style),
-                font_weight: Some(font.weight),
-                font_size: Some(font_size),
-                font_family: Some(font.family.to_string()),
-            };
-            let paragraph = Paragraph::new(&self.formatted_text, constraints.max, &paragraph_style);
+            let mut shaped = self.shape(self.paragraph_style(env));
+            shaped.set_max_size(constraints.max);
 
             // measure the paragraph
+            let paragraph = &shaped.paragraph;
             let metrics = paragraph.metrics();
             let baseline = paragraph
                 .line_metrics()
@@ -278,7 +741,7 @@ impl Widget for Text {
             let size = constraints.constrain(metrics.bounds.size.round_to_pixel(ctx.scale_factor));
 
             TextLayoutResult {
-                paragraph,
+                max_size: constraints.max,
                 measurements: Measurements {
                     size,
                     // TODO clip bounds
@@ -301,19 +764,128 @@ impl Widget for Text {
         }
     }
 
-    fn event(&self, _ctx: &mut EventCtx, _event: &mut Event, _env: &Environment) {}
+    fn intrinsic_widths(&self, ctx: &mut LayoutCtx, env: &Environment) -> Option<IntrinsicWidths> {
+        let mut shaped = self.shape(self.paragraph_style(env));
+        if shaped.intrinsic_widths.is_none() {
+            shaped.set_max_size(Size::new(f64::INFINITY, f64::INFINITY));
+            shaped.intrinsic_widths = Some(IntrinsicWidths {
+                min: shaped.paragraph.min_width(),
+                max: shaped.paragraph.metrics().bounds.size.width,
+            });
+        }
+        let widths = shaped.intrinsic_widths.unwrap();
+        // same rounding as `layout`
+        let round = |width: f64| Size::new(width, 0.0).round_to_pixel(ctx.scale_factor).width;
+        Some(IntrinsicWidths {
+            min: round(widths.min),
+            max: round(widths.max),
+        })
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Event::Internal(InternalEvent::EnvironmentChanged) = event {
+            // the font and color are resolved during layout
+            self.cached_layout.invalidate();
+            return;
+        }
+
+        if self.selectable.is_none() && self.links.is_none() {
+            return;
+        }
+
+        if let Event::Pointer(p) = event {
+            match p.kind {
+                PointerEventKind::PointerOver => ctx.set_cursor_icon(self.cursor_icon(p.position)),
+                // the cursor changes over links
+                PointerEventKind::PointerMove if self.links.is_some() && !ctx.is_capturing_pointer() => {
+                    ctx.set_cursor_icon(self.cursor_icon(p.position))
+                }
+                PointerEventKind::PointerOut => ctx.set_cursor_icon(CursorIcon::Default),
+                _ => {}
+            }
+            if self.link_pointer_event(ctx, p) {
+                return;
+            }
+        }
+
+        if self.selectable.is_none() {
+            return;
+        }
+        let selection = self.selection().unwrap_or_default();
+
+        match event {
+            Event::FocusLost => {
+                if !selection.is_empty() {
+                    self.set_selection(ctx, Selection::empty(selection.end));
+                }
+            }
+            Event::Pointer(p) => match p.kind {
+                PointerEventKind::PointerDown if p.button == Some(PointerButton::LEFT) => {
+                    let position = self.text_position(p.position);
+                    let new_selection = match p.repeat_count {
+                        2 => self.word_at(position),
+                        3 => Selection {
+                            start: 0,
+                            end: self.formatted_text.plain_text.len(),
+                        },
+                        _ if p.modifiers.contains(Modifiers::SHIFT) => Selection {
+                            start: selection.start,
+                            end: position,
+                        },
+                        _ => Selection::empty(position),
+                    };
+                    self.set_selection(ctx, new_selection);
+                    ctx.request_focus();
+                    ctx.capture_pointer();
+                    ctx.set_handled();
+                }
+                PointerEventKind::PointerMove if ctx.is_capturing_pointer() => {
+                    let position = self.text_position(p.position);
+                    self.set_selection(
+                        ctx,
+                        Selection {
+                            start: selection.start,
+                            end: position,
+                        },
+                    );
+                    ctx.set_handled();
+                }
+                PointerEventKind::PointerUp if ctx.is_capturing_pointer() => {
+                    ctx.set_handled();
+                }
+                _ => {}
+            },
+            Event::Keyboard(k) if k.state == KeyState::Down => {
+                if let Some(action) = TextEditBindings::resolve_in_env(env, k) {
+                    if self.perform_action(ctx, selection, action) {
+                        ctx.set_handled();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
 
     fn paint(&self, ctx: &mut PaintCtx) {
         let _span = trace_span!("Text paint").entered();
         let mut renderer = Renderer { ctx, masks: vec![] };
+        let color = self.cached_layout.get_cached().color;
+        let paragraph = self.paragraph();
+        if let Some(selection) = self.selection() {
+            if !selection.is_empty() {
+                renderer.draw_selection(&paragraph, selection.min()..selection.max(), SELECTION_COLOR);
+            }
+        }
         // FIXME: should be a point in absolute coords?
-        let cached = self.cached_layout.get_cached();
-        cached
-            .paragraph
+        paragraph
             .draw(
                 Point::origin(),
                 &mut renderer,
-                &GlyphRunDrawingEffects { color: cached.color },
+                &GlyphRunDrawingEffects {
+                    color,
+                    shadow: self.shadow,
+                    outline: self.outline,
+                },
             )
             .expect("failed to draw paragraph");
     }
@@ -0,0 +1,421 @@
+//! Key bindings for text editing actions.
+use crate::{
+    event::{KeyboardEvent, Modifiers},
+    EnvKey, Environment,
+};
+use kyute_shell::{Shortcut, ShortcutKey};
+use lazy_static::lazy_static;
+use std::{collections::HashMap, sync::Arc};
+
+/// Key bindings used by text editors (`TextEdit`, `TextField`, ...).
+///
+/// If not set, the default bindings for the current platform are used (see `KeyBindingProfile::platform_default`).
+pub const TEXT_EDIT_BINDINGS: EnvKey<TextEditBindings> = builtin_env_key!("kyute.text-edit-bindings");
+
+/// A cursor movement.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Movement {
+    /// Previous grapheme cluster.
+    Left,
+    /// Next grapheme cluster.
+    Right,
+    /// Start of the previous word.
+    LeftWord,
+    /// End of the next word.
+    RightWord,
+    LineStart,
+    LineEnd,
+    DocumentStart,
+    DocumentEnd,
+    /// Previous line, at the same horizontal position (multi-line editors only).
+    LineUp,
+    /// Next line, at the same horizontal position (multi-line editors only).
+    LineDown,
+    /// One page up (multi-line editors only).
+    PageUp,
+    /// One page down (multi-line editors only).
+    PageDown,
+}
+
+/// An action performed by a text editor in response to a key press.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TextEditAction {
+    /// Moves the cursor, collapsing the selection.
+    Move(Movement),
+    /// Moves the end of the selection.
+    Select(Movement),
+    SelectAll,
+    /// Copies the selected text to the clipboard.
+    Copy,
+    /// Replaces the selection with the text on the clipboard.
+    Paste,
+    /// Shows a menu of transformations to apply to the clipboard contents before pasting them
+    /// (see `PasteTransforms`).
+    PasteSpecial,
+    /// Deletes the selected text, or if the selection is empty, the text between the cursor and the destination
+    /// of the movement.
+    Delete(Movement),
+    /// Swaps the characters on both sides of the cursor.
+    Transpose,
+    /// Finishes editing (signals `editing_finished`), or inserts a line break in multi-line editors.
+    Validate,
+}
+
+/// Sets of default key bindings.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum KeyBindingProfile {
+    /// Windows conventions (also used on Linux).
+    Windows,
+    /// macOS conventions: `Option` moves by words, `Command` to the start or end of lines,
+    /// and the basic emacs bindings of Cocoa text views.
+    MacOS,
+    /// Windows conventions with emacs bindings (`Ctrl+F`, `Alt+B`, `Ctrl+T`, ...).
+    Emacs,
+}
+
+impl KeyBindingProfile {
+    /// Returns the profile following the conventions of the current platform.
+    pub fn platform_default() -> KeyBindingProfile {
+        if cfg!(target_os = "macos") {
+            KeyBindingProfile::MacOS
+        } else {
+            KeyBindingProfile::Windows
+        }
+    }
+}
+
+/// Maps keyboard shortcuts to text editing actions.
+#[derive(Clone, Debug, Default)]
+pub struct TextEditBindings {
+    bindings: Arc<HashMap<Shortcut, TextEditAction>>,
+}
+
+impl_env_value!(TextEditBindings);
+
+impl TextEditBindings {
+    /// Creates an empty set of bindings.
+    pub fn new() -> TextEditBindings {
+        TextEditBindings::default()
+    }
+
+    /// Creates the bindings of the specified profile.
+    pub fn from_profile(profile: KeyBindingProfile) -> TextEditBindings {
+        use Movement::*;
+        use ShortcutKey::*;
+        use TextEditAction::*;
+
+        const NONE: Modifiers = Modifiers::empty();
+        const CTRL: Modifiers = Modifiers::CONTROL;
+        const ALT: Modifiers = Modifiers::ALT;
+        const META: Modifiers = Modifiers::META;
+        const SHIFT: Modifiers = Modifiers::SHIFT;
+
+        let mut b = TextEditBindings::new();
+        b.bind_movement(NONE, ArrowLeft, Left);
+        b.bind_movement(NONE, ArrowRight, Right);
+        b.bind_movement(NONE, ArrowUp, LineUp);
+        b.bind_movement(NONE, ArrowDown, LineDown);
+        b.bind_movement(NONE, ShortcutKey::PageUp, Movement::PageUp);
+        b.bind_movement(NONE, ShortcutKey::PageDown, Movement::PageDown);
+        b.bind(NONE, Backspace, TextEditAction::Delete(Left));
+        b.bind(NONE, ShortcutKey::Delete, TextEditAction::Delete(Right));
+        b.bind(NONE, Enter, Validate);
+
+        match profile {
+            KeyBindingProfile::Windows | KeyBindingProfile::Emacs => {
+                b.bind_movement(CTRL, ArrowLeft, LeftWord);
+                b.bind_movement(CTRL, ArrowRight, RightWord);
+                b.bind_movement(NONE, Home, LineStart);
+                b.bind_movement(NONE, End, LineEnd);
+                b.bind_movement(CTRL, Home, DocumentStart);
+                b.bind_movement(CTRL, End, DocumentEnd);
+                b.bind(CTRL, Backspace, TextEditAction::Delete(LeftWord));
+                b.bind(CTRL, ShortcutKey::Delete, TextEditAction::Delete(RightWord));
+                b.bind(CTRL, Character('A'), SelectAll);
+                b.bind(CTRL, Character('C'), Copy);
+                b.bind(CTRL, Insert, Copy);
+                b.bind(CTRL, Character('V'), Paste);
+                b.bind(SHIFT, Insert, Paste);
+                b.bind(CTRL | SHIFT, Character('V'), PasteSpecial);
+            }
+            KeyBindingProfile::MacOS => {
+                b.bind_movement(ALT, ArrowLeft, LeftWord);
+                b.bind_movement(ALT, ArrowRight, RightWord);
+                b.bind_movement(META, ArrowLeft, LineStart);
+                b.bind_movement(META, ArrowRight, LineEnd);
+                b.bind_movement(META, ArrowUp, DocumentStart);
+                b.bind_movement(META, ArrowDown, DocumentEnd);
+                b.bind_movement(NONE, Home, DocumentStart);
+                b.bind_movement(NONE, End, DocumentEnd);
+                b.bind(ALT, Backspace, TextEditAction::Delete(LeftWord));
+                b.bind(ALT, ShortcutKey::Delete, TextEditAction::Delete(RightWord));
+                b.bind(META, Backspace, TextEditAction::Delete(LineStart));
+                b.bind(META, Character('A'), SelectAll);
+                b.bind(META, Character('C'), Copy);
+                b.bind(META, Character('V'), Paste);
+                // "Paste and Match Style"
+                b.bind(META | ALT | SHIFT, Character('V'), PasteSpecial);
+                // emacs bindings supported by Cocoa text views
+                b.bind_movement(CTRL, Character('A'), LineStart);
+                b.bind_movement(CTRL, Character('E'), LineEnd);
+                b.bind_movement(CTRL, Character('B'), Left);
+                b.bind_movement(CTRL, Character('F'), Right);
+                b.bind_movement(CTRL, Character('P'), LineUp);
+                b.bind_movement(CTRL, Character('N'), LineDown);
+                b.bind(CTRL, Character('D'), TextEditAction::Delete(Right));
+                b.bind(CTRL, Character('H'), TextEditAction::Delete(Left));
+                b.bind(CTRL, Character('K'), TextEditAction::Delete(LineEnd));
+                b.bind(CTRL, Character('T'), Transpose);
+            }
+        }
+
+        if profile == KeyBindingProfile::Emacs {
+            // note: Ctrl+A moves to the start of the line instead of selecting all
+            b.bind_movement(CTRL, Character('A'), LineStart);
+            b.bind_movement(CTRL, Character('E'), LineEnd);
+            b.bind_movement(CTRL, Character('B'), Left);
+            b.bind_movement(CTRL, Character('F'), Right);
+            b.bind_movement(ALT, Character('B'), LeftWord);
+            b.bind_movement(ALT, Character('F'), RightWord);
+            b.bind_movement(CTRL, Character('P'), LineUp);
+            b.bind_movement(CTRL, Character('N'), LineDown);
+            b.bind(CTRL, Character('D'), TextEditAction::Delete(Right));
+            b.bind(CTRL, Character('H'), TextEditAction::Delete(Left));
+            b.bind(CTRL, Character('K'), TextEditAction::Delete(LineEnd));
+            b.bind(ALT, Character('D'), TextEditAction::Delete(RightWord));
+            b.bind(ALT, Backspace, TextEditAction::Delete(LeftWord));
+            b.bind(CTRL, Character('T'), Transpose);
+        }
+
+        b
+    }
+
+    /// Binds a shortcut to an action, replacing the previous binding if there was one.
+    pub fn bind(&mut self, modifiers: Modifiers, key: ShortcutKey, action: TextEditAction) {
+        Arc::make_mut(&mut self.bindings).insert(Shortcut::new(modifiers, key), action);
+    }
+
+    /// Binds a shortcut to a cursor movement, and the same shortcut with `Shift` to the corresponding selection.
+    pub fn bind_movement(&mut self, modifiers: Modifiers, key: ShortcutKey, movement: Movement) {
+        self.bind(modifiers, key, TextEditAction::Move(movement));
+        self.bind(modifiers | Modifiers::SHIFT, key, TextEditAction::Select(movement));
+    }
+
+    /// Removes the binding of a shortcut.
+    pub fn unbind(&mut self, shortcut: &Shortcut) {
+        Arc::make_mut(&mut self.bindings).remove(shortcut);
+    }
+
+    /// Returns the action bound to a shortcut.
+    pub fn action(&self, shortcut: &Shortcut) -> Option<TextEditAction> {
+        self.bindings.get(shortcut).cloned()
+    }
+
+    /// Returns the action bound to the key combination of a keyboard event.
+    pub fn resolve(&self, event: &KeyboardEvent) -> Option<TextEditAction> {
+        self.action(&Shortcut::from_key_event(event)?)
+    }
+}
+
+lazy_static! {
+    static ref PLATFORM_DEFAULT_BINDINGS: TextEditBindings =
+        TextEditBindings::from_profile(KeyBindingProfile::platform_default());
+}
+
+impl TextEditBindings {
+    /// Returns the action bound to the key combination of a keyboard event, using the bindings in the environment
+    /// if there are some, or the platform default bindings.
+    pub(crate) fn resolve_in_env(env: &Environment, event: &KeyboardEvent) -> Option<TextEditAction> {
+        match env.get(&TEXT_EDIT_BINDINGS) {
+            Some(bindings) => bindings.resolve(event),
+            None => PLATFORM_DEFAULT_BINDINGS.resolve(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Key;
+    use keyboard_types::KeyState;
+
+    fn key(key: Key, modifiers: Modifiers) -> KeyboardEvent {
+        KeyboardEvent {
+            key,
+            state: KeyState::Down,
+            modifiers,
+            ..Default::default()
+        }
+    }
+
+    fn chr(c: &str, modifiers: Modifiers) -> KeyboardEvent {
+        key(Key::Character(c.to_string()), modifiers)
+    }
+
+    const NONE: Modifiers = Modifiers::empty();
+    const CTRL: Modifiers = Modifiers::CONTROL;
+    const ALT: Modifiers = Modifiers::ALT;
+    const META: Modifiers = Modifiers::META;
+    const SHIFT: Modifiers = Modifiers::SHIFT;
+
+    #[test]
+    fn common_bindings() {
+        for &profile in &[
+            KeyBindingProfile::Windows,
+            KeyBindingProfile::MacOS,
+            KeyBindingProfile::Emacs,
+        ] {
+            let b = TextEditBindings::from_profile(profile);
+            assert_eq!(
+                b.resolve(&key(Key::ArrowLeft, NONE)),
+                Some(TextEditAction::Move(Movement::Left))
+            );
+            assert_eq!(
+                b.resolve(&key(Key::ArrowRight, SHIFT)),
+                Some(TextEditAction::Select(Movement::Right))
+            );
+            assert_eq!(
+                b.resolve(&key(Key::Backspace, NONE)),
+                Some(TextEditAction::Delete(Movement::Left))
+            );
+            assert_eq!(b.resolve(&key(Key::Enter, NONE)), Some(TextEditAction::Validate));
+            assert_eq!(b.resolve(&key(Key::F1, NONE)), None);
+        }
+    }
+
+    #[test]
+    fn windows_bindings() {
+        let b = TextEditBindings::from_profile(KeyBindingProfile::Windows);
+        assert_eq!(
+            b.resolve(&key(Key::ArrowLeft, CTRL)),
+            Some(TextEditAction::Move(Movement::LeftWord))
+        );
+        assert_eq!(
+            b.resolve(&key(Key::End, CTRL | SHIFT)),
+            Some(TextEditAction::Select(Movement::DocumentEnd))
+        );
+        assert_eq!(
+            b.resolve(&key(Key::Home, NONE)),
+            Some(TextEditAction::Move(Movement::LineStart))
+        );
+        assert_eq!(b.resolve(&chr("a", CTRL)), Some(TextEditAction::SelectAll));
+        assert_eq!(b.resolve(&chr("c", CTRL)), Some(TextEditAction::Copy));
+        assert_eq!(b.resolve(&key(Key::Insert, SHIFT)), Some(TextEditAction::Paste));
+        assert_eq!(b.resolve(&chr("V", CTRL | SHIFT)), Some(TextEditAction::PasteSpecial));
+        // no emacs bindings
+        assert_eq!(b.resolve(&chr("f", CTRL)), None);
+        assert_eq!(b.resolve(&chr("t", CTRL)), None);
+    }
+
+    #[test]
+    fn macos_bindings() {
+        let b = TextEditBindings::from_profile(KeyBindingProfile::MacOS);
+        assert_eq!(
+            b.resolve(&key(Key::ArrowLeft, ALT)),
+            Some(TextEditAction::Move(Movement::LeftWord))
+        );
+        assert_eq!(
+            b.resolve(&key(Key::ArrowRight, META | SHIFT)),
+            Some(TextEditAction::Select(Movement::LineEnd))
+        );
+        assert_eq!(
+            b.resolve(&key(Key::Home, NONE)),
+            Some(TextEditAction::Move(Movement::DocumentStart))
+        );
+        assert_eq!(
+            b.resolve(&key(Key::Backspace, META)),
+            Some(TextEditAction::Delete(Movement::LineStart))
+        );
+        assert_eq!(b.resolve(&chr("a", META)), Some(TextEditAction::SelectAll));
+        assert_eq!(
+            b.resolve(&chr("v", META | ALT | SHIFT)),
+            Some(TextEditAction::PasteSpecial)
+        );
+        assert_eq!(b.resolve(&chr("c", CTRL)), None);
+        // emacs bindings of Cocoa text views
+        assert_eq!(
+            b.resolve(&chr("a", CTRL)),
+            Some(TextEditAction::Move(Movement::LineStart))
+        );
+        assert_eq!(
+            b.resolve(&chr("e", CTRL | SHIFT)),
+            Some(TextEditAction::Select(Movement::LineEnd))
+        );
+        assert_eq!(
+            b.resolve(&chr("k", CTRL)),
+            Some(TextEditAction::Delete(Movement::LineEnd))
+        );
+        assert_eq!(b.resolve(&chr("t", CTRL)), Some(TextEditAction::Transpose));
+        // word movements with Alt+B/F are not part of the Cocoa bindings
+        assert_eq!(b.resolve(&chr("b", ALT)), None);
+    }
+
+    #[test]
+    fn emacs_bindings() {
+        let b = TextEditBindings::from_profile(KeyBindingProfile::Emacs);
+        // Windows bindings are kept
+        assert_eq!(
+            b.resolve(&key(Key::ArrowLeft, CTRL)),
+            Some(TextEditAction::Move(Movement::LeftWord))
+        );
+        assert_eq!(b.resolve(&chr("c", CTRL)), Some(TextEditAction::Copy));
+        // Ctrl+A replaces "select all"
+        assert_eq!(
+            b.resolve(&chr("a", CTRL)),
+            Some(TextEditAction::Move(Movement::LineStart))
+        );
+        assert_eq!(
+            b.resolve(&chr("e", CTRL)),
+            Some(TextEditAction::Move(Movement::LineEnd))
+        );
+        assert_eq!(b.resolve(&chr("b", CTRL)), Some(TextEditAction::Move(Movement::Left)));
+        assert_eq!(b.resolve(&chr("f", CTRL)), Some(TextEditAction::Move(Movement::Right)));
+        assert_eq!(
+            b.resolve(&chr("f", ALT | SHIFT)),
+            Some(TextEditAction::Select(Movement::RightWord))
+        );
+        assert_eq!(
+            b.resolve(&chr("b", ALT)),
+            Some(TextEditAction::Move(Movement::LeftWord))
+        );
+        assert_eq!(b.resolve(&chr("p", CTRL)), Some(TextEditAction::Move(Movement::LineUp)));
+        assert_eq!(
+            b.resolve(&chr("n", CTRL)),
+            Some(TextEditAction::Move(Movement::LineDown))
+        );
+        assert_eq!(
+            b.resolve(&chr("d", ALT)),
+            Some(TextEditAction::Delete(Movement::RightWord))
+        );
+        assert_eq!(
+            b.resolve(&key(Key::Backspace, ALT)),
+            Some(TextEditAction::Delete(Movement::LeftWord))
+        );
+        assert_eq!(b.resolve(&chr("t", CTRL)), Some(TextEditAction::Transpose));
+        // control characters produced by Ctrl+letter are mapped to the letter
+        assert_eq!(
+            b.resolve(&chr("\u{b}", CTRL)),
+            Some(TextEditAction::Delete(Movement::LineEnd))
+        );
+    }
+
+    #[test]
+    fn rebind() {
+        let mut b = TextEditBindings::from_profile(KeyBindingProfile::Windows);
+        let copy = b.clone();
+        b.bind(
+            CTRL,
+            ShortcutKey::Character('A'),
+            TextEditAction::Move(Movement::LineStart),
+        );
+        b.unbind(&Shortcut::new(CTRL, ShortcutKey::Character('C')));
+        assert_eq!(
+            b.resolve(&chr("a", CTRL)),
+            Some(TextEditAction::Move(Movement::LineStart))
+        );
+        assert_eq!(b.resolve(&chr("c", CTRL)), None);
+        // clones are not affected
+        assert_eq!(copy.resolve(&chr("a", CTRL)), Some(TextEditAction::SelectAll));
+        assert_eq!(copy.resolve(&chr("c", CTRL)), Some(TextEditAction::Copy));
+    }
+}
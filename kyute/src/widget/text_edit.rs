@@ -1,45 +1,154 @@
 //! Text editor widget.
 use crate::{
+    accessibility::{Role, Semantics},
     cache, composable,
     core::Widget,
     drawing::ToSkia,
     env::Environment,
-    event::{Event, Modifiers, PointerEventKind},
-    widget::{form, prelude::*, Form, StyledBox, Text},
+    event::{Event, PointerEventKind},
+    history::{self, UndoCommand, UndoStack},
+    widget::{
+        caret::Caret,
+        form,
+        paste_special::{PasteContents, PasteTransforms},
+        prelude::*,
+        text::SELECTION_COLOR,
+        text_bindings::{Movement, TextEditAction, TextEditBindings},
+        Form, StyledBox, Text,
+    },
     State,
 };
-use keyboard_types::KeyState;
+use keyboard_types::{CompositionState, KeyState};
 use kyute_common::Color;
 use kyute_shell::{
-    text::{FormattedText, Selection, TextAffinity, TextPosition},
+    clipboard,
+    text::{Attribute, FormattedText, Selection, TextAffinity, TextDecoration, TextPosition},
     winit::window::CursorIcon,
 };
+use parking_lot::{Mutex, MutexGuard};
 use std::{
     cell::Cell,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, Range},
     sync::Arc,
+    time::{Duration, Instant},
 };
 use tracing::trace;
-use unicode_segmentation::GraphemeCursor;
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
-pub enum Movement {
-    Left,
-    Right,
-    LeftWord,
-    RightWord,
-}
-
-fn prev_grapheme_cluster(text: &str, offset: usize) -> Option<usize> {
+pub(crate) fn prev_grapheme_cluster(text: &str, offset: usize) -> Option<usize> {
     let mut c = GraphemeCursor::new(offset, text.len(), true);
     c.prev_boundary(text, 0).unwrap()
 }
 
-fn next_grapheme_cluster(text: &str, offset: usize) -> Option<usize> {
+pub(crate) fn next_grapheme_cluster(text: &str, offset: usize) -> Option<usize> {
     let mut c = GraphemeCursor::new(offset, text.len(), true);
     c.next_boundary(text, 0).unwrap()
 }
 
+/// Returns the start of the word before the given offset, or the start of the text.
+pub(crate) fn prev_word_start(text: &str, offset: usize) -> usize {
+    text.split_word_bound_indices()
+        .filter(|(pos, word)| *pos < offset && word.chars().any(char::is_alphanumeric))
+        .map(|(pos, _)| pos)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Returns the end of the word after the given offset, or the end of the text.
+pub(crate) fn next_word_end(text: &str, offset: usize) -> usize {
+    text.split_word_bound_indices()
+        .find(|(pos, word)| pos + word.len() > offset && word.chars().any(char::is_alphanumeric))
+        .map(|(pos, word)| pos + word.len())
+        .unwrap_or(text.len())
+}
+
+/// ID of the first clipboard history entry in the paste special menu. Entries before it are paste transformations.
+const PASTE_MENU_HISTORY_BASE: usize = 1000;
+
+/// Maximum length of the clipboard history entries displayed in the paste special menu, in characters.
+const PASTE_MENU_HISTORY_LABEL_LEN: usize = 40;
+
+/// Returns the label of a clipboard history entry in the paste special menu: the first line, shortened.
+fn history_entry_label(text: &str) -> String {
+    let line = text.trim().lines().next().unwrap_or_default();
+    let mut label: String = line.chars().take(PASTE_MENU_HISTORY_LABEL_LEN).collect();
+    if label.len() < text.trim().len() {
+        label.push('…');
+    }
+    label
+}
+
+/// Maximum time between two edits of the same kind for them to be undone at once.
+const UNDO_MERGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Text and selection of an editor.
+#[derive(Clone, Debug, PartialEq)]
+struct TextState {
+    text: Arc<str>,
+    selection: Selection,
+}
+
+/// Kinds of edits recorded in the history of a text editor. Consecutive edits of the same kind are undone at once,
+/// except `Other`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum EditKind {
+    Typing,
+    Deletion,
+    Other,
+}
+
+/// An edit of the text of an editor.
+///
+/// Undoing or redoing the edit puts the text to restore in the context: the text is owned by the application, the
+/// editor only signals the change.
+struct TextChange {
+    kind: EditKind,
+    before: TextState,
+    after: TextState,
+    /// Time of the last merged edit.
+    time: Instant,
+}
+
+impl UndoCommand<Option<TextState>> for TextChange {
+    fn label(&self) -> String {
+        match self.kind {
+            EditKind::Typing => "Typing".to_string(),
+            EditKind::Deletion => "Delete".to_string(),
+            EditKind::Other => "Edit".to_string(),
+        }
+    }
+
+    fn undo(&mut self, restore: &mut Option<TextState>) {
+        *restore = Some(self.before.clone());
+    }
+
+    fn redo(&mut self, restore: &mut Option<TextState>) {
+        *restore = Some(self.after.clone());
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        // only edits continuing where the previous one left off: moving the caret starts a new edit
+        if self.kind == EditKind::Other
+            || next.kind != self.kind
+            || next.before != self.after
+            || next.time.saturating_duration_since(self.time) > UNDO_MERGE_INTERVAL
+        {
+            return false;
+        }
+        self.after = next.after.clone();
+        self.time = next.time;
+        true
+    }
+}
+
 /// Text editor widget.
+///
+/// "Paste special" (`TextEditAction::PasteSpecial`) shows a menu of transformations to apply to the clipboard
+/// contents before pasting them, and the recently copied texts. Applications can provide their own transformations
+/// with the `PASTE_TRANSFORMS` environment key.
+///
+/// The editor keeps a history of its edits: it handles the `history::UNDO` and `history::REDO` commands while it
+/// has something to undo or redo. The history is forgotten if the text is changed by the application.
 pub struct BaseTextEdit {
     id: WidgetId,
     /// Input formatted text.
@@ -53,6 +162,16 @@ pub struct BaseTextEdit {
     focused: bool,
     inner: WidgetPod<Text>,
     horizontal_offset: State<f64>,
+    /// Whether the text wraps and can contain line breaks.
+    multiline: bool,
+    /// Horizontal position that the caret tries to keep when moving up or down across lines.
+    preferred_x: State<Option<f64>>,
+    /// Text being composed with an input method (IME), not committed yet.
+    preedit: State<Option<String>>,
+    /// Range of the preedit text in the displayed text.
+    preedit_range: Option<Range<usize>>,
+    caret: Caret,
+    history: Arc<Mutex<UndoStack<Option<TextState>>>>,
 }
 
 /// Helper function that creates a new string with the text under `selection` replaced by the specified string.
@@ -80,7 +199,19 @@ impl BaseTextEdit {
         // clamp selection
         selection.start = selection.start.min(formatted_text.plain_text.len());
         selection.end = selection.end.min(formatted_text.plain_text.len());
-        let inner = WidgetPod::new(Text::new(formatted_text.clone()));
+
+        // show the text being composed in place of the selection, underlined
+        let preedit = cache::state(|| None);
+        let mut displayed_text = formatted_text.clone();
+        let mut preedit_range = None;
+        if let Some(preedit_text) = preedit.get().filter(|text: &String| !text.is_empty()) {
+            let start = selection.min();
+            let range = start..start + preedit_text.len();
+            displayed_text.replace_range(selection.min()..selection.max(), &preedit_text);
+            displayed_text.add_attribute(range.clone(), Attribute::Underline(TextDecoration::default()));
+            preedit_range = Some(range);
+        }
+        let inner = WidgetPod::new(Text::new(displayed_text));
 
         // handle focus changes
         let focused_changed = Signal::new();
@@ -99,9 +230,26 @@ impl BaseTextEdit {
             focused_changed,
             inner,
             horizontal_offset: cache::state(|| 0.0),
+            multiline: false,
+            preferred_x: cache::state(|| None),
+            preedit,
+            preedit_range,
+            caret: Caret::new(),
+            history: cache::once(|| Arc::new(Mutex::new(UndoStack::new()))),
         }
     }
 
+    /// Makes this editor multi-line.
+    ///
+    /// The text wraps at the available width, and the editor is as high as the text:
+    /// put it in a `ScrollArea` to edit long documents (the scroll area follows the caret).
+    /// `Enter` inserts a line break instead of finishing editing; editing finishes when the editor loses focus.
+    #[must_use]
+    pub fn multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
     /// Use if you don't care about the selection.
     #[composable]
     pub fn new(formatted_text: impl Into<FormattedText>) -> BaseTextEdit {
@@ -139,28 +287,313 @@ impl BaseTextEdit {
         self
     }
 
-    /// Moves the cursor forward or backward. Returns the new selection.
-    fn move_cursor(&self, movement: Movement, modify_selection: bool) -> Selection {
-        let offset =
-            match movement {
-                Movement::Left => prev_grapheme_cluster(&self.formatted_text.plain_text, self.selection.end)
-                    .unwrap_or(self.selection.end),
-                Movement::Right => next_grapheme_cluster(&self.formatted_text.plain_text, self.selection.end)
-                    .unwrap_or(self.selection.end),
-                Movement::LeftWord | Movement::RightWord => {
-                    // TODO word navigation (unicode word segmentation)
-                    warn!("word navigation is unimplemented");
-                    self.selection.end
-                }
-            };
+    /// Returns the bounds of the caret at the specified text position, in the coordinates of the text.
+    fn caret_bounds(&self, position: usize) -> Rect {
+        let paragraph = self.inner.inner().paragraph();
+        let hit = paragraph.hit_test_text_position(TextPosition {
+            position,
+            affinity: TextAffinity::Downstream,
+        });
+        Rect::new(hit.point, Size::new(1.0, hit.metrics.bounds.size.height))
+    }
+
+    /// Returns the start of the visual line containing the specified position.
+    fn line_start(&self, position: usize) -> usize {
+        let caret = self.caret_bounds(position);
+        let paragraph = self.inner.inner().paragraph();
+        paragraph.hit_test_point(Point::new(-1.0e6, caret.center().y)).idx
+    }
+
+    /// Returns the end of the visual line containing the specified position (before the line break, if any).
+    fn line_end(&self, position: usize) -> usize {
+        let text = &self.formatted_text.plain_text;
+        let caret = self.caret_bounds(position);
+        let paragraph = self.inner.inner().paragraph();
+        let mut end = paragraph.hit_test_point(Point::new(1.0e6, caret.center().y)).idx;
+        if end > position {
+            if text[..end].ends_with("\r\n") {
+                end -= 2;
+            } else if text[..end].ends_with('\n') {
+                end -= 1;
+            }
+        }
+        end
+    }
+
+    /// Returns the position reached by moving the caret vertically by the specified distance,
+    /// keeping the preferred horizontal position.
+    fn move_vertically(&self, position: usize, distance: f64) -> usize {
+        let text = &self.formatted_text.plain_text;
+        let caret = self.caret_bounds(position);
+        let x = self.preferred_x.get().unwrap_or(caret.origin.x);
+        self.preferred_x.set_without_invalidation(Some(x));
+
+        let paragraph = self.inner.inner().paragraph();
+        let y = caret.center().y + distance;
+        if y < 0.0 {
+            0
+        } else if y > paragraph.metrics().bounds.max_y() {
+            text.len()
+        } else {
+            paragraph.hit_test_point(Point::new(x, y)).idx
+        }
+    }
+
+    /// Moves the cursor. Returns the new selection, or `None` if the movement is not supported by this editor.
+    fn move_cursor(&self, ctx: &EventCtx, movement: Movement, modify_selection: bool) -> Option<Selection> {
+        let text = &self.formatted_text.plain_text;
+        let pos = self.selection.end;
+        let line_height = self.caret_bounds(pos).height();
+        // a page is the height of the window, minus one line so that the user keeps some context
+        let page_height = ctx
+            .window_size()
+            .map(|size| (size.height - line_height).max(line_height))
+            .unwrap_or(line_height);
+
+        let offset = match movement {
+            Movement::Left => prev_grapheme_cluster(text, pos).unwrap_or(pos),
+            Movement::Right => next_grapheme_cluster(text, pos).unwrap_or(pos),
+            Movement::LeftWord => prev_word_start(text, pos),
+            Movement::RightWord => next_word_end(text, pos),
+            Movement::LineStart if self.multiline => self.line_start(pos),
+            Movement::LineEnd if self.multiline => self.line_end(pos),
+            // single-line editor: lines are the whole text
+            Movement::LineStart | Movement::DocumentStart => 0,
+            Movement::LineEnd | Movement::DocumentEnd => text.len(),
+            Movement::LineUp | Movement::LineDown | Movement::PageUp | Movement::PageDown if !self.multiline => {
+                return None
+            }
+            Movement::LineUp => self.move_vertically(pos, -line_height),
+            Movement::LineDown => self.move_vertically(pos, line_height),
+            Movement::PageUp => self.move_vertically(pos, -page_height),
+            Movement::PageDown => self.move_vertically(pos, page_height),
+        };
 
         if modify_selection {
-            Selection {
+            Some(Selection {
                 start: self.selection.start,
                 end: offset,
+            })
+        } else {
+            Some(Selection::empty(offset))
+        }
+    }
+
+    /// Swaps the grapheme clusters before and after the cursor (or the last two if the cursor is at the end of the
+    /// text). Returns the new text and selection.
+    fn transpose(&self) -> Option<(Arc<str>, Selection)> {
+        let text = &self.formatted_text.plain_text;
+        if !self.selection.is_empty() {
+            return None;
+        }
+        let mut pos = self.selection.end;
+        if pos == text.len() {
+            pos = prev_grapheme_cluster(text, pos)?;
+        }
+        let start = prev_grapheme_cluster(text, pos)?;
+        let end = next_grapheme_cluster(text, pos)?;
+        let transposed = format!("{}{}", &text[pos..end], &text[start..pos]);
+        let (new_text, _) = edit_text(text, Selection { start, end }, &transposed);
+        Some((new_text, Selection::empty(end)))
+    }
+
+    /// Shows the paste special menu below the caret: the paste transformations, then the clipboard history.
+    fn show_paste_special_menu(&self, ctx: &mut EventCtx, env: &Environment) {
+        let mut menu = kyute_shell::Menu::new_popup();
+        let contents = PasteContents::from_clipboard();
+        for (i, transform) in PasteTransforms::resolve_in_env(env).iter().enumerate() {
+            let disabled = transform.apply(&contents).is_none();
+            menu.add_item(transform.name(), i, None, false, disabled);
+        }
+        let history = clipboard::history();
+        if !history.is_empty() {
+            let mut history_menu = kyute_shell::Menu::new_popup();
+            for (i, text) in history.iter().enumerate() {
+                history_menu.add_item(
+                    &history_entry_label(text),
+                    PASTE_MENU_HISTORY_BASE + i,
+                    None,
+                    false,
+                    false,
+                );
+            }
+            menu.add_separator();
+            menu.add_submenu("Recently copied", history_menu);
+        }
+
+        let caret = self
+            .caret_bounds(self.selection.end)
+            .translate(Offset::new(self.horizontal_offset.get(), 0.0));
+        let at = ctx
+            .window_transform()
+            .transform_point(Point::new(caret.min_x(), caret.max_y()));
+        ctx.track_popup_menu(menu, at);
+    }
+
+    /// Handles the selection of an item of the paste special menu.
+    fn paste_special(&self, ctx: &mut EventCtx, env: &Environment, item: usize) {
+        let text = if item >= PASTE_MENU_HISTORY_BASE {
+            clipboard::history().into_iter().nth(item - PASTE_MENU_HISTORY_BASE)
+        } else {
+            PasteTransforms::resolve_in_env(env)
+                .get(item)
+                .and_then(|transform| transform.apply(&PasteContents::from_clipboard()))
+        };
+        if let Some(text) = text {
+            self.paste(ctx, &text);
+        }
+    }
+
+    /// Replaces the selection with pasted text. Line breaks are replaced by spaces in single-line editors.
+    fn paste(&self, ctx: &mut EventCtx, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if self.multiline {
+            self.insert(ctx, text, EditKind::Other);
+        } else {
+            self.insert(ctx, &text.lines().collect::<Vec<_>>().join(" "), EditKind::Other);
+        }
+    }
+
+    /// Performs a text editing action. Returns `false` if the action is not supported by this editor.
+    fn perform_action(&self, ctx: &mut EventCtx, env: &Environment, action: TextEditAction) -> bool {
+        // the preferred horizontal position is kept only across consecutive vertical movements
+        match action {
+            TextEditAction::Move(Movement::LineUp | Movement::LineDown | Movement::PageUp | Movement::PageDown)
+            | TextEditAction::Select(Movement::LineUp | Movement::LineDown | Movement::PageUp | Movement::PageDown) => {
             }
+            _ => self.preferred_x.set_without_invalidation(None),
+        }
+
+        match action {
+            TextEditAction::Move(movement) | TextEditAction::Select(movement) => {
+                let modify_selection = matches!(action, TextEditAction::Select(_));
+                if let Some(selection) = self.move_cursor(ctx, movement, modify_selection) {
+                    self.notify_selection_changed(ctx, selection);
+                } else {
+                    return false;
+                }
+            }
+            TextEditAction::SelectAll => {
+                self.notify_selection_changed(
+                    ctx,
+                    Selection {
+                        start: 0,
+                        end: self.formatted_text.plain_text.len(),
+                    },
+                );
+            }
+            TextEditAction::Copy => {
+                if self.selection.is_empty() {
+                    return false;
+                }
+                let text = &self.formatted_text.plain_text[self.selection.min()..self.selection.max()];
+                if let Err(err) = kyute_shell::clipboard::set_text(text) {
+                    warn!("failed to copy text to the clipboard: {}", err);
+                }
+            }
+            // rich text is pasted as its text content
+            TextEditAction::Paste => match PasteContents::from_clipboard().plain_text() {
+                Some(text) => self.paste(ctx, &text),
+                None => return false,
+            },
+            TextEditAction::PasteSpecial => {
+                self.show_paste_special_menu(ctx, env);
+            }
+            TextEditAction::Delete(movement) => {
+                trace!("text edit: delete {:?}", movement);
+                let selection = if self.selection.is_empty() {
+                    match self.move_cursor(ctx, movement, true) {
+                        Some(selection) => selection,
+                        None => return false,
+                    }
+                } else {
+                    self.selection
+                };
+                let (new_text, new_selection) = edit_text(&self.formatted_text.plain_text, selection, "");
+                self.edit(ctx, new_text, new_selection, EditKind::Deletion);
+            }
+            TextEditAction::Transpose => {
+                if let Some((new_text, new_selection)) = self.transpose() {
+                    self.edit(ctx, new_text, new_selection, EditKind::Other);
+                }
+            }
+            TextEditAction::Validate if self.multiline => {
+                self.insert(ctx, "\n", EditKind::Other);
+            }
+            TextEditAction::Validate => {
+                self.notify_editing_finished(ctx, self.formatted_text.plain_text.clone());
+            }
+        }
+        true
+    }
+
+    /// Replaces the selection with the specified text.
+    fn insert(&self, ctx: &mut EventCtx, text: &str, kind: EditKind) {
+        let (new_text, new_selection) = edit_text(&self.formatted_text.plain_text, self.selection, text);
+        trace!("insert {:?}; text after = {}", text, new_text);
+        self.edit(ctx, new_text, new_selection, kind);
+    }
+
+    /// Returns the history of the edits.
+    ///
+    /// The history is forgotten if the text was changed by the application since the last edit.
+    fn history(&self) -> MutexGuard<UndoStack<Option<TextState>>> {
+        let mut history = self.history.lock();
+        if history
+            .last::<TextChange>()
+            .map_or(false, |last| last.after.text != self.formatted_text.plain_text)
+        {
+            history.clear();
+        }
+        history
+    }
+
+    /// Replaces the text, and records the change in the history.
+    fn edit(&self, ctx: &mut EventCtx, new_text: Arc<str>, new_selection: Selection, kind: EditKind) {
+        if new_text == self.formatted_text.plain_text {
+            self.notify_selection_changed(ctx, new_selection);
+            return;
+        }
+        let mut history = self.history();
+        history.push(TextChange {
+            kind,
+            before: TextState {
+                text: self.formatted_text.plain_text.clone(),
+                selection: self.selection,
+            },
+            after: TextState {
+                text: new_text.clone(),
+                selection: new_selection,
+            },
+            time: Instant::now(),
+        });
+        drop(history);
+        self.notify_text_changed(ctx, new_text);
+        self.notify_selection_changed(ctx, new_selection);
+        ctx.request_relayout();
+    }
+
+    /// Undoes or redoes the last edit. Returns `false` if there is nothing to undo or redo.
+    fn undo_redo(&self, ctx: &mut EventCtx, redo: bool) -> bool {
+        let mut history = self.history();
+        let mut restore = None;
+        if redo {
+            history.redo_with(&mut restore);
         } else {
-            Selection::empty(offset)
+            history.undo_with(&mut restore);
+        }
+        drop(history);
+        match restore {
+            Some(state) => {
+                self.preferred_x.set_without_invalidation(None);
+                self.notify_text_changed(ctx, state.text);
+                self.notify_selection_changed(ctx, state.selection);
+                ctx.request_relayout();
+                true
+            }
+            None => false,
         }
     }
 
@@ -208,13 +641,36 @@ impl BaseTextEdit {
         }
     }
 
-    fn notify_selection_changed(&self, _ctx: &mut EventCtx, new_selection: Selection) {
+    fn notify_selection_changed(&self, ctx: &mut EventCtx, new_selection: Selection) {
         if new_selection != self.selection {
             eprintln!("notify selection changed {:?}->{:?}", self.selection, new_selection);
             self.selection_changed.signal(new_selection);
+            if self.multiline {
+                // follow the caret in the enclosing scroll area, using the current layout;
+                // if the text was just edited, the new position may not be valid in the current text:
+                // use the start of the edited range instead
+                let position = if self.formatted_text.plain_text.is_char_boundary(new_selection.end) {
+                    new_selection.end
+                } else {
+                    self.selection.min()
+                };
+                ctx.scroll_into_view(self.caret_bounds(position));
+            }
         }
     }
 
+    /// Tells the OS where the caret is, so that the IME candidate window appears next to it.
+    fn update_ime_cursor_area(&self, ctx: &mut EventCtx) {
+        let position = match self.preedit_range {
+            Some(ref range) => range.start,
+            None => self.selection.end,
+        };
+        let caret = self
+            .caret_bounds(position)
+            .translate(Offset::new(self.horizontal_offset.get(), 0.0));
+        ctx.set_ime_cursor_area(caret);
+    }
+
     fn notify_text_changed(&self, _ctx: &mut EventCtx, new_text: Arc<str>) {
         self.text_changed.signal(new_text);
     }
@@ -230,10 +686,15 @@ impl Widget for BaseTextEdit {
     }
 
     fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
-        // relax text constraints
+        // relax text constraints (multi-line editors wrap the text at the available width)
+        let max_text_width = if self.multiline {
+            constraints.max.width
+        } else {
+            f64::INFINITY
+        };
         let text_constraints = LayoutParams {
             min: Size::zero(),
-            max: Size::new(f64::INFINITY, f64::INFINITY),
+            max: Size::new(max_text_width, f64::INFINITY),
             ..*constraints
         };
         let child_layout = self.inner.layout(ctx, &text_constraints, env);
@@ -241,11 +702,19 @@ impl Widget for BaseTextEdit {
         let width = constraints
             .finite_max_width()
             .unwrap_or(child_layout.measurements.width());
-        let height = constraints
-            .finite_max_height()
-            .unwrap_or(child_layout.measurements.height());
+        let height = if self.multiline {
+            // as high as the text, so that it can be put in a scroll area
+            child_layout.measurements.height().max(constraints.min.height)
+        } else {
+            constraints
+                .finite_max_height()
+                .unwrap_or(child_layout.measurements.height())
+        };
 
-        if !ctx.speculative {
+        if self.multiline {
+            self.inner.set_offset(Offset::zero());
+            self.horizontal_offset.set_without_invalidation(0.0);
+        } else if !ctx.speculative {
             // update the horizontal offset if the cursor position
             // overflows the available space
             let mut h_offset = self.horizontal_offset.get();
@@ -282,11 +751,13 @@ impl Widget for BaseTextEdit {
         }
     }
 
-    fn event(&self, ctx: &mut EventCtx, event: &mut Event, _env: &Environment) {
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
         match event {
             Event::FocusGained => {
                 trace!("text edit: focus gained");
                 self.focused_changed.signal(true);
+                ctx.set_ime_allowed(true);
+                self.update_ime_cursor_area(ctx);
             }
             Event::FocusLost => {
                 trace!("text edit: focus lost");
@@ -296,6 +767,11 @@ impl Widget for BaseTextEdit {
                 }
                 self.notify_editing_finished(ctx, self.formatted_text.plain_text.clone());
                 self.focused_changed.signal(false);
+                // abandon the current composition, if any
+                if self.preedit_range.is_some() {
+                    self.preedit.set(None);
+                }
+                ctx.set_ime_allowed(false);
             }
             Event::Pointer(p) => {
                 match p.kind {
@@ -346,69 +822,67 @@ impl Widget for BaseTextEdit {
                 }
             }
             Event::Keyboard(k) => match k.state {
-                KeyState::Down => match k.key {
-                    keyboard_types::Key::Backspace => {
-                        trace!("text edit: backspace");
-                        let selection = if self.selection.is_empty() {
-                            self.move_cursor(Movement::Left, true)
-                        } else {
-                            self.selection
-                        };
-                        let (new_text, new_selection) = edit_text(&self.formatted_text.plain_text, selection, "");
-                        self.notify_text_changed(ctx, new_text);
-                        self.notify_selection_changed(ctx, new_selection);
-                        ctx.request_relayout();
-                        ctx.set_handled();
-                    }
-                    keyboard_types::Key::Delete => {
-                        trace!("text edit: delete");
-                        let selection = if self.selection.is_empty() {
-                            self.move_cursor(Movement::Right, true)
-                        } else {
-                            self.selection
-                        };
-                        let (new_text, new_selection) = edit_text(&self.formatted_text.plain_text, selection, "");
-                        self.notify_text_changed(ctx, new_text);
-                        self.notify_selection_changed(ctx, new_selection);
-                        ctx.request_relayout();
-                        ctx.set_handled();
-                    }
-                    keyboard_types::Key::ArrowLeft => {
-                        let selection = self.move_cursor(Movement::Left, k.modifiers.contains(Modifiers::SHIFT));
-                        self.notify_selection_changed(ctx, selection);
-                        ctx.set_handled();
-                    }
-                    keyboard_types::Key::ArrowRight => {
-                        let selection = self.move_cursor(Movement::Right, k.modifiers.contains(Modifiers::SHIFT));
-                        self.notify_selection_changed(ctx, selection);
-                        ctx.set_handled();
-                    }
-                    keyboard_types::Key::Character(ref c) => {
-                        // reject control characters (handle in KeyDown instead)
-                        let (new_text, new_selection) = edit_text(&self.formatted_text.plain_text, self.selection, c);
-                        trace!("insert {:?}; text after = {}", c, new_text);
-                        self.notify_text_changed(ctx, new_text);
-                        self.notify_selection_changed(ctx, new_selection);
-                        ctx.request_relayout();
-                        ctx.set_handled();
-                    }
-                    keyboard_types::Key::Enter => {
-                        // enter validates
-                        self.notify_editing_finished(ctx, self.formatted_text.plain_text.clone());
+                KeyState::Down => {
+                    if let Some(action) = TextEditBindings::resolve_in_env(env, k) {
+                        if self.perform_action(ctx, env, action) {
+                            ctx.set_handled();
+                        }
+                    } else if let keyboard_types::Key::Character(ref c) = k.key {
+                        // reject control characters (produced by unbound shortcuts)
+                        if c.chars().any(char::is_control) {
+                            return;
+                        }
+                        self.insert(ctx, c, EditKind::Typing);
                         ctx.set_handled();
                     }
-                    _ => {}
-                },
+                }
                 KeyState::Up => {
                     ctx.set_handled();
                 }
             },
 
-            Event::Composition(_) => {}
+            Event::MenuCommand(item) => {
+                self.paste_special(ctx, env, *item);
+                ctx.set_handled();
+            }
+            Event::Command(id) if *id == history::UNDO || *id == history::REDO => {
+                // with nothing to undo in the editor, the command goes on to the application
+                if self.undo_redo(ctx, *id == history::REDO) {
+                    ctx.set_handled();
+                }
+            }
+            Event::Composition(c) => {
+                match c.state {
+                    CompositionState::Start | CompositionState::Update => {
+                        if c.state == CompositionState::Start {
+                            // the composition starts at the caret
+                            self.update_ime_cursor_area(ctx);
+                        }
+                        self.preedit.set(Some(c.data.clone()));
+                        ctx.request_relayout();
+                    }
+                    CompositionState::End => {
+                        self.preedit.set(None);
+                        if !c.data.is_empty() {
+                            self.insert(ctx, &c.data, EditKind::Typing);
+                        }
+                    }
+                }
+                ctx.set_handled();
+            }
             _ => {}
         }
     }
 
+    fn semantics(&self) -> Option<Semantics> {
+        let role = if self.multiline {
+            Role::MultilineTextInput
+        } else {
+            Role::TextInput
+        };
+        Some(Semantics::new(role).value(&*self.formatted_text.plain_text))
+    }
+
     fn paint(&self, ctx: &mut PaintCtx) {
         use skia_safe as sk;
 
@@ -419,12 +893,15 @@ impl Widget for BaseTextEdit {
 
         // paint the selection over it
         let paragraph = self.inner.inner().paragraph();
-        let selection_boxes =
-            paragraph.hit_test_text_range(self.selection.min()..self.selection.max(), Point::origin());
+        // during composition, the selection is replaced by the preedit text, and the caret is placed after it
+        let (selection_range, caret_position) = match self.preedit_range {
+            Some(ref range) => (range.end..range.end, range.end),
+            None => (self.selection.min()..self.selection.max(), self.selection.end),
+        };
+        let selection_boxes = paragraph.hit_test_text_range(selection_range, Point::origin());
 
         {
-            // TODO color from environment or theme
-            let mut paint = sk::Paint::new(Color::new(0.0, 0.8, 0.8, 0.5).to_skia(), None);
+            let mut paint = sk::Paint::new(SELECTION_COLOR.to_skia(), None);
             for mut sb in selection_boxes {
                 let canvas = ctx.surface.canvas();
                 let offset_sb_bounds = sb.bounds.translate(Offset::new(h_offset, 0.0));
@@ -436,20 +913,22 @@ impl Widget for BaseTextEdit {
         // paint the caret
         if self.focused {
             let caret_hit_test = paragraph.hit_test_text_position(TextPosition {
-                position: self.selection.end,
+                position: caret_position,
                 affinity: TextAffinity::Downstream,
             });
 
             // TODO color from environment or theme
             let caret_color = Color::new(1.0, 1.0, 1.0, 1.0);
-            let paint = sk::Paint::new(caret_color.to_skia(), None);
             let mut pos = caret_hit_test.point;
             pos.x += h_offset;
-            let canvas = ctx.surface.canvas();
-            canvas.draw_rect(
-                Rect::new(pos.floor(), Size::new(1.0, caret_hit_test.metrics.bounds.size.height)).to_skia(),
-                &paint,
+            // on its own layer, so that blinking doesn't repaint the editor
+            self.caret.paint(
+                ctx,
+                Rect::new(pos.floor(), Size::new(1.0, caret_hit_test.metrics.bounds.size.height)),
+                caret_color,
             );
+        } else {
+            self.caret.hide();
         }
     }
 }
@@ -486,6 +965,13 @@ impl TextEdit {
         Self::with_selection(formatted_text, selection).on_selection_changed(|s| selection = s)
     }
 
+    /// Makes this editor multi-line (see `BaseTextEdit::multiline`).
+    #[must_use]
+    pub fn multiline(mut self, multiline: bool) -> Self {
+        self.inner.inner_mut().multiline = multiline;
+        self
+    }
+
     /// Returns whether TODO.
     pub fn editing_finished(&self) -> Option<Arc<str>> {
         self.inner.inner().editing_finished.value()
@@ -0,0 +1,127 @@
+//! Theme switching at runtime.
+use crate::{
+    animation::{Animated, Transition},
+    cache,
+    cache::Restartable,
+    theme::ThemeTokens,
+    widget::prelude::*,
+    InternalEvent, State,
+};
+use std::{sync::Arc, time::Duration};
+
+#[derive(Clone)]
+struct Selection {
+    tokens: ThemeTokens,
+    transition: Option<Transition>,
+}
+
+/// Handle to switch the theme of a `ThemeProvider`.
+#[derive(Clone)]
+pub struct ThemeHandle {
+    selection: State<Selection>,
+}
+
+impl ThemeHandle {
+    /// Returns the selected theme.
+    ///
+    /// During a cross-fade, this is the theme at the end of the transition. Calling this during composition makes
+    /// the calling scope depend on the selected theme.
+    pub fn get(&self) -> ThemeTokens {
+        self.selection.get().tokens
+    }
+
+    /// Switches to the specified theme immediately.
+    pub fn set(&self, tokens: ThemeTokens) {
+        self.selection.set(Selection {
+            tokens,
+            transition: None,
+        })
+    }
+
+    /// Cross-fades to the specified theme.
+    ///
+    /// If a cross-fade is already running, the new one starts from the current state of the theme.
+    pub fn cross_fade(&self, tokens: ThemeTokens, transition: Transition) {
+        self.selection.set(Selection {
+            tokens,
+            transition: Some(transition),
+        })
+    }
+}
+
+/// Applies a theme (see `ThemeTokens`) to its contents, and switches it at runtime.
+///
+/// The theme is passed to the contents in the environment during layout, like `WidgetExt::theme`. When it is
+/// switched with the `ThemeHandle`, the contents are not recomposed: they are laid out and painted again with the
+/// new theme. During a cross-fade, only the theme provider is recomposed at each frame.
+///
+/// Widgets should resolve theme values with `EnvRef`s or from the environment passed to `layout`. Values read
+/// during composition (with `cache::environment`) are not affected by the theme provider.
+pub struct ThemeProvider {
+    id: WidgetId,
+    content: Arc<WidgetPod>,
+    env: Restartable<Environment>,
+}
+
+impl ThemeProvider {
+    /// Creates a theme provider with the specified initial theme.
+    ///
+    /// `content` receives the handle used to switch the theme.
+    #[composable]
+    pub fn new<W: Widget + 'static>(initial: ThemeTokens, content: impl FnOnce(&ThemeHandle) -> W) -> ThemeProvider {
+        let handle = ThemeHandle {
+            selection: cache::state(|| Selection {
+                tokens: initial,
+                transition: None,
+            }),
+        };
+
+        // Only this scope depends on the selected theme: switching it doesn't recompose the contents.
+        let env = {
+            let selection = handle.selection.clone();
+            cache::restartable((), move |_| {
+                let Selection { tokens, transition } = selection.get();
+                // switching without a transition also interrupts a running cross-fade
+                let transition = transition.unwrap_or_else(|| Transition::new(Duration::ZERO));
+                Animated::new(tokens, transition).value().to_environment()
+            })
+        };
+
+        ThemeProvider {
+            id: WidgetId::from_call_id(env.call_id()),
+            content: content(&handle).arc_dyn_pod(),
+            env,
+        }
+    }
+}
+
+impl Widget for ThemeProvider {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let env = env.merged(self.env.get());
+        self.content.layout(ctx, constraints, &env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        let env = env.merged(self.env.get());
+        match event {
+            // the theme has changed
+            Event::Internal(InternalEvent::SubtreeRecomposed) => {
+                self.content
+                    .route_event(ctx, &mut Event::Internal(InternalEvent::EnvironmentChanged), &env)
+            }
+            _ => self.content.route_event(ctx, event, &env),
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx)
+    }
+
+    fn debug_node(&self) -> DebugNode {
+        DebugNode::new("theme provider")
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{
     cache,
     event::{PointerButton, PointerButtons, PointerEventKind},
+    prediction::{prediction_time, PointerPredictor},
     widget::prelude::*,
     Signal, State,
 };
+use parking_lot::Mutex;
+use std::sync::Arc;
 
 /// Widget that provides feedback on the pointer position and status when the pointer is over it.
 #[derive(Clone)]
@@ -11,6 +14,9 @@ pub struct Thumb<Content> {
     id: WidgetId,
     content: Content,
     pointer_buttons: PointerButtons,
+    /// Whether `pointer_moved` reports predicted positions (see `predict_pointer`).
+    predict_pointer: bool,
+    predictor: Arc<Mutex<PointerPredictor>>,
     pointer_down: Signal<(Point, Transform)>,
     pointer_move: Signal<Point>,
     pointer_up: Signal<Point>,
@@ -25,6 +31,8 @@ impl<Content: Widget + 'static> Thumb<Content> {
             id: WidgetId::here(),
             content,
             pointer_buttons: PointerButtons::ALL,
+            predict_pointer: false,
+            predictor: cache::once(|| Arc::new(Mutex::new(PointerPredictor::new()))),
             pointer_down: Signal::new(),
             pointer_move: Signal::new(),
             pointer_up: Signal::new(),
@@ -37,6 +45,16 @@ impl<Content: Widget + 'static> Thumb<Content> {
         self
     }
 
+    /// Makes `pointer_moved` report the position where the pointer is predicted to be when the frame showing the
+    /// effects of the move is displayed, instead of its last known position (see `prediction`).
+    ///
+    /// This reduces the perceived lag of drawing and dragging with a fast moving pen or mouse, at the cost of
+    /// overshooting slightly when the pointer stops. The position reported by `pointer_up` is the real one.
+    pub fn predict_pointer(mut self, predict_pointer: bool) -> Self {
+        self.predict_pointer = predict_pointer;
+        self
+    }
+
     pub fn pointer_down(&self) -> Option<(Point, Transform)> {
         self.pointer_down.value()
     }
@@ -101,13 +119,23 @@ impl<Content: Widget + 'static> Widget for Thumb<Content> {
                 Event::Pointer(p) => match p.kind {
                     PointerEventKind::PointerDown => {
                         if self.pointer_buttons.test(p.button.unwrap()) {
+                            let mut predictor = self.predictor.lock();
+                            predictor.reset();
+                            predictor.add_sample(p.timestamp, p.window_position);
                             self.pointer_down.signal((p.window_position, *ctx.window_transform()));
                             ctx.capture_pointer();
                             ctx.set_handled();
                         }
                     }
                     PointerEventKind::PointerMove => {
-                        self.pointer_move.signal(p.window_position);
+                        let mut predictor = self.predictor.lock();
+                        predictor.add_sample(p.timestamp, p.window_position);
+                        let position = if self.predict_pointer {
+                            predictor.predict(prediction_time(ctx)).unwrap()
+                        } else {
+                            p.window_position
+                        };
+                        self.pointer_move.signal(position);
                         ctx.set_handled();
                     }
                     PointerEventKind::PointerUp => {
@@ -182,6 +210,13 @@ impl<T: Clone + 'static, Content: Widget + 'static> DragController<T, Content> {
         }
     }
 
+    /// Reports deltas to the predicted position of the pointer instead of its last known position (see
+    /// `Thumb::predict_pointer`). The delta is corrected by each new pointer event.
+    pub fn predict_pointer(mut self, predict_pointer: bool) -> Self {
+        self.content = self.content.predict_pointer(predict_pointer);
+        self
+    }
+
     pub fn started(&self) -> bool {
         self.started
     }
@@ -0,0 +1,366 @@
+//! Toolbars.
+use crate::{
+    drawing::{Paint, PaintCtxExt, Shape},
+    event::{PointerButton, PointerEventKind},
+    theme,
+    widget::{prelude::*, Action, Button, Menu, MenuItem, Text},
+};
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Reverse,
+    sync::Arc,
+};
+
+/// Horizontal space between items.
+const ITEM_SPACING: f64 = 2.0;
+/// Width taken by a separator, including margins.
+const SEPARATOR_WIDTH: f64 = 9.0;
+/// Vertical margin above and below separator lines.
+const SEPARATOR_MARGIN: f64 = 4.0;
+
+enum ToolbarItemKind {
+    Widget {
+        widget: Arc<WidgetPod>,
+        menu_item: Option<MenuItem>,
+        priority: i32,
+    },
+    Separator,
+}
+
+/// An item in a `Toolbar`.
+pub struct ToolbarItem {
+    kind: ToolbarItemKind,
+}
+
+impl ToolbarItem {
+    /// Creates an item that shows an arbitrary widget.
+    ///
+    /// When the item doesn't fit in the toolbar, `menu_item` is shown in the overflow menu instead.
+    /// If `menu_item` is `None`, the item is just hidden.
+    #[composable]
+    pub fn new(widget: impl Widget + 'static, menu_item: Option<MenuItem>) -> ToolbarItem {
+        ToolbarItem {
+            kind: ToolbarItemKind::Widget {
+                widget: widget.arc_dyn_pod(),
+                menu_item,
+                priority: 0,
+            },
+        }
+    }
+
+    /// Creates a button that triggers an action, shown in the overflow menu when it doesn't fit in the toolbar.
+    #[composable]
+    pub fn action(text: impl Into<String>, action: &Action) -> ToolbarItem {
+        let text = text.into();
+        let button = Button::new(text.clone());
+        if button.clicked() {
+            action.triggered.signal(());
+        }
+        ToolbarItem::new(button, Some(MenuItem::new(text, action.clone())))
+    }
+
+    /// Creates a separator.
+    ///
+    /// Separators at the start or the end of the visible items, and consecutive separators,
+    /// are hidden automatically.
+    pub fn separator() -> ToolbarItem {
+        ToolbarItem {
+            kind: ToolbarItemKind::Separator,
+        }
+    }
+
+    /// Sets the priority of the item.
+    ///
+    /// When the toolbar is too narrow to show all items, items with the lowest priority are moved to the
+    /// overflow menu first; among items with the same priority, those at the end of the toolbar go first.
+    /// The default priority is 0.
+    #[must_use]
+    pub fn priority(mut self, priority: i32) -> Self {
+        if let ToolbarItemKind::Widget {
+            priority: ref mut p, ..
+        } = self.kind
+        {
+            *p = priority;
+        }
+        self
+    }
+
+    fn is_separator(&self) -> bool {
+        matches!(self.kind, ToolbarItemKind::Separator)
+    }
+}
+
+/// Lays out items horizontally, and moves the items that don't fit into an overflow menu.
+pub struct Toolbar {
+    id: WidgetId,
+    items: Vec<ToolbarItem>,
+    /// Button that opens the overflow menu.
+    overflow_button: Arc<WidgetPod>,
+    /// Whether each item is visible, computed during layout.
+    visible: RefCell<Vec<bool>>,
+    /// Horizontal positions of the visible separators, computed during layout.
+    separators: RefCell<Vec<f64>>,
+    /// Whether some items have been moved to the overflow menu during the last layout.
+    overflowing: Cell<bool>,
+    /// Bounds of the overflow button.
+    overflow_button_bounds: Cell<Rect>,
+    /// The overflow menu currently shown.
+    overflow_menu: RefCell<Option<Menu>>,
+}
+
+impl Toolbar {
+    /// Creates a toolbar with the specified items.
+    #[composable]
+    pub fn new(items: impl IntoIterator<Item = ToolbarItem>) -> Toolbar {
+        Toolbar {
+            id: WidgetId::here(),
+            items: items.into_iter().collect(),
+            overflow_button: Text::new("»").padding(4.dip()).arc_dyn_pod(),
+            visible: RefCell::new(vec![]),
+            separators: RefCell::new(vec![]),
+            overflowing: Cell::new(false),
+            overflow_button_bounds: Cell::new(Rect::zero()),
+            overflow_menu: RefCell::new(None),
+        }
+    }
+
+    /// Appends an item to the toolbar.
+    pub fn push(&mut self, item: ToolbarItem) {
+        self.items.push(item);
+    }
+
+    /// Returns which items are visible if the specified items are moved to the overflow menu.
+    ///
+    /// Separators are visible only if they are between two visible items, and consecutive separators are collapsed.
+    fn visible_items(&self, hidden: &[bool]) -> Vec<bool> {
+        let mut visible = vec![false; self.items.len()];
+        let mut pending_separator = None;
+        let mut any_visible = false;
+        for (i, item) in self.items.iter().enumerate() {
+            if item.is_separator() {
+                if any_visible {
+                    pending_separator = Some(i);
+                }
+            } else if !hidden[i] {
+                if let Some(separator) = pending_separator.take() {
+                    visible[separator] = true;
+                }
+                visible[i] = true;
+                any_visible = true;
+            }
+        }
+        visible
+    }
+
+    /// Returns the menu items of the items that have been moved to the overflow menu.
+    fn overflow_menu_items(&self) -> Vec<MenuItem> {
+        let visible = self.visible.borrow();
+        let mut menu_items = vec![];
+        for (item, &visible) in self.items.iter().zip(visible.iter()) {
+            match item.kind {
+                ToolbarItemKind::Widget {
+                    menu_item: Some(ref menu_item),
+                    ..
+                } if !visible => {
+                    menu_items.push(menu_item.clone());
+                }
+                // keep separators between groups of hidden items
+                ToolbarItemKind::Separator if !menu_items.is_empty() => {
+                    if !matches!(menu_items.last(), Some(MenuItem::Separator)) {
+                        menu_items.push(MenuItem::Separator);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if matches!(menu_items.last(), Some(MenuItem::Separator)) {
+            menu_items.pop();
+        }
+        menu_items
+    }
+}
+
+impl Widget for Toolbar {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let item_constraints = LayoutParams {
+            min: Size::zero(),
+            max: Size::new(f64::INFINITY, constraints.max.height),
+            ..*constraints
+        };
+
+        let geometries: Vec<Option<Geometry>> = self
+            .items
+            .iter()
+            .map(|item| match item.kind {
+                ToolbarItemKind::Widget { ref widget, .. } => Some(widget.layout(ctx, &item_constraints, env)),
+                ToolbarItemKind::Separator => None,
+            })
+            .collect();
+        let widths: Vec<f64> = geometries
+            .iter()
+            .map(|g| g.map(|g| g.padding_box_size().width).unwrap_or(SEPARATOR_WIDTH))
+            .collect();
+        let overflow_geometry = self.overflow_button.layout(ctx, &item_constraints, env);
+        let overflow_size = overflow_geometry.padding_box_size();
+
+        let content_width = |visible: &[bool]| {
+            let (count, width) = widths
+                .iter()
+                .zip(visible.iter())
+                .filter(|(_, &visible)| visible)
+                .fold((0, 0.0), |(count, width), (w, _)| (count + 1, width + w));
+            width + ITEM_SPACING * (count.max(1) - 1) as f64
+        };
+
+        // order in which items are moved to the overflow menu
+        let mut overflow_order: Vec<usize> = (0..self.items.len())
+            .filter(|&i| !self.items[i].is_separator())
+            .collect();
+        overflow_order.sort_by_key(|&i| match self.items[i].kind {
+            ToolbarItemKind::Widget { priority, .. } => (priority, Reverse(i)),
+            ToolbarItemKind::Separator => unreachable!(),
+        });
+
+        let mut hidden = vec![false; self.items.len()];
+        let mut num_hidden = 0;
+        let (visible, width) = loop {
+            let visible = self.visible_items(&hidden);
+            let mut width = content_width(&visible);
+            if num_hidden > 0 {
+                width += ITEM_SPACING + overflow_size.width;
+            }
+            if width <= constraints.max.width || num_hidden == overflow_order.len() {
+                break (visible, width);
+            }
+            hidden[overflow_order[num_hidden]] = true;
+            num_hidden += 1;
+        };
+        let overflowing = num_hidden > 0;
+
+        // height: tallest visible item
+        let mut height = geometries
+            .iter()
+            .zip(visible.iter())
+            .filter_map(|(g, &visible)| {
+                if visible {
+                    g.map(|g| g.padding_box_size().height)
+                } else {
+                    None
+                }
+            })
+            .fold(0.0, f64::max);
+        if overflowing {
+            height = height.max(overflow_size.height);
+        }
+        let size = Size::new(constraints.constrain_width(width), constraints.constrain_height(height));
+
+        // place items, centered vertically
+        let mut separators = vec![];
+        let mut x = 0.0;
+        for (i, item) in self.items.iter().enumerate() {
+            if !visible[i] {
+                continue;
+            }
+            match item.kind {
+                ToolbarItemKind::Widget { ref widget, .. } => {
+                    let geometry = geometries[i].unwrap();
+                    let y = 0.5 * (size.height - geometry.padding_box_size().height);
+                    widget.set_offset(Offset::new(x + geometry.padding_left, y + geometry.padding_top));
+                }
+                ToolbarItemKind::Separator => {
+                    separators.push(x + 0.5 * SEPARATOR_WIDTH);
+                }
+            }
+            x += widths[i] + ITEM_SPACING;
+        }
+
+        // overflow button at the end of the toolbar
+        let overflow_pos = Point::new(
+            size.width - overflow_size.width,
+            0.5 * (size.height - overflow_size.height),
+        );
+        self.overflow_button.set_offset(Offset::new(
+            overflow_pos.x + overflow_geometry.padding_left,
+            overflow_pos.y + overflow_geometry.padding_top,
+        ));
+        self.overflow_button_bounds.set(Rect::new(overflow_pos, overflow_size));
+
+        self.visible.replace(visible);
+        self.separators.replace(separators);
+        self.overflowing.set(overflowing);
+        Geometry::new(size)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        match *event {
+            Event::Pointer(ref p)
+                if self.overflowing.get()
+                    && p.kind == PointerEventKind::PointerDown
+                    && p.button == Some(PointerButton::LEFT)
+                    && self.overflow_button_bounds.get().contains(p.position) =>
+            {
+                let menu = Menu::from_items(self.overflow_menu_items());
+                menu.assign_menu_item_indices();
+                ctx.track_popup_menu(menu.to_shell_menu(true), p.window_position);
+                self.overflow_menu.replace(Some(menu));
+                ctx.set_handled();
+                return;
+            }
+            Event::MenuCommand(index) => {
+                if let Some(menu) = self.overflow_menu.take() {
+                    if let Some(action) = menu.find_action_by_index(index) {
+                        action.triggered.signal(());
+                    }
+                }
+                ctx.set_handled();
+                return;
+            }
+            _ => {}
+        }
+
+        // items in the overflow menu don't receive pointer events
+        let pointer_event = matches!(event, Event::Pointer(_) | Event::Wheel(_));
+        let visible = self.visible.borrow().clone();
+        for (i, item) in self.items.iter().enumerate() {
+            if let ToolbarItemKind::Widget { ref widget, .. } = item.kind {
+                if !pointer_event || visible.get(i).copied().unwrap_or(false) {
+                    widget.route_event(ctx, event, env);
+                }
+            }
+        }
+        if !pointer_event || self.overflowing.get() {
+            self.overflow_button.route_event(ctx, event, env);
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let visible = self.visible.borrow();
+        for (item, &visible) in self.items.iter().zip(visible.iter()) {
+            if let ToolbarItemKind::Widget { ref widget, .. } = item.kind {
+                if visible {
+                    widget.paint(ctx);
+                }
+            }
+        }
+
+        let height = ctx.bounds.size.height;
+        let separator_paint = Paint::from(theme::palette::GREY_500);
+        for &x in self.separators.borrow().iter() {
+            ctx.fill_shape(
+                &Shape::from(Rect::new(
+                    Point::new(x.floor(), SEPARATOR_MARGIN),
+                    Size::new(1.0, (height - 2.0 * SEPARATOR_MARGIN).max(0.0)),
+                )),
+                &separator_paint,
+            );
+        }
+
+        if self.overflowing.get() {
+            self.overflow_button.paint(ctx);
+        }
+    }
+}
@@ -0,0 +1,651 @@
+//! Tree views.
+use crate::{
+    animation::{animate, Easing, Transition},
+    cache,
+    drawing::{Paint, PaintCtxExt, Shape},
+    event::{KeyboardEvent, PointerButton, PointerEventKind},
+    theme,
+    widget::{
+        prelude::*,
+        table::{Identifiable, TableSelection, TableViewStyle},
+        Image, Null, Scaling, ScrollAlignment, Text, VirtualList,
+    },
+    Signal, UnitExt,
+};
+use keyboard_types::{Code, Key, KeyState, Modifiers};
+use kyute_common::imbl;
+use std::{hash::Hash, time::Duration};
+
+/// Estimated height of the rows, before they are measured.
+const ESTIMATED_ROW_HEIGHT: f64 = 24.0;
+/// Duration of the expand and collapse animations.
+const EXPAND_DURATION: Duration = Duration::from_millis(150);
+
+/// Hierarchical data source of a `TreeView`.
+///
+/// The children of a node are only requested while the node is expanded, so that they can be loaded lazily.
+pub trait TreeModel {
+    type Node: Identifiable;
+
+    /// Returns the top-level nodes.
+    fn roots(&self) -> Vec<Self::Node>;
+
+    /// Returns whether the node has children, in which case it can be expanded.
+    ///
+    /// This shouldn't load the children.
+    fn has_children(&self, node: &Self::Node) -> bool;
+
+    /// Returns the children of an expanded node, or `None` if they are still loading (e.g. with `cache::run_async`).
+    ///
+    /// A placeholder row is shown until they are available.
+    fn children(&self, node: &Self::Node) -> Option<Vec<Self::Node>>;
+}
+
+/// Changes requested by the user, applied on the next composition.
+#[derive(Clone, Debug, PartialEq)]
+enum Command<Id> {
+    /// Updates the selection after a click on a row or a keyboard move (see `TableSelection::select`). With `scroll`,
+    /// the row is scrolled into view.
+    Select {
+        id: Id,
+        modifiers: Modifiers,
+        scroll: bool,
+    },
+    SelectAll,
+    Expand(Id),
+    Collapse(Id),
+    Toggle(Id),
+    /// Expands a node and its descendants.
+    ExpandAll(Id),
+}
+
+/// A row that can be reached with the keyboard: a node whose ancestors are all expanded.
+#[derive(Clone, Debug)]
+struct NavRow<Id> {
+    id: Id,
+    /// Index of the parent row.
+    parent: Option<usize>,
+    has_children: bool,
+    expanded: bool,
+}
+
+/// Returns the command corresponding to a key press, given the current row.
+///
+/// - up and down arrows, `Home` and `End` move the selection (extended with Shift);
+/// - the right arrow or `+` expand the current node, the right arrow then moves to its first child;
+/// - the left arrow or `-` collapse it, the left arrow then moves to its parent;
+/// - `*` expands the current node and its descendants;
+/// - Space selects the current row, Ctrl+Space adds it to the selection or removes it;
+/// - Ctrl+A selects all rows.
+fn key_command<Id: Clone>(rows: &[NavRow<Id>], current: Option<usize>, event: &KeyboardEvent) -> Option<Command<Id>> {
+    if rows.is_empty() || event.state != KeyState::Down {
+        return None;
+    }
+
+    let ctrl = event.modifiers.contains(Modifiers::CONTROL);
+    let shift = event.modifiers.contains(Modifiers::SHIFT);
+    let select = |index: usize| {
+        Some(Command::Select {
+            id: rows[index].id.clone(),
+            modifiers: if shift { Modifiers::SHIFT } else { Modifiers::empty() },
+            scroll: true,
+        })
+    };
+
+    if event.code == Code::KeyA && ctrl && !shift {
+        return Some(Command::SelectAll);
+    }
+
+    let current = match current {
+        Some(current) => current,
+        None => {
+            return match event.key {
+                Key::ArrowUp | Key::ArrowDown | Key::Home | Key::End => select(0),
+                _ => None,
+            }
+        }
+    };
+    let row = &rows[current];
+    let first_child = Some(current + 1).filter(|&next| next < rows.len() && rows[next].parent == Some(current));
+
+    match event.key {
+        Key::ArrowUp => select(current.saturating_sub(1)),
+        Key::ArrowDown => select((current + 1).min(rows.len() - 1)),
+        Key::Home => select(0),
+        Key::End => select(rows.len() - 1),
+        Key::ArrowRight if row.has_children && !row.expanded => Some(Command::Expand(row.id.clone())),
+        Key::ArrowRight => first_child.and_then(select),
+        Key::ArrowLeft if row.expanded => Some(Command::Collapse(row.id.clone())),
+        Key::ArrowLeft => row.parent.and_then(select),
+        Key::Character(ref c) => match c.as_str() {
+            "+" if row.has_children && !row.expanded => Some(Command::Expand(row.id.clone())),
+            "-" if row.expanded => Some(Command::Collapse(row.id.clone())),
+            "*" if row.has_children => Some(Command::ExpandAll(row.id.clone())),
+            " " => Some(Command::Select {
+                id: row.id.clone(),
+                modifiers: if ctrl { Modifiers::CONTROL } else { Modifiers::empty() },
+                scroll: false,
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Expand button of a row.
+struct Expander<Id> {
+    content: WidgetPod,
+    id: Id,
+    commands: Signal<Command<Id>>,
+}
+
+impl<Id: Clone + 'static> Widget for Expander<Id> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        None
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.content.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Event::Pointer(p) = event {
+            if p.kind == PointerEventKind::PointerDown && p.button == Some(PointerButton::LEFT) {
+                self.commands.signal(Command::Toggle(self.id.clone()));
+                ctx.set_handled();
+                return;
+            }
+        }
+        self.content.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.content.paint(ctx)
+    }
+}
+
+/// A row of a tree view.
+///
+/// Takes the whole width of the list, and paints the selection highlight below the contents. While the parent
+/// node is expanding or collapsing, only the `reveal` fraction of the height of the row is shown.
+struct TreeRow<Id> {
+    content: WidgetPod,
+    background: Option<Paint>,
+    reveal: f64,
+    /// Identity of the node, `None` for placeholder rows.
+    id: Option<Id>,
+    commands: Signal<Command<Id>>,
+}
+
+impl<Id: Clone + 'static> Widget for TreeRow<Id> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        None
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let content = self.content.layout(ctx, constraints, env);
+        let size = Size::new(
+            content.measurements.size.width.max(constraints.min.width),
+            content.measurements.size.height * self.reveal,
+        );
+        let mut geometry = Geometry::new(size);
+        if self.reveal < 1.0 {
+            geometry.measurements.clip_bounds = Some(Rect::new(Point::origin(), size));
+        }
+        geometry
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        self.content.route_event(ctx, event, env);
+        if ctx.handled() {
+            return;
+        }
+        if let (Some(id), Event::Pointer(p)) = (&self.id, event) {
+            if p.kind == PointerEventKind::PointerDown && p.button == Some(PointerButton::LEFT) {
+                // double-clicks expand or collapse the node
+                self.commands.signal(if p.repeat_count == 2 {
+                    Command::Toggle(id.clone())
+                } else {
+                    Command::Select {
+                        id: id.clone(),
+                        modifiers: p.modifiers,
+                        scroll: false,
+                    }
+                });
+                ctx.set_handled();
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        if let Some(ref background) = self.background {
+            ctx.fill_shape(&Shape::from(ctx.bounds), background);
+        }
+        self.content.paint(ctx)
+    }
+}
+
+/// A row to display.
+enum RowKind<Node> {
+    Node {
+        node: Node,
+        has_children: bool,
+        expanded: bool,
+    },
+    /// Placeholder for children that are still loading.
+    Loading,
+}
+
+struct DisplayRow<Node> {
+    kind: RowKind<Node>,
+    depth: usize,
+    /// Fraction of the height of the row that is shown, below 1 while an ancestor is expanding or collapsing.
+    reveal: f64,
+}
+
+/// Displays hierarchical data (see `TreeModel`), with expandable nodes and a selection of rows.
+///
+/// Rows are selected by clicking on them, with Ctrl and Shift to add to the selection or select a range
+/// (see `TableSelection::select`), and can be navigated with the keyboard (see `key_command`).
+pub struct TreeView<Id> {
+    id: WidgetId,
+    list: WidgetPod,
+    rows: Vec<NavRow<Id>>,
+    /// Index of the current row in `rows`.
+    current: Option<usize>,
+    selection: TableSelection<Id>,
+    selection_changed: bool,
+    commands: Signal<Command<Id>>,
+}
+
+impl<Id: Clone + Eq + Hash + 'static> TreeView<Id> {
+    /// Creates a tree view showing the nodes of `model`. `item` creates the widget showing a node.
+    #[composable]
+    pub fn new<M, W>(model: &M, item: impl Fn(&M::Node) -> W) -> TreeView<Id>
+    where
+        M: TreeModel,
+        M::Node: Identifiable<Id = Id>,
+        W: Widget + 'static,
+    {
+        TreeView::with_style(TableViewStyle::default(), model, item)
+    }
+
+    /// Creates a tree view with the specified style. Only the indentation, the row markers and the selection
+    /// background are used.
+    #[composable]
+    pub fn with_style<M, W>(style: TableViewStyle, model: &M, item: impl Fn(&M::Node) -> W) -> TreeView<Id>
+    where
+        M: TreeModel,
+        M::Node: Identifiable<Id = Id>,
+        W: Widget + 'static,
+    {
+        let expanded_state = cache::state(imbl::HashSet::<Id>::new);
+        let selection_state = cache::state(TableSelection::<Id>::default);
+        let current_state = cache::state(|| None::<Id>);
+        let commands = Signal::new();
+
+        // expand or collapse first, since it changes the rows
+        let command = commands.value();
+        let mut expanded = expanded_state.get();
+        match command {
+            Some(Command::Expand(ref id)) => {
+                expanded.insert(id.clone());
+            }
+            Some(Command::Collapse(ref id)) => {
+                expanded.remove(id);
+            }
+            Some(Command::Toggle(ref id)) => {
+                if expanded.remove(id).is_none() {
+                    expanded.insert(id.clone());
+                }
+            }
+            _ => {}
+        }
+        let expand_all = match command {
+            Some(Command::ExpandAll(ref id)) => Some(id.clone()),
+            _ => None,
+        };
+
+        // flatten the expanded part of the tree, depth-first
+        let env = cache::environment();
+        let transition = Transition::new(theme::animation_duration(&env, EXPAND_DURATION)).easing(Easing::EaseOut);
+        let mut display_rows = Vec::new();
+        let mut rows: Vec<NavRow<Id>> = Vec::new();
+        // (node, depth, parent row if all ancestors are expanded, reveal, whether to expand all descendants)
+        let mut visit: Vec<_> = model
+            .roots()
+            .into_iter()
+            .rev()
+            .map(|node| (node, 0, Some(None), 1.0, false))
+            .collect();
+        while let Some((node, depth, parent, reveal, expand_subtree)) = visit.pop() {
+            let id = node.id();
+            let has_children = model.has_children(&node);
+            let expand_subtree = expand_subtree || expand_all.as_ref() == Some(&id);
+            if has_children && expand_subtree {
+                expanded.insert(id.clone());
+            }
+            let is_expanded = has_children && expanded.contains(&id);
+            // fraction of the children shown, animated when the node is expanded or collapsed
+            let openness = cache::scoped(&id, || animate(if is_expanded { 1.0 } else { 0.0 }, transition));
+
+            let nav_index = parent.map(|parent| {
+                rows.push(NavRow {
+                    id: id.clone(),
+                    parent,
+                    has_children,
+                    expanded: is_expanded,
+                });
+                rows.len() - 1
+            });
+            // the children of a collapsing node can't be reached with the keyboard anymore
+            let child_parent = if is_expanded { nav_index.map(Some) } else { None };
+            let children = if openness > 0.0 {
+                Some(model.children(&node))
+            } else {
+                None
+            };
+
+            // nodes are visited before their children
+            display_rows.push(DisplayRow {
+                kind: RowKind::Node {
+                    node,
+                    has_children,
+                    expanded: is_expanded,
+                },
+                depth,
+                reveal,
+            });
+            match children {
+                Some(Some(children)) => {
+                    for child in children.into_iter().rev() {
+                        visit.push((child, depth + 1, child_parent, reveal * openness, expand_subtree));
+                    }
+                }
+                Some(None) => display_rows.push(DisplayRow {
+                    kind: RowKind::Loading,
+                    depth: depth + 1,
+                    reveal: reveal * openness,
+                }),
+                None => {}
+            }
+        }
+        if command.is_some() {
+            expanded_state.set_without_invalidation(expanded.clone());
+        }
+
+        // then update the selection
+        let ids: Vec<Id> = rows.iter().map(|row| row.id.clone()).collect();
+        let mut selection = selection_state.get();
+        let mut selection_changed = false;
+        let mut scroll_to = None;
+        match command {
+            Some(Command::Select { id, modifiers, scroll }) => {
+                if let Some(index) = ids.iter().position(|x| *x == id) {
+                    selection.select(&ids, index, modifiers);
+                    current_state.set_without_invalidation(Some(id.clone()));
+                    selection_changed = true;
+                    if scroll {
+                        scroll_to = Some(id);
+                    }
+                }
+            }
+            Some(Command::SelectAll) => {
+                for id in ids.iter() {
+                    selection.insert(id.clone());
+                }
+                selection_changed = true;
+            }
+            _ => {}
+        }
+        if selection_changed {
+            selection_state.set_without_invalidation(selection.clone());
+        }
+        let current = current_state
+            .get()
+            .and_then(|current| ids.iter().position(|x| *x == current));
+
+        // build the rows
+        let icon_size = style.indentation;
+        let chevron_expanded = Image::from_uri(&style.expanded_row_marker_uri, Scaling::Contain)
+            .frame(icon_size, icon_size)
+            .arc_pod();
+        let chevron_collapsed = Image::from_uri(&style.collapsed_row_marker_uri, Scaling::Contain)
+            .frame(icon_size, icon_size)
+            .arc_pod();
+        let selected_background = style.selected_row_background.compute_paint(&env);
+        let scroll_index = scroll_to.and_then(|scroll_to| {
+            display_rows.iter().position(|row| match row.kind {
+                RowKind::Node { ref node, .. } => node.id() == scroll_to,
+                RowKind::Loading => false,
+            })
+        });
+
+        let mut list = VirtualList::new(display_rows.len(), ESTIMATED_ROW_HEIGHT, |i| {
+            let row = &display_rows[i];
+            let indent = (row.depth as f64) * style.indentation;
+            match row.kind {
+                RowKind::Node {
+                    ref node,
+                    has_children,
+                    expanded,
+                } => {
+                    let id = node.id();
+                    let marker = if has_children {
+                        Expander {
+                            content: WidgetPod::new(if expanded {
+                                chevron_expanded.clone()
+                            } else {
+                                chevron_collapsed.clone()
+                            }),
+                            id: id.clone(),
+                            commands: commands.clone(),
+                        }
+                        .arc_dyn_pod()
+                    } else {
+                        Null.frame(icon_size, icon_size).arc_dyn_pod()
+                    };
+                    let selected = selection.contains(&id);
+                    TreeRow {
+                        content: WidgetPod::new(
+                            marker
+                                .left_of(item(node), Alignment::CENTER)
+                                .padding_left(indent)
+                                .padding_top(2.dip())
+                                .padding_bottom(2.dip()),
+                        ),
+                        background: if selected {
+                            Some(selected_background.clone())
+                        } else {
+                            None
+                        },
+                        reveal: row.reveal,
+                        id: Some(id),
+                        commands: commands.clone(),
+                    }
+                }
+                RowKind::Loading => TreeRow {
+                    content: WidgetPod::new(
+                        Text::new("Loading…")
+                            .color(theme::SECONDARY_TEXT_COLOR)
+                            .padding_left(icon_size)
+                            .padding_left(indent)
+                            .padding_top(2.dip())
+                            .padding_bottom(2.dip()),
+                    ),
+                    background: None,
+                    reveal: row.reveal,
+                    id: None,
+                    commands: commands.clone(),
+                },
+            }
+        });
+        if let Some(index) = scroll_index {
+            list = list.scroll_to_index(index, ScrollAlignment::Nearest);
+        }
+
+        TreeView {
+            id: WidgetId::here(),
+            list: WidgetPod::new(list),
+            rows,
+            current,
+            selection,
+            selection_changed,
+            commands,
+        }
+    }
+
+    /// Returns the selected rows.
+    pub fn selection(&self) -> &TableSelection<Id> {
+        &self.selection
+    }
+
+    /// Returns the new selection, if the user has changed it.
+    pub fn selection_changed(&self) -> Option<TableSelection<Id>> {
+        if self.selection_changed {
+            Some(self.selection.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Invokes the provided closure if the user has changed the selection.
+    pub fn on_selection_changed(self, f: impl FnOnce(TableSelection<Id>)) -> Self {
+        if let Some(selection) = self.selection_changed() {
+            f(selection);
+        }
+        self
+    }
+}
+
+impl<Id: Clone + Eq + Hash + 'static> Widget for TreeView<Id> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        self.list.layout(ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        match event {
+            Event::Pointer(p) if p.kind == PointerEventKind::PointerDown => {
+                // receive keyboard events
+                ctx.request_focus();
+            }
+            Event::Keyboard(k) => {
+                if let Some(command) = key_command(&self.rows, self.current, k) {
+                    self.commands.signal(command);
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            _ => {}
+        }
+        self.list.route_event(ctx, event, env);
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        self.list.paint(ctx)
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key, modifiers: Modifiers) -> KeyboardEvent {
+        KeyboardEvent {
+            key,
+            state: KeyState::Down,
+            modifiers,
+            ..Default::default()
+        }
+    }
+
+    fn row(id: u32, parent: Option<usize>, has_children: bool, expanded: bool) -> NavRow<u32> {
+        NavRow {
+            id,
+            parent,
+            has_children,
+            expanded,
+        }
+    }
+
+    #[test]
+    fn keyboard_navigation() {
+        // 0
+        // ├ 1
+        // │ └ 2
+        // 3 (collapsed)
+        let rows = [
+            row(0, None, true, true),
+            row(1, Some(0), true, true),
+            row(2, Some(1), false, false),
+            row(3, None, true, false),
+        ];
+        let select = |id| {
+            Some(Command::Select {
+                id,
+                modifiers: Modifiers::empty(),
+                scroll: true,
+            })
+        };
+        let none = Modifiers::empty();
+
+        assert_eq!(key_command(&rows, None, &key(Key::ArrowDown, none)), select(0));
+        assert_eq!(key_command(&rows, Some(3), &key(Key::ArrowDown, none)), select(3));
+        assert_eq!(key_command(&rows, Some(0), &key(Key::ArrowRight, none)), select(1));
+        assert_eq!(
+            key_command(&rows, Some(3), &key(Key::ArrowRight, none)),
+            Some(Command::Expand(3))
+        );
+        assert_eq!(
+            key_command(&rows, Some(1), &key(Key::ArrowLeft, none)),
+            Some(Command::Collapse(1))
+        );
+        assert_eq!(key_command(&rows, Some(2), &key(Key::ArrowLeft, none)), select(1));
+        assert_eq!(key_command(&rows, Some(2), &key(Key::ArrowRight, none)), None);
+        assert_eq!(
+            key_command(&rows, Some(0), &key(Key::Character("*".to_string()), none)),
+            Some(Command::ExpandAll(0))
+        );
+        assert_eq!(
+            key_command(&rows, Some(2), &key(Key::ArrowUp, Modifiers::SHIFT)),
+            Some(Command::Select {
+                id: 1,
+                modifiers: Modifiers::SHIFT,
+                scroll: true
+            })
+        );
+    }
+
+    #[test]
+    fn selection_modifiers() {
+        let rows = [0, 1, 2, 3, 4];
+        let mut selection = TableSelection::default();
+        let selected = |selection: &TableSelection<u32>| {
+            let mut ids: Vec<u32> = selection.iter().copied().collect();
+            ids.sort();
+            ids
+        };
+
+        selection.select(&rows, 1, Modifiers::empty());
+        assert_eq!(selected(&selection), [1]);
+        selection.select(&rows, 3, Modifiers::SHIFT);
+        assert_eq!(selected(&selection), [1, 2, 3]);
+        // the anchor stays on the last row selected without Shift
+        selection.select(&rows, 0, Modifiers::SHIFT);
+        assert_eq!(selected(&selection), [0, 1]);
+        selection.select(&rows, 4, Modifiers::CONTROL);
+        assert_eq!(selected(&selection), [0, 1, 4]);
+        selection.select(&rows, 1, Modifiers::CONTROL);
+        assert_eq!(selected(&selection), [0, 4]);
+        selection.select(&rows, 2, Modifiers::CONTROL | Modifiers::SHIFT);
+        assert_eq!(selected(&selection), [0, 1, 2, 4]);
+    }
+}
@@ -0,0 +1,451 @@
+//! Virtualized lists.
+use crate::{
+    cache,
+    drawing::ToSkia,
+    event::{PointerButton, PointerEventKind},
+    theme,
+    widget::prelude::*,
+    State,
+};
+use parking_lot::Mutex;
+use skia_safe as sk;
+use std::{cell::Cell, ops::Range, sync::Arc};
+
+const DEFAULT_LINE_HEIGHT_DIP: f64 = 20.0;
+/// Number of rows composed above and below the visible ones, so that short scrolls don't show blank space.
+const OVERSCAN_ROWS: usize = 2;
+const SCROLL_THUMB_WIDTH: f64 = 5.0;
+const MIN_SCROLL_THUMB_HEIGHT: f64 = 30.0;
+
+/// Heights of the rows of a `VirtualList`: measured for the rows that have been laid out, estimated for the others.
+///
+/// The heights are stored in a Fenwick tree, so that the offset of a row, and the row at an offset, can be found
+/// in logarithmic time, even after some rows have been remeasured.
+struct RowHeights {
+    estimated: f64,
+    heights: Vec<f64>,
+    /// Fenwick tree over `heights`, 1-based.
+    tree: Vec<f64>,
+}
+
+fn lowest_bit(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
+
+impl RowHeights {
+    fn new(estimated: f64) -> RowHeights {
+        RowHeights {
+            estimated,
+            heights: vec![],
+            tree: vec![0.0],
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.heights.len()
+    }
+
+    /// Sets the number of rows. New rows get the estimated height.
+    fn resize(&mut self, len: usize) {
+        if len == self.len() {
+            return;
+        }
+        self.heights.resize(len, self.estimated);
+        self.tree = vec![0.0; len + 1];
+        self.tree[1..].copy_from_slice(&self.heights);
+        for i in 1..=len {
+            let parent = i + lowest_bit(i);
+            if parent <= len {
+                self.tree[parent] += self.tree[i];
+            }
+        }
+    }
+
+    /// Sets the measured height of a row. Returns whether the height has changed.
+    fn set(&mut self, index: usize, height: f64) -> bool {
+        let delta = height - self.heights[index];
+        if delta == 0.0 {
+            return false;
+        }
+        self.heights[index] = height;
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += lowest_bit(i);
+        }
+        true
+    }
+
+    /// Returns the offset of the top of a row from the top of the list.
+    fn offset_of(&self, index: usize) -> f64 {
+        let mut offset = 0.0;
+        let mut i = index.min(self.len());
+        while i > 0 {
+            offset += self.tree[i];
+            i -= lowest_bit(i);
+        }
+        offset
+    }
+
+    /// Returns the total height of the list.
+    fn total(&self) -> f64 {
+        self.offset_of(self.len())
+    }
+
+    /// Returns the row at the specified offset from the top of the list, clamped to the existing rows.
+    fn index_at(&self, offset: f64) -> usize {
+        let len = self.len();
+        if len == 0 {
+            return 0;
+        }
+        // find the number of rows that end before `offset`
+        let mut index = 0;
+        let mut remaining = offset;
+        let mut step = 1 << (usize::BITS - 1 - len.leading_zeros());
+        while step > 0 {
+            if index + step <= len && self.tree[index + step] <= remaining {
+                index += step;
+                remaining -= self.tree[index];
+            }
+            step >>= 1;
+        }
+        index.min(len - 1)
+    }
+}
+
+/// Where to place a row in the view when scrolling to it (see `VirtualList::scroll_to_index`).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScrollAlignment {
+    /// Align the top of the row with the top of the view.
+    Start,
+    /// Center the row in the view.
+    Center,
+    /// Align the bottom of the row with the bottom of the view.
+    End,
+    /// Scroll as little as possible to make the row visible.
+    Nearest,
+}
+
+struct VirtualRow {
+    index: usize,
+    widget: WidgetPod,
+}
+
+/// A vertically scrolling list that only composes, lays out and paints its visible rows.
+///
+/// Unlike `TableView` or a `Grid`, this can show collections with hundreds of thousands of items. Rows can
+/// have different heights: they are measured when they are first laid out, and until then their height is
+/// assumed to be `estimated_row_height`. The state of the rows that stay visible is retained while scrolling.
+///
+/// The list takes all the available height, which should be finite.
+pub struct VirtualList {
+    id: WidgetId,
+    rows: Vec<VirtualRow>,
+    heights: Arc<Mutex<RowHeights>>,
+    /// Offset of the top of the view from the top of the list.
+    scroll_offset: f64,
+    /// Total height of the rows, at the time of composition.
+    content_height: f64,
+    size: Cell<Size>,
+    /// Pointer position and scroll offset at the start of a drag of the scroll thumb.
+    thumb_drag: Arc<Mutex<Option<(f64, f64)>>>,
+    scroll_request: State<Option<(usize, ScrollAlignment)>>,
+    scrolled: Signal<f64>,
+    thumb_dragged: Signal<f64>,
+    /// Signalled with the height of the view when the visible rows may have changed after a layout.
+    measured: Signal<f64>,
+}
+
+impl VirtualList {
+    /// Creates a list of `item_count` rows.
+    ///
+    /// `item` is called to create the widget of each visible row, with the index of the row.
+    #[composable]
+    pub fn new<W: Widget + 'static>(
+        item_count: usize,
+        estimated_row_height: f64,
+        mut item: impl FnMut(usize) -> W,
+    ) -> VirtualList {
+        #[state]
+        let mut scroll_offset: f64 = 0.0;
+        #[state]
+        let mut viewport_height: f64 = 0.0;
+
+        let heights = cache::once(|| Arc::new(Mutex::new(RowHeights::new(estimated_row_height))));
+        let scroll_request: State<Option<(usize, ScrollAlignment)>> = cache::state(|| None);
+        let scrolled = Signal::new();
+        let thumb_dragged = Signal::new();
+        let measured = Signal::new();
+
+        if let Some(height) = measured.value() {
+            viewport_height = height;
+        }
+        if let Some(delta) = scrolled.value() {
+            scroll_offset += delta;
+        }
+        if let Some(offset) = thumb_dragged.value() {
+            scroll_offset = offset;
+        }
+
+        let (rows, content_height) = {
+            let mut heights = heights.lock();
+            heights.resize(item_count);
+
+            if let Some((index, alignment)) = scroll_request.take_without_invalidation() {
+                if item_count > 0 {
+                    let index = index.min(item_count - 1);
+                    let top = heights.offset_of(index);
+                    let bottom = heights.offset_of(index + 1);
+                    scroll_offset = match alignment {
+                        ScrollAlignment::Start => top,
+                        ScrollAlignment::Center => 0.5 * (top + bottom - viewport_height),
+                        ScrollAlignment::End => bottom - viewport_height,
+                        ScrollAlignment::Nearest if top < scroll_offset => top,
+                        ScrollAlignment::Nearest if bottom > scroll_offset + viewport_height => {
+                            bottom - viewport_height
+                        }
+                        ScrollAlignment::Nearest => scroll_offset,
+                    };
+                }
+            }
+
+            let content_height = heights.total();
+            scroll_offset = scroll_offset.clamp(0.0, (content_height - viewport_height).max(0.0));
+
+            let visible = if item_count > 0 {
+                let first = heights.index_at(scroll_offset).saturating_sub(OVERSCAN_ROWS);
+                let last = (heights.index_at(scroll_offset + viewport_height) + OVERSCAN_ROWS).min(item_count - 1);
+                first..last + 1
+            } else {
+                0..0
+            };
+            drop(heights);
+
+            let rows: Vec<_> = visible
+                .map(|index| {
+                    // the state of the row widgets follows the row, not its position in the view
+                    cache::scoped(index, || VirtualRow {
+                        index,
+                        widget: WidgetPod::new(item(index)),
+                    })
+                })
+                .collect();
+            (rows, content_height)
+        };
+
+        VirtualList {
+            id: WidgetId::here(),
+            rows,
+            heights,
+            scroll_offset,
+            content_height,
+            size: Cell::new(Size::zero()),
+            thumb_drag: cache::once(|| Arc::new(Mutex::new(None))),
+            scroll_request,
+            scrolled,
+            thumb_dragged,
+            measured,
+        }
+    }
+
+    /// Scrolls the list to show the row at `index`, at the next composition.
+    ///
+    /// This should be called in response to an event (e.g. when an item is selected), not on every composition.
+    pub fn scroll_to_index(self, index: usize, alignment: ScrollAlignment) -> Self {
+        self.scroll_request.set(Some((index, alignment)));
+        self
+    }
+
+    /// Returns the indices of the rows that are currently composed.
+    ///
+    /// This includes a few rows above and below the visible ones.
+    pub fn composed_range(&self) -> Range<usize> {
+        match (self.rows.first(), self.rows.last()) {
+            (Some(first), Some(last)) => first.index..last.index + 1,
+            _ => 0..0,
+        }
+    }
+
+    /// Returns the offset of the top of the view from the top of the list.
+    pub fn scroll_offset(&self) -> f64 {
+        self.scroll_offset
+    }
+
+    /// Returns the bounds of the scroll thumb, and the scroll distance corresponding to a movement of the thumb
+    /// of one DIP, or `None` if all rows fit in the view.
+    fn scroll_thumb(&self) -> Option<(Rect, f64)> {
+        let size = self.size.get();
+        let viewport_height = size.height;
+        if self.content_height <= viewport_height {
+            return None;
+        }
+        let thumb_height = (viewport_height * viewport_height / self.content_height).max(MIN_SCROLL_THUMB_HEIGHT);
+        let scroll_per_dip = (self.content_height - viewport_height) / (viewport_height - thumb_height);
+        let thumb_y = self.scroll_offset / scroll_per_dip;
+        Some((
+            Rect::new(
+                Point::new(size.width - SCROLL_THUMB_WIDTH, thumb_y),
+                Size::new(SCROLL_THUMB_WIDTH, thumb_height),
+            ),
+            scroll_per_dip,
+        ))
+    }
+}
+
+impl Widget for VirtualList {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        let width = constraints.finite_max_width();
+        let height = constraints
+            .finite_max_height()
+            .unwrap_or(0.0)
+            .max(constraints.min.height);
+
+        // rows take the width of the list, and their natural height
+        let row_constraints = LayoutParams {
+            min: Size::new(width.unwrap_or(0.0), 0.0),
+            max: Size::new(width.unwrap_or(f64::INFINITY), f64::INFINITY),
+            ..*constraints
+        };
+
+        let mut heights = self.heights.lock();
+        let mut y = match self.rows.first() {
+            Some(row) => heights.offset_of(row.index) - self.scroll_offset,
+            None => 0.0,
+        };
+        let mut max_row_width: f64 = 0.0;
+        let mut remeasured = false;
+        for row in self.rows.iter() {
+            let geometry = row.widget.layout(ctx, &row_constraints, env);
+            let row_size = geometry.measurements.size;
+            max_row_width = max_row_width.max(row_size.width);
+            if !ctx.speculative {
+                remeasured |= heights.set(row.index, row_size.height);
+                row.widget.set_offset(Offset::new(0.0, y));
+            }
+            y += row_size.height;
+        }
+        drop(heights);
+
+        let size = Size::new(width.unwrap_or(max_row_width), height);
+        if !ctx.speculative {
+            if remeasured || size.height != self.size.get().height {
+                // the visible rows may have changed
+                self.measured.signal(size.height);
+            }
+            self.size.set(size);
+        }
+        Geometry::new(size)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        if let Event::Pointer(p) = event {
+            let mut thumb_drag = self.thumb_drag.lock();
+            match p.kind {
+                PointerEventKind::PointerDown if p.button == Some(PointerButton::LEFT) => {
+                    if let Some((thumb, _)) = self.scroll_thumb() {
+                        if thumb.contains(p.position) {
+                            *thumb_drag = Some((p.position.y, self.scroll_offset));
+                            ctx.capture_pointer();
+                            ctx.set_handled();
+                            return;
+                        }
+                    }
+                }
+                PointerEventKind::PointerMove if thumb_drag.is_some() => {
+                    let (start_y, start_offset) = thumb_drag.unwrap();
+                    if let Some((_, scroll_per_dip)) = self.scroll_thumb() {
+                        self.thumb_dragged
+                            .signal(start_offset + (p.position.y - start_y) * scroll_per_dip);
+                    }
+                    ctx.set_handled();
+                    return;
+                }
+                PointerEventKind::PointerUp if thumb_drag.is_some() => {
+                    *thumb_drag = None;
+                    ctx.release_pointer();
+                    ctx.set_handled();
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        for row in self.rows.iter() {
+            row.widget.route_event(ctx, event, env);
+        }
+
+        // scroll to make a rectangle visible if requested by the rows
+        if let Some(rect) = ctx.take_scroll_into_view_request() {
+            let viewport_height = self.size.get().height;
+            if rect.min_y() < 0.0 {
+                self.scrolled.signal(rect.min_y());
+            } else if rect.max_y() > viewport_height {
+                self.scrolled.signal((rect.max_y() - viewport_height).min(rect.min_y()));
+            }
+        }
+
+        if !ctx.handled {
+            if let Event::Wheel(wheel) = event {
+                let delta = wheel.scroll_delta(
+                    &theme::scroll_settings(env),
+                    Size::new(DEFAULT_LINE_HEIGHT_DIP, DEFAULT_LINE_HEIGHT_DIP),
+                    self.size.get(),
+                );
+                if delta.y != 0.0 {
+                    self.scrolled.signal(delta.y);
+                    ctx.set_handled();
+                }
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let bounds = ctx.bounds;
+        ctx.surface.canvas().save();
+        ctx.surface
+            .canvas()
+            .clip_rect(bounds.to_skia(), skia_safe::ClipOp::Intersect, false);
+        for row in self.rows.iter() {
+            row.widget.paint(ctx);
+        }
+        if let Some((thumb, _)) = self.scroll_thumb() {
+            let paint = sk::Paint::new(theme::palette::GREY_500.to_skia(), None);
+            let radius = 0.5 * SCROLL_THUMB_WIDTH as sk::scalar;
+            ctx.surface
+                .canvas()
+                .draw_rrect(sk::RRect::new_rect_xy(thumb.to_skia(), radius, radius), &paint);
+        }
+        ctx.surface.canvas().restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RowHeights;
+
+    #[test]
+    fn row_heights() {
+        let mut heights = RowHeights::new(10.0);
+        heights.resize(1000);
+        assert_eq!(heights.total(), 10000.0);
+        assert_eq!(heights.offset_of(3), 30.0);
+        assert_eq!(heights.index_at(35.0), 3);
+
+        assert!(heights.set(1, 50.0));
+        assert!(!heights.set(1, 50.0));
+        assert_eq!(heights.offset_of(3), 70.0);
+        assert_eq!(heights.index_at(15.0), 1);
+        assert_eq!(heights.index_at(60.0), 2);
+        assert_eq!(heights.index_at(1e9), 999);
+        assert_eq!(heights.total(), 10040.0);
+
+        // measured heights are kept when rows are added
+        heights.resize(1001);
+        assert_eq!(heights.offset_of(3), 70.0);
+        assert_eq!(heights.total(), 10050.0);
+    }
+}
@@ -1,17 +1,24 @@
 use crate::{
     cache,
-    core::{DebugNode, LayerPaintCtx, PaintDamage},
-    drawing::ToSkia,
+    core::{region_to_pixels, DebugNode, LayerPaintCtx, PaintDamage},
+    diagnostics::WidgetPodToken,
+    drawing::{overdraw, ToSkia},
+    frame_debugger,
+    gc::{Releasable, Tracked},
+    jank,
+    region::Region,
+    style::WidgetState,
     widget::prelude::*,
     Bloom, InternalEvent, LayoutParams, PointerEventKind, SizeI, WidgetFilter,
 };
+use bitflags::bitflags;
 use kyute_common::{Color, RectExt};
 use kyute_shell::animation::Layer;
 use skia_safe as sk;
 use std::{
     cell::{Cell, RefCell, RefMut},
-    fmt,
-    sync::Arc,
+    fmt, mem,
+    rc::Rc,
 };
 
 /*#[derive(Clone)]
@@ -72,12 +79,19 @@ impl PaintSurface {
     }
 }
 
+impl Releasable for PaintSurface {
+    fn release(&self) -> bool {
+        // allocated again by `sk_surface_mut`
+        self.sk_surface.borrow_mut().take().is_some()
+    }
+}
+
 /// Specifies where a WidgetPod will draw its content
 enum PaintTarget {
     /// Paint on a native composition layer
-    NativeLayer { layer: Layer },
+    NativeLayer { layer: Rc<Tracked<Layer>> },
     /// Paint on a skia surface
-    Surface { surface: Arc<PaintSurface> },
+    Surface { surface: Rc<Tracked<PaintSurface>> },
     /// Paint on the parent layer / surface
     ParentSurface,
 }
@@ -152,6 +166,40 @@ enum PaintTarget {
     frame.finish(&mut ());
 }*/
 
+bitflags! {
+    /// Containment hints for a `WidgetPod` (similar to the CSS `contain` property).
+    ///
+    /// Declares that the contents of the pod are isolated from the rest of the widget tree, so that
+    /// changes inside don't require recomputing anything outside, and vice versa.
+    #[derive(Default)]
+    pub struct Containment: u8 {
+        /// Relayouts requested by the contents don't invalidate the layout of the ancestors: the contents are
+        /// relaid out in place with the last constraints.
+        ///
+        /// If the resulting geometry differs, the relayout is propagated to ancestors as usual.
+        const LAYOUT = 0b0000_0001;
+        /// The contents don't paint outside the bounds of the pod: they are clipped to them.
+        const PAINT = 0b0000_0010;
+        /// The contents don't inherit the widget state (hovered, active, etc.) of the ancestors, so that
+        /// state changes outside don't restyle and relayout the contents.
+        const STYLE = 0b0000_0100;
+        /// All of the above.
+        const STRICT = Self::LAYOUT.bits | Self::PAINT.bits | Self::STYLE.bits;
+    }
+}
+
+bitflags! {
+    /// Why the cached layout of a `WidgetPod` can't be reused.
+    #[derive(Default)]
+    struct LayoutFlags: u8 {
+        /// The contents requested a relayout: the layout of the contents must be recomputed.
+        const DIRTY = 0b0000_0001;
+        /// Descendant widget pods requested a relayout. The layout of the contents only needs to be recomputed if
+        /// their geometry changes.
+        const CHILD_DIRTY = 0b0000_0010;
+    }
+}
+
 /// A container for a widget.
 pub struct WidgetPod<T: ?Sized = dyn Widget> {
     /// Unique ID of the widget, if it has one.
@@ -163,21 +211,83 @@ pub struct WidgetPod<T: ?Sized = dyn Widget> {
     child_filter: Cell<Option<WidgetFilter>>,
     /// Paint damage done to the content of the widget pod.
     paint_damage: Cell<PaintDamage>,
+    /// Damaged parts of the contents, in local coordinates, if `paint_damage` is `PaintDamage::Partial`.
+    damaged: RefCell<Region>,
     cached_constraints: Cell<LayoutParams>,
+    /// Whether the cached layout is still valid.
+    layout_flags: Cell<LayoutFlags>,
     /// Cached layout result.
-    layout_invalid: Cell<bool>,
     cached_layout: Cell<Option<Geometry>>,
+    /// Incremented each time the layout of the contents is recomputed (see `layout_revision`).
+    layout_revision: Cell<u64>,
+    /// Containment hints.
+    containment: Containment,
+    /// Stacking order among the siblings of this widget pod.
+    z_index: i32,
+
+    /// Keeps track of the number of live widget pods.
+    _token: WidgetPodToken,
 
     /// Inner widget
     content: T,
 }
 
+/// Returns the items in painting order: by increasing z-index, and in the original order for items
+/// with the same z-index.
+///
+/// Containers should paint their children in this order, and deliver events to them in the reverse order.
+/// Only allocates if the items are not already in painting order.
+pub(crate) fn sort_by_z_index<'a, T>(items: &'a [T], z_index: impl Fn(&T) -> i32) -> ZOrder<'a, T> {
+    if items.windows(2).any(|w| z_index(&w[0]) > z_index(&w[1])) {
+        let mut sorted: Vec<&T> = items.iter().collect();
+        // the sort is stable: items with the same z-index stay in order
+        sorted.sort_by_key(|item| z_index(item));
+        ZOrder::Sorted(sorted.into_iter())
+    } else {
+        ZOrder::InOrder(items.iter())
+    }
+}
+
+/// Iterator over items in painting order, returned by `sort_by_z_index`.
+pub(crate) enum ZOrder<'a, T> {
+    InOrder(std::slice::Iter<'a, T>),
+    Sorted(std::vec::IntoIter<&'a T>),
+}
+
+impl<'a, T> Iterator for ZOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        match self {
+            ZOrder::InOrder(iter) => iter.next(),
+            ZOrder::Sorted(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            ZOrder::InOrder(iter) => iter.size_hint(),
+            ZOrder::Sorted(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ZOrder<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        match self {
+            ZOrder::InOrder(iter) => iter.next_back(),
+            ZOrder::Sorted(iter) => iter.next_back(),
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Constructor impls
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
 impl<T: Widget + 'static> WidgetPod<T> {
     /// Creates a new `WidgetPod` wrapping the specified widget.
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn new(widget: T) -> WidgetPod<T> {
         Self::new_inner(widget, PaintTarget::ParentSurface)
     }
@@ -185,17 +295,18 @@ impl<T: Widget + 'static> WidgetPod<T> {
     /// Creates a new widgetpod backed by a native compositor layer.
     #[composable]
     pub fn with_native_layer(widget: T) -> WidgetPod<T> {
-        let layer = cache::once(Layer::new);
+        let layer = cache::once(Tracked::layer);
         Self::new_inner(widget, PaintTarget::NativeLayer { layer })
     }
 
     /// Creates a new widgetpod backed by a surface object.
     #[composable]
     pub fn with_surface(widget: T) -> WidgetPod<T> {
-        let surface = cache::once(|| Arc::new(PaintSurface::new()));
+        let surface = cache::once(|| Tracked::surface(PaintSurface::new()));
         Self::new_inner(widget, PaintTarget::Surface { surface })
     }
 
+    #[cfg_attr(debug_assertions, track_caller)]
     fn new_inner(widget: T, paint_target: PaintTarget) -> WidgetPod<T> {
         let id = widget.widget_id();
         WidgetPod {
@@ -204,10 +315,15 @@ impl<T: Widget + 'static> WidgetPod<T> {
             transform: Cell::new(Default::default()),
             child_filter: Cell::new(None),
             paint_damage: Cell::new(PaintDamage::Repaint),
+            damaged: RefCell::new(Region::new()),
             cached_constraints: Cell::new(Default::default()),
             content: widget,
             cached_layout: Cell::new(None),
-            layout_invalid: Cell::new(true),
+            layout_revision: Cell::new(0),
+            layout_flags: Cell::new(LayoutFlags::DIRTY),
+            containment: Containment::empty(),
+            z_index: 0,
+            _token: WidgetPodToken::new(),
         }
     }
 }
@@ -216,7 +332,89 @@ impl<T: Widget + 'static> WidgetPod<T> {
 // Methods
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+impl<T: Widget> WidgetPod<T> {
+    /// Sets the containment hints of this widget pod.
+    ///
+    /// See `Containment`.
+    pub fn contain(mut self, containment: Containment) -> Self {
+        self.containment = containment;
+        self
+    }
+
+    /// Sets the z-index of this widget pod.
+    ///
+    /// See `WidgetPod::z_index`.
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.z_index = z_index;
+        self
+    }
+}
+
 impl<T: Widget + ?Sized> WidgetPod<T> {
+    /// Returns the containment hints of this widget pod.
+    pub fn containment(&self) -> Containment {
+        self.containment
+    }
+
+    /// Returns the z-index of this widget pod: the z-index set with `with_z_index`, plus the z-order of the
+    /// contents (e.g. the z-index of a pod wrapped in this one).
+    ///
+    /// Containers paint the children with a higher z-index after (above) the others, and deliver events
+    /// to them first. Children with the same z-index are painted in order of insertion.
+    /// Since compositor layers are attached to their parent layer during painting, this also determines
+    /// the order of the native layers.
+    pub fn z_index(&self) -> i32 {
+        self.z_index + self.content.z_order()
+    }
+
+    /// Returns a number that changes each time the layout of the contents is recomputed, or `None` if the contents
+    /// requested a relayout that hasn't happened yet.
+    ///
+    /// Containers can use it to tell whether values derived from the layout of a child, such as its natural size,
+    /// are still valid.
+    pub(crate) fn layout_revision(&self) -> Option<u64> {
+        if self.layout_flags.get().is_empty() {
+            Some(self.layout_revision.get())
+        } else {
+            None
+        }
+    }
+
+    /// Recomputes the layout with the constraints of the last layout, if it is invalid.
+    ///
+    /// Returns whether the resulting geometry is the same as before, in which case the ancestors don't need
+    /// to be relaid out. `parent_ctx` is the layout context of the ancestor being laid out, if any.
+    fn relayout_in_place(&self, parent_ctx: Option<&LayoutCtx>, env: &Environment) -> bool {
+        let previous = match self.cached_layout.get() {
+            Some(layout) => layout,
+            None => return false,
+        };
+        let constraints = self.cached_constraints.get();
+        let mut layout_ctx = match parent_ctx {
+            Some(parent_ctx) => parent_ctx.relayout_ctx(constraints.scale_factor),
+            None => LayoutCtx::new(constraints.scale_factor),
+        };
+        let layout = Widget::layout(self, &mut layout_ctx, &constraints, env);
+        layout == previous
+    }
+
+    /// Relays out the descendant widget pods that need it, with the constraints of their last layout.
+    ///
+    /// Returns whether the geometry of one of them has changed, in which case the layout of the contents must be
+    /// recomputed.
+    fn relayout_dirty_children(&self, ctx: &LayoutCtx, env: &Environment) -> bool {
+        let mut geometry_changed = false;
+        crate::core::send_utility_event(
+            &self.content,
+            &mut Event::Internal(InternalEvent::RelayoutDirty {
+                parent_ctx: ctx,
+                geometry_changed: &mut geometry_changed,
+            }),
+            env,
+        );
+        geometry_changed
+    }
+
     /// Computes the child bloom filter.
     fn compute_child_filter(&self, parent_ctx: &mut EventCtx, env: &Environment) -> Bloom<WidgetId> {
         if let Some(filter) = self.child_filter.get() {
@@ -260,6 +458,7 @@ impl<T: Widget + ?Sized> WidgetPod<T> {
     /// Returns the layer.
     pub fn layer(&self) -> Option<&Layer> {
         if let PaintTarget::NativeLayer { ref layer } = self.paint_target {
+            let layer: &Layer = layer;
             Some(layer)
         } else {
             None
@@ -275,26 +474,72 @@ impl<T: Widget + ?Sized> WidgetPod<T> {
         );
     }
 
+    /// Forces a repaint of the contents on the next call to `repaint_layer`.
+    pub(crate) fn invalidate_paint(&self) {
+        self.paint_damage.set(PaintDamage::Repaint);
+    }
+
+    /// Adds a region (in local coordinates) to the parts of the contents that are repainted on the next call to
+    /// `repaint_layer`.
+    pub(crate) fn invalidate_paint_region(&self, region: &Region) {
+        let bounds = match self.cached_layout.get() {
+            Some(layout) => layout.measurements.local_bounds(),
+            None => return self.invalidate_paint(),
+        };
+        let region = region.clipped(bounds);
+        if !region.is_empty() {
+            self.damaged.borrow_mut().add_region(&region);
+            let mut damage = self.paint_damage.get();
+            damage.merge_up(PaintDamage::Partial);
+            self.paint_damage.set(damage);
+        }
+    }
+
+    /// Returns the paint damage and resets it, with the damaged region if the damage is `PaintDamage::Partial`.
+    /// While a frame is being captured by the frame debugger, or while the overdraw visualization is enabled,
+    /// everything is repainted.
+    ///
+    /// The contents are also repainted if the layer or surface has been released by the collector (see `gc`).
+    fn take_paint_damage(&self) -> (PaintDamage, Region) {
+        let damage = self.paint_damage.replace(PaintDamage::None);
+        let damaged = mem::take(&mut *self.damaged.borrow_mut());
+        let released = match self.paint_target {
+            PaintTarget::NativeLayer { ref layer } => layer.take_released(),
+            PaintTarget::Surface { ref surface } => surface.take_released(),
+            PaintTarget::ParentSurface => false,
+        };
+        if released || frame_debugger::is_capturing() || overdraw::repaint_all() {
+            (PaintDamage::Repaint, Region::new())
+        } else {
+            (damage, damaged)
+        }
+    }
+
     pub(crate) fn repaint_layer(&self, skia_gpu_context: &mut sk::gpu::DirectContext) -> bool {
         if let PaintTarget::NativeLayer { ref layer } = self.paint_target {
             assert!(self.cached_layout.get().is_some(), "repaint called before layout");
-            match self.paint_damage.replace(PaintDamage::None) {
-                PaintDamage::Repaint => {
-                    // straight recursive repaint
+            match self.take_paint_damage() {
+                (damage @ (PaintDamage::Repaint | PaintDamage::Partial), damaged) => {
+                    // straight recursive repaint, clipped to the damaged region if the damage is partial
                     let _span = trace_span!("Repaint layer", id=?self.id).entered();
+                    let _jank_scope = jank::time_widget(self.id, self.content.debug_name(), jank::Phase::Paint);
                     layer.remove_all_children();
-                    let mut layer_paint_ctx = LayerPaintCtx { skia_gpu_context };
+                    frame_debugger::set_layer_owner(self.widget_id(), self.content.debug_name());
+                    let mut layer_paint_ctx = LayerPaintCtx {
+                        skia_gpu_context,
+                        damage: (damage == PaintDamage::Partial).then(|| damaged),
+                    };
                     // use the scale factor we got from the last layout
                     self.content
                         .layer_paint(&mut layer_paint_ctx, layer, self.cached_constraints.get().scale_factor);
                     true
                 }
-                PaintDamage::SubLayers => {
+                (PaintDamage::SubLayers, _) => {
                     let _span = trace_span!("Update layer", id=?self.id).entered();
                     self.update_child_layers(skia_gpu_context);
                     true
                 }
-                PaintDamage::None => false,
+                (PaintDamage::None, _) => false,
             }
         } else {
             warn!("repaint_layer called on non-layered WidgetPod");
@@ -319,15 +564,46 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
         //
         // If 2., then we can skip repaint if the resulting measurements are the same.
 
-        if self.cached_constraints.get() == *constraints && !self.layout_invalid.get() {
+        // with style containment, the contents don't see the widget state of the ancestors
+        let contained_constraints;
+        let constraints = if self.containment.contains(Containment::STYLE) {
+            contained_constraints = LayoutParams {
+                widget_state: WidgetState::default(),
+                ..*constraints
+            };
+            &contained_constraints
+        } else {
+            constraints
+        };
+
+        if self.cached_constraints.get() == *constraints {
             if let Some(layout) = self.cached_layout.get() {
-                // same constraints & cached measurements still valid (no child widget requested a relayout) => skip layout & repaint
-                trace!(
-                    "[{:?}] WidgetPod returning cached layout ({:?})",
-                    self.widget_id(),
-                    layout
-                );
-                return layout;
+                let flags = self.layout_flags.get();
+                if flags.is_empty() {
+                    // same constraints & cached measurements still valid (no child widget requested a relayout) => skip layout & repaint
+                    trace!(
+                        "[{:?}] WidgetPod returning cached layout ({:?})",
+                        self.widget_id(),
+                        layout
+                    );
+                    return layout;
+                }
+
+                // Only descendant widget pods need a relayout: relayout them in place, and keep the cached layout
+                // if their geometry stays the same. Speculative layouts don't update the cached layouts of the
+                // descendants, so they go through the full layout below.
+                if flags == LayoutFlags::CHILD_DIRTY && !ctx.speculative {
+                    #[cfg(debug_assertions)]
+                    ctx.layout_path.push(self.debug_name().to_string());
+                    let geometry_changed = self.relayout_dirty_children(ctx, env);
+                    #[cfg(debug_assertions)]
+                    ctx.layout_path.pop();
+                    if !geometry_changed {
+                        self.layout_flags.set(LayoutFlags::empty());
+                        self.layout_revision.set(self.layout_revision.get() + 1);
+                        return layout;
+                    }
+                }
             }
         }
 
@@ -336,6 +612,7 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
                     id = ?self.id,
                     name = name)
         .entered();*/
+        let _jank_scope = jank::time_widget(self.id, name, jank::Phase::Layout);
 
         // child layout
 
@@ -345,9 +622,39 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
             ctx.speculative
         );
 
+        // In debug builds, check the constraints and the returned measurements, and report the path to the widget
+        // on failure: otherwise a single NaN propagates silently through the whole layout.
+        #[cfg(debug_assertions)]
+        {
+            ctx.layout_path.push(name.to_string());
+            if let Err(err) = constraints.validate() {
+                warn!(
+                    "invalid constraints passed to `{}`: {} {:?}",
+                    ctx.widget_path(),
+                    err,
+                    constraints
+                );
+            }
+        }
+
         let layout = self.content.layout(ctx, constraints, env);
 
-        // also check for invalid size values while we're at it, but that's only for debugging convenience.
+        #[cfg(debug_assertions)]
+        {
+            if let Err(err) = constraints.validate_measurements(&layout.measurements) {
+                warn!(
+                    "invalid layout returned by `{}`: {} (constraints: {:?}, layout: {:?})",
+                    ctx.widget_path(),
+                    err,
+                    constraints,
+                    layout
+                );
+            }
+            ctx.layout_path.pop();
+        }
+
+        // in release builds, only check for invalid size values
+        #[cfg(not(debug_assertions))]
         if !layout.measurements.size.width.is_finite() || !layout.measurements.size.height.is_finite() {
             warn!(
                 "layout[{:?}({})] returned non-finite measurements: {:?}",
@@ -358,7 +665,9 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
         // if we are painting on our own layer OR surface, now we need to decide if we need to repaint it
 
         if !ctx.speculative {
-            if self.cached_layout.get() != Some(layout) {
+            // the layout in DIPs may stay the same when the scale factor changes, but not the size in pixels
+            let scale_factor_changed = self.cached_constraints.get().scale_factor != constraints.scale_factor;
+            if self.cached_layout.get() != Some(layout) || scale_factor_changed {
                 // resize the underlying native layer or surface
                 // TODO take bounds into account
 
@@ -391,12 +700,17 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
             // update cached layout
             self.cached_constraints.set(*constraints);
             self.cached_layout.set(Some(layout));
-            self.layout_invalid.set(false);
+            self.layout_flags.set(LayoutFlags::empty());
+            self.layout_revision.set(self.layout_revision.get() + 1);
         }
 
         layout
     }
 
+    fn intrinsic_widths(&self, ctx: &mut LayoutCtx, env: &Environment) -> Option<IntrinsicWidths> {
+        self.content.intrinsic_widths(ctx, env)
+    }
+
     fn route_event(&self, parent_ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
         // WidgetPod plays an important role during event propagation:
         // First, it maintains a "child filter": a bloom filter containing the set of child widget IDs.
@@ -415,11 +729,28 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
             Event::Internal(InternalEvent::RouteWindowEvent { target, .. })
             | Event::Internal(InternalEvent::RouteEvent { target, .. })
             | Event::Internal(InternalEvent::RoutePointerEvent { target, .. })
+            | Event::Internal(InternalEvent::RouteWheelEvent { target, .. })
             | Event::Internal(InternalEvent::RouteRedrawRequest(target)) => {
                 if Some(target) != self.id && !self.may_contain(target) {
                     return;
                 }
             }
+            // relayout in place if needed; the descendants of a widget pod with a valid layout don't need a relayout
+            Event::Internal(InternalEvent::RelayoutDirty {
+                parent_ctx,
+                ref mut geometry_changed,
+            }) => {
+                if !self.layout_flags.get().is_empty() && !self.relayout_in_place(Some(parent_ctx), env) {
+                    **geometry_changed = true;
+                }
+                return;
+            }
+            // the layer or surface is still in use
+            Event::Internal(InternalEvent::MarkUsedResources) => match self.paint_target {
+                PaintTarget::NativeLayer { ref layer } => layer.mark_used(),
+                PaintTarget::Surface { ref surface } => surface.mark_used(),
+                PaintTarget::ParentSurface => {}
+            },
             // for UpdateChildFilter, if we already have computed and cached the child filter, use that
             // instead of propagating down the tree.
             Event::Internal(InternalEvent::UpdateChildFilter { ref mut filter }) => {
@@ -470,6 +801,16 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
         // continue with default routing behavior
         // hit-testing is done in the main `event` method so that `default_route_event` can do hover processing
         parent_ctx.default_route_event(self, event, &self.transform.get(), self.cached_layout.get(), env);
+
+        // the contents of a subtree below this widget have changed, so may have the IDs of the child widgets
+        if let Event::Internal(InternalEvent::RouteEvent {
+            event: ref inner_event, ..
+        }) = *event
+        {
+            if matches!(**inner_event, Event::Internal(InternalEvent::SubtreeRecomposed)) {
+                self.child_filter.set(None);
+            }
+        }
     }
 
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
@@ -480,7 +821,7 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
                     || p.kind == PointerEventKind::PointerMove =>
             {
                 // pointer input events undergo hit-testing, with some exceptions: if the widget is a pointer-grabbing widget, don't hit test
-                let exempt_from_hit_test = self.id.is_some() && ctx.pointer_capturing_widget() == self.id;
+                let exempt_from_hit_test = self.id.is_some() && ctx.pointer_capture(p.pointer_id) == self.id;
 
                 if !exempt_from_hit_test {
                     if !self
@@ -501,14 +842,32 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
                     }
                 }
             }
+            // dropped data goes to the widgets under the drop position
+            Event::Drop(drop_event) => {
+                if !self
+                    .cached_layout
+                    .get()
+                    .expect("drop event received before layout")
+                    .measurements
+                    .local_bounds()
+                    .contains(drop_event.position)
+                {
+                    return;
+                }
+            }
             _ => {}
         }
 
         self.content.route_event(ctx, event, env);
 
+        if let Event::Internal(InternalEvent::EnvironmentChanged) = event {
+            ctx.request_relayout();
+            ctx.request_repaint();
+        }
+
         // handle event result
-        if ctx.relayout {
-            // a child widget (or ourselves) requested a relayout during event handling;
+        if ctx.relayout || ctx.child_relayout {
+            // a child widget (or ourselves) requested a relayout during event handling, or a descendant widget pod did;
             // invalidate the cached layout, if any. However, don't clear the cached layout just yet,
             // because we may need it to handle additional pointer events that are delivered before a relayout can be done.
             // For example, it's possible for a child widget to receive a PointerOver event,
@@ -516,13 +875,40 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
             // which needs the cached layout in order to be delivered properly.
 
             //eprintln!("inner: {:?}, relayout requested", self.content.debug_name());
-            self.layout_invalid.set(true);
+            let flag = if ctx.relayout {
+                LayoutFlags::DIRTY
+            } else {
+                LayoutFlags::CHILD_DIRTY
+            };
+            self.layout_flags.set(self.layout_flags.get() | flag);
+
+            // The layout of the ancestors only depends on the geometry of this pod, not on its contents: they don't
+            // need to be recomputed unless the geometry changes (see `layout`).
+            ctx.relayout = false;
+            ctx.child_relayout = true;
+
+            // With layout containment, relayout the contents right away instead of invalidating the ancestors.
+            // This is only possible if the geometry of the contents stays the same, otherwise the parent needs to
+            // know about it.
+            if self.containment.contains(Containment::LAYOUT) && self.relayout_in_place(None, env) {
+                ctx.child_relayout = false;
+            }
         }
 
         // update damage
         let mut current_damage = self.paint_damage.get();
         current_damage.merge_up(ctx.paint_damage);
         self.paint_damage.set(current_damage);
+
+        // The parts of the window damaged by the contents are painted on our layer or surface, if we have one:
+        // keep them for the next repaint, in local coordinates.
+        if !matches!(self.paint_target, PaintTarget::ParentSurface) && !ctx.damaged.is_empty() {
+            let damaged = mem::take(&mut ctx.damaged);
+            match ctx.window_transform.inverse() {
+                Some(inverse) => self.invalidate_paint_region(&damaged.transformed(&inverse)),
+                None => self.invalidate_paint(),
+            }
+        }
         /*eprintln!(
             "inner:{:?}, incoming damage: {:?},  {:?} => {:?}",
             self.content.debug_name(),
@@ -534,30 +920,33 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
         // Downgrade `Repaint` to `SubLayers`:
         // if the contents of a layer need to be redrawn, its parent doesn't necessarily need to.
         // As such, a layered WidgetPod acts as a "repaint barrier".
-        if ctx.paint_damage == PaintDamage::Repaint {
+        if ctx.paint_damage >= PaintDamage::Partial {
             ctx.paint_damage = PaintDamage::SubLayers;
         }
     }
 
     fn paint(&self, ctx: &mut PaintCtx) {
         let layout = self.cached_layout.get().expect("paint called before layout");
+        let _jank_scope = jank::time_widget(self.id, self.content.debug_name(), jank::Phase::Paint);
 
         match self.paint_target {
             PaintTarget::NativeLayer { ref layer } => {
-                match self.paint_damage.replace(PaintDamage::None) {
-                    PaintDamage::Repaint => {
-                        // the contents of the layer are dirty
+                match self.take_paint_damage() {
+                    (damage @ (PaintDamage::Repaint | PaintDamage::Partial), damaged) => {
+                        // the contents of the layer are dirty, or parts of them
                         let mut layer_paint_ctx = LayerPaintCtx {
                             skia_gpu_context: ctx.skia_direct_context,
+                            damage: (damage == PaintDamage::Partial).then(|| damaged),
                         };
                         layer.remove_all_children();
+                        frame_debugger::set_layer_owner(self.widget_id(), self.content.debug_name());
                         self.content.layer_paint(&mut layer_paint_ctx, layer, ctx.scale_factor);
                     }
-                    PaintDamage::SubLayers => {
+                    (PaintDamage::SubLayers, _) => {
                         // this layer's contents are still valid, but some sublayers may need to be repainted.
                         self.update_child_layers(ctx.skia_direct_context);
                     }
-                    PaintDamage::None => {}
+                    (PaintDamage::None, _) => {}
                 }
                 ctx.parent_layer().add_child(layer);
                 layer.set_transform(ctx.layer_transform());
@@ -565,9 +954,22 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
             PaintTarget::Surface { ref surface } => {
                 // ...
                 let mut surface = surface.sk_surface_mut(ctx.skia_direct_context);
-                match self.paint_damage.replace(PaintDamage::None) {
-                    PaintDamage::Repaint => {
-                        // the contents of the surface are dirty
+                match self.take_paint_damage() {
+                    (damage @ (PaintDamage::Repaint | PaintDamage::Partial), damaged) => {
+                        // the contents of the surface are dirty, or parts of them: the surface keeps its contents
+                        // between frames, so only those parts are cleared and repainted
+                        let surface_size = SizeI::new(surface.width(), surface.height());
+                        surface.canvas().save();
+                        if damage == PaintDamage::Partial {
+                            let mut clip = sk::Region::new();
+                            for rect in region_to_pixels(&damaged, ctx.scale_factor, surface_size) {
+                                clip.op_rect(
+                                    sk::IRect::new(rect.min_x(), rect.min_y(), rect.max_x(), rect.max_y()),
+                                    sk::region::RegionOp::Union,
+                                );
+                            }
+                            surface.canvas().clip_region(&clip, None);
+                        }
                         let mut child_ctx = PaintCtx::new(
                             &mut *surface,
                             ctx.parent_layer(),
@@ -575,13 +977,19 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
                             ctx.skia_direct_context,
                         );
                         child_ctx.surface.canvas().clear(sk::Color4f::new(0.0, 0.0, 0.0, 0.0));
+                        // the frame debugger sees the surface as a separate layer
+                        frame_debugger::set_layer_owner(self.widget_id(), self.content.debug_name());
+                        frame_debugger::begin_layer(surface_size, ctx.scale_factor);
                         self.content.paint(&mut child_ctx);
+                        child_ctx.draw_overdraw_heatmap();
+                        frame_debugger::end_layer(child_ctx.surface);
+                        child_ctx.surface.canvas().restore();
                     }
-                    PaintDamage::SubLayers => {
+                    (PaintDamage::SubLayers, _) => {
                         // this surface's contents are still valid, but some child surfaces or layers may need to be repainted.
                         self.update_child_layers(ctx.skia_direct_context);
                     }
-                    PaintDamage::None => {}
+                    (PaintDamage::None, _) => {}
                 }
 
                 ctx.with_transform_and_clip(
@@ -600,12 +1008,21 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
             }
             PaintTarget::ParentSurface => {
                 // --- Direct paint on parent surface ---
-                ctx.with_transform_and_clip(
-                    &self.transform.get(),
-                    layout.measurements.local_bounds(),
-                    layout.measurements.clip_bounds,
-                    |ctx| self.content.paint(ctx),
-                )
+                let clip = if self.containment.contains(Containment::PAINT) {
+                    Some(layout.measurements.local_bounds())
+                } else {
+                    layout.measurements.clip_bounds
+                };
+                ctx.with_transform_and_clip(&self.transform.get(), layout.measurements.local_bounds(), clip, |ctx| {
+                    frame_debugger::begin_paint(
+                        ctx,
+                        self.widget_id(),
+                        self.content.debug_name(),
+                        layout.measurements.local_bounds(),
+                    );
+                    self.content.paint(ctx);
+                    frame_debugger::end_paint();
+                })
             }
         }
 
@@ -634,6 +1051,10 @@ impl<T: Widget + ?Sized> Widget for WidgetPod<T> {
             PaintTarget::ParentSurface => DebugNode::default(),
         }
     }
+
+    fn z_order(&self) -> i32 {
+        WidgetPod::z_index(self)
+    }
 }
 
 impl fmt::Debug for WidgetPod {
@@ -659,3 +1080,26 @@ impl<T: ?Sized> WidgetPod<T> {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn z_order() {
+        // already in order: the slice is iterated directly
+        let items = [0, 0, 1, 2];
+        assert!(matches!(sort_by_z_index(&items, |&z| z), ZOrder::InOrder(_)));
+        assert_eq!(sort_by_z_index(&items, |&z| z).rev().count(), 4);
+
+        // (z-index, original position)
+        let items = [(2, 0), (0, 1), (1, 2), (0, 3)];
+        let order: Vec<_> = sort_by_z_index(&items, |item| item.0).map(|item| item.1).collect();
+        assert_eq!(order, [1, 3, 2, 0]);
+        let reversed: Vec<_> = sort_by_z_index(&items, |item| item.0)
+            .rev()
+            .map(|item| item.1)
+            .collect();
+        assert_eq!(reversed, [0, 2, 3, 1]);
+    }
+}
@@ -0,0 +1,203 @@
+//! Scrollable and zoomable view.
+use crate::{
+    drawing::ToSkia,
+    event::{Modifiers, WheelDeltaMode, WheelEvent},
+    widget::{prelude::*, LayoutInspector, Viewport},
+};
+
+const DEFAULT_LINE_HEIGHT_DIP: f64 = 20.0;
+/// Zoom factor applied for each wheel notch.
+const ZOOM_STEP: f64 = 1.1;
+/// Wheel delta corresponding to one notch, for wheel events in pixels.
+const WHEEL_NOTCH_PIXELS: f64 = 120.0;
+
+pub const DEFAULT_MIN_ZOOM: f64 = 0.1;
+pub const DEFAULT_MAX_ZOOM: f64 = 10.0;
+
+/// A view that can scroll and zoom its contents.
+///
+/// The wheel scrolls the contents vertically (horizontally with Shift), and Ctrl+wheel zooms
+/// about the pointer location: the point of the contents under the cursor stays in place.
+pub struct ZoomView<Content> {
+    inner: LayoutInspector<Viewport<LayoutInspector<Content>>>,
+    wheel: Signal<WheelEvent>,
+    zoom: f64,
+    zoom_changed: Option<f64>,
+}
+
+impl<Content: Widget + 'static> ZoomView<Content> {
+    /// Creates a new `ZoomView` with the default zoom limits.
+    #[composable]
+    pub fn new(content: Content) -> ZoomView<Content> {
+        Self::with_zoom_limits(content, DEFAULT_MIN_ZOOM, DEFAULT_MAX_ZOOM)
+    }
+
+    /// Creates a new `ZoomView` with the zoom level clamped to `min_zoom..=max_zoom`.
+    #[composable]
+    pub fn with_zoom_limits(content: Content, min_zoom: f64, max_zoom: f64) -> ZoomView<Content> {
+        assert!(0.0 < min_zoom && min_zoom <= max_zoom, "invalid zoom limits");
+
+        #[state]
+        let mut zoom: f64 = 1.0;
+        #[state]
+        let mut offset = Offset::zero();
+
+        let wheel = Signal::new();
+        let mut viewport = LayoutInspector::new(Viewport::new(LayoutInspector::new(content)));
+        let viewport_size = viewport.size();
+        let content_size = viewport.inner().content().size();
+
+        let zoom_changed = wheel
+            .value()
+            .and_then(|wheel| apply_wheel(&wheel, &mut zoom, &mut offset, min_zoom, max_zoom));
+
+        // clamp the scroll position to the zoomed contents
+        let max_x = (content_size.width * zoom - viewport_size.width).max(0.0);
+        let max_y = (content_size.height * zoom - viewport_size.height).max(0.0);
+        offset.x = offset.x.clamp(0.0, max_x);
+        offset.y = offset.y.clamp(0.0, max_y);
+
+        viewport.inner_mut().set_transform(view_transform(zoom, offset));
+
+        ZoomView {
+            inner: viewport,
+            wheel,
+            zoom,
+            zoom_changed,
+        }
+    }
+
+    /// Creates a `ZoomView` over an unbounded content area, with the zoom level clamped to `min_zoom..=max_zoom`.
+    ///
+    /// Unlike `with_zoom_limits`, the scroll position is not clamped to the size of the contents.
+    /// The contents are created by `content`, which receives the region of the contents visible in the view
+    /// (in content coordinates), so that it can skip what's not visible (see `NodeCanvas`).
+    #[composable]
+    pub fn infinite(min_zoom: f64, max_zoom: f64, content: impl FnOnce(Rect) -> Content) -> ZoomView<Content> {
+        assert!(0.0 < min_zoom && min_zoom <= max_zoom, "invalid zoom limits");
+
+        #[state]
+        let mut zoom: f64 = 1.0;
+        #[state]
+        let mut offset = Offset::zero();
+        #[state]
+        let mut viewport_size = Size::zero();
+
+        let wheel = Signal::new();
+        let zoom_changed = wheel
+            .value()
+            .and_then(|wheel| apply_wheel(&wheel, &mut zoom, &mut offset, min_zoom, max_zoom));
+
+        let transform = view_transform(zoom, offset);
+        let visible = transform
+            .inverse()
+            .map(|inv| inv.outer_transformed_rect(&Rect::new(Point::origin(), viewport_size)))
+            .unwrap_or_else(Rect::zero);
+
+        let mut viewport = LayoutInspector::new(Viewport::new(LayoutInspector::new(content(visible))));
+        // if the size of the view has changed, this causes another recomposition with the new visible region
+        viewport_size = viewport.size();
+        viewport.inner_mut().set_transform(transform);
+
+        ZoomView {
+            inner: viewport,
+            wheel,
+            zoom,
+            zoom_changed,
+        }
+    }
+
+    /// Returns the current zoom level.
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Returns the new zoom level if it has changed since the last composition.
+    pub fn zoom_changed(&self) -> Option<f64> {
+        self.zoom_changed
+    }
+
+    /// Calls the given closure if the zoom level has changed since the last composition.
+    pub fn on_zoom_changed(self, f: impl FnOnce(f64)) -> Self {
+        if let Some(zoom) = self.zoom_changed {
+            f(zoom);
+        }
+        self
+    }
+}
+
+/// Transform from content coordinates to view coordinates.
+fn view_transform(zoom: f64, offset: Offset) -> Transform {
+    Transform::scale(zoom, zoom).then(&(-offset).to_transform())
+}
+
+/// Scrolls or zooms in response to a wheel event.
+///
+/// Returns the new zoom level if it has changed.
+fn apply_wheel(wheel: &WheelEvent, zoom: &mut f64, offset: &mut Offset, min_zoom: f64, max_zoom: f64) -> Option<f64> {
+    if wheel.pointer.modifiers.contains(Modifiers::CONTROL) {
+        let notches = match wheel.delta_mode {
+            WheelDeltaMode::Pixel => wheel.delta_y / WHEEL_NOTCH_PIXELS,
+            _ => wheel.delta_y,
+        };
+        let new_zoom = (*zoom * ZOOM_STEP.powf(notches)).clamp(min_zoom, max_zoom);
+        if new_zoom != *zoom {
+            // keep the point of the contents under the cursor stationary
+            let p = wheel.pointer.position.to_vector();
+            *offset = (*offset + p) * (new_zoom / *zoom) - p;
+            *zoom = new_zoom;
+            return Some(new_zoom);
+        }
+    } else {
+        let (dx, dy) = match wheel.delta_mode {
+            WheelDeltaMode::Pixel => (wheel.delta_x, wheel.delta_y),
+            _ => (
+                DEFAULT_LINE_HEIGHT_DIP * wheel.delta_x,
+                DEFAULT_LINE_HEIGHT_DIP * wheel.delta_y,
+            ),
+        };
+        if wheel.pointer.modifiers.contains(Modifiers::SHIFT) {
+            offset.x -= dy;
+        } else {
+            offset.x -= dx;
+            offset.y -= dy;
+        }
+    }
+    None
+}
+
+impl<Content: Widget + 'static> Widget for ZoomView<Content> {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Widget::widget_id(&self.inner)
+    }
+
+    fn layout(&self, ctx: &mut LayoutCtx, constraints: &LayoutParams, env: &Environment) -> Geometry {
+        Widget::layout(&self.inner, ctx, constraints, env)
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        Widget::route_event(&self.inner, ctx, event, env);
+
+        if !ctx.handled {
+            if let Event::Wheel(wheel) = event {
+                if wheel.delta_mode == WheelDeltaMode::Page {
+                    // TODO
+                    warn!("WheelDeltaMode::Page unimplemented");
+                } else {
+                    self.wheel.signal(*wheel);
+                    ctx.set_handled();
+                }
+            }
+        }
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx) {
+        let bounds = ctx.bounds;
+        ctx.surface.canvas().save();
+        ctx.surface
+            .canvas()
+            .clip_rect(bounds.to_skia(), skia_safe::ClipOp::Intersect, false);
+        self.inner.paint(ctx);
+        ctx.surface.canvas().restore();
+    }
+}
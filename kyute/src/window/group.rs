@@ -0,0 +1,53 @@
+//! Applications with more than one top-level window.
+use crate::{widget::prelude::*, Window};
+
+/// A set of top-level windows.
+///
+/// Use it as the root widget of applications that open more than one top-level window. Windows can be added to
+/// and removed from the group between recompositions: the native windows of the `Window` widgets that are not in
+/// the group anymore are destroyed. Create each window in its own `cache::keyed` scope so that removing a window
+/// doesn't move the state of the others.
+///
+/// The application exits once the last window has been removed.
+pub struct WindowGroup {
+    id: WidgetId,
+    windows: Vec<Window>,
+}
+
+impl WindowGroup {
+    /// Creates a group of windows.
+    #[composable]
+    pub fn new(windows: Vec<Window>) -> WindowGroup {
+        WindowGroup {
+            id: WidgetId::here(),
+            windows,
+        }
+    }
+
+    /// Returns the windows in this group.
+    pub fn windows(&self) -> &[Window] {
+        &self.windows
+    }
+}
+
+impl Widget for WindowGroup {
+    fn widget_id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+
+    fn layout(&self, _ctx: &mut LayoutCtx, _constraints: &LayoutParams, _env: &Environment) -> Geometry {
+        Geometry::default()
+    }
+
+    fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
+        // events for a native window are routed to its widget (see `AppCtx::register_window_widget`),
+        // the other windows skip them
+        for window in self.windows.iter() {
+            window.route_event(ctx, event, env);
+        }
+    }
+
+    fn paint(&self, _ctx: &mut PaintCtx) {
+        // nothing to paint: each window paints its own contents
+    }
+}
@@ -0,0 +1,106 @@
+//! Performance HUD (see `SHOW_PERFORMANCE_HUD`).
+use crate::{core::LayerPaintCtx, diagnostics::LatencyStats, drawing::ToSkia, Color, SizeI, Transform};
+use kyute_shell::animation::Layer;
+use skia_safe as sk;
+use std::time::Duration;
+
+/// Size of the HUD, in DIPs.
+const HUD_WIDTH: f64 = 220.0;
+const HUD_HEIGHT: f64 = 42.0;
+/// Distance between the HUD and the edges of the window, in DIPs.
+const HUD_MARGIN: f64 = 8.0;
+const FONT_SIZE: f64 = 12.0;
+const LINE_HEIGHT: f64 = 15.0;
+const BACKGROUND_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+const TEXT_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Overlay in the top-right corner of a window that shows the end-to-end latency of input events, as measured by
+/// the window (see `WindowState::latency`).
+pub(crate) struct PerformanceHud {
+    layer: Layer,
+    /// Statistics, window size and scale factor when the HUD was last painted.
+    painted: Option<(Option<LatencyStats>, SizeI, f64)>,
+}
+
+impl PerformanceHud {
+    pub(crate) fn new() -> PerformanceHud {
+        PerformanceHud {
+            layer: Layer::new(),
+            painted: None,
+        }
+    }
+
+    pub(crate) fn layer(&self) -> &Layer {
+        &self.layer
+    }
+
+    /// Repaints the HUD with the specified latency statistics, if needed.
+    ///
+    /// `window_size` is the size of the window in pixels. Returns whether the layer was modified.
+    pub(crate) fn update(
+        &mut self,
+        stats: Option<LatencyStats>,
+        window_size: SizeI,
+        scale_factor: f64,
+        skia_gpu_context: &mut sk::gpu::DirectContext,
+    ) -> bool {
+        if self.painted == Some((stats, window_size, scale_factor)) {
+            return false;
+        }
+        self.painted = Some((stats, window_size, scale_factor));
+
+        // layer transforms and sizes are in physical pixels
+        let size = SizeI::new(
+            (HUD_WIDTH * scale_factor).round() as i32,
+            (HUD_HEIGHT * scale_factor).round() as i32,
+        );
+        let margin = (HUD_MARGIN * scale_factor).round();
+        self.layer.set_transform(&Transform::translation(
+            (window_size.width - size.width) as f64 - margin,
+            margin,
+        ));
+        if self.layer.size() != size {
+            self.layer.set_size(size);
+        }
+
+        let lines = match stats {
+            Some(stats) => [
+                format!("input latency: {:.1} ms", millis(stats.last)),
+                format!(
+                    "avg {:.1} ms, max {:.1} ms ({} inputs)",
+                    millis(stats.average),
+                    millis(stats.max),
+                    stats.count
+                ),
+            ],
+            None => ["input latency: -".to_string(), "waiting for input".to_string()],
+        };
+
+        let mut layer_paint_ctx = LayerPaintCtx {
+            skia_gpu_context,
+            damage: None,
+        };
+        layer_paint_ctx.paint_layer(&self.layer, scale_factor, |ctx| {
+            // the canvas is in physical pixels
+            let canvas = ctx.surface.canvas();
+            canvas.clear(BACKGROUND_COLOR.to_skia());
+            let mut font = sk::Font::default();
+            font.set_size((FONT_SIZE * scale_factor) as f32);
+            let paint = sk::Paint::new(TEXT_COLOR.to_skia(), None);
+            for (i, line) in lines.iter().enumerate() {
+                let baseline = (i + 1) as f64 * LINE_HEIGHT + 3.0;
+                canvas.draw_str(
+                    line,
+                    ((6.0 * scale_factor) as f32, (baseline * scale_factor) as f32),
+                    &font,
+                    &paint,
+                );
+            }
+        });
+        true
+    }
+}
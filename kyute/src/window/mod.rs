@@ -1,29 +1,57 @@
+mod group;
+mod hud;
 mod key_code;
+mod splash;
 
+pub use group::WindowGroup;
+pub use kyute_shell::window::{ResizeEdge, WindowPart};
+pub use splash::SplashHandoff;
+
+use self::hud::PerformanceHud;
 use crate::{
-    cache, composable,
-    core::{DebugNode, EventResult, FocusChange, FocusState},
-    drawing::PaintCtx,
-    event::{InputState, KeyboardEvent, PointerButton, PointerEvent, PointerEventKind, WheelDeltaMode, WheelEvent},
+    accessibility::AccessibilityTree,
+    cache,
+    command::{self, KeyMatch},
+    composable,
+    core::{send_utility_event, DebugNode, EventResult, FocusChange, FocusState, LayerPaintCtx},
+    diagnostics::LatencyProbe,
+    drawing::{overdraw, PaintCtx, ToSkia},
+    event::{
+        DropEvent, InputState, KeyboardEvent, PointerButton, PointerButtons, PointerEvent, PointerEventKind, PointerId,
+        WheelDeltaMode, WheelEvent,
+    },
+    focus::FocusChain,
     graal,
     graal::vk::Handle,
+    metrics,
     region::Region,
     style::WidgetState,
-    widget::{Menu, WidgetPod},
-    Data, Environment, Event, EventCtx, Geometry, InternalEvent, LayoutCtx, LayoutParams, Measurements, Point,
-    RoundToPixel, Size, Widget, WidgetId,
+    widget::{Menu, Placement, WidgetPod},
+    Color, Data, Environment, Event, EventCtx, Geometry, InternalEvent, LayoutCtx, LayoutParams, Measurements, Offset,
+    Point, PointI, Rect, RectI, RoundToPixel, Size, SizeI, Transform, Widget, WidgetId, SHOW_OVERDRAW,
+    SHOW_PERFORMANCE_HUD,
 };
-use keyboard_types::{KeyState, Modifiers};
+use keyboard_types::{CompositionEvent, CompositionState, Key, KeyState, Modifiers};
 use kyute_shell::{
+    animation::{ContentScaling, Layer},
     application::Application,
+    window::CustomFrame,
     winit,
     winit::{
-        event::{DeviceId, MouseScrollDelta, WindowEvent},
+        event::{DeviceId, Ime, MouseScrollDelta, TouchPhase, WindowEvent},
         window::WindowBuilder,
     },
+    DragImage, DropEffect, DropEffects, Shortcut, TypedData,
 };
 use skia_safe as sk;
-use std::{cell::RefCell, collections::HashSet, mem, sync::Arc, time::Instant};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    mem,
+    rc::Rc,
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
 use tracing::trace;
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -69,10 +97,69 @@ pub(crate) unsafe fn create_skia_vulkan_backend_context(
     ctx
 }
 
+/// Skia GPU context shared by all windows.
+///
+/// Windows share the GPU resources created by skia (uploaded images, glyph atlases, compiled shaders), so
+/// opening another window doesn't upload everything again.
+struct SharedGpuContext {
+    direct_context: sk::gpu::DirectContext,
+    // must outlive `direct_context`
+    _backend_context: sk::gpu::vk::BackendContext<'static>,
+}
+
+thread_local! {
+    static SHARED_GPU_CONTEXT: SharedGpuContext = {
+        let device = Application::instance().gpu_device().clone();
+        let backend_context = unsafe { create_skia_vulkan_backend_context(&device) };
+        let direct_context =
+            sk::gpu::DirectContext::new_vulkan(&backend_context, &sk::gpu::ContextOptions::new())
+                .expect("failed to create skia recording context");
+        SharedGpuContext {
+            direct_context,
+            _backend_context: backend_context,
+        }
+    };
+}
+
+/// Returns the skia GPU context used to paint the contents of windows.
+fn shared_gpu_context() -> sk::gpu::DirectContext {
+    SHARED_GPU_CONTEXT.with(|ctx| ctx.direct_context.clone())
+}
+
+/// Releases the resources cached by the shared skia GPU context that haven't been used for the specified duration.
+///
+/// Returns the size in bytes and the number of the resources that are still cached.
+pub(crate) fn purge_unused_gpu_resources(not_used: Duration) -> (usize, usize) {
+    let mut context = shared_gpu_context();
+    context.perform_deferred_cleanup(not_used, None);
+    let usage = context.resource_cache_usage();
+    (usage.resource_bytes, usage.resource_count)
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Window state & event handling
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Controls how the contents of a window are updated during interactive resizing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResizeMode {
+    /// Relayout and repaint the contents on every size change.
+    ///
+    /// This is the default.
+    Immediate,
+    /// Stretch the last painted contents to the new size, and relayout at most once per the specified interval.
+    Throttled(Duration),
+    /// Stretch the last painted contents to the new size, and relayout only once the user stops resizing the window
+    /// (releases its border). Programmatic size changes are applied immediately.
+    OnResizeEnd,
+}
+
+impl Default for ResizeMode {
+    fn default() -> Self {
+        ResizeMode::Immediate
+    }
+}
+
 /// Stores information about the last click (for double-click handling)
 struct LastClick {
     device_id: DeviceId,
@@ -82,6 +169,53 @@ struct LastClick {
     repeat_count: u32,
 }
 
+/// Color of the layer that dims the contents of a window while a modal dialog is open.
+const MODAL_DIM_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.35);
+
+/// Moves `window` to the center of `parent`.
+/// Moves a popup window next to its anchor rectangle, keeping it inside the work area of the monitor of the anchor.
+fn place_popup(window: &kyute_shell::window::Window, anchor: &RectI, placement: &Placement) {
+    let anchor_center = PointI::new(
+        anchor.origin.x + anchor.size.width / 2,
+        anchor.origin.y + anchor.size.height / 2,
+    );
+    let to_rect = |r: RectI| {
+        Rect::new(
+            Point::new(r.origin.x as f64, r.origin.y as f64),
+            Size::new(r.size.width as f64, r.size.height as f64),
+        )
+    };
+    let work_area = kyute_shell::window::monitor_work_area(anchor_center).map(to_rect);
+    let size = window.outer_size();
+    // gap and offset are in DIPs
+    let placement = placement.scaled(window.scale_factor());
+    let (_, position) = placement.place(
+        &to_rect(*anchor),
+        Size::new(size.width as f64, size.height as f64),
+        work_area.as_ref(),
+    );
+    window.set_outer_position(PointI::new(position.x.round() as i32, position.y.round() as i32));
+}
+
+fn center_window_on(window: &kyute_shell::window::Window, parent: &kyute_shell::window::Window) {
+    if let Some(parent_position) = parent.outer_position() {
+        let parent_size = parent.outer_size();
+        let size = window.outer_size();
+        window.set_outer_position(PointI::new(
+            parent_position.x + (parent_size.width - size.width) / 2,
+            parent_position.y + (parent_size.height - size.height) / 2,
+        ));
+    }
+}
+
+/// A drag and drop operation requested during event handling (see `EventCtx::start_drag`).
+pub(crate) struct DragRequest {
+    pub(crate) data: Vec<TypedData>,
+    pub(crate) allowed_effects: DropEffects,
+    pub(crate) image: Option<DragImage>,
+    pub(crate) on_finished: Box<dyn FnOnce(DropEffect)>,
+}
+
 /// Retained state of `Window` widgets.
 ///
 /// This is stored in the cache and mutated in place.
@@ -91,21 +225,262 @@ pub(crate) struct WindowState {
     // and we only pass one during event handling.
     // TODO: at some point, replace winit with our thing and delete this horror; I hate it with a passion
     pub(crate) window: Option<kyute_shell::window::Window>,
-    skia_backend_context: skia_safe::gpu::vk::BackendContext<'static>,
+    /// The GPU context shared by all windows (see `shared_gpu_context`).
     skia_recording_context: skia_safe::gpu::DirectContext,
     window_builder: WindowBuilder,
     pub(crate) focus_state: FocusState,
     pub(crate) hovered: HashSet<WidgetId>,
-    focus_chain: Vec<WidgetId>,
+    focus_chain: FocusChain,
     menu: Option<Menu>,
     inputs: InputState,
     last_click: Option<LastClick>,
     scale_factor: f64,
+    /// Scale factor used for the last full relayout.
+    layout_scale_factor: f64,
+    /// Parts of the window that must be repainted on the next update, in window coordinates.
+    ///
+    /// Only this window is repainted: other windows keep their contents until they are invalidated themselves.
     invalid: Region,
     recomposed: bool,
+    resize_mode: ResizeMode,
+    /// Size of the window during the last full relayout.
+    layout_size: Size,
+    /// Size of the window in physical pixels during the last full relayout.
+    layout_physical_size: SizeI,
+    /// Time of the last full relayout.
+    last_layout_time: Option<Instant>,
+    /// Whether the contents are currently stretched, waiting for a relayout.
+    stretched: bool,
+    /// Layer behind the contents, filled with the background color when they are letterboxed during a resize.
+    letterbox_layer: Option<Layer>,
+    /// Whether to create the window hidden, and show it once the first frame has been presented.
+    show_after_first_frame: bool,
+    /// Whether the first frame has been presented.
+    first_frame_presented: bool,
+    /// Splash screen handoff, signalled once the first frame has been presented.
+    splash_handoff: Option<SplashHandoff>,
+    /// Root composition layer of the window. Contains the layer of the contents, and the cursor layer.
+    root_layer: Layer,
+    /// Layer displayed in place of the pointer cursor, and its hotspot.
+    cursor_layer: Option<(Layer, Offset)>,
+    /// Last known position of the pointer in the window.
+    cursor_position: Point,
+    /// Called when the accessibility tree changes.
+    accessibility_handler: Option<Rc<dyn Fn(&AccessibilityTree)>>,
+    /// Last accessibility tree built.
+    accessibility_tree: AccessibilityTree,
+    /// Whether the contents have been recomposed since the accessibility tree was built.
+    accessibility_dirty: bool,
+    /// Layout revision of the contents when the accessibility tree was built.
+    accessibility_layout_revision: Option<u64>,
+    /// Whether an assistive technology was connected to the window when the accessibility tree was last updated.
+    accessibility_connected: bool,
+    /// Thickness of the resize grips if the window has custom decorations, `None` for system decorations.
+    resize_border: Option<f64>,
+    /// Custom decorations last sent to the window.
+    custom_frame: Option<CustomFrame>,
+    /// Input zones with a hit-test priority, with their bounds in window coordinates (see `InputZone`).
+    input_zones: Vec<(Rect, i32, WidgetId)>,
+    /// Whether the contents have been recomposed or repainted since the input zones were collected.
+    input_zones_dirty: bool,
+    /// Layout revision of the contents when the input zones were collected.
+    input_zones_layout_revision: Option<u64>,
+    /// Minimum size of the window contents.
+    min_size: Option<Size>,
+    /// Maximum size of the window contents.
+    max_size: Option<Size>,
+    /// Drag and drop operation to start once the current event has been processed.
+    pub(crate) pending_drag: Option<DragRequest>,
+    /// Called when the user asks to close the window (e.g. with the close button of the title bar).
+    close_requested_handler: Option<Rc<dyn Fn()>>,
+    /// Whether this window is a modal dialog of the window containing it (see `Window::show_modal`).
+    modal: bool,
+    /// The modal dialog currently blocking input to this window, if any.
+    ///
+    /// Modal dialogs register themselves each time the contents of this window are recomposed.
+    modal_dialog: Option<Weak<RefCell<WindowState>>>,
+    /// Layer over the contents that dims them while a modal dialog is open.
+    dim_layer: Option<Layer>,
+    /// If this is a popup window, the rectangle it is anchored to (in physical pixels relative to the desktop),
+    /// and how it is placed relative to it (see `Window::set_popup_anchor`).
+    popup_anchor: Option<(RectI, Placement)>,
+    /// Keys of the chord being entered (see `command::CommandMap`).
+    pending_keys: Vec<Shortcut>,
+    /// Whether to drop the next character: it was produced by a key press that was consumed as a command key.
+    suppress_character: bool,
+    /// Whether the overdraw visualization was enabled during the last repaint (see `SHOW_OVERDRAW`).
+    show_overdraw: bool,
+    /// Environment of the contents (see `Window::with_environment`).
+    content_environment: ContentEnvironment,
+    /// Time at which the OS produced the input event being processed, if any (see `EventCtx::input_timestamp`).
+    pub(crate) input_timestamp: Option<Instant>,
+    /// Pointer that produced the input event being processed, if any (see `EventCtx::pointer_id`).
+    pub(crate) current_pointer: Option<PointerId>,
+    /// Performance overlay, if shown (see `SHOW_PERFORMANCE_HUD`).
+    performance_hud: Option<PerformanceHud>,
+    /// Measures the end-to-end latency of input events (see `EventCtx::input_latency`).
+    ///
+    /// Latency is measured on the layer of the window contents: inputs whose effects are only visible in other layers
+    /// (e.g. animated layers, or layers of scroll areas) are not measured.
+    pub(crate) latency: LatencyProbe,
+}
+
+/// Environment of the contents of a window: the environment of the window layered over the environment of the parent.
+///
+/// The environments are only merged again when one of them has changed, since a new environment invalidates the
+/// layout of the contents. Environments built separately but with the same values are considered unchanged
+/// (see `Environment::same_values`).
+struct ContentEnvironment {
+    window_env: Environment,
+    /// Last environment of the parent, and the result of layering `window_env` over it.
+    merged: Option<(Environment, Environment)>,
+}
+
+impl ContentEnvironment {
+    fn new() -> ContentEnvironment {
+        ContentEnvironment {
+            window_env: Environment::new(),
+            merged: None,
+        }
+    }
+
+    /// Sets the environment layered over the environment of the parent.
+    fn set_window_environment(&mut self, env: Environment) {
+        if !env.same_values(&self.window_env) {
+            self.merged = None;
+        }
+        self.window_env = env;
+    }
+
+    /// Returns the environment of the contents.
+    fn get(&mut self, parent_env: &Environment) -> Environment {
+        match self.merged {
+            Some((ref mut last_parent_env, ref merged)) if last_parent_env.same_values(parent_env) => {
+                *last_parent_env = parent_env.clone();
+                merged.clone()
+            }
+            _ => {
+                let merged = parent_env.merged(self.window_env.clone());
+                self.merged = Some((parent_env.clone(), merged.clone()));
+                merged
+            }
+        }
+    }
+}
+
+impl Drop for WindowState {
+    fn drop(&mut self) {
+        // The window has been removed from the UI tree: detach the contents from the composition tree, destroy the
+        // native window, then release the GPU resources that only this window was using.
+        self.root_layer.remove_all_children();
+        if let Some(window) = self.window.take() {
+            trace!("destroying window {:?}", window.id());
+        }
+        self.skia_recording_context.flush_and_submit();
+        self.skia_recording_context.purge_unlocked_resources(true);
+    }
 }
 
 impl WindowState {
+    /// Runs a drag and drop operation requested by a widget.
+    ///
+    /// This blocks until the operation finishes.
+    fn run_drag(&mut self, drag: DragRequest) {
+        let effect = kyute_shell::clipboard::start_drag(&drag.data, drag.allowed_effects, drag.image.as_ref());
+        // The OS drag loop consumes the button release, so the widgets never see it:
+        // release the pointer grab and reset the button state ourselves.
+        self.focus_state.pointer_grabs.clear();
+        for pointer in self.inputs.pointers.values_mut() {
+            pointer.buttons = PointerButtons::new();
+        }
+        (drag.on_finished)(effect);
+    }
+
+    /// Keeps pointer positions continuous when the window moves to a monitor with a different scale factor.
+    ///
+    /// The OS reports pointer positions in physical pixels: after the change, the same physical position corresponds
+    /// to another position in DIPs, and a widget dragging something with a captured pointer (e.g. a slider thumb)
+    /// would see it jump. The positions of captured pointers are kept, and the difference is added to the positions
+    /// reported by the OS until the pointer is released; the others move to their new position in DIPs.
+    fn rescale_pointers(&mut self, new_scale_factor: f64) {
+        let ratio = self.scale_factor / new_scale_factor;
+        let rescale = |p: Point| Point::new(p.x * ratio, p.y * ratio);
+        for (pointer_id, pointer) in self.inputs.pointers.iter_mut() {
+            let reported = rescale(pointer.position - pointer.grab_offset);
+            if self.focus_state.pointer_grabs.contains_key(pointer_id) {
+                pointer.grab_offset = pointer.position - reported;
+            } else {
+                pointer.position = reported;
+                pointer.grab_offset = Offset::zero();
+            }
+        }
+        self.cursor_position = rescale(self.cursor_position);
+        // positions from before the change can't be compared to the next ones
+        self.last_click = None;
+    }
+
+    /// Marks the whole window as needing a repaint.
+    fn invalidate(&mut self) {
+        let bounds = Rect::new(Point::origin(), self.layout_size);
+        self.invalid.add_rect(bounds);
+    }
+
+    /// Returns the size of the window during the last full relayout.
+    pub(crate) fn layout_size(&self) -> Size {
+        self.layout_size
+    }
+
+    /// Matches a key press against the key sequences of the commands (see `command::CommandMap`).
+    ///
+    /// Returns the event to deliver in place of the keyboard event: an `Event::Command` if the key completes the
+    /// key sequence of a command, nothing if the key is part of a chord, or the keyboard event itself otherwise.
+    /// `from_character` is true if the event comes from a `ReceivedCharacter` window event.
+    fn filter_command_keys(&mut self, event: KeyboardEvent, from_character: bool) -> Option<Event<'static>> {
+        // a key press produces a `KeyboardInput` event followed by a `ReceivedCharacter` event:
+        // if the key press was consumed, so is the character
+        if from_character {
+            if mem::take(&mut self.suppress_character) {
+                return None;
+            }
+        } else if event.state == KeyState::Down {
+            self.suppress_character = false;
+        }
+
+        if event.state != KeyState::Down || event.is_composing {
+            return Some(Event::Keyboard(event));
+        }
+        // modifier keys, and keys that can't be part of shortcuts, don't interrupt chords
+        let shortcut = match Shortcut::from_key_event(&event) {
+            Some(shortcut) => shortcut,
+            None => return Some(Event::Keyboard(event)),
+        };
+
+        self.pending_keys.push(shortcut);
+        let in_chord = self.pending_keys.len() > 1;
+        match command::with_command_map(|map| map.lookup(&self.pending_keys)) {
+            KeyMatch::Command(id) => {
+                trace!("command {} ({:?})", id, self.pending_keys);
+                self.pending_keys.clear();
+                self.suppress_character = !from_character;
+                Some(Event::Command(id))
+            }
+            KeyMatch::Prefix => {
+                self.suppress_character = !from_character;
+                None
+            }
+            KeyMatch::None => {
+                self.pending_keys.clear();
+                if in_chord {
+                    // wrong key in a chord: cancel the chord, and drop the key
+                    self.suppress_character = !from_character;
+                    None
+                } else {
+                    Some(Event::Keyboard(event))
+                }
+            }
+        }
+    }
+
     /// Processes a winit `WindowEvent` sent to this window.
     ///
     /// Updates various states that are tracked across WindowEvents, such as:
@@ -121,26 +496,50 @@ impl WindowState {
             .as_mut()
             .expect("process_window_event received but window not initialized");
 
+        // the time of input events, for latency measurements
+        self.input_timestamp = match window_event {
+            WindowEvent::ReceivedCharacter(_)
+            | WindowEvent::KeyboardInput { .. }
+            | WindowEvent::Ime(_)
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::Touch(_) => Some(Application::instance().input_event_time()),
+            _ => None,
+        };
+        let timestamp = self.input_timestamp.unwrap_or_else(Instant::now);
+
         // ---------------------------------------
         // Default window event processing: update scale factor, input states (pointer pos, keyboard mods).
         // Some input events (pointer, keyboard) are also converted to normal events delivered
         // to the widgets within the window.
         match window_event {
-            // don't send Character events for control characters
-            WindowEvent::ReceivedCharacter(c) if !c.is_control() => {
-                Some(Event::Keyboard(KeyboardEvent {
-                    state: KeyState::Down,
-                    key: keyboard_types::Key::Character(c.to_string()),
-                    code: keyboard_types::Code::Unidentified,
-                    location: keyboard_types::Location::Standard,
-                    modifiers: self.inputs.modifiers,
-                    // TODO
-                    repeat: false,
-                    is_composing: false,
-                }))
+            // don't send Character events for control characters, except those produced by `Ctrl+letter`:
+            // they are the only way to get the letter of these shortcuts
+            WindowEvent::ReceivedCharacter(c)
+                if !c.is_control()
+                    || (('\u{1}'..='\u{1a}').contains(c) && self.inputs.modifiers.contains(Modifiers::CONTROL)) =>
+            {
+                self.filter_command_keys(
+                    KeyboardEvent {
+                        state: KeyState::Down,
+                        key: keyboard_types::Key::Character(c.to_string()),
+                        code: keyboard_types::Code::Unidentified,
+                        location: keyboard_types::Location::Standard,
+                        modifiers: self.inputs.modifiers,
+                        // TODO
+                        repeat: false,
+                        is_composing: self.inputs.composing,
+                    },
+                    true,
+                )
             }
             WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.rescale_pointers(*scale_factor);
                 self.scale_factor = *scale_factor;
+                // the contents are relaid out with the new scale factor in the layout parameters, which invalidates
+                // the cached layouts; repaint everything at the new resolution
+                self.invalidate();
                 None
             }
             WindowEvent::Resized(_size) => None,
@@ -176,19 +575,59 @@ impl WindowState {
                 is_synthetic: _,
             } => {
                 let (key, code) = key_code::key_code_from_winit(input);
-                Some(Event::Keyboard(KeyboardEvent {
-                    state: match input.state {
-                        winit::event::ElementState::Pressed => keyboard_types::KeyState::Down,
-                        winit::event::ElementState::Released => keyboard_types::KeyState::Up,
+                self.filter_command_keys(
+                    KeyboardEvent {
+                        state: match input.state {
+                            winit::event::ElementState::Pressed => keyboard_types::KeyState::Down,
+                            winit::event::ElementState::Released => keyboard_types::KeyState::Up,
+                        },
+                        key,
+                        code,
+                        location: keyboard_types::Location::default(),
+                        modifiers: self.inputs.modifiers,
+                        repeat: false,
+                        is_composing: self.inputs.composing,
                     },
-                    key,
-                    code,
-                    location: keyboard_types::Location::default(),
-                    modifiers: self.inputs.modifiers,
-                    repeat: false,
-                    is_composing: false,
-                }))
+                    false,
+                )
             }
+            WindowEvent::Ime(ime) => match ime {
+                Ime::Enabled => None,
+                Ime::Preedit(text, _cursor) => {
+                    // an empty preedit string after a composition started means that it was cancelled
+                    let state = if !self.inputs.composing {
+                        if text.is_empty() {
+                            return None;
+                        }
+                        self.inputs.composing = true;
+                        CompositionState::Start
+                    } else {
+                        CompositionState::Update
+                    };
+                    Some(Event::Composition(CompositionEvent {
+                        state,
+                        data: text.clone(),
+                    }))
+                }
+                Ime::Commit(text) => {
+                    self.inputs.composing = false;
+                    Some(Event::Composition(CompositionEvent {
+                        state: CompositionState::End,
+                        data: text.clone(),
+                    }))
+                }
+                Ime::Disabled => {
+                    if mem::take(&mut self.inputs.composing) {
+                        // IME disabled in the middle of a composition: discard the preedit text
+                        Some(Event::Composition(CompositionEvent {
+                            state: CompositionState::End,
+                            data: String::new(),
+                        }))
+                    } else {
+                        None
+                    }
+                }
+            },
             WindowEvent::ModifiersChanged(mods) => {
                 let mut modifiers = Modifiers::empty();
                 if mods.ctrl() {
@@ -211,7 +650,16 @@ impl WindowState {
             } => {
                 let logical_position: (f64, f64) = position.to_logical::<f64>(self.scale_factor).into();
                 let logical_position = Point::new(logical_position.0, logical_position.1);
-                let pointer_state = self.inputs.pointers.entry(*device_id).or_default();
+                // the cursor layer follows the actual position of the cursor
+                self.cursor_position = logical_position;
+                self.update_cursor_layer();
+                let pointer_id = PointerId::Mouse(*device_id);
+                let captured = self.focus_state.pointer_grabs.contains_key(&pointer_id);
+                let pointer_state = self.inputs.pointers.entry(pointer_id).or_default();
+                if !captured {
+                    pointer_state.grab_offset = Offset::zero();
+                }
+                let logical_position = logical_position + pointer_state.grab_offset;
                 pointer_state.position = logical_position;
                 Some(Event::Pointer(PointerEvent {
                     kind: PointerEventKind::PointerMove,
@@ -220,17 +668,26 @@ impl WindowState {
                     window_position: logical_position,
                     modifiers: self.inputs.modifiers,
                     buttons: pointer_state.buttons,
-                    pointer_id: *device_id,
+                    pointer_id: PointerId::Mouse(*device_id),
                     button: None,
                     repeat_count: 0,
+                    timestamp,
                 }))
             }
             WindowEvent::CursorEntered { .. } => {
-                // TODO
+                if let Some((ref layer, _)) = self.cursor_layer {
+                    self.root_layer.add_child(layer);
+                }
                 None
             }
             WindowEvent::CursorLeft { .. } => {
-                // TODO
+                // don't leave the cursor layer behind at the edge of the window
+                if let Some((ref layer, _)) = self.cursor_layer {
+                    self.root_layer.remove_child(layer);
+                    if let Some(ref window) = self.window {
+                        window.composition_commit();
+                    }
+                }
                 None
             }
             WindowEvent::MouseWheel {
@@ -239,7 +696,7 @@ impl WindowState {
                 phase: _,
                 ..
             } => {
-                let pointer_state = self.inputs.pointers.entry(*device_id).or_default();
+                let pointer_state = self.inputs.pointers.entry(PointerId::Mouse(*device_id)).or_default();
                 let pointer = PointerEvent {
                     kind: PointerEventKind::PointerMove, // TODO don't care?
                     target: None,
@@ -247,9 +704,10 @@ impl WindowState {
                     window_position: pointer_state.position,
                     modifiers: self.inputs.modifiers,
                     buttons: pointer_state.buttons,
-                    pointer_id: *device_id,
+                    pointer_id: PointerId::Mouse(*device_id),
                     button: None,
                     repeat_count: 0,
+                    timestamp,
                 };
 
                 let wheel_event = match *delta {
@@ -279,7 +737,7 @@ impl WindowState {
                 button,
                 ..
             } => {
-                let pointer_state = self.inputs.pointers.entry(*device_id).or_default();
+                let pointer_state = self.inputs.pointers.entry(PointerId::Mouse(*device_id)).or_default();
                 let button = match button {
                     winit::event::MouseButton::Left => PointerButton::LEFT,
                     winit::event::MouseButton::Right => PointerButton::RIGHT,
@@ -293,7 +751,7 @@ impl WindowState {
                     winit::event::ElementState::Released => pointer_state.buttons.reset(button),
                 };
 
-                let click_time = Instant::now();
+                let click_time = timestamp;
 
                 // determine the repeat count (double-click, triple-click, etc.) for button down event
                 let repeat_count = match &mut self.last_click {
@@ -345,19 +803,410 @@ impl WindowState {
                     window_position: pointer_state.position,
                     modifiers: self.inputs.modifiers,
                     buttons: pointer_state.buttons,
-                    pointer_id: *device_id,
+                    pointer_id: PointerId::Mouse(*device_id),
                     button: Some(button),
                     repeat_count,
+                    timestamp,
                 }))
             }
             winit::event::WindowEvent::TouchpadPressure { .. } => None,
             winit::event::WindowEvent::AxisMotion { .. } => None,
-            winit::event::WindowEvent::Touch(_) => None,
+            WindowEvent::Touch(touch) => {
+                // each contact is a separate pointer, with the left button pressed while it touches the screen
+                let pointer_id = PointerId::Touch(touch.device_id, touch.id);
+                let logical_position: (f64, f64) = touch.location.to_logical::<f64>(self.scale_factor).into();
+                let mut position = Point::new(logical_position.0, logical_position.1);
+                if self.focus_state.pointer_grabs.contains_key(&pointer_id) {
+                    if let Some(pointer_state) = self.inputs.pointers.get(&pointer_id) {
+                        position += pointer_state.grab_offset;
+                    }
+                }
+                let (kind, buttons, button) = match touch.phase {
+                    TouchPhase::Started => (
+                        PointerEventKind::PointerDown,
+                        PointerButtons::new().with(PointerButton::LEFT),
+                        Some(PointerButton::LEFT),
+                    ),
+                    TouchPhase::Moved => (
+                        PointerEventKind::PointerMove,
+                        PointerButtons::new().with(PointerButton::LEFT),
+                        None,
+                    ),
+                    // cancelled contacts are released like the others, so that widgets don't stay active
+                    TouchPhase::Ended | TouchPhase::Cancelled => (
+                        PointerEventKind::PointerUp,
+                        PointerButtons::new(),
+                        Some(PointerButton::LEFT),
+                    ),
+                };
+                if kind == PointerEventKind::PointerUp {
+                    // the ID may be reused for another contact
+                    self.inputs.pointers.remove(&pointer_id);
+                } else {
+                    let pointer_state = self.inputs.pointers.entry(pointer_id).or_default();
+                    pointer_state.position = position;
+                    pointer_state.buttons = buttons;
+                }
+                Some(Event::Pointer(PointerEvent {
+                    kind,
+                    target: None,
+                    position,
+                    window_position: position,
+                    modifiers: self.inputs.modifiers,
+                    buttons,
+                    pointer_id,
+                    button,
+                    repeat_count: if kind == PointerEventKind::PointerMove { 0 } else { 1 },
+                    timestamp,
+                }))
+            }
+            WindowEvent::DroppedFile(path) => {
+                // the window doesn't receive cursor events during drag and drop operations: query the position
+                let position = match self.window.as_ref().and_then(|window| window.cursor_position()) {
+                    Some(position) => Point::new(
+                        position.x as f64 / self.scale_factor,
+                        position.y as f64 / self.scale_factor,
+                    ),
+                    None => self.cursor_position,
+                };
+                // rejected data isn't delivered
+                let data = kyute_shell::clipboard::dropped_files(&[path])?;
+                Some(Event::Drop(DropEvent { position, data }))
+            }
             winit::event::WindowEvent::ThemeChanged(_) => None,
             _ => None,
         }
     }
 
+    /// Returns whether the relayout triggered by the given event can be skipped and replaced by a stretch
+    /// of the existing contents.
+    ///
+    /// Only size changes can be deferred. Any other event ends the resize and performs the pending relayout: in
+    /// particular, the redraw request sent once the throttling interval has elapsed (see `relayout_delay`), or once
+    /// the user releases the border of the window.
+    fn defer_relayout(&self, event: &Event) -> bool {
+        if !matches!(event, Event::WindowEvent(WindowEvent::Resized(_))) {
+            return false;
+        }
+        // the size changes that follow a scale factor change (when the window moves to another monitor) are
+        // never deferred: pointer positions are already converted with the new scale factor, and must match the layout
+        if self.scale_factor != self.layout_scale_factor {
+            return false;
+        }
+        match self.resize_mode {
+            ResizeMode::Immediate => false,
+            ResizeMode::Throttled(interval) => match self.last_layout_time {
+                Some(time) => time.elapsed() < interval,
+                None => false,
+            },
+            // programmatic size changes (e.g. maximizing the window) are never deferred
+            ResizeMode::OnResizeEnd => {
+                self.last_layout_time.is_some() && self.window.as_ref().map_or(false, |w| w.is_in_size_move())
+            }
+        }
+    }
+
+    /// Returns the time after which a relayout deferred by `defer_relayout` should be performed, if the end of the
+    /// resize doesn't trigger it.
+    fn relayout_delay(&self) -> Option<Duration> {
+        match (self.resize_mode, self.last_layout_time) {
+            (ResizeMode::Throttled(interval), Some(time)) => Some(interval.saturating_sub(time.elapsed())),
+            _ => None,
+        }
+    }
+
+    /// Shows the contents painted at the last layout at the specified size, according to the content scaling mode of
+    /// the content layer.
+    fn fit_contents(&mut self, content_layer: &Layer, size: SizeI) {
+        if self.layout_physical_size.is_empty() {
+            return;
+        }
+        let transform = content_layer
+            .content_scaling()
+            .transform(self.layout_physical_size, size);
+        content_layer.set_transform(&transform);
+
+        match content_layer.content_scaling() {
+            ContentScaling::Letterbox(color) => {
+                let letterbox_layer = if let Some(ref layer) = self.letterbox_layer {
+                    layer.clone()
+                } else {
+                    // insert the background layer behind all the others
+                    let layer = Layer::new();
+                    self.root_layer.remove_all_children();
+                    self.root_layer.add_child(&layer);
+                    self.root_layer.add_child(content_layer);
+                    if let Some((ref cursor_layer, _)) = self.cursor_layer {
+                        self.root_layer.add_child(cursor_layer);
+                    }
+                    self.letterbox_layer = Some(layer.clone());
+                    layer
+                };
+                if letterbox_layer.size() != size {
+                    letterbox_layer.set_size(size);
+                    let mut layer_paint_ctx = LayerPaintCtx {
+                        skia_gpu_context: &mut self.skia_recording_context,
+                        damage: None,
+                    };
+                    layer_paint_ctx.paint_layer(&letterbox_layer, self.scale_factor, |ctx| {
+                        ctx.surface.canvas().clear(color.to_skia());
+                    });
+                }
+            }
+            _ => self.remove_letterbox_layer(),
+        }
+    }
+
+    /// Updates the accessibility tree of the contents, and sends it to the platform accessibility API and to the
+    /// accessibility handler if it has changed.
+    ///
+    /// The tree is only rebuilt after a recomposition or a relayout of the contents; otherwise, only the focus is
+    /// updated. Nothing is done until an assistive technology connects to the window or a handler is set.
+    fn update_accessibility_tree(&mut self, content: &WidgetPod, env: &Environment) {
+        let window = if let Some(ref window) = self.window {
+            window
+        } else {
+            return;
+        };
+        let connected = window.is_accessibility_connected();
+        if !connected && self.accessibility_handler.is_none() {
+            return;
+        }
+        // an assistive technology that just connected needs the full tree
+        if connected && !self.accessibility_connected {
+            self.accessibility_dirty = true;
+        }
+        self.accessibility_connected = connected;
+
+        let layout_revision = content.layout_revision();
+        let tree = if self.accessibility_dirty || layout_revision != self.accessibility_layout_revision {
+            let mut nodes = Vec::new();
+            send_utility_event(
+                content,
+                &mut Event::Internal(InternalEvent::BuildAccessibilityTree {
+                    nodes: &mut nodes,
+                    bounds: Rect::new(Point::origin(), self.layout_size),
+                }),
+                env,
+            );
+            self.accessibility_dirty = false;
+            self.accessibility_layout_revision = layout_revision;
+            AccessibilityTree {
+                nodes,
+                focus: self.focus_state.focus,
+            }
+        } else if self.focus_state.focus != self.accessibility_tree.focus {
+            AccessibilityTree {
+                focus: self.focus_state.focus,
+                ..self.accessibility_tree.clone()
+            }
+        } else {
+            return;
+        };
+
+        if tree != self.accessibility_tree {
+            if connected {
+                window.set_accessibility_tree(tree.to_platform());
+            }
+            if let Some(handler) = self.accessibility_handler.clone() {
+                handler(&tree);
+            }
+            self.accessibility_tree = tree;
+        }
+    }
+
+    /// Collects the regions of the custom decorations, and updates the window if they have changed.
+    fn update_custom_frame(&mut self, content: &WidgetPod, env: &Environment) {
+        let frame = self.resize_border.map(|resize_border| {
+            let mut regions = Vec::new();
+            send_utility_event(
+                content,
+                &mut Event::Internal(InternalEvent::CollectFrameRegions { regions: &mut regions }),
+                env,
+            );
+            CustomFrame { resize_border, regions }
+        });
+        if frame != self.custom_frame {
+            if let Some(ref window) = self.window {
+                window.set_custom_frame(frame.clone());
+            }
+            self.custom_frame = frame;
+        }
+    }
+
+    /// Collects the input zones with a hit-test priority again, if the contents have changed since they were last
+    /// collected.
+    fn update_input_zones(&mut self, content: &WidgetPod, env: &Environment) {
+        let layout_revision = content.layout_revision();
+        if !self.input_zones_dirty && layout_revision == self.input_zones_layout_revision {
+            return;
+        }
+        self.input_zones.clear();
+        send_utility_event(
+            content,
+            &mut Event::Internal(InternalEvent::CollectInputZones {
+                zones: &mut self.input_zones,
+            }),
+            env,
+        );
+        self.input_zones_dirty = false;
+        self.input_zones_layout_revision = layout_revision;
+    }
+
+    /// Returns the input zone with the highest hit-test priority at the specified position in the window.
+    ///
+    /// On ties, the first zone collected (the topmost one) wins.
+    fn input_zone_at(&self, position: Point) -> Option<WidgetId> {
+        let mut zone: Option<(i32, WidgetId)> = None;
+        for &(bounds, priority, id) in self.input_zones.iter() {
+            if bounds.contains(position) && zone.map_or(true, |(best, _)| best < priority) {
+                zone = Some((priority, id));
+            }
+        }
+        zone.map(|(_, id)| id)
+    }
+
+    /// Sets the size limits of the window contents, applied immediately if the window is created.
+    fn set_size_limits(&mut self, min_size: Option<Size>, max_size: Option<Size>) {
+        if (min_size, max_size) != (self.min_size, self.max_size) {
+            self.min_size = min_size;
+            self.max_size = max_size;
+            if let Some(ref window) = self.window {
+                window.set_size_limits(min_size, max_size);
+            }
+        }
+    }
+
+    /// Shows the layer that dims the contents if a modal dialog is open, or removes it otherwise.
+    fn update_dim_layer(&mut self) {
+        let window = if let Some(ref window) = self.window {
+            window
+        } else {
+            return;
+        };
+
+        if self.modal_dialog.is_none() {
+            if let Some(layer) = self.dim_layer.take() {
+                self.root_layer.remove_child(&layer);
+                window.composition_commit();
+            }
+            return;
+        }
+
+        let size = window.physical_inner_size();
+        let dim_layer = if let Some(ref layer) = self.dim_layer {
+            layer.clone()
+        } else {
+            // on top of the contents, but below the cursor layer
+            let layer = Layer::new();
+            self.root_layer.add_child(&layer);
+            if let Some((ref cursor_layer, _)) = self.cursor_layer {
+                self.root_layer.remove_child(cursor_layer);
+                self.root_layer.add_child(cursor_layer);
+            }
+            self.dim_layer = Some(layer.clone());
+            layer
+        };
+        if dim_layer.size() != size {
+            dim_layer.set_size(size);
+            let mut layer_paint_ctx = LayerPaintCtx {
+                skia_gpu_context: &mut self.skia_recording_context,
+                damage: None,
+            };
+            layer_paint_ctx.paint_layer(&dim_layer, self.scale_factor, |ctx| {
+                ctx.surface.canvas().clear(MODAL_DIM_COLOR.to_skia());
+            });
+            window.composition_commit();
+        }
+    }
+
+    /// Measures the inputs whose frames have been displayed, then shows or hides the performance HUD, and updates it.
+    fn update_performance_hud(&mut self, content_layer: &Layer, show: bool) {
+        let window = if let Some(ref window) = self.window {
+            window
+        } else {
+            return;
+        };
+
+        if let Some(stats) = content_layer.present_statistics() {
+            self.latency.update(&stats);
+        }
+
+        if !show {
+            if let Some(hud) = self.performance_hud.take() {
+                self.root_layer.remove_child(hud.layer());
+                window.composition_commit();
+            }
+            return;
+        }
+
+        if self.performance_hud.is_none() {
+            // on top of the contents, but below the cursor layer
+            let hud = PerformanceHud::new();
+            self.root_layer.add_child(hud.layer());
+            if let Some((ref cursor_layer, _)) = self.cursor_layer {
+                self.root_layer.remove_child(cursor_layer);
+                self.root_layer.add_child(cursor_layer);
+            }
+            self.performance_hud = Some(hud);
+        }
+        let hud = self.performance_hud.as_mut().unwrap();
+        if hud.update(
+            self.latency.stats(),
+            window.physical_inner_size(),
+            self.scale_factor,
+            &mut self.skia_recording_context,
+        ) {
+            window.composition_commit();
+        }
+    }
+
+    /// Brings the modal dialog blocking input to this window to the front.
+    fn focus_modal_dialog(&self) {
+        if let Some(dialog) = self.modal_dialog.as_ref().and_then(Weak::upgrade) {
+            if let Ok(dialog) = dialog.try_borrow() {
+                if let Some(ref window) = dialog.window {
+                    window.focus();
+                }
+            }
+        }
+    }
+
+    /// Removes the background layer shown behind letterboxed contents.
+    fn remove_letterbox_layer(&mut self) {
+        if let Some(layer) = self.letterbox_layer.take() {
+            self.root_layer.remove_child(&layer);
+        }
+    }
+
+    /// Sets the layer displayed in place of the pointer cursor, or restores the regular cursor if `None`.
+    pub(crate) fn set_cursor_layer(&mut self, cursor_layer: Option<(Layer, Offset)>) {
+        if let Some((layer, _)) = self.cursor_layer.take() {
+            self.root_layer.remove_child(&layer);
+        }
+        if let Some((ref layer, _)) = cursor_layer {
+            // added last, so that it's on top of the contents
+            self.root_layer.add_child(layer);
+        }
+        if let Some(ref window) = self.window {
+            window.set_cursor_visible(cursor_layer.is_none());
+        }
+        self.cursor_layer = cursor_layer;
+        self.update_cursor_layer();
+    }
+
+    /// Moves the cursor layer to the current pointer position.
+    ///
+    /// This only updates the layer transform: the cursor follows the pointer without relayouts or repaints.
+    fn update_cursor_layer(&self) {
+        if let (Some(window), Some((layer, hotspot))) = (&self.window, &self.cursor_layer) {
+            // layer transforms are in physical pixels
+            let position = (self.cursor_position - *hotspot) * self.scale_factor;
+            layer.set_transform(&Transform::translation(position.x.round(), position.y.round()));
+            window.composition_commit();
+        }
+    }
+
     /// Updates the window menu if the window is created.
     fn update_menu(&mut self) {
         if let Some(ref mut window) = self.window {
@@ -405,18 +1254,19 @@ impl<'a, 'b> ContentEventCtx<'a, 'b> {
     /// Unlike other pointer events, they *do not* propagate to descendants on a successful hit-test.
     ///
     /// This is used for sending `Pointer{Out,Over,Enter,Exit}` events.
-    fn send_targeting_pointer_event(&mut self, device_id: DeviceId, target: WidgetId, event_kind: PointerEventKind) {
+    fn send_targeting_pointer_event(&mut self, pointer_id: PointerId, target: WidgetId, event_kind: PointerEventKind) {
         // synthesize a pointer event
-        let event = self.state.inputs.pointers.get(&device_id).map(|state| PointerEvent {
+        let event = self.state.inputs.pointers.get(&pointer_id).map(|state| PointerEvent {
             kind: event_kind,
             target: Some(target),
             position: state.position,
             window_position: state.position,
             modifiers: self.state.inputs.modifiers,
             buttons: state.buttons,
-            pointer_id: device_id,
+            pointer_id,
             button: None,
             repeat_count: 0,
+            timestamp: self.state.input_timestamp.unwrap_or_else(Instant::now),
         });
         if let Some(event) = event {
             let mut event = Event::Internal(InternalEvent::RoutePointerEvent { target, event });
@@ -428,13 +1278,21 @@ impl<'a, 'b> ContentEventCtx<'a, 'b> {
     fn propagate_input_event(&mut self, mut event: Event) {
         let mut event_result = EventResult::default();
 
-        let pointer_grab_auto_release = matches!(
-            event,
+        let pointer_grab_auto_release = match event {
             Event::Pointer(PointerEvent {
                 kind: PointerEventKind::PointerUp,
+                pointer_id,
                 ..
-            })
-        );
+            }) => Some(pointer_id),
+            _ => None,
+        };
+
+        // the pointer that produced the event, used by `EventCtx::capture_pointer`
+        self.state.current_pointer = match event {
+            Event::Pointer(ref pointer_event) => Some(pointer_event.pointer_id),
+            Event::Wheel(ref wheel_event) => Some(wheel_event.pointer.pointer_id),
+            _ => None,
+        };
 
         // send the event
         match event {
@@ -451,7 +1309,7 @@ impl<'a, 'b> ContentEventCtx<'a, 'b> {
                 // Pointer and wheel events are delivered to the node that is currently grabbing the pointer.
                 // If nothing is grabbing the pointer, the pointer event is delivered to a widgets
                 // that passes the hit-test
-                if let Some(target) = self.state.focus_state.pointer_grab {
+                if let Some(target) = self.state.focus_state.pointer_grabs.get(&pointer_id).copied() {
                     trace!("routing pointer event to pointer-capturing widget {:?}", target);
                     match event {
                         Event::Pointer(ref pointer_event) => {
@@ -472,8 +1330,30 @@ impl<'a, 'b> ContentEventCtx<'a, 'b> {
                     let old_hot = self.state.focus_state.hot;
                     let old_hovered = mem::take(&mut self.state.hovered);
 
-                    // send event to computed target
-                    event_result = self.send_event(&mut event);
+                    // If the pointer is over an input zone with a hit-test priority, deliver the event exclusively
+                    // to this zone. Otherwise, send the event to the target computed by the regular hit-test.
+                    let position = match event {
+                        Event::Pointer(ref pointer_event) => pointer_event.position,
+                        Event::Wheel(ref wheel_event) => wheel_event.pointer.position,
+                        _ => unreachable!(),
+                    };
+                    event_result = if let Some(target) = self.state.input_zone_at(position) {
+                        trace!("routing pointer event to input zone {:?}", target);
+                        let mut routed_event = match event {
+                            Event::Pointer(ref pointer_event) => Event::Internal(InternalEvent::RoutePointerEvent {
+                                event: pointer_event.clone(),
+                                target,
+                            }),
+                            Event::Wheel(ref wheel_event) => Event::Internal(InternalEvent::RouteWheelEvent {
+                                event: wheel_event.clone(),
+                                target,
+                            }),
+                            _ => unreachable!(),
+                        };
+                        self.send_event(&mut routed_event)
+                    } else {
+                        self.send_event(&mut event)
+                    };
 
                     let new_hot = self.state.focus_state.hot;
                     let new_hovered = mem::take(&mut self.state.hovered);
@@ -512,9 +1392,45 @@ impl<'a, 'b> ContentEventCtx<'a, 'b> {
                     self.state.focus_state.hot = new_hot;
                 }
             }
-            Event::Keyboard(_) => {
+            Event::Keyboard(ref keyboard_event) => {
+                let keyboard_event = keyboard_event.clone();
+                let focus = self.state.focus_state.focus;
+
+                // menu activation keys (Alt, Alt+mnemonic) go to the menu bar first
+                let is_menu_key = keyboard_event.key == Key::Alt || keyboard_event.modifiers.contains(Modifiers::ALT);
+                let menu_bar = self.state.focus_state.menu_bar.filter(|&id| Some(id) != focus);
+                let mut handled = false;
+                if let (Some(menu_bar), true) = (menu_bar, is_menu_key) {
+                    event_result = self.send_routed_event(menu_bar, Event::Keyboard(keyboard_event));
+                    handled = event_result.handled;
+                }
+
                 // keyboard events are delivered to the widgets that has the focus.
                 // if no widgets has focus, the event is dropped.
+                if !handled {
+                    if let Some(focus) = focus {
+                        event_result = self.send_routed_event(focus, event);
+                    }
+                }
+            }
+            Event::Command(ref id) => {
+                // commands go to the focused widget first, and the widgets on the path can handle them.
+                // if no widget handles it, trigger the global handler.
+                let id = id.clone();
+                metrics::command_invoked(&id);
+                if let Some(focus) = self.state.focus_state.focus {
+                    event_result = self.send_routed_event(focus, event);
+                }
+                if !event_result.handled && !command::with_command_map(|map| map.trigger(&id)) {
+                    trace!("command {} not handled", id);
+                }
+            }
+            Event::Drop(_) => {
+                // dropped data goes to the drop targets under the drop position
+                event_result = self.send_event(&mut event);
+            }
+            Event::Composition(_) => {
+                // like keyboard events, composition events go to the focused widget
                 if let Some(focus) = self.state.focus_state.focus {
                     event_result = self.send_routed_event(focus, event);
                 }
@@ -526,10 +1442,11 @@ impl<'a, 'b> ContentEventCtx<'a, 'b> {
 
         //------------------------------------------------
         // force release pointer grab on pointer up
-        if pointer_grab_auto_release {
+        if let Some(pointer_id) = pointer_grab_auto_release {
             //trace!("forcing release of pointer grab");
-            self.state.focus_state.pointer_grab = None;
+            self.state.focus_state.pointer_grabs.remove(&pointer_id);
         }
+        self.state.current_pointer = None;
 
         //------------------------------------------------
         // handle focus change requests and send FocusGained/FocusLost events to involved widgets.
@@ -544,18 +1461,9 @@ impl<'a, 'b> ContentEventCtx<'a, 'b> {
                 }
                 FocusChange::MoveNext | FocusChange::MovePrev => {
                     if let Some(old_focus) = self.state.focus_state.focus {
-                        // find position in focus chain
-                        if let Some(pos) = self.state.focus_chain.iter().position(|x| old_focus == *x) {
-                            let chain_len = self.state.focus_chain.len();
-                            let adj_pos = match focus_change {
-                                FocusChange::MoveNext if pos + 1 >= chain_len => 0,
-                                FocusChange::MoveNext => pos + 1,
-                                FocusChange::MovePrev if pos == 0 => chain_len - 1,
-                                FocusChange::MovePrev => pos - 1,
-                                _ => unreachable!(),
-                            };
-
-                            let new_focus = self.state.focus_chain[adj_pos];
+                        // find the next widget in tab order, within the focus scope of the current one
+                        let backwards = matches!(focus_change, FocusChange::MovePrev);
+                        if let Some(new_focus) = self.state.focus_chain.next(old_focus, backwards) {
                             self.send_routed_event(old_focus, Event::FocusLost);
                             self.state.focus_state.focus = Some(new_focus);
                             self.send_routed_event(new_focus, Event::FocusGained);
@@ -623,30 +1531,60 @@ impl Window {
         // create the initial window state
         // we don't want to recreate it every time, so it only depends on the call ID.
         let window_state = cache::once(move || {
-            let application = Application::instance();
-            let device = application.gpu_device().clone();
-            let skia_backend_context = unsafe { create_skia_vulkan_backend_context(&device) };
-            let recording_context_options = skia_safe::gpu::ContextOptions::new();
-            let skia_recording_context =
-                skia_safe::gpu::DirectContext::new_vulkan(&skia_backend_context, &recording_context_options)
-                    .expect("failed to create skia recording context");
-
             // --- create the root composition layer ---
             // We don't need a ref to the event loop for it, so create it here
             Arc::new(RefCell::new(WindowState {
                 window: None,
-                skia_backend_context,
-                skia_recording_context,
+                skia_recording_context: shared_gpu_context(),
                 window_builder,
                 focus_state: FocusState::default(),
                 hovered: Default::default(),
-                focus_chain: vec![],
+                focus_chain: FocusChain::new(),
                 menu: None,
                 inputs: Default::default(),
                 last_click: None,
                 scale_factor: 1.0, // initialized during window creation
+                layout_scale_factor: 1.0,
                 invalid: Default::default(),
                 recomposed: true,
+                resize_mode: ResizeMode::default(),
+                layout_size: Size::zero(),
+                layout_physical_size: SizeI::zero(),
+                last_layout_time: None,
+                stretched: false,
+                letterbox_layer: None,
+                show_after_first_frame: false,
+                first_frame_presented: false,
+                splash_handoff: None,
+                root_layer: Layer::new(),
+                cursor_layer: None,
+                cursor_position: Point::origin(),
+                accessibility_handler: None,
+                accessibility_tree: AccessibilityTree::default(),
+                accessibility_dirty: true,
+                accessibility_layout_revision: None,
+                accessibility_connected: false,
+                resize_border: None,
+                custom_frame: None,
+                input_zones: vec![],
+                input_zones_dirty: true,
+                input_zones_layout_revision: None,
+                min_size: None,
+                max_size: None,
+                pending_drag: None,
+                close_requested_handler: None,
+                modal: false,
+                modal_dialog: None,
+                dim_layer: None,
+                popup_anchor: None,
+                pending_keys: vec![],
+                suppress_character: false,
+                show_overdraw: false,
+                content_environment: ContentEnvironment::new(),
+                input_timestamp: None,
+                current_pointer: None,
+                performance_hud: None,
+                latency: LatencyProbe::new(),
             }))
         });
 
@@ -672,6 +1610,140 @@ impl Window {
             content: Arc::new(WidgetPod::with_native_layer(content)),
         }
     }
+
+    /// Creates a window whose contents are in an environment layered over the environment of the parent.
+    ///
+    /// Use this to set defaults for the whole window (e.g. a theme, or a locale) without wrapping the contents in
+    /// an `EnvOverride`. `content` is called in `cache::with_environment`, so the values are visible during the
+    /// composition of the contents; they also apply to the layout, painting and events of the contents.
+    #[composable]
+    pub fn with_environment<W: Widget + 'static>(
+        window_builder: WindowBuilder,
+        env: Environment,
+        content: impl FnOnce() -> W,
+        menu: Option<Menu>,
+    ) -> Window {
+        let content = cache::with_environment(env.clone(), content);
+        let window = Window::new(window_builder, content, menu);
+        window
+            .window_state
+            .borrow_mut()
+            .content_environment
+            .set_window_environment(env);
+        window
+    }
+
+    /// Sets how the contents painted at the last layout are shown when they are not relaid out immediately
+    /// after a size or scale factor change (see `resize_mode`).
+    ///
+    /// See `ContentScaling`. The default is `ContentScaling::Stretch`.
+    pub fn content_scaling(self, content_scaling: ContentScaling) -> Self {
+        self.content.layer().unwrap().set_content_scaling(content_scaling);
+        self
+    }
+
+    /// Sets how the contents of the window are updated during interactive resizing.
+    ///
+    /// See `ResizeMode`.
+    pub fn resize_mode(self, resize_mode: ResizeMode) -> Self {
+        self.window_state.borrow_mut().resize_mode = resize_mode;
+        self
+    }
+
+    /// Sets the function called with the accessibility tree of the contents of the window whenever it changes,
+    /// including when the focus moves to another widget.
+    ///
+    /// The tree is exposed to assistive technologies through the platform accessibility API regardless of the
+    /// handler: this is for additional consumers, such as UI tests.
+    pub fn on_accessibility_update(self, f: impl Fn(&AccessibilityTree) + 'static) -> Self {
+        self.window_state.borrow_mut().accessibility_handler = Some(Rc::new(f));
+        self
+    }
+
+    /// Sets the function called when the user asks to close the window (e.g. with the close button of the title bar).
+    ///
+    /// The window is not closed automatically: it is closed when it's not part of the UI anymore.
+    pub fn on_close_requested(self, f: impl Fn() + 'static) -> Self {
+        self.window_state.borrow_mut().close_requested_handler = Some(Rc::new(f));
+        self
+    }
+
+    /// Shows the window as a modal dialog of the window containing it.
+    ///
+    /// While the window is part of the UI, the parent window ignores input and its contents are dimmed.
+    /// The window is owned by the parent and is centered over it when created.
+    /// See also `Dialog`, which manages the lifetime of the window and returns a result to the caller.
+    pub fn show_modal(self) -> Self {
+        self.window_state.borrow_mut().modal = true;
+        self
+    }
+
+    /// Anchors this window to a rectangle on the desktop (in physical pixels) and moves it next to it.
+    ///
+    /// The window is created hidden and shown once placed. It is moved again when the anchor moves.
+    pub(crate) fn set_popup_anchor(&self, anchor: RectI, placement: Placement) {
+        let mut window_state = self.window_state.borrow_mut();
+        if window_state.popup_anchor == Some((anchor, placement)) {
+            return;
+        }
+        window_state.popup_anchor = Some((anchor, placement));
+        if let Some(ref window) = window_state.window {
+            place_popup(window, &anchor, &placement);
+        }
+    }
+
+    /// Draws the window decorations in the application instead of using the system ones.
+    ///
+    /// The whole window becomes client area: the title bar and the window buttons are regular widgets, marked
+    /// with `FrameRegion` so that the system knows how to react to the pointer over them. The edges of the window,
+    /// over a thickness of `resize_border` DIPs, can be dragged to resize the window.
+    ///
+    /// This is intended for windows created without decorations (`WindowBuilder::with_decorations(false)`).
+    pub fn custom_frame(self, resize_border: f64) -> Self {
+        self.window_state.borrow_mut().resize_border = Some(resize_border);
+        self
+    }
+
+    /// Sets the minimum size of the window contents, enforced during interactive resizing.
+    pub fn min_size(self, size: Size) -> Self {
+        let mut window_state = self.window_state.borrow_mut();
+        let max_size = window_state.max_size;
+        window_state.set_size_limits(Some(size), max_size);
+        drop(window_state);
+        self
+    }
+
+    /// Sets the maximum size of the window contents, enforced during interactive resizing.
+    pub fn max_size(self, size: Size) -> Self {
+        let mut window_state = self.window_state.borrow_mut();
+        let min_size = window_state.min_size;
+        window_state.set_size_limits(min_size, Some(size));
+        drop(window_state);
+        self
+    }
+
+    /// Creates the window hidden and shows it only once the first frame has been presented.
+    ///
+    /// This avoids showing the window with blank contents before the UI is painted.
+    /// Has no effect once the window has been created.
+    pub fn show_after_first_frame(self, show_after_first_frame: bool) -> Self {
+        self.window_state.borrow_mut().show_after_first_frame = show_after_first_frame;
+        self
+    }
+
+    /// Sets the splash screen handoff signalled once the window has presented its first frame.
+    ///
+    /// If the first frame has already been presented, the handoff is signalled immediately.
+    pub fn splash_handoff(self, handoff: SplashHandoff) -> Self {
+        let mut window_state = self.window_state.borrow_mut();
+        if window_state.first_frame_presented {
+            handoff.signal_ready();
+        } else {
+            window_state.splash_handoff = Some(handoff);
+        }
+        drop(window_state);
+        self
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -690,46 +1762,57 @@ impl Widget for Window {
     fn event(&self, ctx: &mut EventCtx, event: &mut Event, env: &Environment) {
         let mut window_state = self.window_state.borrow_mut();
         let wstate = &mut *window_state;
+        let env = &wstate.content_environment.get(env);
+        // time of the input event processed, if any
+        let mut input_timestamp = None;
 
         match event {
             Event::Initialize => {
-                // skip if the window is already created
-                if wstate.window.is_some() {
-                    if wstate.recomposed {
-                        // propagate initialization event
-                        self.content.route_event(ctx, event, env);
-
-                        // build focus chain
-                        wstate.focus_chain.clear();
-                        self.content.route_event(
-                            ctx,
-                            &mut Event::BuildFocusChain {
-                                chain: &mut wstate.focus_chain,
-                            },
-                            env,
-                        );
-                        trace!(
-                            "window {:?}: {} widget(s) in focus chain",
-                            self.id,
-                            wstate.focus_chain.len()
-                        );
-                        wstate.recomposed = false;
+                if wstate.modal {
+                    // register to the parent window, which blocks input while we exist
+                    if let Some(parent) = ctx.window_state.as_deref_mut() {
+                        parent.modal_dialog = Some(Arc::downgrade(&self.window_state));
                     }
-                } else {
+                }
+
+                if wstate.window.is_none() {
                     trace!("creating window");
 
                     // --- actually create the window ---
+                    let mut window_builder = wstate.window_builder.clone();
+                    if wstate.show_after_first_frame || wstate.modal || wstate.popup_anchor.is_some() {
+                        // shown once the first frame is presented, or once placed relative to the parent
+                        window_builder = window_builder.with_visible(false);
+                    }
                     let window = kyute_shell::window::Window::from_builder(
                         ctx.event_loop.unwrap(),
-                        wstate.window_builder.clone(),
+                        window_builder,
                         ctx.window_state.as_ref().and_then(|ws| ws.window.as_ref()),
                     )
                     .expect("failed to create window");
 
                     // register it to the AppCtx, necessary so that the event loop can route window events to this widgets
-                    ctx.register_window(window.id());
+                    ctx.register_window(window.id(), Arc::downgrade(&self.window_state));
+
+                    window.set_root_composition_layer(&wstate.root_layer);
+                    wstate.root_layer.add_child(self.content.layer().unwrap());
 
-                    window.set_root_composition_layer(self.content.layer().unwrap());
+                    // apply the size limits set before the window was created
+                    window.set_size_limits(wstate.min_size, wstate.max_size);
+
+                    if wstate.modal {
+                        if let Some(parent) = ctx.window_state.as_ref().and_then(|ws| ws.window.as_ref()) {
+                            center_window_on(&window, parent);
+                        }
+                        if !wstate.show_after_first_frame {
+                            window.set_visible(true);
+                        }
+                    } else if let Some((anchor, placement)) = wstate.popup_anchor {
+                        place_popup(&window, &anchor, &placement);
+                        if !wstate.show_after_first_frame {
+                            window.set_visible(true);
+                        }
+                    }
 
                     // update window state
                     wstate.scale_factor = window.scale_factor();
@@ -738,14 +1821,129 @@ impl Widget for Window {
                     // create the window menu
                     wstate.update_menu();
                 }
+
+                // the contents are initialized once the window is created, and after each recomposition
+                if wstate.recomposed {
+                    wstate.accessibility_dirty = true;
+                    wstate.input_zones_dirty = true;
+
+                    // modal dialogs in the contents register themselves again
+                    wstate.modal_dialog = None;
+
+                    // propagate initialization event
+                    self.content.route_event(ctx, event, env);
+                    wstate.update_dim_layer();
+
+                    // build focus chain, keeping the previous one to find where the focus goes if the focused
+                    // widget has been removed
+                    let previous_focus_chain = mem::take(&mut wstate.focus_chain);
+                    wstate.focus_chain.track(wstate.focus_state.focus);
+                    self.content.route_event(
+                        ctx,
+                        &mut Event::BuildFocusChain {
+                            chain: &mut wstate.focus_chain,
+                        },
+                        env,
+                    );
+                    trace!(
+                        "window {:?}: {} widget(s) in focus chain",
+                        self.id,
+                        wstate.focus_chain.len()
+                    );
+
+                    // forget the widgets that have been removed: otherwise, the focus would dangle and keyboard
+                    // events would be dropped
+                    if let Some(focus) = wstate.focus_state.focus {
+                        if !wstate.focus_chain.contains_widget(focus) {
+                            // the removed widget can't receive `FocusLost`
+                            let new_focus = wstate.focus_chain.focus_replacement(focus, &previous_focus_chain);
+                            trace!(
+                                "window {:?}: focused widget {:?} removed, focus moves to {:?}",
+                                self.id,
+                                focus,
+                                new_focus
+                            );
+                            wstate.focus_state.focus = new_focus;
+                            if let Some(new_focus) = new_focus {
+                                self.content.route_event(
+                                    ctx,
+                                    &mut Event::Internal(InternalEvent::RouteEvent {
+                                        target: new_focus,
+                                        event: Box::new(Event::FocusGained),
+                                    }),
+                                    env,
+                                );
+                            }
+                        }
+                    }
+                    let focus_chain = &wstate.focus_chain;
+                    let focus_state = &mut wstate.focus_state;
+                    focus_state.hot = focus_state.hot.filter(|&id| focus_chain.contains_widget(id));
+                    focus_state.popup_target = focus_state.popup_target.filter(|&id| focus_chain.contains_widget(id));
+                    focus_state
+                        .pointer_grabs
+                        .retain(|_, id| focus_chain.contains_widget(*id));
+
+                    // find the in-window menu bar, if any
+                    let mut menu_bar = None;
+                    self.content.route_event(
+                        ctx,
+                        &mut Event::Internal(InternalEvent::FindMenuBar {
+                            menu_bar: &mut menu_bar,
+                        }),
+                        env,
+                    );
+                    wstate.focus_state.menu_bar = menu_bar;
+                    wstate.recomposed = false;
+
+                    // popups are activated when shown: give the focus to their first focusable widget, so that
+                    // they can be used with the keyboard right away
+                    if wstate.popup_anchor.is_some() && wstate.focus_state.focus.is_none() {
+                        if let Some(first) = wstate.focus_chain.get(0).map(|entry| entry.id) {
+                            wstate.focus_state.focus = Some(first);
+                            self.content.route_event(
+                                ctx,
+                                &mut Event::Internal(InternalEvent::RouteEvent {
+                                    target: first,
+                                    event: Box::new(Event::FocusGained),
+                                }),
+                                env,
+                            );
+                        }
+                    }
+                }
             }
             Event::WindowEvent(we) => {
+                if let WindowEvent::CloseRequested = we {
+                    if let Some(handler) = wstate.close_requested_handler.clone() {
+                        handler();
+                    }
+                }
                 let content_event = wstate.process_window_event(we);
                 if let Some(content_event) = content_event {
-                    propagate_input_event_to_content(ctx, content_event, wstate, &self.content, env);
+                    if wstate.modal_dialog.is_some() {
+                        // input is blocked by a modal dialog
+                        if let Event::Pointer(PointerEvent {
+                            kind: PointerEventKind::PointerDown,
+                            ..
+                        }) = content_event
+                        {
+                            wstate.focus_modal_dialog();
+                        }
+                    } else {
+                        propagate_input_event_to_content(ctx, content_event, wstate, &self.content, env);
+                    }
                 }
+                input_timestamp = wstate.input_timestamp.take();
+                if let Some(drag) = wstate.pending_drag.take() {
+                    wstate.run_drag(drag);
+                }
+            }
+            Event::WindowRedrawRequest => {
+                // the system asks to redraw this window only
+                wstate.invalidate();
+                forward_event_to_content(ctx, event, wstate, &self.content, env);
             }
-            //Event::WindowRedrawRequest => self.do_redraw(ctx, env),
             _ => {
                 // Forward any other event
                 forward_event_to_content(ctx, event, wstate, &self.content, env);
@@ -755,8 +1953,30 @@ impl Widget for Window {
         // FIXME: EventCtx is a mess: sometimes we have an appctx available, sometimes not.
         // FIXME: when should we relayout and repaint?
 
+        let defer_relayout = wstate.defer_relayout(event);
+        let relayout_delay = wstate.relayout_delay();
+
         if let Some(ref mut window) = wstate.window {
+            if defer_relayout {
+                // --- show the contents painted at the last layout at the new size ---
+                // (the layer transform is in physical pixels, which also handles scale factor changes)
+                let size = window.physical_inner_size();
+                // an immediate redraw request would end the deferral: make sure that we receive one once the interval
+                // has elapsed (with `OnResizeEnd`, the window is redrawn once the user releases the border)
+                if let Some(delay) = relayout_delay {
+                    window.request_redraw_after(delay);
+                }
+                wstate.fit_contents(self.content.layer().unwrap(), size);
+                wstate.stretched = true;
+                if let Some(ref window) = wstate.window {
+                    window.composition_commit();
+                }
+                return;
+            }
+
             // --- update layout ---
+            // (widget pods reuse their cached layout: only those that requested a relayout are relaid out, along
+            // with the ancestors whose geometry depends on them)
             {
                 //let _span = trace_span!("Window relayout").entered();
                 let scale_factor = window.scale_factor();
@@ -779,22 +1999,84 @@ impl Widget for Window {
                     .place_into(&Measurements::new(size))
                     .round_to_pixel(scale_factor);
                 self.content.set_offset(content_offset);
+
+                wstate.layout_size = size;
+                wstate.layout_physical_size = window.physical_inner_size();
+                wstate.layout_scale_factor = scale_factor;
+                wstate.last_layout_time = Some(Instant::now());
             }
 
-            static mut FIRST_PAINT: bool = true;
+            // the contents are relaid out for the new size, undo the stretch
+            let was_stretched = mem::replace(&mut wstate.stretched, false);
+            if was_stretched {
+                self.content.layer().unwrap().set_transform(&Transform::identity());
+                wstate.remove_letterbox_layer();
+            }
+
+            // repaint the invalidated parts of this window
+            if !wstate.invalid.is_empty() {
+                match self.content.transform().inverse() {
+                    Some(inverse) => self
+                        .content
+                        .invalidate_paint_region(&wstate.invalid.transformed(&inverse)),
+                    None => self.content.invalidate_paint(),
+                }
+                wstate.invalid.clear();
+            }
+
+            // the overdraw heatmap covers all layers: they are all repainted while it's shown,
+            // and once more to remove it when it's hidden
+            let show_overdraw = env.get(&SHOW_OVERDRAW).unwrap_or_default();
+            let overdraw_toggled = mem::replace(&mut wstate.show_overdraw, show_overdraw) != show_overdraw;
+            if show_overdraw || overdraw_toggled {
+                self.content.invalidate_paint();
+            }
 
             {
                 // let _span = trace_span!("Window composition layers update").entered();
                 // --- update composition layers ---
+                overdraw::begin_window_repaint(show_overdraw, show_overdraw || overdraw_toggled);
                 let repainted = self.content.repaint_layer(&mut wstate.skia_recording_context);
+                overdraw::end_window_repaint();
+                // layer updates may move the input zones without a relayout (e.g. scrolling)
                 if repainted {
-                    unsafe {
-                        window.composition_commit();
-                        //window.set_root_composition_layer(self.content.layer().unwrap());
-                        //FIRST_PAINT = false;
+                    wstate.input_zones_dirty = true;
+                }
+                if repainted || was_stretched {
+                    window.composition_commit();
+
+                    if let (Some(input_timestamp), Some(stats)) =
+                        (input_timestamp, self.content.layer().unwrap().present_statistics())
+                    {
+                        wstate.latency.frame_presented(input_timestamp, stats.present_count);
+                    }
+
+                    if !wstate.first_frame_presented {
+                        trace!("window {:?}: first frame presented", self.id);
+                        wstate.first_frame_presented = true;
+                        if wstate.show_after_first_frame {
+                            window.set_visible(true);
+                        }
+                        if let Some(handoff) = wstate.splash_handoff.take() {
+                            handoff.signal_ready();
+                        }
                     }
                 }
             }
+
+            // --- keep the dimming layer in sync with the window size ---
+            wstate.update_dim_layer();
+
+            // --- performance overlay ---
+            let show_performance_hud = env.get(&SHOW_PERFORMANCE_HUD).unwrap_or_default();
+            wstate.update_performance_hud(self.content.layer().unwrap(), show_performance_hud);
+
+            // --- update the custom decorations and the input zones, which follow the layout ---
+            wstate.update_custom_frame(&self.content, env);
+            wstate.update_input_zones(&self.content, env);
+
+            // --- update the accessibility tree (also reflects focus changes) ---
+            wstate.update_accessibility_tree(&self.content, env);
         }
     }
 
@@ -807,3 +2089,41 @@ impl Widget for Window {
         DebugNode::new(format!("title: {:?}", window_state.window_builder.window.title))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Atom, EnvKey};
+
+    #[test]
+    fn content_environment_cache() {
+        let parent_key: EnvKey<f64> = EnvKey::new(Atom::from("test-window-parent-env"));
+        let window_key: EnvKey<f64> = EnvKey::new(Atom::from("test-window-env"));
+        let base = Environment::new().add(parent_key.clone(), 1.0);
+        let mut content_env = ContentEnvironment::new();
+        content_env.set_window_environment(Environment::new().add(window_key.clone(), 2.0));
+
+        let env = content_env.get(&base);
+        assert_eq!(env.get(&parent_key), Some(1.0));
+        assert_eq!(env.get(&window_key), Some(2.0));
+
+        // same parent: the cached environment is reused
+        assert!(content_env.get(&base).same(&env));
+        // parent rebuilt with the same values: reused as well
+        let rebuilt = base.merged(Environment::new());
+        assert!(!rebuilt.same(&base));
+        assert!(content_env.get(&rebuilt).same(&env));
+
+        // changed parent: rebuilt
+        let changed = base.clone().add(parent_key.clone(), 3.0);
+        let env = content_env.get(&changed);
+        assert_eq!(env.get(&parent_key), Some(3.0));
+        assert_eq!(env.get(&window_key), Some(2.0));
+        assert!(content_env.get(&changed).same(&env));
+
+        // changed window environment: rebuilt
+        content_env.set_window_environment(Environment::new().add(window_key.clone(), 4.0));
+        let env = content_env.get(&changed);
+        assert_eq!(env.get(&window_key), Some(4.0));
+    }
+}
@@ -0,0 +1,56 @@
+//! Handoff from a native splash screen.
+use parking_lot::Mutex;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+struct SplashHandoffInner {
+    ready: AtomicBool,
+    on_ready: Mutex<Option<Box<dyn FnOnce() + Send>>>,
+}
+
+/// Coordinates the handoff between a native splash screen and a window.
+///
+/// Create it before starting the application, and pass it to the main window with `Window::splash_handoff`.
+/// The callback is invoked once the first frame of the window has been presented (and the window shown, see
+/// `Window::show_after_first_frame`): this is where the splash screen should be closed.
+///
+/// The handle can be cloned and sent to other threads, e.g. to the thread running the splash screen.
+#[derive(Clone)]
+pub struct SplashHandoff(Arc<SplashHandoffInner>);
+
+impl SplashHandoff {
+    /// Creates a new handoff that calls `on_ready` when the window has presented its first frame.
+    pub fn new(on_ready: impl FnOnce() + Send + 'static) -> SplashHandoff {
+        SplashHandoff(Arc::new(SplashHandoffInner {
+            ready: AtomicBool::new(false),
+            on_ready: Mutex::new(Some(Box::new(on_ready))),
+        }))
+    }
+
+    /// Returns whether the window has presented its first frame.
+    pub fn is_ready(&self) -> bool {
+        self.0.ready.load(Ordering::Acquire)
+    }
+
+    /// Signals that the window is ready. Only the first call has an effect.
+    pub(crate) fn signal_ready(&self) {
+        if !self.0.ready.swap(true, Ordering::AcqRel) {
+            if let Some(on_ready) = self.0.on_ready.lock().take() {
+                on_ready();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SplashHandoff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SplashHandoff")
+            .field("ready", &self.is_ready())
+            .finish()
+    }
+}